@@ -1,11 +1,8 @@
 use accumulator::group::Rsa2048;
 use accumulator::{group::Group, AccumulatorWithoutHashToPrime};
-use algebra::{
-    bls12_381::{Bls12_381, Fr, G1Projective},
-    PrimeField,
-};
+use algebra::bls12_381::{Bls12_381, Fr, G1Projective};
 use cpsnarks_set::{
-    commitments::Commitment,
+    commitments::{pedersen::PedersenCommitment, Commitment},
     parameters::Parameters,
     protocols::{
         hash_to_prime::{
@@ -67,8 +64,7 @@ pub fn criterion_benchmark(c: &mut Criterion) {
     ))
     .random_below(&mut rng1);
     let (hashed_value, _) = protocol.hash_to_prime(&value).unwrap();
-    let randomness =
-        Integer::from(Integer::u_pow_u(2, Fr::size_in_bits() as u32)).random_below(&mut rng1);
+    let randomness = PedersenCommitment::<G1Projective>::sample_randomness(&mut rng2);
     let commitment = protocol
         .crs
         .crs_modeq
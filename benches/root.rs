@@ -11,6 +11,7 @@ use cpsnarks_set::{
             Protocol, Statement, Witness,
         },
     },
+    utils::ExpScratch,
 };
 use criterion::{criterion_group, criterion_main, Criterion};
 use merlin::Transcript;
@@ -19,6 +20,8 @@ use rug::rand::RandState;
 use rug::Integer;
 use std::cell::RefCell;
 
+const BATCH_SIZE: usize = 16;
+
 const LARGE_PRIMES: [u64; 3] = [
     12_702_637_924_034_044_211,
     378_373_571_372_703_133,
@@ -39,13 +42,13 @@ pub fn criterion_benchmark(c: &mut Criterion) {
     .unwrap()
     .crs
     .crs_root;
-    let protocol = Protocol::<Rsa2048>::from_crs(&crs);
+    let protocol = Protocol::<Rsa2048>::from_crs(&crs).unwrap();
 
     // prime from https://primes.utm.edu/lists/2small/200bit.html
     let value = (Integer::from(1) << 256) - 189;
     let randomness = Integer::from(5);
     let commitment = protocol
-        .crs
+        .crs()
         .integer_commitment_parameters
         .commit(&value, &randomness)
         .unwrap();
@@ -91,6 +94,66 @@ pub fn criterion_benchmark(c: &mut Criterion) {
     );
     protocol.verify(&mut prover_channel, &statement).unwrap();
 
+    let proofs = (0..BATCH_SIZE)
+        .map(|_| {
+            let proof_transcript = RefCell::new(Transcript::new(b"root"));
+            let mut verifier_channel = TranscriptVerifierChannel::new(&crs, &proof_transcript);
+            protocol
+                .prove(
+                    &mut verifier_channel,
+                    &mut rng1,
+                    &statement,
+                    &Witness {
+                        e: value.clone(),
+                        r: randomness.clone(),
+                        w: w.clone(),
+                    },
+                )
+                .unwrap();
+            verifier_channel.proof().unwrap()
+        })
+        .collect::<Vec<_>>();
+    // baseline: a protocol per proof, as the composed protocols build it, and
+    // a fresh scratch per verification
+    let baseline_crs = crs.clone();
+    let baseline_proofs = proofs.clone();
+    let baseline_statement = statement.clone();
+    c.bench_function("root protocol batch verification baseline", move |b| {
+        b.iter(|| {
+            for proof in &baseline_proofs {
+                let verification_protocol = Protocol::<Rsa2048>::from_crs(&baseline_crs).unwrap();
+                let verification_transcript = RefCell::new(Transcript::new(b"root"));
+                let mut prover_channel =
+                    TranscriptProverChannel::new(&baseline_crs, &verification_transcript, proof);
+                verification_protocol
+                    .verify(&mut prover_channel, &baseline_statement)
+                    .unwrap();
+            }
+        })
+    });
+
+    // the verification equations reuse the values precomputed by `from_crs`
+    // and the scratch, across the whole batch
+    let verification_crs = crs.clone();
+    let verification_protocol = Protocol::<Rsa2048>::from_crs(&verification_crs).unwrap();
+    let verification_statement = statement.clone();
+    let mut scratch = ExpScratch::new();
+    c.bench_function("root protocol batch verification", move |b| {
+        b.iter(|| {
+            for proof in &proofs {
+                let verification_transcript = RefCell::new(Transcript::new(b"root"));
+                let mut prover_channel = TranscriptProverChannel::new(
+                    &verification_crs,
+                    &verification_transcript,
+                    proof,
+                );
+                verification_protocol
+                    .verify_with_scratch(&mut prover_channel, &verification_statement, &mut scratch)
+                    .unwrap();
+            }
+        })
+    });
+
     c.bench_function("root protocol", move |b| {
         b.iter(|| {
             let proof_transcript = RefCell::new(Transcript::new(b"root"));
@@ -0,0 +1,161 @@
+use accumulator::group::Rsa2048;
+use ark_bls12_381::{Bls12_381, Fr, G1Projective};
+use cpsnarks_set::{
+    benchmarks::{BenchmarkReport, MembershipFixture, NonMembershipFixture, Thresholds},
+    parameters::Parameters,
+    protocols::{
+        hash_to_prime::{
+            snark_hash::{HashToPrimeHashParameters, Protocol as HashProtocol},
+            snark_range::Protocol as RangeProtocol,
+        },
+        membership, nonmembership,
+    },
+};
+use criterion::{criterion_group, criterion_main, Criterion};
+use rand::thread_rng;
+use rug::rand::RandState;
+use rug::Integer;
+use std::time::Duration;
+
+const LARGE_PRIMES: [u64; 3] = [
+    12_702_637_924_034_044_211,
+    378_373_571_372_703_133,
+    8_640_171_141_336_142_787,
+];
+
+struct TestHashToPrimeParameters {}
+impl HashToPrimeHashParameters for TestHashToPrimeParameters {
+    const MESSAGE_SIZE: u16 = 254;
+}
+
+/// Regression thresholds, with a wide margin over the measured costs.
+fn thresholds(max_verify_ms: u64, max_proof_size: usize) -> Thresholds {
+    Thresholds {
+        max_verify: Duration::from_millis(max_verify_ms),
+        max_proof_size,
+    }
+}
+
+fn report(name: &str, report: BenchmarkReport, thresholds: &Thresholds) {
+    println!(
+        "{}: proving {:?}, verification {:?}, proof size {} bytes",
+        name, report.prove, report.verify, report.proof_size
+    );
+    if let Err(e) = report.check(thresholds) {
+        panic!("{} regressed: {}", name, e);
+    }
+}
+
+pub fn criterion_benchmark(c: &mut Criterion) {
+    let params = Parameters::from_curve::<Fr>().unwrap().0;
+    println!("params: {}", params);
+    let mut rng1 = RandState::new();
+    rng1.seed(&Integer::from(13));
+    let mut rng2 = thread_rng();
+
+    let set = LARGE_PRIMES
+        .iter()
+        .skip(1)
+        .map(|p| Integer::from(*p))
+        .collect::<Vec<_>>();
+    let prime =
+        Integer::from(Integer::u_pow_u(2, params.hash_to_prime_bits as u32)) - &Integer::from(245);
+    let value = Integer::from(Integer::u_pow_u(2, params.hash_to_prime_bits as u32))
+        .random_below(&mut rng1);
+
+    let crs = membership::Protocol::<Rsa2048, G1Projective, RangeProtocol<Bls12_381>>::setup(
+        &params, &mut rng1, &mut rng2,
+    )
+    .unwrap()
+    .crs;
+    let fixture =
+        MembershipFixture::new(&crs, b"membership", &set, &prime, &mut rng1, &mut rng2).unwrap();
+    report(
+        "membership_prime",
+        fixture.measure(&mut rng1, &mut rng2, 1).unwrap(),
+        &thresholds(1_000, 8_192),
+    );
+    c.bench_function("membership_prime protocol verification", |b| {
+        b.iter(|| fixture.verify().unwrap())
+    });
+
+    let crs = membership::Protocol::<
+        Rsa2048,
+        G1Projective,
+        HashProtocol<Bls12_381, TestHashToPrimeParameters>,
+    >::setup(&params, &mut rng1, &mut rng2)
+    .unwrap()
+    .crs;
+    let fixture =
+        MembershipFixture::new(&crs, b"membership", &set, &value, &mut rng1, &mut rng2).unwrap();
+    report(
+        "membership_hash",
+        fixture.measure(&mut rng1, &mut rng2, 1).unwrap(),
+        &thresholds(1_000, 8_192),
+    );
+    c.bench_function("membership_hash protocol verification", |b| {
+        b.iter(|| fixture.verify().unwrap())
+    });
+
+    let crs = nonmembership::Protocol::<Rsa2048, G1Projective, RangeProtocol<Bls12_381>>::setup(
+        &params, &mut rng1, &mut rng2,
+    )
+    .unwrap()
+    .crs;
+    let fixture =
+        NonMembershipFixture::new(&crs, b"nonmembership", &set, &prime, &mut rng1, &mut rng2)
+            .unwrap();
+    report(
+        "nonmembership_prime",
+        fixture.measure(&mut rng1, &mut rng2, 1).unwrap(),
+        &thresholds(2_000, 12_288),
+    );
+    c.bench_function("nonmembership_prime protocol verification", |b| {
+        b.iter(|| fixture.verify().unwrap())
+    });
+
+    let crs = nonmembership::Protocol::<
+        Rsa2048,
+        G1Projective,
+        HashProtocol<Bls12_381, TestHashToPrimeParameters>,
+    >::setup(&params, &mut rng1, &mut rng2)
+    .unwrap()
+    .crs;
+    let fixture =
+        NonMembershipFixture::new(&crs, b"nonmembership", &set, &value, &mut rng1, &mut rng2)
+            .unwrap();
+    report(
+        "nonmembership_hash",
+        fixture.measure(&mut rng1, &mut rng2, 1).unwrap(),
+        &thresholds(2_000, 12_288),
+    );
+    c.bench_function("nonmembership_hash protocol verification", |b| {
+        b.iter(|| fixture.verify().unwrap())
+    });
+
+    #[cfg(feature = "class")]
+    {
+        use accumulator::group::ClassGroup;
+
+        let crs =
+            membership::Protocol::<ClassGroup, G1Projective, RangeProtocol<Bls12_381>>::setup(
+                &params, &mut rng1, &mut rng2,
+            )
+            .unwrap()
+            .crs;
+        let fixture =
+            MembershipFixture::new(&crs, b"membership", &set, &prime, &mut rng1, &mut rng2)
+                .unwrap();
+        report(
+            "membership_class",
+            fixture.measure(&mut rng1, &mut rng2, 1).unwrap(),
+            &thresholds(60_000, 16_384),
+        );
+        c.bench_function("membership_class protocol verification", |b| {
+            b.iter(|| fixture.verify().unwrap())
+        });
+    }
+}
+
+criterion_group!(benches, criterion_benchmark);
+criterion_main!(benches);
@@ -0,0 +1,33 @@
+use accumulator::group::Rsa2048;
+use cpsnarks_set::set::{AccumulatorProvider, SetAccumulator};
+use rug::rand::RandState;
+use rug::Integer;
+
+use criterion::{criterion_group, criterion_main, Criterion};
+
+const SET_SIZE: usize = 64;
+
+pub fn criterion_benchmark(c: &mut Criterion) {
+    let mut rng = RandState::new();
+    rng.seed(&Integer::from(13));
+    let elements = (0..SET_SIZE)
+        .map(|_| Integer::from(Integer::random_bits(128, &mut rng)).next_prime())
+        .collect::<Vec<_>>();
+    let set = SetAccumulator::<Rsa2048>::from_elements(&elements);
+
+    c.bench_function("membership witnesses, one by one", |b| {
+        b.iter(|| {
+            elements
+                .iter()
+                .map(|e| set.membership_witness(e).unwrap())
+                .collect::<Vec<_>>()
+        })
+    });
+
+    c.bench_function("membership witnesses, product tree", |b| {
+        b.iter(|| set.membership_witnesses())
+    });
+}
+
+criterion_group!(benches, criterion_benchmark);
+criterion_main!(benches);
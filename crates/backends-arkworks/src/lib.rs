@@ -0,0 +1,18 @@
+//! The LegoGroth16 hash-to-prime backends of `cpsnarks-set`, over arkworks
+//! curves such as BLS12-381.
+//!
+//! This crate enables the `arkworks` feature of `cpsnarks-set` and
+//! re-exports its SNARK backends, together with `cpsnarks-set-core` as
+//! `core`. Setting up and proving need the default `prover` feature, which
+//! also brings the R1CS gadgets of `gadgets`.
+pub use cpsnarks_set_core as core;
+
+pub mod hash_to_prime {
+    //! The hash-to-prime and range proof protocols proven with LegoGroth16.
+    pub use cpsnarks_set::protocols::hash_to_prime::{
+        dynamic, link, snark_batch, snark_hash, snark_hash_public, snark_increment, snark_range,
+    };
+}
+
+#[cfg(feature = "prover")]
+pub use cpsnarks_set::protocols::gadgets;
@@ -0,0 +1,12 @@
+//! The Bulletproofs range proof backend of `cpsnarks-set`, over Ristretto.
+//!
+//! This crate enables the `dalek` feature of `cpsnarks-set` and re-exports
+//! its Bulletproofs backend, together with `cpsnarks-set-core` as `core`.
+//! Like the `dalek` feature, it needs a nightly toolchain, so it is excluded
+//! from the workspace and built with its own manifest.
+pub use cpsnarks_set_core as core;
+
+pub mod hash_to_prime {
+    //! The range proof protocol proven with Bulletproofs.
+    pub use cpsnarks_set::protocols::hash_to_prime::bp;
+}
@@ -0,0 +1,20 @@
+//! The traits, commitments and sigma protocols of `cpsnarks-set`, without a
+//! hash-to-prime backend.
+//!
+//! This crate re-exports the backend-independent parts of `cpsnarks-set`,
+//! which it depends on without default features: the membership and
+//! nonmembership protocols are generic over `HashToPrimeProtocol`, and a
+//! backend comes from `cpsnarks-set-backends-arkworks` or
+//! `cpsnarks-set-backends-dalek`. The code stays in `cpsnarks-set`, as the
+//! curve traits are implemented there for the curve types of both backends
+//! and the error types are shared by all the protocols.
+pub use cpsnarks_set::{channels, commitments, parameters, prelude, transcript, utils};
+
+pub mod protocols {
+    //! The sigma protocols and the protocols composed from them.
+    pub use cpsnarks_set::protocols::{
+        anchor, blinding, coprime, hash_to_prime, intersection, linking, membership, mixed, modeq,
+        nonmembership, one_of, pokcp, poke, progress, root, security, sizes, statement,
+        validate_soundness, CRSError, ProofError, SetupError, VerificationError,
+    };
+}
@@ -0,0 +1,24 @@
+//! Accumulator management, storage and deployment of `cpsnarks-set`: the
+//! sets and their witnesses, the anonymous credential roles, CRS registries
+//! and the verification of presentations.
+//!
+//! This crate re-exports these modules of `cpsnarks-set`, together with
+//! `cpsnarks-set-core` as `core`, and takes no hash-to-prime backend. The
+//! `sled` feature adds `set::storage::SledStorage`, and the default `prover`
+//! feature adds `migration` and `benchmarks`.
+pub use cpsnarks_set::{roles, set, strategy};
+pub use cpsnarks_set_core as core;
+
+#[cfg(feature = "prover")]
+pub use cpsnarks_set::benchmarks;
+
+pub mod protocols {
+    //! Verifying, registering and presenting proofs.
+    pub use cpsnarks_set::protocols::{
+        accumulator_digest, audit, augmentation, boxed, cache, context, explain, external,
+        presentation, registry, replay, verifier,
+    };
+
+    #[cfg(feature = "prover")]
+    pub use cpsnarks_set::protocols::migration;
+}
@@ -0,0 +1,412 @@
+//! Benchmarks as library functions, so proving cost, verification cost and
+//! proof sizes can be measured for any backend combination, from the criterion
+//! targets as well as from applications or CI jobs.
+//!
+//! A fixture sets up a statement, a witness and a proof for a protocol. Its
+//! `measure` returns a `BenchmarkReport`, which `check` compares against
+//! regression `Thresholds`.
+use crate::{
+    commitments::Commitment,
+    protocols::{
+        hash_to_prime::HashToPrimeProtocol,
+        membership::{
+            transcript::{
+                TranscriptProverChannel as MembershipTranscriptProverChannel,
+                TranscriptVerifierChannel as MembershipTranscriptVerifierChannel,
+            },
+            Proof as MembershipProof, Protocol as MembershipProtocol,
+            Statement as MembershipStatement, Witness as MembershipWitness, CRS as MembershipCRS,
+        },
+        nonmembership::{
+            transcript::{
+                TranscriptProverChannel as NonMembershipTranscriptProverChannel,
+                TranscriptVerifierChannel as NonMembershipTranscriptVerifierChannel,
+            },
+            Proof as NonMembershipProof, Protocol as NonMembershipProtocol,
+            Statement as NonMembershipStatement, Witness as NonMembershipWitness,
+            CRS as NonMembershipCRS,
+        },
+        ProofError, VerificationError,
+    },
+    utils::{
+        curve::{CurveError, CurvePointProjective, Field},
+        ConvertibleUnknownOrderGroup,
+    },
+};
+use accumulator::{Accumulator, AccumulatorWithoutHashToPrime};
+use merlin::Transcript;
+use rand::{CryptoRng, RngCore};
+use rug::{rand::MutRandState, Integer};
+use std::{
+    cell::RefCell,
+    time::{Duration, Instant},
+};
+
+quick_error! {
+    #[derive(Debug)]
+    pub enum BenchmarkError {
+        InvalidWitness {}
+        SlowVerification(took: Duration, max: Duration) {
+            display("verification took {:?}, more than {:?}", took, max)
+        }
+        LargeProof(size: usize, max: usize) {
+            display("proof has {} bytes, more than {}", size, max)
+        }
+        ProofError(err: ProofError) {
+            from()
+        }
+        VerificationError(err: VerificationError) {
+            from()
+        }
+        CurveError(err: CurveError) {
+            from()
+        }
+    }
+}
+
+/// Average costs over the measured iterations.
+#[derive(Clone, Debug)]
+pub struct BenchmarkReport {
+    pub prove: Duration,
+    pub verify: Duration,
+    /// Size of the proof, see `Proof::serialized_size`.
+    pub proof_size: usize,
+}
+
+/// Upper bounds for a configuration, e.g. the last release's numbers with
+/// some margin.
+#[derive(Clone, Debug)]
+pub struct Thresholds {
+    pub max_verify: Duration,
+    pub max_proof_size: usize,
+}
+
+impl BenchmarkReport {
+    pub fn check(&self, thresholds: &Thresholds) -> Result<(), BenchmarkError> {
+        if self.verify > thresholds.max_verify {
+            return Err(BenchmarkError::SlowVerification(
+                self.verify,
+                thresholds.max_verify,
+            ));
+        }
+        if self.proof_size > thresholds.max_proof_size {
+            return Err(BenchmarkError::LargeProof(
+                self.proof_size,
+                thresholds.max_proof_size,
+            ));
+        }
+        Ok(())
+    }
+}
+
+fn average<F: FnMut() -> Result<(), BenchmarkError>>(
+    iterations: u32,
+    mut f: F,
+) -> Result<Duration, BenchmarkError> {
+    let start = Instant::now();
+    for _ in 0..iterations {
+        f()?;
+    }
+    Ok(start.elapsed() / iterations.max(1))
+}
+
+fn random_randomness<P: CurvePointProjective, R: MutRandState>(rng: &mut R) -> Integer {
+    P::ScalarField::modulus().random_below(rng)
+}
+
+pub struct MembershipFixture<
+    G: ConvertibleUnknownOrderGroup,
+    P: CurvePointProjective,
+    HP: HashToPrimeProtocol<P>,
+> {
+    pub protocol: MembershipProtocol<G, P, HP>,
+    pub statement: MembershipStatement<G, P>,
+    pub witness: MembershipWitness<G>,
+    pub proof: MembershipProof<G, P, HP>,
+    transcript_label: &'static [u8],
+}
+
+impl<G: ConvertibleUnknownOrderGroup, P: CurvePointProjective, HP: HashToPrimeProtocol<P>>
+    MembershipFixture<G, P, HP>
+{
+    /// Proves membership of `value`, hashed to a prime if `HP` does so, in an
+    /// accumulator holding it and the primes in `set`.
+    pub fn new<R1: MutRandState, R2: RngCore + CryptoRng>(
+        crs: &MembershipCRS<G, P, HP>,
+        transcript_label: &'static [u8],
+        set: &[Integer],
+        value: &Integer,
+        rng1: &mut R1,
+        rng2: &mut R2,
+    ) -> Result<MembershipFixture<G, P, HP>, BenchmarkError> {
+        let protocol = MembershipProtocol::from_crs(crs);
+        let (prime, _) = protocol
+            .hash_to_prime(value)
+            .map_err(|_| BenchmarkError::InvalidWitness)?;
+        let randomness = random_randomness::<P, _>(rng1);
+        let (accumulator, membership_proof) =
+            Accumulator::<G, Integer, AccumulatorWithoutHashToPrime>::empty()
+                .add(set)
+                .add_with_proof(&[prime.clone()]);
+        let statement = MembershipStatement {
+            c_p: accumulator.value,
+            c_e_q: crs
+                .crs_modeq
+                .pedersen_commitment_parameters
+                .commit(&prime, &randomness)?,
+        };
+        let witness = MembershipWitness {
+            e: value.clone(),
+            r_q: randomness,
+            w: membership_proof.witness.0.value,
+        };
+        let proof = Self::prove_with(
+            &protocol,
+            transcript_label,
+            &statement,
+            &witness,
+            rng1,
+            rng2,
+        )?;
+        Ok(MembershipFixture {
+            protocol,
+            statement,
+            witness,
+            proof,
+            transcript_label,
+        })
+    }
+
+    fn prove_with<R1: MutRandState, R2: RngCore + CryptoRng>(
+        protocol: &MembershipProtocol<G, P, HP>,
+        transcript_label: &'static [u8],
+        statement: &MembershipStatement<G, P>,
+        witness: &MembershipWitness<G>,
+        rng1: &mut R1,
+        rng2: &mut R2,
+    ) -> Result<MembershipProof<G, P, HP>, BenchmarkError> {
+        let transcript = RefCell::new(Transcript::new(transcript_label));
+        let mut verifier_channel =
+            MembershipTranscriptVerifierChannel::new(&protocol.crs, &transcript);
+        protocol.prove(&mut verifier_channel, rng1, rng2, statement, witness)?;
+        Ok(verifier_channel
+            .proof()
+            .map_err(|_| ProofError::CouldNotCreateProof)?)
+    }
+
+    pub fn prove<R1: MutRandState, R2: RngCore + CryptoRng>(
+        &self,
+        rng1: &mut R1,
+        rng2: &mut R2,
+    ) -> Result<MembershipProof<G, P, HP>, BenchmarkError> {
+        Self::prove_with(
+            &self.protocol,
+            self.transcript_label,
+            &self.statement,
+            &self.witness,
+            rng1,
+            rng2,
+        )
+    }
+
+    pub fn verify(&self) -> Result<(), VerificationError> {
+        let transcript = RefCell::new(Transcript::new(self.transcript_label));
+        let mut prover_channel =
+            MembershipTranscriptProverChannel::new(&self.protocol.crs, &transcript, &self.proof);
+        self.protocol.verify(&mut prover_channel, &self.statement)
+    }
+
+    pub fn measure<R1: MutRandState, R2: RngCore + CryptoRng>(
+        &self,
+        rng1: &mut R1,
+        rng2: &mut R2,
+        iterations: u32,
+    ) -> Result<BenchmarkReport, BenchmarkError> {
+        Ok(BenchmarkReport {
+            prove: average(iterations, || self.prove(rng1, rng2).map(|_| ()))?,
+            verify: average(iterations, || Ok(self.verify()?))?,
+            proof_size: self.proof.serialized_size()?,
+        })
+    }
+}
+
+pub struct NonMembershipFixture<
+    G: ConvertibleUnknownOrderGroup,
+    P: CurvePointProjective,
+    HP: HashToPrimeProtocol<P>,
+> {
+    pub protocol: NonMembershipProtocol<G, P, HP>,
+    pub statement: NonMembershipStatement<G, P>,
+    pub witness: NonMembershipWitness<G>,
+    pub proof: NonMembershipProof<G, P, HP>,
+    transcript_label: &'static [u8],
+}
+
+impl<G: ConvertibleUnknownOrderGroup, P: CurvePointProjective, HP: HashToPrimeProtocol<P>>
+    NonMembershipFixture<G, P, HP>
+{
+    /// Proves nonmembership of `value`, hashed to a prime if `HP` does so, in
+    /// an accumulator holding the primes in `set`.
+    pub fn new<R1: MutRandState, R2: RngCore + CryptoRng>(
+        crs: &NonMembershipCRS<G, P, HP>,
+        transcript_label: &'static [u8],
+        set: &[Integer],
+        value: &Integer,
+        rng1: &mut R1,
+        rng2: &mut R2,
+    ) -> Result<NonMembershipFixture<G, P, HP>, BenchmarkError> {
+        let protocol = NonMembershipProtocol::from_crs(crs);
+        let (prime, _) = protocol
+            .hash_to_prime(value)
+            .map_err(|_| BenchmarkError::InvalidWitness)?;
+        let randomness = random_randomness::<P, _>(rng1);
+        let accumulator =
+            Accumulator::<G, Integer, AccumulatorWithoutHashToPrime>::empty().add(set);
+        let nonmembership_proof = accumulator
+            .prove_nonmembership(set, &[prime.clone()])
+            .map_err(|_| BenchmarkError::InvalidWitness)?;
+        let statement = NonMembershipStatement {
+            c_p: accumulator.value,
+            c_e_q: crs
+                .crs_modeq
+                .pedersen_commitment_parameters
+                .commit(&prime, &randomness)?,
+        };
+        let witness = NonMembershipWitness {
+            e: value.clone(),
+            r_q: randomness,
+            d: nonmembership_proof.d,
+            b: nonmembership_proof.b,
+        };
+        let proof = Self::prove_with(
+            &protocol,
+            transcript_label,
+            &statement,
+            &witness,
+            rng1,
+            rng2,
+        )?;
+        Ok(NonMembershipFixture {
+            protocol,
+            statement,
+            witness,
+            proof,
+            transcript_label,
+        })
+    }
+
+    fn prove_with<R1: MutRandState, R2: RngCore + CryptoRng>(
+        protocol: &NonMembershipProtocol<G, P, HP>,
+        transcript_label: &'static [u8],
+        statement: &NonMembershipStatement<G, P>,
+        witness: &NonMembershipWitness<G>,
+        rng1: &mut R1,
+        rng2: &mut R2,
+    ) -> Result<NonMembershipProof<G, P, HP>, BenchmarkError> {
+        let transcript = RefCell::new(Transcript::new(transcript_label));
+        let mut verifier_channel =
+            NonMembershipTranscriptVerifierChannel::new(&protocol.crs, &transcript);
+        protocol.prove(&mut verifier_channel, rng1, rng2, statement, witness)?;
+        Ok(verifier_channel
+            .proof()
+            .map_err(|_| ProofError::CouldNotCreateProof)?)
+    }
+
+    pub fn prove<R1: MutRandState, R2: RngCore + CryptoRng>(
+        &self,
+        rng1: &mut R1,
+        rng2: &mut R2,
+    ) -> Result<NonMembershipProof<G, P, HP>, BenchmarkError> {
+        Self::prove_with(
+            &self.protocol,
+            self.transcript_label,
+            &self.statement,
+            &self.witness,
+            rng1,
+            rng2,
+        )
+    }
+
+    pub fn verify(&self) -> Result<(), VerificationError> {
+        let transcript = RefCell::new(Transcript::new(self.transcript_label));
+        let mut prover_channel =
+            NonMembershipTranscriptProverChannel::new(&self.protocol.crs, &transcript, &self.proof);
+        self.protocol.verify(&mut prover_channel, &self.statement)
+    }
+
+    pub fn measure<R1: MutRandState, R2: RngCore + CryptoRng>(
+        &self,
+        rng1: &mut R1,
+        rng2: &mut R2,
+        iterations: u32,
+    ) -> Result<BenchmarkReport, BenchmarkError> {
+        Ok(BenchmarkReport {
+            prove: average(iterations, || self.prove(rng1, rng2).map(|_| ()))?,
+            verify: average(iterations, || Ok(self.verify()?))?,
+            proof_size: self.proof.serialized_size()?,
+        })
+    }
+}
+
+#[cfg(all(test, feature = "arkworks"))]
+mod test {
+    use super::{BenchmarkError, MembershipFixture, NonMembershipFixture, Thresholds};
+    use crate::{
+        parameters::Parameters,
+        protocols::{
+            hash_to_prime::snark_range::Protocol as HPProtocol, membership, nonmembership,
+        },
+    };
+    use accumulator::group::Rsa2048;
+    use ark_bls12_381::{Bls12_381, G1Projective};
+    use rand::thread_rng;
+    use rug::rand::RandState;
+    use rug::Integer;
+    use std::time::Duration;
+
+    #[test]
+    fn test_fixtures() {
+        let params = Parameters::from_security_level(128).unwrap();
+        let mut rng1 = RandState::new();
+        rng1.seed(&Integer::from(13));
+        let mut rng2 = thread_rng();
+        let set = [Integer::from(378_373_571_372_703_133u64)];
+        let value = Integer::from(Integer::u_pow_u(2, params.hash_to_prime_bits as u32))
+            - &Integer::from(245);
+
+        let crs = membership::Protocol::<Rsa2048, G1Projective, HPProtocol<Bls12_381>>::setup(
+            &params, &mut rng1, &mut rng2,
+        )
+        .unwrap()
+        .crs;
+        let fixture =
+            MembershipFixture::new(&crs, b"membership", &set, &value, &mut rng1, &mut rng2)
+                .unwrap();
+        let report = fixture.measure(&mut rng1, &mut rng2, 1).unwrap();
+        assert!(report.proof_size > 0);
+        report
+            .check(&Thresholds {
+                max_verify: Duration::from_secs(3600),
+                max_proof_size: report.proof_size,
+            })
+            .unwrap();
+        assert!(matches!(
+            report.check(&Thresholds {
+                max_verify: Duration::from_secs(3600),
+                max_proof_size: report.proof_size - 1,
+            }),
+            Err(BenchmarkError::LargeProof(_, _))
+        ));
+
+        let crs = nonmembership::Protocol::<Rsa2048, G1Projective, HPProtocol<Bls12_381>>::setup(
+            &params, &mut rng1, &mut rng2,
+        )
+        .unwrap()
+        .crs;
+        let fixture =
+            NonMembershipFixture::new(&crs, b"nonmembership", &set, &value, &mut rng1, &mut rng2)
+                .unwrap();
+        fixture.verify().unwrap();
+        assert!(fixture.proof.serialized_size().unwrap() > 0);
+    }
+}
@@ -19,5 +19,69 @@ quick_error! {
         CurveError(e: CurveError) {
             from()
         }
+        OutOfOrder {}
+        Duplicate {}
+    }
+}
+
+/// Tracks the messages a channel handled, so that messages arriving out of
+/// order or twice are rejected instead of silently diverging the transcript.
+#[derive(Clone, Debug, Default)]
+pub struct MessageOrder {
+    handled: Option<usize>,
+}
+
+impl MessageOrder {
+    pub fn new() -> MessageOrder {
+        MessageOrder { handled: None }
+    }
+
+    fn next(&self) -> usize {
+        self.handled.map_or(0, |handled| handled + 1)
+    }
+
+    /// Records message `index`, which has to directly follow the last one.
+    pub fn step(&mut self, index: usize) -> Result<(), ChannelError> {
+        if index < self.next() {
+            return Err(ChannelError::Duplicate);
+        }
+        if index > self.next() {
+            return Err(ChannelError::OutOfOrder);
+        }
+        self.handled = Some(index);
+        Ok(())
+    }
+
+    /// Records a message of phase `index`, which is either the phase of the
+    /// last message or the one following it. Composed channels use phases for
+    /// their sub-protocols, whose channels check the messages in each phase.
+    pub fn enter(&mut self, index: usize) -> Result<(), ChannelError> {
+        if self.handled == Some(index) {
+            return Ok(());
+        }
+        self.step(index).map_err(|_| ChannelError::OutOfOrder)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{ChannelError, MessageOrder};
+
+    #[test]
+    fn test_message_order() {
+        let mut order = MessageOrder::new();
+        assert!(matches!(order.step(1), Err(ChannelError::OutOfOrder)));
+        order.step(0).unwrap();
+        assert!(matches!(order.step(0), Err(ChannelError::Duplicate)));
+        order.step(1).unwrap();
+
+        let mut phases = MessageOrder::new();
+        phases.step(0).unwrap();
+        phases.enter(1).unwrap();
+        phases.enter(1).unwrap();
+        assert!(matches!(phases.enter(3), Err(ChannelError::OutOfOrder)));
+        phases.enter(2).unwrap();
+        assert!(matches!(phases.enter(1), Err(ChannelError::OutOfOrder)));
+        assert!(matches!(phases.step(0), Err(ChannelError::Duplicate)));
     }
 }
@@ -2,7 +2,7 @@
 
 use crate::{
     commitments::{Commitment, CommitmentError},
-    utils::ConvertibleUnknownOrderGroup,
+    utils::{exp_signed, ConvertibleUnknownOrderGroup},
 };
 use rug::rand::MutRandState;
 use rug::Integer;
@@ -17,7 +17,7 @@ impl<G: ConvertibleUnknownOrderGroup> IntegerCommitment<G> {
     pub fn setup<R: MutRandState>(rng: &mut R) -> IntegerCommitment<G> {
         let upper_bound = G::order_upper_bound();
         let g = G::unknown_order_elem();
-        let h = G::exp(&g, &upper_bound.random_below(rng));
+        let h = exp_signed::<G>(&g, &upper_bound.random_below(rng));
         IntegerCommitment { g, h }
     }
 
@@ -37,7 +37,10 @@ impl<G: ConvertibleUnknownOrderGroup> Commitment for IntegerCommitment<G> {
         value: &Integer,
         randomness: &Integer,
     ) -> Result<Self::Instance, CommitmentError> {
-        Ok(G::op(&G::exp(&self.g, value), &G::exp(&self.h, randomness)))
+        Ok(G::op(
+            &exp_signed::<G>(&self.g, value),
+            &exp_signed::<G>(&self.h, randomness),
+        ))
     }
 
     fn open(
@@ -46,7 +49,10 @@ impl<G: ConvertibleUnknownOrderGroup> Commitment for IntegerCommitment<G> {
         value: &Integer,
         randomness: &Integer,
     ) -> Result<(), CommitmentError> {
-        let expected = G::op(&G::exp(&self.g, value), &G::exp(&self.h, randomness));
+        let expected = G::op(
+            &exp_signed::<G>(&self.g, value),
+            &exp_signed::<G>(&self.h, randomness),
+        );
         if expected == *commitment {
             Ok(())
         } else {
@@ -55,10 +61,43 @@ impl<G: ConvertibleUnknownOrderGroup> Commitment for IntegerCommitment<G> {
     }
 }
 
+/// A commitment the caller already has, e.g. `c_e` registered at issuance,
+/// with its randomness, for the protocols to prove about instead of
+/// committing again.
+pub struct IntegerCommitmentOpening<G: ConvertibleUnknownOrderGroup> {
+    pub commitment: G::Elem,
+    pub randomness: Integer,
+}
+
+impl<G: ConvertibleUnknownOrderGroup> Clone for IntegerCommitmentOpening<G> {
+    fn clone(&self) -> Self {
+        Self {
+            commitment: self.commitment.clone(),
+            randomness: self.randomness.clone(),
+        }
+    }
+}
+
+impl<G: ConvertibleUnknownOrderGroup> IntegerCommitmentOpening<G> {
+    /// Checks the commitment opens to `value` under `parameters`, with the
+    /// randomness in `[0, G::order_upper_bound())` like the protocols sample
+    /// it, as the blinding values of the sigma protocols are sized for it.
+    pub fn validate(
+        &self,
+        parameters: &IntegerCommitment<G>,
+        value: &Integer,
+    ) -> Result<(), CommitmentError> {
+        if self.randomness < 0 || self.randomness >= G::order_upper_bound() {
+            return Err(CommitmentError::RandomnessOutOfRange);
+        }
+        parameters.open(&self.commitment, value, &self.randomness)
+    }
+}
+
 #[cfg(test)]
 mod test {
-    use super::IntegerCommitment;
-    use crate::commitments::Commitment;
+    use super::{IntegerCommitment, IntegerCommitmentOpening};
+    use crate::commitments::{Commitment, CommitmentError};
     use accumulator::group::Rsa2048;
     use rug::rand::RandState;
     use rug::Integer;
@@ -85,4 +124,32 @@ mod test {
             .open(&commitment, &wrong_value, &wrong_randomness)
             .unwrap_err();
     }
+
+    #[test]
+    fn test_opening() {
+        let mut rng = RandState::new();
+        rng.seed(&Integer::from(13));
+
+        let value = Integer::from(2);
+        let integer = IntegerCommitment::<Rsa2048>::setup(&mut rng);
+        let opening = |randomness: Integer| IntegerCommitmentOpening::<Rsa2048> {
+            commitment: integer.commit(&value, &randomness).unwrap(),
+            randomness,
+        };
+        opening(Integer::from(5))
+            .validate(&integer, &value)
+            .unwrap();
+        assert!(matches!(
+            opening(Integer::from(5)).validate(&integer, &Integer::from(3)),
+            Err(CommitmentError::WrongOpening)
+        ));
+        assert!(matches!(
+            opening(Integer::from(-5)).validate(&integer, &value),
+            Err(CommitmentError::RandomnessOutOfRange)
+        ));
+        assert!(matches!(
+            opening(Rsa2048::order_upper_bound()).validate(&integer, &value),
+            Err(CommitmentError::RandomnessOutOfRange)
+        ));
+    }
 }
@@ -10,6 +10,7 @@ quick_error! {
     pub enum CommitmentError {
         WrongOpening {}
         IntegerTooBig {}
+        RandomnessOutOfRange {}
         ConversionError(err: std::io::Error) {
             from()
         }
@@ -1,7 +1,11 @@
 //! Pedersen commitment over elliptic curves.
 
 use crate::commitments::{Commitment, CommitmentError};
-use crate::utils::{curve::CurvePointProjective, integer_to_bigint};
+use crate::utils::{
+    bigint_to_integer,
+    curve::{CurvePointProjective, Field},
+    integer_to_bigint,
+};
 use rand::{CryptoRng, RngCore};
 use rug::Integer;
 
@@ -25,7 +29,72 @@ impl<P: CurvePointProjective> PedersenCommitment<P> {
             h: h.clone(),
         }
     }
+
+    /// Samples randomness uniformly in `[0, q)`, with `q` the order of the
+    /// curve, the range `commit` accepts.
+    pub fn sample_randomness<R: RngCore + CryptoRng>(rng: &mut R) -> Integer {
+        bigint_to_integer::<P>(&P::ScalarField::rand(rng))
+    }
+}
+
+/// The randomness as a scalar, rejecting it unless it's in `[0, q)`: larger
+/// values would be reduced here, but not by the provers, which check `r_q` is
+/// reduced, and the same commitment would open to several integers.
+fn randomness_to_bigint<P: CurvePointProjective>(
+    randomness: &Integer,
+) -> Result<P::ScalarField, CommitmentError> {
+    if *randomness < 0 || *randomness >= P::ScalarField::modulus() {
+        return Err(CommitmentError::RandomnessOutOfRange);
+    }
+    Ok(integer_to_bigint::<P>(randomness))
+}
+
+#[cfg(feature = "arkworks")]
+mod arkworks {
+    use super::PedersenCommitment;
+    use ark_ec::{AffineCurve, ProjectiveCurve};
+
+    impl<P: ProjectiveCurve> PedersenCommitment<P> {
+        /// Reuses commitment keys given in affine form, e.g. taken from the
+        /// parameters of another circuit.
+        pub fn from_affine(g: &P::Affine, h: &P::Affine) -> PedersenCommitment<P> {
+            PedersenCommitment {
+                g: g.into_projective(),
+                h: h.into_projective(),
+            }
+        }
+
+        pub fn to_affine(&self) -> (P::Affine, P::Affine) {
+            (self.g.into_affine(), self.h.into_affine())
+        }
+    }
+}
+
+#[cfg(feature = "dalek")]
+mod dalek {
+    use super::PedersenCommitment;
+    use bulletproofs::PedersenGens;
+    use curve25519_dalek::ristretto::RistrettoPoint;
+
+    impl From<PedersenGens> for PedersenCommitment<RistrettoPoint> {
+        fn from(gens: PedersenGens) -> PedersenCommitment<RistrettoPoint> {
+            PedersenCommitment {
+                g: gens.B,
+                h: gens.B_blinding,
+            }
+        }
+    }
+
+    impl From<&PedersenCommitment<RistrettoPoint>> for PedersenGens {
+        fn from(pedersen: &PedersenCommitment<RistrettoPoint>) -> PedersenGens {
+            PedersenGens {
+                B: pedersen.g,
+                B_blinding: pedersen.h,
+            }
+        }
+    }
 }
+
 impl<P: CurvePointProjective> Commitment for PedersenCommitment<P> {
     type Instance = P;
 
@@ -35,7 +104,7 @@ impl<P: CurvePointProjective> Commitment for PedersenCommitment<P> {
         randomness: &Integer,
     ) -> Result<Self::Instance, CommitmentError> {
         let v = integer_to_bigint::<P>(value);
-        let r = integer_to_bigint::<P>(randomness);
+        let r = randomness_to_bigint::<P>(randomness)?;
         Ok(self.g.mul(&v).add(&self.h.mul(&r)))
     }
 
@@ -48,7 +117,7 @@ impl<P: CurvePointProjective> Commitment for PedersenCommitment<P> {
         let expected = self
             .g
             .mul(&integer_to_bigint::<P>(value))
-            .add(&self.h.mul(&integer_to_bigint::<P>(randomness)));
+            .add(&self.h.mul(&randomness_to_bigint::<P>(randomness)?));
         if expected == *commitment {
             Ok(())
         } else {
@@ -60,8 +129,11 @@ impl<P: CurvePointProjective> Commitment for PedersenCommitment<P> {
 #[cfg(all(test, feature = "arkworks"))]
 mod test {
     use super::PedersenCommitment;
-    use crate::commitments::Commitment;
-    use ark_bls12_381::G1Projective;
+    use crate::{
+        commitments::{Commitment, CommitmentError},
+        utils::curve::Field,
+    };
+    use ark_bls12_381::{Fr, G1Projective};
     use rand::thread_rng;
     use rug::Integer;
 
@@ -86,4 +158,45 @@ mod test {
             .open(&commitment, &wrong_value, &wrong_randomness)
             .unwrap_err();
     }
+
+    #[test]
+    fn test_randomness_range() {
+        let mut rng = thread_rng();
+
+        let value = Integer::from(2);
+        let pedersen = PedersenCommitment::<G1Projective>::setup(&mut rng);
+        let q = Fr::modulus();
+        for _ in 0..100 {
+            let randomness = PedersenCommitment::<G1Projective>::sample_randomness(&mut rng);
+            assert!(randomness >= 0 && randomness < q);
+            let commitment = pedersen.commit(&value, &randomness).unwrap();
+            pedersen.open(&commitment, &value, &randomness).unwrap();
+        }
+
+        let commitment = pedersen.commit(&value, &Integer::from(5)).unwrap();
+        for randomness in &[Integer::from(-1), q.clone(), Integer::from(&q + 5)] {
+            assert!(matches!(
+                pedersen.commit(&value, randomness),
+                Err(CommitmentError::RandomnessOutOfRange)
+            ));
+        }
+        // the same commitment doesn't open with the unreduced randomness
+        assert!(matches!(
+            pedersen.open(&commitment, &value, &Integer::from(&q + 5)),
+            Err(CommitmentError::RandomnessOutOfRange)
+        ));
+    }
+
+    #[test]
+    fn test_from_affine() {
+        let mut rng = thread_rng();
+
+        let value = Integer::from(2);
+        let randomness = Integer::from(5);
+        let pedersen = PedersenCommitment::<G1Projective>::setup(&mut rng);
+        let commitment = pedersen.commit(&value, &randomness).unwrap();
+        let (g, h) = pedersen.to_affine();
+        let reused = PedersenCommitment::<G1Projective>::from_affine(&g, &h);
+        reused.open(&commitment, &value, &randomness).unwrap();
+    }
 }
@@ -17,13 +17,29 @@
 //! The higher level protocols (membership, nonmembership) define setup, prove
 //! and verify functions and compose the subprotocols into end-to-end protocols
 //! ready to use.
+//!
+//! Applications should import them from `prelude`, whose names are kept
+//! stable across reorganizations of the modules.
+//!
+//! The default `prover` feature enables setup and proving, and the
+//! `ark-r1cs-std` and `ark-crypto-primitives` dependencies of the SNARK
+//! circuits. Verifiers can depend on the crate with `default-features = false,
+//! features = ["arkworks"]` and load the CRS instead. Without the feature, the
+//! setup and proving of the SNARK hash-to-prime backends fail with
+//! `ProverNotEnabled`.
 
 #[macro_use]
 extern crate quick_error;
 
+#[cfg(feature = "prover")]
+pub mod benchmarks;
 pub mod channels;
 pub mod commitments;
 pub mod parameters;
+pub mod prelude;
 pub mod protocols;
+pub mod roles;
+pub mod set;
+pub mod strategy;
 pub mod transcript;
 pub mod utils;
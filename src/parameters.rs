@@ -16,6 +16,63 @@ pub struct Parameters {
     pub hash_to_prime_bits: u16, // μ
     /// Size of the field the element are taken from.
     pub field_size_bits: u16, // ν
+    /// Security overrides for individual sub-protocols, used instead of
+    /// `security_zk` and `security_soundness` when set.
+    pub overrides: SubProtocolOverrides,
+    /// Hash-to-prime backend set up and used by `DynHashToPrime`. The other
+    /// hash-to-prime protocols ignore it.
+    pub hash_to_prime_backend: Option<HashToPrimeBackend>,
+}
+
+/// The hash-to-prime protocols `DynHashToPrime` can dispatch to.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum HashToPrimeBackend {
+    SnarkRange,
+    SnarkHash,
+    SnarkIncrement,
+}
+
+/// The sub-protocols composed by membership and non-membership.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SubProtocol {
+    Root,
+    Coprime,
+    ModEq,
+    HashToPrime,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SecurityOverride {
+    pub security_zk: u16,
+    pub security_soundness: u16,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct SubProtocolOverrides {
+    pub root: Option<SecurityOverride>,
+    pub coprime: Option<SecurityOverride>,
+    pub modeq: Option<SecurityOverride>,
+    pub hash_to_prime: Option<SecurityOverride>,
+}
+
+impl SubProtocolOverrides {
+    pub fn get(&self, sub_protocol: SubProtocol) -> Option<SecurityOverride> {
+        match sub_protocol {
+            SubProtocol::Root => self.root,
+            SubProtocol::Coprime => self.coprime,
+            SubProtocol::ModEq => self.modeq,
+            SubProtocol::HashToPrime => self.hash_to_prime,
+        }
+    }
+
+    fn get_mut(&mut self, sub_protocol: SubProtocol) -> &mut Option<SecurityOverride> {
+        match sub_protocol {
+            SubProtocol::Root => &mut self.root,
+            SubProtocol::Coprime => &mut self.coprime,
+            SubProtocol::ModEq => &mut self.modeq,
+            SubProtocol::HashToPrime => &mut self.hash_to_prime,
+        }
+    }
 }
 
 impl fmt::Display for Parameters {
@@ -46,6 +103,8 @@ impl Parameters {
             security_soundness: security_level - 2,
             field_size_bits: 2 * security_level,
             hash_to_prime_bits: 2 * security_level - 2,
+            overrides: SubProtocolOverrides::default(),
+            hash_to_prime_backend: None,
         };
 
         parameters.is_valid()?;
@@ -54,7 +113,14 @@ impl Parameters {
 
     /// Derive parameters based on a curve.
     pub fn from_curve<P: Field>() -> Result<(Parameters, u16), ParametersError> {
-        let field_size_bits = P::size_in_bits() as u16;
+        Self::from_field_size_bits(P::size_in_bits() as u16)
+    }
+
+    /// Derive parameters based on the size of the scalar field of a curve,
+    /// as `from_curve` does.
+    pub fn from_field_size_bits(
+        field_size_bits: u16,
+    ) -> Result<(Parameters, u16), ParametersError> {
         let security_level = field_size_bits / 2;
         let parameters = Parameters {
             security_level,
@@ -62,6 +128,8 @@ impl Parameters {
             security_soundness: security_level - 2,
             field_size_bits,
             hash_to_prime_bits: 2 * security_level - 2,
+            overrides: SubProtocolOverrides::default(),
+            hash_to_prime_backend: None,
         };
 
         parameters.is_valid()?;
@@ -98,6 +166,8 @@ impl Parameters {
             security_soundness: security_soundness_zk,
             field_size_bits,
             hash_to_prime_bits: prime_bits,
+            overrides: SubProtocolOverrides::default(),
+            hash_to_prime_backend: None,
         };
 
         parameters.is_valid()?;
@@ -105,20 +175,73 @@ impl Parameters {
     }
 
     /// Check the parameters are valid according to section 4.5 of
-    /// the paper.
+    /// the paper, including the parameters of each sub-protocol with an
+    /// override.
     pub fn is_valid(&self) -> Result<(), ParametersError> {
-        let d = 1 + (self.security_zk + self.security_soundness + 2) / self.hash_to_prime_bits;
+        self.is_valid_pair(self.security_zk, self.security_soundness)?;
+        for sub_protocol in &[
+            SubProtocol::Root,
+            SubProtocol::Coprime,
+            SubProtocol::ModEq,
+            SubProtocol::HashToPrime,
+        ] {
+            if let Some(o) = self.overrides.get(*sub_protocol) {
+                self.is_valid_pair(o.security_zk, o.security_soundness)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn is_valid_pair(
+        &self,
+        security_zk: u16,
+        security_soundness: u16,
+    ) -> Result<(), ParametersError> {
+        let d = 1 + (security_zk + security_soundness + 2) / self.hash_to_prime_bits;
         if d * self.hash_to_prime_bits + 2 <= self.field_size_bits {
             Ok(())
         } else {
             Err(ParametersError::InvalidParameters)
         }
     }
+
+    /// Sets the security of one sub-protocol, failing if the resulting
+    /// parameters are invalid.
+    pub fn with_override(
+        mut self,
+        sub_protocol: SubProtocol,
+        security_override: SecurityOverride,
+    ) -> Result<Parameters, ParametersError> {
+        *self.overrides.get_mut(sub_protocol) = Some(security_override);
+        self.is_valid()?;
+        Ok(self)
+    }
+
+    /// Selects the backend of `DynHashToPrime`.
+    pub fn with_hash_to_prime_backend(mut self, backend: HashToPrimeBackend) -> Parameters {
+        self.hash_to_prime_backend = Some(backend);
+        self
+    }
+
+    /// The parameters used by the CRS of a sub-protocol, with its override
+    /// applied.
+    pub fn for_sub_protocol(&self, sub_protocol: SubProtocol) -> Parameters {
+        let mut parameters = Parameters {
+            overrides: SubProtocolOverrides::default(),
+            ..self.clone()
+        };
+        if let Some(o) = self.overrides.get(sub_protocol) {
+            parameters.security_zk = o.security_zk;
+            parameters.security_soundness = o.security_soundness;
+        }
+        parameters
+    }
 }
 
 #[cfg(test)]
 mod test {
-    use super::Parameters;
+    use super::{Parameters, SecurityOverride, SubProtocol};
 
     #[test]
     fn test_valid_for_128() {
@@ -126,6 +249,36 @@ mod test {
         params.is_valid().unwrap();
     }
 
+    #[test]
+    fn test_sub_protocol_overrides() {
+        let params = Parameters::from_security_level(128)
+            .unwrap()
+            .with_override(
+                SubProtocol::Root,
+                SecurityOverride {
+                    security_zk: 60,
+                    security_soundness: 190,
+                },
+            )
+            .unwrap();
+        let root = params.for_sub_protocol(SubProtocol::Root);
+        assert_eq!((root.security_zk, root.security_soundness), (60, 190));
+        root.is_valid().unwrap();
+        let modeq = params.for_sub_protocol(SubProtocol::ModEq);
+        assert_eq!(modeq.security_zk, params.security_zk);
+        assert_eq!(modeq.security_soundness, params.security_soundness);
+
+        assert!(params
+            .with_override(
+                SubProtocol::Coprime,
+                SecurityOverride {
+                    security_zk: 256,
+                    security_soundness: 256,
+                },
+            )
+            .is_err());
+    }
+
     #[cfg(all(test, feature = "arkworks"))]
     #[test]
     fn test_valid_for_some_fields() {
@@ -0,0 +1,113 @@
+//! The types needed to set up, prove and verify membership and nonmembership,
+//! under names that are kept stable when the modules they are defined in
+//! change. Applications should import from here rather than from the
+//! protocol modules:
+//!
+//! ```ignore
+//! use cpsnarks_set::prelude::*;
+//! ```
+pub use crate::{
+    channels::ChannelError,
+    commitments::{Commitment, CommitmentError},
+    parameters::{HashToPrimeBackend, Parameters, ParametersError},
+    protocols::{
+        boxed::{BoxedMembership, Codec, CodecError, MembershipProver, MembershipVerifier},
+        context::VerificationContext,
+        hash_to_prime::{HashToPrimeError, HashToPrimeProtocol},
+        membership::{
+            transcript::{
+                TranscriptProverChannel as MembershipProverChannel,
+                TranscriptVerifierChannel as MembershipVerifierChannel,
+            },
+            CPMemRSA, CPMemRSAPrm, Proof as MembershipProof, Protocol as MembershipProtocol,
+            Statement as MembershipStatement, Witness as MembershipWitness, CRS as MembershipCRS,
+        },
+        nonmembership::{
+            transcript::{
+                TranscriptProverChannel as NonMembershipProverChannel,
+                TranscriptVerifierChannel as NonMembershipVerifierChannel,
+            },
+            CPNonMemRSA, CPNonMemRSAPrm, Proof as NonMembershipProof,
+            Protocol as NonMembershipProtocol, Statement as NonMembershipStatement,
+            Witness as NonMembershipWitness, CRS as NonMembershipCRS,
+        },
+        CRSError, ProofError, SetupError, VerificationError,
+    },
+    set::{AccumulatorProvider, AccumulatorProviderError, SetAccumulator},
+    transcript::TranscriptChannelError,
+    utils::ConvertibleUnknownOrderGroup,
+};
+
+#[cfg(feature = "arkworks")]
+pub use crate::protocols::hash_to_prime::{
+    dynamic::{DynHashToPrime, DynParameters},
+    snark_hash::{HashToPrimeHashParameters, Protocol as SnarkHashProtocol},
+    snark_range::Protocol as SnarkRangeProtocol,
+};
+
+#[cfg(feature = "dalek")]
+pub use crate::protocols::hash_to_prime::bp::Protocol as BulletproofsProtocol;
+
+#[cfg(all(test, feature = "arkworks"))]
+mod test {
+    use super::*;
+    use accumulator::group::Rsa2048;
+    use ark_bls12_381::{Bls12_381, G1Projective};
+    use merlin::Transcript;
+    use rand::thread_rng;
+    use rug::rand::RandState;
+    use rug::Integer;
+    use std::cell::RefCell;
+
+    #[test]
+    fn test_prelude() {
+        let params = Parameters::from_security_level(128).unwrap();
+        let mut rng1 = RandState::new();
+        rng1.seed(&Integer::from(13));
+        let mut rng2 = thread_rng();
+
+        let crs =
+            MembershipProtocol::<Rsa2048, G1Projective, SnarkRangeProtocol<Bls12_381>>::setup(
+                &params, &mut rng1, &mut rng2,
+            )
+            .unwrap()
+            .crs;
+        let protocol = MembershipProtocol::from_crs(&crs);
+
+        let value = Integer::from(Integer::u_pow_u(2, params.hash_to_prime_bits as u32))
+            - &Integer::from(245);
+        let randomness = Integer::from(5);
+        let set = SetAccumulator::<Rsa2048>::from_elements(&[value.clone()]);
+        let statement = MembershipStatement {
+            c_p: set.value(),
+            c_e_q: crs
+                .crs_modeq
+                .pedersen_commitment_parameters
+                .commit(&value, &randomness)
+                .unwrap(),
+        };
+        let witness = MembershipWitness {
+            w: set.membership_witness(&value).unwrap(),
+            e: value,
+            r_q: randomness,
+        };
+
+        let proof_transcript = RefCell::new(Transcript::new(b"membership"));
+        let mut verifier_channel = MembershipVerifierChannel::new(&crs, &proof_transcript);
+        protocol
+            .prove(
+                &mut verifier_channel,
+                &mut rng1,
+                &mut rng2,
+                &statement,
+                &witness,
+            )
+            .unwrap();
+        let proof: MembershipProof<_, _, _> = verifier_channel.proof().unwrap();
+
+        let verification_transcript = RefCell::new(Transcript::new(b"membership"));
+        let mut prover_channel =
+            MembershipProverChannel::new(&crs, &verification_transcript, &proof);
+        protocol.verify(&mut prover_channel, &statement).unwrap();
+    }
+}
@@ -0,0 +1,256 @@
+//! Statements where the verifier only holds a digest of the accumulator.
+//!
+//! Systems storing only a hash of the accumulator value `c_p`, e.g. on a
+//! blockchain, can't build a membership or nonmembership statement directly.
+//! The prover sends an `AccumulatorOpening` along with the proof, and the
+//! verifier checks it against the stored digest before running the protocols
+//! on the opened value.
+//!
+//! The digest is a binding commitment to `c_p`: the Blake2s-256 hash of a
+//! domain separator, a 32-byte nonce and `G::elem_to_bytes(c_p)`. The nonce
+//! can be fixed, e.g. zero, when hiding `c_p` isn't needed.
+use crate::{
+    commitments::{pedersen::PedersenCommitment, Commitment},
+    protocols::{
+        coprime::channel::CoprimeProverChannel,
+        hash_to_prime::{channel::HashToPrimeProverChannel, HashToPrimeProtocol},
+        membership::{
+            channel::MembershipProverChannel, Protocol as MembershipProtocol,
+            Statement as MembershipStatement,
+        },
+        modeq::channel::ModEqProverChannel,
+        nonmembership::{
+            channel::NonMembershipProverChannel, Protocol as NonMembershipProtocol,
+            Statement as NonMembershipStatement,
+        },
+        root::channel::RootProverChannel,
+        VerificationError,
+    },
+    utils::{curve::CurvePointProjective, ConvertibleUnknownOrderGroup},
+};
+use blake2::{Blake2s, Digest};
+
+pub type AccumulatorDigest = [u8; 32];
+
+/// Commits to the accumulator value `c_p` with `nonce`.
+pub fn commit_accumulator<G: ConvertibleUnknownOrderGroup>(
+    c_p: &G::Elem,
+    nonce: &[u8; 32],
+) -> AccumulatorDigest {
+    let mut hasher = Blake2s::default();
+    hasher.update(b"cpsnarks-set accumulator");
+    hasher.update(nonce);
+    hasher.update(&G::elem_to_bytes(c_p));
+    let mut digest = [0u8; 32];
+    digest.copy_from_slice(&hasher.finalize());
+    digest
+}
+
+pub struct AccumulatorOpening<G: ConvertibleUnknownOrderGroup> {
+    pub c_p: G::Elem,
+    pub nonce: [u8; 32],
+}
+
+impl<G: ConvertibleUnknownOrderGroup> Clone for AccumulatorOpening<G> {
+    fn clone(&self) -> Self {
+        Self {
+            c_p: self.c_p.clone(),
+            nonce: self.nonce,
+        }
+    }
+}
+
+impl<G: ConvertibleUnknownOrderGroup> AccumulatorOpening<G> {
+    pub fn digest(&self) -> AccumulatorDigest {
+        commit_accumulator::<G>(&self.c_p, &self.nonce)
+    }
+
+    /// Returns the accumulator value if the opening matches `digest`.
+    pub fn open(&self, digest: &AccumulatorDigest) -> Result<&G::Elem, VerificationError> {
+        if self.digest() != *digest {
+            return Err(VerificationError::WrongAccumulatorOpening);
+        }
+
+        Ok(&self.c_p)
+    }
+}
+
+/// A membership or nonmembership statement with the accumulator value
+/// replaced by its digest.
+pub struct DigestStatement<P: CurvePointProjective> {
+    pub c_p_digest: AccumulatorDigest,
+    pub c_e_q: <PedersenCommitment<P> as Commitment>::Instance,
+}
+
+impl<P: CurvePointProjective> Clone for DigestStatement<P> {
+    fn clone(&self) -> Self {
+        Self {
+            c_p_digest: self.c_p_digest,
+            c_e_q: self.c_e_q.clone(),
+        }
+    }
+}
+
+impl<P: CurvePointProjective> DigestStatement<P> {
+    pub fn open_membership<G: ConvertibleUnknownOrderGroup>(
+        &self,
+        opening: &AccumulatorOpening<G>,
+    ) -> Result<MembershipStatement<G, P>, VerificationError> {
+        Ok(MembershipStatement {
+            c_p: opening.open(&self.c_p_digest)?.clone(),
+            c_e_q: self.c_e_q.clone(),
+        })
+    }
+
+    pub fn open_nonmembership<G: ConvertibleUnknownOrderGroup>(
+        &self,
+        opening: &AccumulatorOpening<G>,
+    ) -> Result<NonMembershipStatement<G, P>, VerificationError> {
+        Ok(NonMembershipStatement {
+            c_p: opening.open(&self.c_p_digest)?.clone(),
+            c_e_q: self.c_e_q.clone(),
+        })
+    }
+}
+
+impl<G: ConvertibleUnknownOrderGroup, P: CurvePointProjective, HP: HashToPrimeProtocol<P>>
+    MembershipProtocol<G, P, HP>
+{
+    /// Verifies a proof against a statement holding only the digest of the
+    /// accumulator, checking `opening` first.
+    pub fn verify_with_digest<
+        C: MembershipProverChannel<G>
+            + RootProverChannel<G>
+            + ModEqProverChannel<G, P>
+            + HashToPrimeProverChannel<P, HP>,
+    >(
+        &self,
+        prover_channel: &mut C,
+        statement: &DigestStatement<P>,
+        opening: &AccumulatorOpening<G>,
+    ) -> Result<(), VerificationError> {
+        self.verify(prover_channel, &statement.open_membership(opening)?)
+    }
+}
+
+impl<G: ConvertibleUnknownOrderGroup, P: CurvePointProjective, HP: HashToPrimeProtocol<P>>
+    NonMembershipProtocol<G, P, HP>
+{
+    /// Verifies a proof against a statement holding only the digest of the
+    /// accumulator, checking `opening` first.
+    pub fn verify_with_digest<
+        C: NonMembershipProverChannel<G>
+            + CoprimeProverChannel<G>
+            + ModEqProverChannel<G, P>
+            + HashToPrimeProverChannel<P, HP>,
+    >(
+        &self,
+        prover_channel: &mut C,
+        statement: &DigestStatement<P>,
+        opening: &AccumulatorOpening<G>,
+    ) -> Result<(), VerificationError> {
+        self.verify(prover_channel, &statement.open_nonmembership(opening)?)
+    }
+}
+
+#[cfg(all(test, feature = "arkworks"))]
+mod test {
+    use super::{AccumulatorOpening, DigestStatement};
+    use crate::{
+        commitments::Commitment,
+        parameters::Parameters,
+        protocols::{
+            hash_to_prime::snark_range::Protocol as HPProtocol,
+            membership::{
+                transcript::{TranscriptProverChannel, TranscriptVerifierChannel},
+                Protocol, Statement, Witness,
+            },
+            VerificationError,
+        },
+    };
+    use accumulator::{group::Rsa2048, AccumulatorWithoutHashToPrime};
+    use ark_bls12_381::{Bls12_381, G1Projective};
+    use merlin::Transcript;
+    use rand::thread_rng;
+    use rug::rand::RandState;
+    use rug::Integer;
+    use std::cell::RefCell;
+
+    #[test]
+    fn test_verify_with_digest() {
+        let params = Parameters::from_security_level(128).unwrap();
+        let mut rng1 = RandState::new();
+        rng1.seed(&Integer::from(13));
+        let mut rng2 = thread_rng();
+
+        let crs = Protocol::<Rsa2048, G1Projective, HPProtocol<Bls12_381>>::setup(
+            &params, &mut rng1, &mut rng2,
+        )
+        .unwrap()
+        .crs;
+        let protocol = Protocol::<Rsa2048, G1Projective, HPProtocol<Bls12_381>>::from_crs(&crs);
+
+        let value = Integer::from(Integer::u_pow_u(2, params.hash_to_prime_bits as u32))
+            - &Integer::from(245);
+        let randomness = Integer::from(5);
+        let accum =
+            accumulator::Accumulator::<Rsa2048, Integer, AccumulatorWithoutHashToPrime>::empty()
+                .add_with_proof(&[value.clone()]);
+        let statement = Statement {
+            c_p: accum.0.value.clone(),
+            c_e_q: crs
+                .crs_modeq
+                .pedersen_commitment_parameters
+                .commit(&value, &randomness)
+                .unwrap(),
+        };
+        let proof_transcript = RefCell::new(Transcript::new(b"membership"));
+        let mut verifier_channel = TranscriptVerifierChannel::new(&crs, &proof_transcript);
+        protocol
+            .prove(
+                &mut verifier_channel,
+                &mut rng1,
+                &mut rng2,
+                &statement,
+                &Witness {
+                    e: value,
+                    r_q: randomness,
+                    w: accum.1.witness.0.value,
+                },
+            )
+            .unwrap();
+        let proof = verifier_channel.proof().unwrap();
+
+        let opening = AccumulatorOpening::<Rsa2048> {
+            c_p: statement.c_p.clone(),
+            nonce: [7u8; 32],
+        };
+        let digest_statement = DigestStatement {
+            c_p_digest: opening.digest(),
+            c_e_q: statement.c_e_q.clone(),
+        };
+        let verify = |opening: &AccumulatorOpening<Rsa2048>| {
+            let verification_transcript = RefCell::new(Transcript::new(b"membership"));
+            let mut prover_channel =
+                TranscriptProverChannel::new(&crs, &verification_transcript, &proof);
+            protocol.verify_with_digest(&mut prover_channel, &digest_statement, opening)
+        };
+        verify(&opening).unwrap();
+
+        let mut wrong_nonce = opening.clone();
+        wrong_nonce.nonce = [0u8; 32];
+        assert!(matches!(
+            verify(&wrong_nonce),
+            Err(VerificationError::WrongAccumulatorOpening)
+        ));
+        let other_acc =
+            accumulator::Accumulator::<Rsa2048, Integer, AccumulatorWithoutHashToPrime>::empty()
+                .value;
+        let mut wrong_acc = opening;
+        wrong_acc.c_p = other_acc;
+        assert!(matches!(
+            verify(&wrong_acc),
+            Err(VerificationError::WrongAccumulatorOpening)
+        ));
+    }
+}
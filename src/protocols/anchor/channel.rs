@@ -0,0 +1,27 @@
+use crate::{
+    channels::ChannelError,
+    protocols::anchor::{Message1, Message2, Statement},
+    utils::{curve::CurvePointProjective, ConvertibleUnknownOrderGroup},
+};
+use rug::Integer;
+
+pub trait AnchorVerifierChannel<P: CurvePointProjective> {
+    /// Sends the first message, after the statement it is about.
+    fn send_message1<G: ConvertibleUnknownOrderGroup>(
+        &mut self,
+        statement: &Statement<G, P>,
+        message: &Message1<P>,
+    ) -> Result<(), ChannelError>;
+    fn send_message2(&mut self, message: &Message2<P>) -> Result<(), ChannelError>;
+    fn receive_challenge(&mut self) -> Result<Integer, ChannelError>;
+}
+
+pub trait AnchorProverChannel<P: CurvePointProjective> {
+    /// Receives the first message, about `statement`.
+    fn receive_message1<G: ConvertibleUnknownOrderGroup>(
+        &mut self,
+        statement: &Statement<G, P>,
+    ) -> Result<Message1<P>, ChannelError>;
+    fn receive_message2(&mut self) -> Result<Message2<P>, ChannelError>;
+    fn generate_and_send_challenge(&mut self) -> Result<Integer, ChannelError>;
+}
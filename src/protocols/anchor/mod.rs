@@ -0,0 +1,292 @@
+//! Implements an optional sub-protocol tying the accumulator value `c_p` in
+//! the RSA group to a Pedersen commitment on the curve, for systems which
+//! anchor the accumulator as a field element, e.g. on a blockchain.
+//!
+//! The field element is the hash of `c_p` given by `accumulator_to_scalar`,
+//! and the commitment `c_p_q = g^hash * h^r_q` opens to it. Since `c_p` is
+//! public, the protocol proves knowledge of `r_q` such that
+//! `c_p_q * g^-hash = h^r_q`. Running it on the transcript of a membership or
+//! nonmembership proof binds the anchor into that proof.
+use crate::{
+    commitments::{pedersen::PedersenCommitment, Commitment},
+    parameters::Parameters,
+    protocols::{
+        modeq::CRSModEq,
+        statement::{hash_curve_point, StatementDigest, StatementHasher},
+        ProofError, VerificationError,
+    },
+    utils::{
+        bigint_to_integer, bytes_to_integer,
+        curve::{CurveError, CurvePointProjective, Field},
+        integer_to_bigint_mod_q, ConvertibleUnknownOrderGroup,
+    },
+};
+use blake2::{Blake2s, Digest};
+use channel::{AnchorProverChannel, AnchorVerifierChannel};
+use rand::{CryptoRng, RngCore};
+use rug::Integer;
+use std::hash::{Hash, Hasher};
+
+pub mod channel;
+pub mod transcript;
+
+#[derive(Clone)]
+pub struct CRSAnchor<P: CurvePointProjective> {
+    pub parameters: Parameters,
+    pub pedersen_commitment_parameters: PedersenCommitment<P>, // g, h
+}
+
+impl<G: ConvertibleUnknownOrderGroup, P: CurvePointProjective> From<&CRSModEq<G, P>>
+    for CRSAnchor<P>
+{
+    fn from(crs: &CRSModEq<G, P>) -> CRSAnchor<P> {
+        CRSAnchor {
+            parameters: crs.parameters.clone(),
+            pedersen_commitment_parameters: crs.pedersen_commitment_parameters.clone(),
+        }
+    }
+}
+
+pub struct Statement<G: ConvertibleUnknownOrderGroup, P: CurvePointProjective> {
+    pub c_p: G::Elem,
+    pub c_p_q: <PedersenCommitment<P> as Commitment>::Instance,
+}
+
+impl<G: ConvertibleUnknownOrderGroup, P: CurvePointProjective> Clone for Statement<G, P> {
+    fn clone(&self) -> Self {
+        Self {
+            c_p: self.c_p.clone(),
+            c_p_q: self.c_p_q.clone(),
+        }
+    }
+}
+
+impl<G: ConvertibleUnknownOrderGroup, P: CurvePointProjective> PartialEq for Statement<G, P> {
+    fn eq(&self, other: &Self) -> bool {
+        self.c_p == other.c_p && self.c_p_q == other.c_p_q
+    }
+}
+
+impl<G: ConvertibleUnknownOrderGroup, P: CurvePointProjective> Eq for Statement<G, P> {}
+
+impl<G: ConvertibleUnknownOrderGroup, P: CurvePointProjective> Hash for Statement<G, P> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.c_p.hash(state);
+        hash_curve_point(&self.c_p_q, state);
+    }
+}
+
+impl<G: ConvertibleUnknownOrderGroup, P: CurvePointProjective> Statement<G, P> {
+    /// Stable identifier of the statement, see `protocols::statement`.
+    pub fn digest(&self) -> Result<StatementDigest, CurveError> {
+        let mut hasher = StatementHasher::new(b"anchor");
+        hasher.append_group_element::<G>(b"c_p", &self.c_p);
+        hasher.append_curve_point(b"c_p_q", &self.c_p_q)?;
+        Ok(hasher.finish())
+    }
+}
+
+pub struct Witness {
+    pub r_q: Integer,
+}
+
+#[derive(Clone)]
+pub struct Message1<P: CurvePointProjective> {
+    pub alpha: <PedersenCommitment<P> as Commitment>::Instance,
+}
+
+#[derive(Clone)]
+pub struct Message2<P: CurvePointProjective> {
+    pub s_r_q: P::ScalarField,
+}
+
+#[derive(Clone)]
+pub struct Proof<P: CurvePointProjective> {
+    pub message1: Message1<P>,
+    pub message2: Message2<P>,
+}
+
+/// Hashes the accumulator value to the scalar field: Blake2s of its byte
+/// encoding, reduced modulo the field order.
+pub fn accumulator_to_scalar<G: ConvertibleUnknownOrderGroup, P: CurvePointProjective>(
+    c_p: &G::Elem,
+) -> Result<P::ScalarField, Integer> {
+    let mut hasher = Blake2s::default();
+    hasher.update(&G::elem_to_bytes(c_p));
+    integer_to_bigint_mod_q::<P>(&bytes_to_integer(&hasher.finalize()))
+}
+
+pub struct Protocol<P: CurvePointProjective> {
+    pub crs: CRSAnchor<P>,
+}
+
+impl<P: CurvePointProjective> Protocol<P> {
+    pub fn from_crs(crs: &CRSAnchor<P>) -> Protocol<P> {
+        Protocol { crs: crs.clone() }
+    }
+
+    /// Commits to the hash of `c_p`, returning the statement and its witness.
+    pub fn commit<G: ConvertibleUnknownOrderGroup, R: RngCore + CryptoRng>(
+        &self,
+        rng: &mut R,
+        c_p: &G::Elem,
+    ) -> Result<(Statement<G, P>, Witness), ProofError> {
+        let r_q = P::ScalarField::rand(rng);
+        let pedersen = &self.crs.pedersen_commitment_parameters;
+        let c_p_q = pedersen
+            .g
+            .mul(&accumulator_to_scalar::<G, P>(c_p)?)
+            .add(&pedersen.h.mul(&r_q));
+        Ok((
+            Statement {
+                c_p: c_p.clone(),
+                c_p_q,
+            },
+            Witness {
+                r_q: bigint_to_integer::<P>(&r_q),
+            },
+        ))
+    }
+
+    pub fn prove<
+        G: ConvertibleUnknownOrderGroup,
+        R: RngCore + CryptoRng,
+        C: AnchorVerifierChannel<P>,
+    >(
+        &self,
+        verifier_channel: &mut C,
+        rng: &mut R,
+        statement: &Statement<G, P>,
+        witness: &Witness,
+    ) -> Result<(), ProofError> {
+        let r_r_q = P::ScalarField::rand(rng);
+        let alpha = self.crs.pedersen_commitment_parameters.h.mul(&r_r_q);
+        let message1 = Message1::<P> { alpha };
+        verifier_channel.send_message1(statement, &message1)?;
+
+        let c = verifier_channel.receive_challenge()?;
+        let r_q = integer_to_bigint_mod_q::<P>(&witness.r_q)?;
+        let c_big = integer_to_bigint_mod_q::<P>(&c)?;
+        let s_r_q = r_r_q.sub(&r_q.mul(&c_big));
+        verifier_channel.send_message2(&Message2::<P> { s_r_q })?;
+
+        Ok(())
+    }
+
+    pub fn verify<G: ConvertibleUnknownOrderGroup, C: AnchorProverChannel<P>>(
+        &self,
+        prover_channel: &mut C,
+        statement: &Statement<G, P>,
+    ) -> Result<(), VerificationError> {
+        let message1 = prover_channel.receive_message1(statement)?;
+        let c = prover_channel.generate_and_send_challenge()?;
+        let message2 = prover_channel.receive_message2()?;
+
+        let pedersen = &self.crs.pedersen_commitment_parameters;
+        let hash = accumulator_to_scalar::<G, P>(&statement.c_p)?;
+        // h^r_q, if the commitment opens to the hash of c_p
+        let blinding = statement.c_p_q.add(&pedersen.g.mul(&hash.neg()));
+        let c_big = integer_to_bigint_mod_q::<P>(&c)?;
+        let expected_alpha = pedersen.h.mul(&message2.s_r_q).add(&blinding.mul(&c_big));
+
+        if expected_alpha == message1.alpha {
+            Ok(())
+        } else {
+            Err(VerificationError::VerificationFailed)
+        }
+    }
+}
+
+#[cfg(all(test, feature = "arkworks"))]
+mod test {
+    use super::{
+        accumulator_to_scalar,
+        transcript::{TranscriptProverChannel, TranscriptVerifierChannel},
+        CRSAnchor, Protocol, Statement,
+    };
+    use crate::{
+        parameters::Parameters,
+        protocols::{
+            hash_to_prime::snark_range::Protocol as HPProtocol,
+            membership::Protocol as MembershipProtocol,
+        },
+        utils::{curve::CurvePointProjective, integer_to_bigint_mod_q},
+    };
+    use accumulator::{group::Rsa2048, AccumulatorWithoutHashToPrime};
+    use ark_bls12_381::{Bls12_381, G1Projective};
+    use merlin::Transcript;
+    use rand::thread_rng;
+    use rug::rand::RandState;
+    use rug::Integer;
+    use std::cell::RefCell;
+
+    #[test]
+    fn test_proof() {
+        let params = Parameters::from_security_level(128).unwrap();
+        let mut rng1 = RandState::new();
+        rng1.seed(&Integer::from(13));
+        let mut rng2 = thread_rng();
+
+        let crs = MembershipProtocol::<Rsa2048, G1Projective, HPProtocol<Bls12_381>>::setup(
+            &params, &mut rng1, &mut rng2,
+        )
+        .unwrap()
+        .crs;
+        let crs = CRSAnchor::from(&crs.crs_modeq);
+        let protocol = Protocol::<G1Projective>::from_crs(&crs);
+
+        let acc =
+            accumulator::Accumulator::<Rsa2048, Integer, AccumulatorWithoutHashToPrime>::empty()
+                .add(&[Integer::from(553_525_575_239_331_913u64)])
+                .value;
+        let (statement, witness) = protocol.commit::<Rsa2048, _>(&mut rng2, &acc).unwrap();
+
+        let proof_transcript = RefCell::new(Transcript::new(b"anchor"));
+        let mut verifier_channel = TranscriptVerifierChannel::new(&crs, &proof_transcript);
+        protocol
+            .prove(&mut verifier_channel, &mut rng2, &statement, &witness)
+            .unwrap();
+        let proof = verifier_channel.proof().unwrap();
+
+        let verification_transcript = RefCell::new(Transcript::new(b"anchor"));
+        let mut prover_channel =
+            TranscriptProverChannel::new(&crs, &verification_transcript, &proof);
+        protocol.verify(&mut prover_channel, &statement).unwrap();
+
+        // a commitment to another accumulator value is rejected
+        let other_acc =
+            accumulator::Accumulator::<Rsa2048, Integer, AccumulatorWithoutHashToPrime>::empty()
+                .value;
+        let other_statement = Statement::<Rsa2048, G1Projective> {
+            c_p: other_acc.clone(),
+            c_p_q: statement.c_p_q.clone(),
+        };
+        let verification_transcript = RefCell::new(Transcript::new(b"anchor"));
+        let mut prover_channel =
+            TranscriptProverChannel::new(&crs, &verification_transcript, &proof);
+        assert!(protocol
+            .verify(&mut prover_channel, &other_statement)
+            .is_err());
+
+        // and so is the proof against a valid anchor of another accumulator
+        // value with the same blinding, which the statement binds it to
+        let pedersen = &crs.pedersen_commitment_parameters;
+        let other_statement = Statement::<Rsa2048, G1Projective> {
+            c_p_q: pedersen
+                .g
+                .mul(&accumulator_to_scalar::<Rsa2048, G1Projective>(&other_acc).unwrap())
+                .add(
+                    &pedersen
+                        .h
+                        .mul(&integer_to_bigint_mod_q::<G1Projective>(&witness.r_q).unwrap()),
+                ),
+            c_p: other_acc,
+        };
+        let verification_transcript = RefCell::new(Transcript::new(b"anchor"));
+        let mut prover_channel =
+            TranscriptProverChannel::new(&crs, &verification_transcript, &proof);
+        assert!(protocol
+            .verify(&mut prover_channel, &other_statement)
+            .is_err());
+    }
+}
@@ -0,0 +1,157 @@
+use crate::{
+    channels::{ChannelError, MessageOrder},
+    protocols::anchor::{
+        channel::{AnchorProverChannel, AnchorVerifierChannel},
+        CRSAnchor, Message1, Message2, Proof, Statement,
+    },
+    transcript::{
+        versioned_domain_sep, TranscriptChannelError, TranscriptProtocolChallenge,
+        TranscriptProtocolCurve,
+    },
+    utils::{
+        curve::{CurveError, CurvePointProjective},
+        ConvertibleUnknownOrderGroup,
+    },
+};
+use merlin::Transcript;
+use rug::Integer;
+use std::cell::RefCell;
+
+pub trait TranscriptProtocolAnchor<P: CurvePointProjective>:
+    TranscriptProtocolCurve<P> + TranscriptProtocolChallenge
+{
+    fn anchor_domain_sep(&mut self);
+    fn append_anchor_statement<G: ConvertibleUnknownOrderGroup>(
+        &mut self,
+        statement: &Statement<G, P>,
+    ) -> Result<(), CurveError>;
+}
+
+impl<P: CurvePointProjective> TranscriptProtocolAnchor<P> for Transcript {
+    fn anchor_domain_sep(&mut self) {
+        self.append_message(b"dom-sep", &versioned_domain_sep(b"anchor"));
+    }
+
+    fn append_anchor_statement<G: ConvertibleUnknownOrderGroup>(
+        &mut self,
+        statement: &Statement<G, P>,
+    ) -> Result<(), CurveError> {
+        self.append_message(b"c_p", &G::elem_to_bytes(&statement.c_p));
+        self.append_curve_point(b"c_p_q", &statement.c_p_q)
+    }
+}
+
+pub struct TranscriptVerifierChannel<'a, P: CurvePointProjective, T: TranscriptProtocolAnchor<P>> {
+    crs: CRSAnchor<P>,
+    transcript: &'a RefCell<T>,
+    order: MessageOrder,
+    message1: Option<Message1<P>>,
+    message2: Option<Message2<P>>,
+}
+
+impl<'a, P: CurvePointProjective, T: TranscriptProtocolAnchor<P>>
+    TranscriptVerifierChannel<'a, P, T>
+{
+    pub fn new(
+        crs: &CRSAnchor<P>,
+        transcript: &'a RefCell<T>,
+    ) -> TranscriptVerifierChannel<'a, P, T> {
+        TranscriptVerifierChannel {
+            crs: crs.clone(),
+            transcript,
+            order: MessageOrder::new(),
+            message1: None,
+            message2: None,
+        }
+    }
+
+    pub fn proof(&self) -> Result<Proof<P>, TranscriptChannelError> {
+        if self.message1.is_some() && self.message2.is_some() {
+            Ok(Proof {
+                message1: self.message1.as_ref().unwrap().clone(),
+                message2: self.message2.as_ref().unwrap().clone(),
+            })
+        } else {
+            Err(TranscriptChannelError::Incomplete)
+        }
+    }
+}
+
+impl<'a, P: CurvePointProjective, T: TranscriptProtocolAnchor<P>> AnchorVerifierChannel<P>
+    for TranscriptVerifierChannel<'a, P, T>
+{
+    fn send_message1<G: ConvertibleUnknownOrderGroup>(
+        &mut self,
+        statement: &Statement<G, P>,
+        message: &Message1<P>,
+    ) -> Result<(), ChannelError> {
+        self.order.step(0)?;
+        let mut transcript = self.transcript.try_borrow_mut()?;
+        transcript.anchor_domain_sep();
+        transcript.append_anchor_statement(statement)?;
+        transcript.append_curve_point(b"alpha", &message.alpha)?;
+        self.message1 = Some(message.clone());
+        Ok(())
+    }
+    fn send_message2(&mut self, message: &Message2<P>) -> Result<(), ChannelError> {
+        self.order.step(2)?;
+        self.message2 = Some(message.clone());
+        Ok(())
+    }
+    fn receive_challenge(&mut self) -> Result<Integer, ChannelError> {
+        self.order.step(1)?;
+        let mut transcript = self.transcript.try_borrow_mut()?;
+        transcript.anchor_domain_sep();
+        Ok(transcript.challenge_scalar(b"c", self.crs.parameters.security_soundness))
+    }
+}
+
+pub struct TranscriptProverChannel<'a, P: CurvePointProjective, T: TranscriptProtocolAnchor<P>> {
+    crs: CRSAnchor<P>,
+    transcript: &'a RefCell<T>,
+    order: MessageOrder,
+    proof: Proof<P>,
+}
+
+impl<'a, P: CurvePointProjective, T: TranscriptProtocolAnchor<P>>
+    TranscriptProverChannel<'a, P, T>
+{
+    pub fn new(
+        crs: &CRSAnchor<P>,
+        transcript: &'a RefCell<T>,
+        proof: &Proof<P>,
+    ) -> TranscriptProverChannel<'a, P, T> {
+        TranscriptProverChannel {
+            crs: crs.clone(),
+            transcript,
+            order: MessageOrder::new(),
+            proof: proof.clone(),
+        }
+    }
+}
+
+impl<'a, P: CurvePointProjective, T: TranscriptProtocolAnchor<P>> AnchorProverChannel<P>
+    for TranscriptProverChannel<'a, P, T>
+{
+    fn receive_message1<G: ConvertibleUnknownOrderGroup>(
+        &mut self,
+        statement: &Statement<G, P>,
+    ) -> Result<Message1<P>, ChannelError> {
+        self.order.step(0)?;
+        let mut transcript = self.transcript.try_borrow_mut()?;
+        transcript.anchor_domain_sep();
+        transcript.append_anchor_statement(statement)?;
+        transcript.append_curve_point(b"alpha", &self.proof.message1.alpha)?;
+        Ok(self.proof.message1.clone())
+    }
+    fn receive_message2(&mut self) -> Result<Message2<P>, ChannelError> {
+        self.order.step(2)?;
+        Ok(self.proof.message2.clone())
+    }
+    fn generate_and_send_challenge(&mut self) -> Result<Integer, ChannelError> {
+        self.order.step(1)?;
+        let mut transcript = self.transcript.try_borrow_mut()?;
+        transcript.anchor_domain_sep();
+        Ok(transcript.challenge_scalar(b"c", self.crs.parameters.security_soundness))
+    }
+}
@@ -0,0 +1,692 @@
+//! A channel decorator recording every message and challenge of a proof
+//! session, for environments that have to retain proof logs.
+//!
+//! `LoggingChannel` wraps any prover or verifier channel of the membership
+//! and nonmembership protocols and their sub-protocols, interactive or over a
+//! transcript, and passes an `AuditEntry` to its `AuditSink` for each message
+//! that went through the channel. Messages are recorded with the same
+//! encodings as in the transcript: group elements with `elem_to_bytes`, curve
+//! points in affine form and integers with `integer_to_signed_bytes`.
+use crate::{
+    channels::ChannelError,
+    commitments::{integer::IntegerCommitment, Commitment},
+    protocols::{
+        coprime::{
+            channel::{CoprimeProverChannel, CoprimeVerifierChannel},
+            Message1 as CoprimeMessage1, Message2 as CoprimeMessage2, Message3 as CoprimeMessage3,
+        },
+        hash_to_prime::{
+            channel::{HashToPrimeProverChannel, HashToPrimeVerifierChannel},
+            HashToPrimeProtocol,
+        },
+        membership::channel::{MembershipProverChannel, MembershipVerifierChannel},
+        modeq::{
+            channel::{ModEqProverChannel, ModEqVerifierChannel},
+            Message1 as ModEqMessage1, Message2 as ModEqMessage2,
+        },
+        nonmembership::channel::{NonMembershipProverChannel, NonMembershipVerifierChannel},
+        registry::CrsDigest,
+        root::{
+            channel::{RootProverChannel, RootVerifierChannel},
+            Message1 as RootMessage1, Message2 as RootMessage2, Message3 as RootMessage3,
+        },
+    },
+    utils::{
+        bigint_to_bytes, curve::CurvePointProjective, integer_to_signed_bytes,
+        ConvertibleUnknownOrderGroup,
+    },
+};
+use rug::Integer;
+use std::time::SystemTime;
+
+/// Whether a message is sent by the prover or is a challenge of the verifier.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AuditDirection {
+    ProverMessage,
+    Challenge,
+}
+
+/// A message that went through a `LoggingChannel`, with its encoded values.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AuditEntry {
+    pub timestamp: SystemTime,
+    pub sub_protocol: &'static str,
+    pub message: &'static str,
+    pub direction: AuditDirection,
+    pub values: Vec<(&'static str, Vec<u8>)>,
+}
+
+/// Where a `LoggingChannel` records its entries, e.g. an append-only file or
+/// a log service.
+pub trait AuditSink {
+    fn record(&mut self, entry: AuditEntry) -> Result<(), ChannelError>;
+}
+
+impl AuditSink for Vec<AuditEntry> {
+    fn record(&mut self, entry: AuditEntry) -> Result<(), ChannelError> {
+        self.push(entry);
+        Ok(())
+    }
+}
+
+impl<S: AuditSink + ?Sized> AuditSink for &mut S {
+    fn record(&mut self, entry: AuditEntry) -> Result<(), ChannelError> {
+        (**self).record(entry)
+    }
+}
+
+/// Forwards every message to `channel` and records the ones that were sent or
+/// received successfully to `sink`.
+pub struct LoggingChannel<C, S: AuditSink> {
+    pub channel: C,
+    pub sink: S,
+}
+
+impl<C, S: AuditSink> LoggingChannel<C, S> {
+    pub fn new(channel: C, sink: S) -> LoggingChannel<C, S> {
+        LoggingChannel { channel, sink }
+    }
+
+    pub fn into_inner(self) -> (C, S) {
+        (self.channel, self.sink)
+    }
+
+    fn log(
+        &mut self,
+        sub_protocol: &'static str,
+        message: &'static str,
+        direction: AuditDirection,
+        values: Vec<(&'static str, Vec<u8>)>,
+    ) -> Result<(), ChannelError> {
+        self.sink.record(AuditEntry {
+            timestamp: SystemTime::now(),
+            sub_protocol,
+            message,
+            direction,
+            values,
+        })
+    }
+
+    fn log_challenge(
+        &mut self,
+        sub_protocol: &'static str,
+        challenge: &Integer,
+    ) -> Result<(), ChannelError> {
+        self.log(
+            sub_protocol,
+            "challenge",
+            AuditDirection::Challenge,
+            vec![integer("c", challenge)],
+        )
+    }
+}
+
+fn elem<G: ConvertibleUnknownOrderGroup>(
+    label: &'static str,
+    e: &G::Elem,
+) -> (&'static str, Vec<u8>) {
+    (label, G::elem_to_bytes(e))
+}
+
+fn integer(label: &'static str, i: &Integer) -> (&'static str, Vec<u8>) {
+    (label, integer_to_signed_bytes(i))
+}
+
+fn point<P: CurvePointProjective>(
+    label: &'static str,
+    p: &P,
+) -> Result<(&'static str, Vec<u8>), ChannelError> {
+    Ok((label, p.to_affine_bytes()?))
+}
+
+fn root_message1<G: ConvertibleUnknownOrderGroup>(
+    message: &RootMessage1<G>,
+) -> Vec<(&'static str, Vec<u8>)> {
+    vec![
+        elem::<G>("c_w", &message.c_w),
+        elem::<G>("c_r", &message.c_r),
+    ]
+}
+
+fn root_message2<G: ConvertibleUnknownOrderGroup>(
+    message: &RootMessage2<G>,
+) -> Vec<(&'static str, Vec<u8>)> {
+    vec![
+        elem::<G>("alpha1", &message.alpha1),
+        elem::<G>("alpha2", &message.alpha2),
+        elem::<G>("alpha3", &message.alpha3),
+        elem::<G>("alpha4", &message.alpha4),
+    ]
+}
+
+fn root_message3(message: &RootMessage3) -> Vec<(&'static str, Vec<u8>)> {
+    vec![
+        integer("s_e", &message.s_e),
+        integer("s_r", &message.s_r),
+        integer("s_r_2", &message.s_r_2),
+        integer("s_r_3", &message.s_r_3),
+        integer("s_beta", &message.s_beta),
+        integer("s_delta", &message.s_delta),
+    ]
+}
+
+fn coprime_message1<G: ConvertibleUnknownOrderGroup>(
+    message: &CoprimeMessage1<G>,
+) -> Vec<(&'static str, Vec<u8>)> {
+    vec![
+        elem::<G>("c_a", &message.c_a),
+        elem::<G>("c_r_a", &message.c_r_a),
+        elem::<G>("c_b_cap", &message.c_b_cap),
+        elem::<G>("c_rho_b_cap", &message.c_rho_b_cap),
+    ]
+}
+
+fn coprime_message2<G: ConvertibleUnknownOrderGroup>(
+    message: &CoprimeMessage2<G>,
+) -> Vec<(&'static str, Vec<u8>)> {
+    vec![
+        elem::<G>("alpha2", &message.alpha2),
+        elem::<G>("alpha3", &message.alpha3),
+        elem::<G>("alpha4", &message.alpha4),
+        elem::<G>("alpha5", &message.alpha5),
+        elem::<G>("alpha6", &message.alpha6),
+        elem::<G>("alpha7", &message.alpha7),
+    ]
+}
+
+fn coprime_message3(message: &CoprimeMessage3) -> Vec<(&'static str, Vec<u8>)> {
+    vec![
+        integer("s_b", &message.s_b),
+        integer("s_e", &message.s_e),
+        integer("s_rho_b_cap", &message.s_rho_b_cap),
+        integer("s_r", &message.s_r),
+        integer("s_r_a", &message.s_r_a),
+        integer("s_r_a_prime", &message.s_r_a_prime),
+        integer("s_rho_b_cap_prime", &message.s_rho_b_cap_prime),
+        integer("s_beta", &message.s_beta),
+        integer("s_delta", &message.s_delta),
+    ]
+}
+
+fn modeq_message1<G: ConvertibleUnknownOrderGroup, P: CurvePointProjective>(
+    message: &ModEqMessage1<G, P>,
+) -> Result<Vec<(&'static str, Vec<u8>)>, ChannelError> {
+    Ok(vec![
+        elem::<G>("alpha1", &message.alpha1),
+        point("alpha2", &message.alpha2)?,
+    ])
+}
+
+fn modeq_message2<P: CurvePointProjective>(
+    message: &ModEqMessage2<P>,
+) -> Vec<(&'static str, Vec<u8>)> {
+    vec![
+        integer("s_e", &message.s_e),
+        integer("s_r", &message.s_r),
+        ("s_r_q", bigint_to_bytes::<P>(&message.s_r_q)),
+    ]
+}
+
+impl<G: ConvertibleUnknownOrderGroup, C: RootVerifierChannel<G>, S: AuditSink>
+    RootVerifierChannel<G> for LoggingChannel<C, S>
+{
+    fn send_message1(&mut self, message: &RootMessage1<G>) -> Result<(), ChannelError> {
+        RootVerifierChannel::send_message1(&mut self.channel, message)?;
+        self.log(
+            "root",
+            "message1",
+            AuditDirection::ProverMessage,
+            root_message1(message),
+        )
+    }
+    fn send_message2(&mut self, message: &RootMessage2<G>) -> Result<(), ChannelError> {
+        RootVerifierChannel::send_message2(&mut self.channel, message)?;
+        self.log(
+            "root",
+            "message2",
+            AuditDirection::ProverMessage,
+            root_message2(message),
+        )
+    }
+    fn send_message3(&mut self, message: &RootMessage3) -> Result<(), ChannelError> {
+        RootVerifierChannel::send_message3(&mut self.channel, message)?;
+        self.log(
+            "root",
+            "message3",
+            AuditDirection::ProverMessage,
+            root_message3(message),
+        )
+    }
+    fn receive_challenge(&mut self) -> Result<Integer, ChannelError> {
+        let c = RootVerifierChannel::receive_challenge(&mut self.channel)?;
+        self.log_challenge("root", &c)?;
+        Ok(c)
+    }
+}
+
+impl<G: ConvertibleUnknownOrderGroup, C: RootProverChannel<G>, S: AuditSink> RootProverChannel<G>
+    for LoggingChannel<C, S>
+{
+    fn receive_message1(&mut self) -> Result<RootMessage1<G>, ChannelError> {
+        let message = RootProverChannel::receive_message1(&mut self.channel)?;
+        self.log(
+            "root",
+            "message1",
+            AuditDirection::ProverMessage,
+            root_message1(&message),
+        )?;
+        Ok(message)
+    }
+    fn receive_message2(&mut self) -> Result<RootMessage2<G>, ChannelError> {
+        let message = RootProverChannel::receive_message2(&mut self.channel)?;
+        self.log(
+            "root",
+            "message2",
+            AuditDirection::ProverMessage,
+            root_message2(&message),
+        )?;
+        Ok(message)
+    }
+    fn receive_message3(&mut self) -> Result<RootMessage3, ChannelError> {
+        let message = RootProverChannel::receive_message3(&mut self.channel)?;
+        self.log(
+            "root",
+            "message3",
+            AuditDirection::ProverMessage,
+            root_message3(&message),
+        )?;
+        Ok(message)
+    }
+    fn generate_and_send_challenge(&mut self) -> Result<Integer, ChannelError> {
+        let c = RootProverChannel::generate_and_send_challenge(&mut self.channel)?;
+        self.log_challenge("root", &c)?;
+        Ok(c)
+    }
+}
+
+impl<G: ConvertibleUnknownOrderGroup, C: CoprimeVerifierChannel<G>, S: AuditSink>
+    CoprimeVerifierChannel<G> for LoggingChannel<C, S>
+{
+    fn send_message1(&mut self, message: &CoprimeMessage1<G>) -> Result<(), ChannelError> {
+        CoprimeVerifierChannel::send_message1(&mut self.channel, message)?;
+        self.log(
+            "coprime",
+            "message1",
+            AuditDirection::ProverMessage,
+            coprime_message1(message),
+        )
+    }
+    fn send_message2(&mut self, message: &CoprimeMessage2<G>) -> Result<(), ChannelError> {
+        CoprimeVerifierChannel::send_message2(&mut self.channel, message)?;
+        self.log(
+            "coprime",
+            "message2",
+            AuditDirection::ProverMessage,
+            coprime_message2(message),
+        )
+    }
+    fn send_message3(&mut self, message: &CoprimeMessage3) -> Result<(), ChannelError> {
+        CoprimeVerifierChannel::send_message3(&mut self.channel, message)?;
+        self.log(
+            "coprime",
+            "message3",
+            AuditDirection::ProverMessage,
+            coprime_message3(message),
+        )
+    }
+    fn receive_challenge(&mut self) -> Result<Integer, ChannelError> {
+        let c = CoprimeVerifierChannel::receive_challenge(&mut self.channel)?;
+        self.log_challenge("coprime", &c)?;
+        Ok(c)
+    }
+}
+
+impl<G: ConvertibleUnknownOrderGroup, C: CoprimeProverChannel<G>, S: AuditSink>
+    CoprimeProverChannel<G> for LoggingChannel<C, S>
+{
+    fn receive_message1(&mut self) -> Result<CoprimeMessage1<G>, ChannelError> {
+        let message = CoprimeProverChannel::receive_message1(&mut self.channel)?;
+        self.log(
+            "coprime",
+            "message1",
+            AuditDirection::ProverMessage,
+            coprime_message1(&message),
+        )?;
+        Ok(message)
+    }
+    fn receive_message2(&mut self) -> Result<CoprimeMessage2<G>, ChannelError> {
+        let message = CoprimeProverChannel::receive_message2(&mut self.channel)?;
+        self.log(
+            "coprime",
+            "message2",
+            AuditDirection::ProverMessage,
+            coprime_message2(&message),
+        )?;
+        Ok(message)
+    }
+    fn receive_message3(&mut self) -> Result<CoprimeMessage3, ChannelError> {
+        let message = CoprimeProverChannel::receive_message3(&mut self.channel)?;
+        self.log(
+            "coprime",
+            "message3",
+            AuditDirection::ProverMessage,
+            coprime_message3(&message),
+        )?;
+        Ok(message)
+    }
+    fn generate_and_send_challenge(&mut self) -> Result<Integer, ChannelError> {
+        let c = CoprimeProverChannel::generate_and_send_challenge(&mut self.channel)?;
+        self.log_challenge("coprime", &c)?;
+        Ok(c)
+    }
+}
+
+impl<
+        G: ConvertibleUnknownOrderGroup,
+        P: CurvePointProjective,
+        C: ModEqVerifierChannel<G, P>,
+        S: AuditSink,
+    > ModEqVerifierChannel<G, P> for LoggingChannel<C, S>
+{
+    fn send_message1(&mut self, message: &ModEqMessage1<G, P>) -> Result<(), ChannelError> {
+        ModEqVerifierChannel::send_message1(&mut self.channel, message)?;
+        let values = modeq_message1(message)?;
+        self.log("modeq", "message1", AuditDirection::ProverMessage, values)
+    }
+    fn send_message2(&mut self, message: &ModEqMessage2<P>) -> Result<(), ChannelError> {
+        ModEqVerifierChannel::send_message2(&mut self.channel, message)?;
+        self.log(
+            "modeq",
+            "message2",
+            AuditDirection::ProverMessage,
+            modeq_message2(message),
+        )
+    }
+    fn receive_challenge(&mut self) -> Result<Integer, ChannelError> {
+        let c = ModEqVerifierChannel::receive_challenge(&mut self.channel)?;
+        self.log_challenge("modeq", &c)?;
+        Ok(c)
+    }
+}
+
+impl<
+        G: ConvertibleUnknownOrderGroup,
+        P: CurvePointProjective,
+        C: ModEqProverChannel<G, P>,
+        S: AuditSink,
+    > ModEqProverChannel<G, P> for LoggingChannel<C, S>
+{
+    fn receive_message1(&mut self) -> Result<ModEqMessage1<G, P>, ChannelError> {
+        let message = ModEqProverChannel::receive_message1(&mut self.channel)?;
+        let values = modeq_message1(&message)?;
+        self.log("modeq", "message1", AuditDirection::ProverMessage, values)?;
+        Ok(message)
+    }
+    fn receive_message2(&mut self) -> Result<ModEqMessage2<P>, ChannelError> {
+        let message = ModEqProverChannel::receive_message2(&mut self.channel)?;
+        self.log(
+            "modeq",
+            "message2",
+            AuditDirection::ProverMessage,
+            modeq_message2(&message),
+        )?;
+        Ok(message)
+    }
+    fn generate_and_send_challenge(&mut self) -> Result<Integer, ChannelError> {
+        let c = ModEqProverChannel::generate_and_send_challenge(&mut self.channel)?;
+        self.log_challenge("modeq", &c)?;
+        Ok(c)
+    }
+}
+
+impl<
+        P: CurvePointProjective,
+        HP: HashToPrimeProtocol<P>,
+        C: HashToPrimeVerifierChannel<P, HP>,
+        S: AuditSink,
+    > HashToPrimeVerifierChannel<P, HP> for LoggingChannel<C, S>
+{
+    fn send_proof(&mut self, proof: &HP::Proof) -> Result<(), ChannelError> {
+        self.channel.send_proof(proof)?;
+        let values = vec![("proof", HP::proof_to_bytes(proof)?)];
+        self.log(
+            "hash_to_prime",
+            "proof",
+            AuditDirection::ProverMessage,
+            values,
+        )
+    }
+}
+
+impl<
+        P: CurvePointProjective,
+        HP: HashToPrimeProtocol<P>,
+        C: HashToPrimeProverChannel<P, HP>,
+        S: AuditSink,
+    > HashToPrimeProverChannel<P, HP> for LoggingChannel<C, S>
+{
+    fn receive_proof(&mut self) -> Result<HP::Proof, ChannelError> {
+        let proof = self.channel.receive_proof()?;
+        let values = vec![("proof", HP::proof_to_bytes(&proof)?)];
+        self.log(
+            "hash_to_prime",
+            "proof",
+            AuditDirection::ProverMessage,
+            values,
+        )?;
+        Ok(proof)
+    }
+}
+
+impl<G: ConvertibleUnknownOrderGroup, C: MembershipVerifierChannel<G>, S: AuditSink>
+    MembershipVerifierChannel<G> for LoggingChannel<C, S>
+{
+    fn send_crs_digest(&mut self, crs_digest: &CrsDigest) -> Result<(), ChannelError> {
+        MembershipVerifierChannel::<G>::send_crs_digest(&mut self.channel, crs_digest)?;
+        self.log(
+            "membership",
+            "crs_digest",
+            AuditDirection::ProverMessage,
+            vec![("crs_digest", crs_digest.to_vec())],
+        )
+    }
+
+    fn send_c_e(
+        &mut self,
+        c_e: &<IntegerCommitment<G> as Commitment>::Instance,
+    ) -> Result<(), ChannelError> {
+        MembershipVerifierChannel::send_c_e(&mut self.channel, c_e)?;
+        self.log(
+            "membership",
+            "c_e",
+            AuditDirection::ProverMessage,
+            vec![elem::<G>("c_e", c_e)],
+        )
+    }
+}
+
+impl<G: ConvertibleUnknownOrderGroup, C: MembershipProverChannel<G>, S: AuditSink>
+    MembershipProverChannel<G> for LoggingChannel<C, S>
+{
+    fn receive_crs_digest(&mut self) -> Result<CrsDigest, ChannelError> {
+        let crs_digest = MembershipProverChannel::<G>::receive_crs_digest(&mut self.channel)?;
+        self.log(
+            "membership",
+            "crs_digest",
+            AuditDirection::ProverMessage,
+            vec![("crs_digest", crs_digest.to_vec())],
+        )?;
+        Ok(crs_digest)
+    }
+
+    fn receive_c_e(
+        &mut self,
+    ) -> Result<<IntegerCommitment<G> as Commitment>::Instance, ChannelError> {
+        let c_e = MembershipProverChannel::receive_c_e(&mut self.channel)?;
+        self.log(
+            "membership",
+            "c_e",
+            AuditDirection::ProverMessage,
+            vec![elem::<G>("c_e", &c_e)],
+        )?;
+        Ok(c_e)
+    }
+}
+
+impl<G: ConvertibleUnknownOrderGroup, C: NonMembershipVerifierChannel<G>, S: AuditSink>
+    NonMembershipVerifierChannel<G> for LoggingChannel<C, S>
+{
+    fn send_crs_digest(&mut self, crs_digest: &CrsDigest) -> Result<(), ChannelError> {
+        NonMembershipVerifierChannel::<G>::send_crs_digest(&mut self.channel, crs_digest)?;
+        self.log(
+            "nonmembership",
+            "crs_digest",
+            AuditDirection::ProverMessage,
+            vec![("crs_digest", crs_digest.to_vec())],
+        )
+    }
+
+    fn send_c_e(
+        &mut self,
+        c_e: &<IntegerCommitment<G> as Commitment>::Instance,
+    ) -> Result<(), ChannelError> {
+        NonMembershipVerifierChannel::send_c_e(&mut self.channel, c_e)?;
+        self.log(
+            "nonmembership",
+            "c_e",
+            AuditDirection::ProverMessage,
+            vec![elem::<G>("c_e", c_e)],
+        )
+    }
+}
+
+impl<G: ConvertibleUnknownOrderGroup, C: NonMembershipProverChannel<G>, S: AuditSink>
+    NonMembershipProverChannel<G> for LoggingChannel<C, S>
+{
+    fn receive_crs_digest(&mut self) -> Result<CrsDigest, ChannelError> {
+        let crs_digest = NonMembershipProverChannel::<G>::receive_crs_digest(&mut self.channel)?;
+        self.log(
+            "nonmembership",
+            "crs_digest",
+            AuditDirection::ProverMessage,
+            vec![("crs_digest", crs_digest.to_vec())],
+        )?;
+        Ok(crs_digest)
+    }
+
+    fn receive_c_e(
+        &mut self,
+    ) -> Result<<IntegerCommitment<G> as Commitment>::Instance, ChannelError> {
+        let c_e = NonMembershipProverChannel::receive_c_e(&mut self.channel)?;
+        self.log(
+            "nonmembership",
+            "c_e",
+            AuditDirection::ProverMessage,
+            vec![elem::<G>("c_e", &c_e)],
+        )?;
+        Ok(c_e)
+    }
+}
+
+#[cfg(all(test, feature = "arkworks"))]
+mod test {
+    use super::{AuditDirection, AuditEntry, LoggingChannel};
+    use crate::{
+        commitments::Commitment,
+        parameters::Parameters,
+        protocols::{
+            hash_to_prime::snark_range::Protocol as HPProtocol,
+            membership::{
+                transcript::{TranscriptProverChannel, TranscriptVerifierChannel},
+                Protocol, Statement, Witness,
+            },
+        },
+    };
+    use accumulator::{group::Rsa2048, AccumulatorWithoutHashToPrime};
+    use ark_bls12_381::{Bls12_381, G1Projective};
+    use merlin::Transcript;
+    use rand::thread_rng;
+    use rug::rand::RandState;
+    use rug::Integer;
+    use std::cell::RefCell;
+
+    #[test]
+    fn test_logging_channel() {
+        let params = Parameters::from_security_level(128).unwrap();
+        let mut rng1 = RandState::new();
+        rng1.seed(&Integer::from(13));
+        let mut rng2 = thread_rng();
+
+        let crs = Protocol::<Rsa2048, G1Projective, HPProtocol<Bls12_381>>::setup(
+            &params, &mut rng1, &mut rng2,
+        )
+        .unwrap()
+        .crs;
+        let protocol = Protocol::<Rsa2048, G1Projective, HPProtocol<Bls12_381>>::from_crs(&crs);
+
+        let value = Integer::from(Integer::u_pow_u(2, params.hash_to_prime_bits as u32))
+            - &Integer::from(245);
+        let randomness = Integer::from(5);
+        let accum =
+            accumulator::Accumulator::<Rsa2048, Integer, AccumulatorWithoutHashToPrime>::empty()
+                .add_with_proof(&[value.clone()]);
+        let statement = Statement {
+            c_p: accum.0.value,
+            c_e_q: crs
+                .crs_modeq
+                .pedersen_commitment_parameters
+                .commit(&value, &randomness)
+                .unwrap(),
+        };
+        let witness = Witness {
+            e: value,
+            r_q: randomness,
+            w: accum.1.witness.0.value,
+        };
+
+        let proof_transcript = RefCell::new(Transcript::new(b"membership"));
+        let mut prover_log: Vec<AuditEntry> = vec![];
+        let mut verifier_channel = LoggingChannel::new(
+            TranscriptVerifierChannel::new(&crs, &proof_transcript),
+            &mut prover_log,
+        );
+        protocol
+            .prove(
+                &mut verifier_channel,
+                &mut rng1,
+                &mut rng2,
+                &statement,
+                &witness,
+            )
+            .unwrap();
+        let proof = verifier_channel.channel.proof().unwrap();
+
+        let verification_transcript = RefCell::new(Transcript::new(b"membership"));
+        let mut verifier_log: Vec<AuditEntry> = vec![];
+        let mut prover_channel = LoggingChannel::new(
+            TranscriptProverChannel::new(&crs, &verification_transcript, &proof),
+            &mut verifier_log,
+        );
+        protocol.verify(&mut prover_channel, &statement).unwrap();
+
+        // c_e, 3 root messages, 2 modeq messages, their challenges and the
+        // hash-to-prime proof
+        assert_eq!(prover_log.len(), 9);
+        assert_eq!(prover_log[0].sub_protocol, "membership");
+        assert_eq!(
+            prover_log
+                .iter()
+                .filter(|e| e.direction == AuditDirection::Challenge)
+                .count(),
+            2
+        );
+        // both sides see the same messages and challenges
+        assert_eq!(prover_log.len(), verifier_log.len());
+        for (p, v) in prover_log.iter().zip(&verifier_log) {
+            assert_eq!(
+                (p.sub_protocol, p.message, &p.values),
+                (v.sub_protocol, v.message, &v.values)
+            );
+        }
+    }
+}
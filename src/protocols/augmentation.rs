@@ -0,0 +1,332 @@
+//! Extends a CRS to a larger `hash_to_prime_bits` (μ), e.g. to accept larger
+//! elements, without a new setup of the commitment bases.
+//!
+//! Only the hash-to-prime parameters depend on μ through the size of the range
+//! circuit, so they are the only ones generated again, for the same Pedersen
+//! bases. The sigma protocols only use μ to size their blinding. Commitments
+//! made under the old CRS stay valid under the augmented one, and so do the
+//! accumulator and the witnesses, while proofs have to be created again. The
+//! new parts are returned as a `CrsDelta`, from which holders of the old CRS
+//! rebuild the augmented one.
+use crate::{
+    parameters::{Parameters, ParametersError, SubProtocol},
+    protocols::{
+        hash_to_prime::{CRSHashToPrime, HashToPrimeProtocol},
+        membership::CRS as MembershipCRS,
+        nonmembership::CRS as NonMembershipCRS,
+        registry::CrsDigest,
+        statement::digest_to_hex,
+        SetupError,
+    },
+    utils::{
+        curve::{CurveError, CurvePointProjective},
+        ConvertibleUnknownOrderGroup,
+    },
+};
+use rand::{CryptoRng, RngCore};
+
+quick_error! {
+    #[derive(Debug)]
+    pub enum AugmentationError {
+        NotLarger {}
+        WrongCrs {}
+        InvalidParameters(err: ParametersError) {
+            from()
+        }
+        SetupError(err: SetupError) {
+            from()
+        }
+        CurveError(err: CurveError) {
+            from()
+        }
+    }
+}
+
+/// The parts of an augmented CRS which differ from the old one.
+pub struct CrsDelta<P: CurvePointProjective, HP: HashToPrimeProtocol<P>> {
+    pub old_digest: CrsDigest,
+    pub new_digest: CrsDigest,
+    pub old_hash_to_prime_bits: u16,
+    pub hash_to_prime_bits: u16,
+    pub hash_to_prime_parameters: HP::Parameters,
+}
+
+impl<P: CurvePointProjective, HP: HashToPrimeProtocol<P>> Clone for CrsDelta<P, HP> {
+    fn clone(&self) -> Self {
+        Self {
+            old_digest: self.old_digest,
+            new_digest: self.new_digest,
+            old_hash_to_prime_bits: self.old_hash_to_prime_bits,
+            hash_to_prime_bits: self.hash_to_prime_bits,
+            hash_to_prime_parameters: self.hash_to_prime_parameters.clone(),
+        }
+    }
+}
+
+impl<P: CurvePointProjective, HP: HashToPrimeProtocol<P>> CrsDelta<P, HP> {
+    /// Rebuilds the augmented membership CRS from the old one.
+    pub fn apply_membership<G: ConvertibleUnknownOrderGroup>(
+        &self,
+        crs: &MembershipCRS<G, P, HP>,
+    ) -> Result<MembershipCRS<G, P, HP>, AugmentationError> {
+        if crs.digest()? != self.old_digest {
+            return Err(AugmentationError::WrongCrs);
+        }
+        let parameters = augmented_parameters(&crs.parameters, self.hash_to_prime_bits)?;
+        let augmented = augment_membership(crs, &parameters, self.hash_to_prime_parameters.clone());
+        self.check_new_digest(augmented.digest()?)?;
+        Ok(augmented)
+    }
+
+    /// Rebuilds the augmented non-membership CRS from the old one.
+    pub fn apply_nonmembership<G: ConvertibleUnknownOrderGroup>(
+        &self,
+        crs: &NonMembershipCRS<G, P, HP>,
+    ) -> Result<NonMembershipCRS<G, P, HP>, AugmentationError> {
+        if crs.digest()? != self.old_digest {
+            return Err(AugmentationError::WrongCrs);
+        }
+        let parameters = augmented_parameters(&crs.parameters, self.hash_to_prime_bits)?;
+        let augmented =
+            augment_nonmembership(crs, &parameters, self.hash_to_prime_parameters.clone());
+        self.check_new_digest(augmented.digest()?)?;
+        Ok(augmented)
+    }
+
+    fn check_new_digest(&self, digest: CrsDigest) -> Result<(), AugmentationError> {
+        if digest != self.new_digest {
+            return Err(AugmentationError::WrongCrs);
+        }
+        Ok(())
+    }
+
+    /// Notes for the operators of a deployment moving to the augmented CRS.
+    pub fn migration_notes(&self) -> String {
+        format!(
+            "CRS {} augmented to CRS {}:\n\
+             - hash_to_prime_bits raised from {} to {}, elements of up to {} bits are accepted\n\
+             - the integer and Pedersen commitment bases are unchanged, existing commitments stay valid\n\
+             - the accumulator and membership witnesses are unchanged\n\
+             - proofs under the old CRS have to be created again under the new one; register both in a CrsRegistry during the transition\n",
+            digest_to_hex(&self.old_digest),
+            digest_to_hex(&self.new_digest),
+            self.old_hash_to_prime_bits,
+            self.hash_to_prime_bits,
+            self.hash_to_prime_bits,
+        )
+    }
+}
+
+fn augmented_parameters(
+    parameters: &Parameters,
+    hash_to_prime_bits: u16,
+) -> Result<Parameters, AugmentationError> {
+    if hash_to_prime_bits <= parameters.hash_to_prime_bits {
+        return Err(AugmentationError::NotLarger);
+    }
+    let parameters = Parameters {
+        hash_to_prime_bits,
+        ..parameters.clone()
+    };
+    parameters.is_valid()?;
+    Ok(parameters)
+}
+
+fn augment_membership<
+    G: ConvertibleUnknownOrderGroup,
+    P: CurvePointProjective,
+    HP: HashToPrimeProtocol<P>,
+>(
+    crs: &MembershipCRS<G, P, HP>,
+    parameters: &Parameters,
+    hash_to_prime_parameters: HP::Parameters,
+) -> MembershipCRS<G, P, HP> {
+    let mut augmented = crs.clone();
+    augmented.parameters = parameters.clone();
+    augmented.crs_root.parameters = parameters.for_sub_protocol(SubProtocol::Root);
+    augmented.crs_modeq.parameters = parameters.for_sub_protocol(SubProtocol::ModEq);
+    augmented.crs_hash_to_prime = CRSHashToPrime {
+        parameters: parameters.for_sub_protocol(SubProtocol::HashToPrime),
+        pedersen_commitment_parameters: crs
+            .crs_hash_to_prime
+            .pedersen_commitment_parameters
+            .clone(),
+        hash_to_prime_parameters,
+    };
+    augmented
+}
+
+fn augment_nonmembership<
+    G: ConvertibleUnknownOrderGroup,
+    P: CurvePointProjective,
+    HP: HashToPrimeProtocol<P>,
+>(
+    crs: &NonMembershipCRS<G, P, HP>,
+    parameters: &Parameters,
+    hash_to_prime_parameters: HP::Parameters,
+) -> NonMembershipCRS<G, P, HP> {
+    let mut augmented = crs.clone();
+    augmented.parameters = parameters.clone();
+    augmented.crs_coprime.parameters = parameters.for_sub_protocol(SubProtocol::Coprime);
+    augmented.crs_modeq.parameters = parameters.for_sub_protocol(SubProtocol::ModEq);
+    augmented.crs_hash_to_prime = CRSHashToPrime {
+        parameters: parameters.for_sub_protocol(SubProtocol::HashToPrime),
+        pedersen_commitment_parameters: crs
+            .crs_hash_to_prime
+            .pedersen_commitment_parameters
+            .clone(),
+        hash_to_prime_parameters,
+    };
+    augmented
+}
+
+/// Extends a membership CRS to `hash_to_prime_bits`, generating the
+/// hash-to-prime parameters for the existing Pedersen bases.
+pub fn augment_membership_crs<
+    G: ConvertibleUnknownOrderGroup,
+    P: CurvePointProjective,
+    HP: HashToPrimeProtocol<P>,
+    R: RngCore + CryptoRng,
+>(
+    crs: &MembershipCRS<G, P, HP>,
+    hash_to_prime_bits: u16,
+    rng: &mut R,
+) -> Result<(MembershipCRS<G, P, HP>, CrsDelta<P, HP>), AugmentationError> {
+    let parameters = augmented_parameters(&crs.parameters, hash_to_prime_bits)?;
+    let hash_to_prime_parameters = HP::setup(
+        rng,
+        &crs.crs_hash_to_prime.pedersen_commitment_parameters,
+        &parameters.for_sub_protocol(SubProtocol::HashToPrime),
+    )?;
+    let augmented = augment_membership(crs, &parameters, hash_to_prime_parameters.clone());
+    let delta = CrsDelta {
+        old_digest: crs.digest()?,
+        new_digest: augmented.digest()?,
+        old_hash_to_prime_bits: crs.parameters.hash_to_prime_bits,
+        hash_to_prime_bits,
+        hash_to_prime_parameters,
+    };
+    Ok((augmented, delta))
+}
+
+/// Extends a non-membership CRS to `hash_to_prime_bits`, generating the
+/// hash-to-prime parameters for the existing Pedersen bases.
+pub fn augment_nonmembership_crs<
+    G: ConvertibleUnknownOrderGroup,
+    P: CurvePointProjective,
+    HP: HashToPrimeProtocol<P>,
+    R: RngCore + CryptoRng,
+>(
+    crs: &NonMembershipCRS<G, P, HP>,
+    hash_to_prime_bits: u16,
+    rng: &mut R,
+) -> Result<(NonMembershipCRS<G, P, HP>, CrsDelta<P, HP>), AugmentationError> {
+    let parameters = augmented_parameters(&crs.parameters, hash_to_prime_bits)?;
+    let hash_to_prime_parameters = HP::setup(
+        rng,
+        &crs.crs_hash_to_prime.pedersen_commitment_parameters,
+        &parameters.for_sub_protocol(SubProtocol::HashToPrime),
+    )?;
+    let augmented = augment_nonmembership(crs, &parameters, hash_to_prime_parameters.clone());
+    let delta = CrsDelta {
+        old_digest: crs.digest()?,
+        new_digest: augmented.digest()?,
+        old_hash_to_prime_bits: crs.parameters.hash_to_prime_bits,
+        hash_to_prime_bits,
+        hash_to_prime_parameters,
+    };
+    Ok((augmented, delta))
+}
+
+#[cfg(all(test, feature = "arkworks"))]
+mod test {
+    use super::{augment_membership_crs, AugmentationError};
+    use crate::{
+        commitments::Commitment,
+        parameters::Parameters,
+        protocols::{
+            hash_to_prime::snark_range::Protocol as HPProtocol,
+            membership::{
+                transcript::{TranscriptProverChannel, TranscriptVerifierChannel},
+                Protocol, Statement, Witness,
+            },
+        },
+    };
+    use accumulator::{group::Rsa2048, AccumulatorWithoutHashToPrime};
+    use ark_bls12_381::{Bls12_381, G1Projective};
+    use merlin::Transcript;
+    use rand::thread_rng;
+    use rug::rand::RandState;
+    use rug::Integer;
+    use std::cell::RefCell;
+
+    #[test]
+    fn test_augment_membership_crs() {
+        let params = Parameters {
+            hash_to_prime_bits: 127,
+            ..Parameters::from_security_level(128).unwrap()
+        };
+        params.is_valid().unwrap();
+        let mut rng1 = RandState::new();
+        rng1.seed(&Integer::from(13));
+        let mut rng2 = thread_rng();
+
+        let crs = Protocol::<Rsa2048, G1Projective, HPProtocol<Bls12_381>>::setup(
+            &params, &mut rng1, &mut rng2,
+        )
+        .unwrap()
+        .crs;
+        assert!(matches!(
+            augment_membership_crs(&crs, 127, &mut rng2),
+            Err(AugmentationError::NotLarger)
+        ));
+        let (augmented, delta) = augment_membership_crs(&crs, 254, &mut rng2).unwrap();
+        assert_eq!(augmented.digest().unwrap(), delta.new_digest);
+        assert_eq!(
+            delta.apply_membership(&crs).unwrap().digest().unwrap(),
+            delta.new_digest
+        );
+        assert!(matches!(
+            delta.apply_membership(&augmented),
+            Err(AugmentationError::WrongCrs)
+        ));
+        assert!(delta.migration_notes().contains("from 127 to 254"));
+
+        // a commitment made under the old CRS to an element too large for it
+        let value = Integer::from(Integer::u_pow_u(2, 254)) - &Integer::from(245);
+        let randomness = Integer::from(5);
+        let c_e_q = crs
+            .crs_modeq
+            .pedersen_commitment_parameters
+            .commit(&value, &randomness)
+            .unwrap();
+        let accum =
+            accumulator::Accumulator::<Rsa2048, Integer, AccumulatorWithoutHashToPrime>::empty()
+                .add_with_proof(&[value.clone()]);
+        let statement = Statement {
+            c_p: accum.0.value,
+            c_e_q,
+        };
+        let protocol = Protocol::from_crs(&augmented);
+        let proof_transcript = RefCell::new(Transcript::new(b"membership"));
+        let mut verifier_channel = TranscriptVerifierChannel::new(&augmented, &proof_transcript);
+        protocol
+            .prove(
+                &mut verifier_channel,
+                &mut rng1,
+                &mut rng2,
+                &statement,
+                &Witness {
+                    e: value,
+                    r_q: randomness,
+                    w: accum.1.witness.0.value,
+                },
+            )
+            .unwrap();
+        let proof = verifier_channel.proof().unwrap();
+        let verification_transcript = RefCell::new(Transcript::new(b"membership"));
+        let mut prover_channel =
+            TranscriptProverChannel::new(&augmented, &verification_transcript, &proof);
+        protocol.verify(&mut prover_channel, &statement).unwrap();
+    }
+}
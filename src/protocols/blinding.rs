@@ -0,0 +1,149 @@
+//! Detection of blinding value reuse across proofs.
+//!
+//! Answering two different challenges with the same blinding values reveals
+//! the witness. With a working RNG this never happens, so this is only a
+//! defense against deterministic or broken RNGs: a prover session keeps the
+//! hashes of the blinding tuples it used recently and refuses to reuse one.
+//!
+//! It also defines the ranges the blinding values are sampled from, which are
+//! padded by `2^security_zk` beyond the values they mask so that the
+//! responses are statistically hiding.
+use crate::{parameters::Parameters, protocols::ProofError, utils::ConvertibleUnknownOrderGroup};
+use blake2::{Blake2s, Digest};
+use rug::{integer::Order, Integer};
+use std::collections::{HashSet, VecDeque};
+
+pub struct BlindingHistory {
+    capacity: usize,
+    order: VecDeque<[u8; 32]>,
+    seen: HashSet<[u8; 32]>,
+}
+
+impl BlindingHistory {
+    pub const DEFAULT_CAPACITY: usize = 1024;
+
+    /// Keeps the hashes of the last `capacity` blinding tuples. A capacity of
+    /// zero disables the checks.
+    pub fn new(capacity: usize) -> BlindingHistory {
+        BlindingHistory {
+            capacity,
+            order: VecDeque::new(),
+            seen: HashSet::new(),
+        }
+    }
+
+    /// Records the blinding values of one proof, failing with
+    /// `ProofError::BlindingReuse` if they were already used.
+    pub fn record(&mut self, label: &[u8], blindings: &[&Integer]) -> Result<(), ProofError> {
+        if self.capacity == 0 {
+            return Ok(());
+        }
+
+        let mut hasher = Blake2s::default();
+        hasher.update(&(label.len() as u64).to_le_bytes());
+        hasher.update(label);
+        for b in blindings {
+            let digits = b.to_digits::<u8>(Order::MsfBe);
+            hasher.update(&[(b.cmp0() as i8) as u8]);
+            hasher.update(&(digits.len() as u64).to_le_bytes());
+            hasher.update(&digits);
+        }
+        let mut digest = [0u8; 32];
+        digest.copy_from_slice(&hasher.finalize());
+
+        if !self.seen.insert(digest) {
+            return Err(ProofError::BlindingReuse);
+        }
+        self.order.push_back(digest);
+        if self.order.len() > self.capacity {
+            if let Some(oldest) = self.order.pop_front() {
+                self.seen.remove(&oldest);
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn len(&self) -> usize {
+        self.order.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.order.is_empty()
+    }
+}
+
+impl Default for BlindingHistory {
+    fn default() -> BlindingHistory {
+        BlindingHistory::new(Self::DEFAULT_CAPACITY)
+    }
+}
+
+/// Symmetric ranges the blinding values of the sigma protocols are sampled
+/// from, i.e. values are taken uniformly in `[-range, range]`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BlindingRanges {
+    /// Randomness of the auxiliary commitments, `ord(G)/2`.
+    pub randomness: Integer,
+    /// Masks of exponents of `hash_to_prime_bits` bits multiplied by the
+    /// challenge, `2^(zk + soundness + hash_to_prime_bits)`.
+    pub exponent: Integer,
+    /// Masks of commitment randomness multiplied by the challenge,
+    /// `ord(G)/2 * 2^(zk + soundness)`.
+    pub randomness_mask: Integer,
+    /// Masks of products of an exponent and commitment randomness multiplied
+    /// by the challenge, `ord(G)/2 * 2^(zk + soundness + hash_to_prime_bits)`.
+    pub product_mask: Integer,
+}
+
+impl BlindingRanges {
+    pub fn new<G: ConvertibleUnknownOrderGroup>(parameters: &Parameters) -> BlindingRanges {
+        let pow2 = |bits: u16| Integer::from(Integer::u_pow_u(2, bits as u32));
+        let randomness = G::order_upper_bound() / 2;
+        let margin = parameters.security_zk + parameters.security_soundness;
+        BlindingRanges {
+            exponent: pow2(margin + parameters.hash_to_prime_bits),
+            randomness_mask: Integer::from(&randomness * pow2(margin)),
+            product_mask: Integer::from(&randomness * pow2(margin + parameters.hash_to_prime_bits)),
+            randomness,
+        }
+    }
+
+    /// Bound on the honest responses for exponents, `s = r - c * e`, which
+    /// the verifier checks: `2 * exponent`.
+    pub fn exponent_response_bound(&self) -> Integer {
+        Integer::from(&self.exponent * 2)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::BlindingRanges;
+    use crate::{parameters::Parameters, utils::ConvertibleUnknownOrderGroup};
+    use accumulator::group::Rsa2048;
+    use rug::Integer;
+
+    #[test]
+    fn test_masking_margins() {
+        let params = Parameters::from_security_level(128).unwrap();
+        let ranges = BlindingRanges::new::<Rsa2048>(&params);
+        let max_challenge = Integer::from(Integer::u_pow_u(2, params.security_soundness as u32));
+        let max_exponent = Integer::from(Integer::u_pow_u(2, params.hash_to_prime_bits as u32));
+        let margin = Integer::from(Integer::u_pow_u(2, params.security_zk as u32));
+
+        // each mask exceeds the largest value it hides, c * x, by 2^security_zk
+        let masked = |x: &Integer| Integer::from(x * &max_challenge) * &margin;
+        assert_eq!(ranges.exponent, masked(&max_exponent));
+        assert_eq!(ranges.randomness_mask, masked(&ranges.randomness));
+        assert_eq!(
+            ranges.product_mask,
+            masked(&Integer::from(&max_exponent * &ranges.randomness))
+        );
+        assert_eq!(ranges.randomness, Rsa2048::order_upper_bound() / 2);
+
+        // an honest response stays within the bound the verifier checks
+        let largest_masked = Integer::from(&max_challenge * &max_exponent);
+        let largest_response = Integer::from(&ranges.exponent + &largest_masked);
+        assert!(largest_response <= ranges.exponent_response_bound());
+    }
+}
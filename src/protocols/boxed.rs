@@ -0,0 +1,288 @@
+//! Object-safe membership provers and verifiers working on bytes, for plugin
+//! architectures and FFI, which hold protocols over different groups and
+//! curves as `Box<dyn MembershipProver>` and `Box<dyn MembershipVerifier>`.
+//!
+//! The library doesn't fix an encoding for group elements and curve points,
+//! so statements, witnesses and proofs are converted with a `Codec` provided
+//! by the application.
+#[cfg(feature = "prover")]
+use crate::protocols::membership::{transcript::TranscriptVerifierChannel, Witness};
+use crate::{
+    protocols::{
+        hash_to_prime::HashToPrimeProtocol,
+        membership::{transcript::TranscriptProverChannel, Proof, Protocol, Statement},
+        ProofError, VerificationError,
+    },
+    transcript::transcript_with_aad,
+    utils::{curve::CurvePointProjective, ConvertibleUnknownOrderGroup},
+};
+#[cfg(feature = "prover")]
+use rand::thread_rng;
+use std::cell::RefCell;
+
+quick_error! {
+    #[derive(Debug)]
+    pub enum CodecError {
+        InvalidEncoding {}
+    }
+}
+
+/// Converts values of `T` from and to bytes.
+pub trait Codec<T> {
+    fn encode(&self, value: &T) -> Result<Vec<u8>, CodecError>;
+    fn decode(&self, bytes: &[u8]) -> Result<T, CodecError>;
+}
+
+/// Object-safe membership prover, taking the encoded statement and witness
+/// and returning the encoded proof.
+pub trait MembershipProver {
+    /// Proves on a transcript bound to the associated data `aad`.
+    fn prove_bytes(
+        &self,
+        statement: &[u8],
+        witness: &[u8],
+        aad: &[u8],
+    ) -> Result<Vec<u8>, ProofError>;
+}
+
+/// Object-safe membership verifier, taking the encoded statement and proof.
+pub trait MembershipVerifier {
+    /// Verifies `proof`, bound to the associated data `aad` it was created
+    /// with.
+    fn verify_bytes(
+        &self,
+        statement: &[u8],
+        proof: &[u8],
+        aad: &[u8],
+    ) -> Result<(), VerificationError>;
+}
+
+/// Proves and verifies membership with `protocol` on transcripts created with
+/// `transcript_label`, encoding with `codec`.
+pub struct BoxedMembership<G, P, HP, C>
+where
+    G: ConvertibleUnknownOrderGroup,
+    P: CurvePointProjective,
+    HP: HashToPrimeProtocol<P>,
+{
+    pub protocol: Protocol<G, P, HP>,
+    pub codec: C,
+    pub transcript_label: &'static [u8],
+}
+
+impl<G, P, HP, C> BoxedMembership<G, P, HP, C>
+where
+    G: ConvertibleUnknownOrderGroup,
+    P: CurvePointProjective,
+    HP: HashToPrimeProtocol<P>,
+{
+    pub fn new(
+        protocol: Protocol<G, P, HP>,
+        codec: C,
+        transcript_label: &'static [u8],
+    ) -> BoxedMembership<G, P, HP, C> {
+        BoxedMembership {
+            protocol,
+            codec,
+            transcript_label,
+        }
+    }
+}
+
+#[cfg(feature = "prover")]
+impl<G, P, HP, C> MembershipProver for BoxedMembership<G, P, HP, C>
+where
+    G: ConvertibleUnknownOrderGroup,
+    P: CurvePointProjective,
+    HP: HashToPrimeProtocol<P>,
+    C: Codec<Statement<G, P>> + Codec<Witness<G>> + Codec<Proof<G, P, HP>>,
+{
+    fn prove_bytes(
+        &self,
+        statement: &[u8],
+        witness: &[u8],
+        aad: &[u8],
+    ) -> Result<Vec<u8>, ProofError> {
+        let statement: Statement<G, P> = self.codec.decode(statement)?;
+        let witness: Witness<G> = self.codec.decode(witness)?;
+        let transcript = RefCell::new(transcript_with_aad(self.transcript_label, aad));
+        let mut verifier_channel = TranscriptVerifierChannel::new(&self.protocol.crs, &transcript);
+        self.protocol.prove_with_transcript_rng(
+            &mut verifier_channel,
+            &mut thread_rng(),
+            &statement,
+            &witness,
+        )?;
+        let proof = verifier_channel
+            .proof()
+            .map_err(|_| ProofError::CouldNotCreateProof)?;
+        Ok(self.codec.encode(&proof)?)
+    }
+}
+
+impl<G, P, HP, C> MembershipVerifier for BoxedMembership<G, P, HP, C>
+where
+    G: ConvertibleUnknownOrderGroup,
+    P: CurvePointProjective,
+    HP: HashToPrimeProtocol<P>,
+    C: Codec<Statement<G, P>> + Codec<Proof<G, P, HP>>,
+{
+    fn verify_bytes(
+        &self,
+        statement: &[u8],
+        proof: &[u8],
+        aad: &[u8],
+    ) -> Result<(), VerificationError> {
+        let statement: Statement<G, P> = self.codec.decode(statement)?;
+        let proof: Proof<G, P, HP> = self.codec.decode(proof)?;
+        let transcript = RefCell::new(transcript_with_aad(self.transcript_label, aad));
+        let mut prover_channel =
+            TranscriptProverChannel::new(&self.protocol.crs, &transcript, &proof);
+        self.protocol.verify(&mut prover_channel, &statement)
+    }
+}
+
+#[cfg(all(test, feature = "arkworks"))]
+mod test {
+    use super::{BoxedMembership, Codec, CodecError, MembershipProver, MembershipVerifier};
+    use crate::{
+        commitments::Commitment,
+        parameters::Parameters,
+        protocols::{
+            hash_to_prime::snark_range::Protocol as HPProtocol,
+            membership::{Proof, Protocol, Statement, Witness},
+            ProofError, VerificationError,
+        },
+    };
+    use accumulator::{group::Rsa2048, AccumulatorWithoutHashToPrime};
+    use ark_bls12_381::{Bls12_381, G1Projective};
+    use rand::thread_rng;
+    use rug::rand::RandState;
+    use rug::Integer;
+    use std::cell::RefCell;
+
+    type TestProof = Proof<Rsa2048, G1Projective, HPProtocol<Bls12_381>>;
+
+    /// Encodes values as handles to values kept in memory, as an FFI layer
+    /// would.
+    #[derive(Default)]
+    struct HandleCodec {
+        statements: RefCell<Vec<Statement<Rsa2048, G1Projective>>>,
+        witnesses: RefCell<Vec<Option<Witness<Rsa2048>>>>,
+        proofs: RefCell<Vec<TestProof>>,
+    }
+
+    fn handle(bytes: &[u8]) -> Result<usize, CodecError> {
+        if bytes.len() != 8 {
+            return Err(CodecError::InvalidEncoding);
+        }
+        let mut handle = [0u8; 8];
+        handle.copy_from_slice(bytes);
+        Ok(u64::from_le_bytes(handle) as usize)
+    }
+
+    impl Codec<Statement<Rsa2048, G1Projective>> for HandleCodec {
+        fn encode(&self, value: &Statement<Rsa2048, G1Projective>) -> Result<Vec<u8>, CodecError> {
+            self.statements.borrow_mut().push(value.clone());
+            Ok((self.statements.borrow().len() as u64 - 1)
+                .to_le_bytes()
+                .to_vec())
+        }
+
+        fn decode(&self, bytes: &[u8]) -> Result<Statement<Rsa2048, G1Projective>, CodecError> {
+            self.statements
+                .borrow()
+                .get(handle(bytes)?)
+                .cloned()
+                .ok_or(CodecError::InvalidEncoding)
+        }
+    }
+
+    impl Codec<Witness<Rsa2048>> for HandleCodec {
+        fn encode(&self, _: &Witness<Rsa2048>) -> Result<Vec<u8>, CodecError> {
+            Err(CodecError::InvalidEncoding)
+        }
+
+        fn decode(&self, bytes: &[u8]) -> Result<Witness<Rsa2048>, CodecError> {
+            self.witnesses
+                .borrow_mut()
+                .get_mut(handle(bytes)?)
+                .and_then(|w| w.take())
+                .ok_or(CodecError::InvalidEncoding)
+        }
+    }
+
+    impl Codec<TestProof> for HandleCodec {
+        fn encode(&self, value: &TestProof) -> Result<Vec<u8>, CodecError> {
+            self.proofs.borrow_mut().push(value.clone());
+            Ok((self.proofs.borrow().len() as u64 - 1)
+                .to_le_bytes()
+                .to_vec())
+        }
+
+        fn decode(&self, bytes: &[u8]) -> Result<TestProof, CodecError> {
+            self.proofs
+                .borrow()
+                .get(handle(bytes)?)
+                .cloned()
+                .ok_or(CodecError::InvalidEncoding)
+        }
+    }
+
+    #[test]
+    fn test_boxed_membership() {
+        let params = Parameters::from_security_level(128).unwrap();
+        let mut rng1 = RandState::new();
+        rng1.seed(&Integer::from(13));
+        let mut rng2 = thread_rng();
+
+        let crs = Protocol::<Rsa2048, G1Projective, HPProtocol<Bls12_381>>::setup(
+            &params, &mut rng1, &mut rng2,
+        )
+        .unwrap()
+        .crs;
+
+        let value = Integer::from(Integer::u_pow_u(2, params.hash_to_prime_bits as u32))
+            - &Integer::from(245);
+        let randomness = Integer::from(5);
+        let accum =
+            accumulator::Accumulator::<Rsa2048, Integer, AccumulatorWithoutHashToPrime>::empty()
+                .add_with_proof(&[value.clone()]);
+        let statement = Statement {
+            c_p: accum.0.value,
+            c_e_q: crs
+                .crs_modeq
+                .pedersen_commitment_parameters
+                .commit(&value, &randomness)
+                .unwrap(),
+        };
+        let codec = HandleCodec::default();
+        codec.witnesses.borrow_mut().push(Some(Witness {
+            e: value,
+            r_q: randomness,
+            w: accum.1.witness.0.value,
+        }));
+        let statement = codec.encode(&statement).unwrap();
+        let witness = 0u64.to_le_bytes();
+
+        let boxed = BoxedMembership::new(Protocol::from_crs(&crs), codec, b"membership");
+        let proof = boxed.prove_bytes(&statement, &witness, b"session").unwrap();
+        // the witness handle was consumed
+        assert!(matches!(
+            boxed.prove_bytes(&statement, &witness, b"session"),
+            Err(ProofError::CodecError(CodecError::InvalidEncoding))
+        ));
+
+        let verifiers: Vec<Box<dyn MembershipVerifier>> = vec![Box::new(boxed)];
+        verifiers[0]
+            .verify_bytes(&statement, &proof, b"session")
+            .unwrap();
+        assert!(verifiers[0]
+            .verify_bytes(&statement, &proof, b"other session")
+            .is_err());
+        assert!(matches!(
+            verifiers[0].verify_bytes(&statement, &[0u8; 3], b"session"),
+            Err(VerificationError::CodecError(CodecError::InvalidEncoding))
+        ));
+    }
+}
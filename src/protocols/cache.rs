@@ -0,0 +1,167 @@
+//! Caching of successful verifications, for services which see the same proof
+//! presented several times.
+//!
+//! A cached result is only valid in the context the proof was verified in, so
+//! the key has to bind everything the verification depends on: the CRS, the
+//! statement, the proof and any freshness data such as a nonce the verifier
+//! added to the transcript. The key also carries the epoch of the accumulator
+//! the statement was checked against, and the cache drops all entries when the
+//! epoch advances.
+use crate::{
+    protocols::VerificationError,
+    utils::{
+        bigint_to_bytes,
+        curve::{CurveError, CurvePointProjective},
+        integer_to_signed_bytes, ConvertibleUnknownOrderGroup,
+    },
+};
+use blake2::{Blake2s, Digest};
+use rug::Integer;
+use std::collections::{BTreeMap, HashMap};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct CacheKey {
+    pub epoch: u64,
+    digest: [u8; 32],
+}
+
+pub struct CacheKeyBuilder {
+    epoch: u64,
+    hasher: Blake2s,
+}
+
+impl CacheKeyBuilder {
+    pub fn new(epoch: u64) -> CacheKeyBuilder {
+        let mut hasher = Blake2s::default();
+        hasher.update(&epoch.to_le_bytes());
+        CacheKeyBuilder { epoch, hasher }
+    }
+
+    pub fn append_bytes(&mut self, label: &'static [u8], bytes: &[u8]) {
+        self.hasher.update(&(label.len() as u64).to_le_bytes());
+        self.hasher.update(label);
+        self.hasher.update(&(bytes.len() as u64).to_le_bytes());
+        self.hasher.update(bytes);
+    }
+
+    pub fn append_integer(&mut self, label: &'static [u8], value: &Integer) {
+        self.append_bytes(label, &integer_to_signed_bytes(value));
+    }
+
+    pub fn append_group_element<G: ConvertibleUnknownOrderGroup>(
+        &mut self,
+        label: &'static [u8],
+        value: &G::Elem,
+    ) {
+        self.append_bytes(label, &G::elem_to_bytes(value));
+    }
+
+    pub fn append_curve_point<P: CurvePointProjective>(
+        &mut self,
+        label: &'static [u8],
+        value: &P,
+    ) -> Result<(), CurveError> {
+        self.append_bytes(label, &value.to_affine_bytes()?);
+        Ok(())
+    }
+
+    pub fn append_curve_scalar<P: CurvePointProjective>(
+        &mut self,
+        label: &'static [u8],
+        value: &P::ScalarField,
+    ) {
+        self.append_bytes(label, &bigint_to_bytes::<P>(value));
+    }
+
+    pub fn finish(self) -> CacheKey {
+        let mut digest = [0u8; 32];
+        digest.copy_from_slice(&self.hasher.finalize());
+        CacheKey {
+            epoch: self.epoch,
+            digest,
+        }
+    }
+}
+
+/// Least recently used cache of successful verifications. Failed
+/// verifications are never cached.
+pub struct VerifierCache {
+    capacity: usize,
+    epoch: u64,
+    counter: u64,
+    entries: HashMap<CacheKey, u64>,
+    usage: BTreeMap<u64, CacheKey>,
+}
+
+impl VerifierCache {
+    pub fn new(capacity: usize, epoch: u64) -> VerifierCache {
+        VerifierCache {
+            capacity,
+            epoch,
+            counter: 0,
+            entries: HashMap::new(),
+            usage: BTreeMap::new(),
+        }
+    }
+
+    pub fn epoch(&self) -> u64 {
+        self.epoch
+    }
+
+    /// Moves to a newer epoch, dropping the results verified against older
+    /// accumulator values.
+    pub fn advance_epoch(&mut self, epoch: u64) {
+        if epoch > self.epoch {
+            self.epoch = epoch;
+            self.entries.clear();
+            self.usage.clear();
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Returns the cached result for `key`, or runs `verify` and caches it if
+    /// it succeeds. Keys from another epoch are rejected without verifying.
+    pub fn verify<F: FnOnce() -> Result<(), VerificationError>>(
+        &mut self,
+        key: &CacheKey,
+        verify: F,
+    ) -> Result<(), VerificationError> {
+        if key.epoch != self.epoch {
+            return Err(VerificationError::StaleEpoch);
+        }
+        if self.entries.contains_key(key) {
+            self.touch(key);
+            return Ok(());
+        }
+
+        verify()?;
+        if self.capacity > 0 {
+            if self.entries.len() >= self.capacity {
+                let oldest = self.usage.keys().next().cloned();
+                if let Some(oldest) = oldest {
+                    if let Some(evicted) = self.usage.remove(&oldest) {
+                        self.entries.remove(&evicted);
+                    }
+                }
+            }
+            self.touch(key);
+        }
+
+        Ok(())
+    }
+
+    fn touch(&mut self, key: &CacheKey) {
+        self.counter += 1;
+        if let Some(previous) = self.entries.insert(*key, self.counter) {
+            self.usage.remove(&previous);
+        }
+        self.usage.insert(self.counter, *key);
+    }
+}
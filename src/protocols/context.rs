@@ -0,0 +1,329 @@
+//! A verifier's configuration in one object, with a single entry point.
+//!
+//! `VerificationContext` holds the verifiers of the accepted CRSs together
+//! with the runtime policy: the protocol versions accepted, how many epochs
+//! old a presentation may be, an optional `ReplayGuard` and a
+//! `VerificationMetrics` sink. `verify` runs
+//! the checks of `verify_presentation`, and of `verify_presentation_once`
+//! with a guard, and reports every outcome to the sink.
+//!
+//! The policy is separate from the CRSs: the same registry can be verified
+//! against with a different window or without replay protection, e.g. in
+//! tests, and the epoch advances without touching the verifiers.
+use crate::{
+    protocols::{
+        presentation::{verify_presentation, Presentation, PresentationProtocol},
+        registry::CrsRegistry,
+        replay::{verify_presentation_once, ReplayGuard},
+        VerificationError,
+    },
+    transcript::VerifierChallenge,
+};
+use std::{
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+/// Observes the outcome of every presentation a `VerificationContext`
+/// verifies, e.g. to export counters and latencies.
+pub trait VerificationMetrics {
+    fn on_verification(&self, _outcome: &Result<(), VerificationError>, _elapsed: Duration) {}
+}
+
+/// Ignores the outcomes.
+pub struct NoMetrics;
+
+impl VerificationMetrics for NoMetrics {}
+
+/// Aggregates the outcomes a `VerificationCounters` observes.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct VerificationSummary {
+    pub accepted: u64,
+    pub rejected: u64,
+    /// Rejected as replayed or stale, which an attacker can trigger with
+    /// valid proofs.
+    pub replayed_or_stale: u64,
+    pub total_time: Duration,
+}
+
+/// Collects a `VerificationSummary`, and can be shared with other threads.
+#[derive(Clone, Debug, Default)]
+pub struct VerificationCounters {
+    summary: Arc<Mutex<VerificationSummary>>,
+}
+
+impl VerificationCounters {
+    pub fn new() -> VerificationCounters {
+        VerificationCounters::default()
+    }
+
+    pub fn summary(&self) -> VerificationSummary {
+        self.summary
+            .lock()
+            .map(|summary| summary.clone())
+            .unwrap_or_default()
+    }
+}
+
+impl VerificationMetrics for VerificationCounters {
+    fn on_verification(&self, outcome: &Result<(), VerificationError>, elapsed: Duration) {
+        if let Ok(mut summary) = self.summary.lock() {
+            match outcome {
+                Ok(()) => summary.accepted += 1,
+                Err(VerificationError::Replayed) | Err(VerificationError::StaleEpoch) => {
+                    summary.rejected += 1;
+                    summary.replayed_or_stale += 1;
+                }
+                Err(_) => summary.rejected += 1,
+            }
+            summary.total_time += elapsed;
+        }
+    }
+}
+
+pub struct VerificationContext<V, M: VerificationMetrics = NoMetrics> {
+    pub registry: CrsRegistry<V>,
+    /// Keys of the presentations accepted, if replays are rejected.
+    pub replay_guard: Option<ReplayGuard>,
+    pub metrics: M,
+    epoch: u64,
+    max_epoch_age: u64,
+}
+
+impl<V: PresentationProtocol> VerificationContext<V, NoMetrics> {
+    /// A context verifying with the CRSs of `registry`, at its epoch and
+    /// protocol versions, only accepting presentations from the current
+    /// epoch and without replay protection.
+    pub fn new(registry: CrsRegistry<V>) -> VerificationContext<V, NoMetrics> {
+        VerificationContext {
+            epoch: registry.epoch(),
+            registry,
+            replay_guard: None,
+            metrics: NoMetrics,
+            max_epoch_age: 0,
+        }
+    }
+}
+
+impl<V: PresentationProtocol, M: VerificationMetrics> VerificationContext<V, M> {
+    /// Also accepts presentations from the `max_epoch_age` epochs before the
+    /// current one, e.g. from holders who didn't update their witness yet.
+    /// The epoch of the registry is never lowered, so the window only widens
+    /// as the context advances.
+    pub fn with_max_epoch_age(mut self, max_epoch_age: u64) -> VerificationContext<V, M> {
+        self.max_epoch_age = max_epoch_age;
+        self.apply_epoch();
+        self
+    }
+
+    /// Rejects presentations already accepted, keeping up to `window` keys as
+    /// `ReplayGuard::new` does.
+    pub fn with_replay_guard(mut self, window: usize) -> VerificationContext<V, M> {
+        self.replay_guard = Some(ReplayGuard::new(window, self.oldest_epoch()));
+        self
+    }
+
+    /// Accepts proofs tagged with versions from `version` on, see
+    /// `CrsRegistry::set_min_protocol_version`.
+    pub fn with_min_protocol_version(mut self, version: u8) -> VerificationContext<V, M> {
+        self.registry.set_min_protocol_version(version);
+        self
+    }
+
+    pub fn with_metrics<M2: VerificationMetrics>(self, metrics: M2) -> VerificationContext<V, M2> {
+        VerificationContext {
+            registry: self.registry,
+            replay_guard: self.replay_guard,
+            metrics,
+            epoch: self.epoch,
+            max_epoch_age: self.max_epoch_age,
+        }
+    }
+
+    pub fn epoch(&self) -> u64 {
+        self.epoch
+    }
+
+    /// The oldest epoch accepted in presentations.
+    pub fn oldest_epoch(&self) -> u64 {
+        self.registry.epoch()
+    }
+
+    /// Moves to a newer accumulator epoch, rejecting presentations older than
+    /// the window from then on.
+    pub fn advance_epoch(&mut self, epoch: u64) {
+        if epoch > self.epoch {
+            self.epoch = epoch;
+            self.apply_epoch();
+        }
+    }
+
+    fn apply_epoch(&mut self) {
+        let oldest_epoch = self.epoch.saturating_sub(self.max_epoch_age);
+        self.registry.advance_epoch(oldest_epoch);
+        if let Some(guard) = self.replay_guard.as_mut() {
+            guard.advance_epoch(oldest_epoch);
+        }
+    }
+
+    /// Verifies `presentation` under the policy of the context.
+    pub fn verify(&mut self, presentation: &Presentation<V>) -> Result<(), VerificationError> {
+        let start = Instant::now();
+        let outcome = match self.replay_guard.as_mut() {
+            Some(guard) => verify_presentation_once(&self.registry, guard, presentation),
+            None => verify_presentation(&self.registry, presentation),
+        };
+        self.metrics.on_verification(&outcome, start.elapsed());
+        outcome
+    }
+
+    /// Like `verify`, also rejecting the presentation with
+    /// `VerifierChallengeMismatch` unless it is bound to `challenge`.
+    pub fn verify_with_challenge(
+        &mut self,
+        presentation: &Presentation<V>,
+        challenge: &VerifierChallenge,
+    ) -> Result<(), VerificationError> {
+        if presentation.verifier_challenge.as_ref() != Some(challenge) {
+            let outcome = Err(VerificationError::VerifierChallengeMismatch);
+            self.metrics.on_verification(&outcome, Duration::default());
+            return outcome;
+        }
+        self.verify(presentation)
+    }
+}
+
+#[cfg(all(test, feature = "arkworks"))]
+mod test {
+    use super::{VerificationContext, VerificationCounters, VerificationSummary};
+    use crate::{
+        commitments::Commitment,
+        parameters::Parameters,
+        protocols::{
+            hash_to_prime::snark_range::Protocol as HPProtocol,
+            membership::{transcript::TranscriptVerifierChannel, Protocol, Statement, Witness},
+            presentation::{presentation_aad, Presentation},
+            registry::CrsRegistry,
+            VerificationError,
+        },
+        transcript::{transcript_with_aad, PROTOCOL_VERSION},
+    };
+    use accumulator::{group::Rsa2048, AccumulatorWithoutHashToPrime};
+    use ark_bls12_381::{Bls12_381, G1Projective};
+    use rand::thread_rng;
+    use rug::rand::RandState;
+    use rug::Integer;
+    use std::cell::RefCell;
+
+    type MembershipProtocol = Protocol<Rsa2048, G1Projective, HPProtocol<Bls12_381>>;
+
+    #[test]
+    fn test_verification_context() {
+        let params = Parameters::from_security_level(128).unwrap();
+        let mut rng1 = RandState::new();
+        rng1.seed(&Integer::from(13));
+        let mut rng2 = thread_rng();
+
+        let crs = MembershipProtocol::setup(&params, &mut rng1, &mut rng2)
+            .unwrap()
+            .crs;
+        let protocol = MembershipProtocol::from_crs(&crs);
+
+        let value = Integer::from(Integer::u_pow_u(2, params.hash_to_prime_bits as u32))
+            - &Integer::from(245);
+        let randomness = Integer::from(5);
+        let accum =
+            accumulator::Accumulator::<Rsa2048, Integer, AccumulatorWithoutHashToPrime>::empty()
+                .add_with_proof(&[value.clone()]);
+        let statement = Statement {
+            c_p: accum.0.value,
+            c_e_q: crs
+                .crs_modeq
+                .pedersen_commitment_parameters
+                .commit(&value, &randomness)
+                .unwrap(),
+        };
+
+        let proof_transcript = RefCell::new(transcript_with_aad(
+            b"membership",
+            &presentation_aad(3, b"session 1"),
+        ));
+        let mut verifier_channel = TranscriptVerifierChannel::new(&crs, &proof_transcript);
+        protocol
+            .prove(
+                &mut verifier_channel,
+                &mut rng1,
+                &mut rng2,
+                &statement,
+                &Witness {
+                    e: value,
+                    r_q: randomness,
+                    w: accum.1.witness.0.value,
+                },
+            )
+            .unwrap();
+        let proof = verifier_channel.proof().unwrap();
+
+        let c_p = statement.c_p.clone();
+        let new_registry = || {
+            let mut registry = CrsRegistry::<MembershipProtocol>::new(b"membership");
+            let crs_digest = registry
+                .register(MembershipProtocol::from_crs(&crs))
+                .unwrap();
+            registry.set_accumulator(3, &c_p);
+            (registry, crs_digest)
+        };
+        let (registry, crs_digest) = new_registry();
+        let presentation =
+            Presentation::<MembershipProtocol>::new(statement, proof, crs_digest, 3, b"session 1")
+                .unwrap();
+
+        let counters = VerificationCounters::new();
+        let mut context = VerificationContext::new(registry)
+            .with_max_epoch_age(1)
+            .with_replay_guard(16)
+            .with_metrics(counters.clone());
+        context.advance_epoch(3);
+        context.verify(&presentation).unwrap();
+        assert!(matches!(
+            context.verify(&presentation),
+            Err(VerificationError::Replayed)
+        ));
+
+        // the presentation is still in the window, and still replayed
+        context.advance_epoch(4);
+        assert_eq!(context.oldest_epoch(), 3);
+        assert!(matches!(
+            context.verify(&presentation),
+            Err(VerificationError::Replayed)
+        ));
+        context.advance_epoch(5);
+        assert!(matches!(
+            context.verify(&presentation),
+            Err(VerificationError::StaleEpoch)
+        ));
+
+        let summary = counters.summary();
+        assert_eq!(
+            summary,
+            VerificationSummary {
+                accepted: 1,
+                rejected: 3,
+                replayed_or_stale: 3,
+                total_time: summary.total_time,
+            }
+        );
+
+        // without a guard, a presentation verifies as often as it's sent
+        let mut context = VerificationContext::new(new_registry().0);
+        context.verify(&presentation).unwrap();
+        context.verify(&presentation).unwrap();
+
+        let mut context = VerificationContext::new(new_registry().0)
+            .with_min_protocol_version(PROTOCOL_VERSION + 1);
+        assert!(matches!(
+            context.verify(&presentation),
+            Err(VerificationError::UnsupportedVersion)
+        ));
+    }
+}
@@ -2,12 +2,26 @@
 use crate::{
     commitments::{integer::IntegerCommitment, Commitment},
     parameters::Parameters,
-    protocols::{CRSError, ProofError, VerificationError},
-    utils::{random_symmetric_range, ConvertibleUnknownOrderGroup},
+    protocols::{
+        blinding::{BlindingHistory, BlindingRanges},
+        cache::CacheKeyBuilder,
+        statement::{StatementDigest, StatementHasher},
+        validate_soundness, CRSError, ProofError, VerificationError,
+    },
+    utils::{
+        exp_signed, integer_to_signed_bytes, modulus::validate_group_modulus,
+        random_symmetric_range, ConvertibleUnknownOrderGroup,
+    },
 };
 use channel::{CoprimeProverChannel, CoprimeVerifierChannel};
 use rug::rand::MutRandState;
 use rug::Integer;
+#[cfg(feature = "compressed")]
+use std::cell::RefCell;
+use std::cmp::Ordering;
+use std::hash::{Hash, Hasher};
+#[cfg(feature = "compressed")]
+use transcript::TranscriptProtocolCoprime;
 
 pub mod channel;
 pub mod transcript;
@@ -23,10 +37,46 @@ pub struct Statement<G: ConvertibleUnknownOrderGroup> {
     pub acc: G::Elem,
 }
 
+impl<G: ConvertibleUnknownOrderGroup> Clone for Statement<G> {
+    fn clone(&self) -> Self {
+        Self {
+            c_e: self.c_e.clone(),
+            acc: self.acc.clone(),
+        }
+    }
+}
+
+impl<G: ConvertibleUnknownOrderGroup> PartialEq for Statement<G> {
+    fn eq(&self, other: &Self) -> bool {
+        self.c_e == other.c_e && self.acc == other.acc
+    }
+}
+
+impl<G: ConvertibleUnknownOrderGroup> Eq for Statement<G> {}
+
+impl<G: ConvertibleUnknownOrderGroup> Hash for Statement<G> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.c_e.hash(state);
+        self.acc.hash(state);
+    }
+}
+
+impl<G: ConvertibleUnknownOrderGroup> Statement<G> {
+    /// Stable identifier of the statement, see `protocols::statement`.
+    pub fn digest(&self) -> StatementDigest {
+        let mut hasher = StatementHasher::new(b"coprime");
+        hasher.append_group_element::<G>(b"c_e", &self.c_e);
+        hasher.append_group_element::<G>(b"acc", &self.acc);
+        hasher.finish()
+    }
+}
+
 pub struct Witness<G: ConvertibleUnknownOrderGroup> {
     pub e: Integer,
     pub r: Integer,
     pub d: G::Elem,
+    /// Bezout coefficient of the accumulator, with `|b| < 2^hash_to_prime_bits`.
+    /// The cofactor computed by the accumulator satisfies `|b| < e`.
     pub b: Integer,
 }
 
@@ -68,19 +118,113 @@ pub struct Proof<G: ConvertibleUnknownOrderGroup> {
     pub message3: Message3,
 }
 
+impl<G: ConvertibleUnknownOrderGroup> Proof<G> {
+    pub fn append_to_cache_key(&self, key: &mut CacheKeyBuilder) {
+        key.append_group_element::<G>(b"c_a", &self.message1.c_a);
+        key.append_group_element::<G>(b"c_r_a", &self.message1.c_r_a);
+        key.append_group_element::<G>(b"c_b_cap", &self.message1.c_b_cap);
+        key.append_group_element::<G>(b"c_rho_b_cap", &self.message1.c_rho_b_cap);
+        key.append_group_element::<G>(b"alpha2", &self.message2.alpha2);
+        key.append_group_element::<G>(b"alpha3", &self.message2.alpha3);
+        key.append_group_element::<G>(b"alpha4", &self.message2.alpha4);
+        key.append_group_element::<G>(b"alpha5", &self.message2.alpha5);
+        key.append_group_element::<G>(b"alpha6", &self.message2.alpha6);
+        key.append_group_element::<G>(b"alpha7", &self.message2.alpha7);
+        key.append_integer(b"s_b", &self.message3.s_b);
+        key.append_integer(b"s_e", &self.message3.s_e);
+        key.append_integer(b"s_rho_b_cap", &self.message3.s_rho_b_cap);
+        key.append_integer(b"s_r", &self.message3.s_r);
+        key.append_integer(b"s_r_a", &self.message3.s_r_a);
+        key.append_integer(b"s_r_a_prime", &self.message3.s_r_a_prime);
+        key.append_integer(b"s_rho_b_cap_prime", &self.message3.s_rho_b_cap_prime);
+        key.append_integer(b"s_beta", &self.message3.s_beta);
+        key.append_integer(b"s_delta", &self.message3.s_delta);
+    }
+
+    /// Size in bytes, with group elements encoded by `G::elem_to_bytes` and
+    /// integers by `integer_to_signed_bytes`.
+    pub fn serialized_size(&self) -> usize {
+        let elements = [
+            &self.message1.c_a,
+            &self.message1.c_r_a,
+            &self.message1.c_b_cap,
+            &self.message1.c_rho_b_cap,
+            &self.message2.alpha2,
+            &self.message2.alpha3,
+            &self.message2.alpha4,
+            &self.message2.alpha5,
+            &self.message2.alpha6,
+            &self.message2.alpha7,
+        ];
+        let integers = [
+            &self.message3.s_b,
+            &self.message3.s_e,
+            &self.message3.s_rho_b_cap,
+            &self.message3.s_r,
+            &self.message3.s_r_a,
+            &self.message3.s_r_a_prime,
+            &self.message3.s_rho_b_cap_prime,
+            &self.message3.s_beta,
+            &self.message3.s_delta,
+        ];
+        elements
+            .iter()
+            .map(|e| G::elem_to_bytes(e).len())
+            .sum::<usize>()
+            + integers
+                .iter()
+                .map(|i| integer_to_signed_bytes(i).len())
+                .sum::<usize>()
+    }
+}
+
+/// A proof without the alphas, which are recomputed from the challenge and
+/// the responses.
+#[cfg(feature = "compressed")]
+#[derive(Clone)]
+pub struct CompressedProof<G: ConvertibleUnknownOrderGroup> {
+    pub message1: Message1<G>,
+    pub challenge: Integer,
+    pub message3: Message3,
+}
+
+/// The CRS is private so that it can't be changed without recomputing
+/// `s_e_bound`, use `from_crs` to switch to another one.
 pub struct Protocol<G: ConvertibleUnknownOrderGroup> {
-    pub crs: CRSCoprime<G>,
+    crs: CRSCoprime<G>,
+    /// Bound on `s_e` and `s_b`, computed once per protocol rather than once
+    /// per proof.
+    s_e_bound: Integer,
+}
+
+/// Checks coprime supports `G`. RSA groups always are, and class groups only
+/// with the `class` feature, as coprime's soundness over them rests on
+/// assumptions the paper doesn't prove: the adaptive root and low order
+/// assumptions in the class group.
+pub fn validate_coprime_group<G: ConvertibleUnknownOrderGroup>() -> Result<(), CRSError> {
+    if G::rsa_modulus().is_ok() || cfg!(feature = "class") {
+        Ok(())
+    } else {
+        Err(CRSError::UnsupportedGroupForCoprime)
+    }
 }
 
 impl<G: ConvertibleUnknownOrderGroup> Protocol<G> {
     pub fn from_crs(crs: &CRSCoprime<G>) -> Result<Protocol<G>, CRSError> {
-        let modulus = G::rsa_modulus().map_err(|_| CRSError::InvalidParameters)?;
-        if crs.parameters.security_soundness + 1 >= crs.parameters.hash_to_prime_bits
-            || crs.parameters.security_soundness >= modulus / 2
-        {
+        validate_coprime_group::<G>()?;
+        if crs.parameters.security_soundness + 1 >= crs.parameters.hash_to_prime_bits {
             return Err(CRSError::InvalidParameters);
         }
-        Ok(Protocol { crs: crs.clone() })
+        validate_soundness::<G>(&crs.parameters)?;
+        validate_group_modulus::<G>(&crs.parameters).map_err(|_| CRSError::InvalidParameters)?;
+        Ok(Protocol {
+            crs: crs.clone(),
+            s_e_bound: BlindingRanges::new::<G>(&crs.parameters).exponent_response_bound(),
+        })
+    }
+
+    pub fn crs(&self) -> &CRSCoprime<G> {
+        &self.crs
     }
 
     pub fn prove<R: MutRandState, C: CoprimeVerifierChannel<G>>(
@@ -90,13 +234,37 @@ impl<G: ConvertibleUnknownOrderGroup> Protocol<G> {
         statement: &Statement<G>,
         witness: &Witness<G>,
     ) -> Result<(), ProofError> {
-        let r_a = random_symmetric_range(rng, &(G::order_upper_bound() / 2));
-        let r_a_prime = random_symmetric_range(rng, &(G::order_upper_bound() / 2));
-        let rho_b_cap = random_symmetric_range(rng, &(G::order_upper_bound() / 2));
-        let rho_b_cap_prime = random_symmetric_range(rng, &(G::order_upper_bound() / 2));
+        self.prove_with_history(
+            verifier_channel,
+            rng,
+            statement,
+            witness,
+            &mut BlindingHistory::new(0),
+        )
+    }
+
+    /// Proves while recording the blinding values in `history`, failing if
+    /// they were used before.
+    pub fn prove_with_history<R: MutRandState, C: CoprimeVerifierChannel<G>>(
+        &self,
+        verifier_channel: &mut C,
+        rng: &mut R,
+        statement: &Statement<G>,
+        witness: &Witness<G>,
+        history: &mut BlindingHistory,
+    ) -> Result<(), ProofError> {
+        // the blinding of b only hides values of hash_to_prime_bits bits
+        if witness.b.significant_bits() > self.crs.parameters.hash_to_prime_bits as u32 {
+            return Err(ProofError::WitnessOutOfRange);
+        }
+        let ranges = BlindingRanges::new::<G>(&self.crs.parameters);
+        let r_a = random_symmetric_range(rng, &ranges.randomness);
+        let r_a_prime = random_symmetric_range(rng, &ranges.randomness);
+        let rho_b_cap = random_symmetric_range(rng, &ranges.randomness);
+        let rho_b_cap_prime = random_symmetric_range(rng, &ranges.randomness);
         let c_a = G::op(
             &witness.d,
-            &G::exp(&self.crs.integer_commitment_parameters.h, &r_a),
+            &exp_signed::<G>(&self.crs.integer_commitment_parameters.h, &r_a),
         );
         let c_r_a = self
             .crs
@@ -116,37 +284,37 @@ impl<G: ConvertibleUnknownOrderGroup> Protocol<G> {
             c_b_cap,
             c_rho_b_cap,
         };
-        verifier_channel.send_message1(&message1)?;
 
-        let r_b_e_range = Integer::from(Integer::u_pow_u(
-            2,
-            (self.crs.parameters.security_zk
-                + self.crs.parameters.security_soundness
-                + self.crs.parameters.hash_to_prime_bits) as u32,
-        ));
-        let r_b = random_symmetric_range(rng, &r_b_e_range);
-        let r_e = random_symmetric_range(rng, &r_b_e_range);
-
-        let r_r_range = G::order_upper_bound() / 2
-            * Integer::from(Integer::u_pow_u(
-                2,
-                (self.crs.parameters.security_zk + self.crs.parameters.security_soundness) as u32,
-            ));
-        let r_rho_b_cap = random_symmetric_range(rng, &r_r_range);
-        let r_r = random_symmetric_range(rng, &r_r_range);
-        let r_r_a = random_symmetric_range(rng, &r_r_range);
-        let r_r_a_prime = random_symmetric_range(rng, &r_r_range);
-        let r_rho_b_cap_prime = random_symmetric_range(rng, &r_r_range);
-
-        let r_beta_delta_range = G::order_upper_bound() / 2
-            * Integer::from(Integer::u_pow_u(
-                2,
-                (self.crs.parameters.security_zk
-                    + self.crs.parameters.security_soundness
-                    + self.crs.parameters.hash_to_prime_bits) as u32,
-            ));
-        let r_beta = random_symmetric_range(rng, &r_beta_delta_range);
-        let r_delta = random_symmetric_range(rng, &r_beta_delta_range);
+        let r_b = random_symmetric_range(rng, &ranges.exponent);
+        let r_e = random_symmetric_range(rng, &ranges.exponent);
+
+        let r_rho_b_cap = random_symmetric_range(rng, &ranges.randomness_mask);
+        let r_r = random_symmetric_range(rng, &ranges.randomness_mask);
+        let r_r_a = random_symmetric_range(rng, &ranges.randomness_mask);
+        let r_r_a_prime = random_symmetric_range(rng, &ranges.randomness_mask);
+        let r_rho_b_cap_prime = random_symmetric_range(rng, &ranges.randomness_mask);
+
+        let r_beta = random_symmetric_range(rng, &ranges.product_mask);
+        let r_delta = random_symmetric_range(rng, &ranges.product_mask);
+        history.record(
+            b"coprime",
+            &[
+                &r_a,
+                &r_a_prime,
+                &rho_b_cap,
+                &rho_b_cap_prime,
+                &r_b,
+                &r_e,
+                &r_rho_b_cap,
+                &r_r,
+                &r_r_a,
+                &r_r_a_prime,
+                &r_rho_b_cap_prime,
+                &r_beta,
+                &r_delta,
+            ],
+        )?;
+        verifier_channel.send_message1(&message1)?;
 
         let alpha2 = integer_commitment_c_b_cap.commit(&r_b, &r_rho_b_cap)?;
         let alpha3 = self.crs.integer_commitment_parameters.commit(&r_e, &r_r)?;
@@ -160,7 +328,7 @@ impl<G: ConvertibleUnknownOrderGroup> Protocol<G> {
         let alpha5 = integer_commitment_alpha5.commit(&r_e, &r_beta)?;
 
         let alpha6 = G::op(
-            &G::exp(&message1.c_r_a, &r_e),
+            &exp_signed::<G>(&message1.c_r_a, &r_e),
             &self
                 .crs
                 .integer_commitment_parameters
@@ -216,21 +384,65 @@ impl<G: ConvertibleUnknownOrderGroup> Protocol<G> {
         let message2 = prover_channel.receive_message2()?;
         let c = prover_channel.generate_and_send_challenge()?;
         let message3 = prover_channel.receive_message3()?;
+        let expected_message2 = self.expected_message2(statement, &message1, &c, &message3)?;
+
+        // non-short-circuiting, as in root
+        if (expected_message2.alpha2 == message2.alpha2)
+            & (expected_message2.alpha3 == message2.alpha3)
+            & (expected_message2.alpha4 == message2.alpha4)
+            & (expected_message2.alpha5 == message2.alpha5)
+            & (expected_message2.alpha6 == message2.alpha6)
+            & (expected_message2.alpha7 == message2.alpha7)
+            & self.is_exponent_response_in_range(&message3.s_e)
+            & self.is_exponent_response_in_range(&message3.s_b)
+        {
+            Ok(())
+        } else {
+            Err(VerificationError::VerificationFailed)
+        }
+    }
+
+    /// Receives a proof without checking it, advancing `prover_channel`
+    /// through the same messages and challenge as `verify`.
+    pub fn receive_unverified<C: CoprimeProverChannel<G>>(
+        &self,
+        prover_channel: &mut C,
+    ) -> Result<Proof<G>, VerificationError> {
+        let message1 = prover_channel.receive_message1()?;
+        let message2 = prover_channel.receive_message2()?;
+        prover_channel.generate_and_send_challenge()?;
+        let message3 = prover_channel.receive_message3()?;
+        Ok(Proof {
+            message1,
+            message2,
+            message3,
+        })
+    }
+
+    /// Recomputes the alphas the verifier expects given the challenge and the
+    /// responses of the prover.
+    pub fn expected_message2(
+        &self,
+        statement: &Statement<G>,
+        message1: &Message1<G>,
+        c: &Integer,
+        message3: &Message3,
+    ) -> Result<Message2<G>, VerificationError> {
         let integer_commitment_alpha2 =
             IntegerCommitment::<G>::new(&statement.acc, &self.crs.integer_commitment_parameters.h);
         let expected_alpha2 = G::op(
-            &G::exp(&message1.c_b_cap, &c),
+            &exp_signed::<G>(&message1.c_b_cap, c),
             &integer_commitment_alpha2.commit(&message3.s_b, &message3.s_rho_b_cap)?,
         );
         let expected_alpha3 = G::op(
-            &G::exp(&statement.c_e, &c),
+            &exp_signed::<G>(&statement.c_e, c),
             &self
                 .crs
                 .integer_commitment_parameters
                 .commit(&message3.s_e, &message3.s_r)?,
         );
         let expected_alpha4 = G::op(
-            &G::exp(&message1.c_r_a, &c),
+            &exp_signed::<G>(&message1.c_r_a, c),
             &self
                 .crs
                 .integer_commitment_parameters
@@ -239,52 +451,88 @@ impl<G: ConvertibleUnknownOrderGroup> Protocol<G> {
         let integer_commitment_alpha5 =
             IntegerCommitment::<G>::new(&message1.c_a, &G::inv(&message1.c_b_cap));
         let expected_alpha5 = G::op(
-            &integer_commitment_alpha5.commit(&message3.s_e, &c)?,
+            &integer_commitment_alpha5.commit(&message3.s_e, c)?,
             &self
                 .crs
                 .integer_commitment_parameters
-                .commit(&c, &message3.s_beta)?,
+                .commit(c, &message3.s_beta)?,
         );
         let integer_commitment_alpha6 =
             IntegerCommitment::<G>::new(&message1.c_r_a, &G::inv(&message1.c_rho_b_cap));
         let expected_alpha6 = G::op(
-            &integer_commitment_alpha6.commit(&message3.s_e, &c)?,
+            &integer_commitment_alpha6.commit(&message3.s_e, c)?,
             &self
                 .crs
                 .integer_commitment_parameters
                 .commit(&message3.s_beta, &message3.s_delta)?,
         );
         let expected_alpha7 = G::op(
-            &G::exp(&message1.c_rho_b_cap, &c),
+            &exp_signed::<G>(&message1.c_rho_b_cap, c),
             &self
                 .crs
                 .integer_commitment_parameters
                 .commit(&message3.s_rho_b_cap, &message3.s_rho_b_cap_prime)?,
         );
-        let s_e_expected_right = Integer::from(Integer::u_pow_u(
-            2,
-            (self.crs.parameters.security_zk
-                + self.crs.parameters.security_soundness
-                + self.crs.parameters.hash_to_prime_bits
-                + 1) as u32,
-        ));
 
-        let s_e_expected_left: Integer = -s_e_expected_right.clone();
-        let is_s_e_in_range =
-            message3.s_e >= s_e_expected_left && message3.s_e <= s_e_expected_right;
-
-        if expected_alpha2 == message2.alpha2
-            && expected_alpha3 == message2.alpha3
-            && expected_alpha4 == message2.alpha4
-            && expected_alpha5 == message2.alpha5
-            && expected_alpha6 == message2.alpha6
-            && expected_alpha7 == message2.alpha7
-            && is_s_e_in_range
+        Ok(Message2 {
+            alpha2: expected_alpha2,
+            alpha3: expected_alpha3,
+            alpha4: expected_alpha4,
+            alpha5: expected_alpha5,
+            alpha6: expected_alpha6,
+            alpha7: expected_alpha7,
+        })
+    }
+
+    /// Checks a response for an exponent of `hash_to_prime_bits` bits, i.e.
+    /// `s_e` or `s_b`.
+    fn is_exponent_response_in_range(&self, s: &Integer) -> bool {
+        s.cmp_abs(&self.s_e_bound) != Ordering::Greater
+    }
+
+    /// Restores the alphas of a compressed proof, so it can be verified as a
+    /// regular proof. Since the verifier rederives the challenge from the
+    /// restored alphas, a wrong challenge makes verification fail.
+    #[cfg(feature = "compressed")]
+    pub fn decompress(
+        &self,
+        statement: &Statement<G>,
+        proof: &CompressedProof<G>,
+    ) -> Result<Proof<G>, VerificationError> {
+        let message2 = self.expected_message2(
+            statement,
+            &proof.message1,
+            &proof.challenge,
+            &proof.message3,
+        )?;
+        Ok(Proof {
+            message1: proof.message1.clone(),
+            message2,
+            message3: proof.message3.clone(),
+        })
+    }
+
+    /// Verifies a compressed proof, checking the challenge it contains is the
+    /// one derived from the transcript.
+    #[cfg(feature = "compressed")]
+    pub fn verify_compressed<T: TranscriptProtocolCoprime<G>>(
+        &self,
+        transcript: &RefCell<T>,
+        statement: &Statement<G>,
+        proof: &CompressedProof<G>,
+    ) -> Result<(), VerificationError> {
+        let decompressed = self.decompress(statement, proof)?;
+        let c = decompressed.recompute_challenge(&self.crs, transcript)?;
+        if c != proof.challenge {
+            return Err(VerificationError::ChallengeMismatch);
+        }
+        if !self.is_exponent_response_in_range(&proof.message3.s_e)
+            || !self.is_exponent_response_in_range(&proof.message3.s_b)
         {
-            Ok(())
-        } else {
-            Err(VerificationError::VerificationFailed)
+            return Err(VerificationError::VerificationFailed);
         }
+
+        Ok(())
     }
 }
 
@@ -297,7 +545,9 @@ mod test {
         protocols::{
             coprime::transcript::{TranscriptProverChannel, TranscriptVerifierChannel},
             hash_to_prime::snark_range::Protocol as HPProtocol,
+            ProofError,
         },
+        utils::exp_signed,
     };
     use accumulator::{
         group::{Group, Rsa2048},
@@ -359,16 +609,37 @@ mod test {
         let d = non_mem_proof.d.clone();
         let b = non_mem_proof.b;
         assert_eq!(
-            Rsa2048::op(&Rsa2048::exp(&d, &value), &Rsa2048::exp(&acc, &b)),
+            Rsa2048::op(&Rsa2048::exp(&d, &value), &exp_signed::<Rsa2048>(&acc, &b)),
             protocol.crs.integer_commitment_parameters.g
         );
 
         let proof_transcript = RefCell::new(Transcript::new(b"coprime"));
         let mut verifier_channel = TranscriptVerifierChannel::new(&crs, &proof_transcript);
+        // an equivalent witness with b out of range is rejected
+        let shift = Integer::from(Integer::u_pow_u(2, params.hash_to_prime_bits as u32));
+        let out_of_range = Witness {
+            e: value.clone(),
+            r: randomness.clone(),
+            d: Rsa2048::op(&d, &exp_signed::<Rsa2048>(&acc, &-shift.clone())),
+            b: Integer::from(&b + &value * shift),
+        };
+        assert_eq!(
+            Rsa2048::op(
+                &Rsa2048::exp(&out_of_range.d, &value),
+                &exp_signed::<Rsa2048>(&acc, &out_of_range.b)
+            ),
+            protocol.crs.integer_commitment_parameters.g
+        );
         let statement = Statement {
             c_e: commitment,
             acc,
         };
+        assert!(matches!(
+            protocol.prove(&mut verifier_channel, &mut rng1, &statement, &out_of_range),
+            Err(ProofError::WitnessOutOfRange)
+        ));
+        assert!(!protocol.is_exponent_response_in_range(&out_of_range.b));
+
         protocol
             .prove(
                 &mut verifier_channel,
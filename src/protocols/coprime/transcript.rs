@@ -1,10 +1,15 @@
+#[cfg(feature = "compressed")]
+use crate::protocols::coprime::CompressedProof;
 use crate::{
-    channels::ChannelError,
+    channels::{ChannelError, MessageOrder},
     protocols::coprime::{
         channel::{CoprimeProverChannel, CoprimeVerifierChannel},
         CRSCoprime, Message1, Message2, Message3, Proof,
     },
-    transcript::{TranscriptChannelError, TranscriptProtocolChallenge, TranscriptProtocolInteger},
+    transcript::{
+        versioned_domain_sep, TranscriptChannelError, TranscriptProtocolChallenge,
+        TranscriptProtocolInteger,
+    },
     utils::ConvertibleUnknownOrderGroup,
 };
 use merlin::Transcript;
@@ -19,7 +24,7 @@ pub trait TranscriptProtocolCoprime<G: ConvertibleUnknownOrderGroup>:
 
 impl<G: ConvertibleUnknownOrderGroup> TranscriptProtocolCoprime<G> for Transcript {
     fn coprime_domain_sep(&mut self) {
-        self.append_message(b"dom-sep", b"coprime");
+        self.append_message(b"dom-sep", &versioned_domain_sep(b"coprime"));
     }
 }
 
@@ -30,9 +35,11 @@ pub struct TranscriptVerifierChannel<
 > {
     crs: CRSCoprime<G>,
     transcript: &'a RefCell<T>,
+    order: MessageOrder,
     message1: Option<Message1<G>>,
     message2: Option<Message2<G>>,
     message3: Option<Message3>,
+    challenge: Option<Integer>,
 }
 
 impl<'a, G: ConvertibleUnknownOrderGroup, T: TranscriptProtocolCoprime<G>>
@@ -45,9 +52,11 @@ impl<'a, G: ConvertibleUnknownOrderGroup, T: TranscriptProtocolCoprime<G>>
         TranscriptVerifierChannel {
             crs: crs.clone(),
             transcript,
+            order: MessageOrder::new(),
             message1: None,
             message2: None,
             message3: None,
+            challenge: None,
         }
     }
 
@@ -62,12 +71,26 @@ impl<'a, G: ConvertibleUnknownOrderGroup, T: TranscriptProtocolCoprime<G>>
             Err(TranscriptChannelError::Incomplete)
         }
     }
+
+    #[cfg(feature = "compressed")]
+    pub fn compressed_proof(&self) -> Result<CompressedProof<G>, TranscriptChannelError> {
+        if self.message1.is_some() && self.challenge.is_some() && self.message3.is_some() {
+            Ok(CompressedProof {
+                message1: self.message1.as_ref().unwrap().clone(),
+                challenge: self.challenge.as_ref().unwrap().clone(),
+                message3: self.message3.as_ref().unwrap().clone(),
+            })
+        } else {
+            Err(TranscriptChannelError::Incomplete)
+        }
+    }
 }
 
 impl<'a, G: ConvertibleUnknownOrderGroup, T: TranscriptProtocolCoprime<G>> CoprimeVerifierChannel<G>
     for TranscriptVerifierChannel<'a, G, T>
 {
     fn send_message1(&mut self, message: &Message1<G>) -> Result<(), ChannelError> {
+        self.order.step(0)?;
         let mut transcript = self.transcript.try_borrow_mut()?;
         transcript.coprime_domain_sep();
         transcript.append_integer_point(b"c_a", &message.c_a);
@@ -78,6 +101,7 @@ impl<'a, G: ConvertibleUnknownOrderGroup, T: TranscriptProtocolCoprime<G>> Copri
         Ok(())
     }
     fn send_message2(&mut self, message: &Message2<G>) -> Result<(), ChannelError> {
+        self.order.step(1)?;
         let mut transcript = self.transcript.try_borrow_mut()?;
         transcript.coprime_domain_sep();
         transcript.append_integer_point(b"alpha2", &message.alpha2);
@@ -90,13 +114,17 @@ impl<'a, G: ConvertibleUnknownOrderGroup, T: TranscriptProtocolCoprime<G>> Copri
         Ok(())
     }
     fn send_message3(&mut self, message: &Message3) -> Result<(), ChannelError> {
+        self.order.step(3)?;
         self.message3 = Some(message.clone());
         Ok(())
     }
     fn receive_challenge(&mut self) -> Result<Integer, ChannelError> {
+        self.order.step(2)?;
         let mut transcript = self.transcript.try_borrow_mut()?;
         transcript.coprime_domain_sep();
-        Ok(transcript.challenge_scalar(b"c", self.crs.parameters.security_soundness))
+        let c = transcript.challenge_scalar(b"c", self.crs.parameters.security_soundness);
+        self.challenge = Some(c.clone());
+        Ok(c)
     }
 }
 
@@ -107,6 +135,7 @@ pub struct TranscriptProverChannel<
 > {
     crs: CRSCoprime<G>,
     transcript: &'a RefCell<T>,
+    order: MessageOrder,
     proof: Proof<G>,
 }
 
@@ -121,6 +150,7 @@ impl<'a, G: ConvertibleUnknownOrderGroup, T: TranscriptProtocolCoprime<G>>
         TranscriptProverChannel {
             crs: crs.clone(),
             transcript,
+            order: MessageOrder::new(),
             proof: proof.clone(),
         }
     }
@@ -130,6 +160,7 @@ impl<'a, G: ConvertibleUnknownOrderGroup, T: TranscriptProtocolCoprime<G>> Copri
     for TranscriptProverChannel<'a, G, T>
 {
     fn receive_message1(&mut self) -> Result<Message1<G>, ChannelError> {
+        self.order.step(0)?;
         let mut transcript = self.transcript.try_borrow_mut()?;
         transcript.coprime_domain_sep();
         transcript.append_integer_point(b"c_a", &self.proof.message1.c_a);
@@ -139,6 +170,7 @@ impl<'a, G: ConvertibleUnknownOrderGroup, T: TranscriptProtocolCoprime<G>> Copri
         Ok(self.proof.message1.clone())
     }
     fn receive_message2(&mut self) -> Result<Message2<G>, ChannelError> {
+        self.order.step(1)?;
         let mut transcript = self.transcript.try_borrow_mut()?;
         transcript.coprime_domain_sep();
         transcript.append_integer_point(b"alpha2", &self.proof.message2.alpha2);
@@ -151,11 +183,28 @@ impl<'a, G: ConvertibleUnknownOrderGroup, T: TranscriptProtocolCoprime<G>> Copri
         Ok(self.proof.message2.clone())
     }
     fn receive_message3(&mut self) -> Result<Message3, ChannelError> {
+        self.order.step(3)?;
         Ok(self.proof.message3.clone())
     }
     fn generate_and_send_challenge(&mut self) -> Result<Integer, ChannelError> {
+        self.order.step(2)?;
         let mut transcript = self.transcript.try_borrow_mut()?;
         transcript.coprime_domain_sep();
         Ok(transcript.challenge_scalar(b"c", self.crs.parameters.security_soundness))
     }
 }
+
+impl<G: ConvertibleUnknownOrderGroup> Proof<G> {
+    /// Replays the first two messages of the proof on the transcript and
+    /// returns the challenge the verifier derives from them.
+    pub fn recompute_challenge<T: TranscriptProtocolCoprime<G>>(
+        &self,
+        crs: &CRSCoprime<G>,
+        transcript: &RefCell<T>,
+    ) -> Result<Integer, ChannelError> {
+        let mut prover_channel = TranscriptProverChannel::new(crs, transcript, self);
+        prover_channel.receive_message1()?;
+        prover_channel.receive_message2()?;
+        prover_channel.generate_and_send_challenge()
+    }
+}
@@ -0,0 +1,421 @@
+//! Human-readable reports of membership and nonmembership proofs, for
+//! debugging and support.
+//!
+//! `explain_membership` and `explain_nonmembership` decode a proof with the
+//! `Codec` of the application, verify it over a transcript through a
+//! `LoggingChannel` to recover the challenges, and return a `ProofReport`
+//! with the digests of the CRS and the statement, the size of each sub-proof,
+//! its challenge and how close its exponent responses are to the bounds the
+//! verifier checks. The report is produced for proofs that fail verification
+//! too, with the error, and its `Display` implementation prints it.
+use crate::{
+    parameters::{Parameters, SubProtocol},
+    protocols::{
+        audit::{AuditDirection, AuditEntry, LoggingChannel},
+        blinding::BlindingRanges,
+        boxed::{Codec, CodecError},
+        hash_to_prime::HashToPrimeProtocol,
+        membership::{
+            transcript::TranscriptProverChannel as MembershipTranscriptProverChannel,
+            Proof as MembershipProof, Protocol as MembershipProtocol,
+            Statement as MembershipStatement,
+        },
+        nonmembership::{
+            transcript::TranscriptProverChannel as NonMembershipTranscriptProverChannel,
+            Proof as NonMembershipProof, Protocol as NonMembershipProtocol,
+            Statement as NonMembershipStatement,
+        },
+        registry::CrsDigest,
+        statement::{digest_to_hex, StatementDigest},
+        VerificationError,
+    },
+    utils::{
+        curve::{CurveError, CurvePointProjective},
+        signed_bytes_to_integer, ConvertibleUnknownOrderGroup,
+    },
+};
+use merlin::Transcript;
+use rug::Integer;
+use std::{cell::RefCell, fmt};
+
+quick_error! {
+    #[derive(Debug)]
+    pub enum ExplainError {
+        CodecError(err: CodecError) {
+            from()
+        }
+        CurveError(err: CurveError) {
+            from()
+        }
+    }
+}
+
+/// The size of an exponent response against the bound the verifier checks.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ResponseMargin {
+    pub name: &'static str,
+    pub bits: u32,
+    pub bound_bits: u32,
+}
+
+impl ResponseMargin {
+    /// Bits left before the response reaches the bound, negative when it
+    /// exceeds it.
+    pub fn margin(&self) -> i64 {
+        self.bound_bits as i64 - self.bits as i64
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SubProofReport {
+    pub sub_protocol: SubProtocol,
+    pub bytes: usize,
+    /// The challenge of a sigma protocol, `None` for the hash-to-prime proof.
+    pub challenge: Option<Integer>,
+    pub margins: Vec<ResponseMargin>,
+}
+
+#[derive(Debug)]
+pub struct ProofReport {
+    /// `membership` or `nonmembership`.
+    pub protocol: &'static str,
+    pub crs_digest: CrsDigest,
+    pub statement_digest: StatementDigest,
+    pub bytes: usize,
+    pub c_e_bytes: usize,
+    pub sub_proofs: Vec<SubProofReport>,
+    pub verification: Result<(), VerificationError>,
+}
+
+impl fmt::Display for ProofReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "{} proof, {} bytes", self.protocol, self.bytes)?;
+        writeln!(f, "  crs: {}", digest_to_hex(&self.crs_digest))?;
+        writeln!(f, "  statement: {}", digest_to_hex(&self.statement_digest))?;
+        writeln!(f, "  c_e: {} bytes", self.c_e_bytes)?;
+        for sub_proof in &self.sub_proofs {
+            write!(
+                f,
+                "  {:?}: {} bytes",
+                sub_proof.sub_protocol, sub_proof.bytes
+            )?;
+            if let Some(c) = &sub_proof.challenge {
+                write!(f, ", challenge {:x}", c)?;
+            }
+            writeln!(f)?;
+            for m in &sub_proof.margins {
+                writeln!(
+                    f,
+                    "    {}: {} bits, bound {} bits, margin {}",
+                    m.name,
+                    m.bits,
+                    m.bound_bits,
+                    m.margin()
+                )?;
+            }
+        }
+        match &self.verification {
+            Ok(()) => writeln!(f, "  verification: ok"),
+            Err(e) => writeln!(f, "  verification: failed, {:?}", e),
+        }
+    }
+}
+
+fn challenge(entries: &[AuditEntry], sub_protocol: &str) -> Option<Integer> {
+    entries
+        .iter()
+        .find(|e| e.direction == AuditDirection::Challenge && e.sub_protocol == sub_protocol)
+        .and_then(|e| e.values.first())
+        .and_then(|(_, bytes)| signed_bytes_to_integer(bytes))
+}
+
+fn margins<G: ConvertibleUnknownOrderGroup>(
+    parameters: &Parameters,
+    responses: &[(&'static str, &Integer)],
+) -> Vec<ResponseMargin> {
+    let bound_bits = BlindingRanges::new::<G>(parameters)
+        .exponent_response_bound()
+        .significant_bits();
+    responses
+        .iter()
+        .map(|(name, s)| ResponseMargin {
+            name,
+            bits: s.significant_bits(),
+            bound_bits,
+        })
+        .collect()
+}
+
+/// Decodes `proof` and reports on it, verifying it over `transcript`, which
+/// has to be in the state the prover started from.
+pub fn explain_membership<G, P, HP, C>(
+    protocol: &MembershipProtocol<G, P, HP>,
+    codec: &C,
+    statement: &MembershipStatement<G, P>,
+    proof: &[u8],
+    transcript: Transcript,
+) -> Result<ProofReport, ExplainError>
+where
+    G: ConvertibleUnknownOrderGroup,
+    P: CurvePointProjective,
+    HP: HashToPrimeProtocol<P>,
+    C: Codec<MembershipProof<G, P, HP>>,
+{
+    let bytes = proof.len();
+    let proof: MembershipProof<G, P, HP> = codec.decode(proof)?;
+    let crs = &protocol.crs;
+
+    let transcript = RefCell::new(transcript);
+    let mut entries: Vec<AuditEntry> = vec![];
+    let verification = protocol.verify(
+        &mut LoggingChannel::new(
+            MembershipTranscriptProverChannel::new(crs, &transcript, &proof),
+            &mut entries,
+        ),
+        statement,
+    );
+
+    let sub_proofs = vec![
+        SubProofReport {
+            sub_protocol: SubProtocol::Root,
+            bytes: proof.proof_root.serialized_size(),
+            challenge: challenge(&entries, "root"),
+            margins: margins::<G>(
+                &crs.crs_root.parameters,
+                &[("s_e", &proof.proof_root.message3.s_e)],
+            ),
+        },
+        SubProofReport {
+            sub_protocol: SubProtocol::ModEq,
+            bytes: proof.proof_modeq.serialized_size()?,
+            challenge: challenge(&entries, "modeq"),
+            margins: margins::<G>(
+                &crs.crs_modeq.parameters,
+                &[("s_e", &proof.proof_modeq.message2.s_e)],
+            ),
+        },
+        SubProofReport {
+            sub_protocol: SubProtocol::HashToPrime,
+            bytes: HP::proof_to_bytes(&proof.proof_hash_to_prime)?.len(),
+            challenge: None,
+            margins: vec![],
+        },
+    ];
+
+    Ok(ProofReport {
+        protocol: "membership",
+        crs_digest: crs.digest()?,
+        statement_digest: statement.digest()?,
+        bytes,
+        c_e_bytes: G::elem_to_bytes(&proof.c_e).len(),
+        sub_proofs,
+        verification,
+    })
+}
+
+/// Decodes `proof` and reports on it, verifying it over `transcript`, which
+/// has to be in the state the prover started from.
+pub fn explain_nonmembership<G, P, HP, C>(
+    protocol: &NonMembershipProtocol<G, P, HP>,
+    codec: &C,
+    statement: &NonMembershipStatement<G, P>,
+    proof: &[u8],
+    transcript: Transcript,
+) -> Result<ProofReport, ExplainError>
+where
+    G: ConvertibleUnknownOrderGroup,
+    P: CurvePointProjective,
+    HP: HashToPrimeProtocol<P>,
+    C: Codec<NonMembershipProof<G, P, HP>>,
+{
+    let bytes = proof.len();
+    let proof: NonMembershipProof<G, P, HP> = codec.decode(proof)?;
+    let crs = &protocol.crs;
+
+    let transcript = RefCell::new(transcript);
+    let mut entries: Vec<AuditEntry> = vec![];
+    let verification = protocol.verify(
+        &mut LoggingChannel::new(
+            NonMembershipTranscriptProverChannel::new(crs, &transcript, &proof),
+            &mut entries,
+        ),
+        statement,
+    );
+
+    let sub_proofs = vec![
+        SubProofReport {
+            sub_protocol: SubProtocol::Coprime,
+            bytes: proof.proof_coprime.serialized_size(),
+            challenge: challenge(&entries, "coprime"),
+            margins: margins::<G>(
+                &crs.crs_coprime.parameters,
+                &[
+                    ("s_e", &proof.proof_coprime.message3.s_e),
+                    ("s_b", &proof.proof_coprime.message3.s_b),
+                ],
+            ),
+        },
+        SubProofReport {
+            sub_protocol: SubProtocol::ModEq,
+            bytes: proof.proof_modeq.serialized_size()?,
+            challenge: challenge(&entries, "modeq"),
+            margins: margins::<G>(
+                &crs.crs_modeq.parameters,
+                &[("s_e", &proof.proof_modeq.message2.s_e)],
+            ),
+        },
+        SubProofReport {
+            sub_protocol: SubProtocol::HashToPrime,
+            bytes: HP::proof_to_bytes(&proof.proof_hash_to_prime)?.len(),
+            challenge: None,
+            margins: vec![],
+        },
+    ];
+
+    Ok(ProofReport {
+        protocol: "nonmembership",
+        crs_digest: crs.digest()?,
+        statement_digest: statement.digest()?,
+        bytes,
+        c_e_bytes: G::elem_to_bytes(&proof.c_e).len(),
+        sub_proofs,
+        verification,
+    })
+}
+
+#[cfg(all(test, feature = "arkworks"))]
+mod test {
+    use super::explain_membership;
+    use crate::{
+        commitments::Commitment,
+        parameters::{Parameters, SubProtocol},
+        protocols::{
+            boxed::{Codec, CodecError},
+            hash_to_prime::snark_range::Protocol as HPProtocol,
+            membership::{
+                transcript::TranscriptVerifierChannel, Proof, Protocol, Statement, Witness,
+            },
+        },
+    };
+    use accumulator::{group::Rsa2048, AccumulatorWithoutHashToPrime};
+    use ark_bls12_381::{Bls12_381, G1Projective};
+    use merlin::Transcript;
+    use rand::thread_rng;
+    use rug::rand::RandState;
+    use rug::Integer;
+    use std::cell::RefCell;
+
+    type TestProof = Proof<Rsa2048, G1Projective, HPProtocol<Bls12_381>>;
+
+    /// Decodes any bytes to the proof it holds.
+    struct ProofCodec(TestProof);
+
+    impl Codec<TestProof> for ProofCodec {
+        fn encode(&self, _: &TestProof) -> Result<Vec<u8>, CodecError> {
+            Ok(vec![0u8; 8])
+        }
+
+        fn decode(&self, bytes: &[u8]) -> Result<TestProof, CodecError> {
+            if bytes.len() != 8 {
+                return Err(CodecError::InvalidEncoding);
+            }
+            Ok(self.0.clone())
+        }
+    }
+
+    #[test]
+    fn test_explain_membership() {
+        let params = Parameters::from_security_level(128).unwrap();
+        let mut rng1 = RandState::new();
+        rng1.seed(&Integer::from(13));
+        let mut rng2 = thread_rng();
+
+        let crs = Protocol::<Rsa2048, G1Projective, HPProtocol<Bls12_381>>::setup(
+            &params, &mut rng1, &mut rng2,
+        )
+        .unwrap()
+        .crs;
+        let protocol = Protocol::from_crs(&crs);
+
+        let value = Integer::from(Integer::u_pow_u(2, params.hash_to_prime_bits as u32))
+            - &Integer::from(245);
+        let randomness = Integer::from(5);
+        let accum =
+            accumulator::Accumulator::<Rsa2048, Integer, AccumulatorWithoutHashToPrime>::empty()
+                .add_with_proof(&[value.clone()]);
+        let statement = Statement {
+            c_p: accum.0.value,
+            c_e_q: crs
+                .crs_modeq
+                .pedersen_commitment_parameters
+                .commit(&value, &randomness)
+                .unwrap(),
+        };
+
+        let proof_transcript = RefCell::new(Transcript::new(b"membership"));
+        let mut verifier_channel = TranscriptVerifierChannel::new(&crs, &proof_transcript);
+        protocol
+            .prove(
+                &mut verifier_channel,
+                &mut rng1,
+                &mut rng2,
+                &statement,
+                &Witness {
+                    e: value,
+                    r_q: randomness,
+                    w: accum.1.witness.0.value,
+                },
+            )
+            .unwrap();
+        let codec = ProofCodec(verifier_channel.proof().unwrap());
+
+        let report = explain_membership(
+            &protocol,
+            &codec,
+            &statement,
+            &[0u8; 8],
+            Transcript::new(b"membership"),
+        )
+        .unwrap();
+        assert!(report.verification.is_ok());
+        assert_eq!(report.crs_digest, crs.digest().unwrap());
+        assert_eq!(report.statement_digest, statement.digest().unwrap());
+        let sub_protocols = report
+            .sub_proofs
+            .iter()
+            .map(|s| s.sub_protocol)
+            .collect::<Vec<_>>();
+        assert_eq!(
+            sub_protocols,
+            vec![
+                SubProtocol::Root,
+                SubProtocol::ModEq,
+                SubProtocol::HashToPrime
+            ]
+        );
+        for sub_proof in &report.sub_proofs[..2] {
+            assert!(sub_proof.challenge.is_some());
+            assert!(sub_proof.margins.iter().all(|m| m.margin() >= 0));
+        }
+        assert!(report.to_string().contains("verification: ok"));
+
+        // a proof on another transcript is reported with its error
+        let report = explain_membership(
+            &protocol,
+            &codec,
+            &statement,
+            &[0u8; 8],
+            Transcript::new(b"other"),
+        )
+        .unwrap();
+        assert!(report.verification.is_err());
+        assert!(explain_membership(
+            &protocol,
+            &codec,
+            &statement,
+            &[0u8; 3],
+            Transcript::new(b"membership")
+        )
+        .is_err());
+    }
+}
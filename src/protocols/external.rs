@@ -0,0 +1,229 @@
+//! Glue for circuits of other proving stacks, e.g. bellman or halo2, that
+//! take the element as a committed input, so that a statement can be proven
+//! with such a circuit for its application logic and with this crate for the
+//! accumulator part.
+//!
+//! The external circuit exposes a Pedersen commitment `c_ext = g_ext^e h_ext^r_ext`
+//! to its input, as its public input or next to its proof, with bases of its
+//! own. The equality proof shows that `c_ext` and the `c_e_q` of a membership
+//! or nonmembership proof commit to the same `e`, by proving knowledge of
+//! `e`, `r_q` and `r_ext` opening both. Both commitments have to be on the
+//! curve `P` of the membership protocol: the bases and commitment of the
+//! external circuit are imported as points of `P`, e.g. with
+//! `PedersenCommitment::from_affine` and the point decoding of the curve
+//! library. The values are equal modulo the order of `P`, and the membership
+//! proof bounds `e` below it.
+//!
+//! The proof is non-interactive and is run on the transcript of the
+//! membership proof, after it, so that it can't be taken from another
+//! session.
+use crate::{
+    commitments::{pedersen::PedersenCommitment, Commitment},
+    protocols::{ProofError, VerificationError},
+    transcript::{versioned_domain_sep, TranscriptProtocolChallenge, TranscriptProtocolCurve},
+    utils::{
+        curve::{CurvePointProjective, Field},
+        integer_to_bigint_mod_q,
+    },
+};
+use merlin::Transcript;
+use rand::{CryptoRng, RngCore};
+use rug::Integer;
+use std::cell::RefCell;
+
+pub trait TranscriptProtocolExternal<P: CurvePointProjective>:
+    TranscriptProtocolCurve<P> + TranscriptProtocolChallenge
+{
+    fn external_domain_sep(&mut self);
+}
+
+impl<P: CurvePointProjective> TranscriptProtocolExternal<P> for Transcript {
+    fn external_domain_sep(&mut self) {
+        self.append_message(b"dom-sep", &versioned_domain_sep(b"external"));
+    }
+}
+
+#[derive(Clone)]
+pub struct Statement<P: CurvePointProjective> {
+    /// The commitment of the membership or nonmembership statement.
+    pub c_e_q: <PedersenCommitment<P> as Commitment>::Instance,
+    /// The commitment exported by the external circuit.
+    pub c_ext: <PedersenCommitment<P> as Commitment>::Instance,
+}
+
+pub struct Witness {
+    pub e: Integer,
+    pub r_q: Integer,
+    pub r_ext: Integer,
+}
+
+#[derive(Clone)]
+pub struct Proof<P: CurvePointProjective> {
+    pub alpha_q: P,
+    pub alpha_ext: P,
+    pub s_e: P::ScalarField,
+    pub s_r_q: P::ScalarField,
+    pub s_r_ext: P::ScalarField,
+}
+
+pub struct Protocol<P: CurvePointProjective> {
+    /// The bases of `c_e_q`, `crs_modeq.pedersen_commitment_parameters` of
+    /// the membership or nonmembership CRS.
+    pub pedersen: PedersenCommitment<P>,
+    /// The bases of the commitment of the external circuit.
+    pub external: PedersenCommitment<P>,
+    pub security_soundness: u16,
+}
+
+impl<P: CurvePointProjective> Protocol<P> {
+    pub fn new(
+        pedersen: &PedersenCommitment<P>,
+        external: &PedersenCommitment<P>,
+        security_soundness: u16,
+    ) -> Protocol<P> {
+        Protocol {
+            pedersen: pedersen.clone(),
+            external: external.clone(),
+            security_soundness,
+        }
+    }
+
+    fn challenge<T: TranscriptProtocolExternal<P>>(
+        &self,
+        transcript: &RefCell<T>,
+        statement: &Statement<P>,
+        alpha_q: &P,
+        alpha_ext: &P,
+    ) -> Result<Integer, VerificationError> {
+        let mut transcript = transcript
+            .try_borrow_mut()
+            .map_err(|_| VerificationError::VerificationFailed)?;
+        transcript.external_domain_sep();
+        transcript.append_curve_point(b"g_ext", &self.external.g)?;
+        transcript.append_curve_point(b"h_ext", &self.external.h)?;
+        transcript.append_curve_point(b"c_e_q", &statement.c_e_q)?;
+        transcript.append_curve_point(b"c_ext", &statement.c_ext)?;
+        transcript.append_curve_point(b"alpha_q", alpha_q)?;
+        transcript.append_curve_point(b"alpha_ext", alpha_ext)?;
+        Ok(transcript.challenge_scalar(b"c", self.security_soundness))
+    }
+
+    pub fn prove<R: RngCore + CryptoRng, T: TranscriptProtocolExternal<P>>(
+        &self,
+        transcript: &RefCell<T>,
+        rng: &mut R,
+        statement: &Statement<P>,
+        witness: &Witness,
+    ) -> Result<Proof<P>, ProofError> {
+        let r_e = P::ScalarField::rand(rng);
+        let r_r_q = P::ScalarField::rand(rng);
+        let r_r_ext = P::ScalarField::rand(rng);
+        let alpha_q = self.pedersen.g.mul(&r_e).add(&self.pedersen.h.mul(&r_r_q));
+        let alpha_ext = self
+            .external
+            .g
+            .mul(&r_e)
+            .add(&self.external.h.mul(&r_r_ext));
+        let c = self
+            .challenge(transcript, statement, &alpha_q, &alpha_ext)
+            .map_err(|_| ProofError::CouldNotCreateProof)?;
+        let c_big = integer_to_bigint_mod_q::<P>(&c)?;
+
+        let e = integer_to_bigint_mod_q::<P>(&witness.e)?;
+        let r_q = integer_to_bigint_mod_q::<P>(&witness.r_q)?;
+        let r_ext = integer_to_bigint_mod_q::<P>(&witness.r_ext)?;
+        Ok(Proof {
+            alpha_q,
+            alpha_ext,
+            s_e: r_e.sub(&e.mul(&c_big)),
+            s_r_q: r_r_q.sub(&r_q.mul(&c_big)),
+            s_r_ext: r_r_ext.sub(&r_ext.mul(&c_big)),
+        })
+    }
+
+    pub fn verify<T: TranscriptProtocolExternal<P>>(
+        &self,
+        transcript: &RefCell<T>,
+        statement: &Statement<P>,
+        proof: &Proof<P>,
+    ) -> Result<(), VerificationError> {
+        let c = self.challenge(transcript, statement, &proof.alpha_q, &proof.alpha_ext)?;
+        let c_big = integer_to_bigint_mod_q::<P>(&c)?;
+        let expected_alpha_q = self
+            .pedersen
+            .g
+            .mul(&proof.s_e)
+            .add(&self.pedersen.h.mul(&proof.s_r_q))
+            .add(&statement.c_e_q.mul(&c_big));
+        let expected_alpha_ext = self
+            .external
+            .g
+            .mul(&proof.s_e)
+            .add(&self.external.h.mul(&proof.s_r_ext))
+            .add(&statement.c_ext.mul(&c_big));
+        // both equations are checked, whichever fails
+        let q_ok = expected_alpha_q == proof.alpha_q;
+        let ext_ok = expected_alpha_ext == proof.alpha_ext;
+        if q_ok & ext_ok {
+            Ok(())
+        } else {
+            Err(VerificationError::VerificationFailed)
+        }
+    }
+}
+
+#[cfg(all(test, feature = "arkworks"))]
+mod test {
+    use super::{Protocol, Statement, Witness};
+    use crate::commitments::{pedersen::PedersenCommitment, Commitment};
+    use ark_bls12_381::G1Projective;
+    use merlin::Transcript;
+    use rand::thread_rng;
+    use rug::Integer;
+    use std::cell::RefCell;
+
+    #[test]
+    fn test_external_commitment_equality() {
+        let mut rng = thread_rng();
+        let pedersen = PedersenCommitment::<G1Projective>::setup(&mut rng);
+        // e.g. the commitment keys of a bellman circuit over BLS12-381
+        let external = PedersenCommitment::<G1Projective>::setup(&mut rng);
+        let protocol = Protocol::new(&pedersen, &external, 128);
+
+        let e = Integer::from(1234567);
+        let witness = Witness {
+            e: e.clone(),
+            r_q: Integer::from(5),
+            r_ext: Integer::from(17),
+        };
+        let statement = Statement {
+            c_e_q: pedersen.commit(&e, &witness.r_q).unwrap(),
+            c_ext: external.commit(&e, &witness.r_ext).unwrap(),
+        };
+
+        let proof_transcript = RefCell::new(Transcript::new(b"external"));
+        let proof = protocol
+            .prove(&proof_transcript, &mut rng, &statement, &witness)
+            .unwrap();
+        let verification_transcript = RefCell::new(Transcript::new(b"external"));
+        protocol
+            .verify(&verification_transcript, &statement, &proof)
+            .unwrap();
+
+        // the external circuit committed to another value
+        let statement = Statement {
+            c_ext: external
+                .commit(&Integer::from(7654321), &witness.r_ext)
+                .unwrap(),
+            ..statement
+        };
+        let proof_transcript = RefCell::new(Transcript::new(b"external"));
+        let proof = protocol
+            .prove(&proof_transcript, &mut rng, &statement, &witness)
+            .unwrap();
+        let verification_transcript = RefCell::new(Transcript::new(b"external"));
+        assert!(protocol
+            .verify(&verification_transcript, &statement, &proof)
+            .is_err());
+    }
+}
@@ -0,0 +1,496 @@
+//! Witness extraction by rewinding, to test the special soundness of the
+//! sigma protocols.
+//!
+//! The prover is run twice with the same randomness, so it sends the same
+//! first messages, and the channel answers each run with a different
+//! challenge. Given the two accepting transcripts, the witness is recovered
+//! from the differences of the responses, which catches regressions in the
+//! response equations.
+use crate::{
+    channels::ChannelError,
+    commitments::Commitment,
+    protocols::{
+        coprime::{
+            self, channel::CoprimeVerifierChannel, Protocol as CoprimeProtocol,
+            Statement as CoprimeStatement, Witness as CoprimeWitness,
+        },
+        modeq::{
+            self, channel::ModEqVerifierChannel, Protocol as ModEqProtocol,
+            Statement as ModEqStatement, Witness as ModEqWitness,
+        },
+        root::{
+            self, channel::RootVerifierChannel, Protocol as RootProtocol,
+            Statement as RootStatement, Witness as RootWitness,
+        },
+        ProofError,
+    },
+    utils::{
+        bigint_to_integer,
+        curve::{CurvePointProjective, Field},
+        exp_signed, ConvertibleUnknownOrderGroup,
+    },
+};
+use rand::{rngs::StdRng, SeedableRng};
+use rug::{rand::RandState, Integer};
+
+quick_error! {
+    #[derive(Debug)]
+    pub enum ExtractionError {
+        SameChallenge {}
+        DifferentFirstMessages {}
+        NotDivisible {}
+        InvalidWitness {}
+        ProofError(err: ProofError) {
+            from()
+        }
+    }
+}
+
+/// Records the messages of a prover and answers with a fixed challenge.
+pub struct RewindingChannel<M1, M2, M3> {
+    challenge: Integer,
+    message1: Option<M1>,
+    message2: Option<M2>,
+    message3: Option<M3>,
+}
+
+impl<M1, M2, M3> RewindingChannel<M1, M2, M3> {
+    pub fn new(challenge: &Integer) -> RewindingChannel<M1, M2, M3> {
+        RewindingChannel {
+            challenge: challenge.clone(),
+            message1: None,
+            message2: None,
+            message3: None,
+        }
+    }
+}
+
+pub type RootRewindingChannel<G> =
+    RewindingChannel<root::Message1<G>, root::Message2<G>, root::Message3>;
+pub type CoprimeRewindingChannel<G> =
+    RewindingChannel<coprime::Message1<G>, coprime::Message2<G>, coprime::Message3>;
+pub type ModEqRewindingChannel<G, P> =
+    RewindingChannel<modeq::Message1<G, P>, modeq::Message2<P>, ()>;
+
+impl<G: ConvertibleUnknownOrderGroup> RootRewindingChannel<G> {
+    pub fn proof(&self) -> Option<root::Proof<G>> {
+        Some(root::Proof {
+            message1: self.message1.clone()?,
+            message2: self.message2.clone()?,
+            message3: self.message3.clone()?,
+        })
+    }
+}
+
+impl<G: ConvertibleUnknownOrderGroup> CoprimeRewindingChannel<G> {
+    pub fn proof(&self) -> Option<coprime::Proof<G>> {
+        Some(coprime::Proof {
+            message1: self.message1.clone()?,
+            message2: self.message2.clone()?,
+            message3: self.message3.clone()?,
+        })
+    }
+}
+
+impl<G: ConvertibleUnknownOrderGroup, P: CurvePointProjective> ModEqRewindingChannel<G, P> {
+    pub fn proof(&self) -> Option<modeq::Proof<G, P>> {
+        Some(modeq::Proof {
+            message1: self.message1.clone()?,
+            message2: self.message2.clone()?,
+        })
+    }
+}
+
+impl<G: ConvertibleUnknownOrderGroup> RootVerifierChannel<G> for RootRewindingChannel<G> {
+    fn send_message1(&mut self, message: &root::Message1<G>) -> Result<(), ChannelError> {
+        self.message1 = Some(message.clone());
+        Ok(())
+    }
+    fn send_message2(&mut self, message: &root::Message2<G>) -> Result<(), ChannelError> {
+        self.message2 = Some(message.clone());
+        Ok(())
+    }
+    fn send_message3(&mut self, message: &root::Message3) -> Result<(), ChannelError> {
+        self.message3 = Some(message.clone());
+        Ok(())
+    }
+    fn receive_challenge(&mut self) -> Result<Integer, ChannelError> {
+        Ok(self.challenge.clone())
+    }
+}
+
+impl<G: ConvertibleUnknownOrderGroup> CoprimeVerifierChannel<G> for CoprimeRewindingChannel<G> {
+    fn send_message1(&mut self, message: &coprime::Message1<G>) -> Result<(), ChannelError> {
+        self.message1 = Some(message.clone());
+        Ok(())
+    }
+    fn send_message2(&mut self, message: &coprime::Message2<G>) -> Result<(), ChannelError> {
+        self.message2 = Some(message.clone());
+        Ok(())
+    }
+    fn send_message3(&mut self, message: &coprime::Message3) -> Result<(), ChannelError> {
+        self.message3 = Some(message.clone());
+        Ok(())
+    }
+    fn receive_challenge(&mut self) -> Result<Integer, ChannelError> {
+        Ok(self.challenge.clone())
+    }
+}
+
+impl<G: ConvertibleUnknownOrderGroup, P: CurvePointProjective> ModEqVerifierChannel<G, P>
+    for ModEqRewindingChannel<G, P>
+{
+    fn send_message1(&mut self, message: &modeq::Message1<G, P>) -> Result<(), ChannelError> {
+        self.message1 = Some(message.clone());
+        Ok(())
+    }
+    fn send_message2(&mut self, message: &modeq::Message2<P>) -> Result<(), ChannelError> {
+        self.message2 = Some(message.clone());
+        Ok(())
+    }
+    fn receive_challenge(&mut self) -> Result<Integer, ChannelError> {
+        Ok(self.challenge.clone())
+    }
+}
+
+/// Computes `(s_1 - s_2) / (c_2 - c_1)`, which is the witness value `x` when
+/// `s_i = r - c_i * x`.
+fn extract_value(
+    s_1: &Integer,
+    s_2: &Integer,
+    c_1: &Integer,
+    c_2: &Integer,
+) -> Result<Integer, ExtractionError> {
+    let numerator = Integer::from(s_1 - s_2);
+    let denominator = Integer::from(c_2 - c_1);
+    if !numerator.is_divisible(&denominator) {
+        return Err(ExtractionError::NotDivisible);
+    }
+    Ok(numerator.div_exact(&denominator))
+}
+
+fn check_challenges(c_1: &Integer, c_2: &Integer) -> Result<(), ExtractionError> {
+    if c_1 == c_2 {
+        Err(ExtractionError::SameChallenge)
+    } else {
+        Ok(())
+    }
+}
+
+/// Extracts a root witness from two transcripts sharing the first messages.
+pub fn extract_root<G: ConvertibleUnknownOrderGroup>(
+    protocol: &RootProtocol<G>,
+    statement: &RootStatement<G>,
+    (proof_1, c_1): (&root::Proof<G>, &Integer),
+    (proof_2, c_2): (&root::Proof<G>, &Integer),
+) -> Result<RootWitness<G>, ExtractionError> {
+    check_challenges(c_1, c_2)?;
+    let (m_1, m_2) = (&proof_1.message1, &proof_2.message1);
+    let (a_1, a_2) = (&proof_1.message2, &proof_2.message2);
+    if m_1.c_w != m_2.c_w
+        || m_1.c_r != m_2.c_r
+        || a_1.alpha1 != a_2.alpha1
+        || a_1.alpha2 != a_2.alpha2
+        || a_1.alpha3 != a_2.alpha3
+        || a_1.alpha4 != a_2.alpha4
+    {
+        return Err(ExtractionError::DifferentFirstMessages);
+    }
+
+    let (s_1, s_2) = (&proof_1.message3, &proof_2.message3);
+    let e = extract_value(&s_1.s_e, &s_2.s_e, c_1, c_2)?;
+    let r = extract_value(&s_1.s_r, &s_2.s_r, c_1, c_2)?;
+    let r_2 = extract_value(&s_1.s_r_2, &s_2.s_r_2, c_1, c_2)?;
+    // c_w = w * h^r_2
+    let h = &protocol.crs().integer_commitment_parameters.h;
+    let w = G::op(&m_1.c_w, &exp_signed::<G>(&G::inv(h), &r_2));
+
+    protocol
+        .crs()
+        .integer_commitment_parameters
+        .open(&statement.c_e, &e, &r)
+        .map_err(|_| ExtractionError::InvalidWitness)?;
+    if exp_signed::<G>(&w, &e) != statement.acc {
+        return Err(ExtractionError::InvalidWitness);
+    }
+
+    Ok(RootWitness { e, r, w })
+}
+
+/// Extracts a coprime witness from two transcripts sharing the first
+/// messages.
+pub fn extract_coprime<G: ConvertibleUnknownOrderGroup>(
+    protocol: &CoprimeProtocol<G>,
+    statement: &CoprimeStatement<G>,
+    (proof_1, c_1): (&coprime::Proof<G>, &Integer),
+    (proof_2, c_2): (&coprime::Proof<G>, &Integer),
+) -> Result<CoprimeWitness<G>, ExtractionError> {
+    check_challenges(c_1, c_2)?;
+    let (m_1, m_2) = (&proof_1.message1, &proof_2.message1);
+    let (a_1, a_2) = (&proof_1.message2, &proof_2.message2);
+    if m_1.c_a != m_2.c_a
+        || m_1.c_r_a != m_2.c_r_a
+        || m_1.c_b_cap != m_2.c_b_cap
+        || m_1.c_rho_b_cap != m_2.c_rho_b_cap
+        || a_1.alpha2 != a_2.alpha2
+        || a_1.alpha3 != a_2.alpha3
+        || a_1.alpha4 != a_2.alpha4
+        || a_1.alpha5 != a_2.alpha5
+        || a_1.alpha6 != a_2.alpha6
+        || a_1.alpha7 != a_2.alpha7
+    {
+        return Err(ExtractionError::DifferentFirstMessages);
+    }
+
+    let (s_1, s_2) = (&proof_1.message3, &proof_2.message3);
+    let e = extract_value(&s_1.s_e, &s_2.s_e, c_1, c_2)?;
+    let r = extract_value(&s_1.s_r, &s_2.s_r, c_1, c_2)?;
+    let b = extract_value(&s_1.s_b, &s_2.s_b, c_1, c_2)?;
+    let r_a = extract_value(&s_1.s_r_a, &s_2.s_r_a, c_1, c_2)?;
+    // c_a = d * h^r_a
+    let crs = &protocol.crs().integer_commitment_parameters;
+    let d = G::op(&m_1.c_a, &exp_signed::<G>(&G::inv(&crs.h), &r_a));
+
+    crs.open(&statement.c_e, &e, &r)
+        .map_err(|_| ExtractionError::InvalidWitness)?;
+    if G::op(
+        &exp_signed::<G>(&d, &e),
+        &exp_signed::<G>(&statement.acc, &b),
+    ) != crs.g
+    {
+        return Err(ExtractionError::InvalidWitness);
+    }
+
+    Ok(CoprimeWitness { e, r, d, b })
+}
+
+/// Extracts a modeq witness from two transcripts sharing the first message.
+pub fn extract_modeq<G: ConvertibleUnknownOrderGroup, P: CurvePointProjective>(
+    protocol: &ModEqProtocol<G, P>,
+    statement: &ModEqStatement<G, P>,
+    (proof_1, c_1): (&modeq::Proof<G, P>, &Integer),
+    (proof_2, c_2): (&modeq::Proof<G, P>, &Integer),
+) -> Result<ModEqWitness, ExtractionError> {
+    check_challenges(c_1, c_2)?;
+    let (m_1, m_2) = (&proof_1.message1, &proof_2.message1);
+    if m_1.alpha1 != m_2.alpha1 || m_1.alpha2 != m_2.alpha2 {
+        return Err(ExtractionError::DifferentFirstMessages);
+    }
+
+    let (s_1, s_2) = (&proof_1.message2, &proof_2.message2);
+    let e = extract_value(&s_1.s_e, &s_2.s_e, c_1, c_2)?;
+    let r = extract_value(&s_1.s_r, &s_2.s_r, c_1, c_2)?;
+    // the Pedersen randomness is only defined modulo q
+    let q = P::ScalarField::modulus();
+    let s_r_q_diff =
+        Integer::from(bigint_to_integer::<P>(&s_1.s_r_q) - bigint_to_integer::<P>(&s_2.s_r_q));
+    let c_diff_inv = Integer::from(c_2 - c_1)
+        .invert(&q)
+        .map_err(|_| ExtractionError::NotDivisible)?;
+    let r_q = Integer::from((s_r_q_diff * c_diff_inv).rem_euc_ref(&q));
+
+    protocol
+        .crs
+        .integer_commitment_parameters
+        .open(&statement.c_e, &e, &r)
+        .map_err(|_| ExtractionError::InvalidWitness)?;
+    let e_q = Integer::from(e.rem_euc_ref(&q));
+    protocol
+        .crs
+        .pedersen_commitment_parameters
+        .open(&statement.c_e_q, &e_q, &r_q)
+        .map_err(|_| ExtractionError::InvalidWitness)?;
+
+    Ok(ModEqWitness { e, r, r_q })
+}
+
+/// Runs the root prover twice with the same randomness, answering with
+/// `c_1` and `c_2`, and extracts the witness from the two transcripts.
+pub fn rewind_root<G: ConvertibleUnknownOrderGroup>(
+    protocol: &RootProtocol<G>,
+    statement: &RootStatement<G>,
+    witness: &RootWitness<G>,
+    c_1: &Integer,
+    c_2: &Integer,
+) -> Result<RootWitness<G>, ExtractionError> {
+    let mut proofs = vec![];
+    for c in &[c_1, c_2] {
+        let mut rng = RandState::new();
+        rng.seed(&Integer::from(REWIND_SEED));
+        let mut channel = RootRewindingChannel::<G>::new(c);
+        protocol.prove(&mut channel, &mut rng, statement, witness)?;
+        proofs.push(channel.proof().ok_or(ProofError::CouldNotCreateProof)?);
+    }
+
+    extract_root(protocol, statement, (&proofs[0], c_1), (&proofs[1], c_2))
+}
+
+/// Runs the coprime prover twice with the same randomness, answering with
+/// `c_1` and `c_2`, and extracts the witness from the two transcripts.
+pub fn rewind_coprime<G: ConvertibleUnknownOrderGroup>(
+    protocol: &CoprimeProtocol<G>,
+    statement: &CoprimeStatement<G>,
+    witness: &CoprimeWitness<G>,
+    c_1: &Integer,
+    c_2: &Integer,
+) -> Result<CoprimeWitness<G>, ExtractionError> {
+    let mut proofs = vec![];
+    for c in &[c_1, c_2] {
+        let mut rng = RandState::new();
+        rng.seed(&Integer::from(REWIND_SEED));
+        let mut channel = CoprimeRewindingChannel::<G>::new(c);
+        protocol.prove(&mut channel, &mut rng, statement, witness)?;
+        proofs.push(channel.proof().ok_or(ProofError::CouldNotCreateProof)?);
+    }
+
+    extract_coprime(protocol, statement, (&proofs[0], c_1), (&proofs[1], c_2))
+}
+
+/// Runs the modeq prover twice with the same randomness, answering with
+/// `c_1` and `c_2`, and extracts the witness from the two transcripts.
+pub fn rewind_modeq<G: ConvertibleUnknownOrderGroup, P: CurvePointProjective>(
+    protocol: &ModEqProtocol<G, P>,
+    statement: &ModEqStatement<G, P>,
+    witness: &ModEqWitness,
+    c_1: &Integer,
+    c_2: &Integer,
+) -> Result<ModEqWitness, ExtractionError> {
+    let mut proofs = vec![];
+    for c in &[c_1, c_2] {
+        let mut rng1 = RandState::new();
+        rng1.seed(&Integer::from(REWIND_SEED));
+        let mut rng2 = StdRng::seed_from_u64(REWIND_SEED);
+        let mut channel = ModEqRewindingChannel::<G, P>::new(c);
+        protocol.prove(&mut channel, &mut rng1, &mut rng2, statement, witness)?;
+        proofs.push(channel.proof().ok_or(ProofError::CouldNotCreateProof)?);
+    }
+
+    extract_modeq(protocol, statement, (&proofs[0], c_1), (&proofs[1], c_2))
+}
+
+const REWIND_SEED: u64 = 0x5eed;
+
+#[cfg(all(test, feature = "arkworks"))]
+mod test {
+    use super::{rewind_coprime, rewind_modeq, rewind_root, ExtractionError};
+    use crate::{
+        commitments::Commitment,
+        parameters::Parameters,
+        protocols::{
+            coprime::{
+                Protocol as CoprimeProtocol, Statement as CoprimeStatement,
+                Witness as CoprimeWitness,
+            },
+            hash_to_prime::snark_range::Protocol as HPProtocol,
+            modeq::{
+                Protocol as ModEqProtocol, Statement as ModEqStatement, Witness as ModEqWitness,
+            },
+            nonmembership::Protocol as NonMembershipProtocol,
+            root::{Protocol as RootProtocol, Statement as RootStatement, Witness as RootWitness},
+        },
+    };
+    use accumulator::{group::Rsa2048, AccumulatorWithoutHashToPrime};
+    use ark_bls12_381::{Bls12_381, G1Projective};
+    use rand::thread_rng;
+    use rug::rand::RandState;
+    use rug::Integer;
+
+    const LARGE_PRIMES: [u64; 3] = [
+        553_525_575_239_331_913,
+        12_702_637_924_034_044_211,
+        378_373_571_372_703_133,
+    ];
+
+    #[test]
+    fn test_extraction() {
+        let params = Parameters::from_security_level(128).unwrap();
+        let mut rng1 = RandState::new();
+        rng1.seed(&Integer::from(13));
+        let mut rng2 = thread_rng();
+        let crs = NonMembershipProtocol::<Rsa2048, G1Projective, HPProtocol<Bls12_381>>::setup(
+            &params, &mut rng1, &mut rng2,
+        )
+        .unwrap()
+        .crs;
+        let (c_1, c_2) = (Integer::from(1234), Integer::from(5678));
+
+        let value = Integer::from(LARGE_PRIMES[0]);
+        let randomness = Integer::from(5);
+        let c_e = crs
+            .crs_modeq
+            .integer_commitment_parameters
+            .commit(&value, &randomness)
+            .unwrap();
+
+        let accum =
+            accumulator::Accumulator::<Rsa2048, Integer, AccumulatorWithoutHashToPrime>::empty();
+        let accum = accum.add_with_proof(&[value.clone()]);
+        let root = RootProtocol::from_crs(&crate::protocols::root::CRSRoot {
+            parameters: params.clone(),
+            integer_commitment_parameters: crs.crs_coprime.integer_commitment_parameters.clone(),
+        })
+        .unwrap();
+        let root_statement = RootStatement {
+            c_e: c_e.clone(),
+            acc: accum.0.value,
+        };
+        let root_witness = RootWitness {
+            e: value.clone(),
+            r: randomness.clone(),
+            w: accum.1.witness.0.value,
+        };
+        let extracted = rewind_root(&root, &root_statement, &root_witness, &c_1, &c_2).unwrap();
+        assert_eq!(
+            (extracted.e, extracted.r),
+            (value.clone(), randomness.clone())
+        );
+        match rewind_root(&root, &root_statement, &root_witness, &c_1, &c_1) {
+            Err(ExtractionError::SameChallenge) => (),
+            _ => panic!("extraction should need two challenges"),
+        }
+
+        let acc_set = LARGE_PRIMES
+            .iter()
+            .skip(1)
+            .map(|p| Integer::from(*p))
+            .collect::<Vec<_>>();
+        let accum =
+            accumulator::Accumulator::<Rsa2048, Integer, AccumulatorWithoutHashToPrime>::empty()
+                .add(&acc_set);
+        let non_mem_proof = accum
+            .prove_nonmembership(&acc_set, &[value.clone()])
+            .unwrap();
+        let coprime = CoprimeProtocol::from_crs(&crs.crs_coprime).unwrap();
+        let coprime_statement = CoprimeStatement {
+            c_e: c_e.clone(),
+            acc: accum.value,
+        };
+        let coprime_witness = CoprimeWitness {
+            e: value.clone(),
+            r: randomness.clone(),
+            d: non_mem_proof.d.clone(),
+            b: non_mem_proof.b.clone(),
+        };
+        let extracted =
+            rewind_coprime(&coprime, &coprime_statement, &coprime_witness, &c_1, &c_2).unwrap();
+        assert_eq!(extracted.b, non_mem_proof.b);
+
+        let modeq = ModEqProtocol::from_crs(&crs.crs_modeq);
+        let randomness_q = Integer::from(9);
+        let modeq_statement = ModEqStatement {
+            c_e,
+            c_e_q: crs
+                .crs_modeq
+                .pedersen_commitment_parameters
+                .commit(&value, &randomness_q)
+                .unwrap(),
+        };
+        let modeq_witness = ModEqWitness {
+            e: value.clone(),
+            r: randomness,
+            r_q: randomness_q.clone(),
+        };
+        let extracted = rewind_modeq(&modeq, &modeq_statement, &modeq_witness, &c_1, &c_2).unwrap();
+        assert_eq!((extracted.e, extracted.r_q), (value, randomness_q));
+    }
+}
@@ -0,0 +1,296 @@
+//! R1CS gadgets checking the curve side of the proofs inside another SNARK,
+//! so that an outer SNARK can recursively verify them.
+//!
+//! The gadgets are over the base field of the curve `C` of the Pedersen
+//! commitments, i.e. in an outer SNARK whose scalar field is that base field,
+//! such as BW6-761 for BLS12-377. Scalars of `C` are in a different field and
+//! are given as bits. `enforce_pedersen_sigma` checks the Pedersen equation of
+//! modeq, `alpha2 = g^s_e h^s_r_q c_e_q^c`, and `enforce_committed_range`
+//! checks the committed element has `required_bit_size` bits, as the range
+//! hash-to-prime does. `ModEqCurveCircuit` puts both together for a modeq
+//! proof.
+//!
+//! The equations over the group of unknown order, of root, coprime and the
+//! integer side of modeq, would need arithmetic modulo the RSA modulus or over
+//! class group forms, which isn't provided. The challenge isn't recomputed
+//! either, as there is no merlin gadget: it's a public input of the circuit,
+//! and so is `alpha2`, the message it is derived from. The outer verifier
+//! recomputes the challenge from the transcript with `alpha2` and checks both
+//! against the public inputs.
+use crate::{
+    protocols::{
+        modeq::{CRSModEq, Proof as ModEqProof, Statement as ModEqStatement},
+        VerificationError,
+    },
+    utils::{integer_to_bigint_mod_q, ConvertibleUnknownOrderGroup},
+};
+use ark_ec::ProjectiveCurve;
+use ark_ff::{BigInteger, PrimeField};
+use ark_r1cs_std::{alloc::AllocVar, boolean::Boolean, eq::EqGadget, groups::CurveVar};
+use ark_relations::r1cs::{ConstraintSynthesizer, ConstraintSystemRef, SynthesisError};
+use rug::Integer;
+use std::marker::PhantomData;
+
+/// The little-endian bits of `scalar`, for `CurveVar::scalar_mul_le`.
+pub fn scalar_to_bits_le<F: PrimeField>(scalar: &F) -> Vec<bool> {
+    scalar.into_repr().to_bits_le()
+}
+
+/// Enforces the big-endian `bits` represent a number of exactly
+/// `required_bit_size` bits.
+pub fn enforce_bit_size<F: PrimeField>(
+    bits: &[Boolean<F>],
+    required_bit_size: u16,
+) -> Result<(), SynthesisError> {
+    let required_bit_size = required_bit_size as usize;
+    if required_bit_size == 0 || required_bit_size > bits.len() {
+        return Err(SynthesisError::Unsatisfiable);
+    }
+    let bits_to_skip = bits.len() - required_bit_size;
+    for b in bits[..bits_to_skip].iter() {
+        b.enforce_equal(&Boolean::constant(false))?;
+    }
+    bits[bits_to_skip].enforce_equal(&Boolean::constant(true))
+}
+
+/// Computes the Pedersen commitment `g^value h^randomness`, with the scalars
+/// as little-endian bits.
+pub fn pedersen_commit_gadget<C: ProjectiveCurve, F: PrimeField, GV: CurveVar<C, F>>(
+    g: &GV,
+    h: &GV,
+    value: &[Boolean<F>],
+    randomness: &[Boolean<F>],
+) -> Result<GV, SynthesisError> {
+    Ok(g.scalar_mul_le(value.iter())? + &h.scalar_mul_le(randomness.iter())?)
+}
+
+/// Enforces `alpha = g^s_value h^s_randomness commitment^c`, the Pedersen
+/// equation of the sigma protocols, with the scalars as little-endian bits.
+pub fn enforce_pedersen_sigma<C: ProjectiveCurve, F: PrimeField, GV: CurveVar<C, F>>(
+    g: &GV,
+    h: &GV,
+    commitment: &GV,
+    alpha: &GV,
+    s_value: &[Boolean<F>],
+    s_randomness: &[Boolean<F>],
+    c: &[Boolean<F>],
+) -> Result<(), SynthesisError> {
+    let expected = pedersen_commit_gadget(g, h, s_value, s_randomness)?
+        + &commitment.scalar_mul_le(c.iter())?;
+    expected.enforce_equal(alpha)
+}
+
+/// Enforces `commitment = g^value h^randomness` with `value` of exactly
+/// `required_bit_size` bits. `value` is big-endian, as for
+/// `enforce_bit_size`, and `randomness` little-endian.
+pub fn enforce_committed_range<C: ProjectiveCurve, F: PrimeField, GV: CurveVar<C, F>>(
+    g: &GV,
+    h: &GV,
+    commitment: &GV,
+    value: &[Boolean<F>],
+    randomness: &[Boolean<F>],
+    required_bit_size: u16,
+) -> Result<(), SynthesisError> {
+    enforce_bit_size(value, required_bit_size)?;
+    let value_le = value.iter().rev().cloned().collect::<Vec<_>>();
+    pedersen_commit_gadget(g, h, &value_le, randomness)?.enforce_equal(commitment)
+}
+
+/// The curve side of a modeq proof for `c_e_q`, and optionally the range of
+/// its element, as a circuit over the base field `F` of `C`.
+///
+/// `g`, `h`, `c_e_q`, `alpha2` and the challenge are public inputs, in this
+/// order, while the responses and the opening of `c_e_q` are witnesses. A
+/// witness `alpha2` would let a prover pick it after the challenge, so it has
+/// to be the one the challenge was derived from.
+pub struct ModEqCurveCircuit<C: ProjectiveCurve, F: PrimeField, GV: CurveVar<C, F>> {
+    pub g: C,
+    pub h: C,
+    pub c_e_q: C,
+    pub alpha2: C,
+    pub c: C::ScalarField,
+    pub s_e: C::ScalarField,
+    pub s_r_q: C::ScalarField,
+    /// The opening `(e, r_q)` of `c_e_q` and the bit size of `e`, for the
+    /// range check.
+    pub range: Option<(C::ScalarField, C::ScalarField, u16)>,
+    _gadget: PhantomData<(F, GV)>,
+}
+
+impl<C: ProjectiveCurve, F: PrimeField, GV: CurveVar<C, F>> ModEqCurveCircuit<C, F, GV> {
+    /// The circuit of a modeq proof with the challenge `c` of its transcript,
+    /// e.g. from `Proof::recompute_challenge`. The responses are reduced
+    /// modulo the order of `C`, which the curve equation only depends on.
+    pub fn from_proof<G: ConvertibleUnknownOrderGroup>(
+        crs: &CRSModEq<G, C>,
+        statement: &ModEqStatement<G, C>,
+        proof: &ModEqProof<G, C>,
+        c: &Integer,
+    ) -> Result<ModEqCurveCircuit<C, F, GV>, VerificationError> {
+        Ok(ModEqCurveCircuit {
+            g: crs.pedersen_commitment_parameters.g,
+            h: crs.pedersen_commitment_parameters.h,
+            c_e_q: statement.c_e_q,
+            alpha2: proof.message1.alpha2,
+            c: integer_to_bigint_mod_q::<C>(c)?,
+            s_e: integer_to_bigint_mod_q::<C>(&proof.message2.s_e)?,
+            s_r_q: proof.message2.s_r_q,
+            range: None,
+            _gadget: PhantomData,
+        })
+    }
+
+    /// Also checks `c_e_q` opens with `r_q` to `e` of `required_bit_size`
+    /// bits.
+    pub fn with_range(
+        self,
+        e: &Integer,
+        r_q: &Integer,
+        required_bit_size: u16,
+    ) -> Result<ModEqCurveCircuit<C, F, GV>, VerificationError> {
+        Ok(ModEqCurveCircuit {
+            range: Some((
+                integer_to_bigint_mod_q::<C>(e)?,
+                integer_to_bigint_mod_q::<C>(r_q)?,
+                required_bit_size,
+            )),
+            ..self
+        })
+    }
+}
+
+impl<C: ProjectiveCurve, F: PrimeField, GV: CurveVar<C, F>> ConstraintSynthesizer<F>
+    for ModEqCurveCircuit<C, F, GV>
+{
+    fn generate_constraints(self, cs: ConstraintSystemRef<F>) -> Result<(), SynthesisError> {
+        let g = GV::new_input(ark_relations::ns!(cs, "g"), || Ok(self.g))?;
+        let h = GV::new_input(ark_relations::ns!(cs, "h"), || Ok(self.h))?;
+        let c_e_q = GV::new_input(ark_relations::ns!(cs, "c_e_q"), || Ok(self.c_e_q))?;
+        let alpha2 = GV::new_input(ark_relations::ns!(cs, "alpha2"), || Ok(self.alpha2))?;
+        let c = Vec::<Boolean<F>>::new_input(ark_relations::ns!(cs, "c"), || {
+            Ok(scalar_to_bits_le(&self.c))
+        })?;
+        let s_e = Vec::<Boolean<F>>::new_witness(ark_relations::ns!(cs, "s_e"), || {
+            Ok(scalar_to_bits_le(&self.s_e))
+        })?;
+        let s_r_q = Vec::<Boolean<F>>::new_witness(ark_relations::ns!(cs, "s_r_q"), || {
+            Ok(scalar_to_bits_le(&self.s_r_q))
+        })?;
+        enforce_pedersen_sigma(&g, &h, &c_e_q, &alpha2, &s_e, &s_r_q, &c)?;
+
+        if let Some((e, r_q, required_bit_size)) = self.range {
+            let e = Vec::<Boolean<F>>::new_witness(ark_relations::ns!(cs, "e"), || {
+                Ok(e.into_repr().to_bits_be())
+            })?;
+            let r_q = Vec::<Boolean<F>>::new_witness(ark_relations::ns!(cs, "r_q"), || {
+                Ok(scalar_to_bits_le(&r_q))
+            })?;
+            enforce_committed_range(&g, &h, &c_e_q, &e, &r_q, required_bit_size)?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(all(test, feature = "arkworks"))]
+mod test {
+    use super::ModEqCurveCircuit;
+    use crate::{
+        commitments::Commitment,
+        parameters::Parameters,
+        protocols::{
+            hash_to_prime::snark_range::Protocol as HPProtocol,
+            modeq::{
+                transcript::TranscriptVerifierChannel, Protocol, Statement as ModEqStatement,
+                Witness as ModEqWitness,
+            },
+        },
+    };
+    use accumulator::group::Rsa2048;
+    use ark_bls12_381::{Bls12_381, Fq, G1Projective, Parameters as Bls12_381Parameters};
+    use ark_r1cs_std::groups::bls12::G1Var;
+    use ark_relations::r1cs::{ConstraintSynthesizer, ConstraintSystem};
+    use merlin::Transcript;
+    use rand::thread_rng;
+    use rug::rand::RandState;
+    use rug::Integer;
+    use std::cell::RefCell;
+
+    type Circuit = ModEqCurveCircuit<G1Projective, Fq, G1Var<Bls12_381Parameters>>;
+
+    #[test]
+    fn test_modeq_curve_circuit() {
+        let params = Parameters::from_security_level(128).unwrap();
+        let mut rng1 = RandState::new();
+        rng1.seed(&Integer::from(13));
+        let mut rng2 = thread_rng();
+
+        let crs = crate::protocols::membership::Protocol::<
+            Rsa2048,
+            G1Projective,
+            HPProtocol<Bls12_381>,
+        >::setup(&params, &mut rng1, &mut rng2)
+        .unwrap()
+        .crs
+        .crs_modeq;
+        let protocol = Protocol::<Rsa2048, G1Projective>::from_crs(&crs);
+
+        let e = Integer::from(Integer::u_pow_u(2, params.hash_to_prime_bits as u32))
+            - &Integer::from(245);
+        let r = Integer::from(5);
+        let r_q = Integer::from(9);
+        let statement = ModEqStatement {
+            c_e: crs.integer_commitment_parameters.commit(&e, &r).unwrap(),
+            c_e_q: crs.pedersen_commitment_parameters.commit(&e, &r_q).unwrap(),
+        };
+        let proof_transcript = RefCell::new(Transcript::new(b"modeq"));
+        let mut verifier_channel = TranscriptVerifierChannel::new(&crs, &proof_transcript);
+        protocol
+            .prove(
+                &mut verifier_channel,
+                &mut rng1,
+                &mut rng2,
+                &statement,
+                &ModEqWitness {
+                    e: e.clone(),
+                    r,
+                    r_q: r_q.clone(),
+                },
+            )
+            .unwrap();
+        let proof = verifier_channel.proof().unwrap();
+        let c = proof
+            .recompute_challenge(&crs, &RefCell::new(Transcript::new(b"modeq")))
+            .unwrap();
+
+        let cs = ConstraintSystem::<Fq>::new_ref();
+        Circuit::from_proof(&crs, &statement, &proof, &c)
+            .unwrap()
+            .with_range(&e, &r_q, params.hash_to_prime_bits)
+            .unwrap()
+            .generate_constraints(cs.clone())
+            .unwrap();
+        assert!(cs.is_satisfied().unwrap());
+
+        // another challenge
+        let cs = ConstraintSystem::<Fq>::new_ref();
+        Circuit::from_proof(&crs, &statement, &proof, &(c + 1))
+            .unwrap()
+            .generate_constraints(cs.clone())
+            .unwrap();
+        assert!(!cs.is_satisfied().unwrap());
+
+        // an element of the wrong size
+        let cs = ConstraintSystem::<Fq>::new_ref();
+        let c = proof
+            .recompute_challenge(&crs, &RefCell::new(Transcript::new(b"modeq")))
+            .unwrap();
+        Circuit::from_proof(&crs, &statement, &proof, &c)
+            .unwrap()
+            .with_range(&e, &r_q, params.hash_to_prime_bits + 1)
+            .unwrap()
+            .generate_constraints(cs.clone())
+            .unwrap();
+        assert!(!cs.is_satisfied().unwrap());
+    }
+}
@@ -6,11 +6,16 @@ use crate::{
     protocols::{
         hash_to_prime::{
             channel::{HashToPrimeProverChannel, HashToPrimeVerifierChannel},
-            CRSHashToPrime, CRSSize, HashToPrimeError, HashToPrimeProtocol, Statement, Witness,
+            CRSHashToPrime, CRSSize, CircuitStats, HashToPrimeError, HashToPrimeProtocol,
+            HashToPrimeSizes, RangeProofProtocol, Statement, Witness,
         },
+        progress::{report, ProgressObserver, Stage},
         ProofError, SetupError, VerificationError,
     },
-    utils::{curve::Field, integer_to_bigint_mod_q, log2},
+    utils::{
+        curve::{CurveError, Field},
+        integer_to_bigint_mod_q, log2,
+    },
 };
 use bulletproofs::{
     r1cs::{ConstraintSystem, LinearCombination, Prover, R1CSError, R1CSProof, Verifier},
@@ -99,18 +104,49 @@ impl HashToPrimeProtocol<RistrettoPoint> for Protocol {
         }
     }
 
-    fn setup<R: Rng>(
+    fn setup_with_observer<R: Rng, O: ProgressObserver>(
         _: &mut R,
         _: &PedersenCommitment<RistrettoPoint>,
         parameters: &Parameters,
+        observer: &O,
     ) -> Result<Self::Parameters, SetupError> {
+        if !report(observer, Stage::HashToPrimeSetup, 0, 1) {
+            return Err(SetupError::Cancelled);
+        }
         let rounded_hash_to_prime_bits = 1 << log2(parameters.hash_to_prime_bits as usize);
+        let bulletproof_gens = BulletproofGens::new(rounded_hash_to_prime_bits, 1);
+        observer.on_progress(Stage::HashToPrimeSetup, 1, 1);
         Ok(BPParameters {
-            bulletproof_gens: BulletproofGens::new(rounded_hash_to_prime_bits, 1),
+            bulletproof_gens,
             transcript: None,
         })
     }
 
+    fn circuit_stats(parameters: &Parameters) -> Result<CircuitStats, SetupError> {
+        // each bit allocates a multiplier with two constraints on it, and the
+        // highest order bit is additionally constrained to be set
+        let bits = parameters.hash_to_prime_bits as usize;
+        Ok(CircuitStats {
+            constraints: 2 * bits + 1,
+            variables: 3 * bits + 1,
+            public_inputs: 1,
+        })
+    }
+
+    fn estimate_sizes(parameters: &Parameters) -> Result<HashToPrimeSizes, SetupError> {
+        let point = RistrettoPoint::identity().compress().as_bytes().len();
+        let bits = parameters.hash_to_prime_bits as usize;
+        // a one-phase proof: a flag byte, eight points, three scalars and the
+        // inner product proof over the multipliers padded to a power of two
+        let inner_product_rounds = bits.next_power_of_two().trailing_zeros() as usize;
+        Ok(HashToPrimeSizes {
+            proof_bytes: 1 + point * (13 + 2 * inner_product_rounds),
+            // the generators of `setup`, counted like `crs_size`
+            verifier_parameters_bytes: point * (1 << log2(bits)),
+            prover_parameters_bytes: 0,
+        })
+    }
+
     fn prove<R: Rng, C: HashToPrimeVerifierChannel<RistrettoPoint, Self>>(
         &self,
         verifier_channel: &mut C,
@@ -118,22 +154,16 @@ impl HashToPrimeProtocol<RistrettoPoint> for Protocol {
         _: &Statement<RistrettoPoint>,
         witness: &Witness,
     ) -> Result<(), ProofError> {
-        let pedersen_gens = PedersenGens {
-            B: self.crs.pedersen_commitment_parameters.g,
-            B_blinding: self.crs.pedersen_commitment_parameters.h,
-        };
+        let pedersen_gens = PedersenGens::from(&self.crs.pedersen_commitment_parameters);
 
         let (proof, _) = {
             let default_transcript = RefCell::new(Transcript::new(b"bp_range_proof"));
-            let prover_transcript = if self.crs.hash_to_prime_parameters.transcript.is_some() {
-                self.crs
-                    .hash_to_prime_parameters
-                    .transcript
-                    .as_ref()
-                    .unwrap()
-            } else {
-                &default_transcript
-            };
+            let prover_transcript = self
+                .crs
+                .hash_to_prime_parameters
+                .transcript
+                .as_ref()
+                .unwrap_or(&default_transcript);
 
             let mut prover_transcript = prover_transcript
                 .try_borrow_mut()
@@ -170,21 +200,15 @@ impl HashToPrimeProtocol<RistrettoPoint> for Protocol {
         prover_channel: &mut C,
         statement: &Statement<RistrettoPoint>,
     ) -> Result<(), VerificationError> {
-        let pedersen_gens = PedersenGens {
-            B: self.crs.pedersen_commitment_parameters.g,
-            B_blinding: self.crs.pedersen_commitment_parameters.h,
-        };
+        let pedersen_gens = PedersenGens::from(&self.crs.pedersen_commitment_parameters);
 
         let default_transcript = RefCell::new(Transcript::new(b"bp_range_proof"));
-        let verifier_transcript = if self.crs.hash_to_prime_parameters.transcript.is_some() {
-            self.crs
-                .hash_to_prime_parameters
-                .transcript
-                .as_ref()
-                .unwrap()
-        } else {
-            &default_transcript
-        };
+        let verifier_transcript = self
+            .crs
+            .hash_to_prime_parameters
+            .transcript
+            .as_ref()
+            .unwrap_or(&default_transcript);
 
         let mut verifier_transcript = verifier_transcript
             .try_borrow_mut()
@@ -212,11 +236,27 @@ impl HashToPrimeProtocol<RistrettoPoint> for Protocol {
         )?)
     }
 
+    fn proof_to_bytes(proof: &Self::Proof) -> Result<Vec<u8>, CurveError> {
+        Ok(proof.to_bytes())
+    }
+
+    fn parameters_to_bytes(parameters: &Self::Parameters) -> Result<Vec<u8>, CurveError> {
+        // the generators are derived from the capacities
+        let gens = &parameters.bulletproof_gens;
+        Ok([
+            (gens.gens_capacity as u64).to_le_bytes(),
+            (gens.party_capacity as u64).to_le_bytes(),
+        ]
+        .concat())
+    }
+
     fn hash_to_prime(&self, e: &Integer) -> Result<(Integer, u64), HashToPrimeError> {
         Ok((e.clone(), 0))
     }
 }
 
+impl RangeProofProtocol<RistrettoPoint> for Protocol {}
+
 #[cfg(test)]
 mod tests {
     use super::{Protocol, Statement, Witness};
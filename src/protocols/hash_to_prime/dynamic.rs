@@ -0,0 +1,439 @@
+//! Hash-to-prime protocol whose backend is chosen at runtime, from
+//! `Parameters::hash_to_prime_backend`, rather than by a type parameter.
+//!
+//! The SNARK backends share their proof and proving key types, so proofs are
+//! serialized the same way whichever backend produced them. Bulletproofs works
+//! on a different curve and can't be one of the backends.
+
+use crate::{
+    channels::ChannelError,
+    commitments::pedersen::PedersenCommitment,
+    parameters::{HashToPrimeBackend, Parameters},
+    protocols::{
+        hash_to_prime::{
+            channel::{HashToPrimeProverChannel, HashToPrimeVerifierChannel},
+            legogro16_sizes,
+            snark_hash::{self, HashToPrimeHashParameters},
+            snark_increment, snark_range, CRSHashToPrime, CRSSize, CircuitStats, HashToPrimeError,
+            HashToPrimeProtocol, HashToPrimeSizes, MemoryReport, MemoryUsage, Statement, Witness,
+        },
+        progress::ProgressObserver,
+        ProofError, SetupError, VerificationError,
+    },
+    utils::curve::CurveError,
+};
+use ark_ec::PairingEngine;
+use rand::{CryptoRng, RngCore};
+use rug::Integer;
+use std::marker::PhantomData;
+
+/// Proving key of the backend selected at setup.
+pub enum DynParameters<E: PairingEngine> {
+    SnarkRange(legogro16::ProvingKey<E>),
+    SnarkHash(legogro16::ProvingKey<E>),
+    SnarkIncrement(legogro16::ProvingKey<E>),
+}
+
+impl<E: PairingEngine> Clone for DynParameters<E> {
+    fn clone(&self) -> Self {
+        match self {
+            DynParameters::SnarkRange(pk) => DynParameters::SnarkRange(pk.clone()),
+            DynParameters::SnarkHash(pk) => DynParameters::SnarkHash(pk.clone()),
+            DynParameters::SnarkIncrement(pk) => DynParameters::SnarkIncrement(pk.clone()),
+        }
+    }
+}
+
+impl<E: PairingEngine> DynParameters<E> {
+    pub fn backend(&self) -> HashToPrimeBackend {
+        match self {
+            DynParameters::SnarkRange(_) => HashToPrimeBackend::SnarkRange,
+            DynParameters::SnarkHash(_) => HashToPrimeBackend::SnarkHash,
+            DynParameters::SnarkIncrement(_) => HashToPrimeBackend::SnarkIncrement,
+        }
+    }
+
+    pub fn proving_key(&self) -> &legogro16::ProvingKey<E> {
+        match self {
+            DynParameters::SnarkRange(pk)
+            | DynParameters::SnarkHash(pk)
+            | DynParameters::SnarkIncrement(pk) => pk,
+        }
+    }
+}
+
+impl<E: PairingEngine> CRSSize for DynParameters<E> {
+    fn crs_size(&self) -> (usize, usize) {
+        self.proving_key().crs_size()
+    }
+}
+
+impl<E: PairingEngine> MemoryUsage for DynParameters<E> {
+    fn memory_report(&self) -> MemoryReport {
+        self.proving_key().memory_report()
+    }
+}
+
+/// Dispatches to the backend whose proving key is in the CRS.
+pub enum DynHashToPrime<E: PairingEngine, H: HashToPrimeHashParameters> {
+    SnarkRange(snark_range::Protocol<E>),
+    SnarkHash(snark_hash::Protocol<E, H>),
+    SnarkIncrement(snark_increment::Protocol<E, H>),
+}
+
+impl<E: PairingEngine, H: HashToPrimeHashParameters> DynHashToPrime<E, H> {
+    pub fn backend(&self) -> HashToPrimeBackend {
+        match self {
+            DynHashToPrime::SnarkRange(_) => HashToPrimeBackend::SnarkRange,
+            DynHashToPrime::SnarkHash(_) => HashToPrimeBackend::SnarkHash,
+            DynHashToPrime::SnarkIncrement(_) => HashToPrimeBackend::SnarkIncrement,
+        }
+    }
+}
+
+fn backend_crs<
+    E: PairingEngine,
+    H: HashToPrimeHashParameters,
+    HP: HashToPrimeProtocol<E::G1Projective>,
+>(
+    crs: &CRSHashToPrime<E::G1Projective, DynHashToPrime<E, H>>,
+    hash_to_prime_parameters: &HP::Parameters,
+) -> CRSHashToPrime<E::G1Projective, HP> {
+    CRSHashToPrime {
+        parameters: crs.parameters.clone(),
+        pedersen_commitment_parameters: crs.pedersen_commitment_parameters.clone(),
+        hash_to_prime_parameters: hash_to_prime_parameters.clone(),
+    }
+}
+
+/// Forwards the proof of a backend to a channel of `DynHashToPrime`.
+struct BackendVerifierChannel<'a, E, H, C> {
+    channel: &'a mut C,
+    protocol_type: PhantomData<(E, H)>,
+}
+
+impl<'a, E, H, C, HP> HashToPrimeVerifierChannel<E::G1Projective, HP>
+    for BackendVerifierChannel<'a, E, H, C>
+where
+    E: PairingEngine,
+    H: HashToPrimeHashParameters,
+    C: HashToPrimeVerifierChannel<E::G1Projective, DynHashToPrime<E, H>>,
+    HP: HashToPrimeProtocol<E::G1Projective, Proof = legogro16::Proof<E>>,
+{
+    fn send_proof(&mut self, proof: &legogro16::Proof<E>) -> Result<(), ChannelError> {
+        self.channel.send_proof(proof)
+    }
+}
+
+/// Forwards the proof received by a channel of `DynHashToPrime` to a backend.
+struct BackendProverChannel<'a, E, H, C> {
+    channel: &'a mut C,
+    protocol_type: PhantomData<(E, H)>,
+}
+
+impl<'a, E, H, C, HP> HashToPrimeProverChannel<E::G1Projective, HP>
+    for BackendProverChannel<'a, E, H, C>
+where
+    E: PairingEngine,
+    H: HashToPrimeHashParameters,
+    C: HashToPrimeProverChannel<E::G1Projective, DynHashToPrime<E, H>>,
+    HP: HashToPrimeProtocol<E::G1Projective, Proof = legogro16::Proof<E>>,
+{
+    fn receive_proof(&mut self) -> Result<legogro16::Proof<E>, ChannelError> {
+        self.channel.receive_proof()
+    }
+}
+
+impl<E: PairingEngine, H: HashToPrimeHashParameters> HashToPrimeProtocol<E::G1Projective>
+    for DynHashToPrime<E, H>
+{
+    type Proof = legogro16::Proof<E>;
+    type Parameters = DynParameters<E>;
+
+    fn from_crs(crs: &CRSHashToPrime<E::G1Projective, Self>) -> DynHashToPrime<E, H> {
+        match &crs.hash_to_prime_parameters {
+            DynParameters::SnarkRange(pk) => DynHashToPrime::SnarkRange(
+                snark_range::Protocol::<E>::from_crs(&backend_crs(crs, pk)),
+            ),
+            DynParameters::SnarkHash(pk) => DynHashToPrime::SnarkHash(
+                snark_hash::Protocol::<E, H>::from_crs(&backend_crs(crs, pk)),
+            ),
+            DynParameters::SnarkIncrement(pk) => DynHashToPrime::SnarkIncrement(
+                snark_increment::Protocol::<E, H>::from_crs(&backend_crs(crs, pk)),
+            ),
+        }
+    }
+
+    fn setup_with_observer<R: RngCore + CryptoRng, O: ProgressObserver>(
+        rng: &mut R,
+        pedersen_commitment_parameters: &PedersenCommitment<E::G1Projective>,
+        parameters: &Parameters,
+        observer: &O,
+    ) -> Result<Self::Parameters, SetupError> {
+        match parameters.hash_to_prime_backend {
+            Some(HashToPrimeBackend::SnarkRange) => Ok(DynParameters::SnarkRange(
+                snark_range::Protocol::<E>::setup_with_observer(
+                    rng,
+                    pedersen_commitment_parameters,
+                    parameters,
+                    observer,
+                )?,
+            )),
+            Some(HashToPrimeBackend::SnarkHash) => Ok(DynParameters::SnarkHash(
+                snark_hash::Protocol::<E, H>::setup_with_observer(
+                    rng,
+                    pedersen_commitment_parameters,
+                    parameters,
+                    observer,
+                )?,
+            )),
+            Some(HashToPrimeBackend::SnarkIncrement) => {
+                Ok(DynParameters::SnarkIncrement(snark_increment::Protocol::<
+                    E,
+                    H,
+                >::setup_with_observer(
+                    rng,
+                    pedersen_commitment_parameters,
+                    parameters,
+                    observer,
+                )?))
+            }
+            None => Err(SetupError::UnsupportedParameters),
+        }
+    }
+
+    fn circuit_stats(parameters: &Parameters) -> Result<CircuitStats, SetupError> {
+        match parameters.hash_to_prime_backend {
+            Some(HashToPrimeBackend::SnarkRange) => {
+                snark_range::Protocol::<E>::circuit_stats(parameters)
+            }
+            Some(HashToPrimeBackend::SnarkHash) => {
+                snark_hash::Protocol::<E, H>::circuit_stats(parameters)
+            }
+            Some(HashToPrimeBackend::SnarkIncrement) => {
+                snark_increment::Protocol::<E, H>::circuit_stats(parameters)
+            }
+            None => Err(SetupError::UnsupportedParameters),
+        }
+    }
+
+    fn estimate_sizes(parameters: &Parameters) -> Result<HashToPrimeSizes, SetupError> {
+        Ok(legogro16_sizes::<E>(&Self::circuit_stats(parameters)?))
+    }
+
+    fn prove<R: RngCore + CryptoRng, C: HashToPrimeVerifierChannel<E::G1Projective, Self>>(
+        &self,
+        verifier_channel: &mut C,
+        rng: &mut R,
+        statement: &Statement<E::G1Projective>,
+        witness: &Witness,
+    ) -> Result<(), ProofError> {
+        let mut channel = BackendVerifierChannel::<E, H, C> {
+            channel: verifier_channel,
+            protocol_type: PhantomData,
+        };
+        match self {
+            DynHashToPrime::SnarkRange(p) => p.prove(&mut channel, rng, statement, witness),
+            DynHashToPrime::SnarkHash(p) => p.prove(&mut channel, rng, statement, witness),
+            DynHashToPrime::SnarkIncrement(p) => p.prove(&mut channel, rng, statement, witness),
+        }
+    }
+
+    fn verify<C: HashToPrimeProverChannel<E::G1Projective, Self>>(
+        &self,
+        prover_channel: &mut C,
+        statement: &Statement<E::G1Projective>,
+    ) -> Result<(), VerificationError> {
+        let mut channel = BackendProverChannel::<E, H, C> {
+            channel: prover_channel,
+            protocol_type: PhantomData,
+        };
+        match self {
+            DynHashToPrime::SnarkRange(p) => p.verify(&mut channel, statement),
+            DynHashToPrime::SnarkHash(p) => p.verify(&mut channel, statement),
+            DynHashToPrime::SnarkIncrement(p) => p.verify(&mut channel, statement),
+        }
+    }
+
+    fn verify_batch<R: RngCore + CryptoRng>(
+        &self,
+        rng: &mut R,
+        statements: &[Statement<E::G1Projective>],
+        proofs: &[Self::Proof],
+    ) -> Result<(), VerificationError> {
+        match self {
+            DynHashToPrime::SnarkRange(p) => p.verify_batch(rng, statements, proofs),
+            DynHashToPrime::SnarkHash(p) => p.verify_batch(rng, statements, proofs),
+            DynHashToPrime::SnarkIncrement(p) => p.verify_batch(rng, statements, proofs),
+        }
+    }
+
+    fn proof_to_bytes(proof: &Self::Proof) -> Result<Vec<u8>, CurveError> {
+        snark_range::Protocol::<E>::proof_to_bytes(proof)
+    }
+
+    fn parameters_to_bytes(parameters: &Self::Parameters) -> Result<Vec<u8>, CurveError> {
+        match parameters {
+            DynParameters::SnarkRange(pk) => snark_range::Protocol::<E>::parameters_to_bytes(pk),
+            DynParameters::SnarkHash(pk) => snark_hash::Protocol::<E, H>::parameters_to_bytes(pk),
+            DynParameters::SnarkIncrement(pk) => {
+                snark_increment::Protocol::<E, H>::parameters_to_bytes(pk)
+            }
+        }
+    }
+
+    fn max_element_bits(parameters: &Parameters) -> u16 {
+        match parameters.hash_to_prime_backend {
+            Some(HashToPrimeBackend::SnarkHash) => {
+                snark_hash::Protocol::<E, H>::max_element_bits(parameters)
+            }
+            Some(HashToPrimeBackend::SnarkIncrement) => {
+                snark_increment::Protocol::<E, H>::max_element_bits(parameters)
+            }
+            Some(HashToPrimeBackend::SnarkRange) | None => {
+                snark_range::Protocol::<E>::max_element_bits(parameters)
+            }
+        }
+    }
+
+    fn maps_to_unique_prime(parameters: &Parameters) -> bool {
+        match parameters.hash_to_prime_backend {
+            Some(HashToPrimeBackend::SnarkHash) => {
+                snark_hash::Protocol::<E, H>::maps_to_unique_prime(parameters)
+            }
+            Some(HashToPrimeBackend::SnarkIncrement) => {
+                snark_increment::Protocol::<E, H>::maps_to_unique_prime(parameters)
+            }
+            Some(HashToPrimeBackend::SnarkRange) | None => {
+                snark_range::Protocol::<E>::maps_to_unique_prime(parameters)
+            }
+        }
+    }
+
+    fn hash_to_prime(&self, e: &Integer) -> Result<(Integer, u64), HashToPrimeError> {
+        match self {
+            DynHashToPrime::SnarkRange(p) => p.hash_to_prime(e),
+            DynHashToPrime::SnarkHash(p) => p.hash_to_prime(e),
+            DynHashToPrime::SnarkIncrement(p) => p.hash_to_prime(e),
+        }
+    }
+
+    fn hash_to_prime_with_observer<O: ProgressObserver>(
+        &self,
+        e: &Integer,
+        observer: &O,
+    ) -> Result<(Integer, u64), HashToPrimeError> {
+        match self {
+            DynHashToPrime::SnarkRange(p) => p.hash_to_prime_with_observer(e, observer),
+            DynHashToPrime::SnarkHash(p) => p.hash_to_prime_with_observer(e, observer),
+            DynHashToPrime::SnarkIncrement(p) => p.hash_to_prime_with_observer(e, observer),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{DynHashToPrime, Statement, Witness};
+    use crate::{
+        commitments::{pedersen::PedersenCommitment, Commitment},
+        parameters::{HashToPrimeBackend, Parameters},
+        protocols::{
+            hash_to_prime::{
+                snark_hash::HashToPrimeHashParameters,
+                transcript::{TranscriptProverChannel, TranscriptVerifierChannel},
+                HashToPrimeProtocol,
+            },
+            membership::Protocol as MembershipProtocol,
+            SetupError,
+        },
+    };
+    use accumulator::group::Rsa2048;
+    use ark_bls12_381::{Bls12_381, G1Projective};
+    use merlin::Transcript;
+    use rand::thread_rng;
+    use rug::rand::RandState;
+    use rug::Integer;
+    use std::cell::RefCell;
+
+    struct TestParameters {}
+    impl HashToPrimeHashParameters for TestParameters {
+        const MESSAGE_SIZE: u16 = 254;
+    }
+
+    type Protocol = DynHashToPrime<Bls12_381, TestParameters>;
+
+    #[test]
+    fn test_no_backend() {
+        let params = Parameters::from_security_level(128).unwrap();
+        assert!(matches!(
+            Protocol::circuit_stats(&params),
+            Err(SetupError::UnsupportedParameters)
+        ));
+        let mut rng = thread_rng();
+        let pedersen_commitment_parameters = PedersenCommitment::<G1Projective>::setup(&mut rng);
+        assert!(matches!(
+            Protocol::setup(&mut rng, &pedersen_commitment_parameters, &params),
+            Err(SetupError::UnsupportedParameters)
+        ));
+    }
+
+    #[test]
+    fn test_proof() {
+        for backend in &[
+            HashToPrimeBackend::SnarkRange,
+            HashToPrimeBackend::SnarkHash,
+        ] {
+            let params = Parameters::from_security_level(128)
+                .unwrap()
+                .with_hash_to_prime_backend(*backend);
+            let mut rng1 = RandState::new();
+            rng1.seed(&Integer::from(13));
+            let mut rng2 = thread_rng();
+
+            let crs = MembershipProtocol::<Rsa2048, G1Projective, Protocol>::setup(
+                &params, &mut rng1, &mut rng2,
+            )
+            .unwrap()
+            .crs
+            .crs_hash_to_prime;
+            let protocol = Protocol::from_crs(&crs);
+            assert_eq!(protocol.backend(), *backend);
+
+            let value = match backend {
+                HashToPrimeBackend::SnarkRange => {
+                    Integer::from(Integer::u_pow_u(2, params.hash_to_prime_bits as u32))
+                        - &Integer::from(245)
+                }
+                _ => Integer::from(13),
+            };
+            let (hashed_value, _) = protocol.hash_to_prime(&value).unwrap();
+            let randomness = Integer::from(9);
+            let commitment = crs
+                .pedersen_commitment_parameters
+                .commit(&hashed_value, &randomness)
+                .unwrap();
+
+            let proof_transcript = RefCell::new(Transcript::new(b"hash_to_prime"));
+            let statement = Statement { c_e_q: commitment };
+            let mut verifier_channel = TranscriptVerifierChannel::new(&crs, &proof_transcript);
+            protocol
+                .prove(
+                    &mut verifier_channel,
+                    &mut rng2,
+                    &statement,
+                    &Witness {
+                        e: value,
+                        r_q: randomness,
+                    },
+                )
+                .unwrap();
+
+            let proof = verifier_channel.proof().unwrap();
+
+            let verification_transcript = RefCell::new(Transcript::new(b"hash_to_prime"));
+            let mut prover_channel =
+                TranscriptProverChannel::new(&crs, &verification_transcript, &proof);
+            protocol.verify(&mut prover_channel, &statement).unwrap();
+        }
+    }
+}
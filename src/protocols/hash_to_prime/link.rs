@@ -0,0 +1,198 @@
+//! The Pedersen bases linking the committed inputs of the LegoGroth16
+//! hash-to-prime circuits to commitments outside of them.
+//!
+//! The bases are, in order, a random base for the constant one, `g` of the
+//! Pedersen commitments for the element, one base per additional committed
+//! input and `h` for the randomness. The hash-to-prime circuits only commit to
+//! the element, while extensions with more committed public inputs, e.g. a
+//! nullifier or a key and a value, append a base for each of them.
+use crate::{
+    commitments::pedersen::PedersenCommitment,
+    protocols::{progress::ProgressObserver, SetupError, VerificationError},
+};
+use ark_ec::{PairingEngine, ProjectiveCurve};
+use ark_ff::UniformRand;
+use ark_relations::r1cs::ConstraintSynthesizer;
+use legogro16::{ProvingKey, VerifyingKey};
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::thread;
+use std::time::Duration;
+
+/// The committed inputs of the hash-to-prime circuits: the element.
+pub const ELEMENT_COMMITTED_INPUTS: usize = 1;
+
+/// The link bases for a circuit with `committed_inputs` public inputs, the
+/// first being the element and the others committed with `extra_bases`.
+pub fn link_bases<E: PairingEngine, R: Rng>(
+    rng: &mut R,
+    pedersen_commitment_parameters: &PedersenCommitment<E::G1Projective>,
+    extra_bases: &[E::G1Projective],
+    committed_inputs: usize,
+) -> Result<Vec<E::G1Affine>, SetupError> {
+    if committed_inputs != extra_bases.len() + 1 {
+        return Err(SetupError::InvalidLinkBases(
+            extra_bases.len() + 3,
+            committed_inputs + 2,
+        ));
+    }
+    let mut bases = vec![E::G1Projective::rand(rng), pedersen_commitment_parameters.g];
+    bases.extend_from_slice(extra_bases);
+    bases.push(pedersen_commitment_parameters.h);
+    Ok(bases.into_iter().map(|p| p.into_affine()).collect())
+}
+
+/// Generates the LegoGroth16 parameters of `circuit`, which has
+/// `committed_inputs` public inputs, linked to the Pedersen commitments and
+/// `extra_bases`.
+pub fn generate_linked_parameters<E: PairingEngine, C: ConstraintSynthesizer<E::Fr>, R: Rng>(
+    rng: &mut R,
+    circuit: C,
+    pedersen_commitment_parameters: &PedersenCommitment<E::G1Projective>,
+    extra_bases: &[E::G1Projective],
+    committed_inputs: usize,
+) -> Result<ProvingKey<E>, SetupError> {
+    let bases = link_bases::<E, R>(
+        rng,
+        pedersen_commitment_parameters,
+        extra_bases,
+        committed_inputs,
+    )?;
+    Ok(legogro16::generate_random_parameters(circuit, &bases, rng)?)
+}
+
+/// How often `generate_linked_parameters_with_observer` checks whether it
+/// was cancelled.
+const CANCELLATION_POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// Like `generate_linked_parameters`, returning `SetupError::Cancelled` as
+/// soon as `observer` is cancelled, also while the parameters are being
+/// generated. LegoGroth16 can't interrupt the generation, so it runs on a
+/// separate thread seeded from `rng`, and the caller checks the observer
+/// while waiting for it. After a cancellation the thread still runs to
+/// completion in the background and its parameters are dropped.
+pub fn generate_linked_parameters_with_observer<
+    E: PairingEngine,
+    C: ConstraintSynthesizer<E::Fr> + Send + 'static,
+    R: Rng,
+    O: ProgressObserver,
+>(
+    rng: &mut R,
+    circuit: C,
+    pedersen_commitment_parameters: &PedersenCommitment<E::G1Projective>,
+    extra_bases: &[E::G1Projective],
+    committed_inputs: usize,
+    observer: &O,
+) -> Result<ProvingKey<E>, SetupError> {
+    let bases = link_bases::<E, R>(
+        rng,
+        pedersen_commitment_parameters,
+        extra_bases,
+        committed_inputs,
+    )?;
+    let mut generation_rng = StdRng::from_rng(rng).map_err(|_| SetupError::CouldNotPerformSetup)?;
+    let (sender, receiver) = mpsc::channel();
+    thread::spawn(move || {
+        let result = legogro16::generate_random_parameters(circuit, &bases, &mut generation_rng);
+        // nobody is waiting for the parameters if the setup was cancelled
+        let _ = sender.send(result);
+    });
+    loop {
+        if observer.is_cancelled() {
+            return Err(SetupError::Cancelled);
+        }
+        match receiver.recv_timeout(CANCELLATION_POLL_INTERVAL) {
+            Ok(result) => return Ok(result?),
+            Err(RecvTimeoutError::Timeout) => {}
+            Err(RecvTimeoutError::Disconnected) => return Err(SetupError::CouldNotPerformSetup),
+        }
+    }
+}
+
+/// Checks `vk` has the link bases of a circuit with `committed_inputs`
+/// public inputs, e.g. for keys loaded from a file.
+pub fn validate_link_bases<E: PairingEngine>(
+    vk: &VerifyingKey<E>,
+    committed_inputs: usize,
+) -> Result<(), VerificationError> {
+    if vk.link_bases.len() != committed_inputs + 2 || vk.gamma_abc_g1.len() != committed_inputs + 1
+    {
+        return Err(VerificationError::VerificationFailed);
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::{link_bases, validate_link_bases, ELEMENT_COMMITTED_INPUTS};
+    use crate::{
+        commitments::pedersen::PedersenCommitment,
+        parameters::Parameters,
+        protocols::{
+            hash_to_prime::{snark_range::Protocol as HPProtocol, HashToPrimeProtocol},
+            progress::ProgressObserver,
+            SetupError,
+        },
+    };
+    use ark_bls12_381::{Bls12_381, G1Projective};
+    use ark_ec::ProjectiveCurve;
+    use ark_ff::UniformRand;
+    use rand::thread_rng;
+    use std::cell::Cell;
+
+    /// Lets the setup start, and cancels it on the next check, i.e. while the
+    /// parameters are being generated.
+    struct CancelDuringGeneration {
+        checks: Cell<u32>,
+    }
+
+    impl ProgressObserver for CancelDuringGeneration {
+        fn is_cancelled(&self) -> bool {
+            self.checks.set(self.checks.get() + 1);
+            self.checks.get() > 1
+        }
+    }
+
+    #[test]
+    fn test_cancel_generation() {
+        let mut rng = thread_rng();
+        let pedersen = PedersenCommitment::<G1Projective>::setup(&mut rng);
+        let params = Parameters::from_security_level(128).unwrap();
+        let observer = CancelDuringGeneration {
+            checks: Cell::new(0),
+        };
+        assert!(matches!(
+            HPProtocol::<Bls12_381>::setup_with_observer(&mut rng, &pedersen, &params, &observer),
+            Err(SetupError::Cancelled)
+        ));
+        assert_eq!(observer.checks.get(), 2);
+    }
+
+    #[test]
+    fn test_link_bases() {
+        let mut rng = thread_rng();
+        let pedersen = PedersenCommitment::<G1Projective>::setup(&mut rng);
+        let extra = G1Projective::rand(&mut rng);
+
+        let bases = link_bases::<Bls12_381, _>(&mut rng, &pedersen, &[extra], 2).unwrap();
+        assert_eq!(
+            bases[1..],
+            [
+                pedersen.g.into_affine(),
+                extra.into_affine(),
+                pedersen.h.into_affine()
+            ]
+        );
+        assert!(matches!(
+            link_bases::<Bls12_381, _>(&mut rng, &pedersen, &[extra], 1),
+            Err(SetupError::InvalidLinkBases(4, 3))
+        ));
+
+        let params = Parameters::from_security_level(128).unwrap();
+        let mut pk = HPProtocol::<Bls12_381>::setup(&mut rng, &pedersen, &params).unwrap();
+        validate_link_bases(&pk.vk, ELEMENT_COMMITTED_INPUTS).unwrap();
+        pk.vk.link_bases.push(extra.into_affine());
+        assert!(validate_link_bases(&pk.vk, ELEMENT_COMMITTED_INPUTS).is_err());
+    }
+}
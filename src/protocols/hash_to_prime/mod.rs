@@ -1,24 +1,38 @@
 //! Implements an abstract hash-to-prime protocol, which can also be just a range proof.
 use crate::{
+    channels::ChannelError,
     commitments::{pedersen::PedersenCommitment, Commitment},
     parameters::Parameters,
-    protocols::{ProofError, SetupError, VerificationError},
-    utils::curve::CurvePointProjective,
+    protocols::{
+        progress::{NoProgress, ProgressObserver},
+        statement::{hash_curve_point, StatementDigest, StatementHasher},
+        ProofError, SetupError, VerificationError,
+    },
+    utils::curve::{CurveError, CurvePointProjective, Field},
 };
 use channel::{HashToPrimeProverChannel, HashToPrimeVerifierChannel};
 use rand::{CryptoRng, RngCore};
-use rug::Integer;
+use rug::{integer::IsPrime, Integer};
+use std::hash::{Hash, Hasher};
 
 pub mod channel;
+pub mod shared;
+pub mod square_range;
 pub mod transcript;
 
 cfg_if::cfg_if! {
     if #[cfg(feature = "arkworks")] {
+        pub mod dynamic;
+        pub mod link;
+        pub mod snark_batch;
         pub mod snark_hash;
+        pub mod snark_hash_public;
+        pub mod snark_increment;
         pub mod snark_range;
 
         use ark_ec::{PairingEngine, AffineCurve};
         use ark_serialize::CanonicalSerialize;
+        use std::mem::size_of_val;
 
         impl<E: PairingEngine> CRSSize for legogro16::ProvingKey::<E> {
             fn crs_size(&self) -> (usize, usize) {
@@ -76,6 +90,64 @@ cfg_if::cfg_if! {
                 (vk_accum, pk_accum)
             }
         }
+
+        /// The sizes of the LegoGroth16 proofs and keys of a circuit of
+        /// `stats`, counted like `crs_size`. The link of the committed inputs
+        /// compares two commitments, over the link bases and the bases of the
+        /// committed inputs in the proof, and a random base for each row.
+        pub fn legogro16_sizes<E: PairingEngine>(stats: &CircuitStats) -> HashToPrimeSizes {
+            let g1 = E::G1Affine::prime_subgroup_generator().serialized_size();
+            let g2 = E::G2Affine::prime_subgroup_generator().serialized_size();
+            // the constant one is an instance variable too
+            let instance_variables = stats.public_inputs + 1;
+            let link_bases = stats.public_inputs + 2;
+            let link_rows = 2;
+            let link_columns = link_bases + 1;
+            let domain_size = (stats.constraints + instance_variables).next_power_of_two();
+
+            let verifier_parameters_bytes = g1
+                + 3 * g2
+                + instance_variables * g1
+                + g1
+                + 16
+                + g1
+                + g2
+                + link_bases * g1
+                + g2
+                + link_rows * g2;
+            let prover_parameters_bytes = 3 * g1
+                + 2 * stats.variables * g1
+                + stats.variables * g2
+                + (domain_size - 1) * g1
+                + (stats.variables - instance_variables) * g1
+                + link_columns * g1;
+            HashToPrimeSizes {
+                // a, c, d and the link commitment and proof in G1, b in G2
+                proof_bytes: 5 * g1 + g2,
+                verifier_parameters_bytes,
+                prover_parameters_bytes,
+            }
+        }
+
+        impl<E: PairingEngine> MemoryUsage for legogro16::ProvingKey::<E> {
+            fn memory_report(&self) -> MemoryReport {
+                let mut report = MemoryReport::default();
+                report.push(
+                    "vk",
+                    size_of_val(&self.vk)
+                        + size_of_val(&self.vk.gamma_abc_g1[..])
+                        + size_of_val(&self.vk.link_bases[..])
+                        + size_of_val(&self.vk.link_vk.c[..]),
+                );
+                report.push("a_query", size_of_val(&self.a_query[..]));
+                report.push("b_g1_query", size_of_val(&self.b_g1_query[..]));
+                report.push("b_g2_query", size_of_val(&self.b_g2_query[..]));
+                report.push("h_query", size_of_val(&self.h_query[..]));
+                report.push("l_query", size_of_val(&self.l_query[..]));
+                report.push("link_ek", size_of_val(&self.link_ek.p[..]));
+                report
+            }
+        }
     }
 }
 
@@ -89,6 +161,51 @@ pub trait CRSSize {
     fn crs_size(&self) -> (usize, usize);
 }
 
+/// Memory taken by the parts of a CRS when loaded, in bytes.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct MemoryReport {
+    pub entries: Vec<(&'static str, usize)>,
+}
+
+impl MemoryReport {
+    pub fn push(&mut self, name: &'static str, bytes: usize) {
+        self.entries.push((name, bytes));
+    }
+
+    pub fn extend(&mut self, other: MemoryReport) {
+        self.entries.extend(other.entries);
+    }
+
+    pub fn total(&self) -> usize {
+        self.entries.iter().map(|(_, bytes)| bytes).sum()
+    }
+}
+
+/// Reports the memory taken by hash-to-prime parameters, e.g. to check that a
+/// prover fits in a constrained environment.
+pub trait MemoryUsage {
+    fn memory_report(&self) -> MemoryReport;
+}
+
+/// Size of the circuit proven by a hash-to-prime protocol. For Bulletproofs,
+/// `variables` counts the multiplication gates' wires.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CircuitStats {
+    pub constraints: usize,
+    pub variables: usize,
+    pub public_inputs: usize,
+}
+
+/// Estimated sizes in bytes of a hash-to-prime proof and of the parameters,
+/// split like `CRSSize::crs_size` into what the verifier uses and what only
+/// the prover uses on top of it.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct HashToPrimeSizes {
+    pub proof_bytes: usize,
+    pub verifier_parameters_bytes: usize,
+    pub prover_parameters_bytes: usize,
+}
+
 pub trait HashToPrimeProtocol<P: CurvePointProjective> {
     type Proof: Clone;
     type Parameters: Clone;
@@ -101,8 +218,27 @@ pub trait HashToPrimeProtocol<P: CurvePointProjective> {
         rng: &mut R,
         pedersen_commitment_parameters: &PedersenCommitment<P>,
         parameters: &Parameters,
+    ) -> Result<Self::Parameters, SetupError> {
+        Self::setup_with_observer(rng, pedersen_commitment_parameters, parameters, &NoProgress)
+    }
+
+    /// Performs the setup, reporting progress to `observer` and returning
+    /// `SetupError::Cancelled` if it asks to stop.
+    fn setup_with_observer<R: RngCore + CryptoRng, O: ProgressObserver>(
+        rng: &mut R,
+        pedersen_commitment_parameters: &PedersenCommitment<P>,
+        parameters: &Parameters,
+        observer: &O,
     ) -> Result<Self::Parameters, SetupError>;
 
+    /// Computes the circuit size for the given parameters without performing
+    /// the setup.
+    fn circuit_stats(parameters: &Parameters) -> Result<CircuitStats, SetupError>;
+
+    /// Estimates the sizes of the proofs and the parameters for `parameters`
+    /// from closed-form formulas, without performing the setup.
+    fn estimate_sizes(parameters: &Parameters) -> Result<HashToPrimeSizes, SetupError>;
+
     fn prove<R: RngCore + CryptoRng, C: HashToPrimeVerifierChannel<P, Self>>(
         &self,
         verifier_channel: &mut C,
@@ -120,6 +256,203 @@ pub trait HashToPrimeProtocol<P: CurvePointProjective> {
     where
         Self: Sized;
     fn hash_to_prime(&self, e: &Integer) -> Result<(Integer, u64), HashToPrimeError>;
+
+    /// The maximum size of the elements the protocol proves for with
+    /// `parameters`. The range-only protocols use the element as the prime,
+    /// so it's `hash_to_prime_bits` for them.
+    fn max_element_bits(parameters: &Parameters) -> u16
+    where
+        Self: Sized,
+    {
+        parameters.hash_to_prime_bits
+    }
+
+    /// Whether the proof pins the prime an element maps to. The hash-based
+    /// protocols accept any index or offset giving a prime, which keeps
+    /// membership sound but not nonmembership: a prover could show one prime
+    /// of an element isn't accumulated while another one is.
+    fn maps_to_unique_prime(_: &Parameters) -> bool
+    where
+        Self: Sized,
+    {
+        true
+    }
+
+    /// Verifies proofs of `statements`, which some protocols do faster than
+    /// one by one.
+    fn verify_batch<R: RngCore + CryptoRng>(
+        &self,
+        _: &mut R,
+        statements: &[Statement<P>],
+        proofs: &[Self::Proof],
+    ) -> Result<(), VerificationError>
+    where
+        Self: Sized,
+    {
+        if statements.len() != proofs.len() {
+            return Err(VerificationError::VerificationFailed);
+        }
+        for (statement, proof) in statements.iter().zip(proofs) {
+            self.verify(
+                &mut ReceivedProof {
+                    proof: proof.clone(),
+                },
+                statement,
+            )?;
+        }
+        Ok(())
+    }
+
+    /// Serializes a proof, e.g. to derive a verification cache key from it.
+    fn proof_to_bytes(proof: &Self::Proof) -> Result<Vec<u8>, CurveError>;
+
+    /// Serializes what the verifier uses of `parameters`, e.g. the verifying
+    /// key of a SNARK, to bind it in the CRS digest.
+    fn parameters_to_bytes(parameters: &Self::Parameters) -> Result<Vec<u8>, CurveError>;
+
+    /// Like `hash_to_prime`, but reports progress to `observer` and returns
+    /// `HashToPrimeError::Cancelled` if it asks to stop.
+    fn hash_to_prime_with_observer<O: ProgressObserver>(
+        &self,
+        e: &Integer,
+        observer: &O,
+    ) -> Result<(Integer, u64), HashToPrimeError> {
+        if observer.is_cancelled() {
+            return Err(HashToPrimeError::Cancelled);
+        }
+        self.hash_to_prime(e)
+    }
+}
+
+/// Marker for hash-to-prime protocols which are only range proofs, i.e. the
+/// set elements are expected to already be primes of the right size. These are
+/// the protocols used in CPMemRSAPrm and CPNonMemRSAPrm.
+pub trait RangeProofProtocol<P: CurvePointProjective>: HashToPrimeProtocol<P> {}
+
+/// The hash-to-prime proof of a composed proof whose other sub-proofs were
+/// verified, so that invalid proofs can be rejected before the expensive
+/// SNARK verification. The composed proof is only valid once `finalize`
+/// succeeds.
+#[must_use]
+pub struct PartiallyVerified<P: CurvePointProjective, HP: HashToPrimeProtocol<P>> {
+    statement: Statement<P>,
+    proof: HP::Proof,
+}
+
+impl<P: CurvePointProjective, HP: HashToPrimeProtocol<P>> PartiallyVerified<P, HP> {
+    pub(crate) fn new(statement: Statement<P>, proof: HP::Proof) -> PartiallyVerified<P, HP> {
+        PartiallyVerified { statement, proof }
+    }
+
+    /// Verifies the hash-to-prime proof with `protocol`.
+    pub fn finalize(self, protocol: &HP) -> Result<(), VerificationError> {
+        let PartiallyVerified { statement, proof } = self;
+        protocol.verify(&mut ReceivedProof { proof }, &statement)
+    }
+
+    /// Verifies the hash-to-prime proofs of several pre-verified proofs with
+    /// `protocol`, in a batch.
+    pub fn finalize_batch<R: RngCore + CryptoRng>(
+        partially_verified: Vec<PartiallyVerified<P, HP>>,
+        protocol: &HP,
+        rng: &mut R,
+    ) -> Result<(), VerificationError> {
+        let (statements, proofs): (Vec<_>, Vec<_>) = partially_verified
+            .into_iter()
+            .map(|p| (p.statement, p.proof))
+            .unzip();
+        protocol.verify_batch(rng, &statements, &proofs)
+    }
+}
+
+struct ReceivedProof<Pr> {
+    proof: Pr,
+}
+
+impl<P: CurvePointProjective, HP: HashToPrimeProtocol<P>> HashToPrimeProverChannel<P, HP>
+    for ReceivedProof<HP::Proof>
+{
+    fn receive_proof(&mut self) -> Result<HP::Proof, ChannelError> {
+        Ok(self.proof.clone())
+    }
+}
+
+/// The Miller-Rabin rounds of the primality tests. From the gmp
+/// documentation: "A composite number will be identified as a prime with an
+/// asymptotic probability of less than 4^(-reps)", so `security_level / 2`
+/// rounds are used.
+pub fn miller_rabin_rounds(parameters: &Parameters) -> u32 {
+    parameters.security_level as u32 / 2
+}
+
+/// Checks that an element can be used directly in a range-only protocol: it
+/// has to be prime and have exactly `hash_to_prime_bits` bits.
+pub fn validate_prime_element(
+    parameters: &Parameters,
+    e: &Integer,
+) -> Result<(), HashToPrimeError> {
+    if e.significant_bits() != parameters.hash_to_prime_bits as u32 {
+        return Err(HashToPrimeError::InvalidValueSize);
+    }
+    // same number of Miller-Rabin rounds as in the hash-to-prime
+    if e.is_probably_prime(miller_rabin_rounds(parameters)) == IsPrime::No {
+        return Err(HashToPrimeError::ValueNotPrime);
+    }
+
+    Ok(())
+}
+
+/// Checks the element of a composed proof has at most
+/// `HP::max_element_bits` bits, before hashing it.
+pub fn validate_element_size<P: CurvePointProjective, HP: HashToPrimeProtocol<P>>(
+    parameters: &Parameters,
+    e: &Integer,
+) -> Result<(), ProofError> {
+    let max_bits = HP::max_element_bits(parameters);
+    if e.significant_bits() > max_bits as u32 {
+        return Err(ProofError::ElementTooLarge(e.significant_bits(), max_bits));
+    }
+    Ok(())
+}
+
+/// The canonical index mode for public elements: checks `statement.c_e_q`
+/// commits with `r_q` to the prime `HP::hash_to_prime` maps `e` to, i.e. the
+/// one of the smallest index or offset, since the circuits of the hash-based
+/// backends accept any index giving a prime. Only the verifier learning `e`
+/// can do this, see `HashToPrimeProtocol::maps_to_unique_prime`.
+pub fn verify_canonical_prime<P: CurvePointProjective, HP: HashToPrimeProtocol<P>>(
+    crs: &CRSHashToPrime<P, HP>,
+    statement: &Statement<P>,
+    e: &Integer,
+    r_q: &Integer,
+) -> Result<(), VerificationError> {
+    let (prime, _) = HP::from_crs(crs)
+        .hash_to_prime(e)
+        .map_err(|_| VerificationError::VerificationFailed)?;
+    if crs.pedersen_commitment_parameters.commit(&prime, r_q)? != statement.c_e_q {
+        return Err(VerificationError::VerificationFailed);
+    }
+
+    Ok(())
+}
+
+/// Checks the witness of a composed proof before proving: the prime
+/// `hashed_e` has to fit in `hash_to_prime_bits` bits and `r_q` has to be
+/// reduced modulo the order of the curve. The range-only protocols use `e` as
+/// the prime directly, so a larger `e` would only fail at verification.
+pub fn validate_witness<P: CurvePointProjective>(
+    parameters: &Parameters,
+    hashed_e: &Integer,
+    r_q: &Integer,
+) -> Result<(), ProofError> {
+    if *hashed_e < 0 || hashed_e.significant_bits() > parameters.hash_to_prime_bits as u32 {
+        return Err(ProofError::ElementOutOfRange);
+    }
+    if *r_q < 0 || *r_q >= P::ScalarField::modulus() {
+        return Err(ProofError::RandomnessNotReduced);
+    }
+
+    Ok(())
 }
 
 pub struct CRSHashToPrime<P: CurvePointProjective, HP: HashToPrimeProtocol<P>> {
@@ -138,10 +471,55 @@ impl<P: CurvePointProjective, HP: HashToPrimeProtocol<P>> Clone for CRSHashToPri
     }
 }
 
+impl<P: CurvePointProjective, HP: HashToPrimeProtocol<P>> CRSHashToPrime<P, HP>
+where
+    HP::Parameters: MemoryUsage,
+{
+    pub fn memory_report(&self) -> MemoryReport {
+        let mut report = self.hash_to_prime_parameters.memory_report();
+        report.push(
+            "pedersen_commitment_parameters",
+            std::mem::size_of_val(&self.pedersen_commitment_parameters),
+        );
+        report
+    }
+}
+
 pub struct Statement<P: CurvePointProjective> {
     pub c_e_q: <PedersenCommitment<P> as Commitment>::Instance,
 }
 
+impl<P: CurvePointProjective> Clone for Statement<P> {
+    fn clone(&self) -> Self {
+        Self {
+            c_e_q: self.c_e_q.clone(),
+        }
+    }
+}
+
+impl<P: CurvePointProjective> PartialEq for Statement<P> {
+    fn eq(&self, other: &Self) -> bool {
+        self.c_e_q == other.c_e_q
+    }
+}
+
+impl<P: CurvePointProjective> Eq for Statement<P> {}
+
+impl<P: CurvePointProjective> Hash for Statement<P> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        hash_curve_point(&self.c_e_q, state);
+    }
+}
+
+impl<P: CurvePointProjective> Statement<P> {
+    /// Stable identifier of the statement, see `protocols::statement`.
+    pub fn digest(&self) -> Result<StatementDigest, CurveError> {
+        let mut hasher = StatementHasher::new(b"hash_to_prime");
+        hasher.append_curve_point(b"c_e_q", &self.c_e_q)?;
+        Ok(hasher.finish())
+    }
+}
+
 pub struct Witness {
     pub e: Integer,
     pub r_q: Integer,
@@ -152,6 +530,9 @@ quick_error! {
     pub enum HashToPrimeError {
         CouldNotFindIndex {}
         ValueTooBig {}
+        ValueNotPrime {}
+        InvalidValueSize {}
+        Cancelled {}
         IntegerError(num: Integer) {
             from()
         }
@@ -0,0 +1,105 @@
+//! Reuse of hash-to-prime proofs across composed proofs.
+//!
+//! The hash-to-prime proof only depends on `c_e_q` and its opening, and isn't
+//! bound to the transcript, so composed proofs about the same commitment, e.g.
+//! membership in several accumulators, can share it instead of running the
+//! prover again. Sharing it doesn't reveal more than sharing `c_e_q` does.
+//!
+//! Proofs are keyed by the commitment only, so a cache must only be used with
+//! a single CRS.
+use crate::{
+    channels::ChannelError,
+    protocols::{
+        hash_to_prime::{
+            channel::HashToPrimeVerifierChannel, HashToPrimeProtocol, Statement, Witness,
+        },
+        statement::StatementDigest,
+        ProofError,
+    },
+    utils::curve::CurvePointProjective,
+};
+use rand::{CryptoRng, RngCore};
+use std::{collections::HashMap, marker::PhantomData};
+
+/// Hash-to-prime proofs created so far, keyed by the digest of their
+/// statement.
+pub struct SharedSubProofs<P: CurvePointProjective, HP: HashToPrimeProtocol<P>> {
+    proofs: HashMap<StatementDigest, HP::Proof>,
+    point_type: PhantomData<P>,
+}
+
+impl<P: CurvePointProjective, HP: HashToPrimeProtocol<P>> Default for SharedSubProofs<P, HP> {
+    fn default() -> Self {
+        SharedSubProofs::new()
+    }
+}
+
+impl<P: CurvePointProjective, HP: HashToPrimeProtocol<P>> SharedSubProofs<P, HP> {
+    pub fn new() -> SharedSubProofs<P, HP> {
+        SharedSubProofs {
+            proofs: HashMap::new(),
+            point_type: PhantomData,
+        }
+    }
+
+    pub fn get(&self, statement: &Statement<P>) -> Result<Option<&HP::Proof>, ProofError> {
+        Ok(self.proofs.get(&statement.digest()?))
+    }
+
+    pub fn len(&self) -> usize {
+        self.proofs.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.proofs.is_empty()
+    }
+
+    /// Sends the proof cached for `statement`, or proves it with `protocol`
+    /// and caches the proof.
+    pub fn prove<R: RngCore + CryptoRng, C: HashToPrimeVerifierChannel<P, HP>>(
+        &mut self,
+        protocol: &HP,
+        verifier_channel: &mut C,
+        rng: &mut R,
+        statement: &Statement<P>,
+        witness: &Witness,
+    ) -> Result<(), ProofError> {
+        let digest = statement.digest()?;
+        if let Some(proof) = self.proofs.get(&digest) {
+            verifier_channel.send_proof(proof)?;
+            return Ok(());
+        }
+
+        let mut recording_channel = RecordingChannel {
+            inner: verifier_channel,
+            proof: None,
+            point_type: PhantomData,
+        };
+        protocol.prove(&mut recording_channel, rng, statement, witness)?;
+        if let Some(proof) = recording_channel.proof {
+            self.proofs.insert(digest, proof);
+        }
+        Ok(())
+    }
+}
+
+/// Forwards the proof to the inner channel, keeping a copy.
+struct RecordingChannel<'a, P: CurvePointProjective, HP: HashToPrimeProtocol<P>, C> {
+    inner: &'a mut C,
+    proof: Option<HP::Proof>,
+    point_type: PhantomData<P>,
+}
+
+impl<
+        'a,
+        P: CurvePointProjective,
+        HP: HashToPrimeProtocol<P>,
+        C: HashToPrimeVerifierChannel<P, HP>,
+    > HashToPrimeVerifierChannel<P, HP> for RecordingChannel<'a, P, HP, C>
+{
+    fn send_proof(&mut self, proof: &HP::Proof) -> Result<(), ChannelError> {
+        self.inner.send_proof(proof)?;
+        self.proof = Some(proof.clone());
+        Ok(())
+    }
+}
@@ -0,0 +1,176 @@
+//! Batch verification of LegoGroth16 proofs under the same verifying key.
+//!
+//! The pairing equations of the proofs, both the Groth16 one and the one of
+//! the link proof binding `link_d` and `d`, are combined with random
+//! coefficients into a single product of pairings, so a batch costs one final
+//! exponentiation and `n + 6` Miller loops instead of a full verification per
+//! proof. A batch containing an invalid proof passes with probability about
+//! `1/|Fr|`.
+use crate::protocols::{
+    hash_to_prime::{
+        link::{validate_link_bases, ELEMENT_COMMITTED_INPUTS},
+        Statement,
+    },
+    VerificationError,
+};
+use ark_ec::{AffineCurve, PairingEngine, ProjectiveCurve};
+use ark_ff::{One, PrimeField, UniformRand, Zero};
+use legogro16::{Proof, VerifyingKey};
+use rand::Rng;
+use std::ops::Sub;
+
+/// Checks the pairing equations of all of `proofs` at once.
+pub fn verify_batch<E: PairingEngine, R: Rng>(
+    vk: &VerifyingKey<E>,
+    rng: &mut R,
+    proofs: &[&Proof<E>],
+) -> bool {
+    if proofs.is_empty() {
+        return true;
+    }
+
+    let mut pairs = vec![];
+    let mut r_sum = E::Fr::zero();
+    let mut c_sum = E::G1Projective::zero();
+    let mut d_sum = E::G1Projective::zero();
+    let mut link_d_sum = E::G1Projective::zero();
+    let mut link_d_d_sum = E::G1Projective::zero();
+    let mut link_pi_sum = E::G1Projective::zero();
+    for proof in proofs {
+        // independent coefficients for the Groth16 and link equations
+        let r = E::Fr::rand(rng);
+        let s = E::Fr::rand(rng).into_repr();
+        r_sum += &r;
+        let r = r.into_repr();
+        pairs.push((proof.a.mul(r).into_affine().into(), proof.b.into()));
+        c_sum += &proof.c.mul(r);
+        d_sum += &proof.d.mul(r);
+        link_d_sum += &proof.link_d.mul(s);
+        link_d_d_sum += &proof.d.mul(s);
+        link_pi_sum += &proof.link_pi.mul(s);
+    }
+
+    // e(A, B) = e(alpha, beta) * e(D, gamma) * e(C, delta)
+    pairs.push((
+        (-vk.alpha_g1.mul(r_sum.into_repr())).into_affine().into(),
+        vk.beta_g2.into(),
+    ));
+    pairs.push(((-d_sum).into_affine().into(), vk.gamma_g2.into()));
+    pairs.push(((-c_sum).into_affine().into(), vk.delta_g2.into()));
+    // e(link_d, c_0) * e(D, c_1) = e(link_pi, a)
+    pairs.push((link_d_sum.into_affine().into(), vk.link_vk.c[0].into()));
+    pairs.push((link_d_d_sum.into_affine().into(), vk.link_vk.c[1].into()));
+    pairs.push(((-link_pi_sum).into_affine().into(), vk.link_vk.a.into()));
+
+    E::product_of_pairings(&pairs) == E::Fqk::one()
+}
+
+/// Verifies hash-to-prime proofs in a batch, checking each proof commits to
+/// the `c_e_q` of its statement.
+pub fn verify_linked_batch<E: PairingEngine, R: Rng>(
+    vk: &VerifyingKey<E>,
+    rng: &mut R,
+    statements: &[Statement<E::G1Projective>],
+    proofs: &[Proof<E>],
+) -> Result<(), VerificationError> {
+    if statements.len() != proofs.len() {
+        return Err(VerificationError::VerificationFailed);
+    }
+    validate_link_bases(vk, ELEMENT_COMMITTED_INPUTS)?;
+    let base_one = vk.link_bases[0].into_projective();
+    for (statement, proof) in statements.iter().zip(proofs) {
+        if statement.c_e_q != proof.link_d.into_projective().sub(&base_one) {
+            return Err(VerificationError::VerificationFailed);
+        }
+    }
+    if !verify_batch(vk, rng, &proofs.iter().collect::<Vec<_>>()) {
+        return Err(VerificationError::VerificationFailed);
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::verify_linked_batch;
+    use crate::{
+        commitments::Commitment,
+        parameters::Parameters,
+        protocols::hash_to_prime::{
+            snark_range::Protocol as HPProtocol, transcript::TranscriptVerifierChannel,
+            HashToPrimeProtocol, Statement, Witness,
+        },
+    };
+    use accumulator::group::Rsa2048;
+    use ark_bls12_381::{Bls12_381, G1Projective};
+    use merlin::Transcript;
+    use rand::thread_rng;
+    use rug::rand::RandState;
+    use rug::Integer;
+    use std::cell::RefCell;
+
+    #[test]
+    fn test_verify_batch() {
+        let params = Parameters::from_security_level(128).unwrap();
+        let mut rng1 = RandState::new();
+        rng1.seed(&Integer::from(13));
+        let mut rng2 = thread_rng();
+
+        let crs = crate::protocols::membership::Protocol::<
+            Rsa2048,
+            G1Projective,
+            HPProtocol<Bls12_381>,
+        >::setup(&params, &mut rng1, &mut rng2)
+        .unwrap()
+        .crs
+        .crs_hash_to_prime;
+        let protocol = HPProtocol::<Bls12_381>::from_crs(&crs);
+
+        let value = Integer::from(Integer::u_pow_u(2, params.hash_to_prime_bits as u32))
+            - &Integer::from(245);
+        let mut statements = vec![];
+        let mut proofs = vec![];
+        for randomness in 0..4 {
+            let randomness = Integer::from(randomness);
+            let statement = Statement {
+                c_e_q: crs
+                    .pedersen_commitment_parameters
+                    .commit(&value, &randomness)
+                    .unwrap(),
+            };
+            let proof_transcript = RefCell::new(Transcript::new(b"hash_to_prime"));
+            let mut verifier_channel = TranscriptVerifierChannel::new(&crs, &proof_transcript);
+            protocol
+                .prove(
+                    &mut verifier_channel,
+                    &mut rng2,
+                    &statement,
+                    &Witness {
+                        e: value.clone(),
+                        r_q: randomness,
+                    },
+                )
+                .unwrap();
+            statements.push(statement);
+            proofs.push(verifier_channel.proof().unwrap());
+        }
+
+        let vk = &crs.hash_to_prime_parameters.vk;
+        verify_linked_batch(vk, &mut rng2, &statements, &proofs).unwrap();
+        protocol
+            .verify_batch(&mut rng2, &statements, &proofs)
+            .unwrap();
+
+        // a proof for another commitment
+        statements.swap(0, 1);
+        assert!(verify_linked_batch(vk, &mut rng2, &statements, &proofs).is_err());
+        statements.swap(0, 1);
+
+        // a proof with a wrong pairing equation
+        let mut invalid_proofs = proofs.clone();
+        invalid_proofs[2].c = proofs[3].c;
+        assert!(verify_linked_batch(vk, &mut rng2, &statements, &invalid_proofs).is_err());
+
+        assert!(verify_linked_batch(vk, &mut rng2, &statements[1..], &proofs).is_err());
+    }
+}
@@ -1,39 +1,71 @@
 //! LegoGroth16-based hash-to-prime proof, with Blake2s as the hash.
+//!
+//! The element is hashed together with an index, and `hash_to_prime` returns
+//! the prime of the smallest index giving one. The circuit doesn't enforce the
+//! index to be the smallest, which would take a proof that every smaller index
+//! gives a composite, so a prover can use any of the up to
+//! `2^index_bit_length` primes of an element. Membership proofs are sound
+//! regardless, as the prime has to be accumulated. Nonmembership proofs only
+//! show that one of the primes of the element isn't accumulated: the
+//! accumulator could hold its canonical prime while the prover shows another
+//! one isn't accumulated. Verifiers which can't rely on elements only ever
+//! being accumulated through their canonical prime reject the protocol with
+//! `nonmembership::Protocol::from_crs_with_unique_prime`, see
+//! `HashToPrimeProtocol::maps_to_unique_prime`. When the element is public,
+//! `verify_canonical_prime` checks the commitment is to the canonical prime.
 
+#[cfg(feature = "prover")]
+use crate::protocols::hash_to_prime::link::generate_linked_parameters_with_observer;
 use crate::{
     commitments::pedersen::PedersenCommitment,
     parameters::Parameters,
     protocols::{
         hash_to_prime::{
             channel::{HashToPrimeProverChannel, HashToPrimeVerifierChannel},
-            CRSHashToPrime, HashToPrimeError, HashToPrimeProtocol, Statement, Witness,
+            legogro16_sizes,
+            link::{validate_link_bases, ELEMENT_COMMITTED_INPUTS},
+            miller_rabin_rounds,
+            snark_batch::verify_linked_batch,
+            CRSHashToPrime, CircuitStats, HashToPrimeError, HashToPrimeProtocol, HashToPrimeSizes,
+            Statement, Witness,
         },
+        progress::{report, HashToPrimeStats, NoProgress, ProgressObserver, Stage},
         ProofError, SetupError, VerificationError,
     },
     utils::{
         bigint_to_integer, bits_big_endian_to_bytes_big_endian,
-        bytes_big_endian_to_bits_big_endian, integer_to_bigint_mod_q, log2,
+        bytes_big_endian_to_bits_big_endian, curve::CurveError, integer_to_bigint_mod_q, log2,
     },
 };
-use ark_ff::{
-    BigInteger, One, PrimeField, UniformRand,
-};
-use ark_ec::{
-    AffineCurve, PairingEngine, ProjectiveCurve,
-};
+use ark_ec::{AffineCurve, PairingEngine};
+#[cfg(feature = "prover")]
+use ark_ff::UniformRand;
+use ark_ff::{BigInteger, PrimeField};
 
-use blake2::{Blake2s, Digest};
-use ark_crypto_primitives::{prf::blake2s::constraints::evaluate_blake2s};
-use ark_relations::r1cs::{ConstraintSynthesizer, ConstraintSystemRef, SynthesisError};
+#[cfg(feature = "prover")]
+use ark_crypto_primitives::prf::blake2s::constraints::evaluate_blake2s;
+#[cfg(feature = "prover")]
 use ark_r1cs_std::{
-    alloc::{AllocationMode, AllocVar}, bits::ToBitsGadget, boolean::Boolean, eq::EqGadget, fields::fp::FpVar,
+    alloc::{AllocVar, AllocationMode},
+    bits::ToBitsGadget,
+    boolean::Boolean,
+    eq::EqGadget,
+    fields::fp::FpVar,
     Assignment, R1CSVar,
 };
-use rand::Rng;
+#[cfg(feature = "prover")]
+use ark_relations::r1cs::{
+    ConstraintSynthesizer, ConstraintSystem, ConstraintSystemRef, SynthesisError, SynthesisMode,
+};
+use ark_serialize::CanonicalSerialize;
+use blake2::{Blake2s, Digest};
+use rand::{CryptoRng, Rng, RngCore};
 use rug::{integer::IsPrime, Integer};
-use std::ops::{Neg, Sub};
+use std::ops::Sub;
 
-pub trait HashToPrimeHashParameters {
+/// `'static` so that the circuits can be moved to the thread generating their
+/// parameters.
+pub trait HashToPrimeHashParameters: 'static {
     const MESSAGE_SIZE: u16;
 
     fn index_bit_length(security_level: u16) -> u64 {
@@ -41,100 +73,244 @@ pub trait HashToPrimeHashParameters {
     }
 }
 
+/// The message hashed by the hash-based backends: the little-endian bits of
+/// the index, if any, followed by the last `message_size` big-endian bits of
+/// the value, padded with leading zeros to whole bytes. The native search for
+/// a prime and the circuits both encode through it, so that they can't
+/// diverge, e.g. when `MESSAGE_SIZE` changes.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct MessageEncoding {
+    pub message_size: u16,
+    pub index_bit_length: u64,
+}
+
+impl MessageEncoding {
+    pub fn new(message_size: u16, index_bit_length: u64) -> MessageEncoding {
+        MessageEncoding {
+            message_size,
+            index_bit_length,
+        }
+    }
+
+    /// The little-endian bits of `index`.
+    pub fn index_bits(&self, index: u64) -> Vec<bool> {
+        (0..self.index_bit_length)
+            .map(|i| (index >> i) & 1 == 1)
+            .collect()
+    }
+
+    /// Encodes `index_bits` and the big-endian `value_bits`, of which the last
+    /// `message_size` are hashed, with `zero` as padding. Fails if either has
+    /// too few bits.
+    pub fn encode<T: Clone>(&self, index_bits: &[T], value_bits: &[T], zero: T) -> Option<Vec<T>> {
+        let message_size = self.message_size as usize;
+        if index_bits.len() as u64 != self.index_bit_length || value_bits.len() < message_size {
+            return None;
+        }
+        let message = [index_bits, &value_bits[value_bits.len() - message_size..]].concat();
+        let padding = (8 - message.len() % 8) % 8;
+        Some([vec![zero; padding], message].concat())
+    }
+}
+
+/// Hashes the encoded `message` with Blake2s in the circuit, returning the
+/// `required_bit_size` big-endian bits of the prime candidate, the first of
+/// which is set.
+#[cfg(feature = "prover")]
+pub(crate) fn hash_bits_gadget<F: PrimeField>(
+    message: Vec<Boolean<F>>,
+    required_bit_size: u16,
+) -> Result<Vec<Boolean<F>>, SynthesisError> {
+    let hash_result = evaluate_blake2s(&message)?;
+    let hash_bits = hash_result
+        .into_iter()
+        .map(|n| n.to_bits_le())
+        .flatten()
+        .collect::<Vec<Boolean<F>>>();
+
+    let hash_bits = hash_bits
+        .into_iter()
+        .take((required_bit_size - 1) as usize)
+        .collect::<Vec<_>>();
+    Ok([&[Boolean::constant(true)][..], &hash_bits].concat())
+}
+
+/// Native counterpart of `hash_bits_gadget`.
+pub(crate) fn hash_bits(message: Vec<bool>, required_bit_size: u16) -> Vec<bool> {
+    let bits_big_endian = message.into_iter().rev().collect::<Vec<_>>();
+    let bytes_to_hash = bits_big_endian_to_bytes_big_endian(&bits_big_endian)
+        .into_iter()
+        .rev()
+        .collect::<Vec<_>>();
+    let mut hasher = Blake2s::default();
+    hasher.update(&bytes_to_hash);
+    let hash = hasher.finalize();
+    let hash_big_endian = hash.into_iter().rev().collect::<Vec<_>>();
+    [
+        vec![true].as_slice(),
+        bytes_big_endian_to_bits_big_endian(&hash_big_endian)
+            .into_iter()
+            .rev()
+            .take(required_bit_size as usize - 1)
+            .collect::<Vec<_>>()
+            .as_slice(),
+    ]
+    .concat()
+}
+
+/// Returns the big-endian bits of `e` as a field element, as the circuits
+/// decompose it, failing if a bit above the last `message_size` ones, which
+/// are the ones hashed, is set.
+pub(crate) fn value_bits<E: PairingEngine>(
+    e: &Integer,
+    message_size: u16,
+) -> Result<Vec<bool>, HashToPrimeError> {
+    let value = integer_to_bigint_mod_q::<E::G1Projective>(e)?;
+    let raw_bits = value.into_repr().to_bits_be();
+    let size_in_bits = <E::Fr as PrimeField>::size_in_bits();
+    if raw_bits.len() < size_in_bits || size_in_bits < message_size as usize {
+        return Err(HashToPrimeError::InvalidValueSize);
+    }
+    if raw_bits[..raw_bits.len() - message_size as usize]
+        .iter()
+        .any(|b| *b)
+    {
+        return Err(HashToPrimeError::ValueTooBig);
+    }
+    Ok(raw_bits[raw_bits.len() - size_in_bits..].to_vec())
+}
+
+/// Checks the limits of the hash-based backends: the hashed message has to
+/// fit in the scalar field, below its top bit, and the index or offset in a
+/// `u64`. Misconfigurations fail at setup rather than during the synthesis
+/// of the circuit or the search for a prime.
+#[cfg(feature = "prover")]
+pub(crate) fn validate_limits<E: PairingEngine>(
+    message_size: u16,
+    index_bit_length: u64,
+) -> Result<(), SetupError> {
+    if message_size as usize >= <E::Fr as PrimeField>::size_in_bits() || index_bit_length >= 64 {
+        return Err(SetupError::UnsupportedParameters);
+    }
+    Ok(())
+}
+
+/// The largest elements `value_bits` accepts.
+pub(crate) fn max_message_bits<E: PairingEngine>(message_size: u16) -> u16 {
+    message_size.min(<E::Fr as PrimeField>::size_in_bits() as u16 - 1)
+}
+
+/// Searches for the smallest index hashing `e` to a prime, returning the
+/// prime and the index.
+pub(crate) fn search_prime<E: PairingEngine, P: HashToPrimeHashParameters, O: ProgressObserver>(
+    parameters: &Parameters,
+    e: &Integer,
+    observer: &O,
+) -> Result<(Integer, u64), HashToPrimeError> {
+    let encoding = MessageEncoding::new(
+        P::MESSAGE_SIZE,
+        P::index_bit_length(parameters.security_level),
+    );
+    let index_bit_length = encoding.index_bit_length;
+    let value_bits = value_bits::<E>(e, P::MESSAGE_SIZE)?;
+    for index in 0..1 << index_bit_length {
+        if !report(
+            observer,
+            Stage::HashToPrimeSearch,
+            index,
+            1 << index_bit_length,
+        ) {
+            return Err(HashToPrimeError::Cancelled);
+        }
+        let message = encoding
+            .encode(&encoding.index_bits(index), &value_bits, false)
+            .ok_or(HashToPrimeError::InvalidValueSize)?;
+        let hash_bits = hash_bits(message, parameters.hash_to_prime_bits);
+
+        let element = E::Fr::from_repr(<E::Fr as PrimeField>::BigInt::from_bits_be(&hash_bits))
+            .ok_or(HashToPrimeError::InvalidValueSize)?;
+        let integer = bigint_to_integer::<E::G1Projective>(&element);
+        let rounds = miller_rabin_rounds(parameters);
+        if integer.is_probably_prime(rounds) == IsPrime::No {
+            continue;
+        }
+
+        observer.on_hash_to_prime(&HashToPrimeStats {
+            candidates: index + 1,
+            miller_rabin_rounds: rounds,
+        });
+        return Ok((integer, index));
+    }
+
+    Err(HashToPrimeError::CouldNotFindIndex)
+}
+
+#[cfg(feature = "prover")]
 pub struct HashToPrimeHashCircuit<E: PairingEngine, P: HashToPrimeHashParameters> {
     security_level: u16,
     required_bit_size: u16,
     value: Option<E::Fr>,
     index: Option<u64>,
-    parameters_type: std::marker::PhantomData<P>,
+    parameters_type: std::marker::PhantomData<fn() -> P>,
 }
 
+#[cfg(feature = "prover")]
 impl<E: PairingEngine, P: HashToPrimeHashParameters> ConstraintSynthesizer<E::Fr>
     for HashToPrimeHashCircuit<E, P>
 {
-    fn generate_constraints(
-        self,
-        cs: ConstraintSystemRef<E::Fr>,
-    ) -> Result<(), SynthesisError> {
-        let f = FpVar::new_variable(ark_relations::ns!(cs, "alloc value"), || self.value.get(), AllocationMode::Witness)?;
+    fn generate_constraints(self, cs: ConstraintSystemRef<E::Fr>) -> Result<(), SynthesisError> {
+        let f = FpVar::new_variable(
+            ark_relations::ns!(cs, "alloc value"),
+            || self.value.get(),
+            AllocationMode::Witness,
+        )?;
         let mut index_bits = vec![];
-        let index_bit_length = P::index_bit_length(self.security_level);
-        if index_bit_length > 64 {
+        let encoding =
+            MessageEncoding::new(P::MESSAGE_SIZE, P::index_bit_length(self.security_level));
+        if encoding.index_bit_length > 64 {
             return Err(SynthesisError::Unsatisfiable);
         }
-        for i in 0..index_bit_length {
+        for i in 0..encoding.index_bit_length {
             index_bits.push(Boolean::new_variable(
                 ark_relations::ns!(cs, "alloc bit"),
                 || {
-                    if self.index.is_none() {
-                        Err(SynthesisError::AssignmentMissing)
-                    } else {
-                        let mask = 1u64 << i;
-                        Ok((mask & self.index.unwrap()) == mask)
-                    }
+                    let index = self.index.ok_or(SynthesisError::AssignmentMissing)?;
+                    let mask = 1u64 << i;
+                    Ok((mask & index) == mask)
                 },
                 AllocationMode::Witness,
             )?);
         }
         // big-endian bits
         let bits = f.to_bits_be()?;
-        let bits_to_hash: Vec<Boolean<E::Fr>> = [
-            index_bits.as_slice(),
-            &bits[<E::Fr as PrimeField>::size_in_bits() - P::MESSAGE_SIZE as usize..],
-        ]
-        .concat();
-        let bits_to_hash_padded = if bits_to_hash.len() % 8 != 0 {
-            let padding_length = 8 - bits_to_hash.len() % 8;
-            [
-                &vec![Boolean::constant(false); padding_length][..],
-                bits_to_hash.as_slice(),
-            ]
-            .concat()
-        } else {
-            bits_to_hash
-        };
-
-        let hash_result = evaluate_blake2s(&bits_to_hash_padded)?;
-        let hash_bits = hash_result
-            .into_iter()
-            .map(|n| n.to_bits_le())
-            .flatten()
-            .collect::<Vec<Boolean<E::Fr>>>();
-
-        let hash_bits = hash_bits
-            .into_iter()
-            .take((self.required_bit_size - 1) as usize)
-            .collect::<Vec<_>>();
-        let hash_bits = [&[Boolean::constant(true)][..], &hash_bits].concat();
-        let result = FpVar::new_variable(ark_relations::ns!(cs, "prime"), || {
-            if hash_bits.iter().any(|x| x.value().is_err()) {
-                Err(SynthesisError::AssignmentMissing)
-            } else {
-                Ok(E::Fr::from_repr(<E::Fr as PrimeField>::BigInt::from_bits_be(
-                    &hash_bits
-                        .iter()
-                        .map(|x| x.value().unwrap())
-                        .collect::<Vec<_>>(),
-                )).unwrap())
-            }
-        }, AllocationMode::Input)?;
+        let message = encoding
+            .encode(&index_bits, &bits, Boolean::constant(false))
+            .ok_or(SynthesisError::Unsatisfiable)?;
+        let hash_bits = hash_bits_gadget(message, self.required_bit_size)?;
+        let result = FpVar::new_variable(
+            ark_relations::ns!(cs, "prime"),
+            || {
+                let hash_bits = hash_bits
+                    .iter()
+                    .map(|x| x.value())
+                    .collect::<Result<Vec<_>, _>>()?;
+                E::Fr::from_repr(<E::Fr as PrimeField>::BigInt::from_bits_be(&hash_bits))
+                    .ok_or(SynthesisError::Unsatisfiable)
+            },
+            AllocationMode::Input,
+        )?;
         let result_bits = result.to_bits_be()?;
         for b in result_bits
             .iter()
             .take(<E::Fr as PrimeField>::size_in_bits() - self.required_bit_size as usize)
         {
-            b.enforce_equal(
-                &Boolean::constant(false),
-            )?;
+            b.enforce_equal(&Boolean::constant(false))?;
         }
-        for (h, r) in hash_bits
-            .iter()
-            .zip(
-                result_bits
-                    .iter()
-                    .skip(<E::Fr as PrimeField>::size_in_bits() - self.required_bit_size as usize),
-            )
-        {
+        for (h, r) in hash_bits.iter().zip(
+            result_bits
+                .iter()
+                .skip(<E::Fr as PrimeField>::size_in_bits() - self.required_bit_size as usize),
+        ) {
             h.enforce_equal(&r)?;
         }
 
@@ -160,11 +336,20 @@ impl<E: PairingEngine, P: HashToPrimeHashParameters> HashToPrimeProtocol<E::G1Pr
         }
     }
 
-    fn setup<R: Rng>(
+    #[cfg(feature = "prover")]
+    fn setup_with_observer<R: Rng, O: ProgressObserver>(
         rng: &mut R,
         pedersen_commitment_parameters: &PedersenCommitment<E::G1Projective>,
         parameters: &Parameters,
+        observer: &O,
     ) -> Result<Self::Parameters, SetupError> {
+        validate_limits::<E>(
+            P::MESSAGE_SIZE,
+            P::index_bit_length(parameters.security_level),
+        )?;
+        if !report(observer, Stage::HashToPrimeSetup, 0, 1) {
+            return Err(SetupError::Cancelled);
+        }
         let c = HashToPrimeHashCircuit::<E, P> {
             security_level: parameters.security_level,
             required_bit_size: parameters.hash_to_prime_bits,
@@ -172,22 +357,64 @@ impl<E: PairingEngine, P: HashToPrimeHashParameters> HashToPrimeProtocol<E::G1Pr
             index: None,
             parameters_type: std::marker::PhantomData,
         };
-        let base_one = E::G1Projective::rand(rng);
-        let pedersen_bases = vec![
-            base_one,
-            pedersen_commitment_parameters.g,
-            pedersen_commitment_parameters.h,
-        ];
-        Ok(legogro16::generate_random_parameters(
-            c,
-            &pedersen_bases
-                .into_iter()
-                .map(|p| p.into_affine())
-                .collect::<Vec<_>>(),
+        let hash_to_prime_parameters = generate_linked_parameters_with_observer::<E, _, _, _>(
             rng,
-        )?)
+            c,
+            pedersen_commitment_parameters,
+            &[],
+            Self::circuit_stats(parameters)?.public_inputs,
+            observer,
+        )?;
+        observer.on_progress(Stage::HashToPrimeSetup, 1, 1);
+        Ok(hash_to_prime_parameters)
+    }
+
+    #[cfg(not(feature = "prover"))]
+    fn setup_with_observer<R: Rng, O: ProgressObserver>(
+        _: &mut R,
+        _: &PedersenCommitment<E::G1Projective>,
+        _: &Parameters,
+        _: &O,
+    ) -> Result<Self::Parameters, SetupError> {
+        Err(SetupError::ProverNotEnabled)
+    }
+
+    #[cfg(feature = "prover")]
+    fn circuit_stats(parameters: &Parameters) -> Result<CircuitStats, SetupError> {
+        validate_limits::<E>(
+            P::MESSAGE_SIZE,
+            P::index_bit_length(parameters.security_level),
+        )?;
+        let c = HashToPrimeHashCircuit::<E, P> {
+            security_level: parameters.security_level,
+            required_bit_size: parameters.hash_to_prime_bits,
+            value: None,
+            index: None,
+            parameters_type: std::marker::PhantomData,
+        };
+        let cs = ConstraintSystem::<E::Fr>::new_ref();
+        cs.set_mode(SynthesisMode::Setup);
+        c.generate_constraints(cs.clone())?;
+        Ok(CircuitStats {
+            constraints: cs.num_constraints(),
+            variables: cs.num_instance_variables() + cs.num_witness_variables(),
+            // the first instance variable is the constant one
+            public_inputs: cs.num_instance_variables() - 1,
+        })
+    }
+
+    /// The constraints are counted by synthesizing the circuit, which needs
+    /// the `prover` feature.
+    #[cfg(not(feature = "prover"))]
+    fn circuit_stats(_: &Parameters) -> Result<CircuitStats, SetupError> {
+        Err(SetupError::ProverNotEnabled)
+    }
+
+    fn estimate_sizes(parameters: &Parameters) -> Result<HashToPrimeSizes, SetupError> {
+        Ok(legogro16_sizes::<E>(&Self::circuit_stats(parameters)?))
     }
 
+    #[cfg(feature = "prover")]
     fn prove<R: Rng, C: HashToPrimeVerifierChannel<E::G1Projective, Self>>(
         &self,
         verifier_channel: &mut C,
@@ -218,115 +445,121 @@ impl<E: PairingEngine, P: HashToPrimeHashParameters> HashToPrimeProtocol<E::G1Pr
         Ok(())
     }
 
+    #[cfg(not(feature = "prover"))]
+    fn prove<R: Rng, C: HashToPrimeVerifierChannel<E::G1Projective, Self>>(
+        &self,
+        _: &mut C,
+        _: &mut R,
+        _: &Statement<E::G1Projective>,
+        _: &Witness,
+    ) -> Result<(), ProofError> {
+        Err(ProofError::ProverNotEnabled)
+    }
+
     fn verify<C: HashToPrimeProverChannel<E::G1Projective, Self>>(
         &self,
         prover_channel: &mut C,
         statement: &Statement<E::G1Projective>,
     ) -> Result<(), VerificationError> {
         let proof = prover_channel.receive_proof()?;
+        validate_link_bases(
+            &self.crs.hash_to_prime_parameters.vk,
+            ELEMENT_COMMITTED_INPUTS,
+        )?;
         let pvk = legogro16::prepare_verifying_key(&self.crs.hash_to_prime_parameters.vk);
-        if !legogro16::verify_proof(&pvk, &proof)? {
-            return Err(VerificationError::VerificationFailed);
-        }
+        let is_valid = legogro16::verify_proof(&pvk, &proof)?;
         let proof_link_d_without_one = proof
             .link_d
             .into_projective()
             .sub(&self.crs.hash_to_prime_parameters.vk.link_bases[0].into_projective());
-        if statement.c_e_q != proof_link_d_without_one {
-            return Err(VerificationError::VerificationFailed);
+        // both checks run, as in the sigma protocols
+        if is_valid & (statement.c_e_q == proof_link_d_without_one) {
+            Ok(())
+        } else {
+            Err(VerificationError::VerificationFailed)
         }
+    }
 
-        Ok(())
+    fn verify_batch<R: RngCore + CryptoRng>(
+        &self,
+        rng: &mut R,
+        statements: &[Statement<E::G1Projective>],
+        proofs: &[Self::Proof],
+    ) -> Result<(), VerificationError> {
+        verify_linked_batch(
+            &self.crs.hash_to_prime_parameters.vk,
+            rng,
+            statements,
+            proofs,
+        )
+    }
+
+    fn proof_to_bytes(proof: &Self::Proof) -> Result<Vec<u8>, CurveError> {
+        let mut bytes = vec![];
+        proof.serialize(&mut bytes)?;
+        Ok(bytes)
+    }
+
+    fn parameters_to_bytes(parameters: &Self::Parameters) -> Result<Vec<u8>, CurveError> {
+        let mut bytes = vec![];
+        parameters.vk.serialize(&mut bytes)?;
+        Ok(bytes)
+    }
+
+    fn max_element_bits(_: &Parameters) -> u16 {
+        max_message_bits::<E>(P::MESSAGE_SIZE)
+    }
+
+    fn maps_to_unique_prime(_: &Parameters) -> bool {
+        false
     }
 
     fn hash_to_prime(&self, e: &Integer) -> Result<(Integer, u64), HashToPrimeError> {
-        let index_bit_length = P::index_bit_length(self.crs.parameters.security_level);
-        let value = integer_to_bigint_mod_q::<E::G1Projective>(e)?;
-        let bigint_bits = 64 * ((E::Fr::one().neg().into_repr().num_bits() + 63) / 64);
-        let bits_to_skip = bigint_bits as usize - P::MESSAGE_SIZE as usize;
-        let value_raw_bits = value.into_repr().to_bits_be();
-        for b in &value_raw_bits[..bits_to_skip] {
-            if *b {
-                return Err(HashToPrimeError::ValueTooBig);
-            }
-        }
-        let mut value_bits = value_raw_bits[bits_to_skip..].to_vec();
-        if value_bits.len() < P::MESSAGE_SIZE as usize {
-            value_bits = [
-                vec![false; P::MESSAGE_SIZE as usize - value_bits.len()],
-                value_bits,
-            ]
-            .concat();
-        }
-        for index in 0..1 << index_bit_length {
-            let mut index_bits = vec![];
-            for i in 0..index_bit_length {
-                let mask = 1u64 << i;
-                let bit = mask & index == mask;
-                index_bits.push(bit);
-            }
-            let bits_to_hash = [index_bits.as_slice(), &value_bits].concat();
-            let bits_to_hash_padded = if bits_to_hash.len() % 8 != 0 {
-                let padding_length = 8 - bits_to_hash.len() % 8;
-                [&vec![false; padding_length][..], bits_to_hash.as_slice()].concat()
-            } else {
-                bits_to_hash
-            };
-            let bits_big_endian = bits_to_hash_padded.into_iter().rev().collect::<Vec<_>>();
-            let bytes_to_hash = bits_big_endian_to_bytes_big_endian(&bits_big_endian)
-                .into_iter()
-                .rev()
-                .collect::<Vec<_>>();
-            let mut hasher = Blake2s::default();
-            hasher.update(&bytes_to_hash);
-            let hash = hasher.finalize();
-            let hash_big_endian = hash.into_iter().rev().collect::<Vec<_>>();
-            let hash_bits = [
-                vec![true].as_slice(),
-                bytes_big_endian_to_bits_big_endian(&hash_big_endian)
-                    .into_iter()
-                    .rev()
-                    .take(self.crs.parameters.hash_to_prime_bits as usize - 1)
-                    .collect::<Vec<_>>()
-                    .as_slice(),
-            ]
-            .concat();
-
-            let element = E::Fr::from_repr(<E::Fr as PrimeField>::BigInt::from_bits_be(&hash_bits)).unwrap();
-            let integer = bigint_to_integer::<E::G1Projective>(&element);
-            // from the gmp documentation: "A composite number will be identified as a prime with an asymptotic probability of less than 4^(-reps)", so we choose reps = security_level/2
-            let is_prime = integer.is_probably_prime(self.crs.parameters.security_level as u32 / 2);
-            if is_prime == IsPrime::No {
-                continue;
-            }
-
-            return Ok((integer, index));
-        }
+        self.hash_to_prime_with_observer(e, &NoProgress)
+    }
 
-        Err(HashToPrimeError::CouldNotFindIndex)
+    fn hash_to_prime_with_observer<O: ProgressObserver>(
+        &self,
+        e: &Integer,
+        observer: &O,
+    ) -> Result<(Integer, u64), HashToPrimeError> {
+        search_prime::<E, P, O>(&self.crs.parameters, e, observer)
     }
 }
 
 #[cfg(test)]
 mod test {
-    use super::{HashToPrimeHashCircuit, HashToPrimeHashParameters, Protocol, Statement, Witness};
+    use super::{
+        hash_bits, hash_bits_gadget, value_bits, HashToPrimeHashCircuit, HashToPrimeHashParameters,
+        MessageEncoding, Protocol, Statement, Witness,
+    };
     use crate::{
-        commitments::Commitment,
+        commitments::{pedersen::PedersenCommitment, Commitment},
         parameters::Parameters,
-        protocols::hash_to_prime::{
-            snark_hash::Protocol as HPProtocol,
-            transcript::{TranscriptProverChannel, TranscriptVerifierChannel},
-            HashToPrimeProtocol,
+        protocols::{
+            hash_to_prime::{
+                miller_rabin_rounds,
+                snark_hash::Protocol as HPProtocol,
+                transcript::{TranscriptProverChannel, TranscriptVerifierChannel},
+                verify_canonical_prime, HashToPrimeProtocol,
+            },
+            progress::{HashToPrimeStats, HashToPrimeStatsCollector},
+            SetupError,
         },
-        utils::integer_to_bigint_mod_q,
+        utils::{bigint_to_integer, integer_to_bigint_mod_q},
     };
     use accumulator::group::Rsa2048;
     use ark_bls12_381::{Bls12_381, Fr, G1Projective};
-    use merlin::Transcript;
+    use ark_ff::UniformRand;
+    use ark_r1cs_std::{
+        alloc::AllocVar, bits::ToBitsGadget, boolean::Boolean, fields::fp::FpVar, R1CSVar,
+    };
     use ark_relations::r1cs::{ConstraintSynthesizer, ConstraintSystem};
+    use merlin::Transcript;
     use rand::thread_rng;
+    use rand::Rng;
     use rug::rand::RandState;
-    use rug::Integer;
+    use rug::{integer::IsPrime, Integer};
     use std::cell::RefCell;
 
     struct TestParameters {}
@@ -334,6 +567,91 @@ mod test {
         const MESSAGE_SIZE: u16 = 254;
     }
 
+    struct WideParameters {}
+    impl HashToPrimeHashParameters for WideParameters {
+        const MESSAGE_SIZE: u16 = 256;
+    }
+
+    #[test]
+    fn test_limits() {
+        let params = Parameters::from_security_level(128).unwrap();
+        assert_eq!(
+            Protocol::<Bls12_381, TestParameters>::max_element_bits(&params),
+            254
+        );
+        // the message doesn't fit in the scalar field of BLS12-381
+        assert!(matches!(
+            Protocol::<Bls12_381, WideParameters>::circuit_stats(&params),
+            Err(SetupError::UnsupportedParameters)
+        ));
+        let mut rng = thread_rng();
+        let pedersen_commitment_parameters = PedersenCommitment::<G1Projective>::setup(&mut rng);
+        assert!(matches!(
+            Protocol::<Bls12_381, WideParameters>::setup(
+                &mut rng,
+                &pedersen_commitment_parameters,
+                &params
+            ),
+            Err(SetupError::UnsupportedParameters)
+        ));
+    }
+
+    #[test]
+    fn test_message_encoding() {
+        let encoding = MessageEncoding::new(4, 3);
+        assert_eq!(encoding.index_bits(6), vec![false, true, true]);
+        assert_eq!(
+            encoding.encode(&[1, 2, 3], &[9, 9, 4, 5, 6, 7], 0),
+            Some(vec![0, 1, 2, 3, 4, 5, 6, 7])
+        );
+        assert_eq!(encoding.encode(&[1, 2], &[4, 5, 6, 7], 0), None);
+        assert_eq!(encoding.encode(&[1, 2, 3], &[5, 6, 7], 0), None);
+    }
+
+    #[test]
+    fn test_native_and_circuit_hashes() {
+        let params = Parameters::from_security_level(128).unwrap();
+        let mut rng = thread_rng();
+        let encoding = MessageEncoding::new(
+            TestParameters::MESSAGE_SIZE,
+            TestParameters::index_bit_length(params.security_level),
+        );
+        let message_bound = Integer::from(Integer::u_pow_u(2, TestParameters::MESSAGE_SIZE as u32));
+        for _ in 0..2000 {
+            let value = bigint_to_integer::<G1Projective>(&Fr::rand(&mut rng)) % &message_bound;
+            let index = rng.gen_range(0, 1u64 << encoding.index_bit_length);
+
+            let native_bits =
+                value_bits::<Bls12_381>(&value, TestParameters::MESSAGE_SIZE).unwrap();
+            let native_message = encoding
+                .encode(&encoding.index_bits(index), &native_bits, false)
+                .unwrap();
+            let native_hash = hash_bits(native_message.clone(), params.hash_to_prime_bits);
+
+            let cs = ConstraintSystem::<Fr>::new_ref();
+            let f = FpVar::new_witness(cs.clone(), || {
+                Ok(integer_to_bigint_mod_q::<G1Projective>(&value).unwrap())
+            })
+            .unwrap();
+            let index_bits = encoding
+                .index_bits(index)
+                .into_iter()
+                .map(|b| Boolean::new_witness(cs.clone(), || Ok(b)).unwrap())
+                .collect::<Vec<_>>();
+            let message = encoding
+                .encode(
+                    &index_bits,
+                    &f.to_bits_be().unwrap(),
+                    Boolean::constant(false),
+                )
+                .unwrap();
+            assert_eq!(message.value().unwrap(), native_message);
+            let hash = hash_bits_gadget(message, params.hash_to_prime_bits).unwrap();
+            assert_eq!(hash.value().unwrap(), native_hash);
+            assert!(cs.is_satisfied().unwrap());
+        }
+    }
+
     #[test]
     fn test_circuit() {
         let cs = ConstraintSystem::<Fr>::new_ref();
@@ -353,7 +671,19 @@ mod test {
         let protocol = Protocol::<Bls12_381, TestParameters>::from_crs(&crs);
 
         let value = Integer::from(12);
-        let (_, index) = protocol.hash_to_prime(&value).unwrap();
+        let collector = HashToPrimeStatsCollector::new();
+        let (_, index) = protocol
+            .hash_to_prime_with_observer(&value, &collector)
+            .unwrap();
+        let summary = collector.summary();
+        assert_eq!(summary.primes, 1);
+        assert_eq!(
+            summary.last,
+            Some(HashToPrimeStats {
+                candidates: index + 1,
+                miller_rabin_rounds: miller_rabin_rounds(&params),
+            })
+        );
         let c = HashToPrimeHashCircuit::<Bls12_381, TestParameters> {
             security_level: crs.parameters.security_level,
             required_bit_size: crs.parameters.hash_to_prime_bits,
@@ -370,6 +700,75 @@ mod test {
         }
     }
 
+    #[test]
+    fn test_non_canonical_index() {
+        let params = Parameters::from_security_level(128).unwrap();
+        let mut rng1 = RandState::new();
+        rng1.seed(&Integer::from(13));
+        let mut rng2 = thread_rng();
+
+        let crs = crate::protocols::membership::Protocol::<
+            Rsa2048,
+            G1Projective,
+            HPProtocol<Bls12_381, TestParameters>,
+        >::setup(&params, &mut rng1, &mut rng2)
+        .unwrap()
+        .crs
+        .crs_hash_to_prime;
+        let protocol = Protocol::<Bls12_381, TestParameters>::from_crs(&crs);
+
+        let value = Integer::from(12);
+        let (prime, index) = protocol.hash_to_prime(&value).unwrap();
+        // the prime of the next index giving one
+        let encoding = MessageEncoding::new(
+            TestParameters::MESSAGE_SIZE,
+            TestParameters::index_bit_length(params.security_level),
+        );
+        let value_bits = value_bits::<Bls12_381>(&value, TestParameters::MESSAGE_SIZE).unwrap();
+        let (other_prime, other_index) = (index + 1..1 << encoding.index_bit_length)
+            .map(|i| {
+                let message = encoding
+                    .encode(&encoding.index_bits(i), &value_bits, false)
+                    .unwrap();
+                let bits = hash_bits(message, params.hash_to_prime_bits);
+                let candidate = bits
+                    .iter()
+                    .fold(Integer::from(0), |acc, b| (acc << 1) + *b as u32);
+                (candidate, i)
+            })
+            .find(|(candidate, _)| {
+                candidate.is_probably_prime(miller_rabin_rounds(&params)) != IsPrime::No
+            })
+            .unwrap();
+        assert_ne!(other_prime, prime);
+
+        // the circuit accepts the other index
+        let cs = ConstraintSystem::<Fr>::new_ref();
+        HashToPrimeHashCircuit::<Bls12_381, TestParameters> {
+            security_level: crs.parameters.security_level,
+            required_bit_size: crs.parameters.hash_to_prime_bits,
+            value: Some(integer_to_bigint_mod_q::<G1Projective>(&value).unwrap()),
+            index: Some(other_index),
+            parameters_type: std::marker::PhantomData,
+        }
+        .generate_constraints(cs.clone())
+        .unwrap();
+        assert!(cs.is_satisfied().unwrap());
+
+        // while the canonical index mode only accepts the first prime
+        let randomness = Integer::from(9);
+        let statement = |p: &Integer| Statement {
+            c_e_q: crs
+                .pedersen_commitment_parameters
+                .commit(p, &randomness)
+                .unwrap(),
+        };
+        verify_canonical_prime(&crs, &statement(&prime), &value, &randomness).unwrap();
+        assert!(
+            verify_canonical_prime(&crs, &statement(&other_prime), &value, &randomness).is_err()
+        );
+    }
+
     #[test]
     fn test_proof() {
         let params = Parameters::from_security_level(128).unwrap();
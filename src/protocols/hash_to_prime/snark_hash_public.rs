@@ -0,0 +1,313 @@
+//! LegoGroth16-based proof that a disclosed element hashes to a prime with
+//! the mapping of `snark_hash`, keeping only the index hidden.
+//!
+//! This lets an accumulator manager show that it accumulated exactly the
+//! prime of `e` for an `e` it discloses, e.g. to the user who submitted it,
+//! without revealing the index and so without the user repeating the search.
+//! Both the element and the prime are public inputs: the verifier recomputes
+//! their commitment in the proof from the link bases instead of receiving a
+//! Pedersen commitment.
+//!
+//! Constraints: compared to `snark_hash`, the prime isn't decomposed into
+//! bits, as it's linked to the hash by a single equality, and the element is
+//! decomposed without the check that its bits are below the modulus, as its
+//! bits above the message are zero. The hash dominates, so the circuit is
+//! smaller rather than much smaller, see `test_constraints`.
+//!
+//! Soundness: as in `snark_hash`, the index isn't enforced to be the smallest
+//! one, so the proof shows the prime is one of the primes of `e`. The element
+//! being public, the verifier can compare the prime to `hash_to_prime` of
+//! `e`, which only costs the search.
+#[cfg(feature = "prover")]
+use crate::{
+    commitments::pedersen::PedersenCommitment,
+    protocols::{
+        hash_to_prime::{
+            link::generate_linked_parameters,
+            snark_hash::{hash_bits_gadget, search_prime, validate_limits, MessageEncoding},
+            snark_increment::bits_to_fp_var,
+            CircuitStats,
+        },
+        progress::NoProgress,
+        ProofError, SetupError,
+    },
+};
+use crate::{
+    parameters::Parameters,
+    protocols::{
+        hash_to_prime::{
+            link::validate_link_bases,
+            miller_rabin_rounds,
+            snark_hash::{value_bits, HashToPrimeHashParameters},
+        },
+        VerificationError,
+    },
+    utils::integer_to_bigint_mod_q,
+};
+use ark_ec::{AffineCurve, PairingEngine, ProjectiveCurve};
+use ark_ff::PrimeField;
+#[cfg(feature = "prover")]
+use ark_ff::{UniformRand, Zero};
+#[cfg(feature = "prover")]
+use ark_r1cs_std::{
+    alloc::{AllocVar, AllocationMode},
+    bits::ToBitsGadget,
+    boolean::Boolean,
+    eq::EqGadget,
+    fields::fp::FpVar,
+    Assignment, R1CSVar,
+};
+#[cfg(feature = "prover")]
+use ark_relations::r1cs::{
+    ConstraintSynthesizer, ConstraintSystem, ConstraintSystemRef, SynthesisError, SynthesisMode,
+};
+#[cfg(feature = "prover")]
+use rand::Rng;
+use rug::{integer::IsPrime, Integer};
+
+/// The committed inputs of the circuit: the element and the prime.
+pub const PUBLIC_COMMITTED_INPUTS: usize = 2;
+
+#[cfg(feature = "prover")]
+pub struct PublicHashToPrimeCircuit<E: PairingEngine, P: HashToPrimeHashParameters> {
+    security_level: u16,
+    required_bit_size: u16,
+    value: Option<E::Fr>,
+    index: Option<u64>,
+    parameters_type: std::marker::PhantomData<P>,
+}
+
+#[cfg(feature = "prover")]
+impl<E: PairingEngine, P: HashToPrimeHashParameters> ConstraintSynthesizer<E::Fr>
+    for PublicHashToPrimeCircuit<E, P>
+{
+    fn generate_constraints(self, cs: ConstraintSystemRef<E::Fr>) -> Result<(), SynthesisError> {
+        let f = FpVar::new_variable(
+            ark_relations::ns!(cs, "alloc value"),
+            || self.value.get(),
+            AllocationMode::Input,
+        )?;
+        let mut index_bits = vec![];
+        let encoding =
+            MessageEncoding::new(P::MESSAGE_SIZE, P::index_bit_length(self.security_level));
+        if encoding.index_bit_length > 64 {
+            return Err(SynthesisError::Unsatisfiable);
+        }
+        for i in 0..encoding.index_bit_length {
+            index_bits.push(Boolean::new_variable(
+                ark_relations::ns!(cs, "alloc bit"),
+                || {
+                    let index = self.index.ok_or(SynthesisError::AssignmentMissing)?;
+                    let mask = 1u64 << i;
+                    Ok((mask & index) == mask)
+                },
+                AllocationMode::Witness,
+            )?);
+        }
+        // big-endian bits, unique once those above the message are zero, as
+        // the message is below the top bit of the modulus
+        let bits = f.to_non_unique_bits_be()?;
+        let size_in_bits = <E::Fr as PrimeField>::size_in_bits();
+        if bits.len() != size_in_bits || size_in_bits <= P::MESSAGE_SIZE as usize {
+            return Err(SynthesisError::Unsatisfiable);
+        }
+        for b in bits.iter().take(size_in_bits - P::MESSAGE_SIZE as usize) {
+            b.enforce_equal(&Boolean::constant(false))?;
+        }
+        let message = encoding
+            .encode(&index_bits, &bits, Boolean::constant(false))
+            .ok_or(SynthesisError::Unsatisfiable)?;
+        let hash_bits = hash_bits_gadget(message, self.required_bit_size)?;
+        // the hash has fewer bits than the modulus, so the packing is exact
+        let candidate = bits_to_fp_var(&hash_bits)?;
+
+        let result = FpVar::new_variable(
+            ark_relations::ns!(cs, "prime"),
+            || candidate.value(),
+            AllocationMode::Input,
+        )?;
+        result.enforce_equal(&candidate)?;
+
+        Ok(())
+    }
+}
+
+/// Proves and verifies that public elements hash to public primes. Setting
+/// up and proving need the `prover` feature.
+pub struct Protocol<E: PairingEngine, P: HashToPrimeHashParameters> {
+    pub parameters: Parameters,
+    pub proving_key: legogro16::ProvingKey<E>,
+    parameters_type: std::marker::PhantomData<P>,
+}
+
+impl<E: PairingEngine, P: HashToPrimeHashParameters> Clone for Protocol<E, P> {
+    fn clone(&self) -> Self {
+        Protocol {
+            parameters: self.parameters.clone(),
+            proving_key: self.proving_key.clone(),
+            parameters_type: std::marker::PhantomData,
+        }
+    }
+}
+
+impl<E: PairingEngine, P: HashToPrimeHashParameters> Protocol<E, P> {
+    #[cfg(feature = "prover")]
+    fn circuit(parameters: &Parameters) -> PublicHashToPrimeCircuit<E, P> {
+        PublicHashToPrimeCircuit {
+            security_level: parameters.security_level,
+            required_bit_size: parameters.hash_to_prime_bits,
+            value: None,
+            index: None,
+            parameters_type: std::marker::PhantomData,
+        }
+    }
+
+    #[cfg(feature = "prover")]
+    pub fn setup<R: Rng>(
+        rng: &mut R,
+        pedersen_commitment_parameters: &PedersenCommitment<E::G1Projective>,
+        parameters: &Parameters,
+    ) -> Result<Protocol<E, P>, SetupError> {
+        validate_limits::<E>(
+            P::MESSAGE_SIZE,
+            P::index_bit_length(parameters.security_level),
+        )?;
+        let proving_key = generate_linked_parameters::<E, _, _>(
+            rng,
+            Self::circuit(parameters),
+            pedersen_commitment_parameters,
+            &[E::G1Projective::rand(rng)],
+            PUBLIC_COMMITTED_INPUTS,
+        )?;
+        Ok(Protocol {
+            parameters: parameters.clone(),
+            proving_key,
+            parameters_type: std::marker::PhantomData,
+        })
+    }
+
+    #[cfg(feature = "prover")]
+    pub fn circuit_stats(parameters: &Parameters) -> Result<CircuitStats, SetupError> {
+        validate_limits::<E>(
+            P::MESSAGE_SIZE,
+            P::index_bit_length(parameters.security_level),
+        )?;
+        let cs = ConstraintSystem::<E::Fr>::new_ref();
+        cs.set_mode(SynthesisMode::Setup);
+        Self::circuit(parameters).generate_constraints(cs.clone())?;
+        Ok(CircuitStats {
+            constraints: cs.num_constraints(),
+            variables: cs.num_instance_variables() + cs.num_witness_variables(),
+            // the first instance variable is the constant one
+            public_inputs: cs.num_instance_variables() - 1,
+        })
+    }
+
+    /// Hashes `e` to its prime, as `snark_hash` does, returning the prime and
+    /// a proof that it's a hash of `e`.
+    #[cfg(feature = "prover")]
+    pub fn prove<R: Rng>(
+        &self,
+        rng: &mut R,
+        e: &Integer,
+    ) -> Result<(Integer, legogro16::Proof<E>), ProofError> {
+        let (prime, index) = search_prime::<E, P, _>(&self.parameters, e, &NoProgress)?;
+        let c = PublicHashToPrimeCircuit::<E, P> {
+            index: Some(index),
+            value: Some(integer_to_bigint_mod_q::<E::G1Projective>(e)?),
+            ..Self::circuit(&self.parameters)
+        };
+        let v = E::Fr::rand(rng);
+        // the inputs are public, so the commitment to them isn't hiding
+        let proof =
+            legogro16::create_random_proof::<E, _, _>(c, v, E::Fr::zero(), &self.proving_key, rng)?;
+        Ok((prime, proof))
+    }
+
+    /// Verifies that `prime` is a prime `e` hashes to.
+    pub fn verify(
+        &self,
+        e: &Integer,
+        prime: &Integer,
+        proof: &legogro16::Proof<E>,
+    ) -> Result<(), VerificationError> {
+        let vk = &self.proving_key.vk;
+        validate_link_bases(vk, PUBLIC_COMMITTED_INPUTS)?;
+        if value_bits::<E>(e, P::MESSAGE_SIZE).is_err()
+            || prime.significant_bits() != self.parameters.hash_to_prime_bits as u32
+        {
+            return Err(VerificationError::VerificationFailed);
+        }
+        let pvk = legogro16::prepare_verifying_key(vk);
+        let is_valid = legogro16::verify_proof(&pvk, proof)?;
+        let inputs = vk.link_bases[0].into_projective()
+            + vk.link_bases[1].mul(integer_to_bigint_mod_q::<E::G1Projective>(e)?.into_repr())
+            + vk.link_bases[2].mul(integer_to_bigint_mod_q::<E::G1Projective>(prime)?.into_repr());
+        let is_prime =
+            prime.is_probably_prime(miller_rabin_rounds(&self.parameters)) != IsPrime::No;
+        // all the checks run, as in the sigma protocols
+        if is_valid & (proof.link_d.into_projective() == inputs) & is_prime {
+            Ok(())
+        } else {
+            Err(VerificationError::VerificationFailed)
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Protocol, PUBLIC_COMMITTED_INPUTS};
+    use crate::{
+        commitments::pedersen::PedersenCommitment,
+        parameters::Parameters,
+        protocols::hash_to_prime::{
+            snark_hash::{search_prime, HashToPrimeHashParameters, Protocol as HashProtocol},
+            HashToPrimeProtocol,
+        },
+        protocols::progress::NoProgress,
+    };
+    use ark_bls12_381::{Bls12_381, G1Projective};
+    use rand::thread_rng;
+    use rug::Integer;
+
+    struct TestParameters {}
+    impl HashToPrimeHashParameters for TestParameters {
+        const MESSAGE_SIZE: u16 = 254;
+    }
+
+    #[test]
+    fn test_constraints() {
+        let params = Parameters::from_security_level(128).unwrap();
+        let public = Protocol::<Bls12_381, TestParameters>::circuit_stats(&params).unwrap();
+        let hidden = HashProtocol::<Bls12_381, TestParameters>::circuit_stats(&params).unwrap();
+        assert_eq!(public.public_inputs, PUBLIC_COMMITTED_INPUTS);
+        assert!(public.constraints < hidden.constraints);
+    }
+
+    #[test]
+    fn test_proof() {
+        let params = Parameters::from_security_level(128).unwrap();
+        let mut rng = thread_rng();
+        let pedersen_commitment_parameters = PedersenCommitment::<G1Projective>::setup(&mut rng);
+        let protocol = Protocol::<Bls12_381, TestParameters>::setup(
+            &mut rng,
+            &pedersen_commitment_parameters,
+            &params,
+        )
+        .unwrap();
+
+        let value = Integer::from(13);
+        let (prime, proof) = protocol.prove(&mut rng, &value).unwrap();
+        assert_eq!(
+            prime,
+            search_prime::<Bls12_381, TestParameters, _>(&params, &value, &NoProgress)
+                .unwrap()
+                .0
+        );
+        protocol.verify(&value, &prime, &proof).unwrap();
+
+        let (other_prime, _) = protocol.prove(&mut rng, &Integer::from(14)).unwrap();
+        assert!(protocol.verify(&Integer::from(14), &prime, &proof).is_err());
+        assert!(protocol.verify(&value, &other_prime, &proof).is_err());
+    }
+}
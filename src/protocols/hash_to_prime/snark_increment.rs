@@ -0,0 +1,526 @@
+//! LegoGroth16-based hash-to-prime proof, mapping an element to the first
+//! prime at or after its Blake2s hash.
+//!
+//! Compared to `snark_hash`, which hashes the element together with an index
+//! and searches for an index giving a prime, the element is hashed once and
+//! the prime is `hash + offset`, with the offset a small witness of
+//! `offset_bit_length` bits. This is the increment-until-prime mapping used by
+//! many accumulator deployments, so elements map to the same primes as long as
+//! the hashes agree.
+//!
+//! Constraints: the Blake2s input doesn't contain the index, and the prime is
+//! linked to the hash by a single addition instead of bit-by-bit equalities,
+//! so the circuit is slightly smaller, see `test_constraints`.
+//!
+//! Soundness: like the index in `snark_hash`, the circuit doesn't enforce the
+//! offset to be the smallest one, so a prover can pick any prime in
+//! `[hash, hash + 2^offset_bit_length)`. Both constructions give a prover at
+//! most `2^offset_bit_length` candidate primes per element; here they are
+//! consecutive primes rather than independent hashes, which the security of
+//! membership proofs doesn't depend on. Nonmembership proofs only show one of
+//! those primes isn't accumulated, as with `snark_hash`, and
+//! `nonmembership::Protocol::from_crs_with_unique_prime` rejects the
+//! protocol. Verifiers knowing the element can require the smallest offset
+//! with `verify_canonical_prime`.
+use crate::{
+    commitments::pedersen::PedersenCommitment,
+    parameters::Parameters,
+    protocols::{
+        hash_to_prime::{
+            channel::{HashToPrimeProverChannel, HashToPrimeVerifierChannel},
+            legogro16_sizes,
+            link::{validate_link_bases, ELEMENT_COMMITTED_INPUTS},
+            miller_rabin_rounds,
+            snark_batch::verify_linked_batch,
+            snark_hash::{
+                hash_bits, max_message_bits, value_bits, HashToPrimeHashParameters, MessageEncoding,
+            },
+            CRSHashToPrime, CircuitStats, HashToPrimeError, HashToPrimeProtocol, HashToPrimeSizes,
+            Statement, Witness,
+        },
+        progress::{report, HashToPrimeStats, NoProgress, ProgressObserver, Stage},
+        ProofError, SetupError, VerificationError,
+    },
+    utils::{bigint_to_integer, curve::CurveError, log2},
+};
+#[cfg(feature = "prover")]
+use crate::{
+    protocols::hash_to_prime::{
+        link::generate_linked_parameters_with_observer,
+        snark_hash::{hash_bits_gadget, validate_limits},
+    },
+    utils::integer_to_bigint_mod_q,
+};
+use ark_ec::{AffineCurve, PairingEngine};
+#[cfg(feature = "prover")]
+use ark_ff::UniformRand;
+use ark_ff::{BigInteger, PrimeField};
+#[cfg(feature = "prover")]
+use ark_r1cs_std::{
+    alloc::{AllocVar, AllocationMode},
+    bits::ToBitsGadget,
+    boolean::Boolean,
+    eq::EqGadget,
+    fields::{fp::FpVar, FieldVar},
+    Assignment, R1CSVar,
+};
+#[cfg(feature = "prover")]
+use ark_relations::r1cs::{
+    ConstraintSynthesizer, ConstraintSystem, ConstraintSystemRef, SynthesisError, SynthesisMode,
+};
+use ark_serialize::CanonicalSerialize;
+use rand::{CryptoRng, Rng, RngCore};
+use rug::{integer::IsPrime, Integer};
+use std::ops::Sub;
+
+/// Bit length of the offset, enough for the gaps between primes of
+/// `hash_to_prime_bits` bits, which are `O(hash_to_prime_bits^2)` under
+/// Cramér's conjecture.
+pub fn offset_bit_length(security_level: u16, hash_to_prime_bits: u16) -> u64 {
+    log2((security_level as usize) * (hash_to_prime_bits as usize)) as u64
+}
+
+/// Packs big-endian bits into a field element, without constraints.
+#[cfg(feature = "prover")]
+pub(crate) fn bits_to_fp_var<F: PrimeField>(
+    bits: &[Boolean<F>],
+) -> Result<FpVar<F>, SynthesisError> {
+    let mut result = FpVar::<F>::zero();
+    for b in bits {
+        result = result.double()? + FpVar::from(b.clone());
+    }
+    Ok(result)
+}
+
+#[cfg(feature = "prover")]
+pub struct HashToPrimeIncrementCircuit<E: PairingEngine, P: HashToPrimeHashParameters> {
+    security_level: u16,
+    required_bit_size: u16,
+    value: Option<E::Fr>,
+    offset: Option<u64>,
+    parameters_type: std::marker::PhantomData<fn() -> P>,
+}
+
+#[cfg(feature = "prover")]
+impl<E: PairingEngine, P: HashToPrimeHashParameters> ConstraintSynthesizer<E::Fr>
+    for HashToPrimeIncrementCircuit<E, P>
+{
+    fn generate_constraints(self, cs: ConstraintSystemRef<E::Fr>) -> Result<(), SynthesisError> {
+        let f = FpVar::new_variable(
+            ark_relations::ns!(cs, "alloc value"),
+            || self.value.get(),
+            AllocationMode::Witness,
+        )?;
+        let offset_bit_length = offset_bit_length(self.security_level, self.required_bit_size);
+        if offset_bit_length > 64 {
+            return Err(SynthesisError::Unsatisfiable);
+        }
+        // big-endian bits
+        let mut offset_bits = vec![];
+        for i in (0..offset_bit_length).rev() {
+            offset_bits.push(Boolean::new_variable(
+                ark_relations::ns!(cs, "alloc offset bit"),
+                || {
+                    let mask = 1u64 << i;
+                    self.offset
+                        .map(|offset| (mask & offset) == mask)
+                        .ok_or(SynthesisError::AssignmentMissing)
+                },
+                AllocationMode::Witness,
+            )?);
+        }
+        let bits = f.to_bits_be()?;
+        let message = MessageEncoding::new(P::MESSAGE_SIZE, 0)
+            .encode(&[], &bits, Boolean::constant(false))
+            .ok_or(SynthesisError::Unsatisfiable)?;
+        let hash_bits = hash_bits_gadget(message, self.required_bit_size)?;
+        let candidate = bits_to_fp_var(&hash_bits)? + bits_to_fp_var(&offset_bits)?;
+
+        let result = FpVar::new_variable(
+            ark_relations::ns!(cs, "prime"),
+            || candidate.value(),
+            AllocationMode::Input,
+        )?;
+        result.enforce_equal(&candidate)?;
+        // the sum can't wrap around, but can exceed the required size
+        let result_bits = result.to_bits_be()?;
+        for b in result_bits
+            .iter()
+            .take(<E::Fr as PrimeField>::size_in_bits() - self.required_bit_size as usize)
+        {
+            b.enforce_equal(&Boolean::constant(false))?;
+        }
+
+        Ok(())
+    }
+}
+
+pub struct Protocol<E: PairingEngine, P: HashToPrimeHashParameters> {
+    pub crs: CRSHashToPrime<E::G1Projective, Self>,
+    parameters_type: std::marker::PhantomData<P>,
+}
+
+impl<E: PairingEngine, P: HashToPrimeHashParameters> HashToPrimeProtocol<E::G1Projective>
+    for Protocol<E, P>
+{
+    type Proof = legogro16::Proof<E>;
+    type Parameters = legogro16::ProvingKey<E>;
+
+    fn from_crs(crs: &CRSHashToPrime<E::G1Projective, Self>) -> Protocol<E, P> {
+        Protocol {
+            crs: (*crs).clone(),
+            parameters_type: std::marker::PhantomData,
+        }
+    }
+
+    #[cfg(feature = "prover")]
+    fn setup_with_observer<R: Rng, O: ProgressObserver>(
+        rng: &mut R,
+        pedersen_commitment_parameters: &PedersenCommitment<E::G1Projective>,
+        parameters: &Parameters,
+        observer: &O,
+    ) -> Result<Self::Parameters, SetupError> {
+        validate_limits::<E>(
+            P::MESSAGE_SIZE,
+            offset_bit_length(parameters.security_level, parameters.hash_to_prime_bits),
+        )?;
+        if !report(observer, Stage::HashToPrimeSetup, 0, 1) {
+            return Err(SetupError::Cancelled);
+        }
+        let c = HashToPrimeIncrementCircuit::<E, P> {
+            security_level: parameters.security_level,
+            required_bit_size: parameters.hash_to_prime_bits,
+            value: None,
+            offset: None,
+            parameters_type: std::marker::PhantomData,
+        };
+        let hash_to_prime_parameters = generate_linked_parameters_with_observer::<E, _, _, _>(
+            rng,
+            c,
+            pedersen_commitment_parameters,
+            &[],
+            Self::circuit_stats(parameters)?.public_inputs,
+            observer,
+        )?;
+        observer.on_progress(Stage::HashToPrimeSetup, 1, 1);
+        Ok(hash_to_prime_parameters)
+    }
+
+    #[cfg(not(feature = "prover"))]
+    fn setup_with_observer<R: Rng, O: ProgressObserver>(
+        _: &mut R,
+        _: &PedersenCommitment<E::G1Projective>,
+        _: &Parameters,
+        _: &O,
+    ) -> Result<Self::Parameters, SetupError> {
+        Err(SetupError::ProverNotEnabled)
+    }
+
+    #[cfg(feature = "prover")]
+    fn circuit_stats(parameters: &Parameters) -> Result<CircuitStats, SetupError> {
+        validate_limits::<E>(
+            P::MESSAGE_SIZE,
+            offset_bit_length(parameters.security_level, parameters.hash_to_prime_bits),
+        )?;
+        let c = HashToPrimeIncrementCircuit::<E, P> {
+            security_level: parameters.security_level,
+            required_bit_size: parameters.hash_to_prime_bits,
+            value: None,
+            offset: None,
+            parameters_type: std::marker::PhantomData,
+        };
+        let cs = ConstraintSystem::<E::Fr>::new_ref();
+        cs.set_mode(SynthesisMode::Setup);
+        c.generate_constraints(cs.clone())?;
+        Ok(CircuitStats {
+            constraints: cs.num_constraints(),
+            variables: cs.num_instance_variables() + cs.num_witness_variables(),
+            // the first instance variable is the constant one
+            public_inputs: cs.num_instance_variables() - 1,
+        })
+    }
+
+    /// The constraints are counted by synthesizing the circuit, which needs
+    /// the `prover` feature.
+    #[cfg(not(feature = "prover"))]
+    fn circuit_stats(_: &Parameters) -> Result<CircuitStats, SetupError> {
+        Err(SetupError::ProverNotEnabled)
+    }
+
+    fn estimate_sizes(parameters: &Parameters) -> Result<HashToPrimeSizes, SetupError> {
+        Ok(legogro16_sizes::<E>(&Self::circuit_stats(parameters)?))
+    }
+
+    #[cfg(feature = "prover")]
+    fn prove<R: Rng, C: HashToPrimeVerifierChannel<E::G1Projective, Self>>(
+        &self,
+        verifier_channel: &mut C,
+        rng: &mut R,
+        _: &Statement<E::G1Projective>,
+        witness: &Witness,
+    ) -> Result<(), ProofError> {
+        let (_, offset) = self.hash_to_prime(&witness.e)?;
+        let c = HashToPrimeIncrementCircuit::<E, P> {
+            security_level: self.crs.parameters.security_level,
+            required_bit_size: self.crs.parameters.hash_to_prime_bits,
+            value: Some(integer_to_bigint_mod_q::<E::G1Projective>(&witness.e)?),
+            offset: Some(offset),
+            parameters_type: std::marker::PhantomData,
+        };
+        let v = E::Fr::rand(rng);
+        let link_v = integer_to_bigint_mod_q::<E::G1Projective>(&witness.r_q)?;
+        let proof = legogro16::create_random_proof::<E, _, _>(
+            c,
+            v,
+            link_v,
+            &self.crs.hash_to_prime_parameters,
+            rng,
+        )?;
+        verifier_channel.send_proof(&proof)?;
+        Ok(())
+    }
+
+    #[cfg(not(feature = "prover"))]
+    fn prove<R: Rng, C: HashToPrimeVerifierChannel<E::G1Projective, Self>>(
+        &self,
+        _: &mut C,
+        _: &mut R,
+        _: &Statement<E::G1Projective>,
+        _: &Witness,
+    ) -> Result<(), ProofError> {
+        Err(ProofError::ProverNotEnabled)
+    }
+
+    fn verify<C: HashToPrimeProverChannel<E::G1Projective, Self>>(
+        &self,
+        prover_channel: &mut C,
+        statement: &Statement<E::G1Projective>,
+    ) -> Result<(), VerificationError> {
+        let proof = prover_channel.receive_proof()?;
+        validate_link_bases(
+            &self.crs.hash_to_prime_parameters.vk,
+            ELEMENT_COMMITTED_INPUTS,
+        )?;
+        let pvk = legogro16::prepare_verifying_key(&self.crs.hash_to_prime_parameters.vk);
+        let is_valid = legogro16::verify_proof(&pvk, &proof)?;
+        let proof_link_d_without_one = proof
+            .link_d
+            .into_projective()
+            .sub(&self.crs.hash_to_prime_parameters.vk.link_bases[0].into_projective());
+        // both checks run, as in the sigma protocols
+        if is_valid & (statement.c_e_q == proof_link_d_without_one) {
+            Ok(())
+        } else {
+            Err(VerificationError::VerificationFailed)
+        }
+    }
+
+    fn verify_batch<R: RngCore + CryptoRng>(
+        &self,
+        rng: &mut R,
+        statements: &[Statement<E::G1Projective>],
+        proofs: &[Self::Proof],
+    ) -> Result<(), VerificationError> {
+        verify_linked_batch(
+            &self.crs.hash_to_prime_parameters.vk,
+            rng,
+            statements,
+            proofs,
+        )
+    }
+
+    fn proof_to_bytes(proof: &Self::Proof) -> Result<Vec<u8>, CurveError> {
+        let mut bytes = vec![];
+        proof.serialize(&mut bytes)?;
+        Ok(bytes)
+    }
+
+    fn parameters_to_bytes(parameters: &Self::Parameters) -> Result<Vec<u8>, CurveError> {
+        let mut bytes = vec![];
+        parameters.vk.serialize(&mut bytes)?;
+        Ok(bytes)
+    }
+
+    /// Returns the prime and its offset from the hash of `e`.
+    fn max_element_bits(_: &Parameters) -> u16 {
+        max_message_bits::<E>(P::MESSAGE_SIZE)
+    }
+
+    fn maps_to_unique_prime(_: &Parameters) -> bool {
+        false
+    }
+
+    fn hash_to_prime(&self, e: &Integer) -> Result<(Integer, u64), HashToPrimeError> {
+        self.hash_to_prime_with_observer(e, &NoProgress)
+    }
+
+    fn hash_to_prime_with_observer<O: ProgressObserver>(
+        &self,
+        e: &Integer,
+        observer: &O,
+    ) -> Result<(Integer, u64), HashToPrimeError> {
+        let required_bit_size = self.crs.parameters.hash_to_prime_bits;
+        let offset_bit_length =
+            offset_bit_length(self.crs.parameters.security_level, required_bit_size);
+        let message = MessageEncoding::new(P::MESSAGE_SIZE, 0)
+            .encode(&[], &value_bits::<E>(e, P::MESSAGE_SIZE)?, false)
+            .ok_or(HashToPrimeError::InvalidValueSize)?;
+        let hash_bits = hash_bits(message, required_bit_size);
+        let hash = bigint_to_integer::<E::G1Projective>(
+            &E::Fr::from_repr(<E::Fr as PrimeField>::BigInt::from_bits_be(&hash_bits))
+                .ok_or(HashToPrimeError::InvalidValueSize)?,
+        );
+        for offset in 0..1 << offset_bit_length {
+            if !report(
+                observer,
+                Stage::HashToPrimeSearch,
+                offset,
+                1 << offset_bit_length,
+            ) {
+                return Err(HashToPrimeError::Cancelled);
+            }
+            let integer = Integer::from(&hash + offset);
+            if integer.significant_bits() > required_bit_size as u32 {
+                break;
+            }
+            let rounds = miller_rabin_rounds(&self.crs.parameters);
+            if integer.is_probably_prime(rounds) == IsPrime::No {
+                continue;
+            }
+
+            observer.on_hash_to_prime(&HashToPrimeStats {
+                candidates: offset + 1,
+                miller_rabin_rounds: rounds,
+            });
+            return Ok((integer, offset));
+        }
+
+        Err(HashToPrimeError::CouldNotFindIndex)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{HashToPrimeIncrementCircuit, Protocol, Statement, Witness};
+    use crate::{
+        commitments::Commitment,
+        parameters::Parameters,
+        protocols::hash_to_prime::{
+            snark_hash::{HashToPrimeHashParameters, Protocol as HPHashProtocol},
+            transcript::{TranscriptProverChannel, TranscriptVerifierChannel},
+            HashToPrimeProtocol,
+        },
+        utils::integer_to_bigint_mod_q,
+    };
+    use accumulator::group::Rsa2048;
+    use ark_bls12_381::{Bls12_381, Fr, G1Projective};
+    use ark_relations::r1cs::{ConstraintSynthesizer, ConstraintSystem};
+    use merlin::Transcript;
+    use rand::thread_rng;
+    use rug::rand::RandState;
+    use rug::{integer::IsPrime, Integer};
+    use std::cell::RefCell;
+
+    struct TestParameters {}
+    impl HashToPrimeHashParameters for TestParameters {
+        const MESSAGE_SIZE: u16 = 254;
+    }
+
+    #[test]
+    fn test_circuit() {
+        let cs = ConstraintSystem::<Fr>::new_ref();
+        let params = Parameters::from_security_level(128).unwrap();
+        let mut rng1 = RandState::new();
+        rng1.seed(&Integer::from(13));
+        let mut rng2 = thread_rng();
+
+        let crs = crate::protocols::membership::Protocol::<
+            Rsa2048,
+            G1Projective,
+            Protocol<Bls12_381, TestParameters>,
+        >::setup(&params, &mut rng1, &mut rng2)
+        .unwrap()
+        .crs
+        .crs_hash_to_prime;
+        let protocol = Protocol::<Bls12_381, TestParameters>::from_crs(&crs);
+
+        let value = Integer::from(12);
+        let (prime, offset) = protocol.hash_to_prime(&value).unwrap();
+        assert_ne!(prime.is_probably_prime(64), IsPrime::No);
+        // no prime between the hash and the returned one
+        for smaller_offset in 0..offset {
+            assert_eq!(
+                Integer::from(&prime - (offset - smaller_offset)).is_probably_prime(64),
+                IsPrime::No
+            );
+        }
+
+        let c = HashToPrimeIncrementCircuit::<Bls12_381, TestParameters> {
+            security_level: crs.parameters.security_level,
+            required_bit_size: crs.parameters.hash_to_prime_bits,
+            value: Some(integer_to_bigint_mod_q::<G1Projective>(&value).unwrap()),
+            offset: Some(offset),
+            parameters_type: std::marker::PhantomData,
+        };
+        c.generate_constraints(cs.clone()).unwrap();
+        assert!(cs.is_satisfied().unwrap());
+    }
+
+    #[test]
+    fn test_constraints() {
+        let params = Parameters::from_security_level(128).unwrap();
+        let increment = Protocol::<Bls12_381, TestParameters>::circuit_stats(&params).unwrap();
+        let index = HPHashProtocol::<Bls12_381, TestParameters>::circuit_stats(&params).unwrap();
+        assert!(increment.constraints < index.constraints);
+        assert_eq!(increment.public_inputs, index.public_inputs);
+    }
+
+    #[test]
+    fn test_proof() {
+        let params = Parameters::from_security_level(128).unwrap();
+        let mut rng1 = RandState::new();
+        rng1.seed(&Integer::from(13));
+        let mut rng2 = thread_rng();
+
+        let crs = crate::protocols::membership::Protocol::<
+            Rsa2048,
+            G1Projective,
+            Protocol<Bls12_381, TestParameters>,
+        >::setup(&params, &mut rng1, &mut rng2)
+        .unwrap()
+        .crs
+        .crs_hash_to_prime;
+        let protocol = Protocol::<Bls12_381, TestParameters>::from_crs(&crs);
+
+        let value = Integer::from(13);
+        let (hashed_value, _) = protocol.hash_to_prime(&value).unwrap();
+        let randomness = Integer::from(9);
+        let commitment = protocol
+            .crs
+            .pedersen_commitment_parameters
+            .commit(&hashed_value, &randomness)
+            .unwrap();
+
+        let proof_transcript = RefCell::new(Transcript::new(b"hash_to_prime"));
+        let statement = Statement { c_e_q: commitment };
+        let mut verifier_channel = TranscriptVerifierChannel::new(&crs, &proof_transcript);
+        protocol
+            .prove(
+                &mut verifier_channel,
+                &mut rng2,
+                &statement,
+                &Witness {
+                    e: value,
+                    r_q: randomness,
+                },
+            )
+            .unwrap();
+
+        let proof = verifier_channel.proof().unwrap();
+
+        let verification_transcript = RefCell::new(Transcript::new(b"hash_to_prime"));
+        let mut prover_channel =
+            TranscriptProverChannel::new(&crs, &verification_transcript, &proof);
+        protocol.verify(&mut prover_channel, &statement).unwrap();
+    }
+}
@@ -6,46 +6,62 @@ use crate::{
     protocols::{
         hash_to_prime::{
             channel::{HashToPrimeProverChannel, HashToPrimeVerifierChannel},
-            CRSHashToPrime, HashToPrimeError, HashToPrimeProtocol, Statement, Witness,
+            legogro16_sizes,
+            link::{validate_link_bases, ELEMENT_COMMITTED_INPUTS},
+            snark_batch::verify_linked_batch,
+            CRSHashToPrime, CircuitStats, HashToPrimeError, HashToPrimeProtocol, HashToPrimeSizes,
+            RangeProofProtocol, Statement, Witness,
         },
+        progress::ProgressObserver,
         ProofError, SetupError, VerificationError,
     },
+    utils::curve::CurveError,
+};
+#[cfg(feature = "prover")]
+use crate::{
+    protocols::{
+        gadgets::enforce_bit_size,
+        hash_to_prime::link::generate_linked_parameters_with_observer,
+        progress::{report, Stage},
+    },
     utils::integer_to_bigint_mod_q,
 };
-use ark_ff::{PrimeField, UniformRand};
-use ark_ec::{AffineCurve, PairingEngine, ProjectiveCurve};
-use ark_relations::r1cs::{ConstraintSynthesizer, ConstraintSystemRef, SynthesisError};
+use ark_ec::{AffineCurve, PairingEngine};
+#[cfg(feature = "prover")]
+use ark_ff::UniformRand;
+#[cfg(feature = "prover")]
 use ark_r1cs_std::{
-    alloc::{AllocVar, AllocationMode}, bits::ToBitsGadget, boolean::Boolean, eq::EqGadget, fields::fp::FpVar,
+    alloc::{AllocVar, AllocationMode},
+    bits::ToBitsGadget,
+    fields::fp::FpVar,
     Assignment,
 };
-use rand::Rng;
+#[cfg(feature = "prover")]
+use ark_relations::r1cs::{
+    ConstraintSynthesizer, ConstraintSystem, ConstraintSystemRef, SynthesisError, SynthesisMode,
+};
+use ark_serialize::CanonicalSerialize;
+use rand::{CryptoRng, Rng, RngCore};
 use rug::Integer;
 use std::ops::Sub;
 
+#[cfg(feature = "prover")]
 pub struct HashToPrimeCircuit<E: PairingEngine> {
     required_bit_size: u16,
     value: Option<E::Fr>,
 }
 
+#[cfg(feature = "prover")]
 impl<E: PairingEngine> ConstraintSynthesizer<E::Fr> for HashToPrimeCircuit<E> {
-    fn generate_constraints(
-        self,
-        cs: ConstraintSystemRef<E::Fr>,
-    ) -> Result<(), SynthesisError> {
-        let f = FpVar::new_variable(ark_relations::ns!(cs, "alloc value"), || self.value.get(), AllocationMode::Input)?;
+    fn generate_constraints(self, cs: ConstraintSystemRef<E::Fr>) -> Result<(), SynthesisError> {
+        let f = FpVar::new_variable(
+            ark_relations::ns!(cs, "alloc value"),
+            || self.value.get(),
+            AllocationMode::Input,
+        )?;
         // big-endian bits
         let bits = f.to_non_unique_bits_be()?;
-        let modulus_bits = E::Fr::size_in_bits();
-        let bits_to_skip = modulus_bits - self.required_bit_size as usize;
-        for b in bits[..bits_to_skip].iter() {
-            b.enforce_equal(
-                &Boolean::constant(false),
-            )?;
-        }
-        bits[bits_to_skip].enforce_equal(
-            &Boolean::constant(true),
-        )?;
+        enforce_bit_size(&bits, self.required_bit_size)?;
 
         Ok(())
     }
@@ -65,31 +81,71 @@ impl<E: PairingEngine> HashToPrimeProtocol<E::G1Projective> for Protocol<E> {
         }
     }
 
-    fn setup<R: Rng>(
+    #[cfg(feature = "prover")]
+    fn setup_with_observer<R: Rng, O: ProgressObserver>(
         rng: &mut R,
         pedersen_commitment_parameters: &PedersenCommitment<E::G1Projective>,
         parameters: &Parameters,
+        observer: &O,
     ) -> Result<Self::Parameters, SetupError> {
+        if !report(observer, Stage::HashToPrimeSetup, 0, 1) {
+            return Err(SetupError::Cancelled);
+        }
         let c = HashToPrimeCircuit::<E> {
             required_bit_size: parameters.hash_to_prime_bits,
             value: None,
         };
-        let base_one = E::G1Projective::rand(rng);
-        let pedersen_bases = vec![
-            base_one,
-            pedersen_commitment_parameters.g,
-            pedersen_commitment_parameters.h,
-        ];
-        Ok(legogro16::generate_random_parameters(
-            c,
-            &pedersen_bases
-                .into_iter()
-                .map(|p| p.into_affine())
-                .collect::<Vec<_>>(),
+        let hash_to_prime_parameters = generate_linked_parameters_with_observer::<E, _, _, _>(
             rng,
-        )?)
+            c,
+            pedersen_commitment_parameters,
+            &[],
+            Self::circuit_stats(parameters)?.public_inputs,
+            observer,
+        )?;
+        observer.on_progress(Stage::HashToPrimeSetup, 1, 1);
+        Ok(hash_to_prime_parameters)
+    }
+
+    #[cfg(not(feature = "prover"))]
+    fn setup_with_observer<R: Rng, O: ProgressObserver>(
+        _: &mut R,
+        _: &PedersenCommitment<E::G1Projective>,
+        _: &Parameters,
+        _: &O,
+    ) -> Result<Self::Parameters, SetupError> {
+        Err(SetupError::ProverNotEnabled)
+    }
+
+    #[cfg(feature = "prover")]
+    fn circuit_stats(parameters: &Parameters) -> Result<CircuitStats, SetupError> {
+        let c = HashToPrimeCircuit::<E> {
+            required_bit_size: parameters.hash_to_prime_bits,
+            value: None,
+        };
+        let cs = ConstraintSystem::<E::Fr>::new_ref();
+        cs.set_mode(SynthesisMode::Setup);
+        c.generate_constraints(cs.clone())?;
+        Ok(CircuitStats {
+            constraints: cs.num_constraints(),
+            variables: cs.num_instance_variables() + cs.num_witness_variables(),
+            // the first instance variable is the constant one
+            public_inputs: cs.num_instance_variables() - 1,
+        })
+    }
+
+    /// The constraints are counted by synthesizing the circuit, which needs
+    /// the `prover` feature.
+    #[cfg(not(feature = "prover"))]
+    fn circuit_stats(_: &Parameters) -> Result<CircuitStats, SetupError> {
+        Err(SetupError::ProverNotEnabled)
+    }
+
+    fn estimate_sizes(parameters: &Parameters) -> Result<HashToPrimeSizes, SetupError> {
+        Ok(legogro16_sizes::<E>(&Self::circuit_stats(parameters)?))
     }
 
+    #[cfg(feature = "prover")]
     fn prove<R: Rng, C: HashToPrimeVerifierChannel<E::G1Projective, Self>>(
         &self,
         verifier_channel: &mut C,
@@ -116,25 +172,65 @@ impl<E: PairingEngine> HashToPrimeProtocol<E::G1Projective> for Protocol<E> {
         Ok(())
     }
 
+    #[cfg(not(feature = "prover"))]
+    fn prove<R: Rng, C: HashToPrimeVerifierChannel<E::G1Projective, Self>>(
+        &self,
+        _: &mut C,
+        _: &mut R,
+        _: &Statement<E::G1Projective>,
+        _: &Witness,
+    ) -> Result<(), ProofError> {
+        Err(ProofError::ProverNotEnabled)
+    }
+
     fn verify<C: HashToPrimeProverChannel<E::G1Projective, Self>>(
         &self,
         prover_channel: &mut C,
         statement: &Statement<E::G1Projective>,
     ) -> Result<(), VerificationError> {
         let proof = prover_channel.receive_proof()?;
+        validate_link_bases(
+            &self.crs.hash_to_prime_parameters.vk,
+            ELEMENT_COMMITTED_INPUTS,
+        )?;
         let pvk = legogro16::prepare_verifying_key(&self.crs.hash_to_prime_parameters.vk);
-        if !legogro16::verify_proof(&pvk, &proof)? {
-            return Err(VerificationError::VerificationFailed);
-        }
+        let is_valid = legogro16::verify_proof(&pvk, &proof)?;
         let proof_link_d_without_one = proof
             .link_d
             .into_projective()
             .sub(&self.crs.hash_to_prime_parameters.vk.link_bases[0].into_projective());
-        if statement.c_e_q != proof_link_d_without_one {
-            return Err(VerificationError::VerificationFailed);
+        // both checks run, as in the sigma protocols
+        if is_valid & (statement.c_e_q == proof_link_d_without_one) {
+            Ok(())
+        } else {
+            Err(VerificationError::VerificationFailed)
         }
+    }
 
-        Ok(())
+    fn verify_batch<R: RngCore + CryptoRng>(
+        &self,
+        rng: &mut R,
+        statements: &[Statement<E::G1Projective>],
+        proofs: &[Self::Proof],
+    ) -> Result<(), VerificationError> {
+        verify_linked_batch(
+            &self.crs.hash_to_prime_parameters.vk,
+            rng,
+            statements,
+            proofs,
+        )
+    }
+
+    fn proof_to_bytes(proof: &Self::Proof) -> Result<Vec<u8>, CurveError> {
+        let mut bytes = vec![];
+        proof.serialize(&mut bytes)?;
+        Ok(bytes)
+    }
+
+    fn parameters_to_bytes(parameters: &Self::Parameters) -> Result<Vec<u8>, CurveError> {
+        let mut bytes = vec![];
+        parameters.vk.serialize(&mut bytes)?;
+        Ok(bytes)
     }
 
     fn hash_to_prime(&self, e: &Integer) -> Result<(Integer, u64), HashToPrimeError> {
@@ -142,6 +238,8 @@ impl<E: PairingEngine> HashToPrimeProtocol<E::G1Projective> for Protocol<E> {
     }
 }
 
+impl<E: PairingEngine> RangeProofProtocol<E::G1Projective> for Protocol<E> {}
+
 #[cfg(test)]
 mod test {
     use super::{HashToPrimeCircuit, Protocol, Statement, Witness};
@@ -157,8 +255,8 @@ mod test {
     };
     use accumulator::group::Rsa2048;
     use ark_bls12_381::{Bls12_381, Fr, G1Projective};
-    use merlin::Transcript;
     use ark_relations::r1cs::{ConstraintSynthesizer, ConstraintSystem};
+    use merlin::Transcript;
     use rand::thread_rng;
     use rug::rand::RandState;
     use rug::Integer;
@@ -181,6 +279,14 @@ mod test {
         }
     }
 
+    #[test]
+    fn test_circuit_stats() {
+        let params = Parameters::from_security_level(128).unwrap();
+        let stats = Protocol::<Bls12_381>::circuit_stats(&params).unwrap();
+        assert!(stats.constraints > params.hash_to_prime_bits as usize);
+        assert_eq!(stats.public_inputs, 1);
+    }
+
     #[test]
     fn test_proof() {
         let params = Parameters::from_security_level(128).unwrap();
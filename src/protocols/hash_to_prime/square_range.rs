@@ -0,0 +1,700 @@
+//! Range proof over the group of unknown order, with no SNARK.
+//!
+//! The prover commits to the element `e` with an integer commitment in the
+//! group, and shows that `e - 2^(μ-1)` and `2^μ - 1 - e` are sums of four
+//! squares, so that `e` has exactly `hash_to_prime_bits` bits, as in the
+//! arguments of Lipmaa and Boudot. A sigma protocol, made non-interactive with
+//! its own transcript, additionally shows the integer commitment and the
+//! Pedersen commitment of the statement hold the same value. The curve is only
+//! used for this link, so the protocol works with any curve and needs no
+//! circuit-specific setup, only the generators of the integer commitment.
+use crate::{
+    commitments::{integer::IntegerCommitment, pedersen::PedersenCommitment, Commitment},
+    parameters::Parameters,
+    protocols::{
+        blinding::BlindingRanges,
+        hash_to_prime::{
+            channel::{HashToPrimeProverChannel, HashToPrimeVerifierChannel},
+            CRSHashToPrime, CircuitStats, HashToPrimeError, HashToPrimeProtocol, HashToPrimeSizes,
+            RangeProofProtocol, Statement, Witness,
+        },
+        progress::{report, ProgressObserver, Stage},
+        sizes::{group_element_bytes, response_bytes},
+        ProofError, SetupError, VerificationError,
+    },
+    transcript::TranscriptProtocolChallenge,
+    utils::{
+        bigint_to_bytes, bigint_to_integer, bytes_to_integer,
+        curve::{CurveError, CurvePointProjective, Field},
+        exp_signed, integer_mod_q, integer_to_bigint_mod_q, integer_to_signed_bytes,
+        scalar_byte_length, ConvertibleUnknownOrderGroup,
+    },
+};
+use merlin::Transcript;
+use rand::{CryptoRng, RngCore};
+use rug::{integer::IsPrime, Integer};
+
+/// Remainders below this are written as sums of two squares by exhaustive
+/// search.
+const SMALL_REMAINDER: u32 = 1 << 16;
+
+/// Writes `n >= 0` as a sum of four squares, following Rabin and Shallit:
+/// `x1` and `x2` are decreased from their largest values until
+/// `n - x1^2 - x2^2` is a sum of two squares which can be found efficiently.
+pub fn four_squares(n: &Integer) -> Option<[Integer; 4]> {
+    if *n < 0 {
+        return None;
+    }
+    let mut x1 = Integer::from(n.sqrt_ref());
+    loop {
+        let m = Integer::from(n - Integer::from(x1.square_ref()));
+        let mut x2 = Integer::from(m.sqrt_ref());
+        loop {
+            let p = Integer::from(&m - Integer::from(x2.square_ref()));
+            if let Some((x3, x4)) = two_squares(&p) {
+                return Some([x1, x2, x3, x4]);
+            }
+            if x2 == 0 {
+                break;
+            }
+            x2 -= 1;
+        }
+        if x1 == 0 {
+            return None;
+        }
+        x1 -= 1;
+    }
+}
+
+/// Writes `p` as a sum of two squares, if it's small or a prime congruent to
+/// 1 modulo 4.
+fn two_squares(p: &Integer) -> Option<(Integer, Integer)> {
+    let is_square = |x: &Integer| {
+        let root = Integer::from(x.sqrt_ref());
+        if Integer::from(root.square_ref()) == *x {
+            Some(root)
+        } else {
+            None
+        }
+    };
+
+    if *p < SMALL_REMAINDER {
+        let mut a = Integer::from(p.sqrt_ref());
+        loop {
+            if let Some(b) = is_square(&Integer::from(p - Integer::from(a.square_ref()))) {
+                return Some((a, b));
+            }
+            if a == 0 {
+                return None;
+            }
+            a -= 1;
+        }
+    }
+    if p.mod_u(4) != 1 || p.is_probably_prime(30) == IsPrime::No {
+        return None;
+    }
+
+    // a square root of -1 modulo p, from a quadratic non-residue
+    let minus_one = Integer::from(p - 1);
+    let exponent = Integer::from(&minus_one / 4u32);
+    let t = (2u32..1000).find_map(|c| {
+        let t = Integer::from(c).pow_mod(&exponent, p).ok()?;
+        if Integer::from(t.square_ref()) % p == minus_one {
+            Some(t)
+        } else {
+            None
+        }
+    })?;
+    // Hermite-Serret: the first remainder of the Euclidean algorithm on
+    // (p, t) below sqrt(p)
+    let (mut a, mut b) = (p.clone(), t);
+    while Integer::from(b.square_ref()) > *p {
+        let r = Integer::from(&a % &b);
+        a = b;
+        b = r;
+    }
+    let c = is_square(&Integer::from(p - Integer::from(b.square_ref())))?;
+    Some((b, c))
+}
+
+/// Samples uniformly below `bound`, reducing 128 more bits than its size to
+/// keep the bias negligible.
+fn random_below<R: RngCore>(rng: &mut R, bound: &Integer) -> Integer {
+    let mut bytes = vec![0u8; (bound.significant_bits() as usize + 128 + 7) / 8];
+    rng.fill_bytes(&mut bytes);
+    bytes_to_integer(&bytes) % bound
+}
+
+fn random_symmetric_range<R: RngCore>(rng: &mut R, max: &Integer) -> Integer {
+    random_below(rng, &(Integer::from(max * 2u32) + 1u32)) - max
+}
+
+/// Shows that the value committed in `V = prod X_i^{x_i} h^rho` is
+/// `sum x_i^2`, where `X_i = g^{x_i} h^{r_i}`.
+pub struct SquaresProof<G: ConvertibleUnknownOrderGroup> {
+    pub commitments: Vec<G::Elem>,
+    pub s_x: Vec<Integer>,
+    pub s_r: Vec<Integer>,
+    pub s_rho: Integer,
+}
+
+impl<G: ConvertibleUnknownOrderGroup> Clone for SquaresProof<G> {
+    fn clone(&self) -> Self {
+        Self {
+            commitments: self.commitments.clone(),
+            s_x: self.s_x.clone(),
+            s_r: self.s_r.clone(),
+            s_rho: self.s_rho.clone(),
+        }
+    }
+}
+
+pub struct Proof<G: ConvertibleUnknownOrderGroup, P: CurvePointProjective> {
+    pub c_e: G::Elem,
+    pub lower: SquaresProof<G>,
+    pub upper: SquaresProof<G>,
+    pub challenge: Integer,
+    pub s_e: Integer,
+    pub s_r: Integer,
+    pub s_r_q: P::ScalarField,
+}
+
+impl<G: ConvertibleUnknownOrderGroup, P: CurvePointProjective> Clone for Proof<G, P> {
+    fn clone(&self) -> Self {
+        Self {
+            c_e: self.c_e.clone(),
+            lower: self.lower.clone(),
+            upper: self.upper.clone(),
+            challenge: self.challenge.clone(),
+            s_e: self.s_e.clone(),
+            s_r: self.s_r.clone(),
+            s_r_q: self.s_r_q.clone(),
+        }
+    }
+}
+
+/// The first message of the sigma protocol, which is recomputed by the
+/// verifier rather than sent.
+struct Alphas<G: ConvertibleUnknownOrderGroup, P: CurvePointProjective> {
+    alpha_e: G::Elem,
+    alpha_e_q: P,
+    lower: Vec<G::Elem>,
+    upper: Vec<G::Elem>,
+}
+
+/// The witness and blinding values of a `SquaresProof`.
+struct SquaresProver<G: ConvertibleUnknownOrderGroup> {
+    commitments: Vec<G::Elem>,
+    x: Vec<Integer>,
+    r: Vec<Integer>,
+    rho: Integer,
+    m_x: Vec<Integer>,
+    m_r: Vec<Integer>,
+    m_rho: Integer,
+}
+
+impl<G: ConvertibleUnknownOrderGroup> SquaresProver<G> {
+    /// Commits to the squares of `value`, committed in `V` with randomness
+    /// `r_v`.
+    fn new<R: RngCore>(
+        rng: &mut R,
+        integer_commitment: &IntegerCommitment<G>,
+        ranges: &BlindingRanges,
+        value: &Integer,
+        r_v: &Integer,
+    ) -> Result<SquaresProver<G>, ProofError> {
+        let x = four_squares(value).ok_or(ProofError::CouldNotCreateProof)?;
+        let r = (0..4)
+            .map(|_| random_below(rng, &ranges.randomness))
+            .collect::<Vec<_>>();
+        let commitments = x
+            .iter()
+            .zip(&r)
+            .map(|(x, r)| integer_commitment.commit(x, r))
+            .collect::<Result<Vec<_>, _>>()?;
+        let rho = x
+            .iter()
+            .zip(&r)
+            .fold(r_v.clone(), |rho, (x, r)| rho - Integer::from(x * r));
+        Ok(SquaresProver {
+            commitments,
+            x: x.to_vec(),
+            r,
+            rho,
+            m_x: (0..4)
+                .map(|_| random_symmetric_range(rng, &ranges.exponent))
+                .collect(),
+            m_r: (0..4)
+                .map(|_| random_symmetric_range(rng, &ranges.randomness_mask))
+                .collect(),
+            m_rho: random_symmetric_range(rng, &ranges.product_mask),
+        })
+    }
+
+    fn alphas(
+        &self,
+        integer_commitment: &IntegerCommitment<G>,
+    ) -> Result<Vec<G::Elem>, ProofError> {
+        let mut alphas = self
+            .m_x
+            .iter()
+            .zip(&self.m_r)
+            .map(|(m_x, m_r)| integer_commitment.commit(m_x, m_r))
+            .collect::<Result<Vec<_>, _>>()?;
+        alphas.push(product(
+            integer_commitment,
+            &self.commitments,
+            &self.m_x,
+            &self.m_rho,
+        ));
+        Ok(alphas)
+    }
+
+    fn respond(self, c: &Integer) -> SquaresProof<G> {
+        let respond = |m: &Integer, w: &Integer| Integer::from(m - Integer::from(c * w));
+        SquaresProof {
+            s_x: self
+                .m_x
+                .iter()
+                .zip(&self.x)
+                .map(|(m, x)| respond(m, x))
+                .collect(),
+            s_r: self
+                .m_r
+                .iter()
+                .zip(&self.r)
+                .map(|(m, r)| respond(m, r))
+                .collect(),
+            s_rho: respond(&self.m_rho, &self.rho),
+            commitments: self.commitments,
+        }
+    }
+}
+
+/// `prod commitments_i^{exponents_i} h^rho`.
+fn product<G: ConvertibleUnknownOrderGroup>(
+    integer_commitment: &IntegerCommitment<G>,
+    commitments: &[G::Elem],
+    exponents: &[Integer],
+    rho: &Integer,
+) -> G::Elem {
+    commitments.iter().zip(exponents).fold(
+        exp_signed::<G>(&integer_commitment.h, rho),
+        |acc, (commitment, exponent)| G::op(&acc, &exp_signed::<G>(commitment, exponent)),
+    )
+}
+
+impl<G: ConvertibleUnknownOrderGroup> SquaresProof<G> {
+    /// Recomputes the alphas from the challenge, where `v` commits to the sum
+    /// of the squares.
+    fn expected_alphas(
+        &self,
+        integer_commitment: &IntegerCommitment<G>,
+        v: &G::Elem,
+        c: &Integer,
+    ) -> Result<Vec<G::Elem>, VerificationError> {
+        if self.commitments.len() != 4 || self.s_x.len() != 4 || self.s_r.len() != 4 {
+            return Err(VerificationError::VerificationFailed);
+        }
+        let mut alphas = Vec::with_capacity(5);
+        for ((commitment, s_x), s_r) in self.commitments.iter().zip(&self.s_x).zip(&self.s_r) {
+            alphas.push(G::op(
+                &integer_commitment.commit(s_x, s_r)?,
+                &exp_signed::<G>(commitment, c),
+            ));
+        }
+        alphas.push(G::op(
+            &product(
+                integer_commitment,
+                &self.commitments,
+                &self.s_x,
+                &self.s_rho,
+            ),
+            &exp_signed::<G>(v, c),
+        ));
+        Ok(alphas)
+    }
+}
+
+pub struct Protocol<G: ConvertibleUnknownOrderGroup, P: CurvePointProjective> {
+    pub crs: CRSHashToPrime<P, Self>,
+}
+
+impl<G: ConvertibleUnknownOrderGroup, P: CurvePointProjective> Protocol<G, P> {
+    /// The smallest and largest elements with `hash_to_prime_bits` bits.
+    fn bounds(&self) -> (Integer, Integer) {
+        let bits = self.crs.parameters.hash_to_prime_bits as u32;
+        (
+            Integer::from(Integer::u_pow_u(2, bits - 1)),
+            Integer::from(Integer::u_pow_u(2, bits)) - 1,
+        )
+    }
+
+    /// Commitments to `e - lower` and `upper - e`, given `c_e`.
+    fn square_commitments(&self, c_e: &G::Elem) -> (G::Elem, G::Elem) {
+        let (lower, upper) = self.bounds();
+        let g = &self.crs.hash_to_prime_parameters.g;
+        (
+            G::op(c_e, &exp_signed::<G>(g, &Integer::from(-lower))),
+            G::op(
+                &exp_signed::<G>(g, &upper),
+                &exp_signed::<G>(c_e, &Integer::from(-1)),
+            ),
+        )
+    }
+
+    fn challenge(
+        &self,
+        statement: &Statement<P>,
+        proof_c_e: &G::Elem,
+        lower: &[G::Elem],
+        upper: &[G::Elem],
+        alphas: &Alphas<G, P>,
+    ) -> Result<Integer, CurveError> {
+        let mut transcript = Transcript::new(b"square_range");
+        transcript.append_message(
+            b"hash_to_prime_bits",
+            &self.crs.parameters.hash_to_prime_bits.to_le_bytes(),
+        );
+        transcript.append_message(b"c_e_q", &statement.c_e_q.to_affine_bytes()?);
+        transcript.append_message(b"c_e", &G::elem_to_bytes(proof_c_e));
+        for commitment in lower.iter().chain(upper) {
+            transcript.append_message(b"square", &G::elem_to_bytes(commitment));
+        }
+        transcript.append_message(b"alpha_e", &G::elem_to_bytes(&alphas.alpha_e));
+        transcript.append_message(b"alpha_e_q", &alphas.alpha_e_q.to_affine_bytes()?);
+        for alpha in alphas.lower.iter().chain(&alphas.upper) {
+            transcript.append_message(b"alpha", &G::elem_to_bytes(alpha));
+        }
+        Ok(transcript.challenge_scalar(b"c", self.crs.parameters.security_soundness))
+    }
+}
+
+impl<G: ConvertibleUnknownOrderGroup, P: CurvePointProjective> HashToPrimeProtocol<P>
+    for Protocol<G, P>
+{
+    type Proof = Proof<G, P>;
+    type Parameters = IntegerCommitment<G>;
+
+    fn from_crs(crs: &CRSHashToPrime<P, Self>) -> Protocol<G, P> {
+        Protocol {
+            crs: (*crs).clone(),
+        }
+    }
+
+    fn setup_with_observer<R: RngCore + CryptoRng, O: ProgressObserver>(
+        rng: &mut R,
+        _: &PedersenCommitment<P>,
+        _: &Parameters,
+        observer: &O,
+    ) -> Result<Self::Parameters, SetupError> {
+        if !report(observer, Stage::HashToPrimeSetup, 0, 1) {
+            return Err(SetupError::Cancelled);
+        }
+        // the exponent of h is discarded
+        let g = G::unknown_order_elem();
+        let h = exp_signed::<G>(&g, &random_below(rng, &G::order_upper_bound()));
+        observer.on_progress(Stage::HashToPrimeSetup, 1, 1);
+        Ok(IntegerCommitment::new(&g, &h))
+    }
+
+    /// There's no circuit.
+    fn circuit_stats(_: &Parameters) -> Result<CircuitStats, SetupError> {
+        Ok(CircuitStats {
+            constraints: 0,
+            variables: 0,
+            public_inputs: 0,
+        })
+    }
+
+    fn estimate_sizes(parameters: &Parameters) -> Result<HashToPrimeSizes, SetupError> {
+        // every value of `proof_to_bytes` and `parameters_to_bytes` is
+        // prefixed by its length
+        let prefixed = |bytes: usize| 8 + bytes;
+        let ranges = BlindingRanges::new::<G>(parameters);
+        let element = prefixed(group_element_bytes::<G>());
+        // four commitments, s_x and s_r for each, and s_rho
+        let squares = 4 * element
+            + 4 * prefixed(response_bytes(&ranges.exponent))
+            + 4 * prefixed(response_bytes(&ranges.randomness_mask))
+            + prefixed(response_bytes(&ranges.product_mask));
+        let challenge = prefixed(1 + (parameters.security_soundness as usize + 7) / 8);
+        Ok(HashToPrimeSizes {
+            proof_bytes: element
+                + 2 * squares
+                + challenge
+                + prefixed(response_bytes(&ranges.exponent))
+                + prefixed(response_bytes(&ranges.randomness_mask))
+                + prefixed(scalar_byte_length::<P>()),
+            verifier_parameters_bytes: 2 * element,
+            prover_parameters_bytes: 0,
+        })
+    }
+
+    fn prove<R: RngCore + CryptoRng, C: HashToPrimeVerifierChannel<P, Self>>(
+        &self,
+        verifier_channel: &mut C,
+        rng: &mut R,
+        statement: &Statement<P>,
+        witness: &Witness,
+    ) -> Result<(), ProofError> {
+        let integer_commitment = &self.crs.hash_to_prime_parameters;
+        let ranges = BlindingRanges::new::<G>(&self.crs.parameters);
+        let (lower, upper) = self.bounds();
+        if witness.e < lower || witness.e > upper {
+            return Err(ProofError::WitnessOutOfRange);
+        }
+
+        let r = random_below(rng, &ranges.randomness);
+        let c_e = integer_commitment.commit(&witness.e, &r)?;
+        let lower_prover = SquaresProver::new(
+            rng,
+            integer_commitment,
+            &ranges,
+            &Integer::from(&witness.e - &lower),
+            &r,
+        )?;
+        let upper_prover = SquaresProver::new(
+            rng,
+            integer_commitment,
+            &ranges,
+            &Integer::from(&upper - &witness.e),
+            &Integer::from(-&r),
+        )?;
+
+        let m_e = random_symmetric_range(rng, &ranges.exponent);
+        let m_r = random_symmetric_range(rng, &ranges.randomness_mask);
+        let m_r_q = P::ScalarField::rand(rng);
+        let alphas = Alphas {
+            alpha_e: integer_commitment.commit(&m_e, &m_r)?,
+            alpha_e_q: self
+                .crs
+                .pedersen_commitment_parameters
+                .commit(&integer_mod_q::<P>(&m_e)?, &bigint_to_integer::<P>(&m_r_q))?,
+            lower: lower_prover.alphas(integer_commitment)?,
+            upper: upper_prover.alphas(integer_commitment)?,
+        };
+
+        let c = self.challenge(
+            statement,
+            &c_e,
+            &lower_prover.commitments,
+            &upper_prover.commitments,
+            &alphas,
+        )?;
+        let r_q = integer_to_bigint_mod_q::<P>(&witness.r_q)?;
+        let c_field = integer_to_bigint_mod_q::<P>(&c)?;
+        let proof = Proof {
+            c_e,
+            lower: lower_prover.respond(&c),
+            upper: upper_prover.respond(&c),
+            s_e: m_e - Integer::from(&c * &witness.e),
+            s_r: m_r - Integer::from(&c * &r),
+            s_r_q: m_r_q.sub(&r_q.mul(&c_field)),
+            challenge: c,
+        };
+        verifier_channel.send_proof(&proof)?;
+
+        Ok(())
+    }
+
+    fn verify<C: HashToPrimeProverChannel<P, Self>>(
+        &self,
+        prover_channel: &mut C,
+        statement: &Statement<P>,
+    ) -> Result<(), VerificationError> {
+        let proof = prover_channel.receive_proof()?;
+        let integer_commitment = &self.crs.hash_to_prime_parameters;
+        let c = &proof.challenge;
+        let (lower_v, upper_v) = self.square_commitments(&proof.c_e);
+
+        let alpha_e_q = self
+            .crs
+            .pedersen_commitment_parameters
+            .commit(
+                &integer_mod_q::<P>(&proof.s_e)?,
+                &bigint_to_integer::<P>(&proof.s_r_q),
+            )?
+            .add(&statement.c_e_q.mul(&integer_to_bigint_mod_q::<P>(c)?));
+        let alphas = Alphas {
+            alpha_e: G::op(
+                &integer_commitment.commit(&proof.s_e, &proof.s_r)?,
+                &exp_signed::<G>(&proof.c_e, c),
+            ),
+            alpha_e_q,
+            lower: proof
+                .lower
+                .expected_alphas(integer_commitment, &lower_v, c)?,
+            upper: proof
+                .upper
+                .expected_alphas(integer_commitment, &upper_v, c)?,
+        };
+
+        let expected_c = self.challenge(
+            statement,
+            &proof.c_e,
+            &proof.lower.commitments,
+            &proof.upper.commitments,
+            &alphas,
+        )?;
+        if expected_c == *c {
+            Ok(())
+        } else {
+            Err(VerificationError::VerificationFailed)
+        }
+    }
+
+    fn proof_to_bytes(proof: &Self::Proof) -> Result<Vec<u8>, CurveError> {
+        let mut bytes = vec![];
+        let mut append = |value: &[u8]| {
+            bytes.extend_from_slice(&(value.len() as u64).to_le_bytes());
+            bytes.extend_from_slice(value);
+        };
+        append(&G::elem_to_bytes(&proof.c_e));
+        for squares in &[&proof.lower, &proof.upper] {
+            for commitment in &squares.commitments {
+                append(&G::elem_to_bytes(commitment));
+            }
+            for s in squares.s_x.iter().chain(&squares.s_r) {
+                append(&integer_to_signed_bytes(s));
+            }
+            append(&integer_to_signed_bytes(&squares.s_rho));
+        }
+        append(&integer_to_signed_bytes(&proof.challenge));
+        append(&integer_to_signed_bytes(&proof.s_e));
+        append(&integer_to_signed_bytes(&proof.s_r));
+        append(&bigint_to_bytes::<P>(&proof.s_r_q));
+        Ok(bytes)
+    }
+
+    fn parameters_to_bytes(parameters: &Self::Parameters) -> Result<Vec<u8>, CurveError> {
+        let mut bytes = vec![];
+        for base in &[&parameters.g, &parameters.h] {
+            let base = G::elem_to_bytes(base);
+            bytes.extend_from_slice(&(base.len() as u64).to_le_bytes());
+            bytes.extend_from_slice(&base);
+        }
+        Ok(bytes)
+    }
+
+    fn hash_to_prime(&self, e: &Integer) -> Result<(Integer, u64), HashToPrimeError> {
+        Ok((e.clone(), 0))
+    }
+}
+
+impl<G: ConvertibleUnknownOrderGroup, P: CurvePointProjective> RangeProofProtocol<P>
+    for Protocol<G, P>
+{
+}
+
+#[cfg(all(test, feature = "arkworks"))]
+mod test {
+    use super::{four_squares, Protocol};
+    use crate::{
+        commitments::Commitment,
+        parameters::Parameters,
+        protocols::{
+            hash_to_prime::{
+                transcript::{TranscriptProverChannel, TranscriptVerifierChannel},
+                HashToPrimeProtocol, Statement, Witness,
+            },
+            membership::Protocol as MembershipProtocol,
+            ProofError,
+        },
+    };
+    use accumulator::group::Rsa2048;
+    use ark_bls12_381::G1Projective;
+    use merlin::Transcript;
+    use rand::thread_rng;
+    use rug::rand::RandState;
+    use rug::Integer;
+    use std::cell::RefCell;
+
+    type HPProtocol = Protocol<Rsa2048, G1Projective>;
+
+    #[test]
+    fn test_four_squares() {
+        let mut values = (0..200).map(Integer::from).collect::<Vec<_>>();
+        values.push(Integer::from(Integer::u_pow_u(2, 253)) - 245);
+        values.push(Integer::from(Integer::u_pow_u(3, 161)));
+        for n in &values {
+            let squares = four_squares(n).unwrap();
+            let sum = squares
+                .iter()
+                .fold(Integer::new(), |sum, x| sum + Integer::from(x.square_ref()));
+            assert_eq!(sum, *n);
+        }
+        assert!(four_squares(&Integer::from(-1)).is_none());
+    }
+
+    #[test]
+    fn test_proof() {
+        let params = Parameters::from_security_level(128).unwrap();
+        let mut rng1 = RandState::new();
+        rng1.seed(&Integer::from(13));
+        let mut rng2 = thread_rng();
+
+        let crs = MembershipProtocol::<Rsa2048, G1Projective, HPProtocol>::setup(
+            &params, &mut rng1, &mut rng2,
+        )
+        .unwrap()
+        .crs
+        .crs_hash_to_prime;
+        let protocol = HPProtocol::from_crs(&crs);
+
+        let prove = |value: &Integer, randomness: &Integer| {
+            let statement = Statement {
+                c_e_q: crs
+                    .pedersen_commitment_parameters
+                    .commit(value, randomness)
+                    .unwrap(),
+            };
+            let proof_transcript = RefCell::new(Transcript::new(b"hash_to_prime"));
+            let mut verifier_channel = TranscriptVerifierChannel::new(&crs, &proof_transcript);
+            let result = protocol.prove(
+                &mut verifier_channel,
+                &mut thread_rng(),
+                &statement,
+                &Witness {
+                    e: value.clone(),
+                    r_q: randomness.clone(),
+                },
+            );
+            result.map(|_| (statement, verifier_channel.proof().unwrap()))
+        };
+        let verify = |statement: &Statement<G1Projective>, proof| {
+            let verification_transcript = RefCell::new(Transcript::new(b"hash_to_prime"));
+            let mut prover_channel =
+                TranscriptProverChannel::new(&crs, &verification_transcript, proof);
+            protocol.verify(&mut prover_channel, statement)
+        };
+
+        let bits = params.hash_to_prime_bits as u32;
+        let largest = Integer::from(Integer::u_pow_u(2, bits)) - 1;
+        let smallest = Integer::from(Integer::u_pow_u(2, bits - 1));
+        let value = Integer::from(&largest - 244);
+        let (statement, proof) = prove(&value, &Integer::from(9)).unwrap();
+        verify(&statement, &proof).unwrap();
+        for value in &[largest.clone(), smallest.clone()] {
+            let (statement, proof) = prove(value, &Integer::from(5)).unwrap();
+            verify(&statement, &proof).unwrap();
+        }
+
+        for value in &[Integer::from(&largest + 1), Integer::from(&smallest - 1)] {
+            assert!(matches!(
+                prove(value, &Integer::from(5)),
+                Err(ProofError::WitnessOutOfRange)
+            ));
+        }
+
+        // a proof for another commitment
+        let (other_statement, _) = prove(&value, &Integer::from(10)).unwrap();
+        assert!(verify(&other_statement, &proof).is_err());
+        let mut wrong_proof = proof.clone();
+        wrong_proof.lower.s_x[0] += 1;
+        assert!(verify(&statement, &wrong_proof).is_err());
+        let mut wrong_proof = proof;
+        wrong_proof.upper.commitments.pop();
+        assert!(verify(&statement, &wrong_proof).is_err());
+    }
+}
@@ -1,12 +1,16 @@
 use crate::{
-    channels::ChannelError,
+    channels::{ChannelError, MessageOrder},
     protocols::hash_to_prime::{
         channel::{HashToPrimeProverChannel, HashToPrimeVerifierChannel},
         CRSHashToPrime, HashToPrimeProtocol,
     },
-    transcript::{TranscriptChannelError, TranscriptProtocolChallenge, TranscriptProtocolCurve},
+    transcript::{
+        versioned_domain_sep, TranscriptChannelError, TranscriptProtocolChallenge,
+        TranscriptProtocolCurve,
+    },
     utils::curve::CurvePointProjective,
 };
+use blake2::{Blake2s, Digest};
 use merlin::Transcript;
 use std::cell::RefCell;
 
@@ -14,11 +18,25 @@ pub trait TranscriptProtocolHashToPrime<P: CurvePointProjective>:
     TranscriptProtocolCurve<P> + TranscriptProtocolChallenge
 {
     fn hash_to_prime_domain_sep(&mut self);
+    fn append_proof_digest(&mut self, label: &'static [u8], proof: &[u8]);
+    /// Fills `dest` with a challenge drawn from a copy of the transcript,
+    /// leaving the transcript itself unchanged.
+    fn fork_challenge_bytes(&self, label: &'static [u8], dest: &mut [u8]);
 }
 
 impl<P: CurvePointProjective> TranscriptProtocolHashToPrime<P> for Transcript {
     fn hash_to_prime_domain_sep(&mut self) {
-        self.append_message(b"dom-sep", b"hash_to_prime");
+        self.append_message(b"dom-sep", &versioned_domain_sep(b"hash_to_prime"));
+    }
+
+    /// Appends a Blake2s digest of a serialized proof, rather than the proof,
+    /// which is much larger.
+    fn append_proof_digest(&mut self, label: &'static [u8], proof: &[u8]) {
+        self.append_message(label, &Blake2s::digest(proof));
+    }
+
+    fn fork_challenge_bytes(&self, label: &'static [u8], dest: &mut [u8]) {
+        self.clone().challenge_bytes(label, dest);
     }
 }
 
@@ -28,9 +46,10 @@ pub struct TranscriptVerifierChannel<
     HP: HashToPrimeProtocol<P>,
     T: TranscriptProtocolHashToPrime<P>,
 > {
+    transcript: &'a RefCell<T>,
+    order: MessageOrder,
     proof: Option<HP::Proof>,
     crs_type: std::marker::PhantomData<CRSHashToPrime<P, HP>>,
-    transcript_type: std::marker::PhantomData<&'a RefCell<T>>,
 }
 
 impl<
@@ -42,12 +61,13 @@ impl<
 {
     pub fn new(
         _: &CRSHashToPrime<P, HP>,
-        _: &'a RefCell<T>,
+        transcript: &'a RefCell<T>,
     ) -> TranscriptVerifierChannel<'a, P, HP, T> {
         TranscriptVerifierChannel {
+            transcript,
+            order: MessageOrder::new(),
             proof: None,
             crs_type: std::marker::PhantomData,
-            transcript_type: std::marker::PhantomData,
         }
     }
 
@@ -68,6 +88,10 @@ impl<
     > HashToPrimeVerifierChannel<P, HP> for TranscriptVerifierChannel<'a, P, HP, T>
 {
     fn send_proof(&mut self, proof: &HP::Proof) -> Result<(), ChannelError> {
+        self.order.step(0)?;
+        let mut transcript = self.transcript.try_borrow_mut()?;
+        transcript.hash_to_prime_domain_sep();
+        transcript.append_proof_digest(b"proof", &HP::proof_to_bytes(proof)?);
         self.proof = Some(proof.clone());
         Ok(())
     }
@@ -79,9 +103,11 @@ pub struct TranscriptProverChannel<
     HP: HashToPrimeProtocol<P>,
     T: TranscriptProtocolHashToPrime<P>,
 > {
+    transcript: &'a RefCell<T>,
+    order: MessageOrder,
     proof: HP::Proof,
+    pre_proof_digest: Option<[u8; 32]>,
     crs_type: std::marker::PhantomData<CRSHashToPrime<P, HP>>,
-    transcript_type: std::marker::PhantomData<&'a RefCell<T>>,
 }
 
 impl<
@@ -93,15 +119,24 @@ impl<
 {
     pub fn new(
         _: &CRSHashToPrime<P, HP>,
-        _: &'a RefCell<T>,
+        transcript: &'a RefCell<T>,
         proof: &HP::Proof,
     ) -> TranscriptProverChannel<'a, P, HP, T> {
         TranscriptProverChannel {
+            transcript,
+            order: MessageOrder::new(),
             proof: proof.clone(),
+            pre_proof_digest: None,
             crs_type: std::marker::PhantomData,
-            transcript_type: std::marker::PhantomData,
         }
     }
+
+    /// A digest of the transcript right before the proof was received. It
+    /// binds every message before the proof but not the proof itself, which a
+    /// third party can re-randomize.
+    pub fn pre_proof_digest(&self) -> Option<[u8; 32]> {
+        self.pre_proof_digest
+    }
 }
 
 impl<
@@ -112,6 +147,98 @@ impl<
     > HashToPrimeProverChannel<P, HP> for TranscriptProverChannel<'a, P, HP, T>
 {
     fn receive_proof(&mut self) -> Result<HP::Proof, ChannelError> {
+        self.order.step(0)?;
+        let mut transcript = self.transcript.try_borrow_mut()?;
+        let mut pre_proof_digest = [0u8; 32];
+        transcript.fork_challenge_bytes(b"pre_proof", &mut pre_proof_digest);
+        self.pre_proof_digest = Some(pre_proof_digest);
+        transcript.hash_to_prime_domain_sep();
+        transcript.append_proof_digest(b"proof", &HP::proof_to_bytes(&self.proof)?);
         Ok(self.proof.clone())
     }
 }
+
+#[cfg(all(test, feature = "arkworks"))]
+mod test {
+    use super::{TranscriptProverChannel, TranscriptVerifierChannel};
+    use crate::{
+        commitments::Commitment,
+        parameters::Parameters,
+        protocols::hash_to_prime::{
+            channel::{HashToPrimeProverChannel, HashToPrimeVerifierChannel},
+            snark_range::Protocol as HPProtocol,
+            HashToPrimeProtocol, Statement, Witness,
+        },
+        transcript::TranscriptProtocolChallenge,
+    };
+    use accumulator::group::Rsa2048;
+    use ark_bls12_381::{Bls12_381, G1Projective};
+    use merlin::Transcript;
+    use rand::thread_rng;
+    use rug::rand::RandState;
+    use rug::Integer;
+    use std::cell::RefCell;
+
+    #[test]
+    fn test_proof_absorbed() {
+        let params = Parameters::from_security_level(128).unwrap();
+        let mut rng1 = RandState::new();
+        rng1.seed(&Integer::from(13));
+        let mut rng2 = thread_rng();
+
+        let crs = crate::protocols::membership::Protocol::<
+            Rsa2048,
+            G1Projective,
+            HPProtocol<Bls12_381>,
+        >::setup(&params, &mut rng1, &mut rng2)
+        .unwrap()
+        .crs
+        .crs_hash_to_prime;
+        let protocol = HPProtocol::<Bls12_381>::from_crs(&crs);
+
+        let value = Integer::from(Integer::u_pow_u(2, params.hash_to_prime_bits as u32))
+            - &Integer::from(245);
+        let randomness = Integer::from(9);
+        let statement = Statement {
+            c_e_q: crs
+                .pedersen_commitment_parameters
+                .commit(&value, &randomness)
+                .unwrap(),
+        };
+        let witness = Witness {
+            e: value,
+            r_q: randomness,
+        };
+        let mut prove = || {
+            let transcript = RefCell::new(Transcript::new(b"hash_to_prime"));
+            let mut verifier_channel = TranscriptVerifierChannel::new(&crs, &transcript);
+            protocol
+                .prove(&mut verifier_channel, &mut rng2, &statement, &witness)
+                .unwrap();
+            let c = transcript.borrow_mut().challenge_scalar(b"c", 128);
+            (verifier_channel.proof().unwrap(), c)
+        };
+        let (proof, c) = prove();
+        let (_, other_c) = prove();
+        // proofs are randomized, so a challenge following them differs
+        assert_ne!(c, other_c);
+
+        // the verifier derives the same challenge after receiving the proof
+        let transcript = RefCell::new(Transcript::new(b"hash_to_prime"));
+        let mut prover_channel = TranscriptProverChannel::new(&crs, &transcript, &proof);
+        prover_channel.receive_proof().unwrap();
+        assert_eq!(transcript.borrow_mut().challenge_scalar(b"c", 128), c);
+
+        // but not when squeezing the challenge before receiving the proof
+        let transcript = RefCell::new(Transcript::new(b"hash_to_prime"));
+        let mut prover_channel = TranscriptProverChannel::new(&crs, &transcript, &proof);
+        let early_c = transcript.borrow_mut().challenge_scalar(b"c", 128);
+        prover_channel.receive_proof().unwrap();
+        assert_ne!(early_c, c);
+
+        let transcript = RefCell::new(Transcript::new(b"hash_to_prime"));
+        let mut verifier_channel = TranscriptVerifierChannel::new(&crs, &transcript);
+        verifier_channel.send_proof(&proof).unwrap();
+        assert_eq!(transcript.borrow_mut().challenge_scalar(b"c", 128), c);
+    }
+}
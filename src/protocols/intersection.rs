@@ -0,0 +1,841 @@
+//! Proves the element committed in `c_e_q` is in both of two accumulators or
+//! in neither, without revealing which, as a building block for private
+//! set-intersection statements.
+//!
+//! The statement is the OR of two branches about the same integer commitment
+//! `c_e`: two root proofs for membership in both accumulators, and two coprime
+//! proofs for nonmembership in both. Following Cramer, Damgård and
+//! Schoenmakers, the prover simulates the branch it has no witness for with a
+//! challenge of its choice, and the challenges of the two branches have to add
+//! up to the challenge of the transcript modulo `2^security_soundness`. The
+//! modeq and hash-to-prime proofs linking `c_e` to `c_e_q` are shared by the
+//! branches.
+//!
+//! The sub-protocols respond as soon as they receive their challenge, so the
+//! proofs of the branch the prover has a witness for are run twice with the
+//! same randomness: once to obtain the first messages, which the challenge of
+//! the transcript depends on, and once with the challenge of the branch. The
+//! proofs are non-interactive only.
+use crate::{
+    channels::ChannelError,
+    commitments::{integer::IntegerCommitment, pedersen::PedersenCommitment, Commitment},
+    parameters::{Parameters, SubProtocol},
+    protocols::{
+        coprime::{
+            self,
+            channel::{CoprimeProverChannel, CoprimeVerifierChannel},
+            transcript::{
+                TranscriptProtocolCoprime,
+                TranscriptProverChannel as CoprimeTranscriptProverChannel,
+            },
+            validate_coprime_group, CRSCoprime, Proof as CoprimeProof, Protocol as CoprimeProtocol,
+            Statement as CoprimeStatement, Witness as CoprimeWitness,
+        },
+        hash_to_prime::{
+            transcript::{
+                TranscriptProtocolHashToPrime,
+                TranscriptProverChannel as HashToPrimeTranscriptProverChannel,
+                TranscriptVerifierChannel as HashToPrimeTranscriptVerifierChannel,
+            },
+            validate_element_size, validate_witness, CRSHashToPrime, HashToPrimeProtocol,
+            Statement as HashToPrimeStatement, Witness as HashToPrimeWitness,
+        },
+        modeq::{
+            transcript::{
+                TranscriptProtocolModEq, TranscriptProverChannel as ModEqTranscriptProverChannel,
+                TranscriptVerifierChannel as ModEqTranscriptVerifierChannel,
+            },
+            CRSModEq, Proof as ModEqProof, Protocol as ModEqProtocol, Statement as ModEqStatement,
+            Witness as ModEqWitness,
+        },
+        root::{
+            self,
+            channel::{RootProverChannel, RootVerifierChannel},
+            transcript::{
+                TranscriptProtocolRoot, TranscriptProverChannel as RootTranscriptProverChannel,
+            },
+            CRSRoot, Proof as RootProof, Protocol as RootProtocol, Statement as RootStatement,
+            Witness as RootWitness,
+        },
+        simulation::{simulate_coprime_with_challenge, simulate_root_with_challenge},
+        ProofError, SetupError, VerificationError,
+    },
+    transcript::{versioned_domain_sep, TranscriptProtocolChallenge, TranscriptProtocolInteger},
+    utils::{curve::CurvePointProjective, random_between, ConvertibleUnknownOrderGroup},
+};
+use merlin::Transcript;
+use rand::{rngs::StdRng, CryptoRng, RngCore, SeedableRng};
+use rug::rand::{MutRandState, RandGen, RandState};
+use rug::Integer;
+use std::cell::RefCell;
+
+pub trait TranscriptProtocolIntersection<G: ConvertibleUnknownOrderGroup>:
+    TranscriptProtocolInteger<G> + TranscriptProtocolChallenge
+{
+    fn intersection_domain_sep(&mut self);
+}
+
+impl<G: ConvertibleUnknownOrderGroup> TranscriptProtocolIntersection<G> for Transcript {
+    fn intersection_domain_sep(&mut self) {
+        self.append_message(b"dom-sep", &versioned_domain_sep(b"intersection"));
+    }
+}
+
+pub struct CRS<G: ConvertibleUnknownOrderGroup, P: CurvePointProjective, HP: HashToPrimeProtocol<P>>
+{
+    // G contains the information about Z^*_N
+    pub parameters: Parameters,
+    pub crs_root: CRSRoot<G>,
+    pub crs_coprime: CRSCoprime<G>,
+    pub crs_modeq: CRSModEq<G, P>,
+    pub crs_hash_to_prime: CRSHashToPrime<P, HP>,
+}
+
+impl<G: ConvertibleUnknownOrderGroup, P: CurvePointProjective, HP: HashToPrimeProtocol<P>> Clone
+    for CRS<G, P, HP>
+{
+    fn clone(&self) -> Self {
+        Self {
+            parameters: self.parameters.clone(),
+            crs_root: self.crs_root.clone(),
+            crs_coprime: self.crs_coprime.clone(),
+            crs_modeq: self.crs_modeq.clone(),
+            crs_hash_to_prime: self.crs_hash_to_prime.clone(),
+        }
+    }
+}
+
+pub struct Protocol<
+    G: ConvertibleUnknownOrderGroup,
+    P: CurvePointProjective,
+    HP: HashToPrimeProtocol<P>,
+> {
+    pub crs: CRS<G, P, HP>,
+}
+
+pub struct Statement<G: ConvertibleUnknownOrderGroup, P: CurvePointProjective> {
+    pub c_p_1: G::Elem,
+    pub c_p_2: G::Elem,
+    pub c_e_q: <PedersenCommitment<P> as Commitment>::Instance,
+}
+
+impl<G: ConvertibleUnknownOrderGroup, P: CurvePointProjective> Clone for Statement<G, P> {
+    fn clone(&self) -> Self {
+        Self {
+            c_p_1: self.c_p_1.clone(),
+            c_p_2: self.c_p_2.clone(),
+            c_e_q: self.c_e_q.clone(),
+        }
+    }
+}
+
+/// The witness of the branch which holds.
+pub enum Witness<G: ConvertibleUnknownOrderGroup> {
+    /// `w_1` and `w_2` are membership witnesses for `c_p_1` and `c_p_2`.
+    Both {
+        e: Integer,
+        r_q: Integer,
+        w_1: G::Elem,
+        w_2: G::Elem,
+    },
+    /// `(d_1, b_1)` and `(d_2, b_2)` are nonmembership witnesses for `c_p_1`
+    /// and `c_p_2`.
+    Neither {
+        e: Integer,
+        r_q: Integer,
+        d_1: G::Elem,
+        b_1: Integer,
+        d_2: G::Elem,
+        b_2: Integer,
+    },
+}
+
+impl<G: ConvertibleUnknownOrderGroup> Witness<G> {
+    fn e(&self) -> &Integer {
+        match self {
+            Witness::Both { e, .. } | Witness::Neither { e, .. } => e,
+        }
+    }
+
+    fn r_q(&self) -> &Integer {
+        match self {
+            Witness::Both { r_q, .. } | Witness::Neither { r_q, .. } => r_q,
+        }
+    }
+}
+
+pub struct Proof<
+    G: ConvertibleUnknownOrderGroup,
+    P: CurvePointProjective,
+    HP: HashToPrimeProtocol<P>,
+> {
+    pub c_e: <IntegerCommitment<G> as Commitment>::Instance,
+    /// The challenge of the membership branch. The one of the nonmembership
+    /// branch is the rest of the challenge of the transcript.
+    pub c_both: Integer,
+    pub proof_root_1: RootProof<G>,
+    pub proof_root_2: RootProof<G>,
+    pub proof_coprime_1: CoprimeProof<G>,
+    pub proof_coprime_2: CoprimeProof<G>,
+    pub proof_modeq: ModEqProof<G, P>,
+    pub proof_hash_to_prime: HP::Proof,
+}
+
+impl<G: ConvertibleUnknownOrderGroup, P: CurvePointProjective, HP: HashToPrimeProtocol<P>> Clone
+    for Proof<G, P, HP>
+{
+    fn clone(&self) -> Self {
+        Self {
+            c_e: self.c_e.clone(),
+            c_both: self.c_both.clone(),
+            proof_root_1: self.proof_root_1.clone(),
+            proof_root_2: self.proof_root_2.clone(),
+            proof_coprime_1: self.proof_coprime_1.clone(),
+            proof_coprime_2: self.proof_coprime_2.clone(),
+            proof_modeq: self.proof_modeq.clone(),
+            proof_hash_to_prime: self.proof_hash_to_prime.clone(),
+        }
+    }
+}
+
+/// Stands in for the verifier or the prover of a root or coprime proof,
+/// answering with the challenge assigned to its branch.
+struct BranchChannel<M1, M2, M3> {
+    challenge: Integer,
+    message1: Option<M1>,
+    message2: Option<M2>,
+    message3: Option<M3>,
+}
+
+impl<M1: Clone, M2: Clone, M3: Clone> BranchChannel<M1, M2, M3> {
+    fn new(challenge: &Integer) -> BranchChannel<M1, M2, M3> {
+        BranchChannel {
+            challenge: challenge.clone(),
+            message1: None,
+            message2: None,
+            message3: None,
+        }
+    }
+
+    fn with_messages(
+        challenge: &Integer,
+        message1: &M1,
+        message2: &M2,
+        message3: &M3,
+    ) -> BranchChannel<M1, M2, M3> {
+        BranchChannel {
+            challenge: challenge.clone(),
+            message1: Some(message1.clone()),
+            message2: Some(message2.clone()),
+            message3: Some(message3.clone()),
+        }
+    }
+
+    fn messages(&self) -> Result<(M1, M2, M3), ProofError> {
+        match (&self.message1, &self.message2, &self.message3) {
+            (Some(message1), Some(message2), Some(message3)) => {
+                Ok((message1.clone(), message2.clone(), message3.clone()))
+            }
+            _ => Err(ProofError::CouldNotCreateProof),
+        }
+    }
+}
+
+fn received<M: Clone>(message: &Option<M>) -> Result<M, ChannelError> {
+    message.clone().ok_or(ChannelError::CouldNotSend)
+}
+
+type RootBranchChannel<G> = BranchChannel<root::Message1<G>, root::Message2<G>, root::Message3>;
+type CoprimeBranchChannel<G> =
+    BranchChannel<coprime::Message1<G>, coprime::Message2<G>, coprime::Message3>;
+
+impl<G: ConvertibleUnknownOrderGroup> RootVerifierChannel<G> for RootBranchChannel<G> {
+    fn send_message1(&mut self, message: &root::Message1<G>) -> Result<(), ChannelError> {
+        self.message1 = Some(message.clone());
+        Ok(())
+    }
+    fn send_message2(&mut self, message: &root::Message2<G>) -> Result<(), ChannelError> {
+        self.message2 = Some(message.clone());
+        Ok(())
+    }
+    fn send_message3(&mut self, message: &root::Message3) -> Result<(), ChannelError> {
+        self.message3 = Some(message.clone());
+        Ok(())
+    }
+    fn receive_challenge(&mut self) -> Result<Integer, ChannelError> {
+        Ok(self.challenge.clone())
+    }
+}
+
+impl<G: ConvertibleUnknownOrderGroup> RootProverChannel<G> for RootBranchChannel<G> {
+    fn receive_message1(&mut self) -> Result<root::Message1<G>, ChannelError> {
+        received(&self.message1)
+    }
+    fn receive_message2(&mut self) -> Result<root::Message2<G>, ChannelError> {
+        received(&self.message2)
+    }
+    fn receive_message3(&mut self) -> Result<root::Message3, ChannelError> {
+        received(&self.message3)
+    }
+    fn generate_and_send_challenge(&mut self) -> Result<Integer, ChannelError> {
+        Ok(self.challenge.clone())
+    }
+}
+
+impl<G: ConvertibleUnknownOrderGroup> CoprimeVerifierChannel<G> for CoprimeBranchChannel<G> {
+    fn send_message1(&mut self, message: &coprime::Message1<G>) -> Result<(), ChannelError> {
+        self.message1 = Some(message.clone());
+        Ok(())
+    }
+    fn send_message2(&mut self, message: &coprime::Message2<G>) -> Result<(), ChannelError> {
+        self.message2 = Some(message.clone());
+        Ok(())
+    }
+    fn send_message3(&mut self, message: &coprime::Message3) -> Result<(), ChannelError> {
+        self.message3 = Some(message.clone());
+        Ok(())
+    }
+    fn receive_challenge(&mut self) -> Result<Integer, ChannelError> {
+        Ok(self.challenge.clone())
+    }
+}
+
+impl<G: ConvertibleUnknownOrderGroup> CoprimeProverChannel<G> for CoprimeBranchChannel<G> {
+    fn receive_message1(&mut self) -> Result<coprime::Message1<G>, ChannelError> {
+        received(&self.message1)
+    }
+    fn receive_message2(&mut self) -> Result<coprime::Message2<G>, ChannelError> {
+        received(&self.message2)
+    }
+    fn receive_message3(&mut self) -> Result<coprime::Message3, ChannelError> {
+        received(&self.message3)
+    }
+    fn generate_and_send_challenge(&mut self) -> Result<Integer, ChannelError> {
+        Ok(self.challenge.clone())
+    }
+}
+
+/// Replays the integer randomness of a proof from its seed.
+struct SeededRng(StdRng);
+
+impl RandGen for SeededRng {
+    fn gen(&mut self) -> u32 {
+        self.0.next_u32()
+    }
+}
+
+/// Runs `prove` with the randomness derived from `seed`.
+fn with_seeded_rng<T, F: FnOnce(&mut RandState) -> Result<T, ProofError>>(
+    seed: [u8; 32],
+    prove: F,
+) -> Result<T, ProofError> {
+    let mut seeded_rng = SeededRng(StdRng::from_seed(seed));
+    let mut rng = RandState::new_custom(&mut seeded_rng);
+    prove(&mut rng)
+}
+
+/// Returns `value mod modulus`, in `[0, modulus)`.
+fn reduce(value: Integer, modulus: &Integer) -> Integer {
+    let mut value = value % modulus;
+    if value < 0 {
+        value += modulus;
+    }
+    value
+}
+
+impl<G: ConvertibleUnknownOrderGroup, P: CurvePointProjective, HP: HashToPrimeProtocol<P>>
+    Protocol<G, P, HP>
+{
+    pub fn setup<R1: MutRandState, R2: RngCore + CryptoRng>(
+        parameters: &Parameters,
+        rng1: &mut R1,
+        rng2: &mut R2,
+    ) -> Result<Protocol<G, P, HP>, SetupError> {
+        validate_coprime_group::<G>().map_err(|_| SetupError::UnsupportedGroupForCoprime)?;
+        let integer_commitment_parameters = IntegerCommitment::<G>::setup(rng1);
+        let pedersen_commitment_parameters = PedersenCommitment::<P>::setup(rng2);
+        let hash_to_prime_parameters = HP::setup(
+            rng2,
+            &pedersen_commitment_parameters,
+            &parameters.for_sub_protocol(SubProtocol::HashToPrime),
+        )?;
+        Ok(Protocol {
+            crs: CRS::<G, P, HP> {
+                parameters: parameters.clone(),
+                crs_root: CRSRoot::<G> {
+                    parameters: parameters.for_sub_protocol(SubProtocol::Root),
+                    integer_commitment_parameters: integer_commitment_parameters.clone(),
+                },
+                crs_coprime: CRSCoprime::<G> {
+                    parameters: parameters.for_sub_protocol(SubProtocol::Coprime),
+                    integer_commitment_parameters: integer_commitment_parameters.clone(),
+                },
+                crs_modeq: CRSModEq::<G, P> {
+                    parameters: parameters.for_sub_protocol(SubProtocol::ModEq),
+                    integer_commitment_parameters,
+                    pedersen_commitment_parameters: pedersen_commitment_parameters.clone(),
+                },
+                crs_hash_to_prime: CRSHashToPrime::<P, HP> {
+                    parameters: parameters.for_sub_protocol(SubProtocol::HashToPrime),
+                    pedersen_commitment_parameters,
+                    hash_to_prime_parameters,
+                },
+            },
+        })
+    }
+
+    pub fn from_crs(crs: &CRS<G, P, HP>) -> Protocol<G, P, HP> {
+        Protocol { crs: crs.clone() }
+    }
+
+    /// The challenges of the branches are below `2^security_soundness` of
+    /// both the root and the coprime protocols.
+    fn challenge_bits(&self) -> u16 {
+        std::cmp::min(
+            self.crs.crs_root.parameters.security_soundness,
+            self.crs.crs_coprime.parameters.security_soundness,
+        )
+    }
+
+    fn challenge_modulus(&self) -> Integer {
+        Integer::from(Integer::u_pow_u(2, self.challenge_bits() as u32))
+    }
+
+    fn root_statements(&self, statement: &Statement<G, P>, c_e: &G::Elem) -> [RootStatement<G>; 2] {
+        [
+            RootStatement {
+                c_e: c_e.clone(),
+                acc: statement.c_p_1.clone(),
+            },
+            RootStatement {
+                c_e: c_e.clone(),
+                acc: statement.c_p_2.clone(),
+            },
+        ]
+    }
+
+    fn coprime_statements(
+        &self,
+        statement: &Statement<G, P>,
+        c_e: &G::Elem,
+    ) -> [CoprimeStatement<G>; 2] {
+        [
+            CoprimeStatement {
+                c_e: c_e.clone(),
+                acc: statement.c_p_1.clone(),
+            },
+            CoprimeStatement {
+                c_e: c_e.clone(),
+                acc: statement.c_p_2.clone(),
+            },
+        ]
+    }
+
+    /// Absorbs `c_e` and the first messages of the four branch proofs, and
+    /// derives the challenge the branch challenges add up to.
+    fn challenge<
+        T: TranscriptProtocolIntersection<G>
+            + TranscriptProtocolRoot<G>
+            + TranscriptProtocolCoprime<G>,
+    >(
+        &self,
+        transcript: &RefCell<T>,
+        c_e: &G::Elem,
+        proofs_root: [&RootProof<G>; 2],
+        proofs_coprime: [&CoprimeProof<G>; 2],
+    ) -> Result<Integer, ChannelError> {
+        {
+            let mut transcript = transcript.try_borrow_mut()?;
+            transcript.intersection_domain_sep();
+            transcript.append_integer_point(b"c_e", c_e);
+        }
+        for proof in proofs_root.iter() {
+            let mut channel =
+                RootTranscriptProverChannel::new(&self.crs.crs_root, transcript, proof);
+            channel.receive_message1()?;
+            channel.receive_message2()?;
+        }
+        for proof in proofs_coprime.iter() {
+            let mut channel =
+                CoprimeTranscriptProverChannel::new(&self.crs.crs_coprime, transcript, proof);
+            channel.receive_message1()?;
+            channel.receive_message2()?;
+        }
+        let mut transcript = transcript.try_borrow_mut()?;
+        transcript.intersection_domain_sep();
+        Ok(transcript.challenge_scalar(b"c", self.challenge_bits()))
+    }
+
+    pub fn prove<
+        R1: MutRandState,
+        R2: RngCore + CryptoRng,
+        T: TranscriptProtocolIntersection<G>
+            + TranscriptProtocolRoot<G>
+            + TranscriptProtocolCoprime<G>
+            + TranscriptProtocolModEq<G, P>
+            + TranscriptProtocolHashToPrime<P>,
+    >(
+        &self,
+        transcript: &RefCell<T>,
+        rng1: &mut R1,
+        rng2: &mut R2,
+        statement: &Statement<G, P>,
+        witness: &Witness<G>,
+    ) -> Result<Proof<G, P, HP>, ProofError> {
+        let hash_to_prime = HP::from_crs(&self.crs.crs_hash_to_prime);
+        validate_element_size::<P, HP>(&self.crs.parameters, witness.e())?;
+        let (hashed_e, _) = hash_to_prime.hash_to_prime(witness.e())?;
+        validate_witness::<P>(&self.crs.parameters, &hashed_e, witness.r_q())?;
+        let r = random_between(rng1, &Integer::from(0), &G::order_upper_bound());
+        let c_e = self
+            .crs
+            .crs_root
+            .integer_commitment_parameters
+            .commit(&hashed_e, &r)?;
+        let root = RootProtocol::from_crs(&self.crs.crs_root)?;
+        let coprime = CoprimeProtocol::from_crs(&self.crs.crs_coprime)?;
+        let root_statements = self.root_statements(statement, &c_e);
+        let coprime_statements = self.coprime_statements(statement, &c_e);
+
+        let modulus = self.challenge_modulus();
+        let c_simulated = modulus.clone().random_below(rng1);
+        let mut seeds = [[0u8; 32]; 2];
+        rng2.fill_bytes(&mut seeds[0]);
+        rng2.fill_bytes(&mut seeds[1]);
+        let (c_both, proofs_root, proofs_coprime) = match witness {
+            Witness::Both { w_1, w_2, .. } => {
+                let simulate = |i: usize, rng: &mut R1| {
+                    simulate_coprime_with_challenge(
+                        &coprime,
+                        &coprime_statements[i],
+                        &c_simulated,
+                        rng,
+                    )
+                    .map_err(|_| ProofError::CouldNotCreateProof)
+                };
+                let proofs_coprime = [simulate(0, rng1)?, simulate(1, rng1)?];
+                let root_witnesses = [w_1, w_2]
+                    .iter()
+                    .map(|w| RootWitness {
+                        e: hashed_e.clone(),
+                        r: r.clone(),
+                        w: (*w).clone(),
+                    })
+                    .collect::<Vec<_>>();
+                let prove = |i: usize, c: &Integer| {
+                    with_seeded_rng(seeds[i], |rng| {
+                        let mut channel = RootBranchChannel::new(c);
+                        root.prove(&mut channel, rng, &root_statements[i], &root_witnesses[i])?;
+                        let (message1, message2, message3) = channel.messages()?;
+                        Ok(RootProof {
+                            message1,
+                            message2,
+                            message3,
+                        })
+                    })
+                };
+                let first_messages = [prove(0, &Integer::from(0))?, prove(1, &Integer::from(0))?];
+                let c = self.challenge(
+                    transcript,
+                    &c_e,
+                    [&first_messages[0], &first_messages[1]],
+                    [&proofs_coprime[0], &proofs_coprime[1]],
+                )?;
+                let c_both = reduce(c - &c_simulated, &modulus);
+                let proofs_root = [prove(0, &c_both)?, prove(1, &c_both)?];
+                (c_both, proofs_root, proofs_coprime)
+            }
+            Witness::Neither {
+                d_1, b_1, d_2, b_2, ..
+            } => {
+                let simulate = |i: usize, rng: &mut R1| {
+                    simulate_root_with_challenge(&root, &root_statements[i], &c_simulated, rng)
+                        .map_err(|_| ProofError::CouldNotCreateProof)
+                };
+                let proofs_root = [simulate(0, rng1)?, simulate(1, rng1)?];
+                let coprime_witnesses = [(d_1, b_1), (d_2, b_2)]
+                    .iter()
+                    .map(|(d, b)| CoprimeWitness {
+                        e: hashed_e.clone(),
+                        r: r.clone(),
+                        d: (*d).clone(),
+                        b: (*b).clone(),
+                    })
+                    .collect::<Vec<_>>();
+                let prove = |i: usize, c: &Integer| {
+                    with_seeded_rng(seeds[i], |rng| {
+                        let mut channel = CoprimeBranchChannel::new(c);
+                        coprime.prove(
+                            &mut channel,
+                            rng,
+                            &coprime_statements[i],
+                            &coprime_witnesses[i],
+                        )?;
+                        let (message1, message2, message3) = channel.messages()?;
+                        Ok(CoprimeProof {
+                            message1,
+                            message2,
+                            message3,
+                        })
+                    })
+                };
+                let first_messages = [prove(0, &Integer::from(0))?, prove(1, &Integer::from(0))?];
+                let c = self.challenge(
+                    transcript,
+                    &c_e,
+                    [&proofs_root[0], &proofs_root[1]],
+                    [&first_messages[0], &first_messages[1]],
+                )?;
+                let c_neither = reduce(c - &c_simulated, &modulus);
+                let proofs_coprime = [prove(0, &c_neither)?, prove(1, &c_neither)?];
+                (c_simulated, proofs_root, proofs_coprime)
+            }
+        };
+
+        let modeq = ModEqProtocol::from_crs(&self.crs.crs_modeq);
+        let mut modeq_channel =
+            ModEqTranscriptVerifierChannel::new(&self.crs.crs_modeq, transcript);
+        modeq.prove(
+            &mut modeq_channel,
+            rng1,
+            rng2,
+            &ModEqStatement {
+                c_e: c_e.clone(),
+                c_e_q: statement.c_e_q.clone(),
+            },
+            &ModEqWitness {
+                e: hashed_e,
+                r,
+                r_q: witness.r_q().clone(),
+            },
+        )?;
+        let mut hash_to_prime_channel =
+            HashToPrimeTranscriptVerifierChannel::new(&self.crs.crs_hash_to_prime, transcript);
+        hash_to_prime.prove(
+            &mut hash_to_prime_channel,
+            rng2,
+            &HashToPrimeStatement {
+                c_e_q: statement.c_e_q.clone(),
+            },
+            &HashToPrimeWitness {
+                e: witness.e().clone(),
+                r_q: witness.r_q().clone(),
+            },
+        )?;
+
+        let [proof_root_1, proof_root_2] = proofs_root;
+        let [proof_coprime_1, proof_coprime_2] = proofs_coprime;
+        Ok(Proof {
+            c_e,
+            c_both,
+            proof_root_1,
+            proof_root_2,
+            proof_coprime_1,
+            proof_coprime_2,
+            proof_modeq: modeq_channel
+                .proof()
+                .map_err(|_| ProofError::CouldNotCreateProof)?,
+            proof_hash_to_prime: hash_to_prime_channel
+                .proof()
+                .map_err(|_| ProofError::CouldNotCreateProof)?,
+        })
+    }
+
+    pub fn verify<
+        T: TranscriptProtocolIntersection<G>
+            + TranscriptProtocolRoot<G>
+            + TranscriptProtocolCoprime<G>
+            + TranscriptProtocolModEq<G, P>
+            + TranscriptProtocolHashToPrime<P>,
+    >(
+        &self,
+        transcript: &RefCell<T>,
+        statement: &Statement<G, P>,
+        proof: &Proof<G, P, HP>,
+    ) -> Result<(), VerificationError> {
+        let modulus = self.challenge_modulus();
+        if proof.c_both < 0 || proof.c_both >= modulus {
+            return Err(VerificationError::VerificationFailed);
+        }
+        let c = self.challenge(
+            transcript,
+            &proof.c_e,
+            [&proof.proof_root_1, &proof.proof_root_2],
+            [&proof.proof_coprime_1, &proof.proof_coprime_2],
+        )?;
+        let c_neither = reduce(c - &proof.c_both, &modulus);
+
+        let root = RootProtocol::from_crs(&self.crs.crs_root)?;
+        let root_statements = self.root_statements(statement, &proof.c_e);
+        for (proof_root, root_statement) in [&proof.proof_root_1, &proof.proof_root_2]
+            .iter()
+            .zip(root_statements.iter())
+        {
+            let mut channel = RootBranchChannel::with_messages(
+                &proof.c_both,
+                &proof_root.message1,
+                &proof_root.message2,
+                &proof_root.message3,
+            );
+            root.verify(&mut channel, root_statement)?;
+        }
+        let coprime = CoprimeProtocol::from_crs(&self.crs.crs_coprime)?;
+        let coprime_statements = self.coprime_statements(statement, &proof.c_e);
+        for (proof_coprime, coprime_statement) in [&proof.proof_coprime_1, &proof.proof_coprime_2]
+            .iter()
+            .zip(coprime_statements.iter())
+        {
+            let mut channel = CoprimeBranchChannel::with_messages(
+                &c_neither,
+                &proof_coprime.message1,
+                &proof_coprime.message2,
+                &proof_coprime.message3,
+            );
+            coprime.verify(&mut channel, coprime_statement)?;
+        }
+
+        let modeq = ModEqProtocol::from_crs(&self.crs.crs_modeq);
+        let mut modeq_channel =
+            ModEqTranscriptProverChannel::new(&self.crs.crs_modeq, transcript, &proof.proof_modeq);
+        modeq.verify(
+            &mut modeq_channel,
+            &ModEqStatement {
+                c_e: proof.c_e.clone(),
+                c_e_q: statement.c_e_q.clone(),
+            },
+        )?;
+        let hash_to_prime = HP::from_crs(&self.crs.crs_hash_to_prime);
+        let mut hash_to_prime_channel = HashToPrimeTranscriptProverChannel::new(
+            &self.crs.crs_hash_to_prime,
+            transcript,
+            &proof.proof_hash_to_prime,
+        );
+        hash_to_prime.verify(
+            &mut hash_to_prime_channel,
+            &HashToPrimeStatement {
+                c_e_q: statement.c_e_q.clone(),
+            },
+        )
+    }
+}
+
+#[cfg(all(test, feature = "arkworks"))]
+mod test {
+    use super::{Protocol, Statement, Witness};
+    use crate::{
+        commitments::Commitment, parameters::Parameters,
+        protocols::hash_to_prime::snark_range::Protocol as HPProtocol,
+    };
+    use accumulator::{group::Rsa2048, AccumulatorWithoutHashToPrime};
+    use ark_bls12_381::{Bls12_381, G1Projective};
+    use merlin::Transcript;
+    use rand::thread_rng;
+    use rug::rand::RandState;
+    use rug::Integer;
+    use std::cell::RefCell;
+
+    const LARGE_PRIMES: [u64; 3] = [
+        12_702_637_924_034_044_211,
+        378_373_571_372_703_133,
+        8_640_171_141_336_142_787,
+    ];
+
+    type Accumulator = accumulator::Accumulator<Rsa2048, Integer, AccumulatorWithoutHashToPrime>;
+
+    #[test]
+    fn test_both_or_neither() {
+        let params = Parameters::from_security_level(128).unwrap();
+        let mut rng1 = RandState::new();
+        rng1.seed(&Integer::from(13));
+        let mut rng2 = thread_rng();
+
+        let protocol = Protocol::<Rsa2048, G1Projective, HPProtocol<Bls12_381>>::setup(
+            &params, &mut rng1, &mut rng2,
+        )
+        .unwrap();
+
+        let value = Integer::from(Integer::u_pow_u(2, params.hash_to_prime_bits as u32))
+            - &Integer::from(245);
+        let randomness = Integer::from(5);
+        let c_e_q = protocol
+            .crs
+            .crs_modeq
+            .pedersen_commitment_parameters
+            .commit(&value, &randomness)
+            .unwrap();
+        let prove_and_verify = |statement: &Statement<Rsa2048, G1Projective>,
+                                witness: &Witness<Rsa2048>,
+                                rng1: &mut RandState| {
+            let mut rng2 = thread_rng();
+            let proof_transcript = RefCell::new(Transcript::new(b"intersection"));
+            let proof = protocol
+                .prove(&proof_transcript, rng1, &mut rng2, statement, witness)
+                .unwrap();
+            let verification_transcript = RefCell::new(Transcript::new(b"intersection"));
+            protocol.verify(&verification_transcript, statement, &proof)
+        };
+
+        // in both accumulators
+        let members_1 = Accumulator::empty()
+            .add(&[Integer::from(LARGE_PRIMES[0])])
+            .add_with_proof(&[value.clone()]);
+        let members_2 = Accumulator::empty()
+            .add(&[Integer::from(LARGE_PRIMES[1])])
+            .add_with_proof(&[value.clone()]);
+        let statement = Statement {
+            c_p_1: members_1.0.value.clone(),
+            c_p_2: members_2.0.value.clone(),
+            c_e_q: c_e_q.clone(),
+        };
+        let witness = Witness::Both {
+            e: value.clone(),
+            r_q: randomness.clone(),
+            w_1: members_1.1.witness.0.value.clone(),
+            w_2: members_2.1.witness.0.value.clone(),
+        };
+        prove_and_verify(&statement, &witness, &mut rng1).unwrap();
+
+        // in neither
+        let set_1 = vec![
+            Integer::from(LARGE_PRIMES[0]),
+            Integer::from(LARGE_PRIMES[1]),
+        ];
+        let set_2 = vec![Integer::from(LARGE_PRIMES[2])];
+        let nonmembers_1 = Accumulator::empty().add(&set_1);
+        let nonmembers_2 = Accumulator::empty().add(&set_2);
+        let proof_1 = nonmembers_1
+            .prove_nonmembership(&set_1, &[value.clone()])
+            .unwrap();
+        let proof_2 = nonmembers_2
+            .prove_nonmembership(&set_2, &[value.clone()])
+            .unwrap();
+        let statement = Statement {
+            c_p_1: nonmembers_1.value.clone(),
+            c_p_2: nonmembers_2.value.clone(),
+            c_e_q: c_e_q.clone(),
+        };
+        let witness = Witness::Neither {
+            e: value.clone(),
+            r_q: randomness.clone(),
+            d_1: proof_1.d,
+            b_1: proof_1.b,
+            d_2: proof_2.d,
+            b_2: proof_2.b,
+        };
+        prove_and_verify(&statement, &witness, &mut rng1).unwrap();
+
+        // in only one of them
+        let statement = Statement {
+            c_p_1: members_1.0.value.clone(),
+            c_p_2: nonmembers_2.value,
+            c_e_q,
+        };
+        let witness = Witness::Both {
+            e: value,
+            r_q: randomness,
+            w_1: members_1.1.witness.0.value,
+            w_2: members_2.1.witness.0.value,
+        };
+        assert!(prove_and_verify(&statement, &witness, &mut rng1).is_err());
+    }
+}
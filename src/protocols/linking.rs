@@ -0,0 +1,358 @@
+//! Links two membership proofs, possibly against different accumulators or
+//! epochs, to the same hidden element, e.g. for a user who chooses to show two
+//! services that their credentials belong to the same identity.
+//!
+//! Each membership proof commits to the element in its `c_e_q`, under its own
+//! randomness. The linking proof shows both commitments open to the same
+//! value, by proving knowledge of `r = r_q_1 - r_q_2` such that
+//! `c_e_q_1 - c_e_q_2 = h^r`: by the binding of Pedersen commitments, a prover
+//! can't know such an `r` for commitments to different values. The two
+//! membership proofs and the linking proof are run on one transcript, so the
+//! artifact can't be assembled from proofs made for other purposes. The
+//! proofs are non-interactive only.
+use crate::{
+    commitments::{pedersen::PedersenCommitment, Commitment},
+    protocols::{
+        hash_to_prime::{transcript::TranscriptProtocolHashToPrime, HashToPrimeProtocol},
+        membership::{
+            transcript::{TranscriptProtocolMembership, TranscriptProverChannel},
+            Proof as MembershipProof, Protocol as MembershipProtocol,
+            Statement as MembershipStatement, Witness as MembershipWitness, CRS,
+        },
+        modeq::transcript::TranscriptProtocolModEq,
+        root::transcript::TranscriptProtocolRoot,
+        VerificationError,
+    },
+    transcript::{versioned_domain_sep, TranscriptProtocolChallenge, TranscriptProtocolCurve},
+    utils::{
+        curve::CurvePointProjective, integer_to_bigint, integer_to_bigint_mod_q,
+        ConvertibleUnknownOrderGroup,
+    },
+};
+#[cfg(feature = "prover")]
+use crate::{
+    protocols::{membership::transcript::TranscriptVerifierChannel, ProofError},
+    utils::curve::Field,
+};
+use merlin::Transcript;
+#[cfg(feature = "prover")]
+use rand::{CryptoRng, RngCore};
+#[cfg(feature = "prover")]
+use rug::rand::MutRandState;
+use rug::Integer;
+use std::cell::RefCell;
+
+pub trait TranscriptProtocolLinking<P: CurvePointProjective>:
+    TranscriptProtocolCurve<P> + TranscriptProtocolChallenge
+{
+    fn linking_domain_sep(&mut self);
+}
+
+impl<P: CurvePointProjective> TranscriptProtocolLinking<P> for Transcript {
+    fn linking_domain_sep(&mut self) {
+        self.append_message(b"dom-sep", &versioned_domain_sep(b"linking"));
+    }
+}
+
+pub struct Statement<G: ConvertibleUnknownOrderGroup, P: CurvePointProjective> {
+    pub statement_1: MembershipStatement<G, P>,
+    pub statement_2: MembershipStatement<G, P>,
+}
+
+impl<G: ConvertibleUnknownOrderGroup, P: CurvePointProjective> Clone for Statement<G, P> {
+    fn clone(&self) -> Self {
+        Self {
+            statement_1: self.statement_1.clone(),
+            statement_2: self.statement_2.clone(),
+        }
+    }
+}
+
+/// The witnesses of the two membership proofs, which have to be for the same
+/// element.
+pub struct Witness<G: ConvertibleUnknownOrderGroup> {
+    pub witness_1: MembershipWitness<G>,
+    pub witness_2: MembershipWitness<G>,
+}
+
+pub struct Proof<
+    G: ConvertibleUnknownOrderGroup,
+    P: CurvePointProjective,
+    HP: HashToPrimeProtocol<P>,
+> {
+    pub proof_1: MembershipProof<G, P, HP>,
+    pub proof_2: MembershipProof<G, P, HP>,
+    pub alpha: <PedersenCommitment<P> as Commitment>::Instance,
+    pub s_r: P::ScalarField,
+}
+
+impl<G: ConvertibleUnknownOrderGroup, P: CurvePointProjective, HP: HashToPrimeProtocol<P>> Clone
+    for Proof<G, P, HP>
+{
+    fn clone(&self) -> Self {
+        Self {
+            proof_1: self.proof_1.clone(),
+            proof_2: self.proof_2.clone(),
+            alpha: self.alpha.clone(),
+            s_r: self.s_r.clone(),
+        }
+    }
+}
+
+pub struct Protocol<
+    G: ConvertibleUnknownOrderGroup,
+    P: CurvePointProjective,
+    HP: HashToPrimeProtocol<P>,
+> {
+    pub crs: CRS<G, P, HP>,
+}
+
+impl<G: ConvertibleUnknownOrderGroup, P: CurvePointProjective, HP: HashToPrimeProtocol<P>>
+    Protocol<G, P, HP>
+{
+    /// Both membership proofs are made with `crs`, so that their commitments
+    /// use the same Pedersen bases.
+    pub fn from_crs(crs: &CRS<G, P, HP>) -> Protocol<G, P, HP> {
+        Protocol { crs: crs.clone() }
+    }
+
+    /// `c_e_q_1 - c_e_q_2`, which is `h^(r_q_1 - r_q_2)` for commitments to
+    /// the same value.
+    fn difference(&self, statement: &Statement<G, P>) -> P {
+        let minus_one = integer_to_bigint::<P>(&Integer::from(-1));
+        statement
+            .statement_1
+            .c_e_q
+            .add(&statement.statement_2.c_e_q.mul(&minus_one))
+    }
+
+    fn challenge<T: TranscriptProtocolLinking<P>>(
+        &self,
+        transcript: &RefCell<T>,
+        statement: &Statement<G, P>,
+        alpha: &P,
+    ) -> Result<Integer, VerificationError> {
+        let mut transcript = transcript
+            .try_borrow_mut()
+            .map_err(|_| VerificationError::VerificationFailed)?;
+        transcript.linking_domain_sep();
+        transcript.append_curve_point(b"c_e_q_1", &statement.statement_1.c_e_q)?;
+        transcript.append_curve_point(b"c_e_q_2", &statement.statement_2.c_e_q)?;
+        transcript.append_curve_point(b"alpha", alpha)?;
+        Ok(transcript.challenge_scalar(b"c", self.crs.parameters.security_soundness))
+    }
+
+    #[cfg(feature = "prover")]
+    pub fn prove<
+        R1: MutRandState,
+        R2: RngCore + CryptoRng,
+        T: TranscriptProtocolMembership<G>
+            + TranscriptProtocolRoot<G>
+            + TranscriptProtocolModEq<G, P>
+            + TranscriptProtocolHashToPrime<P>
+            + TranscriptProtocolLinking<P>,
+    >(
+        &self,
+        transcript: &RefCell<T>,
+        rng1: &mut R1,
+        rng2: &mut R2,
+        statement: &Statement<G, P>,
+        witness: &Witness<G>,
+    ) -> Result<Proof<G, P, HP>, ProofError> {
+        if witness.witness_1.e != witness.witness_2.e {
+            return Err(ProofError::CouldNotCreateProof);
+        }
+        let membership = MembershipProtocol::from_crs(&self.crs);
+        let mut verifier_channel = TranscriptVerifierChannel::new(&self.crs, transcript);
+        membership.prove(
+            &mut verifier_channel,
+            rng1,
+            rng2,
+            &statement.statement_1,
+            &witness.witness_1,
+        )?;
+        let proof_1 = verifier_channel
+            .proof()
+            .map_err(|_| ProofError::CouldNotCreateProof)?;
+        let mut verifier_channel = TranscriptVerifierChannel::new(&self.crs, transcript);
+        membership.prove(
+            &mut verifier_channel,
+            rng1,
+            rng2,
+            &statement.statement_2,
+            &witness.witness_2,
+        )?;
+        let proof_2 = verifier_channel
+            .proof()
+            .map_err(|_| ProofError::CouldNotCreateProof)?;
+
+        let h = &self.crs.crs_modeq.pedersen_commitment_parameters.h;
+        let r_r = P::ScalarField::rand(rng2);
+        let alpha = h.mul(&r_r);
+        let c = self
+            .challenge(transcript, statement, &alpha)
+            .map_err(|_| ProofError::CouldNotCreateProof)?;
+        let r = integer_to_bigint_mod_q::<P>(&Integer::from(
+            &witness.witness_1.r_q - &witness.witness_2.r_q,
+        ))?;
+        let c_big = integer_to_bigint_mod_q::<P>(&c)?;
+        let s_r = r_r.sub(&r.mul(&c_big));
+
+        Ok(Proof {
+            proof_1,
+            proof_2,
+            alpha,
+            s_r,
+        })
+    }
+
+    pub fn verify<
+        T: TranscriptProtocolMembership<G>
+            + TranscriptProtocolRoot<G>
+            + TranscriptProtocolModEq<G, P>
+            + TranscriptProtocolHashToPrime<P>
+            + TranscriptProtocolLinking<P>,
+    >(
+        &self,
+        transcript: &RefCell<T>,
+        statement: &Statement<G, P>,
+        proof: &Proof<G, P, HP>,
+    ) -> Result<(), VerificationError> {
+        let membership = MembershipProtocol::from_crs(&self.crs);
+        let mut prover_channel =
+            TranscriptProverChannel::new(&self.crs, transcript, &proof.proof_1);
+        membership.verify(&mut prover_channel, &statement.statement_1)?;
+        let mut prover_channel =
+            TranscriptProverChannel::new(&self.crs, transcript, &proof.proof_2);
+        membership.verify(&mut prover_channel, &statement.statement_2)?;
+
+        let c = self.challenge(transcript, statement, &proof.alpha)?;
+        let c_big = integer_to_bigint_mod_q::<P>(&c)?;
+        let h = &self.crs.crs_modeq.pedersen_commitment_parameters.h;
+        let expected_alpha = h
+            .mul(&proof.s_r)
+            .add(&self.difference(statement).mul(&c_big));
+        if expected_alpha == proof.alpha {
+            Ok(())
+        } else {
+            Err(VerificationError::VerificationFailed)
+        }
+    }
+}
+
+#[cfg(all(test, feature = "arkworks"))]
+mod test {
+    use super::{Protocol, Statement, Witness};
+    use crate::{
+        commitments::Commitment,
+        parameters::Parameters,
+        protocols::{
+            hash_to_prime::snark_range::Protocol as HPProtocol,
+            membership::{
+                Protocol as MembershipProtocol, Statement as MembershipStatement,
+                Witness as MembershipWitness,
+            },
+            ProofError,
+        },
+        set::{AccumulatorProvider, SetAccumulator},
+        utils::curve::CurvePointProjective,
+    };
+    use accumulator::group::Rsa2048;
+    use ark_bls12_381::{Bls12_381, G1Projective};
+    use merlin::Transcript;
+    use rand::thread_rng;
+    use rug::rand::RandState;
+    use rug::Integer;
+    use std::cell::RefCell;
+
+    #[test]
+    fn test_linking() {
+        let params = Parameters::from_security_level(128).unwrap();
+        let mut rng1 = RandState::new();
+        rng1.seed(&Integer::from(13));
+        let mut rng2 = thread_rng();
+
+        let crs = MembershipProtocol::<Rsa2048, G1Projective, HPProtocol<Bls12_381>>::setup(
+            &params, &mut rng1, &mut rng2,
+        )
+        .unwrap()
+        .crs;
+        let protocol = Protocol::from_crs(&crs);
+
+        let value = Integer::from(Integer::u_pow_u(2, params.hash_to_prime_bits as u32))
+            - &Integer::from(245);
+        let other = Integer::from(Integer::u_pow_u(2, params.hash_to_prime_bits as u32))
+            - &Integer::from(159);
+        // the element is in two different sets
+        let set_1 = SetAccumulator::<Rsa2048>::from_elements(&[value.clone()]);
+        let set_2 = SetAccumulator::<Rsa2048>::from_elements(&[value.clone(), other.clone()]);
+        let pedersen = &crs.crs_modeq.pedersen_commitment_parameters;
+        let member = |set: &SetAccumulator<Rsa2048>, e: &Integer, r_q: u64| {
+            let r_q = Integer::from(r_q);
+            (
+                MembershipStatement {
+                    c_p: set.value(),
+                    c_e_q: pedersen.commit(e, &r_q).unwrap(),
+                },
+                MembershipWitness {
+                    e: e.clone(),
+                    r_q,
+                    w: set.membership_witness(e).unwrap(),
+                },
+            )
+        };
+        let (statement_1, witness_1) = member(&set_1, &value, 5);
+        let (statement_2, witness_2) = member(&set_2, &value, 9);
+        let statement = Statement {
+            statement_1,
+            statement_2,
+        };
+
+        let proof_transcript = RefCell::new(Transcript::new(b"linking"));
+        let proof = protocol
+            .prove(
+                &proof_transcript,
+                &mut rng1,
+                &mut rng2,
+                &statement,
+                &Witness {
+                    witness_1,
+                    witness_2,
+                },
+            )
+            .unwrap();
+        let verification_transcript = RefCell::new(Transcript::new(b"linking"));
+        protocol
+            .verify(&verification_transcript, &statement, &proof)
+            .unwrap();
+
+        // two membership proofs for different elements can't be linked
+        let (statement_1, witness_1) = member(&set_1, &value, 5);
+        let (statement_2, witness_2) = member(&set_2, &other, 9);
+        let mismatched = Statement {
+            statement_1,
+            statement_2,
+        };
+        let proof_transcript = RefCell::new(Transcript::new(b"linking"));
+        assert!(matches!(
+            protocol.prove(
+                &proof_transcript,
+                &mut rng1,
+                &mut rng2,
+                &mismatched,
+                &Witness {
+                    witness_1,
+                    witness_2,
+                },
+            ),
+            Err(ProofError::CouldNotCreateProof)
+        ));
+
+        let mut tampered = proof.clone();
+        tampered.alpha = tampered.alpha.add(&pedersen.h);
+        let verification_transcript = RefCell::new(Transcript::new(b"linking"));
+        assert!(protocol
+            .verify(&verification_transcript, &statement, &tampered)
+            .is_err());
+    }
+}
@@ -0,0 +1,216 @@
+//! Membership proofs relative to a hidden accumulator.
+//!
+//! A private set owner can publish a `HiddenAccumulator` instead of the
+//! accumulator value, and hand its opening to the provers along with their
+//! witnesses. The root protocol then shows the witness is a root of the
+//! accumulator hidden in `c_acc`, with the randomness committed in `c_a`, so
+//! verifiers never see the accumulator value.
+#[cfg(feature = "prover")]
+use crate::protocols::{
+    blinding::BlindingHistory,
+    hash_to_prime::channel::HashToPrimeVerifierChannel,
+    membership::{channel::MembershipVerifierChannel, Witness},
+    modeq::channel::ModEqVerifierChannel,
+    root::{channel::RootVerifierChannel, HiddenAccumulatorOpening},
+    ProofError,
+};
+use crate::{
+    commitments::{pedersen::PedersenCommitment, Commitment},
+    protocols::{
+        hash_to_prime::{channel::HashToPrimeProverChannel, HashToPrimeProtocol},
+        membership::{channel::MembershipProverChannel, Protocol},
+        modeq::channel::ModEqProverChannel,
+        root::{
+            channel::RootProverChannel, HiddenAccumulator, HiddenStatement as RootHiddenStatement,
+            Protocol as RootProtocol,
+        },
+        VerificationError,
+    },
+    utils::{curve::CurvePointProjective, ConvertibleUnknownOrderGroup},
+};
+#[cfg(feature = "prover")]
+use rand::{CryptoRng, RngCore};
+#[cfg(feature = "prover")]
+use rug::rand::MutRandState;
+
+pub struct HiddenStatement<G: ConvertibleUnknownOrderGroup, P: CurvePointProjective> {
+    pub acc: HiddenAccumulator<G>,
+    pub c_e_q: <PedersenCommitment<P> as Commitment>::Instance,
+}
+
+impl<G: ConvertibleUnknownOrderGroup, P: CurvePointProjective> Clone for HiddenStatement<G, P> {
+    fn clone(&self) -> Self {
+        Self {
+            acc: self.acc.clone(),
+            c_e_q: self.c_e_q.clone(),
+        }
+    }
+}
+
+impl<G: ConvertibleUnknownOrderGroup, P: CurvePointProjective, HP: HashToPrimeProtocol<P>>
+    Protocol<G, P, HP>
+{
+    /// Proves membership in the accumulator hidden in `statement`, opened by
+    /// `opening`.
+    #[cfg(feature = "prover")]
+    pub fn prove_hidden<
+        R1: MutRandState,
+        R2: RngCore + CryptoRng,
+        C: MembershipVerifierChannel<G>
+            + RootVerifierChannel<G>
+            + ModEqVerifierChannel<G, P>
+            + HashToPrimeVerifierChannel<P, HP>,
+    >(
+        &self,
+        verifier_channel: &mut C,
+        rng1: &mut R1,
+        rng2: &mut R2,
+        statement: &HiddenStatement<G, P>,
+        witness: &Witness<G>,
+        opening: &HiddenAccumulatorOpening,
+    ) -> Result<(), ProofError> {
+        let mut history = BlindingHistory::new(0);
+        let (c_e, root_witness) = self.commit_to_prime(verifier_channel, rng1, witness, None)?;
+        let root = RootProtocol::from_crs(&self.crs.crs_root)?;
+        root.prove_hidden_with_history(
+            verifier_channel,
+            rng1,
+            &RootHiddenStatement {
+                c_e: c_e.clone(),
+                acc: statement.acc.clone(),
+            },
+            &root_witness,
+            opening,
+            &mut history,
+        )?;
+        self.prove_modeq_and_hash_to_prime(
+            verifier_channel,
+            rng1,
+            rng2,
+            &statement.c_e_q,
+            witness,
+            c_e,
+            root_witness,
+            &mut history,
+            None,
+        )
+    }
+
+    pub fn verify_hidden<
+        C: MembershipProverChannel<G>
+            + RootProverChannel<G>
+            + ModEqProverChannel<G, P>
+            + HashToPrimeProverChannel<P, HP>,
+    >(
+        &self,
+        prover_channel: &mut C,
+        statement: &HiddenStatement<G, P>,
+    ) -> Result<(), VerificationError> {
+        let c_e = self.receive_c_e(prover_channel)?;
+        let root = RootProtocol::from_crs(&self.crs.crs_root)?;
+        let root_result = root.verify_hidden(
+            prover_channel,
+            &RootHiddenStatement {
+                c_e: c_e.clone(),
+                acc: statement.acc.clone(),
+            },
+        );
+        let (modeq_result, partially_verified) =
+            self.preverify_modeq(prover_channel, c_e, &statement.c_e_q)?;
+        let hash_to_prime_result = self.finalize(partially_verified);
+        root_result.and(modeq_result).and(hash_to_prime_result)
+    }
+}
+
+#[cfg(all(test, feature = "arkworks"))]
+mod test {
+    use super::HiddenStatement;
+    use crate::{
+        commitments::Commitment,
+        parameters::Parameters,
+        protocols::{
+            hash_to_prime::snark_range::Protocol as HPProtocol,
+            membership::{
+                transcript::{TranscriptProverChannel, TranscriptVerifierChannel},
+                Protocol, Witness,
+            },
+            root::HiddenAccumulator,
+        },
+    };
+    use accumulator::{group::Rsa2048, AccumulatorWithoutHashToPrime};
+    use ark_bls12_381::{Bls12_381, G1Projective};
+    use merlin::Transcript;
+    use rand::thread_rng;
+    use rug::rand::RandState;
+    use rug::Integer;
+    use std::cell::RefCell;
+
+    #[test]
+    fn test_hidden_accumulator() {
+        let params = Parameters::from_security_level(128).unwrap();
+        let mut rng1 = RandState::new();
+        rng1.seed(&Integer::from(13));
+        let mut rng2 = thread_rng();
+
+        let crs = Protocol::<Rsa2048, G1Projective, HPProtocol<Bls12_381>>::setup(
+            &params, &mut rng1, &mut rng2,
+        )
+        .unwrap()
+        .crs;
+        let protocol = Protocol::<Rsa2048, G1Projective, HPProtocol<Bls12_381>>::from_crs(&crs);
+
+        let value = Integer::from(Integer::u_pow_u(2, params.hash_to_prime_bits as u32))
+            - &Integer::from(245);
+        let randomness = Integer::from(5);
+        let accum =
+            accumulator::Accumulator::<Rsa2048, Integer, AccumulatorWithoutHashToPrime>::empty()
+                .add_with_proof(&[value.clone()]);
+        let (hidden, opening) =
+            HiddenAccumulator::hide(&crs.crs_root, &accum.0.value, &mut rng1).unwrap();
+        let statement = HiddenStatement {
+            acc: hidden,
+            c_e_q: crs
+                .crs_modeq
+                .pedersen_commitment_parameters
+                .commit(&value, &randomness)
+                .unwrap(),
+        };
+
+        let proof_transcript = RefCell::new(Transcript::new(b"membership"));
+        let mut verifier_channel = TranscriptVerifierChannel::new(&crs, &proof_transcript);
+        protocol
+            .prove_hidden(
+                &mut verifier_channel,
+                &mut rng1,
+                &mut rng2,
+                &statement,
+                &Witness {
+                    e: value,
+                    r_q: randomness,
+                    w: accum.1.witness.0.value,
+                },
+                &opening,
+            )
+            .unwrap();
+        let proof = verifier_channel.proof().unwrap();
+
+        let verification_transcript = RefCell::new(Transcript::new(b"membership"));
+        let mut prover_channel =
+            TranscriptProverChannel::new(&crs, &verification_transcript, &proof);
+        protocol
+            .verify_hidden(&mut prover_channel, &statement)
+            .unwrap();
+
+        // hiding the same accumulator again gives an unrelated statement
+        let (other_hidden, _) =
+            HiddenAccumulator::hide(&crs.crs_root, &accum.0.value, &mut rng1).unwrap();
+        let mut other_statement = statement;
+        other_statement.acc = other_hidden;
+        let verification_transcript = RefCell::new(Transcript::new(b"membership"));
+        let mut prover_channel =
+            TranscriptProverChannel::new(&crs, &verification_transcript, &proof);
+        assert!(protocol
+            .verify_hidden(&mut prover_channel, &other_statement)
+            .is_err());
+    }
+}
@@ -1,293 +1,1724 @@
 //! Implements CPMemRSA and CPMemRSAPrm.
+//!
+//! Besides `verify`, proofs can be checked in two steps, with `preverify`
+//! running the sigma protocols and `finalize` the hash-to-prime proof, or only
+//! in part with `verify_partially`. A proof is only valid once `finalize`
+//! succeeds, and a partially verified proof only as far as the sub-proofs it
+//! skipped were verified elsewhere.
+#[cfg(feature = "compressed")]
+use crate::protocols::{
+    modeq::CompressedProof as ModEqCompressedProof, root::CompressedProof as RootCompressedProof,
+};
+use crate::{
+    commitments::{
+        integer::{IntegerCommitment, IntegerCommitmentOpening},
+        pedersen::PedersenCommitment,
+        Commitment,
+    },
+    parameters::{Parameters, SubProtocol},
+    protocols::{
+        cache::{CacheKey, CacheKeyBuilder},
+        hash_to_prime::{
+            channel::HashToPrimeProverChannel, CRSHashToPrime, HashToPrimeError,
+            HashToPrimeProtocol, MemoryReport, MemoryUsage, PartiallyVerified,
+            Statement as HashToPrimeStatement,
+        },
+        modeq::{
+            channel::ModEqProverChannel, CRSModEq, Proof as ModEqProof, Protocol as ModEqProtocol,
+            Statement as ModEqStatement,
+        },
+        progress::ProgressObserver,
+        registry::{crs_digest, CrsDigest},
+        root::{
+            channel::RootProverChannel, CRSRoot, Proof as RootProof, Protocol as RootProtocol,
+            Statement as RootStatement,
+        },
+        statement::{hash_curve_point, StatementDigest, StatementHasher},
+        VerificationError,
+    },
+    utils::curve::{CurveError, CurvePointProjective},
+    utils::ConvertibleUnknownOrderGroup,
+};
+#[cfg(feature = "prover")]
 use crate::{
-    commitments::{integer::IntegerCommitment, pedersen::PedersenCommitment, Commitment},
-    parameters::Parameters,
     protocols::{
+        blinding::BlindingHistory,
         hash_to_prime::{
-            channel::{HashToPrimeProverChannel, HashToPrimeVerifierChannel},
-            CRSHashToPrime, HashToPrimeError, HashToPrimeProtocol,
-            Statement as HashToPrimeStatement, Witness as HashToPrimeWitness,
+            channel::HashToPrimeVerifierChannel, shared::SharedSubProofs,
+            transcript::TranscriptProtocolHashToPrime, validate_element_size, validate_witness,
+            Witness as HashToPrimeWitness,
         },
         modeq::{
-            channel::{ModEqProverChannel, ModEqVerifierChannel},
-            CRSModEq, Proof as ModEqProof, Protocol as ModEqProtocol, Statement as ModEqStatement,
+            channel::ModEqVerifierChannel, transcript::TranscriptProtocolModEq,
             Witness as ModEqWitness,
         },
+        progress::{report, NoProgress, Stage},
         root::{
-            channel::{RootProverChannel, RootVerifierChannel},
-            CRSRoot, Proof as RootProof, Protocol as RootProtocol, Statement as RootStatement,
+            channel::RootVerifierChannel, transcript::TranscriptProtocolRoot,
             Witness as RootWitness,
         },
-        ProofError, SetupError, VerificationError,
+        ProofError, SetupError,
     },
-    utils::ConvertibleUnknownOrderGroup,
-    utils::{curve::CurvePointProjective, random_between},
+    transcript::TranscriptProtocolWitnessRng,
+    utils::random_between,
 };
-use channel::{MembershipProverChannel, MembershipVerifierChannel};
+use channel::MembershipProverChannel;
+#[cfg(feature = "prover")]
+use channel::MembershipVerifierChannel;
 use rand::{CryptoRng, RngCore};
-use rug::rand::MutRandState;
+#[cfg(feature = "prover")]
+use rug::rand::{MutRandState, RandState};
 use rug::Integer;
+#[cfg(feature = "prover")]
+use std::cell::RefCell;
+use std::hash::{Hash, Hasher};
+#[cfg(feature = "prover")]
+use transcript::{
+    TranscriptProtocolMembership, TranscriptProverChannel, TranscriptVerifierChannel,
+};
+
+pub mod channel;
+pub mod hidden;
+pub mod multiplicity;
+pub mod prime;
+pub mod transcript;
+
+pub use prime::CPMemRSAPrm;
+
+pub struct CRS<G: ConvertibleUnknownOrderGroup, P: CurvePointProjective, HP: HashToPrimeProtocol<P>>
+{
+    // G contains the information about Z^*_N
+    pub parameters: Parameters,
+    pub crs_root: CRSRoot<G>,
+    pub crs_modeq: CRSModEq<G, P>,
+    pub crs_hash_to_prime: CRSHashToPrime<P, HP>,
+}
+
+impl<G: ConvertibleUnknownOrderGroup, P: CurvePointProjective, HP: HashToPrimeProtocol<P>> Clone
+    for CRS<G, P, HP>
+{
+    fn clone(&self) -> Self {
+        Self {
+            parameters: self.parameters.clone(),
+            crs_root: self.crs_root.clone(),
+            crs_modeq: self.crs_modeq.clone(),
+            crs_hash_to_prime: self.crs_hash_to_prime.clone(),
+        }
+    }
+}
+
+impl<G: ConvertibleUnknownOrderGroup, P: CurvePointProjective, HP: HashToPrimeProtocol<P>>
+    CRS<G, P, HP>
+{
+    /// Identifies the CRS, e.g. to select the verifier of a proof in a
+    /// `CrsRegistry`.
+    pub fn digest(&self) -> Result<CrsDigest, CurveError> {
+        crs_digest::<G, P>(
+            b"membership",
+            &[
+                &self.parameters,
+                &self.crs_root.parameters,
+                &self.crs_modeq.parameters,
+                &self.crs_hash_to_prime.parameters,
+            ],
+            &self.crs_root.integer_commitment_parameters,
+            &self.crs_modeq.pedersen_commitment_parameters,
+            &HP::parameters_to_bytes(&self.crs_hash_to_prime.hash_to_prime_parameters)?,
+        )
+    }
+}
+
+impl<G: ConvertibleUnknownOrderGroup, P: CurvePointProjective, HP: HashToPrimeProtocol<P>>
+    CRS<G, P, HP>
+where
+    HP::Parameters: MemoryUsage,
+{
+    /// The memory taken by the CRS, dominated by the hash-to-prime parameters.
+    pub fn memory_report(&self) -> MemoryReport {
+        let integer_commitment_parameters = &self.crs_root.integer_commitment_parameters;
+        let mut report = MemoryReport::default();
+        report.push(
+            "integer_commitment_parameters",
+            G::elem_to_bytes(&integer_commitment_parameters.g).len()
+                + G::elem_to_bytes(&integer_commitment_parameters.h).len(),
+        );
+        report.extend(self.crs_hash_to_prime.memory_report());
+        report
+    }
+}
+
+/// CPMemRSA, the membership protocol for arbitrary elements, which are hashed to primes
+/// using `HP`.
+pub type CPMemRSA<G, P, HP> = Protocol<G, P, HP>;
+
+pub struct Protocol<
+    G: ConvertibleUnknownOrderGroup,
+    P: CurvePointProjective,
+    HP: HashToPrimeProtocol<P>,
+> {
+    pub crs: CRS<G, P, HP>,
+}
+
+pub struct Statement<G: ConvertibleUnknownOrderGroup, P: CurvePointProjective> {
+    pub c_p: G::Elem,
+    pub c_e_q: <PedersenCommitment<P> as Commitment>::Instance,
+}
+
+impl<G: ConvertibleUnknownOrderGroup, P: CurvePointProjective> Clone for Statement<G, P> {
+    fn clone(&self) -> Self {
+        Self {
+            c_p: self.c_p.clone(),
+            c_e_q: self.c_e_q.clone(),
+        }
+    }
+}
+
+impl<G: ConvertibleUnknownOrderGroup, P: CurvePointProjective> PartialEq for Statement<G, P> {
+    fn eq(&self, other: &Self) -> bool {
+        self.c_p == other.c_p && self.c_e_q == other.c_e_q
+    }
+}
+
+impl<G: ConvertibleUnknownOrderGroup, P: CurvePointProjective> Eq for Statement<G, P> {}
+
+impl<G: ConvertibleUnknownOrderGroup, P: CurvePointProjective> Hash for Statement<G, P> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.c_p.hash(state);
+        hash_curve_point(&self.c_e_q, state);
+    }
+}
+
+impl<G: ConvertibleUnknownOrderGroup, P: CurvePointProjective> Statement<G, P> {
+    /// Stable identifier of the statement, see `protocols::statement`.
+    pub fn digest(&self) -> Result<StatementDigest, CurveError> {
+        let mut hasher = StatementHasher::new(b"membership");
+        hasher.append_group_element::<G>(b"c_p", &self.c_p);
+        hasher.append_curve_point(b"c_e_q", &self.c_e_q)?;
+        Ok(hasher.finish())
+    }
+}
+
+pub struct Witness<G: ConvertibleUnknownOrderGroup> {
+    pub e: Integer,
+    pub r_q: Integer,
+    pub w: G::Elem,
+}
+
+pub struct Proof<
+    G: ConvertibleUnknownOrderGroup,
+    P: CurvePointProjective,
+    HP: HashToPrimeProtocol<P>,
+> {
+    pub crs_digest: CrsDigest,
+    pub c_e: <IntegerCommitment<G> as Commitment>::Instance,
+    pub proof_root: RootProof<G>,
+    pub proof_modeq: ModEqProof<G, P>,
+    pub proof_hash_to_prime: HP::Proof,
+}
+
+impl<G: ConvertibleUnknownOrderGroup, P: CurvePointProjective, HP: HashToPrimeProtocol<P>> Clone
+    for Proof<G, P, HP>
+{
+    fn clone(&self) -> Self {
+        Self {
+            crs_digest: self.crs_digest,
+            c_e: self.c_e.clone(),
+            proof_root: self.proof_root.clone(),
+            proof_modeq: self.proof_modeq.clone(),
+            proof_hash_to_prime: self.proof_hash_to_prime.clone(),
+        }
+    }
+}
+
+impl<G: ConvertibleUnknownOrderGroup, P: CurvePointProjective, HP: HashToPrimeProtocol<P>>
+    Proof<G, P, HP>
+{
+    /// Size in bytes of the proof with the encodings of the sub-proofs'
+    /// `serialized_size`, and the hash-to-prime proof encoded by
+    /// `HP::proof_to_bytes`.
+    pub fn serialized_size(&self) -> Result<usize, CurveError> {
+        Ok(self.crs_digest.len()
+            + G::elem_to_bytes(&self.c_e).len()
+            + self.proof_root.serialized_size()
+            + self.proof_modeq.serialized_size()?
+            + HP::proof_to_bytes(&self.proof_hash_to_prime)?.len())
+    }
+
+    /// The statement `proof_root` is about, to examine or verify it on its own.
+    pub fn root_statement(&self, statement: &Statement<G, P>) -> RootStatement<G> {
+        RootStatement {
+            c_e: self.c_e.clone(),
+            acc: statement.c_p.clone(),
+        }
+    }
+
+    pub fn modeq_statement(&self, statement: &Statement<G, P>) -> ModEqStatement<G, P> {
+        ModEqStatement {
+            c_e: self.c_e.clone(),
+            c_e_q: statement.c_e_q.clone(),
+        }
+    }
+
+    pub fn hash_to_prime_statement(&self, statement: &Statement<G, P>) -> HashToPrimeStatement<P> {
+        HashToPrimeStatement {
+            c_e_q: statement.c_e_q.clone(),
+        }
+    }
+}
+
+/// A proof where the alphas of the sigma protocols are omitted and recomputed
+/// from the challenges and responses.
+#[cfg(feature = "compressed")]
+pub struct CompressedProof<
+    G: ConvertibleUnknownOrderGroup,
+    P: CurvePointProjective,
+    HP: HashToPrimeProtocol<P>,
+> {
+    pub crs_digest: CrsDigest,
+    pub c_e: <IntegerCommitment<G> as Commitment>::Instance,
+    pub proof_root: RootCompressedProof<G>,
+    pub proof_modeq: ModEqCompressedProof<P>,
+    pub proof_hash_to_prime: HP::Proof,
+}
+
+#[cfg(feature = "compressed")]
+impl<G: ConvertibleUnknownOrderGroup, P: CurvePointProjective, HP: HashToPrimeProtocol<P>> Clone
+    for CompressedProof<G, P, HP>
+{
+    fn clone(&self) -> Self {
+        Self {
+            crs_digest: self.crs_digest,
+            c_e: self.c_e.clone(),
+            proof_root: self.proof_root.clone(),
+            proof_modeq: self.proof_modeq.clone(),
+            proof_hash_to_prime: self.proof_hash_to_prime.clone(),
+        }
+    }
+}
+
+/// Setup and proving, behind the `prover` feature.
+#[cfg(feature = "prover")]
+impl<G: ConvertibleUnknownOrderGroup, P: CurvePointProjective, HP: HashToPrimeProtocol<P>>
+    Protocol<G, P, HP>
+{
+    pub fn setup<R1: MutRandState, R2: RngCore + CryptoRng>(
+        parameters: &Parameters,
+        rng1: &mut R1,
+        rng2: &mut R2,
+    ) -> Result<Protocol<G, P, HP>, SetupError> {
+        Self::setup_with_observer(parameters, rng1, rng2, &NoProgress)
+    }
+
+    /// Performs setup, reporting progress to `observer` and returning
+    /// `SetupError::Cancelled` if it asks to stop.
+    pub fn setup_with_observer<R1: MutRandState, R2: RngCore + CryptoRng, O: ProgressObserver>(
+        parameters: &Parameters,
+        rng1: &mut R1,
+        rng2: &mut R2,
+        observer: &O,
+    ) -> Result<Protocol<G, P, HP>, SetupError> {
+        let pedersen_commitment_parameters = PedersenCommitment::<P>::setup(rng2);
+        Self::setup_inner(
+            parameters,
+            &pedersen_commitment_parameters,
+            rng1,
+            rng2,
+            observer,
+        )
+    }
+
+    /// Performs setup reusing existing Pedersen commitment keys, so that
+    /// commitments to set elements produced elsewhere can be used as `c_e_q`
+    /// directly.
+    pub fn setup_with_pedersen_commitment_parameters<R1: MutRandState, R2: RngCore + CryptoRng>(
+        parameters: &Parameters,
+        pedersen_commitment_parameters: &PedersenCommitment<P>,
+        rng1: &mut R1,
+        rng2: &mut R2,
+    ) -> Result<Protocol<G, P, HP>, SetupError> {
+        Self::setup_inner(
+            parameters,
+            pedersen_commitment_parameters,
+            rng1,
+            rng2,
+            &NoProgress,
+        )
+    }
+
+    fn setup_inner<R1: MutRandState, R2: RngCore + CryptoRng, O: ProgressObserver>(
+        parameters: &Parameters,
+        pedersen_commitment_parameters: &PedersenCommitment<P>,
+        rng1: &mut R1,
+        rng2: &mut R2,
+        observer: &O,
+    ) -> Result<Protocol<G, P, HP>, SetupError> {
+        if !report(observer, Stage::IntegerCommitmentSetup, 0, 1) {
+            return Err(SetupError::Cancelled);
+        }
+        let integer_commitment_parameters = IntegerCommitment::<G>::setup(rng1);
+        observer.on_progress(Stage::IntegerCommitmentSetup, 1, 1);
+        let pedersen_commitment_parameters = pedersen_commitment_parameters.clone();
+        let hash_to_prime_parameters = HP::setup_with_observer(
+            rng2,
+            &pedersen_commitment_parameters,
+            &parameters.for_sub_protocol(SubProtocol::HashToPrime),
+            observer,
+        )?;
+        Ok(Protocol::with_crs(CRS::<G, P, HP> {
+            parameters: parameters.clone(),
+            crs_modeq: CRSModEq::<G, P> {
+                parameters: parameters.for_sub_protocol(SubProtocol::ModEq),
+                integer_commitment_parameters: integer_commitment_parameters.clone(),
+                pedersen_commitment_parameters: pedersen_commitment_parameters.clone(),
+            },
+            crs_root: CRSRoot::<G> {
+                parameters: parameters.for_sub_protocol(SubProtocol::Root),
+                integer_commitment_parameters,
+            },
+            crs_hash_to_prime: CRSHashToPrime::<P, HP> {
+                parameters: parameters.for_sub_protocol(SubProtocol::HashToPrime),
+                pedersen_commitment_parameters,
+                hash_to_prime_parameters,
+            },
+        }))
+    }
+
+    pub fn prove<
+        R1: MutRandState,
+        R2: RngCore + CryptoRng,
+        C: MembershipVerifierChannel<G>
+            + RootVerifierChannel<G>
+            + ModEqVerifierChannel<G, P>
+            + HashToPrimeVerifierChannel<P, HP>,
+    >(
+        &self,
+        verifier_channel: &mut C,
+        rng1: &mut R1,
+        rng2: &mut R2,
+        statement: &Statement<G, P>,
+        witness: &Witness<G>,
+    ) -> Result<(), ProofError> {
+        self.prove_with_history(
+            verifier_channel,
+            rng1,
+            rng2,
+            statement,
+            witness,
+            &mut BlindingHistory::new(0),
+        )
+    }
+
+    /// Proves over a transcript, deriving the randomness of the prover from
+    /// the transcript, the statement and the witness as well as `rng`, so that
+    /// blinding values can't repeat across statements even if `rng` does.
+    pub fn prove_with_transcript_rng<
+        'a,
+        R: RngCore + CryptoRng,
+        T: TranscriptProtocolMembership<G>
+            + TranscriptProtocolRoot<G>
+            + TranscriptProtocolModEq<G, P>
+            + TranscriptProtocolHashToPrime<P>
+            + TranscriptProtocolWitnessRng,
+    >(
+        &self,
+        verifier_channel: &mut TranscriptVerifierChannel<'a, G, P, HP, T>,
+        rng: &mut R,
+        statement: &Statement<G, P>,
+        witness: &Witness<G>,
+    ) -> Result<(), ProofError> {
+        let (mut integer_rng, mut curve_rng) =
+            verifier_channel.witness_rngs(statement, witness, rng)?;
+        let mut rng1 = RandState::new_custom(&mut integer_rng);
+        self.prove(
+            verifier_channel,
+            &mut rng1,
+            &mut curve_rng,
+            statement,
+            witness,
+        )
+    }
+
+    /// Proves over `transcript` and verifies the proof locally on a copy of
+    /// the transcript as it was before proving, which reproduces the
+    /// challenges, so that a CRS or environment mismatch is caught before the
+    /// proof is sent.
+    pub fn prove_and_check<
+        R1: MutRandState,
+        R2: RngCore + CryptoRng,
+        T: TranscriptProtocolMembership<G>
+            + TranscriptProtocolRoot<G>
+            + TranscriptProtocolModEq<G, P>
+            + TranscriptProtocolHashToPrime<P>
+            + Clone,
+    >(
+        &self,
+        transcript: &RefCell<T>,
+        rng1: &mut R1,
+        rng2: &mut R2,
+        statement: &Statement<G, P>,
+        witness: &Witness<G>,
+    ) -> Result<Proof<G, P, HP>, ProofError> {
+        let verification_transcript = RefCell::new(transcript.borrow().clone());
+        let mut verifier_channel = TranscriptVerifierChannel::new(&self.crs, transcript);
+        self.prove(&mut verifier_channel, rng1, rng2, statement, witness)?;
+        let proof = verifier_channel
+            .proof()
+            .map_err(|_| ProofError::CouldNotCreateProof)?;
+        let mut prover_channel =
+            TranscriptProverChannel::new(&self.crs, &verification_transcript, &proof);
+        self.verify(&mut prover_channel, statement)
+            .map_err(|e| ProofError::SelfCheckFailed(Box::new(e)))?;
+        Ok(proof)
+    }
+
+    /// Proves while recording the blinding values of the sigma protocols in
+    /// `history`, failing if they were used before.
+    pub fn prove_with_history<
+        R1: MutRandState,
+        R2: RngCore + CryptoRng,
+        C: MembershipVerifierChannel<G>
+            + RootVerifierChannel<G>
+            + ModEqVerifierChannel<G, P>
+            + HashToPrimeVerifierChannel<P, HP>,
+    >(
+        &self,
+        verifier_channel: &mut C,
+        rng1: &mut R1,
+        rng2: &mut R2,
+        statement: &Statement<G, P>,
+        witness: &Witness<G>,
+        history: &mut BlindingHistory,
+    ) -> Result<(), ProofError> {
+        self.prove_composed(
+            verifier_channel,
+            rng1,
+            rng2,
+            statement,
+            witness,
+            history,
+            None,
+            None,
+        )
+    }
+
+    /// Proves, reusing the hash-to-prime proof from `shared` if another proof
+    /// about the same `c_e_q` was created with it, and adding it otherwise.
+    pub fn prove_with_shared_sub_proofs<
+        R1: MutRandState,
+        R2: RngCore + CryptoRng,
+        C: MembershipVerifierChannel<G>
+            + RootVerifierChannel<G>
+            + ModEqVerifierChannel<G, P>
+            + HashToPrimeVerifierChannel<P, HP>,
+    >(
+        &self,
+        verifier_channel: &mut C,
+        rng1: &mut R1,
+        rng2: &mut R2,
+        statement: &Statement<G, P>,
+        witness: &Witness<G>,
+        shared: &mut SharedSubProofs<P, HP>,
+    ) -> Result<(), ProofError> {
+        self.prove_composed(
+            verifier_channel,
+            rng1,
+            rng2,
+            statement,
+            witness,
+            &mut BlindingHistory::new(0),
+            Some(shared),
+            None,
+        )
+    }
+
+    /// Proves about `opening.commitment`, a commitment to the prime `e`
+    /// hashes to which the caller already has, e.g. one registered at
+    /// issuance, instead of a fresh `c_e`. Verification is unchanged, and the
+    /// proof carries `opening.commitment` as `c_e`.
+    pub fn prove_with_integer_commitment<
+        R1: MutRandState,
+        R2: RngCore + CryptoRng,
+        C: MembershipVerifierChannel<G>
+            + RootVerifierChannel<G>
+            + ModEqVerifierChannel<G, P>
+            + HashToPrimeVerifierChannel<P, HP>,
+    >(
+        &self,
+        verifier_channel: &mut C,
+        rng1: &mut R1,
+        rng2: &mut R2,
+        statement: &Statement<G, P>,
+        witness: &Witness<G>,
+        opening: &IntegerCommitmentOpening<G>,
+    ) -> Result<(), ProofError> {
+        self.prove_composed(
+            verifier_channel,
+            rng1,
+            rng2,
+            statement,
+            witness,
+            &mut BlindingHistory::new(0),
+            None,
+            Some(opening),
+        )
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn prove_composed<
+        R1: MutRandState,
+        R2: RngCore + CryptoRng,
+        C: MembershipVerifierChannel<G>
+            + RootVerifierChannel<G>
+            + ModEqVerifierChannel<G, P>
+            + HashToPrimeVerifierChannel<P, HP>,
+    >(
+        &self,
+        verifier_channel: &mut C,
+        rng1: &mut R1,
+        rng2: &mut R2,
+        statement: &Statement<G, P>,
+        witness: &Witness<G>,
+        history: &mut BlindingHistory,
+        shared: Option<&mut SharedSubProofs<P, HP>>,
+        opening: Option<&IntegerCommitmentOpening<G>>,
+    ) -> Result<(), ProofError> {
+        let (c_e, root_witness) = self.commit_to_prime(verifier_channel, rng1, witness, opening)?;
+        let root = RootProtocol::from_crs(&self.crs.crs_root)?;
+        root.prove_with_history(
+            verifier_channel,
+            rng1,
+            &RootStatement {
+                c_e: c_e.clone(),
+                acc: statement.c_p.clone(),
+            },
+            &root_witness,
+            history,
+        )?;
+        self.prove_modeq_and_hash_to_prime(
+            verifier_channel,
+            rng1,
+            rng2,
+            &statement.c_e_q,
+            witness,
+            c_e,
+            root_witness,
+            history,
+            shared,
+        )
+    }
+
+    /// Hashes the element to a prime and sends the integer commitment `c_e`
+    /// to it, `opening` if given, returning the witness of the root protocol.
+    fn commit_to_prime<R1: MutRandState, C: MembershipVerifierChannel<G>>(
+        &self,
+        verifier_channel: &mut C,
+        rng1: &mut R1,
+        witness: &Witness<G>,
+        opening: Option<&IntegerCommitmentOpening<G>>,
+    ) -> Result<
+        (
+            <IntegerCommitment<G> as Commitment>::Instance,
+            RootWitness<G>,
+        ),
+        ProofError,
+    > {
+        validate_element_size::<P, HP>(&self.crs.parameters, &witness.e)?;
+        let (hashed_e, _) = self.hash_to_prime(&witness.e)?;
+        validate_witness::<P>(&self.crs.parameters, &hashed_e, &witness.r_q)?;
+        let integer_commitment_parameters = &self.crs.crs_root.integer_commitment_parameters;
+        let (c_e, r) = match opening {
+            Some(opening) => {
+                opening.validate(integer_commitment_parameters, &hashed_e)?;
+                (opening.commitment.clone(), opening.randomness.clone())
+            }
+            None => {
+                let r = random_between(rng1, &Integer::from(0), &G::order_upper_bound());
+                (integer_commitment_parameters.commit(&hashed_e, &r)?, r)
+            }
+        };
+        verifier_channel.send_crs_digest(&self.crs_digest()?)?;
+        verifier_channel.send_c_e(&c_e)?;
+        Ok((
+            c_e,
+            RootWitness {
+                e: hashed_e,
+                r,
+                w: witness.w.clone(),
+            },
+        ))
+    }
+
+    /// Proves `c_e` and `c_e_q` commit to the same element, and that it's the
+    /// hash of the committed element.
+    #[allow(clippy::too_many_arguments)]
+    fn prove_modeq_and_hash_to_prime<
+        R1: MutRandState,
+        R2: RngCore + CryptoRng,
+        C: ModEqVerifierChannel<G, P> + HashToPrimeVerifierChannel<P, HP>,
+    >(
+        &self,
+        verifier_channel: &mut C,
+        rng1: &mut R1,
+        rng2: &mut R2,
+        c_e_q: &<PedersenCommitment<P> as Commitment>::Instance,
+        witness: &Witness<G>,
+        c_e: <IntegerCommitment<G> as Commitment>::Instance,
+        root_witness: RootWitness<G>,
+        history: &mut BlindingHistory,
+        shared: Option<&mut SharedSubProofs<P, HP>>,
+    ) -> Result<(), ProofError> {
+        let RootWitness { e: hashed_e, r, .. } = root_witness;
+        let modeq = ModEqProtocol::from_crs(&self.crs.crs_modeq);
+        modeq.prove_with_history(
+            verifier_channel,
+            rng1,
+            rng2,
+            &ModEqStatement {
+                c_e,
+                c_e_q: c_e_q.clone(),
+            },
+            &ModEqWitness {
+                e: hashed_e,
+                r,
+                r_q: witness.r_q.clone(),
+            },
+            history,
+        )?;
+        let hash_to_prime = HashToPrimeProtocol::from_crs(&self.crs.crs_hash_to_prime);
+        let hash_to_prime_statement = HashToPrimeStatement {
+            c_e_q: c_e_q.clone(),
+        };
+        let hash_to_prime_witness = HashToPrimeWitness {
+            e: witness.e.clone(),
+            r_q: witness.r_q.clone(),
+        };
+        match shared {
+            Some(shared) => shared.prove(
+                &hash_to_prime,
+                verifier_channel,
+                rng2,
+                &hash_to_prime_statement,
+                &hash_to_prime_witness,
+            )?,
+            None => hash_to_prime.prove(
+                verifier_channel,
+                rng2,
+                &hash_to_prime_statement,
+                &hash_to_prime_witness,
+            )?,
+        }
+
+        Ok(())
+    }
+}
+
+impl<G: ConvertibleUnknownOrderGroup, P: CurvePointProjective, HP: HashToPrimeProtocol<P>>
+    Protocol<G, P, HP>
+{
+    /// Runs every sub-check whatever the first failing one is, so that a
+    /// remote verifier doesn't reveal through timing which one failed. Only
+    /// channel errors and malformed CRSs end verification early. The integer
+    /// and curve arithmetic aren't constant-time, which is fine for public
+    /// values.
+    pub fn verify<
+        C: MembershipProverChannel<G>
+            + RootProverChannel<G>
+            + ModEqProverChannel<G, P>
+            + HashToPrimeProverChannel<P, HP>,
+    >(
+        &self,
+        prover_channel: &mut C,
+        statement: &Statement<G, P>,
+    ) -> Result<(), VerificationError> {
+        let (sigma_result, partially_verified) =
+            self.preverify_deferred(prover_channel, statement)?;
+        let hash_to_prime_result = self.finalize(partially_verified);
+        sigma_result.and(hash_to_prime_result)
+    }
+
+    /// Runs the verification of the sigma protocols, which is cheap, and
+    /// receives the hash-to-prime proof without verifying it, so that
+    /// `finalize` can be called later, e.g. once a batch was pre-screened.
+    pub fn preverify<
+        C: MembershipProverChannel<G>
+            + RootProverChannel<G>
+            + ModEqProverChannel<G, P>
+            + HashToPrimeProverChannel<P, HP>,
+    >(
+        &self,
+        prover_channel: &mut C,
+        statement: &Statement<G, P>,
+    ) -> Result<PartiallyVerified<P, HP>, VerificationError> {
+        let (sigma_result, partially_verified) =
+            self.preverify_deferred(prover_channel, statement)?;
+        sigma_result.map(|_| partially_verified)
+    }
+
+    /// Runs the checks of both sigma protocols even if the first one fails,
+    /// returning their result along with the received hash-to-prime proof.
+    /// Only channel errors, which don't depend on the values in the proof,
+    /// are returned early.
+    #[allow(clippy::type_complexity)]
+    fn preverify_deferred<
+        C: MembershipProverChannel<G>
+            + RootProverChannel<G>
+            + ModEqProverChannel<G, P>
+            + HashToPrimeProverChannel<P, HP>,
+    >(
+        &self,
+        prover_channel: &mut C,
+        statement: &Statement<G, P>,
+    ) -> Result<(Result<(), VerificationError>, PartiallyVerified<P, HP>), VerificationError> {
+        let c_e = self.receive_c_e(prover_channel)?;
+        let root = RootProtocol::from_crs(&self.crs.crs_root)?;
+        let root_result = root.verify(
+            prover_channel,
+            &RootStatement {
+                c_e: c_e.clone(),
+                acc: statement.c_p.clone(),
+            },
+        );
+        let (modeq_result, partially_verified) =
+            self.preverify_modeq(prover_channel, c_e, &statement.c_e_q)?;
+        Ok((root_result.and(modeq_result), partially_verified))
+    }
+
+    /// Verifies the modeq proof and receives the hash-to-prime proof,
+    /// returning the result of the modeq checks separately.
+    #[allow(clippy::type_complexity)]
+    fn preverify_modeq<C: ModEqProverChannel<G, P> + HashToPrimeProverChannel<P, HP>>(
+        &self,
+        prover_channel: &mut C,
+        c_e: <IntegerCommitment<G> as Commitment>::Instance,
+        c_e_q: &<PedersenCommitment<P> as Commitment>::Instance,
+    ) -> Result<(Result<(), VerificationError>, PartiallyVerified<P, HP>), VerificationError> {
+        let modeq = ModEqProtocol::from_crs(&self.crs.crs_modeq);
+        let modeq_result = modeq.verify(
+            prover_channel,
+            &ModEqStatement {
+                c_e,
+                c_e_q: c_e_q.clone(),
+            },
+        );
+        let proof_hash_to_prime = prover_channel.receive_proof()?;
+
+        Ok((
+            modeq_result,
+            PartiallyVerified::new(
+                HashToPrimeStatement {
+                    c_e_q: c_e_q.clone(),
+                },
+                proof_hash_to_prime,
+            ),
+        ))
+    }
+
+    /// Verifies only the sub-proofs in `checked`, e.g. when another system
+    /// already verified the others. The other sub-proofs are still received,
+    /// in the same order as by `verify`, so that the challenges of the checked
+    /// ones are derived from the same transcript as in a full verification,
+    /// but nothing about them is checked.
+    pub fn verify_partially<
+        C: MembershipProverChannel<G>
+            + RootProverChannel<G>
+            + ModEqProverChannel<G, P>
+            + HashToPrimeProverChannel<P, HP>,
+    >(
+        &self,
+        prover_channel: &mut C,
+        statement: &Statement<G, P>,
+        checked: &[SubProtocol],
+    ) -> Result<(), VerificationError> {
+        let c_e = self.receive_c_e(prover_channel)?;
+        let root = RootProtocol::from_crs(&self.crs.crs_root)?;
+        let root_statement = RootStatement {
+            c_e: c_e.clone(),
+            acc: statement.c_p.clone(),
+        };
+        if checked.contains(&SubProtocol::Root) {
+            root.verify(prover_channel, &root_statement)?;
+        } else {
+            root.receive_unverified(prover_channel)?;
+        }
+        let modeq = ModEqProtocol::from_crs(&self.crs.crs_modeq);
+        let modeq_statement = ModEqStatement {
+            c_e,
+            c_e_q: statement.c_e_q.clone(),
+        };
+        if checked.contains(&SubProtocol::ModEq) {
+            modeq.verify(prover_channel, &modeq_statement)?;
+        } else {
+            modeq.receive_unverified(prover_channel)?;
+        }
+        let proof_hash_to_prime = prover_channel.receive_proof()?;
+        if checked.contains(&SubProtocol::HashToPrime) {
+            self.finalize(PartiallyVerified::new(
+                HashToPrimeStatement {
+                    c_e_q: statement.c_e_q.clone(),
+                },
+                proof_hash_to_prime,
+            ))?;
+        }
+
+        Ok(())
+    }
+
+    /// Verifies the hash-to-prime proof of a pre-verified proof.
+    pub fn finalize(
+        &self,
+        partially_verified: PartiallyVerified<P, HP>,
+    ) -> Result<(), VerificationError> {
+        let hash_to_prime = HashToPrimeProtocol::from_crs(&self.crs.crs_hash_to_prime);
+        partially_verified.finalize(&hash_to_prime)
+    }
+
+    /// Verifies the hash-to-prime proofs of several pre-verified proofs in a
+    /// batch, which is faster than finalizing them one by one for SNARKs.
+    pub fn finalize_batch<R: RngCore + CryptoRng>(
+        &self,
+        rng: &mut R,
+        partially_verified: Vec<PartiallyVerified<P, HP>>,
+    ) -> Result<(), VerificationError> {
+        let hash_to_prime = HashToPrimeProtocol::from_crs(&self.crs.crs_hash_to_prime);
+        PartiallyVerified::finalize_batch(partially_verified, &hash_to_prime, rng)
+    }
+
+    /// Restores the alphas of the sub-protocols in a compressed proof, so it
+    /// can be verified as a regular proof.
+    #[cfg(feature = "compressed")]
+    pub fn decompress(
+        &self,
+        statement: &Statement<G, P>,
+        proof: &CompressedProof<G, P, HP>,
+    ) -> Result<Proof<G, P, HP>, VerificationError> {
+        let root = RootProtocol::from_crs(&self.crs.crs_root)?;
+        let proof_root = root.decompress(
+            &RootStatement {
+                c_e: proof.c_e.clone(),
+                acc: statement.c_p.clone(),
+            },
+            &proof.proof_root,
+        )?;
+        let modeq = ModEqProtocol::from_crs(&self.crs.crs_modeq);
+        let proof_modeq = modeq.decompress(
+            &ModEqStatement {
+                c_e: proof.c_e.clone(),
+                c_e_q: statement.c_e_q.clone(),
+            },
+            &proof.proof_modeq,
+        )?;
+
+        Ok(Proof {
+            crs_digest: proof.crs_digest,
+            c_e: proof.c_e.clone(),
+            proof_root,
+            proof_modeq,
+            proof_hash_to_prime: proof.proof_hash_to_prime.clone(),
+        })
+    }
+
+    /// Computes the key under which a successful verification of `proof` for
+    /// `statement` can be cached. Freshness data bound to the verification,
+    /// such as a nonce added to the transcript, has to be appended to `key`
+    /// by the caller beforehand.
+    pub fn cache_key(
+        &self,
+        mut key: CacheKeyBuilder,
+        statement: &Statement<G, P>,
+        proof: &Proof<G, P, HP>,
+    ) -> Result<CacheKey, VerificationError> {
+        key.append_bytes(b"protocol", b"membership");
+        // The verifier's own digest binds the parameters and the
+        // hash-to-prime verifying key besides the commitment bases, so CRSs
+        // sharing bases don't share entries.
+        key.append_bytes(b"crs_digest", &self.crs_digest()?);
+        key.append_group_element::<G>(b"c_p", &statement.c_p);
+        key.append_curve_point(b"c_e_q", &statement.c_e_q)?;
+        key.append_group_element::<G>(b"c_e", &proof.c_e);
+        proof.proof_root.append_to_cache_key(&mut key);
+        proof.proof_modeq.append_to_cache_key(&mut key)?;
+        key.append_bytes(
+            b"proof_hash_to_prime",
+            &HP::proof_to_bytes(&proof.proof_hash_to_prime)?,
+        );
+        Ok(key.finish())
+    }
+
+    pub fn hash_to_prime(&self, e: &Integer) -> Result<(Integer, u64), HashToPrimeError> {
+        let hash_to_prime = HashToPrimeProtocol::from_crs(&self.crs.crs_hash_to_prime);
+        hash_to_prime.hash_to_prime(e)
+    }
+
+    pub fn hash_to_prime_with_observer<O: ProgressObserver>(
+        &self,
+        e: &Integer,
+        observer: &O,
+    ) -> Result<(Integer, u64), HashToPrimeError> {
+        let hash_to_prime = HP::from_crs(&self.crs.crs_hash_to_prime);
+        hash_to_prime.hash_to_prime_with_observer(e, observer)
+    }
+
+    pub fn from_crs(crs: &CRS<G, P, HP>) -> Protocol<G, P, HP> {
+        Self::with_crs(crs.clone())
+    }
+
+    fn with_crs(crs: CRS<G, P, HP>) -> Protocol<G, P, HP> {
+        Protocol { crs }
+    }
+
+    /// The digest of `crs`, which every proof carries. It's computed on each
+    /// call, so it always matches the current `crs`.
+    pub fn crs_digest(&self) -> Result<CrsDigest, CurveError> {
+        self.crs.digest()
+    }
+
+    /// Receives the digest of the CRS the proof was created under, which has
+    /// to be the one of this protocol, and then `c_e`.
+    fn receive_c_e<C: MembershipProverChannel<G>>(
+        &self,
+        prover_channel: &mut C,
+    ) -> Result<<IntegerCommitment<G> as Commitment>::Instance, VerificationError> {
+        if prover_channel.receive_crs_digest()? != self.crs_digest()? {
+            return Err(VerificationError::CrsMismatch);
+        }
+        Ok(prover_channel.receive_c_e()?)
+    }
+
+    /// The maximum size of the elements `prove` accepts, larger ones are
+    /// rejected with `ProofError::ElementTooLarge`.
+    pub fn max_element_bits(&self) -> u16 {
+        HP::max_element_bits(&self.crs.parameters)
+    }
+}
+
+#[cfg(all(test, feature = "arkworks"))]
+mod test {
+    use super::{Protocol, Statement, Witness};
+    use crate::{
+        commitments::{integer::IntegerCommitmentOpening, Commitment, CommitmentError},
+        parameters::{Parameters, SubProtocol},
+        protocols::hash_to_prime::snark_range::Protocol as HPProtocol,
+        protocols::{
+            cache::{CacheKeyBuilder, VerifierCache},
+            hash_to_prime::{
+                shared::SharedSubProofs,
+                snark_hash::{HashToPrimeHashParameters, Protocol as HPHashProtocol},
+                HashToPrimeProtocol,
+            },
+            membership::transcript::{TranscriptProverChannel, TranscriptVerifierChannel},
+            progress::CancellationToken,
+            registry::{CrsRegistry, VersionedProof},
+            ProofError, SetupError, VerificationError,
+        },
+        transcript::PROTOCOL_VERSION,
+        utils::{
+            curve::{CurvePointProjective, Field},
+            trapdoor::TrapdoorRsa512,
+        },
+    };
+    use accumulator::group::{ClassGroup, Rsa2048};
+    use accumulator::{group::Group, AccumulatorWithoutHashToPrime};
+    use ark_bls12_381::{Bls12_381, G1Projective};
+    use merlin::Transcript;
+    use rand::{rngs::StdRng, thread_rng, SeedableRng};
+    use rug::rand::RandState;
+    use rug::Integer;
+    use std::cell::RefCell;
+
+    const LARGE_PRIMES: [u64; 4] = [
+        553_525_575_239_331_913,
+        12_702_637_924_034_044_211,
+        378_373_571_372_703_133,
+        8_640_171_141_336_142_787,
+    ];
+
+    #[test]
+    fn test_setup_cancelled() {
+        let params = Parameters::from_security_level(128).unwrap();
+        let mut rng1 = RandState::new();
+        rng1.seed(&Integer::from(13));
+        let mut rng2 = thread_rng();
+
+        let token = CancellationToken::new();
+        token.cancel();
+        let result = Protocol::<Rsa2048, G1Projective, HPProtocol<Bls12_381>>::setup_with_observer(
+            &params, &mut rng1, &mut rng2, &token,
+        );
+        assert!(matches!(result, Err(SetupError::Cancelled)));
+    }
+
+    #[test]
+    fn test_verify_partially() {
+        let params = Parameters::from_security_level(128).unwrap();
+        let mut rng1 = RandState::new();
+        rng1.seed(&Integer::from(13));
+        let mut rng2 = thread_rng();
+
+        let crs = Protocol::<Rsa2048, G1Projective, HPProtocol<Bls12_381>>::setup(
+            &params, &mut rng1, &mut rng2,
+        )
+        .unwrap()
+        .crs;
+        let protocol = Protocol::<Rsa2048, G1Projective, HPProtocol<Bls12_381>>::from_crs(&crs);
+
+        let value = Integer::from(Integer::u_pow_u(2, params.hash_to_prime_bits as u32))
+            - &Integer::from(245);
+        let randomness = Integer::from(5);
+        let accum =
+            accumulator::Accumulator::<Rsa2048, Integer, AccumulatorWithoutHashToPrime>::empty()
+                .add_with_proof(&[value.clone()]);
+        let statement = Statement {
+            c_p: accum.0.value,
+            c_e_q: crs
+                .crs_modeq
+                .pedersen_commitment_parameters
+                .commit(&value, &randomness)
+                .unwrap(),
+        };
+        let proof_transcript = RefCell::new(Transcript::new(b"membership"));
+        let mut verifier_channel = TranscriptVerifierChannel::new(&crs, &proof_transcript);
+        protocol
+            .prove(
+                &mut verifier_channel,
+                &mut rng1,
+                &mut rng2,
+                &statement,
+                &Witness {
+                    e: value,
+                    r_q: randomness,
+                    w: accum.1.witness.0.value,
+                },
+            )
+            .unwrap();
+        let proof = verifier_channel.proof().unwrap();
+        assert!(proof.modeq_statement(&statement).c_e == proof.c_e);
+        assert!(proof.root_statement(&statement).acc == statement.c_p);
+
+        let verify_partially = |proof, checked: &[SubProtocol]| {
+            let verification_transcript = RefCell::new(Transcript::new(b"membership"));
+            let mut prover_channel =
+                TranscriptProverChannel::new(&crs, &verification_transcript, proof);
+            protocol.verify_partially(&mut prover_channel, &statement, checked)
+        };
+        let all = [
+            SubProtocol::Root,
+            SubProtocol::ModEq,
+            SubProtocol::HashToPrime,
+        ];
+        verify_partially(&proof, &all).unwrap();
+
+        // the root proof is skipped, but still determines the modeq challenge
+        let mut wrong_root = proof.clone();
+        wrong_root.proof_root.message3.s_e += 1;
+        assert!(verify_partially(&wrong_root, &all).is_err());
+        verify_partially(&wrong_root, &[SubProtocol::ModEq, SubProtocol::HashToPrime]).unwrap();
+        let mut reordered_root = proof.clone();
+        reordered_root.proof_root.message2.alpha1 = proof.proof_root.message2.alpha2.clone();
+        assert!(verify_partially(&reordered_root, &[SubProtocol::ModEq]).is_err());
+
+        let mut wrong_modeq = proof;
+        wrong_modeq.proof_modeq.message2.s_e += 1;
+        assert!(verify_partially(&wrong_modeq, &[SubProtocol::ModEq]).is_err());
+        verify_partially(&wrong_modeq, &[SubProtocol::Root, SubProtocol::HashToPrime]).unwrap();
+    }
+
+    #[test]
+    fn test_witness_out_of_range() {
+        let params = Parameters::from_security_level(128).unwrap();
+        let mut rng1 = RandState::new();
+        rng1.seed(&Integer::from(13));
+        let mut rng2 = thread_rng();
+
+        let crs = Protocol::<Rsa2048, G1Projective, HPProtocol<Bls12_381>>::setup(
+            &params, &mut rng1, &mut rng2,
+        )
+        .unwrap()
+        .crs;
+        let protocol = Protocol::<Rsa2048, G1Projective, HPProtocol<Bls12_381>>::from_crs(&crs);
+
+        let modulus = <G1Projective as CurvePointProjective>::ScalarField::modulus();
+        let mut prove = |value: &Integer, randomness: &Integer| {
+            let accum =
+                accumulator::Accumulator::<Rsa2048, Integer, AccumulatorWithoutHashToPrime>::empty(
+                )
+                .add_with_proof(&[value.clone()]);
+            let statement = Statement {
+                c_p: accum.0.value,
+                // reduced, so that an unreduced witness reaches the prover
+                c_e_q: crs
+                    .crs_modeq
+                    .pedersen_commitment_parameters
+                    .commit(value, &Integer::from(randomness % &modulus))
+                    .unwrap(),
+            };
+            let proof_transcript = RefCell::new(Transcript::new(b"membership"));
+            let mut verifier_channel = TranscriptVerifierChannel::new(&crs, &proof_transcript);
+            protocol.prove(
+                &mut verifier_channel,
+                &mut rng1,
+                &mut rng2,
+                &statement,
+                &Witness {
+                    e: value.clone(),
+                    r_q: randomness.clone(),
+                    w: accum.1.witness.0.value,
+                },
+            )
+        };
+
+        let value = Integer::from(Integer::u_pow_u(2, params.hash_to_prime_bits as u32))
+            - &Integer::from(245);
+        prove(&value, &Integer::from(5)).unwrap();
+        // the range-only protocol would accept to prove a larger prime
+        let large_value =
+            Integer::from(Integer::u_pow_u(2, params.hash_to_prime_bits as u32)).next_prime();
+        assert_eq!(protocol.max_element_bits(), params.hash_to_prime_bits);
+        assert!(matches!(
+            prove(&large_value, &Integer::from(5)),
+            Err(ProofError::ElementTooLarge(bits, max_bits))
+                if bits == params.hash_to_prime_bits as u32 + 1 && max_bits == params.hash_to_prime_bits
+        ));
+        assert!(matches!(
+            prove(&value, &modulus),
+            Err(ProofError::RandomnessNotReduced)
+        ));
+    }
+
+    #[test]
+    fn test_prove_and_check() {
+        let params = Parameters::from_security_level(128).unwrap();
+        let mut rng1 = RandState::new();
+        rng1.seed(&Integer::from(13));
+        let mut rng2 = thread_rng();
+
+        let crs = Protocol::<Rsa2048, G1Projective, HPProtocol<Bls12_381>>::setup(
+            &params, &mut rng1, &mut rng2,
+        )
+        .unwrap()
+        .crs;
+        let protocol = Protocol::<Rsa2048, G1Projective, HPProtocol<Bls12_381>>::from_crs(&crs);
+
+        let value = Integer::from(Integer::u_pow_u(2, params.hash_to_prime_bits as u32))
+            - &Integer::from(245);
+        let randomness = Integer::from(5);
+        let accum =
+            accumulator::Accumulator::<Rsa2048, Integer, AccumulatorWithoutHashToPrime>::empty()
+                .add_with_proof(&[value.clone()]);
+        let statement = Statement {
+            c_p: accum.0.value,
+            c_e_q: crs
+                .crs_modeq
+                .pedersen_commitment_parameters
+                .commit(&value, &randomness)
+                .unwrap(),
+        };
+        let witness = Witness {
+            e: value,
+            r_q: randomness,
+            w: accum.1.witness.0.value,
+        };
+
+        let proof_transcript = RefCell::new(Transcript::new(b"membership"));
+        let proof = protocol
+            .prove_and_check(
+                &proof_transcript,
+                &mut rng1,
+                &mut rng2,
+                &statement,
+                &witness,
+            )
+            .unwrap();
+        let verification_transcript = RefCell::new(Transcript::new(b"membership"));
+        let mut prover_channel =
+            TranscriptProverChannel::new(&crs, &verification_transcript, &proof);
+        protocol.verify(&mut prover_channel, &statement).unwrap();
+
+        // a witness for another accumulator is caught before sending the proof
+        let mut wrong_statement = statement;
+        wrong_statement.c_p = Rsa2048::op(&wrong_statement.c_p, &wrong_statement.c_p);
+        let proof_transcript = RefCell::new(Transcript::new(b"membership"));
+        assert!(matches!(
+            protocol.prove_and_check(
+                &proof_transcript,
+                &mut rng1,
+                &mut rng2,
+                &wrong_statement,
+                &witness,
+            ),
+            Err(ProofError::SelfCheckFailed(_))
+        ));
+    }
+
+    #[test]
+    fn test_prove_with_integer_commitment() {
+        let params = Parameters::from_security_level(128).unwrap();
+        let mut rng1 = RandState::new();
+        rng1.seed(&Integer::from(13));
+        let mut rng2 = thread_rng();
+
+        let crs = Protocol::<Rsa2048, G1Projective, HPProtocol<Bls12_381>>::setup(
+            &params, &mut rng1, &mut rng2,
+        )
+        .unwrap()
+        .crs;
+        let protocol = Protocol::<Rsa2048, G1Projective, HPProtocol<Bls12_381>>::from_crs(&crs);
+
+        let value = Integer::from(Integer::u_pow_u(2, params.hash_to_prime_bits as u32))
+            - &Integer::from(245);
+        let randomness = Integer::from(5);
+        let accum =
+            accumulator::Accumulator::<Rsa2048, Integer, AccumulatorWithoutHashToPrime>::empty()
+                .add_with_proof(&[value.clone()]);
+        let statement = Statement {
+            c_p: accum.0.value,
+            c_e_q: crs
+                .crs_modeq
+                .pedersen_commitment_parameters
+                .commit(&value, &randomness)
+                .unwrap(),
+        };
+        let witness = Witness {
+            e: value.clone(),
+            r_q: randomness,
+            w: accum.1.witness.0.value,
+        };
+        // registered at issuance
+        let integer_commitment_parameters = &crs.crs_root.integer_commitment_parameters;
+        let r = Integer::from(12_345);
+        let opening = IntegerCommitmentOpening {
+            commitment: integer_commitment_parameters.commit(&value, &r).unwrap(),
+            randomness: r,
+        };
+
+        let proof_transcript = RefCell::new(Transcript::new(b"membership"));
+        let mut verifier_channel = TranscriptVerifierChannel::new(&crs, &proof_transcript);
+        protocol
+            .prove_with_integer_commitment(
+                &mut verifier_channel,
+                &mut rng1,
+                &mut rng2,
+                &statement,
+                &witness,
+                &opening,
+            )
+            .unwrap();
+        let proof = verifier_channel.proof().unwrap();
+        assert_eq!(proof.c_e, opening.commitment);
+        let verification_transcript = RefCell::new(Transcript::new(b"membership"));
+        let mut prover_channel =
+            TranscriptProverChannel::new(&crs, &verification_transcript, &proof);
+        protocol.verify(&mut prover_channel, &statement).unwrap();
+
+        // the commitment has to open to the element
+        let wrong_opening = IntegerCommitmentOpening {
+            randomness: Integer::from(54_321),
+            ..opening
+        };
+        let proof_transcript = RefCell::new(Transcript::new(b"membership"));
+        let mut verifier_channel = TranscriptVerifierChannel::new(&crs, &proof_transcript);
+        assert!(matches!(
+            protocol.prove_with_integer_commitment(
+                &mut verifier_channel,
+                &mut rng1,
+                &mut rng2,
+                &statement,
+                &witness,
+                &wrong_opening,
+            ),
+            Err(ProofError::CommitmentError(CommitmentError::WrongOpening))
+        ));
+    }
+
+    #[test]
+    fn test_e2e_prime_rsa() {
+        let params = Parameters::from_security_level(128).unwrap();
+        let mut rng1 = RandState::new();
+        rng1.seed(&Integer::from(13));
+        let mut rng2 = thread_rng();
+
+        let crs = crate::protocols::membership::Protocol::<
+            Rsa2048,
+            G1Projective,
+            HPProtocol<Bls12_381>,
+        >::setup(&params, &mut rng1, &mut rng2)
+        .unwrap()
+        .crs;
+        let protocol = Protocol::<Rsa2048, G1Projective, HPProtocol<Bls12_381>>::from_crs(&crs);
+
+        let value = Integer::from(Integer::u_pow_u(
+            2,
+            (crs.parameters.hash_to_prime_bits) as u32,
+        )) - &Integer::from(245);
+        let randomness = Integer::from(5);
+        let commitment = protocol
+            .crs
+            .crs_modeq
+            .pedersen_commitment_parameters
+            .commit(&value, &randomness)
+            .unwrap();
+
+        let accum =
+            accumulator::Accumulator::<Rsa2048, Integer, AccumulatorWithoutHashToPrime>::empty();
+        let accum = accum.add(
+            &LARGE_PRIMES
+                .iter()
+                .skip(1)
+                .map(|p| Integer::from(*p))
+                .collect::<Vec<_>>(),
+        );
+
+        let accum = accum.add_with_proof(&[value.clone()]);
+        let acc = accum.0.value;
+        let w = accum.1.witness.0.value;
+        assert_eq!(Rsa2048::exp(&w, &value), acc);
+
+        let proof_transcript = RefCell::new(Transcript::new(b"membership"));
+        let mut verifier_channel = TranscriptVerifierChannel::new(&crs, &proof_transcript);
+        let statement = Statement {
+            c_e_q: commitment,
+            c_p: acc,
+        };
+        protocol
+            .prove(
+                &mut verifier_channel,
+                &mut rng1,
+                &mut rng2,
+                &statement,
+                &Witness {
+                    e: value,
+                    r_q: randomness,
+                    w,
+                },
+            )
+            .unwrap();
+        let proof = verifier_channel.proof().unwrap();
+        let verification_transcript = RefCell::new(Transcript::new(b"membership"));
+        let mut prover_channel =
+            TranscriptProverChannel::new(&crs, &verification_transcript, &proof);
+        protocol.verify(&mut prover_channel, &statement).unwrap();
+
+        let mut cache = VerifierCache::new(16, 1);
+        let key = protocol
+            .cache_key(CacheKeyBuilder::new(1), &statement, &proof)
+            .unwrap();
+        for i in 0..2 {
+            let mut verified = false;
+            cache
+                .verify(&key, || {
+                    verified = true;
+                    let verification_transcript = RefCell::new(Transcript::new(b"membership"));
+                    let mut prover_channel =
+                        TranscriptProverChannel::new(&crs, &verification_transcript, &proof);
+                    protocol.verify(&mut prover_channel, &statement)
+                })
+                .unwrap();
+            // only the first presentation is verified
+            assert_eq!(verified, i == 0);
+        }
 
-pub mod channel;
-pub mod transcript;
+        cache.advance_epoch(2);
+        assert!(cache.is_empty());
+        assert!(matches!(
+            cache.verify(&key, || Ok(())),
+            Err(VerificationError::StaleEpoch)
+        ));
 
-pub struct CRS<G: ConvertibleUnknownOrderGroup, P: CurvePointProjective, HP: HashToPrimeProtocol<P>>
-{
-    // G contains the information about Z^*_N
-    pub parameters: Parameters,
-    pub crs_root: CRSRoot<G>,
-    pub crs_modeq: CRSModEq<G, P>,
-    pub crs_hash_to_prime: CRSHashToPrime<P, HP>,
-}
+        let new_crs = crate::protocols::membership::Protocol::<
+            Rsa2048,
+            G1Projective,
+            HPProtocol<Bls12_381>,
+        >::setup(&params, &mut rng1, &mut rng2)
+        .unwrap()
+        .crs;
+        // a CRS sharing the commitment bases, as augmentation produces, must
+        // not hit the entries verified under the old verifying key
+        let mut shared_bases_crs = crs.clone();
+        shared_bases_crs.crs_hash_to_prime.hash_to_prime_parameters =
+            new_crs.crs_hash_to_prime.hash_to_prime_parameters.clone();
+        let shared_bases_protocol =
+            Protocol::<Rsa2048, G1Projective, HPProtocol<Bls12_381>>::from_crs(&shared_bases_crs);
+        let key = protocol
+            .cache_key(CacheKeyBuilder::new(2), &statement, &proof)
+            .unwrap();
+        let shared_bases_key = shared_bases_protocol
+            .cache_key(CacheKeyBuilder::new(2), &statement, &proof)
+            .unwrap();
+        assert_ne!(key, shared_bases_key);
+        cache.verify(&key, || Ok(())).unwrap();
+        assert!(cache
+            .verify(&shared_bases_key, || {
+                let verification_transcript = RefCell::new(Transcript::new(b"membership"));
+                let mut prover_channel = TranscriptProverChannel::new(
+                    &shared_bases_crs,
+                    &verification_transcript,
+                    &proof,
+                );
+                shared_bases_protocol.verify(&mut prover_channel, &statement)
+            })
+            .is_err());
+        assert_eq!(cache.len(), 1);
 
-impl<G: ConvertibleUnknownOrderGroup, P: CurvePointProjective, HP: HashToPrimeProtocol<P>> Clone
-    for CRS<G, P, HP>
-{
-    fn clone(&self) -> Self {
-        Self {
-            parameters: self.parameters.clone(),
-            crs_root: self.crs_root.clone(),
-            crs_modeq: self.crs_modeq.clone(),
-            crs_hash_to_prime: self.crs_hash_to_prime.clone(),
+        let mut registry =
+            CrsRegistry::<Protocol<Rsa2048, G1Projective, HPProtocol<Bls12_381>>>::new(
+                b"membership",
+            );
+        let digest = registry.register(Protocol::from_crs(&crs)).unwrap();
+        let new_digest = registry.register(Protocol::from_crs(&new_crs)).unwrap();
+        assert_ne!(digest, new_digest);
+        assert_eq!(registry.len(), 2);
+        assert_eq!(proof.crs_digest, digest);
+        // the digest binds the verifying key, not only the commitment bases
+        let mut mixed_crs = crs.clone();
+        mixed_crs.crs_hash_to_prime.hash_to_prime_parameters =
+            new_crs.crs_hash_to_prime.hash_to_prime_parameters.clone();
+        assert_ne!(mixed_crs.digest().unwrap(), digest);
+
+        let mut versioned_proof = VersionedProof::new(digest, proof);
+        registry
+            .verify_any(&statement, &versioned_proof, &[])
+            .unwrap();
+        // older and unknown versions are rejected before verifying
+        for version in &[0, PROTOCOL_VERSION + 1] {
+            let mut downgraded_proof = versioned_proof.clone();
+            downgraded_proof.protocol_version = *version;
+            assert!(matches!(
+                registry.verify_any(&statement, &downgraded_proof, &[]),
+                Err(VerificationError::UnsupportedVersion)
+            ));
         }
+        versioned_proof.crs_digest = new_digest;
+        assert!(matches!(
+            registry.verify_any(&statement, &versioned_proof, &[]),
+            Err(VerificationError::CrsMismatch)
+        ));
+        registry.remove(&digest);
+        versioned_proof.crs_digest = digest;
+        assert!(matches!(
+            registry.verify_any(&statement, &versioned_proof, &[]),
+            Err(VerificationError::UnknownCrs)
+        ));
     }
-}
 
-pub struct Protocol<
-    G: ConvertibleUnknownOrderGroup,
-    P: CurvePointProjective,
-    HP: HashToPrimeProtocol<P>,
-> {
-    pub crs: CRS<G, P, HP>,
-}
+    #[test]
+    fn test_shared_sub_proofs() {
+        let params = Parameters::from_security_level(128).unwrap();
+        let mut rng1 = RandState::new();
+        rng1.seed(&Integer::from(13));
+        let mut rng2 = thread_rng();
 
-pub struct Statement<G: ConvertibleUnknownOrderGroup, P: CurvePointProjective> {
-    pub c_p: G::Elem,
-    pub c_e_q: <PedersenCommitment<P> as Commitment>::Instance,
-}
+        let crs = Protocol::<Rsa2048, G1Projective, HPProtocol<Bls12_381>>::setup(
+            &params, &mut rng1, &mut rng2,
+        )
+        .unwrap()
+        .crs;
+        let protocol = Protocol::<Rsa2048, G1Projective, HPProtocol<Bls12_381>>::from_crs(&crs);
 
-pub struct Witness<G: ConvertibleUnknownOrderGroup> {
-    pub e: Integer,
-    pub r_q: Integer,
-    pub w: G::Elem,
-}
+        let value = Integer::from(Integer::u_pow_u(2, params.hash_to_prime_bits as u32))
+            - &Integer::from(245);
+        let randomness = Integer::from(5);
+        let commitment = protocol
+            .crs
+            .crs_modeq
+            .pedersen_commitment_parameters
+            .commit(&value, &randomness)
+            .unwrap();
 
-pub struct Proof<
-    G: ConvertibleUnknownOrderGroup,
-    P: CurvePointProjective,
-    HP: HashToPrimeProtocol<P>,
-> {
-    pub c_e: <IntegerCommitment<G> as Commitment>::Instance,
-    pub proof_root: RootProof<G>,
-    pub proof_modeq: ModEqProof<G, P>,
-    pub proof_hash_to_prime: HP::Proof,
-}
+        let mut shared = SharedSubProofs::new();
+        let mut proofs = vec![];
+        // membership of the same element in two accumulators
+        for primes in &[&LARGE_PRIMES[1..2], &LARGE_PRIMES[2..]] {
+            let accum =
+                accumulator::Accumulator::<Rsa2048, Integer, AccumulatorWithoutHashToPrime>::empty(
+                )
+                .add(&primes.iter().map(|p| Integer::from(*p)).collect::<Vec<_>>())
+                .add_with_proof(&[value.clone()]);
+            let statement = Statement {
+                c_e_q: commitment,
+                c_p: accum.0.value,
+            };
+            let proof_transcript = RefCell::new(Transcript::new(b"membership"));
+            let mut verifier_channel = TranscriptVerifierChannel::new(&crs, &proof_transcript);
+            protocol
+                .prove_with_shared_sub_proofs(
+                    &mut verifier_channel,
+                    &mut rng1,
+                    &mut rng2,
+                    &statement,
+                    &Witness {
+                        e: value.clone(),
+                        r_q: randomness.clone(),
+                        w: accum.1.witness.0.value,
+                    },
+                    &mut shared,
+                )
+                .unwrap();
+            let proof = verifier_channel.proof().unwrap();
 
-impl<G: ConvertibleUnknownOrderGroup, P: CurvePointProjective, HP: HashToPrimeProtocol<P>> Clone
-    for Proof<G, P, HP>
-{
-    fn clone(&self) -> Self {
-        Self {
-            c_e: self.c_e.clone(),
-            proof_root: self.proof_root.clone(),
-            proof_modeq: self.proof_modeq.clone(),
-            proof_hash_to_prime: self.proof_hash_to_prime.clone(),
+            let verification_transcript = RefCell::new(Transcript::new(b"membership"));
+            let mut prover_channel =
+                TranscriptProverChannel::new(&crs, &verification_transcript, &proof);
+            protocol.verify(&mut prover_channel, &statement).unwrap();
+            proofs.push(proof);
         }
-    }
-}
 
-impl<G: ConvertibleUnknownOrderGroup, P: CurvePointProjective, HP: HashToPrimeProtocol<P>>
-    Protocol<G, P, HP>
-{
-    pub fn setup<R1: MutRandState, R2: RngCore + CryptoRng>(
-        parameters: &Parameters,
-        rng1: &mut R1,
-        rng2: &mut R2,
-    ) -> Result<Protocol<G, P, HP>, SetupError> {
-        let integer_commitment_parameters = IntegerCommitment::<G>::setup(rng1);
-        let pedersen_commitment_parameters = PedersenCommitment::<P>::setup(rng2);
-        let hash_to_prime_parameters =
-            HP::setup(rng2, &pedersen_commitment_parameters, parameters)?;
-        Ok(Protocol {
-            crs: CRS::<G, P, HP> {
-                parameters: parameters.clone(),
-                crs_modeq: CRSModEq::<G, P> {
-                    parameters: parameters.clone(),
-                    integer_commitment_parameters: integer_commitment_parameters.clone(),
-                    pedersen_commitment_parameters: pedersen_commitment_parameters.clone(),
-                },
-                crs_root: CRSRoot::<G> {
-                    parameters: parameters.clone(),
-                    integer_commitment_parameters,
-                },
-                crs_hash_to_prime: CRSHashToPrime::<P, HP> {
-                    parameters: parameters.clone(),
-                    pedersen_commitment_parameters,
-                    hash_to_prime_parameters,
-                },
-            },
-        })
+        assert_eq!(shared.len(), 1);
+        assert_eq!(
+            HPProtocol::<Bls12_381>::proof_to_bytes(&proofs[0].proof_hash_to_prime).unwrap(),
+            HPProtocol::<Bls12_381>::proof_to_bytes(&proofs[1].proof_hash_to_prime).unwrap()
+        );
     }
 
-    pub fn prove<
-        R1: MutRandState,
-        R2: RngCore + CryptoRng,
-        C: MembershipVerifierChannel<G>
-            + RootVerifierChannel<G>
-            + ModEqVerifierChannel<G, P>
-            + HashToPrimeVerifierChannel<P, HP>,
-    >(
-        &self,
-        verifier_channel: &mut C,
-        rng1: &mut R1,
-        rng2: &mut R2,
-        statement: &Statement<G, P>,
-        witness: &Witness<G>,
-    ) -> Result<(), ProofError> {
-        let (hashed_e, _) = self.hash_to_prime(&witness.e)?;
-        let r = random_between(rng1, &Integer::from(0), &G::order_upper_bound());
-        let c_e = self
-            .crs
-            .crs_root
-            .integer_commitment_parameters
-            .commit(&hashed_e, &r)?;
-        verifier_channel.send_c_e(&c_e)?;
-        let root = RootProtocol::from_crs(&self.crs.crs_root);
-        root.prove(
-            verifier_channel,
-            rng1,
-            &RootStatement {
-                c_e: c_e.clone(),
-                acc: statement.c_p.clone(),
-            },
-            &RootWitness {
-                e: hashed_e.clone(),
-                r: r.clone(),
-                w: witness.w.clone(),
-            },
-        )?;
-        let modeq = ModEqProtocol::from_crs(&self.crs.crs_modeq);
-        modeq.prove(
-            verifier_channel,
-            rng1,
-            rng2,
-            &ModEqStatement {
-                c_e,
-                c_e_q: statement.c_e_q.clone(),
-            },
-            &ModEqWitness {
-                e: hashed_e,
-                r,
-                r_q: witness.r_q.clone(),
-            },
-        )?;
-        let hash_to_prime = HashToPrimeProtocol::from_crs(&self.crs.crs_hash_to_prime);
-        hash_to_prime.prove(
-            verifier_channel,
-            rng2,
-            &HashToPrimeStatement {
-                c_e_q: statement.c_e_q.clone(),
-            },
-            &HashToPrimeWitness {
-                e: witness.e.clone(),
-                r_q: witness.r_q.clone(),
-            },
-        )?;
+    #[test]
+    fn test_preverify() {
+        let params = Parameters::from_security_level(128).unwrap();
+        let mut rng1 = RandState::new();
+        rng1.seed(&Integer::from(13));
+        let mut rng2 = thread_rng();
 
-        Ok(())
-    }
+        let crs = Protocol::<Rsa2048, G1Projective, HPProtocol<Bls12_381>>::setup(
+            &params, &mut rng1, &mut rng2,
+        )
+        .unwrap()
+        .crs;
+        let protocol = Protocol::<Rsa2048, G1Projective, HPProtocol<Bls12_381>>::from_crs(&crs);
 
-    pub fn verify<
-        C: MembershipProverChannel<G>
-            + RootProverChannel<G>
-            + ModEqProverChannel<G, P>
-            + HashToPrimeProverChannel<P, HP>,
-    >(
-        &self,
-        prover_channel: &mut C,
-        statement: &Statement<G, P>,
-    ) -> Result<(), VerificationError> {
-        let c_e = prover_channel.receive_c_e()?;
-        let root = RootProtocol::from_crs(&self.crs.crs_root);
-        root.verify(
-            prover_channel,
-            &RootStatement {
-                c_e: c_e.clone(),
-                acc: statement.c_p.clone(),
-            },
-        )?;
-        let modeq = ModEqProtocol::from_crs(&self.crs.crs_modeq);
-        modeq.verify(
-            prover_channel,
-            &ModEqStatement {
-                c_e,
-                c_e_q: statement.c_e_q.clone(),
-            },
-        )?;
-        let hash_to_prime = HashToPrimeProtocol::from_crs(&self.crs.crs_hash_to_prime);
-        hash_to_prime.verify(
-            prover_channel,
-            &HashToPrimeStatement {
-                c_e_q: statement.c_e_q.clone(),
-            },
-        )?;
+        let value = Integer::from(Integer::u_pow_u(2, params.hash_to_prime_bits as u32))
+            - &Integer::from(245);
+        let accum =
+            accumulator::Accumulator::<Rsa2048, Integer, AccumulatorWithoutHashToPrime>::empty()
+                .add_with_proof(&[value.clone()]);
+        let mut prove = |randomness: Integer| {
+            let statement = Statement {
+                c_e_q: crs
+                    .crs_modeq
+                    .pedersen_commitment_parameters
+                    .commit(&value, &randomness)
+                    .unwrap(),
+                c_p: accum.0.value.clone(),
+            };
+            let proof_transcript = RefCell::new(Transcript::new(b"membership"));
+            let mut verifier_channel = TranscriptVerifierChannel::new(&crs, &proof_transcript);
+            protocol
+                .prove(
+                    &mut verifier_channel,
+                    &mut rng1,
+                    &mut rng2,
+                    &statement,
+                    &Witness {
+                        e: value.clone(),
+                        r_q: randomness,
+                        w: accum.1.witness.0.value.clone(),
+                    },
+                )
+                .unwrap();
+            (statement, verifier_channel.proof().unwrap())
+        };
+        let (statement, proof) = prove(Integer::from(5));
+        let (_, other_proof) = prove(Integer::from(6));
 
-        Ok(())
-    }
+        let preverify = |proof| {
+            let verification_transcript = RefCell::new(Transcript::new(b"membership"));
+            let mut prover_channel =
+                TranscriptProverChannel::new(&crs, &verification_transcript, proof);
+            protocol.preverify(&mut prover_channel, &statement)
+        };
+        let partially_verified = preverify(&proof).unwrap();
+        protocol.finalize(partially_verified).unwrap();
 
-    pub fn hash_to_prime(&self, e: &Integer) -> Result<(Integer, u64), HashToPrimeError> {
-        let hash_to_prime = HashToPrimeProtocol::from_crs(&self.crs.crs_hash_to_prime);
-        hash_to_prime.hash_to_prime(e)
-    }
+        // a wrong sigma proof is rejected by the pre-screening
+        let mut invalid_proof = proof.clone();
+        invalid_proof.proof_root.message3.s_e += 1;
+        assert!(preverify(&invalid_proof).is_err());
 
-    pub fn from_crs(crs: &CRS<G, P, HP>) -> Protocol<G, P, HP> {
-        Protocol { crs: crs.clone() }
+        // a wrong hash-to-prime proof only when finalizing
+        let mut invalid_proof = proof.clone();
+        invalid_proof.proof_hash_to_prime = other_proof.proof_hash_to_prime;
+        let partially_verified = preverify(&invalid_proof).unwrap();
+        assert!(protocol.finalize(partially_verified).is_err());
+
+        let batch = vec![preverify(&proof).unwrap(), preverify(&proof).unwrap()];
+        protocol.finalize_batch(&mut rng2, batch).unwrap();
+        let batch = vec![
+            preverify(&proof).unwrap(),
+            preverify(&invalid_proof).unwrap(),
+        ];
+        assert!(protocol.finalize_batch(&mut rng2, batch).is_err());
     }
-}
 
-#[cfg(all(test, feature = "arkworks"))]
-mod test {
-    use super::{Protocol, Statement, Witness};
-    use crate::{
-        commitments::Commitment,
-        parameters::Parameters,
-        protocols::hash_to_prime::snark_range::Protocol as HPProtocol,
-        protocols::{
-            hash_to_prime::snark_hash::{HashToPrimeHashParameters, Protocol as HPHashProtocol},
-            membership::transcript::{TranscriptProverChannel, TranscriptVerifierChannel},
-        },
-    };
-    use accumulator::group::{ClassGroup, Rsa2048};
-    use accumulator::{group::Group, AccumulatorWithoutHashToPrime};
-    use ark_bls12_381::{Bls12_381, G1Projective};
-    use merlin::Transcript;
-    use rand::thread_rng;
-    use rug::rand::RandState;
-    use rug::Integer;
-    use std::cell::RefCell;
+    #[test]
+    fn test_prove_with_transcript_rng() {
+        let params = Parameters::from_security_level(128).unwrap();
+        let mut rng1 = RandState::new();
+        rng1.seed(&Integer::from(13));
+        let mut rng2 = thread_rng();
 
-    const LARGE_PRIMES: [u64; 4] = [
-        553_525_575_239_331_913,
-        12_702_637_924_034_044_211,
-        378_373_571_372_703_133,
-        8_640_171_141_336_142_787,
-    ];
+        let crs = Protocol::<Rsa2048, G1Projective, HPProtocol<Bls12_381>>::setup(
+            &params, &mut rng1, &mut rng2,
+        )
+        .unwrap()
+        .crs;
+        let protocol = Protocol::<Rsa2048, G1Projective, HPProtocol<Bls12_381>>::from_crs(&crs);
+
+        let value = Integer::from(Integer::u_pow_u(2, params.hash_to_prime_bits as u32))
+            - &Integer::from(245);
+        let accum =
+            accumulator::Accumulator::<Rsa2048, Integer, AccumulatorWithoutHashToPrime>::empty()
+                .add_with_proof(&[value.clone()]);
+        let prove = |randomness: u64, seed: u64| {
+            let statement = Statement {
+                c_e_q: crs
+                    .crs_modeq
+                    .pedersen_commitment_parameters
+                    .commit(&value, &Integer::from(randomness))
+                    .unwrap(),
+                c_p: accum.0.value.clone(),
+            };
+            let proof_transcript = RefCell::new(Transcript::new(b"membership"));
+            let mut verifier_channel = TranscriptVerifierChannel::new(&crs, &proof_transcript);
+            protocol
+                .prove_with_transcript_rng(
+                    &mut verifier_channel,
+                    &mut StdRng::seed_from_u64(seed),
+                    &statement,
+                    &Witness {
+                        e: value.clone(),
+                        r_q: Integer::from(randomness),
+                        w: accum.1.witness.0.value.clone(),
+                    },
+                )
+                .unwrap();
+            (statement, verifier_channel.proof().unwrap())
+        };
+        let (statement, proof) = prove(5, 1);
+        let verification_transcript = RefCell::new(Transcript::new(b"membership"));
+        let mut prover_channel =
+            TranscriptProverChannel::new(&crs, &verification_transcript, &proof);
+        protocol.verify(&mut prover_channel, &statement).unwrap();
+
+        // with a repeated rng, the blinding values only repeat for the same
+        // statement and witness
+        let (_, same_proof) = prove(5, 1);
+        assert!(proof.proof_modeq.message1.alpha2 == same_proof.proof_modeq.message1.alpha2);
+        let (_, other_proof) = prove(6, 1);
+        assert!(proof.proof_modeq.message1.alpha2 != other_proof.proof_modeq.message1.alpha2);
+        assert!(proof.proof_root.message1.c_w != other_proof.proof_root.message1.c_w);
+    }
 
     #[test]
-    fn test_e2e_prime_rsa() {
+    fn test_e2e_prime_class_group() {
         let params = Parameters::from_security_level(128).unwrap();
         let mut rng1 = RandState::new();
         rng1.seed(&Integer::from(13));
         let mut rng2 = thread_rng();
 
         let crs = crate::protocols::membership::Protocol::<
-            Rsa2048,
+            ClassGroup,
             G1Projective,
             HPProtocol<Bls12_381>,
         >::setup(&params, &mut rng1, &mut rng2)
         .unwrap()
         .crs;
-        let protocol = Protocol::<Rsa2048, G1Projective, HPProtocol<Bls12_381>>::from_crs(&crs);
+        let protocol = Protocol::<ClassGroup, G1Projective, HPProtocol<Bls12_381>>::from_crs(&crs);
 
         let value = Integer::from(Integer::u_pow_u(
             2,
@@ -302,7 +1733,7 @@ mod test {
             .unwrap();
 
         let accum =
-            accumulator::Accumulator::<Rsa2048, Integer, AccumulatorWithoutHashToPrime>::empty();
+            accumulator::Accumulator::<ClassGroup, Integer, AccumulatorWithoutHashToPrime>::empty();
         let accum = accum.add(
             &LARGE_PRIMES
                 .iter()
@@ -314,7 +1745,7 @@ mod test {
         let accum = accum.add_with_proof(&[value.clone()]);
         let acc = accum.0.value;
         let w = accum.1.witness.0.value;
-        assert_eq!(Rsa2048::exp(&w, &value), acc);
+        assert_eq!(ClassGroup::exp(&w, &value), acc);
 
         let proof_transcript = RefCell::new(Transcript::new(b"membership"));
         let mut verifier_channel = TranscriptVerifierChannel::new(&crs, &proof_transcript);
@@ -343,20 +1774,21 @@ mod test {
     }
 
     #[test]
-    fn test_e2e_prime_class_group() {
+    fn test_e2e_prime_trapdoor() {
         let params = Parameters::from_security_level(128).unwrap();
         let mut rng1 = RandState::new();
         rng1.seed(&Integer::from(13));
         let mut rng2 = thread_rng();
 
         let crs = crate::protocols::membership::Protocol::<
-            ClassGroup,
+            TrapdoorRsa512,
             G1Projective,
             HPProtocol<Bls12_381>,
         >::setup(&params, &mut rng1, &mut rng2)
         .unwrap()
         .crs;
-        let protocol = Protocol::<ClassGroup, G1Projective, HPProtocol<Bls12_381>>::from_crs(&crs);
+        let protocol =
+            Protocol::<TrapdoorRsa512, G1Projective, HPProtocol<Bls12_381>>::from_crs(&crs);
 
         let value = Integer::from(Integer::u_pow_u(
             2,
@@ -370,8 +1802,11 @@ mod test {
             .commit(&value, &randomness)
             .unwrap();
 
-        let accum =
-            accumulator::Accumulator::<ClassGroup, Integer, AccumulatorWithoutHashToPrime>::empty();
+        let accum = accumulator::Accumulator::<
+            TrapdoorRsa512,
+            Integer,
+            AccumulatorWithoutHashToPrime,
+        >::empty();
         let accum = accum.add(
             &LARGE_PRIMES
                 .iter()
@@ -383,7 +1818,7 @@ mod test {
         let accum = accum.add_with_proof(&[value.clone()]);
         let acc = accum.0.value;
         let w = accum.1.witness.0.value;
-        assert_eq!(ClassGroup::exp(&w, &value), acc);
+        assert_eq!(TrapdoorRsa512::exp(&w, &value), acc);
 
         let proof_transcript = RefCell::new(Transcript::new(b"membership"));
         let mut verifier_channel = TranscriptVerifierChannel::new(&crs, &proof_transcript);
@@ -489,7 +1924,7 @@ mod test {
 }
 
 #[cfg(all(test, feature = "dalek"))]
-mod test {
+mod test_dalek {
     use super::{Protocol, Statement, Witness};
     use crate::{
         commitments::Commitment,
@@ -0,0 +1,315 @@
+//! Membership with multiplicity: proofs that the committed element was
+//! accumulated at least `k` times, e.g. for rate limiting or stake weighting
+//! where an element is inserted once per unit.
+//!
+//! The witness is a root `w` such that `w^(e^k)` is the accumulator, which is
+//! shown with a chain of `k` root proofs sharing `c_e`. The first is the root
+//! proof of the membership proof, for `w_1 = w^(e^(k-1))`. Each of the others
+//! proves `w_(i+1)` is an `e`-th root of the `w_i` hidden in `c_w` and `c_r`
+//! of the previous one, which have the form of a `HiddenAccumulator`, so the
+//! intermediate roots aren't revealed. The proofs are non-interactive only.
+#[cfg(feature = "prover")]
+use crate::protocols::{
+    blinding::BlindingHistory,
+    membership::{transcript::TranscriptVerifierChannel, Witness},
+    root::{
+        transcript::TranscriptVerifierChannel as RootTranscriptVerifierChannel,
+        Witness as RootWitness,
+    },
+    ProofError,
+};
+use crate::{
+    protocols::{
+        hash_to_prime::{transcript::TranscriptProtocolHashToPrime, HashToPrimeProtocol},
+        membership::{
+            channel::MembershipProverChannel,
+            transcript::{TranscriptProtocolMembership, TranscriptProverChannel},
+            Proof, Protocol, Statement,
+        },
+        modeq::transcript::TranscriptProtocolModEq,
+        root::{
+            transcript::{
+                TranscriptProtocolRoot, TranscriptProverChannel as RootTranscriptProverChannel,
+            },
+            HiddenAccumulator, HiddenStatement as RootHiddenStatement, Proof as RootProof,
+            Protocol as RootProtocol, Statement as RootStatement,
+        },
+        VerificationError,
+    },
+    utils::{curve::CurvePointProjective, ConvertibleUnknownOrderGroup},
+};
+#[cfg(feature = "prover")]
+use rand::{CryptoRng, RngCore};
+#[cfg(feature = "prover")]
+use rug::rand::MutRandState;
+use std::cell::RefCell;
+
+pub struct MultiplicityProof<
+    G: ConvertibleUnknownOrderGroup,
+    P: CurvePointProjective,
+    HP: HashToPrimeProtocol<P>,
+> {
+    pub proof: Proof<G, P, HP>,
+    /// The `k - 1` root proofs following the one of `proof`.
+    pub proofs_root: Vec<RootProof<G>>,
+}
+
+impl<G: ConvertibleUnknownOrderGroup, P: CurvePointProjective, HP: HashToPrimeProtocol<P>> Clone
+    for MultiplicityProof<G, P, HP>
+{
+    fn clone(&self) -> Self {
+        Self {
+            proof: self.proof.clone(),
+            proofs_root: self.proofs_root.clone(),
+        }
+    }
+}
+
+/// The statement of the root proof chained to `previous`.
+fn chained_statement<G: ConvertibleUnknownOrderGroup>(
+    c_e: &G::Elem,
+    previous: &RootProof<G>,
+) -> RootHiddenStatement<G> {
+    RootHiddenStatement {
+        c_e: c_e.clone(),
+        acc: HiddenAccumulator {
+            c_acc: previous.message1.c_w.clone(),
+            c_a: previous.message1.c_r.clone(),
+        },
+    }
+}
+
+impl<G: ConvertibleUnknownOrderGroup, P: CurvePointProjective, HP: HashToPrimeProtocol<P>>
+    Protocol<G, P, HP>
+{
+    /// Proves the element of `witness` was accumulated at least `k` times in
+    /// `statement.c_p`, with `witness.w` such that `w^(e^k)` is the
+    /// accumulator, `e` being the hashed element.
+    #[cfg(feature = "prover")]
+    pub fn prove_multiplicity<
+        R1: MutRandState,
+        R2: RngCore + CryptoRng,
+        T: TranscriptProtocolMembership<G>
+            + TranscriptProtocolRoot<G>
+            + TranscriptProtocolModEq<G, P>
+            + TranscriptProtocolHashToPrime<P>,
+    >(
+        &self,
+        transcript: &RefCell<T>,
+        rng1: &mut R1,
+        rng2: &mut R2,
+        statement: &Statement<G, P>,
+        witness: &Witness<G>,
+        k: usize,
+    ) -> Result<MultiplicityProof<G, P, HP>, ProofError> {
+        if k == 0 {
+            return Err(ProofError::CouldNotCreateProof);
+        }
+        let mut history = BlindingHistory::new(0);
+        let mut verifier_channel = TranscriptVerifierChannel::new(&self.crs, transcript);
+        let (c_e, root_witness) =
+            self.commit_to_prime(&mut verifier_channel, rng1, witness, None)?;
+        // roots[i] is w_(i+1), with roots[k - 1] = w
+        let mut roots = vec![witness.w.clone()];
+        for _ in 1..k {
+            let next = G::exp(&roots[0], &root_witness.e);
+            roots.insert(0, next);
+        }
+
+        let root = RootProtocol::from_crs(&self.crs.crs_root)?;
+        let mut opening = root.prove_internal(
+            &mut verifier_channel,
+            rng1,
+            &RootWitness {
+                e: root_witness.e.clone(),
+                r: root_witness.r.clone(),
+                w: roots[0].clone(),
+            },
+            None,
+            &mut history,
+        )?;
+        let mut proofs_root = Vec::with_capacity(k - 1);
+        for w in &roots[1..] {
+            let mut root_channel =
+                RootTranscriptVerifierChannel::new(&self.crs.crs_root, transcript);
+            opening = root.prove_internal(
+                &mut root_channel,
+                rng1,
+                &RootWitness {
+                    e: root_witness.e.clone(),
+                    r: root_witness.r.clone(),
+                    w: w.clone(),
+                },
+                Some(&opening),
+                &mut history,
+            )?;
+            proofs_root.push(
+                root_channel
+                    .proof()
+                    .map_err(|_| ProofError::CouldNotCreateProof)?,
+            );
+        }
+
+        self.prove_modeq_and_hash_to_prime(
+            &mut verifier_channel,
+            rng1,
+            rng2,
+            &statement.c_e_q,
+            witness,
+            c_e,
+            root_witness,
+            &mut history,
+            None,
+        )?;
+        let proof = verifier_channel
+            .proof()
+            .map_err(|_| ProofError::CouldNotCreateProof)?;
+
+        Ok(MultiplicityProof { proof, proofs_root })
+    }
+
+    /// Verifies the element committed in `statement.c_e_q` was accumulated at
+    /// least `k` times in `statement.c_p`.
+    pub fn verify_multiplicity<
+        T: TranscriptProtocolMembership<G>
+            + TranscriptProtocolRoot<G>
+            + TranscriptProtocolModEq<G, P>
+            + TranscriptProtocolHashToPrime<P>,
+    >(
+        &self,
+        transcript: &RefCell<T>,
+        statement: &Statement<G, P>,
+        proof: &MultiplicityProof<G, P, HP>,
+        k: usize,
+    ) -> Result<(), VerificationError> {
+        if k == 0 || proof.proofs_root.len() != k - 1 {
+            return Err(VerificationError::VerificationFailed);
+        }
+        let mut prover_channel = TranscriptProverChannel::new(&self.crs, transcript, &proof.proof);
+        let c_e = self.receive_c_e(&mut prover_channel)?;
+        let root = RootProtocol::from_crs(&self.crs.crs_root)?;
+        let mut root_result = root.verify(
+            &mut prover_channel,
+            &RootStatement {
+                c_e: c_e.clone(),
+                acc: statement.c_p.clone(),
+            },
+        );
+        let mut previous = &proof.proof.proof_root;
+        for proof_root in &proof.proofs_root {
+            let mut root_channel =
+                RootTranscriptProverChannel::new(&self.crs.crs_root, transcript, proof_root);
+            let result =
+                root.verify_hidden(&mut root_channel, &chained_statement::<G>(&c_e, previous));
+            root_result = root_result.and(result);
+            previous = proof_root;
+        }
+
+        let (modeq_result, partially_verified) =
+            self.preverify_modeq(&mut prover_channel, c_e, &statement.c_e_q)?;
+        let hash_to_prime_result = self.finalize(partially_verified);
+        root_result.and(modeq_result).and(hash_to_prime_result)
+    }
+}
+
+#[cfg(all(test, feature = "arkworks"))]
+mod test {
+    use crate::{
+        commitments::Commitment,
+        parameters::Parameters,
+        protocols::{
+            hash_to_prime::snark_range::Protocol as HPProtocol,
+            membership::{Protocol, Statement, Witness},
+        },
+        set::{AccumulatorProvider, SetAccumulator},
+    };
+    use accumulator::group::Rsa2048;
+    use ark_bls12_381::{Bls12_381, G1Projective};
+    use merlin::Transcript;
+    use rand::thread_rng;
+    use rug::rand::RandState;
+    use rug::Integer;
+    use std::cell::RefCell;
+
+    #[test]
+    fn test_multiplicity() {
+        let params = Parameters::from_security_level(128).unwrap();
+        let mut rng1 = RandState::new();
+        rng1.seed(&Integer::from(13));
+        let mut rng2 = thread_rng();
+
+        let crs = Protocol::<Rsa2048, G1Projective, HPProtocol<Bls12_381>>::setup(
+            &params, &mut rng1, &mut rng2,
+        )
+        .unwrap()
+        .crs;
+        let protocol = Protocol::<Rsa2048, G1Projective, HPProtocol<Bls12_381>>::from_crs(&crs);
+
+        let value = Integer::from(Integer::u_pow_u(2, params.hash_to_prime_bits as u32))
+            - &Integer::from(245);
+        let other = Integer::from(Integer::u_pow_u(2, params.hash_to_prime_bits as u32))
+            - &Integer::from(159);
+        // the element was added three times
+        let set = SetAccumulator::<Rsa2048>::from_elements(&[
+            value.clone(),
+            other,
+            value.clone(),
+            value.clone(),
+        ]);
+        assert_eq!(set.multiplicity(&value), 3);
+        assert!(set.multiplicity_witness(&value, 4).is_err());
+
+        let randomness = Integer::from(5);
+        let statement = Statement {
+            c_p: set.value(),
+            c_e_q: crs
+                .crs_modeq
+                .pedersen_commitment_parameters
+                .commit(&value, &randomness)
+                .unwrap(),
+        };
+        let witness = |k: usize| Witness {
+            e: value.clone(),
+            r_q: randomness.clone(),
+            w: set.multiplicity_witness(&value, k).unwrap(),
+        };
+
+        let proof_transcript = RefCell::new(Transcript::new(b"multiplicity"));
+        let proof = protocol
+            .prove_multiplicity(
+                &proof_transcript,
+                &mut rng1,
+                &mut rng2,
+                &statement,
+                &witness(3),
+                3,
+            )
+            .unwrap();
+        assert_eq!(proof.proofs_root.len(), 2);
+        let verification_transcript = RefCell::new(Transcript::new(b"multiplicity"));
+        protocol
+            .verify_multiplicity(&verification_transcript, &statement, &proof, 3)
+            .unwrap();
+        let verification_transcript = RefCell::new(Transcript::new(b"multiplicity"));
+        assert!(protocol
+            .verify_multiplicity(&verification_transcript, &statement, &proof, 2)
+            .is_err());
+
+        // a witness of multiplicity 2 doesn't prove 3
+        let proof_transcript = RefCell::new(Transcript::new(b"multiplicity"));
+        let proof = protocol
+            .prove_multiplicity(
+                &proof_transcript,
+                &mut rng1,
+                &mut rng2,
+                &statement,
+                &witness(2),
+                3,
+            )
+            .unwrap();
+        let verification_transcript = RefCell::new(Transcript::new(b"multiplicity"));
+        assert!(protocol
+            .verify_multiplicity(&verification_transcript, &statement, &proof, 3)
+            .is_err());
+    }
+}
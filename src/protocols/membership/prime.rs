@@ -0,0 +1,214 @@
+//! Implements CPMemRSAPrm, the membership protocol for sets whose elements are
+//! already primes of `hash_to_prime_bits` bits, so only a range proof is needed
+//! instead of a hash-to-prime proof.
+#[cfg(feature = "prover")]
+use crate::{
+    parameters::Parameters,
+    protocols::{
+        hash_to_prime::{channel::HashToPrimeVerifierChannel, validate_prime_element},
+        membership::channel::MembershipVerifierChannel,
+        modeq::channel::ModEqVerifierChannel,
+        root::channel::RootVerifierChannel,
+        ProofError, SetupError,
+    },
+};
+use crate::{
+    protocols::{
+        hash_to_prime::{channel::HashToPrimeProverChannel, RangeProofProtocol},
+        membership::{
+            channel::MembershipProverChannel, Protocol, Statement as MembershipStatement,
+            Witness as MembershipWitness, CRS,
+        },
+        modeq::channel::ModEqProverChannel,
+        root::channel::RootProverChannel,
+        VerificationError,
+    },
+    utils::{curve::CurvePointProjective, ConvertibleUnknownOrderGroup},
+};
+#[cfg(feature = "prover")]
+use rand::{CryptoRng, RngCore};
+#[cfg(feature = "prover")]
+use rug::rand::MutRandState;
+
+/// The statement of CPMemRSAPrm: `c_e_q` commits directly to the prime element.
+pub type Statement<G, P> = MembershipStatement<G, P>;
+/// The witness of CPMemRSAPrm: `e` is the prime element itself.
+pub type Witness<G> = MembershipWitness<G>;
+
+pub struct CPMemRSAPrm<
+    G: ConvertibleUnknownOrderGroup,
+    P: CurvePointProjective,
+    HP: RangeProofProtocol<P>,
+> {
+    pub protocol: Protocol<G, P, HP>,
+}
+
+impl<G: ConvertibleUnknownOrderGroup, P: CurvePointProjective, HP: RangeProofProtocol<P>>
+    CPMemRSAPrm<G, P, HP>
+{
+    #[cfg(feature = "prover")]
+    pub fn setup<R1: MutRandState, R2: RngCore + CryptoRng>(
+        parameters: &Parameters,
+        rng1: &mut R1,
+        rng2: &mut R2,
+    ) -> Result<CPMemRSAPrm<G, P, HP>, SetupError> {
+        Ok(CPMemRSAPrm {
+            protocol: Protocol::setup(parameters, rng1, rng2)?,
+        })
+    }
+
+    pub fn from_crs(crs: &CRS<G, P, HP>) -> CPMemRSAPrm<G, P, HP> {
+        CPMemRSAPrm {
+            protocol: Protocol::from_crs(crs),
+        }
+    }
+
+    pub fn crs(&self) -> &CRS<G, P, HP> {
+        &self.protocol.crs
+    }
+
+    #[cfg(feature = "prover")]
+    pub fn prove<
+        R1: MutRandState,
+        R2: RngCore + CryptoRng,
+        C: MembershipVerifierChannel<G>
+            + RootVerifierChannel<G>
+            + ModEqVerifierChannel<G, P>
+            + HashToPrimeVerifierChannel<P, HP>,
+    >(
+        &self,
+        verifier_channel: &mut C,
+        rng1: &mut R1,
+        rng2: &mut R2,
+        statement: &Statement<G, P>,
+        witness: &Witness<G>,
+    ) -> Result<(), ProofError> {
+        validate_prime_element(&self.protocol.crs.parameters, &witness.e)?;
+        self.protocol
+            .prove(verifier_channel, rng1, rng2, statement, witness)
+    }
+
+    pub fn verify<
+        C: MembershipProverChannel<G>
+            + RootProverChannel<G>
+            + ModEqProverChannel<G, P>
+            + HashToPrimeProverChannel<P, HP>,
+    >(
+        &self,
+        prover_channel: &mut C,
+        statement: &Statement<G, P>,
+    ) -> Result<(), VerificationError> {
+        self.protocol.verify(prover_channel, statement)
+    }
+}
+
+#[cfg(all(test, feature = "arkworks"))]
+mod test {
+    use super::{CPMemRSAPrm, Statement, Witness};
+    use crate::{
+        commitments::Commitment,
+        parameters::Parameters,
+        protocols::{
+            hash_to_prime::snark_range::Protocol as HPProtocol,
+            membership::transcript::{TranscriptProverChannel, TranscriptVerifierChannel},
+            ProofError,
+        },
+    };
+    use accumulator::group::{Group, Rsa2048};
+    use accumulator::AccumulatorWithoutHashToPrime;
+    use ark_bls12_381::{Bls12_381, G1Projective};
+    use merlin::Transcript;
+    use rand::thread_rng;
+    use rug::rand::RandState;
+    use rug::Integer;
+    use std::cell::RefCell;
+
+    const LARGE_PRIMES: [u64; 3] = [
+        12_702_637_924_034_044_211,
+        378_373_571_372_703_133,
+        8_640_171_141_336_142_787,
+    ];
+
+    #[test]
+    fn test_e2e_prm() {
+        let params = Parameters::from_security_level(128).unwrap();
+        let mut rng1 = RandState::new();
+        rng1.seed(&Integer::from(13));
+        let mut rng2 = thread_rng();
+
+        let protocol = CPMemRSAPrm::<Rsa2048, G1Projective, HPProtocol<Bls12_381>>::setup(
+            &params, &mut rng1, &mut rng2,
+        )
+        .unwrap();
+        let crs = protocol.crs().clone();
+
+        // 2^254 - 245 is a 254-bit prime
+        let value = Integer::from(Integer::u_pow_u(
+            2,
+            (crs.parameters.hash_to_prime_bits) as u32,
+        )) - &Integer::from(245);
+        let randomness = Integer::from(5);
+        let commitment = crs
+            .crs_modeq
+            .pedersen_commitment_parameters
+            .commit(&value, &randomness)
+            .unwrap();
+
+        let accum =
+            accumulator::Accumulator::<Rsa2048, Integer, AccumulatorWithoutHashToPrime>::empty();
+        let accum = accum.add(
+            &LARGE_PRIMES
+                .iter()
+                .map(|p| Integer::from(*p))
+                .collect::<Vec<_>>(),
+        );
+        let accum = accum.add_with_proof(&[value.clone()]);
+        let acc = accum.0.value;
+        let w = accum.1.witness.0.value;
+        assert_eq!(Rsa2048::exp(&w, &value), acc);
+
+        let statement = Statement {
+            c_e_q: commitment,
+            c_p: acc,
+        };
+
+        let not_prime = value.clone() + 1;
+        let proof_transcript = RefCell::new(Transcript::new(b"membership"));
+        let mut verifier_channel = TranscriptVerifierChannel::new(&crs, &proof_transcript);
+        match protocol.prove(
+            &mut verifier_channel,
+            &mut rng1,
+            &mut rng2,
+            &statement,
+            &Witness {
+                e: not_prime,
+                r_q: randomness.clone(),
+                w: w.clone(),
+            },
+        ) {
+            Err(ProofError::PrimeError(_)) => {}
+            _ => panic!("expected a non-prime element to be rejected"),
+        }
+
+        let proof_transcript = RefCell::new(Transcript::new(b"membership"));
+        let mut verifier_channel = TranscriptVerifierChannel::new(&crs, &proof_transcript);
+        protocol
+            .prove(
+                &mut verifier_channel,
+                &mut rng1,
+                &mut rng2,
+                &statement,
+                &Witness {
+                    e: value,
+                    r_q: randomness,
+                    w,
+                },
+            )
+            .unwrap();
+        let proof = verifier_channel.proof().unwrap();
+        let verification_transcript = RefCell::new(Transcript::new(b"membership"));
+        let mut prover_channel =
+            TranscriptProverChannel::new(&crs, &verification_transcript, &proof);
+        protocol.verify(&mut prover_channel, &statement).unwrap();
+    }
+}
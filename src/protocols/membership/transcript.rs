@@ -1,5 +1,7 @@
+#[cfg(feature = "compressed")]
+use crate::protocols::membership::CompressedProof;
 use crate::{
-    channels::ChannelError,
+    channels::{ChannelError, MessageOrder},
     commitments::{integer::IntegerCommitment, Commitment},
     protocols::{
         hash_to_prime::{
@@ -13,7 +15,7 @@ use crate::{
         },
         membership::{
             channel::{MembershipProverChannel, MembershipVerifierChannel},
-            Proof, CRS,
+            Proof, Statement, Witness, CRS,
         },
         modeq::{
             channel::{ModEqProverChannel, ModEqVerifierChannel},
@@ -22,6 +24,7 @@ use crate::{
                 TranscriptVerifierChannel as ModEqTranscriptVerifierChannel,
             },
         },
+        registry::CrsDigest,
         root::{
             channel::{RootProverChannel, RootVerifierChannel},
             transcript::{
@@ -30,10 +33,15 @@ use crate::{
             },
         },
     },
-    transcript::{TranscriptChannelError, TranscriptProtocolChallenge, TranscriptProtocolInteger},
-    utils::{curve::CurvePointProjective, ConvertibleUnknownOrderGroup},
+    transcript::{
+        versioned_domain_sep, TranscriptChannelError, TranscriptProtocolChallenge,
+        TranscriptProtocolInteger, TranscriptProtocolVerifierChallenge,
+        TranscriptProtocolWitnessRng, VerifierChallenge, WitnessRng,
+    },
+    utils::{curve::CurvePointProjective, integer_to_signed_bytes, ConvertibleUnknownOrderGroup},
 };
 use merlin::Transcript;
+use rand::{CryptoRng, RngCore};
 use rug::Integer;
 use std::cell::RefCell;
 
@@ -41,13 +49,27 @@ pub trait TranscriptProtocolMembership<G: ConvertibleUnknownOrderGroup>:
     TranscriptProtocolInteger<G> + TranscriptProtocolChallenge
 {
     fn membership_domain_sep(&mut self);
+    fn append_crs_digest(&mut self, crs_digest: &CrsDigest);
 }
 
 impl<G: ConvertibleUnknownOrderGroup> TranscriptProtocolMembership<G> for Transcript {
     fn membership_domain_sep(&mut self) {
-        self.append_message(b"dom-sep", b"membership");
+        self.append_message(b"dom-sep", &versioned_domain_sep(b"membership"));
+    }
+
+    fn append_crs_digest(&mut self, crs_digest: &CrsDigest) {
+        self.append_message(b"crs_digest", crs_digest);
     }
 }
+
+/// The transcript of the sub-protocols of `CPMemRSAPrm`.
+pub trait TranscriptProtocolMembershipPrime<
+    G: ConvertibleUnknownOrderGroup,
+    P: CurvePointProjective,
+>:
+    TranscriptProtocolRoot<G> + TranscriptProtocolModEq<G, P> + TranscriptProtocolHashToPrime<P>
+{
+}
 pub struct TranscriptVerifierChannel<
     'a,
     G: ConvertibleUnknownOrderGroup,
@@ -59,6 +81,8 @@ pub struct TranscriptVerifierChannel<
         + TranscriptProtocolHashToPrime<P>,
 > {
     transcript: &'a RefCell<T>,
+    order: MessageOrder,
+    crs_digest: Option<CrsDigest>,
     c_e: Option<<IntegerCommitment<G> as Commitment>::Instance>,
     root_transcript_verifier_channel: RootTranscriptVerifierChannel<'a, G, T>,
     modeq_transcript_verifier_channel: ModEqTranscriptVerifierChannel<'a, G, P, T>,
@@ -82,6 +106,8 @@ impl<
     ) -> TranscriptVerifierChannel<'a, G, P, HP, T> {
         TranscriptVerifierChannel {
             transcript,
+            order: MessageOrder::new(),
+            crs_digest: None,
             c_e: None,
             root_transcript_verifier_channel: RootTranscriptVerifierChannel::new(
                 &crs.crs_root,
@@ -102,9 +128,10 @@ impl<
         let proof_root = self.root_transcript_verifier_channel.proof()?;
         let proof_modeq = self.modeq_transcript_verifier_channel.proof()?;
         let proof_hash_to_prime = self.hash_to_prime_transcript_verifier_channel.proof()?;
-        if self.c_e.is_some() {
+        if let (Some(crs_digest), Some(c_e)) = (self.crs_digest, &self.c_e) {
             Ok(Proof {
-                c_e: self.c_e.as_ref().unwrap().clone(),
+                crs_digest,
+                c_e: c_e.clone(),
                 proof_root,
                 proof_modeq,
                 proof_hash_to_prime,
@@ -113,6 +140,84 @@ impl<
             Err(TranscriptChannelError::Incomplete)
         }
     }
+
+    #[cfg(feature = "compressed")]
+    pub fn compressed_proof(&self) -> Result<CompressedProof<G, P, HP>, TranscriptChannelError> {
+        let proof_root = self.root_transcript_verifier_channel.compressed_proof()?;
+        let proof_modeq = self.modeq_transcript_verifier_channel.compressed_proof()?;
+        let proof_hash_to_prime = self.hash_to_prime_transcript_verifier_channel.proof()?;
+        if let (Some(crs_digest), Some(c_e)) = (self.crs_digest, &self.c_e) {
+            Ok(CompressedProof {
+                crs_digest,
+                c_e: c_e.clone(),
+                proof_root,
+                proof_modeq,
+                proof_hash_to_prime,
+            })
+        } else {
+            Err(TranscriptChannelError::Incomplete)
+        }
+    }
+}
+
+impl<
+        'a,
+        G: ConvertibleUnknownOrderGroup,
+        P: CurvePointProjective,
+        HP: HashToPrimeProtocol<P>,
+        T: TranscriptProtocolMembership<G>
+            + TranscriptProtocolRoot<G>
+            + TranscriptProtocolModEq<G, P>
+            + TranscriptProtocolHashToPrime<P>
+            + TranscriptProtocolWitnessRng,
+    > TranscriptVerifierChannel<'a, G, P, HP, T>
+{
+    /// Derives the RNGs for the integer and the curve randomness of the
+    /// prover from the transcript, the statement, the witness and `rng`.
+    pub fn witness_rngs<R: RngCore + CryptoRng>(
+        &self,
+        statement: &Statement<G, P>,
+        witness: &Witness<G>,
+        rng: &mut R,
+    ) -> Result<(WitnessRng, WitnessRng), ChannelError> {
+        let transcript = self.transcript.try_borrow()?;
+        let witness_bytes: [&[u8]; 4] = [
+            &statement.digest()?,
+            &integer_to_signed_bytes(&witness.e),
+            &integer_to_signed_bytes(&witness.r_q),
+            &G::elem_to_bytes(&witness.w),
+        ];
+        Ok((
+            transcript.witness_rng(b"integer", &witness_bytes, rng),
+            transcript.witness_rng(b"curve", &witness_bytes, rng),
+        ))
+    }
+}
+
+impl<
+        'a,
+        G: ConvertibleUnknownOrderGroup,
+        P: CurvePointProjective,
+        HP: HashToPrimeProtocol<P>,
+        T: TranscriptProtocolMembership<G>
+            + TranscriptProtocolRoot<G>
+            + TranscriptProtocolModEq<G, P>
+            + TranscriptProtocolHashToPrime<P>
+            + TranscriptProtocolVerifierChallenge,
+    > TranscriptVerifierChannel<'a, G, P, HP, T>
+{
+    /// Like `new`, absorbing the verifier's `challenge` first, so that every
+    /// challenge of the proof depends on it.
+    pub fn with_verifier_challenge(
+        crs: &CRS<G, P, HP>,
+        transcript: &'a RefCell<T>,
+        challenge: &VerifierChallenge,
+    ) -> Result<TranscriptVerifierChannel<'a, G, P, HP, T>, ChannelError> {
+        transcript
+            .try_borrow_mut()?
+            .append_verifier_challenge(challenge);
+        Ok(Self::new(crs, transcript))
+    }
 }
 
 impl<
@@ -130,21 +235,25 @@ impl<
         &mut self,
         message: &crate::protocols::root::Message1<G>,
     ) -> Result<(), ChannelError> {
+        self.order.enter(2)?;
         self.root_transcript_verifier_channel.send_message1(message)
     }
     fn send_message2(
         &mut self,
         message: &crate::protocols::root::Message2<G>,
     ) -> Result<(), ChannelError> {
+        self.order.enter(2)?;
         self.root_transcript_verifier_channel.send_message2(message)
     }
     fn send_message3(
         &mut self,
         message: &crate::protocols::root::Message3,
     ) -> Result<(), ChannelError> {
+        self.order.enter(2)?;
         self.root_transcript_verifier_channel.send_message3(message)
     }
     fn receive_challenge(&mut self) -> Result<Integer, ChannelError> {
+        self.order.enter(2)?;
         self.root_transcript_verifier_channel.receive_challenge()
     }
 }
@@ -164,6 +273,7 @@ impl<
         &mut self,
         message: &crate::protocols::modeq::Message1<G, P>,
     ) -> Result<(), ChannelError> {
+        self.order.enter(3)?;
         self.modeq_transcript_verifier_channel
             .send_message1(message)
     }
@@ -171,10 +281,12 @@ impl<
         &mut self,
         message: &crate::protocols::modeq::Message2<P>,
     ) -> Result<(), ChannelError> {
+        self.order.enter(3)?;
         self.modeq_transcript_verifier_channel
             .send_message2(message)
     }
     fn receive_challenge(&mut self) -> Result<Integer, ChannelError> {
+        self.order.enter(3)?;
         self.modeq_transcript_verifier_channel.receive_challenge()
     }
 }
@@ -191,6 +303,7 @@ impl<
     > HashToPrimeVerifierChannel<P, HP> for TranscriptVerifierChannel<'a, G, P, HP, T>
 {
     fn send_proof(&mut self, proof: &HP::Proof) -> Result<(), ChannelError> {
+        self.order.enter(4)?;
         self.hash_to_prime_transcript_verifier_channel
             .send_proof(proof)
     }
@@ -207,6 +320,7 @@ pub struct TranscriptProverChannel<
         + TranscriptProtocolHashToPrime<P>,
 > {
     transcript: &'a RefCell<T>,
+    order: MessageOrder,
     root_transcript_prover_channel: RootTranscriptProverChannel<'a, G, T>,
     modeq_transcript_prover_channel: ModEqTranscriptProverChannel<'a, G, P, T>,
     hash_to_prime_transcript_prover_channel: HashToPrimeTranscriptProverChannel<'a, P, HP, T>,
@@ -225,15 +339,19 @@ impl<
     > RootProverChannel<G> for TranscriptProverChannel<'a, G, P, HP, T>
 {
     fn receive_message1(&mut self) -> Result<crate::protocols::root::Message1<G>, ChannelError> {
+        self.order.enter(2)?;
         self.root_transcript_prover_channel.receive_message1()
     }
     fn receive_message2(&mut self) -> Result<crate::protocols::root::Message2<G>, ChannelError> {
+        self.order.enter(2)?;
         self.root_transcript_prover_channel.receive_message2()
     }
     fn receive_message3(&mut self) -> Result<crate::protocols::root::Message3, ChannelError> {
+        self.order.enter(2)?;
         self.root_transcript_prover_channel.receive_message3()
     }
     fn generate_and_send_challenge(&mut self) -> Result<Integer, ChannelError> {
+        self.order.enter(2)?;
         self.root_transcript_prover_channel
             .generate_and_send_challenge()
     }
@@ -253,12 +371,15 @@ impl<
     fn receive_message1(
         &mut self,
     ) -> Result<crate::protocols::modeq::Message1<G, P>, ChannelError> {
+        self.order.enter(3)?;
         self.modeq_transcript_prover_channel.receive_message1()
     }
     fn receive_message2(&mut self) -> Result<crate::protocols::modeq::Message2<P>, ChannelError> {
+        self.order.enter(3)?;
         self.modeq_transcript_prover_channel.receive_message2()
     }
     fn generate_and_send_challenge(&mut self) -> Result<Integer, ChannelError> {
+        self.order.enter(3)?;
         self.modeq_transcript_prover_channel
             .generate_and_send_challenge()
     }
@@ -276,6 +397,7 @@ impl<
     > HashToPrimeProverChannel<P, HP> for TranscriptProverChannel<'a, G, P, HP, T>
 {
     fn receive_proof(&mut self) -> Result<HP::Proof, ChannelError> {
+        self.order.enter(4)?;
         self.hash_to_prime_transcript_prover_channel.receive_proof()
     }
 }
@@ -291,12 +413,21 @@ impl<
             + TranscriptProtocolHashToPrime<P>,
     > MembershipVerifierChannel<G> for TranscriptVerifierChannel<'a, G, P, HP, T>
 {
+    fn send_crs_digest(&mut self, crs_digest: &CrsDigest) -> Result<(), ChannelError> {
+        self.order.step(0)?;
+        let mut transcript = self.transcript.try_borrow_mut()?;
+        transcript.membership_domain_sep();
+        transcript.append_crs_digest(crs_digest);
+        self.crs_digest = Some(*crs_digest);
+        Ok(())
+    }
+
     fn send_c_e(
         &mut self,
         c_e: &<IntegerCommitment<G> as Commitment>::Instance,
     ) -> Result<(), ChannelError> {
+        self.order.step(1)?;
         let mut transcript = self.transcript.try_borrow_mut()?;
-        transcript.membership_domain_sep();
         transcript.append_integer_point(b"c_e", c_e);
         self.c_e = Some(c_e.clone());
         Ok(())
@@ -314,11 +445,19 @@ impl<
             + TranscriptProtocolHashToPrime<P>,
     > MembershipProverChannel<G> for TranscriptProverChannel<'a, G, P, HP, T>
 {
+    fn receive_crs_digest(&mut self) -> Result<CrsDigest, ChannelError> {
+        self.order.step(0)?;
+        let mut transcript = self.transcript.try_borrow_mut()?;
+        transcript.membership_domain_sep();
+        transcript.append_crs_digest(&self.proof.crs_digest);
+        Ok(self.proof.crs_digest)
+    }
+
     fn receive_c_e(
         &mut self,
     ) -> Result<<IntegerCommitment<G> as Commitment>::Instance, ChannelError> {
+        self.order.step(1)?;
         let mut transcript = self.transcript.try_borrow_mut()?;
-        transcript.membership_domain_sep();
         transcript.append_integer_point(b"c_e", &self.proof.c_e);
         Ok(self.proof.c_e.clone())
     }
@@ -342,6 +481,7 @@ impl<
     ) -> TranscriptProverChannel<'a, G, P, HP, T> {
         TranscriptProverChannel {
             transcript,
+            order: MessageOrder::new(),
             root_transcript_prover_channel: RootTranscriptProverChannel::new(
                 &crs.crs_root,
                 transcript,
@@ -360,4 +500,36 @@ impl<
             proof: proof.clone(),
         }
     }
+
+    /// See `hash_to_prime::transcript::TranscriptProverChannel::pre_proof_digest`.
+    pub fn pre_proof_digest(&self) -> Option<[u8; 32]> {
+        self.hash_to_prime_transcript_prover_channel
+            .pre_proof_digest()
+    }
+}
+
+impl<
+        'a,
+        G: ConvertibleUnknownOrderGroup,
+        P: CurvePointProjective,
+        HP: HashToPrimeProtocol<P>,
+        T: TranscriptProtocolMembership<G>
+            + TranscriptProtocolRoot<G>
+            + TranscriptProtocolModEq<G, P>
+            + TranscriptProtocolHashToPrime<P>
+            + TranscriptProtocolVerifierChallenge,
+    > TranscriptProverChannel<'a, G, P, HP, T>
+{
+    /// Like `new`, absorbing the `challenge` the verifier handed the prover.
+    pub fn with_verifier_challenge(
+        crs: &CRS<G, P, HP>,
+        transcript: &'a RefCell<T>,
+        proof: &Proof<G, P, HP>,
+        challenge: &VerifierChallenge,
+    ) -> Result<TranscriptProverChannel<'a, G, P, HP, T>, ChannelError> {
+        transcript
+            .try_borrow_mut()?
+            .append_verifier_challenge(challenge);
+        Ok(Self::new(crs, transcript, proof))
+    }
 }
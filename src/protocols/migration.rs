@@ -0,0 +1,297 @@
+//! Migration of statements and proofs to a new CRS, e.g. after a new setup
+//! ceremony.
+//!
+//! The Pedersen bases change with the CRS, so `c_e_q` has to be re-committed
+//! under the new bases, with fresh randomness so the old and new commitments
+//! can't be linked. The old statement and witness are consumed: after a
+//! migration, only the returned ones should be used.
+use crate::{
+    commitments::{pedersen::PedersenCommitment, Commitment, CommitmentError},
+    protocols::{
+        hash_to_prime::HashToPrimeProtocol,
+        membership::{
+            transcript::TranscriptVerifierChannel as MembershipTranscriptVerifierChannel,
+            Proof as MembershipProof, Protocol as MembershipProtocol,
+            Statement as MembershipStatement, Witness as MembershipWitness, CRS as MembershipCRS,
+        },
+        nonmembership::{
+            transcript::TranscriptVerifierChannel as NonMembershipTranscriptVerifierChannel,
+            Proof as NonMembershipProof, Protocol as NonMembershipProtocol,
+            Statement as NonMembershipStatement, Witness as NonMembershipWitness,
+            CRS as NonMembershipCRS,
+        },
+        registry::{CrsDigest, VersionedProof},
+        ProofError,
+    },
+    transcript::{transcript_with_aad, TranscriptChannelError},
+    utils::{
+        bigint_to_integer,
+        curve::{CurveError, CurvePointProjective, Field},
+        ConvertibleUnknownOrderGroup,
+    },
+};
+use rand::{CryptoRng, RngCore};
+use rug::{rand::MutRandState, Integer};
+use std::cell::RefCell;
+
+quick_error! {
+    #[derive(Debug)]
+    pub enum MigrationError {
+        SameCrs {}
+        WrongOpening(err: CommitmentError) {
+            from()
+        }
+        CurveError(err: CurveError) {
+            from()
+        }
+        ProofError(err: ProofError) {
+            from()
+        }
+        TranscriptChannelError(err: TranscriptChannelError) {
+            from()
+        }
+    }
+}
+
+/// A statement re-committed under a new CRS, with its witness and a proof
+/// tagged with the new CRS digest.
+pub struct Migrated<S, W, Pr> {
+    pub statement: S,
+    pub witness: W,
+    pub proof: VersionedProof<Pr>,
+}
+
+/// Checks that `c_e_q` opens to `e` under the old bases and re-commits `e`
+/// under the new ones, returning the new commitment and randomness.
+fn recommit<P: CurvePointProjective, R: RngCore + CryptoRng>(
+    old_pedersen: &PedersenCommitment<P>,
+    new_pedersen: &PedersenCommitment<P>,
+    c_e_q: &P,
+    e: &Integer,
+    r_q: &Integer,
+    rng: &mut R,
+) -> Result<(P, Integer), MigrationError> {
+    old_pedersen.open(c_e_q, e, r_q)?;
+    let new_r_q = bigint_to_integer::<P>(&P::ScalarField::rand(rng));
+    let new_c_e_q = new_pedersen.commit(e, &new_r_q)?;
+    Ok((new_c_e_q, new_r_q))
+}
+
+fn check_digests(old: CrsDigest, new: CrsDigest) -> Result<CrsDigest, MigrationError> {
+    if old == new {
+        return Err(MigrationError::SameCrs);
+    }
+    Ok(new)
+}
+
+/// Moves a membership statement from `old_crs` to `new_crs` and proves it
+/// under the new CRS, on a transcript created with `transcript_label` and bound to the
+/// associated data `aad`.
+pub fn migrate_membership_proof<
+    G: ConvertibleUnknownOrderGroup,
+    P: CurvePointProjective,
+    HP: HashToPrimeProtocol<P>,
+    R1: MutRandState,
+    R2: RngCore + CryptoRng,
+>(
+    old_crs: &MembershipCRS<G, P, HP>,
+    new_crs: &MembershipCRS<G, P, HP>,
+    statement: MembershipStatement<G, P>,
+    witness: MembershipWitness<G>,
+    transcript_label: &'static [u8],
+    aad: &[u8],
+    rng1: &mut R1,
+    rng2: &mut R2,
+) -> Result<
+    Migrated<MembershipStatement<G, P>, MembershipWitness<G>, MembershipProof<G, P, HP>>,
+    MigrationError,
+> {
+    let crs_digest = check_digests(old_crs.digest()?, new_crs.digest()?)?;
+    let (c_e_q, r_q) = recommit(
+        &old_crs.crs_modeq.pedersen_commitment_parameters,
+        &new_crs.crs_modeq.pedersen_commitment_parameters,
+        &statement.c_e_q,
+        &witness.e,
+        &witness.r_q,
+        rng2,
+    )?;
+    let statement = MembershipStatement {
+        c_p: statement.c_p,
+        c_e_q,
+    };
+    let witness = MembershipWitness {
+        e: witness.e,
+        r_q,
+        w: witness.w,
+    };
+
+    let protocol = MembershipProtocol::from_crs(new_crs);
+    let transcript = RefCell::new(transcript_with_aad(transcript_label, aad));
+    let mut verifier_channel = MembershipTranscriptVerifierChannel::new(new_crs, &transcript);
+    protocol.prove(&mut verifier_channel, rng1, rng2, &statement, &witness)?;
+
+    Ok(Migrated {
+        statement,
+        witness,
+        proof: VersionedProof::new(crs_digest, verifier_channel.proof()?),
+    })
+}
+
+/// Moves a non-membership statement from `old_crs` to `new_crs` and proves
+/// it under the new CRS, on a transcript created with `transcript_label` and bound to the
+/// associated data `aad`.
+pub fn migrate_nonmembership_proof<
+    G: ConvertibleUnknownOrderGroup,
+    P: CurvePointProjective,
+    HP: HashToPrimeProtocol<P>,
+    R1: MutRandState,
+    R2: RngCore + CryptoRng,
+>(
+    old_crs: &NonMembershipCRS<G, P, HP>,
+    new_crs: &NonMembershipCRS<G, P, HP>,
+    statement: NonMembershipStatement<G, P>,
+    witness: NonMembershipWitness<G>,
+    transcript_label: &'static [u8],
+    aad: &[u8],
+    rng1: &mut R1,
+    rng2: &mut R2,
+) -> Result<
+    Migrated<NonMembershipStatement<G, P>, NonMembershipWitness<G>, NonMembershipProof<G, P, HP>>,
+    MigrationError,
+> {
+    let crs_digest = check_digests(old_crs.digest()?, new_crs.digest()?)?;
+    let (c_e_q, r_q) = recommit(
+        &old_crs.crs_modeq.pedersen_commitment_parameters,
+        &new_crs.crs_modeq.pedersen_commitment_parameters,
+        &statement.c_e_q,
+        &witness.e,
+        &witness.r_q,
+        rng2,
+    )?;
+    let statement = NonMembershipStatement {
+        c_p: statement.c_p,
+        c_e_q,
+    };
+    let witness = NonMembershipWitness {
+        e: witness.e,
+        r_q,
+        d: witness.d,
+        b: witness.b,
+    };
+
+    let protocol = NonMembershipProtocol::from_crs(new_crs);
+    let transcript = RefCell::new(transcript_with_aad(transcript_label, aad));
+    let mut verifier_channel = NonMembershipTranscriptVerifierChannel::new(new_crs, &transcript);
+    protocol.prove(&mut verifier_channel, rng1, rng2, &statement, &witness)?;
+
+    Ok(Migrated {
+        statement,
+        witness,
+        proof: VersionedProof::new(crs_digest, verifier_channel.proof()?),
+    })
+}
+
+#[cfg(all(test, feature = "arkworks"))]
+mod test {
+    use super::{migrate_membership_proof, MigrationError};
+    use crate::{
+        commitments::Commitment,
+        parameters::Parameters,
+        protocols::{
+            hash_to_prime::snark_range::Protocol as HPProtocol,
+            membership::{Protocol, Statement, Witness},
+            registry::CrsRegistry,
+        },
+    };
+    use accumulator::{group::Rsa2048, AccumulatorWithoutHashToPrime};
+    use ark_bls12_381::{Bls12_381, G1Projective};
+    use rand::thread_rng;
+    use rug::rand::RandState;
+    use rug::Integer;
+
+    #[test]
+    fn test_migrate_membership_proof() {
+        let params = Parameters::from_security_level(128).unwrap();
+        let mut rng1 = RandState::new();
+        rng1.seed(&Integer::from(13));
+        let mut rng2 = thread_rng();
+
+        let old_crs = Protocol::<Rsa2048, G1Projective, HPProtocol<Bls12_381>>::setup(
+            &params, &mut rng1, &mut rng2,
+        )
+        .unwrap()
+        .crs;
+        let new_crs = Protocol::<Rsa2048, G1Projective, HPProtocol<Bls12_381>>::setup(
+            &params, &mut rng1, &mut rng2,
+        )
+        .unwrap()
+        .crs;
+
+        let value = Integer::from(Integer::u_pow_u(2, params.hash_to_prime_bits as u32))
+            - &Integer::from(245);
+        let randomness = Integer::from(5);
+        let accum =
+            accumulator::Accumulator::<Rsa2048, Integer, AccumulatorWithoutHashToPrime>::empty()
+                .add_with_proof(&[value.clone()]);
+        let statement = || Statement {
+            c_p: accum.0.value.clone(),
+            c_e_q: old_crs
+                .crs_modeq
+                .pedersen_commitment_parameters
+                .commit(&value, &randomness)
+                .unwrap(),
+        };
+        let witness = || Witness {
+            e: value.clone(),
+            r_q: randomness.clone(),
+            w: accum.1.witness.0.value.clone(),
+        };
+
+        assert!(matches!(
+            migrate_membership_proof(
+                &old_crs,
+                &old_crs,
+                statement(),
+                witness(),
+                b"membership",
+                b"",
+                &mut rng1,
+                &mut rng2,
+            ),
+            Err(MigrationError::SameCrs)
+        ));
+
+        let migrated = migrate_membership_proof(
+            &old_crs,
+            &new_crs,
+            statement(),
+            witness(),
+            b"membership",
+            b"chain 1",
+            &mut rng1,
+            &mut rng2,
+        )
+        .unwrap();
+        new_crs
+            .crs_modeq
+            .pedersen_commitment_parameters
+            .open(
+                &migrated.statement.c_e_q,
+                &migrated.witness.e,
+                &migrated.witness.r_q,
+            )
+            .unwrap();
+
+        let mut registry =
+            CrsRegistry::<Protocol<Rsa2048, G1Projective, HPProtocol<Bls12_381>>>::new(
+                b"membership",
+            );
+        registry.register(Protocol::from_crs(&new_crs)).unwrap();
+        registry
+            .verify_any(&migrated.statement, &migrated.proof, b"chain 1")
+            .unwrap();
+        assert!(registry
+            .verify_any(&migrated.statement, &migrated.proof, b"chain 2")
+            .is_err());
+    }
+}
@@ -0,0 +1,775 @@
+//! Membership and nonmembership with the statement commitment and the
+//! hash-to-prime proof on different curves, e.g. a Bulletproofs range proof
+//! over Ristretto for an element committed on BLS12-381, where downstream
+//! SNARKs can consume the commitment.
+//!
+//! The statement commits to the element in `c_e_q` on `P`. The prover commits
+//! to it again in `c_e_bridge` on `Q`, the curve of the hash-to-prime
+//! protocol, and bridges the two with a modeq proof for each curve against the
+//! same integer commitment `c_e`. Both modeq proofs share the integer
+//! commitment parameters, so they show the two commitments hold the element
+//! committed in `c_e`, reduced modulo the order of their curve. The parameters
+//! have to be valid for the smaller of the two fields.
+//!
+//! The same bridge commits in G2 of a pairing engine while the hash-to-prime
+//! proof stays in G1, where the LegoGroth16 link bases live, for downstream
+//! aggregation that expects commitments in G2.
+//!
+//! Proofs are non-interactive, over a transcript.
+use crate::{
+    commitments::{integer::IntegerCommitment, pedersen::PedersenCommitment, Commitment},
+    parameters::{Parameters, SubProtocol},
+    protocols::{
+        coprime::{
+            transcript::{
+                TranscriptProtocolCoprime,
+                TranscriptProverChannel as CoprimeTranscriptProverChannel,
+                TranscriptVerifierChannel as CoprimeTranscriptVerifierChannel,
+            },
+            CRSCoprime, Proof as CoprimeProof, Protocol as CoprimeProtocol,
+            Statement as CoprimeStatement, Witness as CoprimeWitness,
+        },
+        hash_to_prime::{
+            transcript::{
+                TranscriptProtocolHashToPrime,
+                TranscriptProverChannel as HashToPrimeTranscriptProverChannel,
+                TranscriptVerifierChannel as HashToPrimeTranscriptVerifierChannel,
+            },
+            validate_element_size, validate_witness, CRSHashToPrime, HashToPrimeProtocol,
+            Statement as HashToPrimeStatement, Witness as HashToPrimeWitness,
+        },
+        membership::{Statement as MembershipStatement, Witness as MembershipWitness},
+        modeq::{
+            transcript::{
+                TranscriptProtocolModEq, TranscriptProverChannel as ModEqTranscriptProverChannel,
+                TranscriptVerifierChannel as ModEqTranscriptVerifierChannel,
+            },
+            CRSModEq, Proof as ModEqProof, Protocol as ModEqProtocol, Statement as ModEqStatement,
+            Witness as ModEqWitness,
+        },
+        nonmembership::{Statement as NonMembershipStatement, Witness as NonMembershipWitness},
+        root::{
+            transcript::{
+                TranscriptProtocolRoot, TranscriptProverChannel as RootTranscriptProverChannel,
+                TranscriptVerifierChannel as RootTranscriptVerifierChannel,
+            },
+            CRSRoot, Proof as RootProof, Protocol as RootProtocol, Statement as RootStatement,
+            Witness as RootWitness,
+        },
+        ProofError, SetupError, VerificationError,
+    },
+    transcript::{versioned_domain_sep, TranscriptProtocolCurve, TranscriptProtocolInteger},
+    utils::{
+        bigint_to_integer,
+        curve::{CurvePointProjective, Field},
+        random_between, ConvertibleUnknownOrderGroup,
+    },
+};
+use merlin::Transcript;
+use rand::{CryptoRng, RngCore};
+use rug::rand::MutRandState;
+use rug::Integer;
+use std::cell::RefCell;
+
+pub trait TranscriptProtocolMixed<G: ConvertibleUnknownOrderGroup, Q: CurvePointProjective>:
+    TranscriptProtocolInteger<G> + TranscriptProtocolCurve<Q>
+{
+    fn mixed_domain_sep(&mut self);
+}
+
+impl<G: ConvertibleUnknownOrderGroup, Q: CurvePointProjective> TranscriptProtocolMixed<G, Q>
+    for Transcript
+{
+    fn mixed_domain_sep(&mut self) {
+        self.append_message(b"dom-sep", &versioned_domain_sep(b"mixed"));
+    }
+}
+
+pub struct CRS<
+    G: ConvertibleUnknownOrderGroup,
+    P: CurvePointProjective,
+    Q: CurvePointProjective,
+    HP: HashToPrimeProtocol<Q>,
+> {
+    // G contains the information about Z^*_N
+    pub parameters: Parameters,
+    pub crs_root: CRSRoot<G>,
+    pub crs_coprime: CRSCoprime<G>,
+    /// Links `c_e` to the statement commitment on `P`.
+    pub crs_modeq: CRSModEq<G, P>,
+    /// Links `c_e` to the commitment on `Q` the hash-to-prime proof is about.
+    pub crs_modeq_bridge: CRSModEq<G, Q>,
+    pub crs_hash_to_prime: CRSHashToPrime<Q, HP>,
+}
+
+impl<
+        G: ConvertibleUnknownOrderGroup,
+        P: CurvePointProjective,
+        Q: CurvePointProjective,
+        HP: HashToPrimeProtocol<Q>,
+    > Clone for CRS<G, P, Q, HP>
+{
+    fn clone(&self) -> Self {
+        Self {
+            parameters: self.parameters.clone(),
+            crs_root: self.crs_root.clone(),
+            crs_coprime: self.crs_coprime.clone(),
+            crs_modeq: self.crs_modeq.clone(),
+            crs_modeq_bridge: self.crs_modeq_bridge.clone(),
+            crs_hash_to_prime: self.crs_hash_to_prime.clone(),
+        }
+    }
+}
+
+pub struct Protocol<
+    G: ConvertibleUnknownOrderGroup,
+    P: CurvePointProjective,
+    Q: CurvePointProjective,
+    HP: HashToPrimeProtocol<Q>,
+> {
+    pub crs: CRS<G, P, Q, HP>,
+}
+
+/// The proofs linking `c_e` to the statement commitment and to the element.
+pub struct BridgeProof<
+    G: ConvertibleUnknownOrderGroup,
+    P: CurvePointProjective,
+    Q: CurvePointProjective,
+    HP: HashToPrimeProtocol<Q>,
+> {
+    pub c_e_bridge: <PedersenCommitment<Q> as Commitment>::Instance,
+    pub proof_modeq: ModEqProof<G, P>,
+    pub proof_modeq_bridge: ModEqProof<G, Q>,
+    pub proof_hash_to_prime: HP::Proof,
+}
+
+impl<
+        G: ConvertibleUnknownOrderGroup,
+        P: CurvePointProjective,
+        Q: CurvePointProjective,
+        HP: HashToPrimeProtocol<Q>,
+    > Clone for BridgeProof<G, P, Q, HP>
+{
+    fn clone(&self) -> Self {
+        Self {
+            c_e_bridge: self.c_e_bridge.clone(),
+            proof_modeq: self.proof_modeq.clone(),
+            proof_modeq_bridge: self.proof_modeq_bridge.clone(),
+            proof_hash_to_prime: self.proof_hash_to_prime.clone(),
+        }
+    }
+}
+
+pub struct MembershipProof<
+    G: ConvertibleUnknownOrderGroup,
+    P: CurvePointProjective,
+    Q: CurvePointProjective,
+    HP: HashToPrimeProtocol<Q>,
+> {
+    pub c_e: <IntegerCommitment<G> as Commitment>::Instance,
+    pub proof_root: RootProof<G>,
+    pub bridge: BridgeProof<G, P, Q, HP>,
+}
+
+impl<
+        G: ConvertibleUnknownOrderGroup,
+        P: CurvePointProjective,
+        Q: CurvePointProjective,
+        HP: HashToPrimeProtocol<Q>,
+    > Clone for MembershipProof<G, P, Q, HP>
+{
+    fn clone(&self) -> Self {
+        Self {
+            c_e: self.c_e.clone(),
+            proof_root: self.proof_root.clone(),
+            bridge: self.bridge.clone(),
+        }
+    }
+}
+
+pub struct NonMembershipProof<
+    G: ConvertibleUnknownOrderGroup,
+    P: CurvePointProjective,
+    Q: CurvePointProjective,
+    HP: HashToPrimeProtocol<Q>,
+> {
+    pub c_e: <IntegerCommitment<G> as Commitment>::Instance,
+    pub proof_coprime: CoprimeProof<G>,
+    pub bridge: BridgeProof<G, P, Q, HP>,
+}
+
+impl<
+        G: ConvertibleUnknownOrderGroup,
+        P: CurvePointProjective,
+        Q: CurvePointProjective,
+        HP: HashToPrimeProtocol<Q>,
+    > Clone for NonMembershipProof<G, P, Q, HP>
+{
+    fn clone(&self) -> Self {
+        Self {
+            c_e: self.c_e.clone(),
+            proof_coprime: self.proof_coprime.clone(),
+            bridge: self.bridge.clone(),
+        }
+    }
+}
+
+/// The integer commitment `c_e` to the prime, its opening and the
+/// commitment to the prime on `Q` with its randomness.
+struct Commitments<G: ConvertibleUnknownOrderGroup, Q: CurvePointProjective> {
+    c_e: <IntegerCommitment<G> as Commitment>::Instance,
+    hashed_e: Integer,
+    r: Integer,
+    c_e_bridge: <PedersenCommitment<Q> as Commitment>::Instance,
+    r_q_bridge: Integer,
+}
+
+/// Parameters for a modeq CRS on a curve with a scalar field of `F`.
+fn for_field<F: Field>(parameters: &Parameters) -> Parameters {
+    let mut parameters = parameters.for_sub_protocol(SubProtocol::ModEq);
+    parameters.field_size_bits = F::size_in_bits() as u16;
+    parameters
+}
+
+impl<
+        G: ConvertibleUnknownOrderGroup,
+        P: CurvePointProjective,
+        Q: CurvePointProjective,
+        HP: HashToPrimeProtocol<Q>,
+    > Protocol<G, P, Q, HP>
+{
+    /// Performs setup with `parameters` valid for the smaller of the scalar
+    /// fields of `P` and `Q`.
+    pub fn setup<R1: MutRandState, R2: RngCore + CryptoRng>(
+        parameters: &Parameters,
+        rng1: &mut R1,
+        rng2: &mut R2,
+    ) -> Result<Protocol<G, P, Q, HP>, SetupError> {
+        let field_size_bits = parameters.field_size_bits as usize;
+        if field_size_bits > P::ScalarField::size_in_bits()
+            || field_size_bits > Q::ScalarField::size_in_bits()
+        {
+            return Err(SetupError::CouldNotPerformSetup);
+        }
+        let integer_commitment_parameters = IntegerCommitment::<G>::setup(rng1);
+        let pedersen_commitment_parameters = PedersenCommitment::<P>::setup(rng2);
+        let bridge_commitment_parameters = PedersenCommitment::<Q>::setup(rng2);
+        let hash_to_prime_parameters = HP::setup(
+            rng2,
+            &bridge_commitment_parameters,
+            &parameters.for_sub_protocol(SubProtocol::HashToPrime),
+        )?;
+        Ok(Protocol {
+            crs: CRS::<G, P, Q, HP> {
+                parameters: parameters.clone(),
+                crs_root: CRSRoot::<G> {
+                    parameters: parameters.for_sub_protocol(SubProtocol::Root),
+                    integer_commitment_parameters: integer_commitment_parameters.clone(),
+                },
+                crs_coprime: CRSCoprime::<G> {
+                    parameters: parameters.for_sub_protocol(SubProtocol::Coprime),
+                    integer_commitment_parameters: integer_commitment_parameters.clone(),
+                },
+                crs_modeq: CRSModEq::<G, P> {
+                    parameters: for_field::<P::ScalarField>(parameters),
+                    integer_commitment_parameters: integer_commitment_parameters.clone(),
+                    pedersen_commitment_parameters,
+                },
+                crs_modeq_bridge: CRSModEq::<G, Q> {
+                    parameters: for_field::<Q::ScalarField>(parameters),
+                    integer_commitment_parameters,
+                    pedersen_commitment_parameters: bridge_commitment_parameters.clone(),
+                },
+                crs_hash_to_prime: CRSHashToPrime::<Q, HP> {
+                    parameters: parameters.for_sub_protocol(SubProtocol::HashToPrime),
+                    pedersen_commitment_parameters: bridge_commitment_parameters,
+                    hash_to_prime_parameters,
+                },
+            },
+        })
+    }
+
+    pub fn from_crs(crs: &CRS<G, P, Q, HP>) -> Protocol<G, P, Q, HP> {
+        Protocol { crs: crs.clone() }
+    }
+
+    pub fn hash_to_prime(&self, e: &Integer) -> Result<(Integer, u64), ProofError> {
+        let hash_to_prime = HP::from_crs(&self.crs.crs_hash_to_prime);
+        Ok(hash_to_prime.hash_to_prime(e)?)
+    }
+
+    /// Commits to the prime of `e` in `c_e` and `c_e_bridge`, and adds both to
+    /// the transcript, after checking `e` and `r_q` are in range.
+    fn commit<R1: MutRandState, R2: RngCore + CryptoRng, T: TranscriptProtocolMixed<G, Q>>(
+        &self,
+        transcript: &RefCell<T>,
+        rng1: &mut R1,
+        rng2: &mut R2,
+        e: &Integer,
+        r_q: &Integer,
+    ) -> Result<Commitments<G, Q>, ProofError> {
+        validate_element_size::<Q, HP>(&self.crs.parameters, e)?;
+        let (hashed_e, _) = self.hash_to_prime(e)?;
+        validate_witness::<P>(&self.crs.parameters, &hashed_e, r_q)?;
+        let r = random_between(rng1, &Integer::from(0), &G::order_upper_bound());
+        let c_e = self
+            .crs
+            .crs_root
+            .integer_commitment_parameters
+            .commit(&hashed_e, &r)?;
+        let r_q_bridge = bigint_to_integer::<Q>(&Q::ScalarField::rand(rng2));
+        let c_e_bridge = self
+            .crs
+            .crs_modeq_bridge
+            .pedersen_commitment_parameters
+            .commit(&hashed_e, &r_q_bridge)?;
+        self.absorb(transcript, &c_e, &c_e_bridge)
+            .map_err(|_| ProofError::CouldNotCreateProof)?;
+        Ok(Commitments {
+            c_e,
+            hashed_e,
+            r,
+            c_e_bridge,
+            r_q_bridge,
+        })
+    }
+
+    fn absorb<T: TranscriptProtocolMixed<G, Q>>(
+        &self,
+        transcript: &RefCell<T>,
+        c_e: &G::Elem,
+        c_e_bridge: &Q,
+    ) -> Result<(), VerificationError> {
+        let mut transcript = transcript
+            .try_borrow_mut()
+            .map_err(|_| VerificationError::VerificationFailed)?;
+        transcript.mixed_domain_sep();
+        transcript.append_integer_point(b"c_e", c_e);
+        transcript.append_curve_point(b"c_e_bridge", c_e_bridge)?;
+        Ok(())
+    }
+
+    /// Proves both modeq proofs and the hash-to-prime proof on `Q`.
+    #[allow(clippy::too_many_arguments)]
+    fn prove_bridge<
+        R1: MutRandState,
+        R2: RngCore + CryptoRng,
+        T: TranscriptProtocolModEq<G, P>
+            + TranscriptProtocolModEq<G, Q>
+            + TranscriptProtocolHashToPrime<Q>,
+    >(
+        &self,
+        transcript: &RefCell<T>,
+        rng1: &mut R1,
+        rng2: &mut R2,
+        c_e_q: &<PedersenCommitment<P> as Commitment>::Instance,
+        e: &Integer,
+        r_q: &Integer,
+        commitments: Commitments<G, Q>,
+    ) -> Result<BridgeProof<G, P, Q, HP>, ProofError> {
+        let modeq = ModEqProtocol::from_crs(&self.crs.crs_modeq);
+        let mut modeq_channel =
+            ModEqTranscriptVerifierChannel::new(&self.crs.crs_modeq, transcript);
+        modeq.prove(
+            &mut modeq_channel,
+            rng1,
+            rng2,
+            &ModEqStatement {
+                c_e: commitments.c_e.clone(),
+                c_e_q: c_e_q.clone(),
+            },
+            &ModEqWitness {
+                e: commitments.hashed_e.clone(),
+                r: commitments.r.clone(),
+                r_q: r_q.clone(),
+            },
+        )?;
+        let modeq_bridge = ModEqProtocol::from_crs(&self.crs.crs_modeq_bridge);
+        let mut modeq_bridge_channel =
+            ModEqTranscriptVerifierChannel::new(&self.crs.crs_modeq_bridge, transcript);
+        modeq_bridge.prove(
+            &mut modeq_bridge_channel,
+            rng1,
+            rng2,
+            &ModEqStatement {
+                c_e: commitments.c_e,
+                c_e_q: commitments.c_e_bridge.clone(),
+            },
+            &ModEqWitness {
+                e: commitments.hashed_e,
+                r: commitments.r,
+                r_q: commitments.r_q_bridge.clone(),
+            },
+        )?;
+        let hash_to_prime = HP::from_crs(&self.crs.crs_hash_to_prime);
+        let mut hash_to_prime_channel =
+            HashToPrimeTranscriptVerifierChannel::new(&self.crs.crs_hash_to_prime, transcript);
+        hash_to_prime.prove(
+            &mut hash_to_prime_channel,
+            rng2,
+            &HashToPrimeStatement {
+                c_e_q: commitments.c_e_bridge.clone(),
+            },
+            &HashToPrimeWitness {
+                e: e.clone(),
+                r_q: commitments.r_q_bridge,
+            },
+        )?;
+
+        let incomplete = |_| ProofError::CouldNotCreateProof;
+        Ok(BridgeProof {
+            c_e_bridge: commitments.c_e_bridge,
+            proof_modeq: modeq_channel.proof().map_err(incomplete)?,
+            proof_modeq_bridge: modeq_bridge_channel.proof().map_err(incomplete)?,
+            proof_hash_to_prime: hash_to_prime_channel.proof().map_err(incomplete)?,
+        })
+    }
+
+    fn verify_bridge<
+        T: TranscriptProtocolModEq<G, P>
+            + TranscriptProtocolModEq<G, Q>
+            + TranscriptProtocolHashToPrime<Q>,
+    >(
+        &self,
+        transcript: &RefCell<T>,
+        c_e: &G::Elem,
+        c_e_q: &<PedersenCommitment<P> as Commitment>::Instance,
+        bridge: &BridgeProof<G, P, Q, HP>,
+    ) -> Result<(), VerificationError> {
+        let modeq = ModEqProtocol::from_crs(&self.crs.crs_modeq);
+        let mut modeq_channel =
+            ModEqTranscriptProverChannel::new(&self.crs.crs_modeq, transcript, &bridge.proof_modeq);
+        modeq.verify(
+            &mut modeq_channel,
+            &ModEqStatement {
+                c_e: c_e.clone(),
+                c_e_q: c_e_q.clone(),
+            },
+        )?;
+        let modeq_bridge = ModEqProtocol::from_crs(&self.crs.crs_modeq_bridge);
+        let mut modeq_bridge_channel = ModEqTranscriptProverChannel::new(
+            &self.crs.crs_modeq_bridge,
+            transcript,
+            &bridge.proof_modeq_bridge,
+        );
+        modeq_bridge.verify(
+            &mut modeq_bridge_channel,
+            &ModEqStatement {
+                c_e: c_e.clone(),
+                c_e_q: bridge.c_e_bridge.clone(),
+            },
+        )?;
+        let hash_to_prime = HP::from_crs(&self.crs.crs_hash_to_prime);
+        let mut hash_to_prime_channel = HashToPrimeTranscriptProverChannel::new(
+            &self.crs.crs_hash_to_prime,
+            transcript,
+            &bridge.proof_hash_to_prime,
+        );
+        hash_to_prime.verify(
+            &mut hash_to_prime_channel,
+            &HashToPrimeStatement {
+                c_e_q: bridge.c_e_bridge.clone(),
+            },
+        )
+    }
+
+    pub fn prove_membership<
+        R1: MutRandState,
+        R2: RngCore + CryptoRng,
+        T: TranscriptProtocolMixed<G, Q>
+            + TranscriptProtocolRoot<G>
+            + TranscriptProtocolModEq<G, P>
+            + TranscriptProtocolModEq<G, Q>
+            + TranscriptProtocolHashToPrime<Q>,
+    >(
+        &self,
+        transcript: &RefCell<T>,
+        rng1: &mut R1,
+        rng2: &mut R2,
+        statement: &MembershipStatement<G, P>,
+        witness: &MembershipWitness<G>,
+    ) -> Result<MembershipProof<G, P, Q, HP>, ProofError> {
+        let commitments = self.commit(transcript, rng1, rng2, &witness.e, &witness.r_q)?;
+        let root = RootProtocol::from_crs(&self.crs.crs_root)?;
+        let mut root_channel = RootTranscriptVerifierChannel::new(&self.crs.crs_root, transcript);
+        root.prove(
+            &mut root_channel,
+            rng1,
+            &RootStatement {
+                c_e: commitments.c_e.clone(),
+                acc: statement.c_p.clone(),
+            },
+            &RootWitness {
+                e: commitments.hashed_e.clone(),
+                r: commitments.r.clone(),
+                w: witness.w.clone(),
+            },
+        )?;
+        let proof_root = root_channel
+            .proof()
+            .map_err(|_| ProofError::CouldNotCreateProof)?;
+        let c_e = commitments.c_e.clone();
+        let bridge = self.prove_bridge(
+            transcript,
+            rng1,
+            rng2,
+            &statement.c_e_q,
+            &witness.e,
+            &witness.r_q,
+            commitments,
+        )?;
+
+        Ok(MembershipProof {
+            c_e,
+            proof_root,
+            bridge,
+        })
+    }
+
+    pub fn verify_membership<
+        T: TranscriptProtocolMixed<G, Q>
+            + TranscriptProtocolRoot<G>
+            + TranscriptProtocolModEq<G, P>
+            + TranscriptProtocolModEq<G, Q>
+            + TranscriptProtocolHashToPrime<Q>,
+    >(
+        &self,
+        transcript: &RefCell<T>,
+        statement: &MembershipStatement<G, P>,
+        proof: &MembershipProof<G, P, Q, HP>,
+    ) -> Result<(), VerificationError> {
+        self.absorb(transcript, &proof.c_e, &proof.bridge.c_e_bridge)?;
+        let root = RootProtocol::from_crs(&self.crs.crs_root)?;
+        let mut root_channel =
+            RootTranscriptProverChannel::new(&self.crs.crs_root, transcript, &proof.proof_root);
+        root.verify(
+            &mut root_channel,
+            &RootStatement {
+                c_e: proof.c_e.clone(),
+                acc: statement.c_p.clone(),
+            },
+        )?;
+        self.verify_bridge(transcript, &proof.c_e, &statement.c_e_q, &proof.bridge)
+    }
+
+    pub fn prove_nonmembership<
+        R1: MutRandState,
+        R2: RngCore + CryptoRng,
+        T: TranscriptProtocolMixed<G, Q>
+            + TranscriptProtocolCoprime<G>
+            + TranscriptProtocolModEq<G, P>
+            + TranscriptProtocolModEq<G, Q>
+            + TranscriptProtocolHashToPrime<Q>,
+    >(
+        &self,
+        transcript: &RefCell<T>,
+        rng1: &mut R1,
+        rng2: &mut R2,
+        statement: &NonMembershipStatement<G, P>,
+        witness: &NonMembershipWitness<G>,
+    ) -> Result<NonMembershipProof<G, P, Q, HP>, ProofError> {
+        let commitments = self.commit(transcript, rng1, rng2, &witness.e, &witness.r_q)?;
+        let coprime = CoprimeProtocol::from_crs(&self.crs.crs_coprime)?;
+        let mut coprime_channel =
+            CoprimeTranscriptVerifierChannel::new(&self.crs.crs_coprime, transcript);
+        coprime.prove(
+            &mut coprime_channel,
+            rng1,
+            &CoprimeStatement {
+                c_e: commitments.c_e.clone(),
+                acc: statement.c_p.clone(),
+            },
+            &CoprimeWitness {
+                e: commitments.hashed_e.clone(),
+                r: commitments.r.clone(),
+                d: witness.d.clone(),
+                b: witness.b.clone(),
+            },
+        )?;
+        let proof_coprime = coprime_channel
+            .proof()
+            .map_err(|_| ProofError::CouldNotCreateProof)?;
+        let c_e = commitments.c_e.clone();
+        let bridge = self.prove_bridge(
+            transcript,
+            rng1,
+            rng2,
+            &statement.c_e_q,
+            &witness.e,
+            &witness.r_q,
+            commitments,
+        )?;
+
+        Ok(NonMembershipProof {
+            c_e,
+            proof_coprime,
+            bridge,
+        })
+    }
+
+    pub fn verify_nonmembership<
+        T: TranscriptProtocolMixed<G, Q>
+            + TranscriptProtocolCoprime<G>
+            + TranscriptProtocolModEq<G, P>
+            + TranscriptProtocolModEq<G, Q>
+            + TranscriptProtocolHashToPrime<Q>,
+    >(
+        &self,
+        transcript: &RefCell<T>,
+        statement: &NonMembershipStatement<G, P>,
+        proof: &NonMembershipProof<G, P, Q, HP>,
+    ) -> Result<(), VerificationError> {
+        self.absorb(transcript, &proof.c_e, &proof.bridge.c_e_bridge)?;
+        let coprime = CoprimeProtocol::from_crs(&self.crs.crs_coprime)?;
+        let mut coprime_channel = CoprimeTranscriptProverChannel::new(
+            &self.crs.crs_coprime,
+            transcript,
+            &proof.proof_coprime,
+        );
+        coprime.verify(
+            &mut coprime_channel,
+            &CoprimeStatement {
+                c_e: proof.c_e.clone(),
+                acc: statement.c_p.clone(),
+            },
+        )?;
+        self.verify_bridge(transcript, &proof.c_e, &statement.c_e_q, &proof.bridge)
+    }
+}
+
+#[cfg(all(test, feature = "arkworks"))]
+mod test {
+    use super::Protocol;
+    #[cfg(feature = "dalek")]
+    use crate::protocols::hash_to_prime::bp::Protocol as HPProtocol;
+    use crate::{
+        commitments::Commitment,
+        parameters::Parameters,
+        protocols::{
+            hash_to_prime::snark_range::Protocol as SnarkRangeProtocol,
+            membership::{Statement, Witness},
+        },
+    };
+    use accumulator::{group::Rsa2048, AccumulatorWithoutHashToPrime};
+    use ark_bls12_381::{Bls12_381, G1Projective, G2Projective};
+    #[cfg(feature = "dalek")]
+    use curve25519_dalek::{ristretto::RistrettoPoint, scalar::Scalar};
+    use merlin::Transcript;
+    use rand::thread_rng;
+    use rug::rand::RandState;
+    use rug::Integer;
+    use std::cell::RefCell;
+
+    #[cfg(feature = "dalek")]
+    #[test]
+    fn test_mixed_membership() {
+        let params = Parameters::from_curve::<Scalar>().unwrap().0;
+        let mut rng1 = RandState::new();
+        rng1.seed(&Integer::from(13));
+        let mut rng2 = thread_rng();
+
+        let protocol = Protocol::<Rsa2048, G1Projective, RistrettoPoint, HPProtocol>::setup(
+            &params, &mut rng1, &mut rng2,
+        )
+        .unwrap();
+
+        let value = Integer::from(Integer::u_pow_u(2, params.hash_to_prime_bits as u32))
+            - &Integer::from(129);
+        let randomness = Integer::from(5);
+        let accum =
+            accumulator::Accumulator::<Rsa2048, Integer, AccumulatorWithoutHashToPrime>::empty()
+                .add_with_proof(&[value.clone()]);
+        let statement = Statement {
+            c_p: accum.0.value,
+            c_e_q: protocol
+                .crs
+                .crs_modeq
+                .pedersen_commitment_parameters
+                .commit(&value, &randomness)
+                .unwrap(),
+        };
+
+        let proof_transcript = RefCell::new(Transcript::new(b"mixed"));
+        let proof = protocol
+            .prove_membership(
+                &proof_transcript,
+                &mut rng1,
+                &mut rng2,
+                &statement,
+                &Witness {
+                    e: value.clone(),
+                    r_q: randomness,
+                    w: accum.1.witness.0.value,
+                },
+            )
+            .unwrap();
+
+        let verification_transcript = RefCell::new(Transcript::new(b"mixed"));
+        protocol
+            .verify_membership(&verification_transcript, &statement, &proof)
+            .unwrap();
+
+        // the statement commitment has to hold the same element
+        let mut other_statement = statement;
+        other_statement.c_e_q = protocol
+            .crs
+            .crs_modeq
+            .pedersen_commitment_parameters
+            .commit(&(value - 2), &Integer::from(5))
+            .unwrap();
+        let verification_transcript = RefCell::new(Transcript::new(b"mixed"));
+        assert!(protocol
+            .verify_membership(&verification_transcript, &other_statement, &proof)
+            .is_err());
+    }
+
+    #[test]
+    fn test_g2_commitment() {
+        let params = Parameters::from_security_level(128).unwrap();
+        let mut rng1 = RandState::new();
+        rng1.seed(&Integer::from(13));
+        let mut rng2 = thread_rng();
+
+        let protocol =
+            Protocol::<Rsa2048, G2Projective, G1Projective, SnarkRangeProtocol<Bls12_381>>::setup(
+                &params, &mut rng1, &mut rng2,
+            )
+            .unwrap();
+
+        let value = Integer::from(Integer::u_pow_u(2, params.hash_to_prime_bits as u32))
+            - &Integer::from(245);
+        let randomness = Integer::from(5);
+        let accum =
+            accumulator::Accumulator::<Rsa2048, Integer, AccumulatorWithoutHashToPrime>::empty()
+                .add_with_proof(&[value.clone()]);
+        let statement = Statement {
+            c_p: accum.0.value,
+            c_e_q: protocol
+                .crs
+                .crs_modeq
+                .pedersen_commitment_parameters
+                .commit(&value, &randomness)
+                .unwrap(),
+        };
+
+        let proof_transcript = RefCell::new(Transcript::new(b"mixed"));
+        let proof = protocol
+            .prove_membership(
+                &proof_transcript,
+                &mut rng1,
+                &mut rng2,
+                &statement,
+                &Witness {
+                    e: value,
+                    r_q: randomness,
+                    w: accum.1.witness.0.value,
+                },
+            )
+            .unwrap();
+
+        let verification_transcript = RefCell::new(Transcript::new(b"mixed"));
+        protocol
+            .verify_membership(&verification_transcript, &statement, &proof)
+            .unwrap();
+    }
+}
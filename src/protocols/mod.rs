@@ -1,30 +1,86 @@
 //! All the protocol implementations.
 
 use crate::{
-    channels::ChannelError, commitments::CommitmentError,
-    protocols::hash_to_prime::HashToPrimeError,
+    channels::ChannelError,
+    commitments::CommitmentError,
+    parameters::Parameters,
+    protocols::{boxed::CodecError, hash_to_prime::HashToPrimeError},
+    utils::{curve::CurveError, ConvertibleUnknownOrderGroup},
 };
 use ark_relations::r1cs::SynthesisError;
 use rug::Integer;
 
+pub mod accumulator_digest;
+pub mod anchor;
+pub mod audit;
+pub mod augmentation;
+pub mod blinding;
+pub mod boxed;
+pub mod cache;
+pub mod context;
 pub mod coprime;
+pub mod explain;
+pub mod external;
+#[cfg(any(test, feature = "testing"))]
+#[doc(hidden)]
+pub mod extraction;
+#[cfg(all(feature = "arkworks", feature = "prover"))]
+pub mod gadgets;
 pub mod hash_to_prime;
+pub mod intersection;
+pub mod linking;
 pub mod membership;
+#[cfg(feature = "prover")]
+pub mod migration;
+pub mod mixed;
 pub mod modeq;
 pub mod nonmembership;
+pub mod one_of;
+pub mod pokcp;
+pub mod poke;
+pub mod presentation;
+pub mod progress;
+pub mod registry;
+pub mod replay;
 pub mod root;
+pub mod security;
+#[doc(hidden)]
+pub mod simulation;
+pub mod sizes;
+pub mod statement;
+pub mod verifier;
 
 quick_error! {
     #[derive(Debug)]
     pub enum CRSError {
         InvalidParameters {}
+        UnsupportedGroupForCoprime {}
     }
 }
 
+/// Checks the soundness security is supported by the challenge space of `G`.
+pub fn validate_soundness<G: ConvertibleUnknownOrderGroup>(
+    parameters: &Parameters,
+) -> Result<(), CRSError> {
+    let challenge_bound = Integer::from(Integer::u_pow_u(2, parameters.security_soundness as u32));
+    if challenge_bound >= G::soundness_bound() {
+        return Err(CRSError::InvalidParameters);
+    }
+
+    Ok(())
+}
+
 quick_error! {
     #[derive(Debug)]
     pub enum SetupError {
         CouldNotPerformSetup {}
+        Cancelled {}
+        UnsupportedParameters {}
+        UnsupportedGroupForCoprime {}
+        ProverNotEnabled {}
+        InvalidLinkBases(found: usize, expected: usize) {
+            display("{} link bases for a circuit needing {}", found, expected)
+        }
         SNARKError(err: SynthesisError) {
             from()
         }
@@ -34,18 +90,27 @@ quick_error! {
 #[cfg(feature = "dalek")]
 type R1CSError = bulletproofs::r1cs::R1CSError;
 
-#[cfg(feature = "arkworks")]
+#[cfg(not(feature = "dalek"))]
 quick_error! {
     #[derive(Debug)]
     pub enum DummyBPError {}
 }
-#[cfg(feature = "arkworks")]
+#[cfg(not(feature = "dalek"))]
 type R1CSError = DummyBPError;
 
 quick_error! {
     #[derive(Debug)]
     pub enum ProofError {
         CouldNotCreateProof {}
+        BlindingReuse {}
+        WitnessOutOfRange {}
+        ElementOutOfRange {}
+        ElementTooLarge(bits: u32, max_bits: u16) {
+            display("element has {} bits, at most {} are supported", bits, max_bits)
+        }
+        RandomnessNotReduced {}
+        ProverNotEnabled {}
+        SelfCheckFailed(err: Box<VerificationError>) {}
         CommitmentError(err: CommitmentError) {
             from()
         }
@@ -58,6 +123,9 @@ quick_error! {
         VerifierChannelError(err: ChannelError) {
             from()
         }
+        CurveError(err: CurveError) {
+            from()
+        }
         PrimeError(err: HashToPrimeError) {
             from()
         }
@@ -67,6 +135,9 @@ quick_error! {
         CRSInitError(err: CRSError) {
             from()
         }
+        CodecError(err: CodecError) {
+            from()
+        }
     }
 }
 
@@ -74,6 +145,19 @@ quick_error! {
     #[derive(Debug)]
     pub enum VerificationError {
         VerificationFailed {}
+        ChallengeMismatch {}
+        StaleEpoch {}
+        UnknownEpoch {}
+        AccumulatorMismatch {}
+        UnsupportedVersion {}
+        UnknownCrs {}
+        WrongType {}
+        WrongAccumulatorOpening {}
+        MissingStatement {}
+        StatementMismatch {}
+        Replayed {}
+        VerifierChallengeMismatch {}
+        CrsMismatch {}
         CommitmentError(err: CommitmentError) {
             from()
         }
@@ -86,11 +170,17 @@ quick_error! {
         ProverChannelError(err: ChannelError) {
             from()
         }
+        CurveError(err: CurveError) {
+            from()
+        }
         BPError(err: R1CSError) {
             from()
         }
         CRSInitError(err: CRSError) {
             from()
         }
+        CodecError(err: CodecError) {
+            from()
+        }
     }
 }
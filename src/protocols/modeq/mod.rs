@@ -2,19 +2,30 @@
 use crate::commitments::{integer::IntegerCommitment, pedersen::PedersenCommitment, Commitment};
 use crate::{
     parameters::Parameters,
-    protocols::{ProofError, VerificationError},
+    protocols::{
+        blinding::{BlindingHistory, BlindingRanges},
+        cache::CacheKeyBuilder,
+        statement::{hash_curve_point, StatementDigest, StatementHasher},
+        CRSError, ProofError, VerificationError,
+    },
     utils::{
-        bigint_to_integer,
-        curve::{CurvePointProjective, Field},
-        integer_mod_q, integer_to_bigint_mod_q, random_symmetric_range,
-        ConvertibleUnknownOrderGroup,
+        bigint_to_bytes, bigint_to_integer,
+        curve::{CurveError, CurvePointProjective, Field},
+        exp_signed, integer_mod_q, integer_to_bigint_mod_q, integer_to_signed_bytes,
+        random_symmetric_range, ConvertibleUnknownOrderGroup,
     },
 };
 use channel::{ModEqProverChannel, ModEqVerifierChannel};
 use rand::{CryptoRng, RngCore};
 use rug::{rand::MutRandState, Integer};
+#[cfg(feature = "compressed")]
+use std::cell::RefCell;
+use std::hash::{Hash, Hasher};
+#[cfg(feature = "compressed")]
+use transcript::TranscriptProtocolModEq;
 
 pub mod channel;
+pub mod reference;
 pub mod transcript;
 
 #[derive(Clone)]
@@ -30,6 +41,40 @@ pub struct Statement<G: ConvertibleUnknownOrderGroup, P: CurvePointProjective> {
     pub c_e_q: <PedersenCommitment<P> as Commitment>::Instance,
 }
 
+impl<G: ConvertibleUnknownOrderGroup, P: CurvePointProjective> Clone for Statement<G, P> {
+    fn clone(&self) -> Self {
+        Self {
+            c_e: self.c_e.clone(),
+            c_e_q: self.c_e_q.clone(),
+        }
+    }
+}
+
+impl<G: ConvertibleUnknownOrderGroup, P: CurvePointProjective> PartialEq for Statement<G, P> {
+    fn eq(&self, other: &Self) -> bool {
+        self.c_e == other.c_e && self.c_e_q == other.c_e_q
+    }
+}
+
+impl<G: ConvertibleUnknownOrderGroup, P: CurvePointProjective> Eq for Statement<G, P> {}
+
+impl<G: ConvertibleUnknownOrderGroup, P: CurvePointProjective> Hash for Statement<G, P> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.c_e.hash(state);
+        hash_curve_point(&self.c_e_q, state);
+    }
+}
+
+impl<G: ConvertibleUnknownOrderGroup, P: CurvePointProjective> Statement<G, P> {
+    /// Stable identifier of the statement, see `protocols::statement`.
+    pub fn digest(&self) -> Result<StatementDigest, CurveError> {
+        let mut hasher = StatementHasher::new(b"modeq");
+        hasher.append_group_element::<G>(b"c_e", &self.c_e);
+        hasher.append_curve_point(b"c_e_q", &self.c_e_q)?;
+        Ok(hasher.finish())
+    }
+}
+
 pub struct Witness {
     pub e: Integer,
     pub r: Integer,
@@ -55,6 +100,37 @@ pub struct Proof<G: ConvertibleUnknownOrderGroup, P: CurvePointProjective> {
     pub message2: Message2<P>,
 }
 
+impl<G: ConvertibleUnknownOrderGroup, P: CurvePointProjective> Proof<G, P> {
+    pub fn append_to_cache_key(&self, key: &mut CacheKeyBuilder) -> Result<(), CurveError> {
+        key.append_group_element::<G>(b"alpha1", &self.message1.alpha1);
+        key.append_curve_point(b"alpha2", &self.message1.alpha2)?;
+        key.append_integer(b"s_e", &self.message2.s_e);
+        key.append_integer(b"s_r", &self.message2.s_r);
+        key.append_curve_scalar::<P>(b"s_r_q", &self.message2.s_r_q);
+        Ok(())
+    }
+
+    /// Size in bytes, with group elements encoded by `G::elem_to_bytes`,
+    /// curve points in compressed affine form and integers by
+    /// `integer_to_signed_bytes`.
+    pub fn serialized_size(&self) -> Result<usize, CurveError> {
+        Ok(G::elem_to_bytes(&self.message1.alpha1).len()
+            + self.message1.alpha2.to_affine_bytes()?.len()
+            + integer_to_signed_bytes(&self.message2.s_e).len()
+            + integer_to_signed_bytes(&self.message2.s_r).len()
+            + bigint_to_bytes::<P>(&self.message2.s_r_q).len())
+    }
+}
+
+/// A proof without the alphas, which are recomputed from the challenge and
+/// the responses.
+#[cfg(feature = "compressed")]
+#[derive(Clone)]
+pub struct CompressedProof<P: CurvePointProjective> {
+    pub challenge: Integer,
+    pub message2: Message2<P>,
+}
+
 pub struct Protocol<G: ConvertibleUnknownOrderGroup, P: CurvePointProjective> {
     pub crs: CRSModEq<G, P>,
 }
@@ -65,29 +141,47 @@ impl<G: ConvertibleUnknownOrderGroup, P: CurvePointProjective> Protocol<G, P> {
     }
 
     pub fn prove<R1: MutRandState, R2: RngCore + CryptoRng, C: ModEqVerifierChannel<G, P>>(
+        &self,
+        verifier_channel: &mut C,
+        rng1: &mut R1,
+        rng2: &mut R2,
+        statement: &Statement<G, P>,
+        witness: &Witness,
+    ) -> Result<(), ProofError> {
+        self.prove_with_history(
+            verifier_channel,
+            rng1,
+            rng2,
+            statement,
+            witness,
+            &mut BlindingHistory::new(0),
+        )
+    }
+
+    /// Proves while recording the blinding values in `history`, failing if
+    /// they were used before.
+    pub fn prove_with_history<
+        R1: MutRandState,
+        R2: RngCore + CryptoRng,
+        C: ModEqVerifierChannel<G, P>,
+    >(
         &self,
         verifier_channel: &mut C,
         rng1: &mut R1,
         rng2: &mut R2,
         _: &Statement<G, P>,
         witness: &Witness,
+        history: &mut BlindingHistory,
     ) -> Result<(), ProofError> {
-        let r_e_range = Integer::from(Integer::u_pow_u(
-            2,
-            (self.crs.parameters.security_zk
-                + self.crs.parameters.security_soundness
-                + self.crs.parameters.hash_to_prime_bits) as u32,
-        ));
-        let r_e = random_symmetric_range(rng1, &r_e_range);
-        let r_r_range: Integer = G::order_upper_bound() / 2
-            * Integer::from(Integer::u_pow_u(
-                2,
-                (self.crs.parameters.security_zk + self.crs.parameters.security_soundness) as u32,
-            ));
-        let r_r = random_symmetric_range(rng1, &r_r_range);
-        assert!(self.crs.parameters.field_size_bits as usize >= P::ScalarField::size_in_bits());
+        let ranges = BlindingRanges::new::<G>(&self.crs.parameters);
+        let r_e = random_symmetric_range(rng1, &ranges.exponent);
+        let r_r = random_symmetric_range(rng1, &ranges.randomness_mask);
+        if (self.crs.parameters.field_size_bits as usize) < P::ScalarField::size_in_bits() {
+            return Err(CRSError::InvalidParameters.into());
+        }
         let r_r_q_field = P::ScalarField::rand(rng2);
         let r_r_q = bigint_to_integer::<P>(&r_r_q_field);
+        history.record(b"modeq", &[&r_e, &r_r, &r_r_q])?;
 
         let alpha1 = self.crs.integer_commitment_parameters.commit(&r_e, &r_r)?;
         let alpha2 = self
@@ -119,12 +213,43 @@ impl<G: ConvertibleUnknownOrderGroup, P: CurvePointProjective> Protocol<G, P> {
         let message1 = prover_channel.receive_message1()?;
         let c = prover_channel.generate_and_send_challenge()?;
         let message2 = prover_channel.receive_message2()?;
+        let expected_message1 = self.expected_message1(statement, &c, &message2)?;
+
+        // non-short-circuiting, as in root
+        if (expected_message1.alpha1 == message1.alpha1)
+            & (expected_message1.alpha2 == message1.alpha2)
+        {
+            Ok(())
+        } else {
+            Err(VerificationError::VerificationFailed)
+        }
+    }
+
+    /// Receives a proof without checking it, advancing `prover_channel`
+    /// through the same messages and challenge as `verify`.
+    pub fn receive_unverified<C: ModEqProverChannel<G, P>>(
+        &self,
+        prover_channel: &mut C,
+    ) -> Result<Proof<G, P>, VerificationError> {
+        let message1 = prover_channel.receive_message1()?;
+        prover_channel.generate_and_send_challenge()?;
+        let message2 = prover_channel.receive_message2()?;
+        Ok(Proof { message1, message2 })
+    }
 
+    /// Recomputes the alphas the verifier expects given the challenge and the
+    /// responses of the prover.
+    pub fn expected_message1(
+        &self,
+        statement: &Statement<G, P>,
+        c: &Integer,
+        message2: &Message2<P>,
+    ) -> Result<Message1<G, P>, VerificationError> {
         let commitment2 = self
             .crs
             .integer_commitment_parameters
             .commit(&message2.s_e, &message2.s_r)?;
-        let commitment2_extra = G::exp(&statement.c_e, &c);
+        let commitment2_extra = exp_signed::<G>(&statement.c_e, c);
         let expected_alpha1 = G::op(&commitment2, &commitment2_extra);
 
         let s_e_mod_q = integer_mod_q::<P>(&message2.s_e)?;
@@ -133,15 +258,48 @@ impl<G: ConvertibleUnknownOrderGroup, P: CurvePointProjective> Protocol<G, P> {
             .crs
             .pedersen_commitment_parameters
             .commit(&s_e_mod_q, &s_r_q_int)?;
-        let c_big = integer_to_bigint_mod_q::<P>(&c)?;
+        let c_big = integer_to_bigint_mod_q::<P>(c)?;
         let commitment1_extra = statement.c_e_q.mul(&c_big);
         let expected_alpha2 = commitment1.add(&commitment1_extra);
 
-        if expected_alpha1 == message1.alpha1 && expected_alpha2 == message1.alpha2 {
-            Ok(())
-        } else {
-            Err(VerificationError::VerificationFailed)
+        Ok(Message1 {
+            alpha1: expected_alpha1,
+            alpha2: expected_alpha2,
+        })
+    }
+
+    /// Restores the alphas of a compressed proof, so it can be verified as a
+    /// regular proof. Since the verifier rederives the challenge from the
+    /// restored alphas, a wrong challenge makes verification fail.
+    #[cfg(feature = "compressed")]
+    pub fn decompress(
+        &self,
+        statement: &Statement<G, P>,
+        proof: &CompressedProof<P>,
+    ) -> Result<Proof<G, P>, VerificationError> {
+        let message1 = self.expected_message1(statement, &proof.challenge, &proof.message2)?;
+        Ok(Proof {
+            message1,
+            message2: proof.message2.clone(),
+        })
+    }
+
+    /// Verifies a compressed proof, checking the challenge it contains is the
+    /// one derived from the transcript.
+    #[cfg(feature = "compressed")]
+    pub fn verify_compressed<T: TranscriptProtocolModEq<G, P>>(
+        &self,
+        transcript: &RefCell<T>,
+        statement: &Statement<G, P>,
+        proof: &CompressedProof<P>,
+    ) -> Result<(), VerificationError> {
+        let decompressed = self.decompress(statement, proof)?;
+        let c = decompressed.recompute_challenge(&self.crs, transcript)?;
+        if c != proof.challenge {
+            return Err(VerificationError::ChallengeMismatch);
         }
+
+        Ok(())
     }
 }
 
@@ -149,20 +307,32 @@ impl<G: ConvertibleUnknownOrderGroup, P: CurvePointProjective> Protocol<G, P> {
 mod test {
     use super::{Protocol, Statement, Witness};
     use crate::{
+        channels::ChannelError,
         commitments::Commitment,
         parameters::Parameters,
         protocols::{
             hash_to_prime::snark_range::Protocol as HPProtocol,
-            modeq::transcript::{TranscriptProverChannel, TranscriptVerifierChannel},
+            modeq::{
+                channel::{ModEqProverChannel, ModEqVerifierChannel},
+                reference::{self, Vector},
+                transcript::{TranscriptProverChannel, TranscriptVerifierChannel},
+            },
+            CRSError, ProofError,
+        },
+        transcript::TranscriptChannelError,
+        utils::{
+            curve::{CurvePointProjective, Field},
+            integer_mod_q,
         },
     };
-    use accumulator::group::Rsa2048;
+    use accumulator::group::{Rsa2048, UnknownOrderGroup};
     use ark_bls12_381::{Bls12_381, G1Projective};
     use merlin::Transcript;
     use rand::thread_rng;
     use rug::rand::RandState;
     use rug::Integer;
     use std::cell::RefCell;
+    use std::collections::HashSet;
 
     #[test]
     fn test_proof() {
@@ -221,5 +391,253 @@ mod test {
         let mut prover_channel =
             TranscriptProverChannel::new(&crs, &verification_transcript, &proof);
         protocol.verify(&mut prover_channel, &statement).unwrap();
+
+        // responses with a flipped sign are rejected
+        for negate_s_r in &[false, true] {
+            let mut negated_proof = proof.clone();
+            if *negate_s_r {
+                negated_proof.message2.s_r = -negated_proof.message2.s_r;
+            } else {
+                negated_proof.message2.s_e = -negated_proof.message2.s_e;
+            }
+            let verification_transcript = RefCell::new(Transcript::new(b"modeq"));
+            let mut prover_channel =
+                TranscriptProverChannel::new(&crs, &verification_transcript, &negated_proof);
+            assert!(protocol.verify(&mut prover_channel, &statement).is_err());
+        }
+
+        let c = proof
+            .recompute_challenge(&crs, &RefCell::new(Transcript::new(b"modeq")))
+            .unwrap();
+        let vector =
+            Vector::from_proof(&crs, &statement, &proof.message1, &c, &proof.message2).unwrap();
+        let vector = Vector::from_text(&vector.to_text()).unwrap();
+        assert!(reference::verify(&vector).unwrap());
+
+        let mut wrong_vector = vector.clone();
+        wrong_vector.s_e += 1;
+        assert!(!reference::verify(&wrong_vector).unwrap());
+    }
+
+    #[test]
+    fn test_message_order() {
+        let params = Parameters::from_security_level(128).unwrap();
+        let mut rng1 = RandState::new();
+        rng1.seed(&Integer::from(13));
+        let mut rng2 = thread_rng();
+
+        let crs = crate::protocols::membership::Protocol::<
+            Rsa2048,
+            G1Projective,
+            HPProtocol<Bls12_381>,
+        >::setup(&params, &mut rng1, &mut rng2)
+        .unwrap()
+        .crs
+        .crs_modeq;
+        let protocol = Protocol::<Rsa2048, G1Projective>::from_crs(&crs);
+
+        let value = Integer::from(2);
+        let randomness1 = Integer::from(5);
+        let randomness2 = Integer::from(9);
+        let statement = Statement {
+            c_e: crs
+                .integer_commitment_parameters
+                .commit(&value, &randomness1)
+                .unwrap(),
+            c_e_q: crs
+                .pedersen_commitment_parameters
+                .commit(&value, &randomness2)
+                .unwrap(),
+        };
+        let proof_transcript = RefCell::new(Transcript::new(b"modeq"));
+        let mut verifier_channel = TranscriptVerifierChannel::new(&crs, &proof_transcript);
+        protocol
+            .prove(
+                &mut verifier_channel,
+                &mut rng1,
+                &mut rng2,
+                &statement,
+                &Witness {
+                    e: value,
+                    r: randomness1,
+                    r_q: randomness2,
+                },
+            )
+            .unwrap();
+        let proof = verifier_channel.proof().unwrap();
+
+        let transcript = RefCell::new(Transcript::new(b"modeq"));
+        let mut verifier_channel = TranscriptVerifierChannel::new(&crs, &transcript);
+        assert!(matches!(
+            verifier_channel.send_message2(&proof.message2),
+            Err(ChannelError::OutOfOrder)
+        ));
+        verifier_channel.send_message1(&proof.message1).unwrap();
+        assert!(matches!(
+            verifier_channel.send_message1(&proof.message1),
+            Err(ChannelError::Duplicate)
+        ));
+        assert!(matches!(
+            verifier_channel.send_message2(&proof.message2),
+            Err(ChannelError::OutOfOrder)
+        ));
+        verifier_channel.receive_challenge().unwrap();
+        verifier_channel.send_message2(&proof.message2).unwrap();
+
+        let transcript = RefCell::new(Transcript::new(b"modeq"));
+        let mut prover_channel = TranscriptProverChannel::new(&crs, &transcript, &proof);
+        assert!(matches!(
+            prover_channel.generate_and_send_challenge(),
+            Err(ChannelError::OutOfOrder)
+        ));
+        prover_channel.receive_message1().unwrap();
+        prover_channel.generate_and_send_challenge().unwrap();
+        assert!(matches!(
+            prover_channel.generate_and_send_challenge(),
+            Err(ChannelError::Duplicate)
+        ));
+        prover_channel.receive_message2().unwrap();
+    }
+
+    #[test]
+    fn test_small_field_size() {
+        let params = Parameters::from_security_level(128).unwrap();
+        let mut rng1 = RandState::new();
+        rng1.seed(&Integer::from(13));
+        let mut rng2 = thread_rng();
+
+        let mut crs = crate::protocols::membership::Protocol::<
+            Rsa2048,
+            G1Projective,
+            HPProtocol<Bls12_381>,
+        >::setup(&params, &mut rng1, &mut rng2)
+        .unwrap()
+        .crs
+        .crs_modeq;
+        // smaller than the scalar field of BLS12-381
+        crs.parameters.field_size_bits = 128;
+        let protocol = Protocol::<Rsa2048, G1Projective>::from_crs(&crs);
+
+        let value = Integer::from(2);
+        let randomness1 = Integer::from(5);
+        let randomness2 = Integer::from(9);
+        let statement = Statement {
+            c_e: crs
+                .integer_commitment_parameters
+                .commit(&value, &randomness1)
+                .unwrap(),
+            c_e_q: crs
+                .pedersen_commitment_parameters
+                .commit(&value, &randomness2)
+                .unwrap(),
+        };
+        let proof_transcript = RefCell::new(Transcript::new(b"modeq"));
+        let mut verifier_channel = TranscriptVerifierChannel::new(&crs, &proof_transcript);
+        assert!(matches!(
+            protocol.prove(
+                &mut verifier_channel,
+                &mut rng1,
+                &mut rng2,
+                &statement,
+                &Witness {
+                    e: value,
+                    r: randomness1,
+                    r_q: randomness2,
+                },
+            ),
+            Err(ProofError::CRSInitError(CRSError::InvalidParameters))
+        ));
+        assert!(matches!(
+            verifier_channel.proof(),
+            Err(TranscriptChannelError::Incomplete)
+        ));
+    }
+
+    #[test]
+    fn test_reference_reduction() {
+        let q = <G1Projective as CurvePointProjective>::ScalarField::modulus();
+        for x in &[
+            Integer::from(-5),
+            Integer::from(&q + 5),
+            -Integer::from(&q * 3) - 7,
+        ] {
+            assert_eq!(
+                reference::reduce(x, &q),
+                integer_mod_q::<G1Projective>(x).unwrap()
+            );
+        }
+        assert_eq!(reference::reduce(&Integer::from(-5), &q), q - 5);
+    }
+
+    /// The checked in vector uses the RSA-2048 challenge modulus and the
+    /// order of the BLS12-381 scalar field, with a negative `s_e` larger than
+    /// `q` so that the reduction of negative responses is covered.
+    #[test]
+    fn test_reference_vector() {
+        let text = include_str!("vectors/rsa2048_bls12_381.txt");
+        let vector = Vector::from_text(text).unwrap();
+        assert_eq!(vector.to_text(), text);
+        assert_eq!(vector.modulus, Rsa2048::rsa_modulus().unwrap());
+        assert_eq!(
+            vector.q,
+            <G1Projective as CurvePointProjective>::ScalarField::modulus()
+        );
+        assert!(vector.s_e < 0 && Integer::from(vector.s_e.abs_ref()) > vector.q);
+        assert_eq!(
+            vector.s_e_mod_q,
+            integer_mod_q::<G1Projective>(&vector.s_e).unwrap()
+        );
+        assert_eq!(
+            vector.c_mod_q,
+            integer_mod_q::<G1Projective>(&vector.c).unwrap()
+        );
+        assert!(reference::verify(&vector).unwrap());
+
+        let mut wrong_vector = vector.clone();
+        wrong_vector.s_e_mod_q = vector.s_e.clone();
+        assert!(!reference::verify(&wrong_vector).unwrap());
+    }
+
+    #[test]
+    fn test_statement_equality() {
+        let params = Parameters::from_security_level(128).unwrap();
+        let mut rng1 = RandState::new();
+        rng1.seed(&Integer::from(13));
+        let mut rng2 = thread_rng();
+
+        let crs = crate::protocols::membership::Protocol::<
+            Rsa2048,
+            G1Projective,
+            HPProtocol<Bls12_381>,
+        >::setup(&params, &mut rng1, &mut rng2)
+        .unwrap()
+        .crs
+        .crs_modeq;
+        let statement = |randomness: u64| Statement::<Rsa2048, G1Projective> {
+            c_e: crs
+                .integer_commitment_parameters
+                .commit(&Integer::from(2), &Integer::from(5))
+                .unwrap(),
+            c_e_q: crs
+                .pedersen_commitment_parameters
+                .commit(&Integer::from(2), &Integer::from(randomness))
+                .unwrap(),
+        };
+
+        let statement1 = statement(9);
+        let statement2 = statement(10);
+        assert!(statement1 == statement1.clone());
+        assert!(statement1 != statement2);
+        assert_eq!(
+            statement1.digest().unwrap(),
+            statement1.clone().digest().unwrap()
+        );
+        assert_ne!(statement1.digest().unwrap(), statement2.digest().unwrap());
+
+        let mut statements = HashSet::new();
+        statements.insert(statement1.clone());
+        statements.insert(statement2);
+        statements.insert(statement1);
+        assert_eq!(statements.len(), 2);
     }
 }
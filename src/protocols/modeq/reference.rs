@@ -0,0 +1,189 @@
+//! Reference verification of modeq in plain integer arithmetic, with a text
+//! encoding of test vectors so the checks can be reproduced in other
+//! languages.
+//!
+//! The reference covers what can be checked without curve arithmetic: the
+//! integer commitment relation in the RSA group, and the reductions of the
+//! responses and the challenge modulo the order `q` of the Pedersen group,
+//! which are the scalars the verifier uses on the curve side. RSA elements
+//! are taken up to sign, since the accumulator works in `Z_N^* / {1, -1}`.
+//!
+//! `vectors/rsa2048_bls12_381.txt` is a vector in the text encoding, over the
+//! RSA-2048 challenge modulus and the BLS12-381 scalar field.
+use crate::{
+    protocols::{
+        modeq::{CRSModEq, Message1, Message2, Statement},
+        VerificationError,
+    },
+    utils::{
+        bytes_to_integer,
+        curve::{CurvePointProjective, Field},
+        integer_mod_q, ConvertibleUnknownOrderGroup,
+    },
+};
+use rug::Integer;
+
+quick_error! {
+    #[derive(Debug)]
+    pub enum ReferenceError {
+        MissingField(name: &'static str) {}
+        InvalidLine(line: String) {}
+        NotInvertible {}
+    }
+}
+
+const FIELDS: [&str; 11] = [
+    "modulus",
+    "g",
+    "h",
+    "c_e",
+    "alpha1",
+    "c",
+    "s_e",
+    "s_r",
+    "q",
+    "s_e_mod_q",
+    "c_mod_q",
+];
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Vector {
+    pub modulus: Integer,
+    pub g: Integer,
+    pub h: Integer,
+    pub c_e: Integer,
+    pub alpha1: Integer,
+    pub c: Integer,
+    pub s_e: Integer,
+    pub s_r: Integer,
+    pub q: Integer,
+    pub s_e_mod_q: Integer,
+    pub c_mod_q: Integer,
+}
+
+/// Reduces `x` to `[0, modulus)`, also for negative `x`.
+pub fn reduce(x: &Integer, modulus: &Integer) -> Integer {
+    Integer::from(x.rem_euc_ref(modulus))
+}
+
+fn exp(base: &Integer, e: &Integer, modulus: &Integer) -> Result<Integer, ReferenceError> {
+    // negative exponents need the inverse of the base
+    base.clone()
+        .pow_mod(e, modulus)
+        .map_err(|_| ReferenceError::NotInvertible)
+}
+
+fn equal_up_to_sign(a: &Integer, b: &Integer, modulus: &Integer) -> bool {
+    let a = reduce(a, modulus);
+    let b = reduce(b, modulus);
+    a == b || Integer::from(&a + &b) == *modulus
+}
+
+/// Checks `alpha1 = c_e^c * g^s_e * h^s_r` in `Z_N^* / {1, -1}`, and that the
+/// curve side scalars are the canonical reductions of `s_e` and `c`.
+pub fn verify(vector: &Vector) -> Result<bool, ReferenceError> {
+    let n = &vector.modulus;
+    let expected_alpha1 = Integer::from(
+        exp(&vector.c_e, &vector.c, n)?
+            * exp(&vector.g, &vector.s_e, n)?
+            * exp(&vector.h, &vector.s_r, n)?,
+    ) % n;
+    if !equal_up_to_sign(&expected_alpha1, &vector.alpha1, n) {
+        return Ok(false);
+    }
+
+    Ok(vector.s_e_mod_q == reduce(&vector.s_e, &vector.q)
+        && vector.c_mod_q == reduce(&vector.c, &vector.q))
+}
+
+impl Vector {
+    /// Builds a vector from a modeq transcript over an RSA group. The mod-q
+    /// values are computed by the library, so the vector records its
+    /// behavior.
+    pub fn from_proof<G: ConvertibleUnknownOrderGroup, P: CurvePointProjective>(
+        crs: &CRSModEq<G, P>,
+        statement: &Statement<G, P>,
+        message1: &Message1<G, P>,
+        c: &Integer,
+        message2: &Message2<P>,
+    ) -> Result<Vector, VerificationError> {
+        let modulus = G::rsa_modulus().map_err(|_| VerificationError::VerificationFailed)?;
+        let to_integer = |e: &G::Elem| bytes_to_integer(&G::elem_to_bytes(e));
+        Ok(Vector {
+            modulus,
+            g: to_integer(&crs.integer_commitment_parameters.g),
+            h: to_integer(&crs.integer_commitment_parameters.h),
+            c_e: to_integer(&statement.c_e),
+            alpha1: to_integer(&message1.alpha1),
+            c: c.clone(),
+            s_e: message2.s_e.clone(),
+            s_r: message2.s_r.clone(),
+            q: P::ScalarField::modulus(),
+            s_e_mod_q: integer_mod_q::<P>(&message2.s_e)?,
+            c_mod_q: integer_mod_q::<P>(c)?,
+        })
+    }
+
+    fn values(&self) -> [&Integer; 11] {
+        [
+            &self.modulus,
+            &self.g,
+            &self.h,
+            &self.c_e,
+            &self.alpha1,
+            &self.c,
+            &self.s_e,
+            &self.s_r,
+            &self.q,
+            &self.s_e_mod_q,
+            &self.c_mod_q,
+        ]
+    }
+
+    /// Encodes the vector as `name = value` lines, with values in signed
+    /// hexadecimal.
+    pub fn to_text(&self) -> String {
+        FIELDS
+            .iter()
+            .zip(self.values().iter())
+            .map(|(name, value)| format!("{} = {}\n", name, value.to_string_radix(16)))
+            .collect()
+    }
+
+    pub fn from_text(text: &str) -> Result<Vector, ReferenceError> {
+        let mut values = vec![None; FIELDS.len()];
+        for line in text.lines().map(str::trim).filter(|l| !l.is_empty()) {
+            let mut parts = line.splitn(2, '=').map(str::trim);
+            let (name, value) = match (parts.next(), parts.next()) {
+                (Some(name), Some(value)) => (name, value),
+                _ => return Err(ReferenceError::InvalidLine(line.to_string())),
+            };
+            let index = FIELDS
+                .iter()
+                .position(|f| *f == name)
+                .ok_or_else(|| ReferenceError::InvalidLine(line.to_string()))?;
+            let value = Integer::from_str_radix(value, 16)
+                .map_err(|_| ReferenceError::InvalidLine(line.to_string()))?;
+            values[index] = Some(value);
+        }
+        let mut values = values
+            .into_iter()
+            .zip(FIELDS.iter())
+            .map(|(v, name)| v.ok_or(ReferenceError::MissingField(*name)));
+
+        let mut next = || values.next().unwrap();
+        Ok(Vector {
+            modulus: next()?,
+            g: next()?,
+            h: next()?,
+            c_e: next()?,
+            alpha1: next()?,
+            c: next()?,
+            s_e: next()?,
+            s_r: next()?,
+            q: next()?,
+            s_e_mod_q: next()?,
+            c_mod_q: next()?,
+        })
+    }
+}
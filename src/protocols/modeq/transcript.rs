@@ -1,12 +1,14 @@
+#[cfg(feature = "compressed")]
+use crate::protocols::modeq::CompressedProof;
 use crate::{
-    channels::ChannelError,
+    channels::{ChannelError, MessageOrder},
     protocols::modeq::{
         channel::{ModEqProverChannel, ModEqVerifierChannel},
         CRSModEq, Message1, Message2, Proof,
     },
     transcript::{
-        TranscriptChannelError, TranscriptProtocolChallenge, TranscriptProtocolCurve,
-        TranscriptProtocolInteger,
+        versioned_domain_sep, TranscriptChannelError, TranscriptProtocolChallenge,
+        TranscriptProtocolCurve, TranscriptProtocolInteger,
     },
     utils::{curve::CurvePointProjective, ConvertibleUnknownOrderGroup},
 };
@@ -24,7 +26,7 @@ impl<G: ConvertibleUnknownOrderGroup, P: CurvePointProjective> TranscriptProtoco
     for Transcript
 {
     fn modeq_domain_sep(&mut self) {
-        self.append_message(b"dom-sep", b"modeq");
+        self.append_message(b"dom-sep", &versioned_domain_sep(b"modeq"));
     }
 }
 pub struct TranscriptVerifierChannel<
@@ -35,8 +37,10 @@ pub struct TranscriptVerifierChannel<
 > {
     crs: CRSModEq<G, P>,
     transcript: &'a RefCell<T>,
+    order: MessageOrder,
     message1: Option<Message1<G, P>>,
     message2: Option<Message2<P>>,
+    challenge: Option<Integer>,
 }
 
 impl<
@@ -53,8 +57,10 @@ impl<
         TranscriptVerifierChannel {
             crs: crs.clone(),
             transcript,
+            order: MessageOrder::new(),
             message1: None,
             message2: None,
+            challenge: None,
         }
     }
 
@@ -68,6 +74,18 @@ impl<
             Err(TranscriptChannelError::Incomplete)
         }
     }
+
+    #[cfg(feature = "compressed")]
+    pub fn compressed_proof(&self) -> Result<CompressedProof<P>, TranscriptChannelError> {
+        if self.challenge.is_some() && self.message2.is_some() {
+            Ok(CompressedProof {
+                challenge: self.challenge.as_ref().unwrap().clone(),
+                message2: self.message2.as_ref().unwrap().clone(),
+            })
+        } else {
+            Err(TranscriptChannelError::Incomplete)
+        }
+    }
 }
 
 impl<
@@ -78,6 +96,7 @@ impl<
     > ModEqVerifierChannel<G, P> for TranscriptVerifierChannel<'a, G, P, T>
 {
     fn send_message1(&mut self, message: &Message1<G, P>) -> Result<(), ChannelError> {
+        self.order.step(0)?;
         let mut transcript = self.transcript.try_borrow_mut()?;
         transcript.modeq_domain_sep();
         transcript.append_integer_point(b"alpha1", &message.alpha1);
@@ -86,13 +105,17 @@ impl<
         Ok(())
     }
     fn send_message2(&mut self, message: &Message2<P>) -> Result<(), ChannelError> {
+        self.order.step(2)?;
         self.message2 = Some(message.clone());
         Ok(())
     }
     fn receive_challenge(&mut self) -> Result<Integer, ChannelError> {
+        self.order.step(1)?;
         let mut transcript = self.transcript.try_borrow_mut()?;
         transcript.modeq_domain_sep();
-        Ok(transcript.challenge_scalar(b"c", self.crs.parameters.security_soundness))
+        let c = transcript.challenge_scalar(b"c", self.crs.parameters.security_soundness);
+        self.challenge = Some(c.clone());
+        Ok(c)
     }
 }
 
@@ -104,6 +127,7 @@ pub struct TranscriptProverChannel<
 > {
     crs: CRSModEq<G, P>,
     transcript: &'a RefCell<T>,
+    order: MessageOrder,
     proof: Proof<G, P>,
 }
 
@@ -122,6 +146,7 @@ impl<
         TranscriptProverChannel {
             crs: crs.clone(),
             transcript,
+            order: MessageOrder::new(),
             proof: proof.clone(),
         }
     }
@@ -135,6 +160,7 @@ impl<
     > ModEqProverChannel<G, P> for TranscriptProverChannel<'a, G, P, T>
 {
     fn receive_message1(&mut self) -> Result<Message1<G, P>, ChannelError> {
+        self.order.step(0)?;
         let mut transcript = self.transcript.try_borrow_mut()?;
         transcript.modeq_domain_sep();
         transcript.append_integer_point(b"alpha1", &self.proof.message1.alpha1);
@@ -142,11 +168,27 @@ impl<
         Ok(self.proof.message1.clone())
     }
     fn receive_message2(&mut self) -> Result<Message2<P>, ChannelError> {
+        self.order.step(2)?;
         Ok(self.proof.message2.clone())
     }
     fn generate_and_send_challenge(&mut self) -> Result<Integer, ChannelError> {
+        self.order.step(1)?;
         let mut transcript = self.transcript.try_borrow_mut()?;
         transcript.modeq_domain_sep();
         Ok(transcript.challenge_scalar(b"c", self.crs.parameters.security_soundness))
     }
 }
+
+impl<G: ConvertibleUnknownOrderGroup, P: CurvePointProjective> Proof<G, P> {
+    /// Replays the first message of the proof on the transcript and returns
+    /// the challenge the verifier derives from it.
+    pub fn recompute_challenge<T: TranscriptProtocolModEq<G, P>>(
+        &self,
+        crs: &CRSModEq<G, P>,
+        transcript: &RefCell<T>,
+    ) -> Result<Integer, ChannelError> {
+        let mut prover_channel = TranscriptProverChannel::new(crs, transcript, self);
+        prover_channel.receive_message1()?;
+        prover_channel.generate_and_send_challenge()
+    }
+}
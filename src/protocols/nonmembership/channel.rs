@@ -1,10 +1,12 @@
 use crate::{
     channels::ChannelError,
     commitments::{integer::IntegerCommitment, Commitment},
+    protocols::registry::CrsDigest,
     utils::ConvertibleUnknownOrderGroup,
 };
 
 pub trait NonMembershipVerifierChannel<G: ConvertibleUnknownOrderGroup> {
+    fn send_crs_digest(&mut self, crs_digest: &CrsDigest) -> Result<(), ChannelError>;
     fn send_c_e(
         &mut self,
         c_e: &<IntegerCommitment<G> as Commitment>::Instance,
@@ -12,6 +14,7 @@ pub trait NonMembershipVerifierChannel<G: ConvertibleUnknownOrderGroup> {
 }
 
 pub trait NonMembershipProverChannel<G: ConvertibleUnknownOrderGroup> {
+    fn receive_crs_digest(&mut self) -> Result<CrsDigest, ChannelError>;
     fn receive_c_e(
         &mut self,
     ) -> Result<<IntegerCommitment<G> as Commitment>::Instance, ChannelError>;
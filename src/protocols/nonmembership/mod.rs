@@ -1,36 +1,89 @@
 //! Implements CPNonMemRSA and CPNonMemRSAPrm.
+//!
+//! With `snark_hash` and `snark_increment`, an element maps to any of a few
+//! primes, and a proof only shows that the one the prover chose isn't
+//! accumulated. This is enough when elements are only ever accumulated
+//! through their canonical prime; other verifiers create the protocol with
+//! `from_crs_with_unique_prime`, which rejects these backends.
+#[cfg(feature = "compressed")]
+use crate::protocols::{
+    coprime::CompressedProof as CoprimeCompressedProof,
+    modeq::CompressedProof as ModEqCompressedProof,
+};
 use crate::{
-    commitments::{integer::IntegerCommitment, pedersen::PedersenCommitment, Commitment},
-    parameters::Parameters,
+    commitments::{
+        integer::{IntegerCommitment, IntegerCommitmentOpening},
+        pedersen::PedersenCommitment,
+        Commitment,
+    },
+    parameters::{Parameters, SubProtocol},
     protocols::{
+        cache::{CacheKey, CacheKeyBuilder},
         coprime::{
-            channel::{CoprimeProverChannel, CoprimeVerifierChannel},
-            CRSCoprime, Proof as CoprimeProof, Protocol as CoprimeProtocol,
-            Statement as CoprimeStatement, Witness as CoprimeWitness,
+            channel::CoprimeProverChannel, validate_coprime_group, CRSCoprime,
+            Proof as CoprimeProof, Protocol as CoprimeProtocol, Statement as CoprimeStatement,
         },
         hash_to_prime::{
-            channel::{HashToPrimeProverChannel, HashToPrimeVerifierChannel},
-            CRSHashToPrime, HashToPrimeError, HashToPrimeProtocol,
-            Statement as HashToPrimeStatement, Witness as HashToPrimeWitness,
+            channel::HashToPrimeProverChannel, CRSHashToPrime, HashToPrimeError,
+            HashToPrimeProtocol, MemoryReport, MemoryUsage, PartiallyVerified,
+            Statement as HashToPrimeStatement,
         },
         modeq::{
-            channel::{ModEqProverChannel, ModEqVerifierChannel},
-            CRSModEq, Proof as ModEqProof, Protocol as ModEqProtocol, Statement as ModEqStatement,
-            Witness as ModEqWitness,
+            channel::ModEqProverChannel, CRSModEq, Proof as ModEqProof, Protocol as ModEqProtocol,
+            Statement as ModEqStatement,
         },
-        ProofError, SetupError, VerificationError,
+        progress::ProgressObserver,
+        registry::{crs_digest, CrsDigest},
+        statement::{hash_curve_point, StatementDigest, StatementHasher},
+        CRSError, VerificationError,
     },
+    utils::curve::{CurveError, CurvePointProjective},
     utils::ConvertibleUnknownOrderGroup,
-    utils::{curve::CurvePointProjective, random_between},
 };
-use channel::{NonMembershipProverChannel, NonMembershipVerifierChannel};
+#[cfg(feature = "prover")]
+use crate::{
+    protocols::{
+        blinding::BlindingHistory,
+        coprime::{
+            channel::CoprimeVerifierChannel, transcript::TranscriptProtocolCoprime,
+            Witness as CoprimeWitness,
+        },
+        hash_to_prime::{
+            channel::HashToPrimeVerifierChannel, shared::SharedSubProofs,
+            transcript::TranscriptProtocolHashToPrime, validate_element_size, validate_witness,
+            Witness as HashToPrimeWitness,
+        },
+        modeq::{
+            channel::ModEqVerifierChannel, transcript::TranscriptProtocolModEq,
+            Witness as ModEqWitness,
+        },
+        progress::{report, NoProgress, Stage},
+        ProofError, SetupError,
+    },
+    transcript::TranscriptProtocolWitnessRng,
+    utils::random_between,
+};
+use channel::NonMembershipProverChannel;
+#[cfg(feature = "prover")]
+use channel::NonMembershipVerifierChannel;
 use rand::{CryptoRng, RngCore};
-use rug::rand::MutRandState;
+#[cfg(feature = "prover")]
+use rug::rand::{MutRandState, RandState};
 use rug::Integer;
+#[cfg(feature = "prover")]
+use std::cell::RefCell;
+use std::hash::{Hash, Hasher};
+#[cfg(feature = "prover")]
+use transcript::{
+    TranscriptProtocolNonMembership, TranscriptProverChannel, TranscriptVerifierChannel,
+};
 
 pub mod channel;
+pub mod prime;
 pub mod transcript;
 
+pub use prime::CPNonMemRSAPrm;
+
 pub struct CRS<G: ConvertibleUnknownOrderGroup, P: CurvePointProjective, HP: HashToPrimeProtocol<P>>
 {
     // G contains the information about Z^*_N
@@ -53,6 +106,50 @@ impl<G: ConvertibleUnknownOrderGroup, P: CurvePointProjective, HP: HashToPrimePr
     }
 }
 
+impl<G: ConvertibleUnknownOrderGroup, P: CurvePointProjective, HP: HashToPrimeProtocol<P>>
+    CRS<G, P, HP>
+{
+    /// Identifies the CRS, e.g. to select the verifier of a proof in a
+    /// `CrsRegistry`.
+    pub fn digest(&self) -> Result<CrsDigest, CurveError> {
+        crs_digest::<G, P>(
+            b"nonmembership",
+            &[
+                &self.parameters,
+                &self.crs_coprime.parameters,
+                &self.crs_modeq.parameters,
+                &self.crs_hash_to_prime.parameters,
+            ],
+            &self.crs_coprime.integer_commitment_parameters,
+            &self.crs_modeq.pedersen_commitment_parameters,
+            &HP::parameters_to_bytes(&self.crs_hash_to_prime.hash_to_prime_parameters)?,
+        )
+    }
+}
+
+impl<G: ConvertibleUnknownOrderGroup, P: CurvePointProjective, HP: HashToPrimeProtocol<P>>
+    CRS<G, P, HP>
+where
+    HP::Parameters: MemoryUsage,
+{
+    /// The memory taken by the CRS, dominated by the hash-to-prime parameters.
+    pub fn memory_report(&self) -> MemoryReport {
+        let integer_commitment_parameters = &self.crs_coprime.integer_commitment_parameters;
+        let mut report = MemoryReport::default();
+        report.push(
+            "integer_commitment_parameters",
+            G::elem_to_bytes(&integer_commitment_parameters.g).len()
+                + G::elem_to_bytes(&integer_commitment_parameters.h).len(),
+        );
+        report.extend(self.crs_hash_to_prime.memory_report());
+        report
+    }
+}
+
+/// CPNonMemRSA, the nonmembership protocol for arbitrary elements, which are hashed to primes
+/// using `HP`.
+pub type CPNonMemRSA<G, P, HP> = Protocol<G, P, HP>;
+
 pub struct Protocol<
     G: ConvertibleUnknownOrderGroup,
     P: CurvePointProjective,
@@ -66,6 +163,40 @@ pub struct Statement<G: ConvertibleUnknownOrderGroup, P: CurvePointProjective> {
     pub c_e_q: <PedersenCommitment<P> as Commitment>::Instance,
 }
 
+impl<G: ConvertibleUnknownOrderGroup, P: CurvePointProjective> Clone for Statement<G, P> {
+    fn clone(&self) -> Self {
+        Self {
+            c_p: self.c_p.clone(),
+            c_e_q: self.c_e_q.clone(),
+        }
+    }
+}
+
+impl<G: ConvertibleUnknownOrderGroup, P: CurvePointProjective> PartialEq for Statement<G, P> {
+    fn eq(&self, other: &Self) -> bool {
+        self.c_p == other.c_p && self.c_e_q == other.c_e_q
+    }
+}
+
+impl<G: ConvertibleUnknownOrderGroup, P: CurvePointProjective> Eq for Statement<G, P> {}
+
+impl<G: ConvertibleUnknownOrderGroup, P: CurvePointProjective> Hash for Statement<G, P> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.c_p.hash(state);
+        hash_curve_point(&self.c_e_q, state);
+    }
+}
+
+impl<G: ConvertibleUnknownOrderGroup, P: CurvePointProjective> Statement<G, P> {
+    /// Stable identifier of the statement, see `protocols::statement`.
+    pub fn digest(&self) -> Result<StatementDigest, CurveError> {
+        let mut hasher = StatementHasher::new(b"nonmembership");
+        hasher.append_group_element::<G>(b"c_p", &self.c_p);
+        hasher.append_curve_point(b"c_e_q", &self.c_e_q)?;
+        Ok(hasher.finish())
+    }
+}
+
 pub struct Witness<G: ConvertibleUnknownOrderGroup> {
     pub e: Integer,
     pub r_q: Integer,
@@ -78,6 +209,7 @@ pub struct Proof<
     P: CurvePointProjective,
     HP: HashToPrimeProtocol<P>,
 > {
+    pub crs_digest: CrsDigest,
     pub c_e: <IntegerCommitment<G> as Commitment>::Instance,
     pub proof_coprime: CoprimeProof<G>,
     pub proof_modeq: ModEqProof<G, P>,
@@ -89,6 +221,7 @@ impl<G: ConvertibleUnknownOrderGroup, P: CurvePointProjective, HP: HashToPrimePr
 {
     fn clone(&self) -> Self {
         Self {
+            crs_digest: self.crs_digest,
             c_e: self.c_e.clone(),
             proof_coprime: self.proof_coprime.clone(),
             proof_modeq: self.proof_modeq.clone(),
@@ -97,6 +230,74 @@ impl<G: ConvertibleUnknownOrderGroup, P: CurvePointProjective, HP: HashToPrimePr
     }
 }
 
+impl<G: ConvertibleUnknownOrderGroup, P: CurvePointProjective, HP: HashToPrimeProtocol<P>>
+    Proof<G, P, HP>
+{
+    /// Size in bytes of the proof with the encodings of the sub-proofs'
+    /// `serialized_size`, and the hash-to-prime proof encoded by
+    /// `HP::proof_to_bytes`.
+    pub fn serialized_size(&self) -> Result<usize, CurveError> {
+        Ok(self.crs_digest.len()
+            + G::elem_to_bytes(&self.c_e).len()
+            + self.proof_coprime.serialized_size()
+            + self.proof_modeq.serialized_size()?
+            + HP::proof_to_bytes(&self.proof_hash_to_prime)?.len())
+    }
+
+    /// The statement `proof_coprime` is about, to examine or verify it on its own.
+    pub fn coprime_statement(&self, statement: &Statement<G, P>) -> CoprimeStatement<G> {
+        CoprimeStatement {
+            c_e: self.c_e.clone(),
+            acc: statement.c_p.clone(),
+        }
+    }
+
+    pub fn modeq_statement(&self, statement: &Statement<G, P>) -> ModEqStatement<G, P> {
+        ModEqStatement {
+            c_e: self.c_e.clone(),
+            c_e_q: statement.c_e_q.clone(),
+        }
+    }
+
+    pub fn hash_to_prime_statement(&self, statement: &Statement<G, P>) -> HashToPrimeStatement<P> {
+        HashToPrimeStatement {
+            c_e_q: statement.c_e_q.clone(),
+        }
+    }
+}
+
+/// A proof where the alphas of the sigma protocols are omitted and recomputed
+/// from the challenges and responses.
+#[cfg(feature = "compressed")]
+pub struct CompressedProof<
+    G: ConvertibleUnknownOrderGroup,
+    P: CurvePointProjective,
+    HP: HashToPrimeProtocol<P>,
+> {
+    pub crs_digest: CrsDigest,
+    pub c_e: <IntegerCommitment<G> as Commitment>::Instance,
+    pub proof_coprime: CoprimeCompressedProof<G>,
+    pub proof_modeq: ModEqCompressedProof<P>,
+    pub proof_hash_to_prime: HP::Proof,
+}
+
+#[cfg(feature = "compressed")]
+impl<G: ConvertibleUnknownOrderGroup, P: CurvePointProjective, HP: HashToPrimeProtocol<P>> Clone
+    for CompressedProof<G, P, HP>
+{
+    fn clone(&self) -> Self {
+        Self {
+            crs_digest: self.crs_digest,
+            c_e: self.c_e.clone(),
+            proof_coprime: self.proof_coprime.clone(),
+            proof_modeq: self.proof_modeq.clone(),
+            proof_hash_to_prime: self.proof_hash_to_prime.clone(),
+        }
+    }
+}
+
+/// Setup and proving, behind the `prover` feature.
+#[cfg(feature = "prover")]
 impl<G: ConvertibleUnknownOrderGroup, P: CurvePointProjective, HP: HashToPrimeProtocol<P>>
     Protocol<G, P, HP>
 {
@@ -105,29 +306,82 @@ impl<G: ConvertibleUnknownOrderGroup, P: CurvePointProjective, HP: HashToPrimePr
         rng1: &mut R1,
         rng2: &mut R2,
     ) -> Result<Protocol<G, P, HP>, SetupError> {
-        let integer_commitment_parameters = IntegerCommitment::<G>::setup(rng1);
+        Self::setup_with_observer(parameters, rng1, rng2, &NoProgress)
+    }
+
+    /// Performs setup, reporting progress to `observer` and returning
+    /// `SetupError::Cancelled` if it asks to stop.
+    pub fn setup_with_observer<R1: MutRandState, R2: RngCore + CryptoRng, O: ProgressObserver>(
+        parameters: &Parameters,
+        rng1: &mut R1,
+        rng2: &mut R2,
+        observer: &O,
+    ) -> Result<Protocol<G, P, HP>, SetupError> {
         let pedersen_commitment_parameters = PedersenCommitment::<P>::setup(rng2);
-        let hash_to_prime_parameters =
-            HP::setup(rng2, &pedersen_commitment_parameters, parameters)?;
-        Ok(Protocol {
-            crs: CRS::<G, P, HP> {
-                parameters: parameters.clone(),
-                crs_modeq: CRSModEq::<G, P> {
-                    parameters: parameters.clone(),
-                    integer_commitment_parameters: integer_commitment_parameters.clone(),
-                    pedersen_commitment_parameters: pedersen_commitment_parameters.clone(),
-                },
-                crs_coprime: CRSCoprime::<G> {
-                    parameters: parameters.clone(),
-                    integer_commitment_parameters,
-                },
-                crs_hash_to_prime: CRSHashToPrime::<P, HP> {
-                    parameters: parameters.clone(),
-                    pedersen_commitment_parameters,
-                    hash_to_prime_parameters,
-                },
+        Self::setup_inner(
+            parameters,
+            &pedersen_commitment_parameters,
+            rng1,
+            rng2,
+            observer,
+        )
+    }
+
+    /// Performs setup reusing existing Pedersen commitment keys, so that
+    /// commitments to set elements produced elsewhere can be used as `c_e_q`
+    /// directly.
+    pub fn setup_with_pedersen_commitment_parameters<R1: MutRandState, R2: RngCore + CryptoRng>(
+        parameters: &Parameters,
+        pedersen_commitment_parameters: &PedersenCommitment<P>,
+        rng1: &mut R1,
+        rng2: &mut R2,
+    ) -> Result<Protocol<G, P, HP>, SetupError> {
+        Self::setup_inner(
+            parameters,
+            pedersen_commitment_parameters,
+            rng1,
+            rng2,
+            &NoProgress,
+        )
+    }
+
+    fn setup_inner<R1: MutRandState, R2: RngCore + CryptoRng, O: ProgressObserver>(
+        parameters: &Parameters,
+        pedersen_commitment_parameters: &PedersenCommitment<P>,
+        rng1: &mut R1,
+        rng2: &mut R2,
+        observer: &O,
+    ) -> Result<Protocol<G, P, HP>, SetupError> {
+        validate_coprime_group::<G>().map_err(|_| SetupError::UnsupportedGroupForCoprime)?;
+        if !report(observer, Stage::IntegerCommitmentSetup, 0, 1) {
+            return Err(SetupError::Cancelled);
+        }
+        let integer_commitment_parameters = IntegerCommitment::<G>::setup(rng1);
+        observer.on_progress(Stage::IntegerCommitmentSetup, 1, 1);
+        let pedersen_commitment_parameters = pedersen_commitment_parameters.clone();
+        let hash_to_prime_parameters = HP::setup_with_observer(
+            rng2,
+            &pedersen_commitment_parameters,
+            &parameters.for_sub_protocol(SubProtocol::HashToPrime),
+            observer,
+        )?;
+        Ok(Protocol::with_crs(CRS::<G, P, HP> {
+            parameters: parameters.clone(),
+            crs_modeq: CRSModEq::<G, P> {
+                parameters: parameters.for_sub_protocol(SubProtocol::ModEq),
+                integer_commitment_parameters: integer_commitment_parameters.clone(),
+                pedersen_commitment_parameters: pedersen_commitment_parameters.clone(),
             },
-        })
+            crs_coprime: CRSCoprime::<G> {
+                parameters: parameters.for_sub_protocol(SubProtocol::Coprime),
+                integer_commitment_parameters,
+            },
+            crs_hash_to_prime: CRSHashToPrime::<P, HP> {
+                parameters: parameters.for_sub_protocol(SubProtocol::HashToPrime),
+                pedersen_commitment_parameters,
+                hash_to_prime_parameters,
+            },
+        }))
     }
 
     pub fn prove<
@@ -145,16 +399,207 @@ impl<G: ConvertibleUnknownOrderGroup, P: CurvePointProjective, HP: HashToPrimePr
         statement: &Statement<G, P>,
         witness: &Witness<G>,
     ) -> Result<(), ProofError> {
+        self.prove_with_history(
+            verifier_channel,
+            rng1,
+            rng2,
+            statement,
+            witness,
+            &mut BlindingHistory::new(0),
+        )
+    }
+
+    /// Proves over a transcript, deriving the randomness of the prover from
+    /// the transcript, the statement and the witness as well as `rng`, so that
+    /// blinding values can't repeat across statements even if `rng` does.
+    pub fn prove_with_transcript_rng<
+        'a,
+        R: RngCore + CryptoRng,
+        T: TranscriptProtocolNonMembership<G>
+            + TranscriptProtocolCoprime<G>
+            + TranscriptProtocolModEq<G, P>
+            + TranscriptProtocolHashToPrime<P>
+            + TranscriptProtocolWitnessRng,
+    >(
+        &self,
+        verifier_channel: &mut TranscriptVerifierChannel<'a, G, P, HP, T>,
+        rng: &mut R,
+        statement: &Statement<G, P>,
+        witness: &Witness<G>,
+    ) -> Result<(), ProofError> {
+        let (mut integer_rng, mut curve_rng) =
+            verifier_channel.witness_rngs(statement, witness, rng)?;
+        let mut rng1 = RandState::new_custom(&mut integer_rng);
+        self.prove(
+            verifier_channel,
+            &mut rng1,
+            &mut curve_rng,
+            statement,
+            witness,
+        )
+    }
+
+    /// Proves over `transcript` and verifies the proof locally on a copy of
+    /// the transcript as it was before proving, which reproduces the
+    /// challenges, so that a CRS or environment mismatch is caught before the
+    /// proof is sent.
+    pub fn prove_and_check<
+        R1: MutRandState,
+        R2: RngCore + CryptoRng,
+        T: TranscriptProtocolNonMembership<G>
+            + TranscriptProtocolCoprime<G>
+            + TranscriptProtocolModEq<G, P>
+            + TranscriptProtocolHashToPrime<P>
+            + Clone,
+    >(
+        &self,
+        transcript: &RefCell<T>,
+        rng1: &mut R1,
+        rng2: &mut R2,
+        statement: &Statement<G, P>,
+        witness: &Witness<G>,
+    ) -> Result<Proof<G, P, HP>, ProofError> {
+        let verification_transcript = RefCell::new(transcript.borrow().clone());
+        let mut verifier_channel = TranscriptVerifierChannel::new(&self.crs, transcript);
+        self.prove(&mut verifier_channel, rng1, rng2, statement, witness)?;
+        let proof = verifier_channel
+            .proof()
+            .map_err(|_| ProofError::CouldNotCreateProof)?;
+        let mut prover_channel =
+            TranscriptProverChannel::new(&self.crs, &verification_transcript, &proof);
+        self.verify(&mut prover_channel, statement)
+            .map_err(|e| ProofError::SelfCheckFailed(Box::new(e)))?;
+        Ok(proof)
+    }
+
+    /// Proves while recording the blinding values of the sigma protocols in
+    /// `history`, failing if they were used before.
+    pub fn prove_with_history<
+        R1: MutRandState,
+        R2: RngCore + CryptoRng,
+        C: NonMembershipVerifierChannel<G>
+            + CoprimeVerifierChannel<G>
+            + ModEqVerifierChannel<G, P>
+            + HashToPrimeVerifierChannel<P, HP>,
+    >(
+        &self,
+        verifier_channel: &mut C,
+        rng1: &mut R1,
+        rng2: &mut R2,
+        statement: &Statement<G, P>,
+        witness: &Witness<G>,
+        history: &mut BlindingHistory,
+    ) -> Result<(), ProofError> {
+        self.prove_composed(
+            verifier_channel,
+            rng1,
+            rng2,
+            statement,
+            witness,
+            history,
+            None,
+            None,
+        )
+    }
+
+    /// Proves, reusing the hash-to-prime proof from `shared` if another proof
+    /// about the same `c_e_q` was created with it, and adding it otherwise.
+    pub fn prove_with_shared_sub_proofs<
+        R1: MutRandState,
+        R2: RngCore + CryptoRng,
+        C: NonMembershipVerifierChannel<G>
+            + CoprimeVerifierChannel<G>
+            + ModEqVerifierChannel<G, P>
+            + HashToPrimeVerifierChannel<P, HP>,
+    >(
+        &self,
+        verifier_channel: &mut C,
+        rng1: &mut R1,
+        rng2: &mut R2,
+        statement: &Statement<G, P>,
+        witness: &Witness<G>,
+        shared: &mut SharedSubProofs<P, HP>,
+    ) -> Result<(), ProofError> {
+        self.prove_composed(
+            verifier_channel,
+            rng1,
+            rng2,
+            statement,
+            witness,
+            &mut BlindingHistory::new(0),
+            Some(shared),
+            None,
+        )
+    }
+
+    /// Proves about `opening.commitment`, a commitment to the prime `e`
+    /// hashes to which the caller already has, instead of a fresh `c_e`, as
+    /// `membership::Protocol::prove_with_integer_commitment`.
+    pub fn prove_with_integer_commitment<
+        R1: MutRandState,
+        R2: RngCore + CryptoRng,
+        C: NonMembershipVerifierChannel<G>
+            + CoprimeVerifierChannel<G>
+            + ModEqVerifierChannel<G, P>
+            + HashToPrimeVerifierChannel<P, HP>,
+    >(
+        &self,
+        verifier_channel: &mut C,
+        rng1: &mut R1,
+        rng2: &mut R2,
+        statement: &Statement<G, P>,
+        witness: &Witness<G>,
+        opening: &IntegerCommitmentOpening<G>,
+    ) -> Result<(), ProofError> {
+        self.prove_composed(
+            verifier_channel,
+            rng1,
+            rng2,
+            statement,
+            witness,
+            &mut BlindingHistory::new(0),
+            None,
+            Some(opening),
+        )
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn prove_composed<
+        R1: MutRandState,
+        R2: RngCore + CryptoRng,
+        C: NonMembershipVerifierChannel<G>
+            + CoprimeVerifierChannel<G>
+            + ModEqVerifierChannel<G, P>
+            + HashToPrimeVerifierChannel<P, HP>,
+    >(
+        &self,
+        verifier_channel: &mut C,
+        rng1: &mut R1,
+        rng2: &mut R2,
+        statement: &Statement<G, P>,
+        witness: &Witness<G>,
+        history: &mut BlindingHistory,
+        shared: Option<&mut SharedSubProofs<P, HP>>,
+        opening: Option<&IntegerCommitmentOpening<G>>,
+    ) -> Result<(), ProofError> {
+        validate_element_size::<P, HP>(&self.crs.parameters, &witness.e)?;
         let (hashed_e, _) = self.hash_to_prime(&witness.e)?;
-        let r = random_between(rng1, &Integer::from(0), &G::order_upper_bound());
-        let c_e = self
-            .crs
-            .crs_coprime
-            .integer_commitment_parameters
-            .commit(&hashed_e, &r)?;
+        validate_witness::<P>(&self.crs.parameters, &hashed_e, &witness.r_q)?;
+        let integer_commitment_parameters = &self.crs.crs_coprime.integer_commitment_parameters;
+        let (c_e, r) = match opening {
+            Some(opening) => {
+                opening.validate(integer_commitment_parameters, &hashed_e)?;
+                (opening.commitment.clone(), opening.randomness.clone())
+            }
+            None => {
+                let r = random_between(rng1, &Integer::from(0), &G::order_upper_bound());
+                (integer_commitment_parameters.commit(&hashed_e, &r)?, r)
+            }
+        };
+        verifier_channel.send_crs_digest(&self.crs_digest()?)?;
         verifier_channel.send_c_e(&c_e)?;
         let coprime = CoprimeProtocol::from_crs(&self.crs.crs_coprime)?;
-        coprime.prove(
+        coprime.prove_with_history(
             verifier_channel,
             rng1,
             &CoprimeStatement {
@@ -167,9 +612,10 @@ impl<G: ConvertibleUnknownOrderGroup, P: CurvePointProjective, HP: HashToPrimePr
                 d: witness.d.clone(),
                 b: witness.b.clone(),
             },
+            history,
         )?;
         let modeq = ModEqProtocol::from_crs(&self.crs.crs_modeq);
-        modeq.prove(
+        modeq.prove_with_history(
             verifier_channel,
             rng1,
             rng2,
@@ -182,23 +628,44 @@ impl<G: ConvertibleUnknownOrderGroup, P: CurvePointProjective, HP: HashToPrimePr
                 r,
                 r_q: witness.r_q.clone(),
             },
+            history,
         )?;
         let hash_to_prime = HashToPrimeProtocol::from_crs(&self.crs.crs_hash_to_prime);
-        hash_to_prime.prove(
-            verifier_channel,
-            rng2,
-            &HashToPrimeStatement {
-                c_e_q: statement.c_e_q.clone(),
-            },
-            &HashToPrimeWitness {
-                e: witness.e.clone(),
-                r_q: witness.r_q.clone(),
-            },
-        )?;
+        let hash_to_prime_statement = HashToPrimeStatement {
+            c_e_q: statement.c_e_q.clone(),
+        };
+        let hash_to_prime_witness = HashToPrimeWitness {
+            e: witness.e.clone(),
+            r_q: witness.r_q.clone(),
+        };
+        match shared {
+            Some(shared) => shared.prove(
+                &hash_to_prime,
+                verifier_channel,
+                rng2,
+                &hash_to_prime_statement,
+                &hash_to_prime_witness,
+            )?,
+            None => hash_to_prime.prove(
+                verifier_channel,
+                rng2,
+                &hash_to_prime_statement,
+                &hash_to_prime_witness,
+            )?,
+        }
 
         Ok(())
     }
+}
 
+impl<G: ConvertibleUnknownOrderGroup, P: CurvePointProjective, HP: HashToPrimeProtocol<P>>
+    Protocol<G, P, HP>
+{
+    /// Runs every sub-check whatever the first failing one is, so that a
+    /// remote verifier doesn't reveal through timing which one failed. Only
+    /// channel errors and malformed CRSs end verification early. The integer
+    /// and curve arithmetic aren't constant-time, which is fine for public
+    /// values.
     pub fn verify<
         C: NonMembershipProverChannel<G>
             + CoprimeProverChannel<G>
@@ -209,32 +676,204 @@ impl<G: ConvertibleUnknownOrderGroup, P: CurvePointProjective, HP: HashToPrimePr
         prover_channel: &mut C,
         statement: &Statement<G, P>,
     ) -> Result<(), VerificationError> {
-        let c_e = prover_channel.receive_c_e()?;
+        let (sigma_result, partially_verified) =
+            self.preverify_deferred(prover_channel, statement)?;
+        let hash_to_prime_result = self.finalize(partially_verified);
+        sigma_result.and(hash_to_prime_result)
+    }
+
+    /// Runs the verification of the sigma protocols, which is cheap, and
+    /// receives the hash-to-prime proof without verifying it, so that
+    /// `finalize` can be called later, e.g. once a batch was pre-screened.
+    pub fn preverify<
+        C: NonMembershipProverChannel<G>
+            + CoprimeProverChannel<G>
+            + ModEqProverChannel<G, P>
+            + HashToPrimeProverChannel<P, HP>,
+    >(
+        &self,
+        prover_channel: &mut C,
+        statement: &Statement<G, P>,
+    ) -> Result<PartiallyVerified<P, HP>, VerificationError> {
+        let (sigma_result, partially_verified) =
+            self.preverify_deferred(prover_channel, statement)?;
+        sigma_result.map(|_| partially_verified)
+    }
+
+    /// Runs the checks of both sigma protocols even if the first one fails,
+    /// returning their result along with the received hash-to-prime proof.
+    /// Only channel errors, which don't depend on the values in the proof,
+    /// are returned early.
+    #[allow(clippy::type_complexity)]
+    fn preverify_deferred<
+        C: NonMembershipProverChannel<G>
+            + CoprimeProverChannel<G>
+            + ModEqProverChannel<G, P>
+            + HashToPrimeProverChannel<P, HP>,
+    >(
+        &self,
+        prover_channel: &mut C,
+        statement: &Statement<G, P>,
+    ) -> Result<(Result<(), VerificationError>, PartiallyVerified<P, HP>), VerificationError> {
+        let c_e = self.receive_c_e(prover_channel)?;
         let coprime = CoprimeProtocol::from_crs(&self.crs.crs_coprime)?;
-        coprime.verify(
+        let coprime_result = coprime.verify(
             prover_channel,
             &CoprimeStatement {
                 c_e: c_e.clone(),
                 acc: statement.c_p.clone(),
             },
-        )?;
+        );
         let modeq = ModEqProtocol::from_crs(&self.crs.crs_modeq);
-        modeq.verify(
+        let modeq_result = modeq.verify(
             prover_channel,
             &ModEqStatement {
                 c_e,
                 c_e_q: statement.c_e_q.clone(),
             },
-        )?;
+        );
+        let proof_hash_to_prime = prover_channel.receive_proof()?;
+
+        Ok((
+            coprime_result.and(modeq_result),
+            PartiallyVerified::new(
+                HashToPrimeStatement {
+                    c_e_q: statement.c_e_q.clone(),
+                },
+                proof_hash_to_prime,
+            ),
+        ))
+    }
+
+    /// Verifies only the sub-proofs in `checked`, e.g. when another system
+    /// already verified the others. The other sub-proofs are still received,
+    /// in the same order as by `verify`, so that the challenges of the checked
+    /// ones are derived from the same transcript as in a full verification,
+    /// but nothing about them is checked.
+    pub fn verify_partially<
+        C: NonMembershipProverChannel<G>
+            + CoprimeProverChannel<G>
+            + ModEqProverChannel<G, P>
+            + HashToPrimeProverChannel<P, HP>,
+    >(
+        &self,
+        prover_channel: &mut C,
+        statement: &Statement<G, P>,
+        checked: &[SubProtocol],
+    ) -> Result<(), VerificationError> {
+        let c_e = self.receive_c_e(prover_channel)?;
+        let coprime = CoprimeProtocol::from_crs(&self.crs.crs_coprime)?;
+        let coprime_statement = CoprimeStatement {
+            c_e: c_e.clone(),
+            acc: statement.c_p.clone(),
+        };
+        if checked.contains(&SubProtocol::Coprime) {
+            coprime.verify(prover_channel, &coprime_statement)?;
+        } else {
+            coprime.receive_unverified(prover_channel)?;
+        }
+        let modeq = ModEqProtocol::from_crs(&self.crs.crs_modeq);
+        let modeq_statement = ModEqStatement {
+            c_e,
+            c_e_q: statement.c_e_q.clone(),
+        };
+        if checked.contains(&SubProtocol::ModEq) {
+            modeq.verify(prover_channel, &modeq_statement)?;
+        } else {
+            modeq.receive_unverified(prover_channel)?;
+        }
+        let proof_hash_to_prime = prover_channel.receive_proof()?;
+        if checked.contains(&SubProtocol::HashToPrime) {
+            self.finalize(PartiallyVerified::new(
+                HashToPrimeStatement {
+                    c_e_q: statement.c_e_q.clone(),
+                },
+                proof_hash_to_prime,
+            ))?;
+        }
+
+        Ok(())
+    }
+
+    /// Verifies the hash-to-prime proof of a pre-verified proof.
+    pub fn finalize(
+        &self,
+        partially_verified: PartiallyVerified<P, HP>,
+    ) -> Result<(), VerificationError> {
         let hash_to_prime = HashToPrimeProtocol::from_crs(&self.crs.crs_hash_to_prime);
-        hash_to_prime.verify(
-            prover_channel,
-            &HashToPrimeStatement {
+        partially_verified.finalize(&hash_to_prime)
+    }
+
+    /// Verifies the hash-to-prime proofs of several pre-verified proofs in a
+    /// batch, which is faster than finalizing them one by one for SNARKs.
+    pub fn finalize_batch<R: RngCore + CryptoRng>(
+        &self,
+        rng: &mut R,
+        partially_verified: Vec<PartiallyVerified<P, HP>>,
+    ) -> Result<(), VerificationError> {
+        let hash_to_prime = HashToPrimeProtocol::from_crs(&self.crs.crs_hash_to_prime);
+        PartiallyVerified::finalize_batch(partially_verified, &hash_to_prime, rng)
+    }
+
+    /// Restores the alphas of the sub-protocols in a compressed proof, so it
+    /// can be verified as a regular proof.
+    #[cfg(feature = "compressed")]
+    pub fn decompress(
+        &self,
+        statement: &Statement<G, P>,
+        proof: &CompressedProof<G, P, HP>,
+    ) -> Result<Proof<G, P, HP>, VerificationError> {
+        let coprime = CoprimeProtocol::from_crs(&self.crs.crs_coprime)?;
+        let proof_coprime = coprime.decompress(
+            &CoprimeStatement {
+                c_e: proof.c_e.clone(),
+                acc: statement.c_p.clone(),
+            },
+            &proof.proof_coprime,
+        )?;
+        let modeq = ModEqProtocol::from_crs(&self.crs.crs_modeq);
+        let proof_modeq = modeq.decompress(
+            &ModEqStatement {
+                c_e: proof.c_e.clone(),
                 c_e_q: statement.c_e_q.clone(),
             },
+            &proof.proof_modeq,
         )?;
 
-        Ok(())
+        Ok(Proof {
+            crs_digest: proof.crs_digest,
+            c_e: proof.c_e.clone(),
+            proof_coprime,
+            proof_modeq,
+            proof_hash_to_prime: proof.proof_hash_to_prime.clone(),
+        })
+    }
+
+    /// Computes the key under which a successful verification of `proof` for
+    /// `statement` can be cached. Freshness data bound to the verification,
+    /// such as a nonce added to the transcript, has to be appended to `key`
+    /// by the caller beforehand.
+    pub fn cache_key(
+        &self,
+        mut key: CacheKeyBuilder,
+        statement: &Statement<G, P>,
+        proof: &Proof<G, P, HP>,
+    ) -> Result<CacheKey, VerificationError> {
+        key.append_bytes(b"protocol", b"nonmembership");
+        // The verifier's own digest binds the parameters and the
+        // hash-to-prime verifying key besides the commitment bases, so CRSs
+        // sharing bases don't share entries.
+        key.append_bytes(b"crs_digest", &self.crs_digest()?);
+        key.append_group_element::<G>(b"c_p", &statement.c_p);
+        key.append_curve_point(b"c_e_q", &statement.c_e_q)?;
+        key.append_group_element::<G>(b"c_e", &proof.c_e);
+        proof.proof_coprime.append_to_cache_key(&mut key);
+        proof.proof_modeq.append_to_cache_key(&mut key)?;
+        key.append_bytes(
+            b"proof_hash_to_prime",
+            &HP::proof_to_bytes(&proof.proof_hash_to_prime)?,
+        );
+        Ok(key.finish())
     }
 
     pub fn hash_to_prime(&self, e: &Integer) -> Result<(Integer, u64), HashToPrimeError> {
@@ -242,8 +881,59 @@ impl<G: ConvertibleUnknownOrderGroup, P: CurvePointProjective, HP: HashToPrimePr
         hash_to_prime.hash_to_prime(e)
     }
 
+    pub fn hash_to_prime_with_observer<O: ProgressObserver>(
+        &self,
+        e: &Integer,
+        observer: &O,
+    ) -> Result<(Integer, u64), HashToPrimeError> {
+        let hash_to_prime = HP::from_crs(&self.crs.crs_hash_to_prime);
+        hash_to_prime.hash_to_prime_with_observer(e, observer)
+    }
+
+    /// Wraps `crs`. Setup rejects groups coprime doesn't support, so a CRS
+    /// for one can only come from elsewhere, and proving and verifying with it
+    /// fail with `CRSInitError(CRSError::UnsupportedGroupForCoprime)`.
     pub fn from_crs(crs: &CRS<G, P, HP>) -> Protocol<G, P, HP> {
-        Protocol { crs: crs.clone() }
+        Self::with_crs(crs.clone())
+    }
+
+    /// Like `from_crs`, for verifiers relying on the element not being
+    /// accumulated under any of the primes it could map to. Fails if the
+    /// hash-to-prime protocol lets the prover choose among several primes of
+    /// an element, see `HashToPrimeProtocol::maps_to_unique_prime`.
+    pub fn from_crs_with_unique_prime(crs: &CRS<G, P, HP>) -> Result<Protocol<G, P, HP>, CRSError> {
+        if !HP::maps_to_unique_prime(&crs.crs_hash_to_prime.parameters) {
+            return Err(CRSError::InvalidParameters);
+        }
+        Ok(Self::from_crs(crs))
+    }
+
+    fn with_crs(crs: CRS<G, P, HP>) -> Protocol<G, P, HP> {
+        Protocol { crs }
+    }
+
+    /// The digest of `crs`, which every proof carries. It's computed on each
+    /// call, so it always matches the current `crs`.
+    pub fn crs_digest(&self) -> Result<CrsDigest, CurveError> {
+        self.crs.digest()
+    }
+
+    /// Receives the digest of the CRS the proof was created under, which has
+    /// to be the one of this protocol, and then `c_e`.
+    fn receive_c_e<C: NonMembershipProverChannel<G>>(
+        &self,
+        prover_channel: &mut C,
+    ) -> Result<<IntegerCommitment<G> as Commitment>::Instance, VerificationError> {
+        if prover_channel.receive_crs_digest()? != self.crs_digest()? {
+            return Err(VerificationError::CrsMismatch);
+        }
+        Ok(prover_channel.receive_c_e()?)
+    }
+
+    /// The maximum size of the elements `prove` accepts, larger ones are
+    /// rejected with `ProofError::ElementTooLarge`.
+    pub fn max_element_bits(&self) -> u16 {
+        HP::max_element_bits(&self.crs.parameters)
     }
 }
 
@@ -259,7 +949,9 @@ mod test {
                 snark_range::Protocol as HPProtocol,
             },
             nonmembership::transcript::{TranscriptProverChannel, TranscriptVerifierChannel},
+            CRSError, SetupError,
         },
+        utils::exp_signed,
     };
     use accumulator::group::{ClassGroup, Rsa2048};
     use accumulator::{group::Group, AccumulatorWithoutHashToPrime};
@@ -291,7 +983,12 @@ mod test {
         >::setup(&params, &mut rng1, &mut rng2)
         .unwrap()
         .crs;
-        let protocol = Protocol::<Rsa2048, G1Projective, HPProtocol<Bls12_381>>::from_crs(&crs);
+        // the range proof pins the prime
+        let protocol =
+            Protocol::<Rsa2048, G1Projective, HPProtocol<Bls12_381>>::from_crs_with_unique_prime(
+                &crs,
+            )
+            .unwrap();
 
         let value = Integer::from(Integer::u_pow_u(
             2,
@@ -322,7 +1019,7 @@ mod test {
         let d = non_mem_proof.d.clone();
         let b = non_mem_proof.b;
         assert_eq!(
-            Rsa2048::op(&Rsa2048::exp(&d, &value), &Rsa2048::exp(&acc, &b)),
+            Rsa2048::op(&Rsa2048::exp(&d, &value), &exp_signed::<Rsa2048>(&acc, &b)),
             protocol.crs.crs_coprime.integer_commitment_parameters.g
         );
 
@@ -353,9 +1050,25 @@ mod test {
         protocol.verify(&mut prover_channel, &statement).unwrap();
     }
 
-    // panics because coprime is not supported for class groups right now
+    #[cfg(not(feature = "class"))]
+    #[test]
+    fn test_class_group_unsupported() {
+        let params = Parameters::from_security_level(128).unwrap();
+        let mut rng1 = RandState::new();
+        rng1.seed(&Integer::from(13));
+        let mut rng2 = thread_rng();
+
+        // class groups need the `class` feature
+        assert!(matches!(
+            Protocol::<ClassGroup, G1Projective, HPProtocol<Bls12_381>>::setup(
+                &params, &mut rng1, &mut rng2
+            ),
+            Err(SetupError::UnsupportedGroupForCoprime)
+        ));
+    }
+
+    #[cfg(feature = "class")]
     #[test]
-    #[should_panic]
     fn test_e2e_prime_class_group() {
         let params = Parameters::from_security_level(128).unwrap();
         let mut rng1 = RandState::new();
@@ -400,7 +1113,10 @@ mod test {
         let d = non_mem_proof.d.clone();
         let b = non_mem_proof.b;
         assert_eq!(
-            ClassGroup::op(&ClassGroup::exp(&d, &value), &ClassGroup::exp(&acc, &b)),
+            ClassGroup::op(
+                &ClassGroup::exp(&d, &value),
+                &exp_signed::<ClassGroup>(&acc, &b)
+            ),
             protocol.crs.crs_coprime.integer_commitment_parameters.g
         );
 
@@ -455,6 +1171,16 @@ mod test {
             G1Projective,
             HPHashProtocol<Bls12_381, TestHashToPrimeParameters>,
         >::from_crs(&crs);
+        // the prover could pick a prime of the element other than the
+        // accumulated one
+        assert!(matches!(
+            Protocol::<
+                Rsa2048,
+                G1Projective,
+                HPHashProtocol<Bls12_381, TestHashToPrimeParameters>,
+            >::from_crs_with_unique_prime(&crs),
+            Err(CRSError::InvalidParameters)
+        ));
 
         let value = Integer::from(24_928_329);
         let (hashed_value, _) = protocol.hash_to_prime(&value).unwrap();
@@ -483,7 +1209,10 @@ mod test {
         let d = non_mem_proof.d.clone();
         let b = non_mem_proof.b;
         assert_eq!(
-            Rsa2048::op(&Rsa2048::exp(&d, &hashed_value), &Rsa2048::exp(&acc, &b)),
+            Rsa2048::op(
+                &Rsa2048::exp(&d, &hashed_value),
+                &exp_signed::<Rsa2048>(&acc, &b)
+            ),
             protocol.crs.crs_coprime.integer_commitment_parameters.g
         );
 
@@ -516,7 +1245,7 @@ mod test {
 }
 
 #[cfg(all(test, feature = "dalek"))]
-mod test {
+mod test_dalek {
     use super::{Protocol, Statement, Witness};
     use crate::{
         commitments::Commitment,
@@ -525,6 +1254,7 @@ mod test {
             hash_to_prime::bp::Protocol as HPProtocol,
             nonmembership::transcript::{TranscriptProverChannel, TranscriptVerifierChannel},
         },
+        utils::exp_signed,
     };
     use accumulator::group::Rsa2048;
     use accumulator::{group::Group, AccumulatorWithoutHashToPrime};
@@ -587,7 +1317,7 @@ mod test {
         let d = non_mem_proof.d.clone();
         let b = non_mem_proof.b;
         assert_eq!(
-            Rsa2048::op(&Rsa2048::exp(&d, &value), &Rsa2048::exp(&acc, &b)),
+            Rsa2048::op(&Rsa2048::exp(&d, &value), &exp_signed::<Rsa2048>(&acc, &b)),
             protocol.crs.crs_modeq.integer_commitment_parameters.g
         );
 
@@ -1,5 +1,7 @@
+#[cfg(feature = "compressed")]
+use crate::protocols::nonmembership::CompressedProof;
 use crate::{
-    channels::ChannelError,
+    channels::{ChannelError, MessageOrder},
     commitments::{integer::IntegerCommitment, Commitment},
     protocols::{
         coprime::{
@@ -28,13 +30,19 @@ use crate::{
         },
         nonmembership::{
             channel::{NonMembershipProverChannel, NonMembershipVerifierChannel},
-            Proof, CRS,
+            Proof, Statement, Witness, CRS,
         },
+        registry::CrsDigest,
     },
-    transcript::{TranscriptChannelError, TranscriptProtocolChallenge, TranscriptProtocolInteger},
-    utils::{curve::CurvePointProjective, ConvertibleUnknownOrderGroup},
+    transcript::{
+        versioned_domain_sep, TranscriptChannelError, TranscriptProtocolChallenge,
+        TranscriptProtocolInteger, TranscriptProtocolVerifierChallenge,
+        TranscriptProtocolWitnessRng, VerifierChallenge, WitnessRng,
+    },
+    utils::{curve::CurvePointProjective, integer_to_signed_bytes, ConvertibleUnknownOrderGroup},
 };
 use merlin::Transcript;
+use rand::{CryptoRng, RngCore};
 use rug::Integer;
 use std::cell::RefCell;
 
@@ -42,11 +50,16 @@ pub trait TranscriptProtocolNonMembership<G: ConvertibleUnknownOrderGroup>:
     TranscriptProtocolInteger<G> + TranscriptProtocolChallenge
 {
     fn nonmembership_domain_sep(&mut self);
+    fn append_crs_digest(&mut self, crs_digest: &CrsDigest);
 }
 
 impl<G: ConvertibleUnknownOrderGroup> TranscriptProtocolNonMembership<G> for Transcript {
     fn nonmembership_domain_sep(&mut self) {
-        self.append_message(b"dom-sep", b"nonmembership");
+        self.append_message(b"dom-sep", &versioned_domain_sep(b"nonmembership"));
+    }
+
+    fn append_crs_digest(&mut self, crs_digest: &CrsDigest) {
+        self.append_message(b"crs_digest", crs_digest);
     }
 }
 pub struct TranscriptVerifierChannel<
@@ -60,6 +73,8 @@ pub struct TranscriptVerifierChannel<
         + TranscriptProtocolHashToPrime<P>,
 > {
     transcript: &'a RefCell<T>,
+    order: MessageOrder,
+    crs_digest: Option<CrsDigest>,
     c_e: Option<<IntegerCommitment<G> as Commitment>::Instance>,
     coprime_transcript_verifier_channel: CoprimeTranscriptVerifierChannel<'a, G, T>,
     modeq_transcript_verifier_channel: ModEqTranscriptVerifierChannel<'a, G, P, T>,
@@ -83,6 +98,8 @@ impl<
     ) -> TranscriptVerifierChannel<'a, G, P, HP, T> {
         TranscriptVerifierChannel {
             transcript,
+            order: MessageOrder::new(),
+            crs_digest: None,
             c_e: None,
             coprime_transcript_verifier_channel: CoprimeTranscriptVerifierChannel::new(
                 &crs.crs_coprime,
@@ -103,9 +120,30 @@ impl<
         let proof_coprime = self.coprime_transcript_verifier_channel.proof()?;
         let proof_modeq = self.modeq_transcript_verifier_channel.proof()?;
         let proof_hash_to_prime = self.hash_to_prime_transcript_verifier_channel.proof()?;
-        if self.c_e.is_some() {
+        if let (Some(crs_digest), Some(c_e)) = (self.crs_digest, &self.c_e) {
             Ok(Proof {
-                c_e: self.c_e.as_ref().unwrap().clone(),
+                crs_digest,
+                c_e: c_e.clone(),
+                proof_coprime,
+                proof_modeq,
+                proof_hash_to_prime,
+            })
+        } else {
+            Err(TranscriptChannelError::Incomplete)
+        }
+    }
+
+    #[cfg(feature = "compressed")]
+    pub fn compressed_proof(&self) -> Result<CompressedProof<G, P, HP>, TranscriptChannelError> {
+        let proof_coprime = self
+            .coprime_transcript_verifier_channel
+            .compressed_proof()?;
+        let proof_modeq = self.modeq_transcript_verifier_channel.compressed_proof()?;
+        let proof_hash_to_prime = self.hash_to_prime_transcript_verifier_channel.proof()?;
+        if let (Some(crs_digest), Some(c_e)) = (self.crs_digest, &self.c_e) {
+            Ok(CompressedProof {
+                crs_digest,
+                c_e: c_e.clone(),
                 proof_coprime,
                 proof_modeq,
                 proof_hash_to_prime,
@@ -116,6 +154,67 @@ impl<
     }
 }
 
+impl<
+        'a,
+        G: ConvertibleUnknownOrderGroup,
+        P: CurvePointProjective,
+        HP: HashToPrimeProtocol<P>,
+        T: TranscriptProtocolNonMembership<G>
+            + TranscriptProtocolCoprime<G>
+            + TranscriptProtocolModEq<G, P>
+            + TranscriptProtocolHashToPrime<P>
+            + TranscriptProtocolWitnessRng,
+    > TranscriptVerifierChannel<'a, G, P, HP, T>
+{
+    /// Derives the RNGs for the integer and the curve randomness of the
+    /// prover from the transcript, the statement, the witness and `rng`.
+    pub fn witness_rngs<R: RngCore + CryptoRng>(
+        &self,
+        statement: &Statement<G, P>,
+        witness: &Witness<G>,
+        rng: &mut R,
+    ) -> Result<(WitnessRng, WitnessRng), ChannelError> {
+        let transcript = self.transcript.try_borrow()?;
+        let witness_bytes: [&[u8]; 5] = [
+            &statement.digest()?,
+            &integer_to_signed_bytes(&witness.e),
+            &integer_to_signed_bytes(&witness.r_q),
+            &G::elem_to_bytes(&witness.d),
+            &integer_to_signed_bytes(&witness.b),
+        ];
+        Ok((
+            transcript.witness_rng(b"integer", &witness_bytes, rng),
+            transcript.witness_rng(b"curve", &witness_bytes, rng),
+        ))
+    }
+}
+
+impl<
+        'a,
+        G: ConvertibleUnknownOrderGroup,
+        P: CurvePointProjective,
+        HP: HashToPrimeProtocol<P>,
+        T: TranscriptProtocolNonMembership<G>
+            + TranscriptProtocolCoprime<G>
+            + TranscriptProtocolModEq<G, P>
+            + TranscriptProtocolHashToPrime<P>
+            + TranscriptProtocolVerifierChallenge,
+    > TranscriptVerifierChannel<'a, G, P, HP, T>
+{
+    /// Like `new`, absorbing the verifier's `challenge` first, so that every
+    /// challenge of the proof depends on it.
+    pub fn with_verifier_challenge(
+        crs: &CRS<G, P, HP>,
+        transcript: &'a RefCell<T>,
+        challenge: &VerifierChallenge,
+    ) -> Result<TranscriptVerifierChannel<'a, G, P, HP, T>, ChannelError> {
+        transcript
+            .try_borrow_mut()?
+            .append_verifier_challenge(challenge);
+        Ok(Self::new(crs, transcript))
+    }
+}
+
 impl<
         'a,
         G: ConvertibleUnknownOrderGroup,
@@ -131,6 +230,7 @@ impl<
         &mut self,
         message: &crate::protocols::coprime::Message1<G>,
     ) -> Result<(), ChannelError> {
+        self.order.enter(2)?;
         self.coprime_transcript_verifier_channel
             .send_message1(message)
     }
@@ -138,6 +238,7 @@ impl<
         &mut self,
         message: &crate::protocols::coprime::Message2<G>,
     ) -> Result<(), ChannelError> {
+        self.order.enter(2)?;
         self.coprime_transcript_verifier_channel
             .send_message2(message)
     }
@@ -145,10 +246,12 @@ impl<
         &mut self,
         message: &crate::protocols::coprime::Message3,
     ) -> Result<(), ChannelError> {
+        self.order.enter(2)?;
         self.coprime_transcript_verifier_channel
             .send_message3(message)
     }
     fn receive_challenge(&mut self) -> Result<Integer, ChannelError> {
+        self.order.enter(2)?;
         self.coprime_transcript_verifier_channel.receive_challenge()
     }
 }
@@ -168,6 +271,7 @@ impl<
         &mut self,
         message: &crate::protocols::modeq::Message1<G, P>,
     ) -> Result<(), ChannelError> {
+        self.order.enter(3)?;
         self.modeq_transcript_verifier_channel
             .send_message1(message)
     }
@@ -175,10 +279,12 @@ impl<
         &mut self,
         message: &crate::protocols::modeq::Message2<P>,
     ) -> Result<(), ChannelError> {
+        self.order.enter(3)?;
         self.modeq_transcript_verifier_channel
             .send_message2(message)
     }
     fn receive_challenge(&mut self) -> Result<Integer, ChannelError> {
+        self.order.enter(3)?;
         self.modeq_transcript_verifier_channel.receive_challenge()
     }
 }
@@ -195,6 +301,7 @@ impl<
     > HashToPrimeVerifierChannel<P, HP> for TranscriptVerifierChannel<'a, G, P, HP, T>
 {
     fn send_proof(&mut self, proof: &HP::Proof) -> Result<(), ChannelError> {
+        self.order.enter(4)?;
         self.hash_to_prime_transcript_verifier_channel
             .send_proof(proof)
     }
@@ -211,6 +318,7 @@ pub struct TranscriptProverChannel<
         + TranscriptProtocolHashToPrime<P>,
 > {
     transcript: &'a RefCell<T>,
+    order: MessageOrder,
     coprime_transcript_prover_channel: CoprimeTranscriptProverChannel<'a, G, T>,
     modeq_transcript_prover_channel: ModEqTranscriptProverChannel<'a, G, P, T>,
     hash_to_prime_transcript_prover_channel: HashToPrimeTranscriptProverChannel<'a, P, HP, T>,
@@ -229,15 +337,19 @@ impl<
     > CoprimeProverChannel<G> for TranscriptProverChannel<'a, G, P, HP, T>
 {
     fn receive_message1(&mut self) -> Result<crate::protocols::coprime::Message1<G>, ChannelError> {
+        self.order.enter(2)?;
         self.coprime_transcript_prover_channel.receive_message1()
     }
     fn receive_message2(&mut self) -> Result<crate::protocols::coprime::Message2<G>, ChannelError> {
+        self.order.enter(2)?;
         self.coprime_transcript_prover_channel.receive_message2()
     }
     fn receive_message3(&mut self) -> Result<crate::protocols::coprime::Message3, ChannelError> {
+        self.order.enter(2)?;
         self.coprime_transcript_prover_channel.receive_message3()
     }
     fn generate_and_send_challenge(&mut self) -> Result<Integer, ChannelError> {
+        self.order.enter(2)?;
         self.coprime_transcript_prover_channel
             .generate_and_send_challenge()
     }
@@ -257,12 +369,15 @@ impl<
     fn receive_message1(
         &mut self,
     ) -> Result<crate::protocols::modeq::Message1<G, P>, ChannelError> {
+        self.order.enter(3)?;
         self.modeq_transcript_prover_channel.receive_message1()
     }
     fn receive_message2(&mut self) -> Result<crate::protocols::modeq::Message2<P>, ChannelError> {
+        self.order.enter(3)?;
         self.modeq_transcript_prover_channel.receive_message2()
     }
     fn generate_and_send_challenge(&mut self) -> Result<Integer, ChannelError> {
+        self.order.enter(3)?;
         self.modeq_transcript_prover_channel
             .generate_and_send_challenge()
     }
@@ -280,6 +395,7 @@ impl<
     > HashToPrimeProverChannel<P, HP> for TranscriptProverChannel<'a, G, P, HP, T>
 {
     fn receive_proof(&mut self) -> Result<HP::Proof, ChannelError> {
+        self.order.enter(4)?;
         self.hash_to_prime_transcript_prover_channel.receive_proof()
     }
 }
@@ -295,12 +411,21 @@ impl<
             + TranscriptProtocolHashToPrime<P>,
     > NonMembershipVerifierChannel<G> for TranscriptVerifierChannel<'a, G, P, HP, T>
 {
+    fn send_crs_digest(&mut self, crs_digest: &CrsDigest) -> Result<(), ChannelError> {
+        self.order.step(0)?;
+        let mut transcript = self.transcript.try_borrow_mut()?;
+        transcript.nonmembership_domain_sep();
+        transcript.append_crs_digest(crs_digest);
+        self.crs_digest = Some(*crs_digest);
+        Ok(())
+    }
+
     fn send_c_e(
         &mut self,
         c_e: &<IntegerCommitment<G> as Commitment>::Instance,
     ) -> Result<(), ChannelError> {
+        self.order.step(1)?;
         let mut transcript = self.transcript.try_borrow_mut()?;
-        transcript.nonmembership_domain_sep();
         transcript.append_integer_point(b"c_e", c_e);
         self.c_e = Some(c_e.clone());
         Ok(())
@@ -318,11 +443,19 @@ impl<
             + TranscriptProtocolHashToPrime<P>,
     > NonMembershipProverChannel<G> for TranscriptProverChannel<'a, G, P, HP, T>
 {
+    fn receive_crs_digest(&mut self) -> Result<CrsDigest, ChannelError> {
+        self.order.step(0)?;
+        let mut transcript = self.transcript.try_borrow_mut()?;
+        transcript.nonmembership_domain_sep();
+        transcript.append_crs_digest(&self.proof.crs_digest);
+        Ok(self.proof.crs_digest)
+    }
+
     fn receive_c_e(
         &mut self,
     ) -> Result<<IntegerCommitment<G> as Commitment>::Instance, ChannelError> {
+        self.order.step(1)?;
         let mut transcript = self.transcript.try_borrow_mut()?;
-        transcript.nonmembership_domain_sep();
         transcript.append_integer_point(b"c_e", &self.proof.c_e);
         Ok(self.proof.c_e.clone())
     }
@@ -346,6 +479,7 @@ impl<
     ) -> TranscriptProverChannel<'a, G, P, HP, T> {
         TranscriptProverChannel {
             transcript,
+            order: MessageOrder::new(),
             coprime_transcript_prover_channel: CoprimeTranscriptProverChannel::new(
                 &crs.crs_coprime,
                 transcript,
@@ -364,4 +498,36 @@ impl<
             proof: proof.clone(),
         }
     }
+
+    /// See `hash_to_prime::transcript::TranscriptProverChannel::pre_proof_digest`.
+    pub fn pre_proof_digest(&self) -> Option<[u8; 32]> {
+        self.hash_to_prime_transcript_prover_channel
+            .pre_proof_digest()
+    }
+}
+
+impl<
+        'a,
+        G: ConvertibleUnknownOrderGroup,
+        P: CurvePointProjective,
+        HP: HashToPrimeProtocol<P>,
+        T: TranscriptProtocolNonMembership<G>
+            + TranscriptProtocolCoprime<G>
+            + TranscriptProtocolModEq<G, P>
+            + TranscriptProtocolHashToPrime<P>
+            + TranscriptProtocolVerifierChallenge,
+    > TranscriptProverChannel<'a, G, P, HP, T>
+{
+    /// Like `new`, absorbing the `challenge` the verifier handed the prover.
+    pub fn with_verifier_challenge(
+        crs: &CRS<G, P, HP>,
+        transcript: &'a RefCell<T>,
+        proof: &Proof<G, P, HP>,
+        challenge: &VerifierChallenge,
+    ) -> Result<TranscriptProverChannel<'a, G, P, HP, T>, ChannelError> {
+        transcript
+            .try_borrow_mut()?
+            .append_verifier_challenge(challenge);
+        Ok(Self::new(crs, transcript, proof))
+    }
 }
@@ -0,0 +1,590 @@
+//! Membership in one of `k` accumulators, without revealing which, e.g. for
+//! federated registries with one accumulator per issuer where the verifier
+//! shouldn't learn the issuer.
+//!
+//! The proof is a membership proof whose root proof is replaced by an OR of
+//! `k` root proofs sharing `c_e`, one per accumulator. The prover simulates
+//! the branches it has no witness for, with challenges of its choice, and the
+//! challenge of the remaining branch is the XOR of the challenge derived from
+//! the transcript with the others, so that at least one branch is proven
+//! honestly. The modeq and hash-to-prime proofs are the ones of the membership
+//! protocol. The proofs are non-interactive only.
+use crate::{
+    channels::ChannelError,
+    commitments::{integer::IntegerCommitment, pedersen::PedersenCommitment, Commitment},
+    protocols::{
+        hash_to_prime::{
+            transcript::{
+                TranscriptProtocolHashToPrime,
+                TranscriptProverChannel as HashToPrimeTranscriptProverChannel,
+                TranscriptVerifierChannel as HashToPrimeTranscriptVerifierChannel,
+            },
+            validate_element_size, validate_witness, HashToPrimeProtocol,
+            Statement as HashToPrimeStatement, Witness as HashToPrimeWitness,
+        },
+        membership::{Protocol as MembershipProtocol, CRS},
+        modeq::{
+            transcript::{
+                TranscriptProtocolModEq, TranscriptProverChannel as ModEqTranscriptProverChannel,
+                TranscriptVerifierChannel as ModEqTranscriptVerifierChannel,
+            },
+            Proof as ModEqProof, Protocol as ModEqProtocol, Statement as ModEqStatement,
+            Witness as ModEqWitness,
+        },
+        root::{
+            channel::{RootProverChannel, RootVerifierChannel},
+            Message1 as RootMessage1, Message2 as RootMessage2, Message3 as RootMessage3,
+            Proof as RootProof, Protocol as RootProtocol, Statement as RootStatement,
+            Witness as RootWitness,
+        },
+        simulation::simulate_root_with_challenge,
+        ProofError, VerificationError,
+    },
+    transcript::{versioned_domain_sep, TranscriptProtocolChallenge, TranscriptProtocolInteger},
+    utils::{curve::CurvePointProjective, random_between, ConvertibleUnknownOrderGroup},
+};
+use merlin::Transcript;
+use rand::{CryptoRng, RngCore};
+use rug::rand::MutRandState;
+use rug::Integer;
+use std::cell::RefCell;
+
+pub trait TranscriptProtocolOneOf<G: ConvertibleUnknownOrderGroup>:
+    TranscriptProtocolInteger<G> + TranscriptProtocolChallenge
+{
+    fn one_of_domain_sep(&mut self);
+}
+
+impl<G: ConvertibleUnknownOrderGroup> TranscriptProtocolOneOf<G> for Transcript {
+    fn one_of_domain_sep(&mut self) {
+        self.append_message(b"dom-sep", &versioned_domain_sep(b"one_of"));
+    }
+}
+
+pub struct Statement<G: ConvertibleUnknownOrderGroup, P: CurvePointProjective> {
+    /// The accumulators, e.g. one per issuer.
+    pub c_ps: Vec<G::Elem>,
+    pub c_e_q: <PedersenCommitment<P> as Commitment>::Instance,
+}
+
+impl<G: ConvertibleUnknownOrderGroup, P: CurvePointProjective> Clone for Statement<G, P> {
+    fn clone(&self) -> Self {
+        Self {
+            c_ps: self.c_ps.clone(),
+            c_e_q: self.c_e_q.clone(),
+        }
+    }
+}
+
+/// The witness of membership in the accumulator `c_ps[index]`.
+pub struct Witness<G: ConvertibleUnknownOrderGroup> {
+    pub index: usize,
+    pub e: Integer,
+    pub r_q: Integer,
+    pub w: G::Elem,
+}
+
+pub struct Proof<
+    G: ConvertibleUnknownOrderGroup,
+    P: CurvePointProjective,
+    HP: HashToPrimeProtocol<P>,
+> {
+    pub c_e: <IntegerCommitment<G> as Commitment>::Instance,
+    /// One root proof per accumulator.
+    pub proofs_root: Vec<RootProof<G>>,
+    /// The challenges of the root proofs, whose XOR is the challenge derived
+    /// from the transcript.
+    pub challenges_root: Vec<Integer>,
+    pub proof_modeq: ModEqProof<G, P>,
+    pub proof_hash_to_prime: HP::Proof,
+}
+
+impl<G: ConvertibleUnknownOrderGroup, P: CurvePointProjective, HP: HashToPrimeProtocol<P>> Clone
+    for Proof<G, P, HP>
+{
+    fn clone(&self) -> Self {
+        Self {
+            c_e: self.c_e.clone(),
+            proofs_root: self.proofs_root.clone(),
+            challenges_root: self.challenges_root.clone(),
+            proof_modeq: self.proof_modeq.clone(),
+            proof_hash_to_prime: self.proof_hash_to_prime.clone(),
+        }
+    }
+}
+
+pub struct Protocol<
+    G: ConvertibleUnknownOrderGroup,
+    P: CurvePointProjective,
+    HP: HashToPrimeProtocol<P>,
+> {
+    pub crs: CRS<G, P, HP>,
+}
+
+/// Appends the first two messages of every branch and derives the challenge
+/// they share.
+fn branches_challenge<G: ConvertibleUnknownOrderGroup, T: TranscriptProtocolOneOf<G>>(
+    transcript: &RefCell<T>,
+    security_soundness: u16,
+    c_ps: &[G::Elem],
+    messages: &[(&RootMessage1<G>, &RootMessage2<G>)],
+) -> Result<Integer, ChannelError> {
+    let mut transcript = transcript.try_borrow_mut()?;
+    transcript.one_of_domain_sep();
+    for (c_p, (message1, message2)) in c_ps.iter().zip(messages) {
+        transcript.append_integer_point(b"c_p", c_p);
+        transcript.append_integer_point(b"c_w", &message1.c_w);
+        transcript.append_integer_point(b"c_r", &message1.c_r);
+        transcript.append_integer_point(b"alpha1", &message2.alpha1);
+        transcript.append_integer_point(b"alpha2", &message2.alpha2);
+        transcript.append_integer_point(b"alpha3", &message2.alpha3);
+        transcript.append_integer_point(b"alpha4", &message2.alpha4);
+    }
+    Ok(transcript.challenge_scalar(b"c", security_soundness))
+}
+
+fn xor_all<'a, I: Iterator<Item = &'a Integer>>(challenges: I) -> Integer {
+    challenges.fold(Integer::from(0), |acc, c| acc ^ c)
+}
+
+/// Runs the root protocol of the branch with a witness, answering its
+/// challenge once the simulated branches and its own messages are known.
+struct ProvenBranchChannel<'a, G: ConvertibleUnknownOrderGroup, T: TranscriptProtocolOneOf<G>> {
+    transcript: &'a RefCell<T>,
+    security_soundness: u16,
+    c_ps: &'a [G::Elem],
+    simulated: &'a [Option<(RootProof<G>, Integer)>],
+    message1: Option<RootMessage1<G>>,
+    message2: Option<RootMessage2<G>>,
+    message3: Option<RootMessage3>,
+    challenge: Option<Integer>,
+}
+
+impl<'a, G: ConvertibleUnknownOrderGroup, T: TranscriptProtocolOneOf<G>> RootVerifierChannel<G>
+    for ProvenBranchChannel<'a, G, T>
+{
+    fn send_message1(&mut self, message: &RootMessage1<G>) -> Result<(), ChannelError> {
+        self.message1 = Some(message.clone());
+        Ok(())
+    }
+    fn send_message2(&mut self, message: &RootMessage2<G>) -> Result<(), ChannelError> {
+        self.message2 = Some(message.clone());
+        Ok(())
+    }
+    fn send_message3(&mut self, message: &RootMessage3) -> Result<(), ChannelError> {
+        self.message3 = Some(message.clone());
+        Ok(())
+    }
+    fn receive_challenge(&mut self) -> Result<Integer, ChannelError> {
+        let (message1, message2) = match (&self.message1, &self.message2) {
+            (Some(message1), Some(message2)) => (message1, message2),
+            _ => return Err(ChannelError::OutOfOrder),
+        };
+        let messages = self
+            .simulated
+            .iter()
+            .map(|branch| match branch {
+                Some((proof, _)) => (&proof.message1, &proof.message2),
+                None => (message1, message2),
+            })
+            .collect::<Vec<_>>();
+        let c = branches_challenge(
+            self.transcript,
+            self.security_soundness,
+            self.c_ps,
+            &messages,
+        )?;
+        let c_index = c ^ xor_all(self.simulated.iter().flatten().map(|(_, c)| c));
+        self.challenge = Some(c_index.clone());
+        Ok(c_index)
+    }
+}
+
+/// Replays a branch of a proof with its challenge.
+struct BranchChannel<'a, G: ConvertibleUnknownOrderGroup> {
+    proof: &'a RootProof<G>,
+    challenge: &'a Integer,
+}
+
+impl<'a, G: ConvertibleUnknownOrderGroup> RootProverChannel<G> for BranchChannel<'a, G> {
+    fn receive_message1(&mut self) -> Result<RootMessage1<G>, ChannelError> {
+        Ok(self.proof.message1.clone())
+    }
+    fn receive_message2(&mut self) -> Result<RootMessage2<G>, ChannelError> {
+        Ok(self.proof.message2.clone())
+    }
+    fn receive_message3(&mut self) -> Result<RootMessage3, ChannelError> {
+        Ok(self.proof.message3.clone())
+    }
+    fn generate_and_send_challenge(&mut self) -> Result<Integer, ChannelError> {
+        Ok(self.challenge.clone())
+    }
+}
+
+impl<G: ConvertibleUnknownOrderGroup, P: CurvePointProjective, HP: HashToPrimeProtocol<P>>
+    Protocol<G, P, HP>
+{
+    /// The accumulators all use `crs`, e.g. with one `SetAccumulator` per
+    /// issuer over the same group.
+    pub fn from_crs(crs: &CRS<G, P, HP>) -> Protocol<G, P, HP> {
+        Protocol { crs: crs.clone() }
+    }
+
+    pub fn prove<
+        R1: MutRandState,
+        R2: RngCore + CryptoRng,
+        T: TranscriptProtocolOneOf<G>
+            + TranscriptProtocolModEq<G, P>
+            + TranscriptProtocolHashToPrime<P>,
+    >(
+        &self,
+        transcript: &RefCell<T>,
+        rng1: &mut R1,
+        rng2: &mut R2,
+        statement: &Statement<G, P>,
+        witness: &Witness<G>,
+    ) -> Result<Proof<G, P, HP>, ProofError> {
+        if witness.index >= statement.c_ps.len() {
+            return Err(ProofError::CouldNotCreateProof);
+        }
+        let parameters = &self.crs.parameters;
+        validate_element_size::<P, HP>(parameters, &witness.e)?;
+        let (hashed_e, _) = MembershipProtocol::from_crs(&self.crs).hash_to_prime(&witness.e)?;
+        validate_witness::<P>(parameters, &hashed_e, &witness.r_q)?;
+        let r = random_between(rng1, &Integer::from(0), &G::order_upper_bound());
+        let c_e = self
+            .crs
+            .crs_root
+            .integer_commitment_parameters
+            .commit(&hashed_e, &r)?;
+        {
+            let mut transcript = transcript.try_borrow_mut().map_err(ChannelError::from)?;
+            transcript.one_of_domain_sep();
+            transcript.append_integer_point(b"c_e", &c_e);
+        }
+
+        let root = RootProtocol::from_crs(&self.crs.crs_root)?;
+        let challenge_bound = Integer::from(Integer::u_pow_u(
+            2,
+            self.crs.crs_root.parameters.security_soundness as u32,
+        ));
+        let mut simulated = Vec::with_capacity(statement.c_ps.len());
+        for (i, c_p) in statement.c_ps.iter().enumerate() {
+            if i == witness.index {
+                simulated.push(None);
+                continue;
+            }
+            let c = challenge_bound.clone().random_below(rng1);
+            let proof = simulate_root_with_challenge(
+                &root,
+                &RootStatement {
+                    c_e: c_e.clone(),
+                    acc: c_p.clone(),
+                },
+                &c,
+                rng1,
+            )
+            .map_err(|_| ProofError::CouldNotCreateProof)?;
+            simulated.push(Some((proof, c)));
+        }
+
+        let mut branch_channel = ProvenBranchChannel {
+            transcript,
+            security_soundness: self.crs.crs_root.parameters.security_soundness,
+            c_ps: &statement.c_ps,
+            simulated: &simulated,
+            message1: None,
+            message2: None,
+            message3: None,
+            challenge: None,
+        };
+        root.prove(
+            &mut branch_channel,
+            rng1,
+            &RootStatement {
+                c_e: c_e.clone(),
+                acc: statement.c_ps[witness.index].clone(),
+            },
+            &RootWitness {
+                e: hashed_e.clone(),
+                r: r.clone(),
+                w: witness.w.clone(),
+            },
+        )?;
+        let ProvenBranchChannel {
+            message1,
+            message2,
+            message3,
+            challenge,
+            ..
+        } = branch_channel;
+        let proven = match (message1, message2, message3, challenge) {
+            (Some(message1), Some(message2), Some(message3), Some(challenge)) => (
+                RootProof {
+                    message1,
+                    message2,
+                    message3,
+                },
+                challenge,
+            ),
+            _ => return Err(ProofError::CouldNotCreateProof),
+        };
+        let mut proven = Some(proven);
+        let (proofs_root, challenges_root) = simulated
+            .into_iter()
+            .enumerate()
+            .map(|(i, branch)| match branch {
+                Some(branch) => Ok(branch),
+                None if i == witness.index => proven.take().ok_or(ProofError::CouldNotCreateProof),
+                None => Err(ProofError::CouldNotCreateProof),
+            })
+            .collect::<Result<Vec<_>, _>>()?
+            .into_iter()
+            .unzip();
+
+        let mut modeq_channel =
+            ModEqTranscriptVerifierChannel::new(&self.crs.crs_modeq, transcript);
+        ModEqProtocol::from_crs(&self.crs.crs_modeq).prove(
+            &mut modeq_channel,
+            rng1,
+            rng2,
+            &ModEqStatement {
+                c_e: c_e.clone(),
+                c_e_q: statement.c_e_q.clone(),
+            },
+            &ModEqWitness {
+                e: hashed_e,
+                r,
+                r_q: witness.r_q.clone(),
+            },
+        )?;
+        let proof_modeq = modeq_channel
+            .proof()
+            .map_err(|_| ProofError::CouldNotCreateProof)?;
+
+        let mut hash_to_prime_channel =
+            HashToPrimeTranscriptVerifierChannel::new(&self.crs.crs_hash_to_prime, transcript);
+        HP::from_crs(&self.crs.crs_hash_to_prime).prove(
+            &mut hash_to_prime_channel,
+            rng2,
+            &HashToPrimeStatement {
+                c_e_q: statement.c_e_q.clone(),
+            },
+            &HashToPrimeWitness {
+                e: witness.e.clone(),
+                r_q: witness.r_q.clone(),
+            },
+        )?;
+        let proof_hash_to_prime = hash_to_prime_channel
+            .proof()
+            .map_err(|_| ProofError::CouldNotCreateProof)?;
+
+        Ok(Proof {
+            c_e,
+            proofs_root,
+            challenges_root,
+            proof_modeq,
+            proof_hash_to_prime,
+        })
+    }
+
+    /// Verifies every branch, the modeq and the hash-to-prime proofs, whatever
+    /// the first failing one is.
+    pub fn verify<
+        T: TranscriptProtocolOneOf<G>
+            + TranscriptProtocolModEq<G, P>
+            + TranscriptProtocolHashToPrime<P>,
+    >(
+        &self,
+        transcript: &RefCell<T>,
+        statement: &Statement<G, P>,
+        proof: &Proof<G, P, HP>,
+    ) -> Result<(), VerificationError> {
+        let k = statement.c_ps.len();
+        if k == 0 || proof.proofs_root.len() != k || proof.challenges_root.len() != k {
+            return Err(VerificationError::VerificationFailed);
+        }
+        {
+            let mut transcript = transcript.try_borrow_mut().map_err(ChannelError::from)?;
+            transcript.one_of_domain_sep();
+            transcript.append_integer_point(b"c_e", &proof.c_e);
+        }
+
+        let security_soundness = self.crs.crs_root.parameters.security_soundness;
+        let messages = proof
+            .proofs_root
+            .iter()
+            .map(|p| (&p.message1, &p.message2))
+            .collect::<Vec<_>>();
+        let c = branches_challenge(transcript, security_soundness, &statement.c_ps, &messages)?;
+        let challenges_result = if (xor_all(proof.challenges_root.iter()) == c)
+            & proof
+                .challenges_root
+                .iter()
+                .all(|c| *c >= 0 && c.significant_bits() <= security_soundness as u32)
+        {
+            Ok(())
+        } else {
+            Err(VerificationError::VerificationFailed)
+        };
+
+        let root = RootProtocol::from_crs(&self.crs.crs_root)?;
+        let root_result = statement
+            .c_ps
+            .iter()
+            .zip(proof.proofs_root.iter().zip(&proof.challenges_root))
+            .map(|(c_p, (proof_root, challenge))| {
+                root.verify(
+                    &mut BranchChannel {
+                        proof: proof_root,
+                        challenge,
+                    },
+                    &RootStatement {
+                        c_e: proof.c_e.clone(),
+                        acc: c_p.clone(),
+                    },
+                )
+            })
+            .fold(challenges_result, |acc, r| acc.and(r));
+
+        let modeq_result = ModEqProtocol::from_crs(&self.crs.crs_modeq).verify(
+            &mut ModEqTranscriptProverChannel::new(
+                &self.crs.crs_modeq,
+                transcript,
+                &proof.proof_modeq,
+            ),
+            &ModEqStatement {
+                c_e: proof.c_e.clone(),
+                c_e_q: statement.c_e_q.clone(),
+            },
+        );
+        let hash_to_prime_result = HP::from_crs(&self.crs.crs_hash_to_prime).verify(
+            &mut HashToPrimeTranscriptProverChannel::new(
+                &self.crs.crs_hash_to_prime,
+                transcript,
+                &proof.proof_hash_to_prime,
+            ),
+            &HashToPrimeStatement {
+                c_e_q: statement.c_e_q.clone(),
+            },
+        );
+
+        root_result.and(modeq_result).and(hash_to_prime_result)
+    }
+}
+
+#[cfg(all(test, feature = "arkworks"))]
+mod test {
+    use super::{Protocol, Statement, Witness};
+    use crate::{
+        commitments::Commitment,
+        parameters::Parameters,
+        protocols::{
+            hash_to_prime::snark_range::Protocol as HPProtocol,
+            membership::Protocol as MembershipProtocol,
+            root::{Protocol as RootProtocol, Statement as RootStatement},
+        },
+        set::{AccumulatorProvider, SetAccumulator},
+    };
+    use accumulator::group::Rsa2048;
+    use ark_bls12_381::{Bls12_381, G1Projective};
+    use merlin::Transcript;
+    use rand::thread_rng;
+    use rug::rand::RandState;
+    use rug::Integer;
+    use std::cell::RefCell;
+
+    #[test]
+    fn test_one_of() {
+        let params = Parameters::from_security_level(128).unwrap();
+        let mut rng1 = RandState::new();
+        rng1.seed(&Integer::from(13));
+        let mut rng2 = thread_rng();
+
+        let crs = MembershipProtocol::<Rsa2048, G1Projective, HPProtocol<Bls12_381>>::setup(
+            &params, &mut rng1, &mut rng2,
+        )
+        .unwrap()
+        .crs;
+        let protocol = Protocol::from_crs(&crs);
+
+        let value = Integer::from(Integer::u_pow_u(2, params.hash_to_prime_bits as u32))
+            - &Integer::from(245);
+        let other = Integer::from(Integer::u_pow_u(2, params.hash_to_prime_bits as u32))
+            - &Integer::from(159);
+        // one accumulator per issuer, the element was issued by the second
+        let issuers = vec![
+            SetAccumulator::<Rsa2048>::from_elements(&[other.clone()]),
+            SetAccumulator::<Rsa2048>::from_elements(&[value.clone(), other]),
+            SetAccumulator::<Rsa2048>::from_elements(&[]),
+        ];
+        let r_q = Integer::from(5);
+        let statement = Statement {
+            c_ps: issuers.iter().map(|set| set.value()).collect(),
+            c_e_q: crs
+                .crs_modeq
+                .pedersen_commitment_parameters
+                .commit(&value, &r_q)
+                .unwrap(),
+        };
+        let witness = |index: usize| Witness {
+            index,
+            e: value.clone(),
+            r_q: r_q.clone(),
+            w: issuers[1].membership_witness(&value).unwrap(),
+        };
+
+        let proof_transcript = RefCell::new(Transcript::new(b"one_of"));
+        let proof = protocol
+            .prove(
+                &proof_transcript,
+                &mut rng1,
+                &mut rng2,
+                &statement,
+                &witness(1),
+            )
+            .unwrap();
+        let verification_transcript = RefCell::new(Transcript::new(b"one_of"));
+        protocol
+            .verify(&verification_transcript, &statement, &proof)
+            .unwrap();
+
+        // the challenges have to XOR to the one derived from the transcript:
+        // the simulated branch is redone for another challenge, so that it
+        // verifies on its own and only the XOR fails
+        let mut tampered = proof.clone();
+        tampered.challenges_root[0] += 1;
+        let root = RootProtocol::from_crs(&crs.crs_root).unwrap();
+        tampered.proofs_root[0].message2 = root
+            .expected_message2(
+                &RootStatement {
+                    c_e: proof.c_e.clone(),
+                    acc: statement.c_ps[0].clone(),
+                },
+                &tampered.proofs_root[0].message1,
+                &tampered.challenges_root[0],
+                &tampered.proofs_root[0].message3,
+            )
+            .unwrap();
+        let verification_transcript = RefCell::new(Transcript::new(b"one_of"));
+        assert!(protocol
+            .verify(&verification_transcript, &statement, &tampered)
+            .is_err());
+
+        // the witness isn't one for the first accumulator
+        let proof_transcript = RefCell::new(Transcript::new(b"one_of"));
+        let proof = protocol
+            .prove(
+                &proof_transcript,
+                &mut rng1,
+                &mut rng2,
+                &statement,
+                &witness(0),
+            )
+            .unwrap();
+        let verification_transcript = RefCell::new(Transcript::new(b"one_of"));
+        assert!(protocol
+            .verify(&verification_transcript, &statement, &proof)
+            .is_err());
+    }
+}
@@ -0,0 +1,18 @@
+use crate::{
+    channels::ChannelError,
+    protocols::pokcp::{Message1, Message2},
+    utils::ConvertibleUnknownOrderGroup,
+};
+use rug::Integer;
+
+pub trait PoKCPVerifierChannel<G: ConvertibleUnknownOrderGroup> {
+    fn send_message1(&mut self, message: &Message1<G>) -> Result<(), ChannelError>;
+    fn send_message2(&mut self, message: &Message2) -> Result<(), ChannelError>;
+    fn receive_challenge(&mut self) -> Result<Integer, ChannelError>;
+}
+
+pub trait PoKCPProverChannel<G: ConvertibleUnknownOrderGroup> {
+    fn receive_message1(&mut self) -> Result<Message1<G>, ChannelError>;
+    fn receive_message2(&mut self) -> Result<Message2, ChannelError>;
+    fn generate_and_send_challenge(&mut self) -> Result<Integer, ChannelError>;
+}
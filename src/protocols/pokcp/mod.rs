@@ -0,0 +1,303 @@
+//! Implements a proof of knowledge of committed values and their product,
+//! PoKCP, as a building block for composed statements.
+//!
+//! Proves knowledge of openings of the integer commitments `c_a = g^a h^{r_a}`,
+//! `c_b = g^b h^{r_b}` and `c_c = g^{ab} h^{r_c}`, using that
+//! `c_c = c_b^a h^{r_c - a r_b}`. This is the relation root and coprime use to
+//! tie the commitments to the randomness of their auxiliary elements to the
+//! committed element. As in the other protocols, the statement isn't added to
+//! the transcript, which is left to the caller.
+use crate::{
+    commitments::{integer::IntegerCommitment, Commitment},
+    parameters::Parameters,
+    protocols::{
+        blinding::{BlindingHistory, BlindingRanges},
+        validate_soundness, CRSError, ProofError, VerificationError,
+    },
+    utils::{
+        exp_signed, modulus::validate_group_modulus, random_symmetric_range,
+        ConvertibleUnknownOrderGroup,
+    },
+};
+use channel::{PoKCPProverChannel, PoKCPVerifierChannel};
+use rug::rand::MutRandState;
+use rug::Integer;
+use std::cmp::Ordering;
+
+pub mod channel;
+pub mod transcript;
+
+#[derive(Clone)]
+pub struct CRSPoKCP<G: ConvertibleUnknownOrderGroup> {
+    // G contains the information about Z^*_N
+    pub parameters: Parameters,
+    pub integer_commitment_parameters: IntegerCommitment<G>, // G, H
+}
+
+impl<G: ConvertibleUnknownOrderGroup> CRSPoKCP<G> {
+    pub fn setup<R: MutRandState>(parameters: &Parameters, rng: &mut R) -> CRSPoKCP<G> {
+        CRSPoKCP {
+            parameters: parameters.clone(),
+            integer_commitment_parameters: IntegerCommitment::<G>::setup(rng),
+        }
+    }
+}
+
+pub struct Statement<G: ConvertibleUnknownOrderGroup> {
+    pub c_a: <IntegerCommitment<G> as Commitment>::Instance,
+    pub c_b: <IntegerCommitment<G> as Commitment>::Instance,
+    pub c_c: <IntegerCommitment<G> as Commitment>::Instance,
+}
+
+impl<G: ConvertibleUnknownOrderGroup> Clone for Statement<G> {
+    fn clone(&self) -> Self {
+        Self {
+            c_a: self.c_a.clone(),
+            c_b: self.c_b.clone(),
+            c_c: self.c_c.clone(),
+        }
+    }
+}
+
+/// `a` and `b` have at most `hash_to_prime_bits` bits, which the blinding
+/// covers.
+pub struct Witness {
+    pub a: Integer,
+    pub r_a: Integer,
+    pub b: Integer,
+    pub r_b: Integer,
+    pub r_c: Integer,
+}
+
+#[derive(Clone)]
+pub struct Message1<G: ConvertibleUnknownOrderGroup> {
+    pub alpha1: <IntegerCommitment<G> as Commitment>::Instance,
+    pub alpha2: <IntegerCommitment<G> as Commitment>::Instance,
+    pub alpha3: G::Elem,
+}
+
+#[derive(Clone)]
+pub struct Message2 {
+    pub s_a: Integer,
+    pub s_r_a: Integer,
+    pub s_b: Integer,
+    pub s_r_b: Integer,
+    pub s_t: Integer,
+}
+
+#[derive(Clone)]
+pub struct Proof<G: ConvertibleUnknownOrderGroup> {
+    pub message1: Message1<G>,
+    pub message2: Message2,
+}
+
+pub struct Protocol<G: ConvertibleUnknownOrderGroup> {
+    pub crs: CRSPoKCP<G>,
+}
+
+impl<G: ConvertibleUnknownOrderGroup> Protocol<G> {
+    pub fn from_crs(crs: &CRSPoKCP<G>) -> Result<Protocol<G>, CRSError> {
+        validate_soundness::<G>(&crs.parameters)?;
+        validate_group_modulus::<G>(&crs.parameters).map_err(|_| CRSError::InvalidParameters)?;
+        Ok(Protocol { crs: crs.clone() })
+    }
+
+    pub fn prove<R: MutRandState, C: PoKCPVerifierChannel<G>>(
+        &self,
+        verifier_channel: &mut C,
+        rng: &mut R,
+        statement: &Statement<G>,
+        witness: &Witness,
+    ) -> Result<(), ProofError> {
+        self.prove_with_history(
+            verifier_channel,
+            rng,
+            statement,
+            witness,
+            &mut BlindingHistory::new(0),
+        )
+    }
+
+    /// Proves while recording the blinding values in `history`, failing if
+    /// they were used before.
+    pub fn prove_with_history<R: MutRandState, C: PoKCPVerifierChannel<G>>(
+        &self,
+        verifier_channel: &mut C,
+        rng: &mut R,
+        statement: &Statement<G>,
+        witness: &Witness,
+        history: &mut BlindingHistory,
+    ) -> Result<(), ProofError> {
+        let bits = self.crs.parameters.hash_to_prime_bits as u32;
+        if witness.a.significant_bits() > bits || witness.b.significant_bits() > bits {
+            return Err(ProofError::WitnessOutOfRange);
+        }
+        let ranges = BlindingRanges::new::<G>(&self.crs.parameters);
+        let r_a = random_symmetric_range(rng, &ranges.exponent);
+        let r_r_a = random_symmetric_range(rng, &ranges.randomness_mask);
+        let r_b = random_symmetric_range(rng, &ranges.exponent);
+        let r_r_b = random_symmetric_range(rng, &ranges.randomness_mask);
+        let r_t = random_symmetric_range(rng, &ranges.product_mask);
+        history.record(b"pokcp", &[&r_a, &r_r_a, &r_b, &r_r_b, &r_t])?;
+
+        let integer_commitment_alpha3 =
+            IntegerCommitment::<G>::new(&statement.c_b, &self.crs.integer_commitment_parameters.h);
+        let message1 = Message1::<G> {
+            alpha1: self
+                .crs
+                .integer_commitment_parameters
+                .commit(&r_a, &r_r_a)?,
+            alpha2: self
+                .crs
+                .integer_commitment_parameters
+                .commit(&r_b, &r_r_b)?,
+            alpha3: integer_commitment_alpha3.commit(&r_a, &r_t)?,
+        };
+        verifier_channel.send_message1(&message1)?;
+
+        let c = verifier_channel.receive_challenge()?;
+        let t = witness.r_c.clone() - witness.a.clone() * witness.r_b.clone();
+        let message2 = Message2 {
+            s_a: r_a - c.clone() * witness.a.clone(),
+            s_r_a: r_r_a - c.clone() * witness.r_a.clone(),
+            s_b: r_b - c.clone() * witness.b.clone(),
+            s_r_b: r_r_b - c.clone() * witness.r_b.clone(),
+            s_t: r_t - c * t,
+        };
+        verifier_channel.send_message2(&message2)?;
+
+        Ok(())
+    }
+
+    pub fn verify<C: PoKCPProverChannel<G>>(
+        &self,
+        prover_channel: &mut C,
+        statement: &Statement<G>,
+    ) -> Result<(), VerificationError> {
+        let message1 = prover_channel.receive_message1()?;
+        let c = prover_channel.generate_and_send_challenge()?;
+        let message2 = prover_channel.receive_message2()?;
+        let expected_message1 = self.expected_message1(statement, &c, &message2)?;
+
+        let s_bound = BlindingRanges::new::<G>(&self.crs.parameters).exponent_response_bound();
+        // non-short-circuiting, as in root
+        if (expected_message1.alpha1 == message1.alpha1)
+            & (expected_message1.alpha2 == message1.alpha2)
+            & (expected_message1.alpha3 == message1.alpha3)
+            & (message2.s_a.cmp_abs(&s_bound) != Ordering::Greater)
+            & (message2.s_b.cmp_abs(&s_bound) != Ordering::Greater)
+        {
+            Ok(())
+        } else {
+            Err(VerificationError::VerificationFailed)
+        }
+    }
+
+    /// Recomputes the alphas the verifier expects given the challenge and the
+    /// responses of the prover.
+    pub fn expected_message1(
+        &self,
+        statement: &Statement<G>,
+        c: &Integer,
+        message2: &Message2,
+    ) -> Result<Message1<G>, VerificationError> {
+        let alpha1 = G::op(
+            &exp_signed::<G>(&statement.c_a, c),
+            &self
+                .crs
+                .integer_commitment_parameters
+                .commit(&message2.s_a, &message2.s_r_a)?,
+        );
+        let alpha2 = G::op(
+            &exp_signed::<G>(&statement.c_b, c),
+            &self
+                .crs
+                .integer_commitment_parameters
+                .commit(&message2.s_b, &message2.s_r_b)?,
+        );
+        let integer_commitment_alpha3 =
+            IntegerCommitment::<G>::new(&statement.c_b, &self.crs.integer_commitment_parameters.h);
+        let alpha3 = G::op(
+            &exp_signed::<G>(&statement.c_c, c),
+            &integer_commitment_alpha3.commit(&message2.s_a, &message2.s_t)?,
+        );
+        Ok(Message1 {
+            alpha1,
+            alpha2,
+            alpha3,
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{CRSPoKCP, Protocol, Statement, Witness};
+    use crate::{
+        commitments::Commitment,
+        parameters::Parameters,
+        protocols::pokcp::transcript::{TranscriptProverChannel, TranscriptVerifierChannel},
+    };
+    use accumulator::group::Rsa2048;
+    use merlin::Transcript;
+    use rug::rand::RandState;
+    use rug::Integer;
+    use std::cell::RefCell;
+
+    #[test]
+    fn test_proof() {
+        let params = Parameters::from_security_level(128).unwrap();
+        let mut rng = RandState::new();
+        rng.seed(&Integer::from(13));
+
+        let crs = CRSPoKCP::<Rsa2048>::setup(&params, &mut rng);
+        let protocol = Protocol::<Rsa2048>::from_crs(&crs).unwrap();
+
+        let a = Integer::from(553_525_575_239_331_913u64);
+        let b = Integer::from(378_373_571_372_703_133u64);
+        let witness = Witness {
+            r_a: Integer::from(5),
+            r_b: Integer::from(6),
+            r_c: Integer::from(7),
+            a,
+            b,
+        };
+        let commit = |value: &Integer, randomness: &Integer| {
+            crs.integer_commitment_parameters
+                .commit(value, randomness)
+                .unwrap()
+        };
+        let statement = Statement {
+            c_a: commit(&witness.a, &witness.r_a),
+            c_b: commit(&witness.b, &witness.r_b),
+            c_c: commit(&Integer::from(&witness.a * &witness.b), &witness.r_c),
+        };
+
+        let proof_transcript = RefCell::new(Transcript::new(b"pokcp"));
+        let mut verifier_channel = TranscriptVerifierChannel::new(&crs, &proof_transcript);
+        protocol
+            .prove(&mut verifier_channel, &mut rng, &statement, &witness)
+            .unwrap();
+        let proof = verifier_channel.proof().unwrap();
+
+        let verification_transcript = RefCell::new(Transcript::new(b"pokcp"));
+        let mut prover_channel =
+            TranscriptProverChannel::new(&crs, &verification_transcript, &proof);
+        protocol.verify(&mut prover_channel, &statement).unwrap();
+
+        // c_c doesn't commit to the product
+        let mut wrong_statement = statement.clone();
+        wrong_statement.c_c = commit(&Integer::from(&witness.a + &witness.b), &witness.r_c);
+        let proof_transcript = RefCell::new(Transcript::new(b"pokcp"));
+        let mut verifier_channel = TranscriptVerifierChannel::new(&crs, &proof_transcript);
+        protocol
+            .prove(&mut verifier_channel, &mut rng, &wrong_statement, &witness)
+            .unwrap();
+        let proof = verifier_channel.proof().unwrap();
+        let verification_transcript = RefCell::new(Transcript::new(b"pokcp"));
+        let mut prover_channel =
+            TranscriptProverChannel::new(&crs, &verification_transcript, &proof);
+        assert!(protocol
+            .verify(&mut prover_channel, &wrong_statement)
+            .is_err());
+    }
+}
@@ -0,0 +1,270 @@
+//! Implements a proof of knowledge of an exponent, PoKE, as a building block
+//! for composed statements.
+//!
+//! Proves knowledge of `(e, r)` such that `c_e = g^e h^r` and `y = u^e`, for a
+//! public base `u` of the group of unknown order. This is the relation between
+//! the commitment to the element and the accumulator in root, and it can be
+//! combined with the other protocols over the same transcript, e.g. to prove
+//! discrete logarithm relations between several bases. As in the other
+//! protocols, the statement isn't added to the transcript, which is left to
+//! the caller.
+use crate::{
+    commitments::{integer::IntegerCommitment, Commitment},
+    parameters::Parameters,
+    protocols::{
+        blinding::{BlindingHistory, BlindingRanges},
+        validate_soundness, CRSError, ProofError, VerificationError,
+    },
+    utils::{
+        exp_signed, modulus::validate_group_modulus, random_symmetric_range,
+        ConvertibleUnknownOrderGroup,
+    },
+};
+use channel::{PoKEProverChannel, PoKEVerifierChannel};
+use rug::rand::MutRandState;
+use rug::Integer;
+use std::cmp::Ordering;
+
+pub mod channel;
+pub mod transcript;
+
+#[derive(Clone)]
+pub struct CRSPoKE<G: ConvertibleUnknownOrderGroup> {
+    // G contains the information about Z^*_N
+    pub parameters: Parameters,
+    pub integer_commitment_parameters: IntegerCommitment<G>, // G, H
+}
+
+impl<G: ConvertibleUnknownOrderGroup> CRSPoKE<G> {
+    pub fn setup<R: MutRandState>(parameters: &Parameters, rng: &mut R) -> CRSPoKE<G> {
+        CRSPoKE {
+            parameters: parameters.clone(),
+            integer_commitment_parameters: IntegerCommitment::<G>::setup(rng),
+        }
+    }
+}
+
+pub struct Statement<G: ConvertibleUnknownOrderGroup> {
+    pub c_e: <IntegerCommitment<G> as Commitment>::Instance,
+    pub u: G::Elem,
+    pub y: G::Elem,
+}
+
+impl<G: ConvertibleUnknownOrderGroup> Clone for Statement<G> {
+    fn clone(&self) -> Self {
+        Self {
+            c_e: self.c_e.clone(),
+            u: self.u.clone(),
+            y: self.y.clone(),
+        }
+    }
+}
+
+/// `e` has at most `hash_to_prime_bits` bits, which the blinding covers.
+pub struct Witness {
+    pub e: Integer,
+    pub r: Integer,
+}
+
+#[derive(Clone)]
+pub struct Message1<G: ConvertibleUnknownOrderGroup> {
+    pub alpha1: <IntegerCommitment<G> as Commitment>::Instance,
+    pub alpha2: G::Elem,
+}
+
+#[derive(Clone)]
+pub struct Message2 {
+    pub s_e: Integer,
+    pub s_r: Integer,
+}
+
+#[derive(Clone)]
+pub struct Proof<G: ConvertibleUnknownOrderGroup> {
+    pub message1: Message1<G>,
+    pub message2: Message2,
+}
+
+pub struct Protocol<G: ConvertibleUnknownOrderGroup> {
+    pub crs: CRSPoKE<G>,
+}
+
+impl<G: ConvertibleUnknownOrderGroup> Protocol<G> {
+    pub fn from_crs(crs: &CRSPoKE<G>) -> Result<Protocol<G>, CRSError> {
+        validate_soundness::<G>(&crs.parameters)?;
+        validate_group_modulus::<G>(&crs.parameters).map_err(|_| CRSError::InvalidParameters)?;
+        Ok(Protocol { crs: crs.clone() })
+    }
+
+    pub fn prove<R: MutRandState, C: PoKEVerifierChannel<G>>(
+        &self,
+        verifier_channel: &mut C,
+        rng: &mut R,
+        statement: &Statement<G>,
+        witness: &Witness,
+    ) -> Result<(), ProofError> {
+        self.prove_with_history(
+            verifier_channel,
+            rng,
+            statement,
+            witness,
+            &mut BlindingHistory::new(0),
+        )
+    }
+
+    /// Proves while recording the blinding values in `history`, failing if
+    /// they were used before.
+    pub fn prove_with_history<R: MutRandState, C: PoKEVerifierChannel<G>>(
+        &self,
+        verifier_channel: &mut C,
+        rng: &mut R,
+        statement: &Statement<G>,
+        witness: &Witness,
+        history: &mut BlindingHistory,
+    ) -> Result<(), ProofError> {
+        if witness.e.significant_bits() > self.crs.parameters.hash_to_prime_bits as u32 {
+            return Err(ProofError::WitnessOutOfRange);
+        }
+        let ranges = BlindingRanges::new::<G>(&self.crs.parameters);
+        let r_e = random_symmetric_range(rng, &ranges.exponent);
+        let r_r = random_symmetric_range(rng, &ranges.randomness_mask);
+        history.record(b"poke", &[&r_e, &r_r])?;
+
+        let message1 = Message1::<G> {
+            alpha1: self.crs.integer_commitment_parameters.commit(&r_e, &r_r)?,
+            alpha2: exp_signed::<G>(&statement.u, &r_e),
+        };
+        verifier_channel.send_message1(&message1)?;
+
+        let c = verifier_channel.receive_challenge()?;
+        let message2 = Message2 {
+            s_e: r_e - c.clone() * witness.e.clone(),
+            s_r: r_r - c * witness.r.clone(),
+        };
+        verifier_channel.send_message2(&message2)?;
+
+        Ok(())
+    }
+
+    pub fn verify<C: PoKEProverChannel<G>>(
+        &self,
+        prover_channel: &mut C,
+        statement: &Statement<G>,
+    ) -> Result<(), VerificationError> {
+        let message1 = prover_channel.receive_message1()?;
+        let c = prover_channel.generate_and_send_challenge()?;
+        let message2 = prover_channel.receive_message2()?;
+        let expected_message1 = self.expected_message1(statement, &c, &message2)?;
+
+        let s_e_bound = BlindingRanges::new::<G>(&self.crs.parameters).exponent_response_bound();
+        // non-short-circuiting, as in root
+        if (expected_message1.alpha1 == message1.alpha1)
+            & (expected_message1.alpha2 == message1.alpha2)
+            & (message2.s_e.cmp_abs(&s_e_bound) != Ordering::Greater)
+        {
+            Ok(())
+        } else {
+            Err(VerificationError::VerificationFailed)
+        }
+    }
+
+    /// Recomputes the alphas the verifier expects given the challenge and the
+    /// responses of the prover.
+    pub fn expected_message1(
+        &self,
+        statement: &Statement<G>,
+        c: &Integer,
+        message2: &Message2,
+    ) -> Result<Message1<G>, VerificationError> {
+        let alpha1 = G::op(
+            &exp_signed::<G>(&statement.c_e, c),
+            &self
+                .crs
+                .integer_commitment_parameters
+                .commit(&message2.s_e, &message2.s_r)?,
+        );
+        let alpha2 = G::op(
+            &exp_signed::<G>(&statement.y, c),
+            &exp_signed::<G>(&statement.u, &message2.s_e),
+        );
+        Ok(Message1 { alpha1, alpha2 })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{CRSPoKE, Protocol, Statement, Witness};
+    use crate::{
+        commitments::Commitment,
+        parameters::Parameters,
+        protocols::{
+            poke::transcript::{TranscriptProverChannel, TranscriptVerifierChannel},
+            ProofError,
+        },
+    };
+    use accumulator::group::{Group, Rsa2048, UnknownOrderGroup};
+    use merlin::Transcript;
+    use rug::rand::RandState;
+    use rug::Integer;
+    use std::cell::RefCell;
+
+    #[test]
+    fn test_proof() {
+        let params = Parameters::from_security_level(128).unwrap();
+        let mut rng = RandState::new();
+        rng.seed(&Integer::from(13));
+
+        let crs = CRSPoKE::<Rsa2048>::setup(&params, &mut rng);
+        let protocol = Protocol::<Rsa2048>::from_crs(&crs).unwrap();
+
+        let e = Integer::from(553_525_575_239_331_913u64);
+        let r = Integer::from(5);
+        let u = Rsa2048::exp(&Rsa2048::unknown_order_elem(), &Integer::from(7));
+        let statement = Statement {
+            c_e: crs.integer_commitment_parameters.commit(&e, &r).unwrap(),
+            y: Rsa2048::exp(&u, &e),
+            u,
+        };
+
+        let proof_transcript = RefCell::new(Transcript::new(b"poke"));
+        let mut verifier_channel = TranscriptVerifierChannel::new(&crs, &proof_transcript);
+        protocol
+            .prove(
+                &mut verifier_channel,
+                &mut rng,
+                &statement,
+                &Witness {
+                    e: e.clone(),
+                    r: r.clone(),
+                },
+            )
+            .unwrap();
+        let proof = verifier_channel.proof().unwrap();
+
+        let verification_transcript = RefCell::new(Transcript::new(b"poke"));
+        let mut prover_channel =
+            TranscriptProverChannel::new(&crs, &verification_transcript, &proof);
+        protocol.verify(&mut prover_channel, &statement).unwrap();
+
+        let mut wrong_statement = statement.clone();
+        wrong_statement.y = Rsa2048::op(&statement.y, &statement.u);
+        let verification_transcript = RefCell::new(Transcript::new(b"poke"));
+        let mut prover_channel =
+            TranscriptProverChannel::new(&crs, &verification_transcript, &proof);
+        assert!(protocol
+            .verify(&mut prover_channel, &wrong_statement)
+            .is_err());
+
+        let large_e = Integer::from(Integer::u_pow_u(2, params.hash_to_prime_bits as u32));
+        let proof_transcript = RefCell::new(Transcript::new(b"poke"));
+        let mut verifier_channel = TranscriptVerifierChannel::new(&crs, &proof_transcript);
+        assert!(matches!(
+            protocol.prove(
+                &mut verifier_channel,
+                &mut rng,
+                &statement,
+                &Witness { e: large_e, r },
+            ),
+            Err(ProofError::WitnessOutOfRange)
+        ));
+    }
+}
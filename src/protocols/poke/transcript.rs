@@ -0,0 +1,143 @@
+use crate::{
+    channels::{ChannelError, MessageOrder},
+    protocols::poke::{
+        channel::{PoKEProverChannel, PoKEVerifierChannel},
+        CRSPoKE, Message1, Message2, Proof,
+    },
+    transcript::{
+        versioned_domain_sep, TranscriptChannelError, TranscriptProtocolChallenge,
+        TranscriptProtocolInteger,
+    },
+    utils::ConvertibleUnknownOrderGroup,
+};
+use merlin::Transcript;
+use rug::Integer;
+use std::cell::RefCell;
+
+pub trait TranscriptProtocolPoKE<G: ConvertibleUnknownOrderGroup>:
+    TranscriptProtocolInteger<G> + TranscriptProtocolChallenge
+{
+    fn poke_domain_sep(&mut self);
+}
+
+impl<G: ConvertibleUnknownOrderGroup> TranscriptProtocolPoKE<G> for Transcript {
+    fn poke_domain_sep(&mut self) {
+        self.append_message(b"dom-sep", &versioned_domain_sep(b"poke"));
+    }
+}
+
+pub struct TranscriptVerifierChannel<
+    'a,
+    G: ConvertibleUnknownOrderGroup,
+    T: TranscriptProtocolPoKE<G>,
+> {
+    crs: CRSPoKE<G>,
+    transcript: &'a RefCell<T>,
+    order: MessageOrder,
+    message1: Option<Message1<G>>,
+    message2: Option<Message2>,
+}
+
+impl<'a, G: ConvertibleUnknownOrderGroup, T: TranscriptProtocolPoKE<G>>
+    TranscriptVerifierChannel<'a, G, T>
+{
+    pub fn new(
+        crs: &CRSPoKE<G>,
+        transcript: &'a RefCell<T>,
+    ) -> TranscriptVerifierChannel<'a, G, T> {
+        TranscriptVerifierChannel {
+            crs: crs.clone(),
+            transcript,
+            order: MessageOrder::new(),
+            message1: None,
+            message2: None,
+        }
+    }
+
+    pub fn proof(&self) -> Result<Proof<G>, TranscriptChannelError> {
+        if self.message1.is_some() && self.message2.is_some() {
+            Ok(Proof {
+                message1: self.message1.as_ref().unwrap().clone(),
+                message2: self.message2.as_ref().unwrap().clone(),
+            })
+        } else {
+            Err(TranscriptChannelError::Incomplete)
+        }
+    }
+}
+
+impl<'a, G: ConvertibleUnknownOrderGroup, T: TranscriptProtocolPoKE<G>> PoKEVerifierChannel<G>
+    for TranscriptVerifierChannel<'a, G, T>
+{
+    fn send_message1(&mut self, message: &Message1<G>) -> Result<(), ChannelError> {
+        self.order.step(0)?;
+        let mut transcript = self.transcript.try_borrow_mut()?;
+        transcript.poke_domain_sep();
+        transcript.append_integer_point(b"alpha1", &message.alpha1);
+        transcript.append_integer_point(b"alpha2", &message.alpha2);
+        self.message1 = Some(message.clone());
+        Ok(())
+    }
+    fn send_message2(&mut self, message: &Message2) -> Result<(), ChannelError> {
+        self.order.step(2)?;
+        self.message2 = Some(message.clone());
+        Ok(())
+    }
+    fn receive_challenge(&mut self) -> Result<Integer, ChannelError> {
+        self.order.step(1)?;
+        let mut transcript = self.transcript.try_borrow_mut()?;
+        transcript.poke_domain_sep();
+        Ok(transcript.challenge_scalar(b"c", self.crs.parameters.security_soundness))
+    }
+}
+
+pub struct TranscriptProverChannel<
+    'a,
+    G: ConvertibleUnknownOrderGroup,
+    T: TranscriptProtocolPoKE<G>,
+> {
+    crs: CRSPoKE<G>,
+    transcript: &'a RefCell<T>,
+    order: MessageOrder,
+    proof: Proof<G>,
+}
+
+impl<'a, G: ConvertibleUnknownOrderGroup, T: TranscriptProtocolPoKE<G>>
+    TranscriptProverChannel<'a, G, T>
+{
+    pub fn new(
+        crs: &CRSPoKE<G>,
+        transcript: &'a RefCell<T>,
+        proof: &Proof<G>,
+    ) -> TranscriptProverChannel<'a, G, T> {
+        TranscriptProverChannel {
+            crs: crs.clone(),
+            transcript,
+            order: MessageOrder::new(),
+            proof: proof.clone(),
+        }
+    }
+}
+
+impl<'a, G: ConvertibleUnknownOrderGroup, T: TranscriptProtocolPoKE<G>> PoKEProverChannel<G>
+    for TranscriptProverChannel<'a, G, T>
+{
+    fn receive_message1(&mut self) -> Result<Message1<G>, ChannelError> {
+        self.order.step(0)?;
+        let mut transcript = self.transcript.try_borrow_mut()?;
+        transcript.poke_domain_sep();
+        transcript.append_integer_point(b"alpha1", &self.proof.message1.alpha1);
+        transcript.append_integer_point(b"alpha2", &self.proof.message1.alpha2);
+        Ok(self.proof.message1.clone())
+    }
+    fn receive_message2(&mut self) -> Result<Message2, ChannelError> {
+        self.order.step(2)?;
+        Ok(self.proof.message2.clone())
+    }
+    fn generate_and_send_challenge(&mut self) -> Result<Integer, ChannelError> {
+        self.order.step(1)?;
+        let mut transcript = self.transcript.try_borrow_mut()?;
+        transcript.poke_domain_sep();
+        Ok(transcript.challenge_scalar(b"c", self.crs.parameters.security_soundness))
+    }
+}
@@ -0,0 +1,505 @@
+//! Presentations, the single object a holder sends a verifier: a proof with
+//! everything needed to check it.
+//!
+//! A `Presentation` carries the digest of the statement, the statement itself
+//! or only a reference to it through the digest, the proof with the digest of
+//! its CRS, the epoch of the accumulator and the associated data. The proof is
+//! bound to the epoch and the associated data together: provers create their
+//! transcript with `transcript_with_aad(label, &presentation_aad(epoch, aad))`.
+//! `verify_presentation` checks the statement against its digest, rejects
+//! epochs older than the registry's, checks the statement is about the
+//! accumulator value the registry recorded for the epoch with
+//! `CrsRegistry::set_accumulator`, and verifies the proof under its CRS.
+//!
+//! Online verifiers can also hand the prover a `VerifierChallenge`, absorbed
+//! after the associated data: the prover creates the transcript with
+//! `transcript_with_verifier_challenge` and attaches the challenge with
+//! `with_verifier_challenge`, and `verify_presentation_with_challenge` checks
+//! it is the one the verifier issued.
+use crate::{
+    protocols::{
+        hash_to_prime::HashToPrimeProtocol,
+        membership::{
+            transcript::TranscriptProverChannel as MembershipTranscriptProverChannel,
+            Proof as MembershipProof, Protocol as MembershipProtocol,
+            Statement as MembershipStatement,
+        },
+        nonmembership::{
+            transcript::TranscriptProverChannel as NonMembershipTranscriptProverChannel,
+            Proof as NonMembershipProof, Protocol as NonMembershipProtocol,
+            Statement as NonMembershipStatement,
+        },
+        registry::{CrsDigest, CrsRegistry, VersionedProof},
+        replay::ReplayKey,
+        statement::StatementDigest,
+        VerificationError,
+    },
+    transcript::{transcript_with_aad, transcript_with_verifier_challenge, VerifierChallenge},
+    utils::{
+        curve::{CurveError, CurvePointProjective},
+        ConvertibleUnknownOrderGroup,
+    },
+};
+use merlin::Transcript;
+use std::cell::RefCell;
+
+/// Associated data binding a proof to both `epoch` and `aad`.
+pub fn presentation_aad(epoch: u64, aad: &[u8]) -> Vec<u8> {
+    let mut bytes = Vec::with_capacity(8 + aad.len());
+    bytes.extend_from_slice(&epoch.to_le_bytes());
+    bytes.extend_from_slice(aad);
+    bytes
+}
+
+/// Protocols whose proofs can be presented.
+pub trait PresentationProtocol {
+    type Statement;
+    type Proof;
+
+    fn statement_digest(statement: &Self::Statement) -> Result<StatementDigest, CurveError>;
+
+    /// The accumulator value `statement` is about, encoded as in
+    /// `CrsRegistry::set_accumulator`.
+    fn accumulator_bytes(statement: &Self::Statement) -> Vec<u8>;
+
+    /// Verifies `proof` on `transcript`, returning the key `ReplayGuard`
+    /// records for it, see `replay`.
+    fn verify_on_transcript(
+        &self,
+        transcript: &RefCell<Transcript>,
+        statement: &Self::Statement,
+        proof: &Self::Proof,
+    ) -> Result<ReplayKey, VerificationError>;
+}
+
+impl<G: ConvertibleUnknownOrderGroup, P: CurvePointProjective, HP: HashToPrimeProtocol<P>>
+    PresentationProtocol for MembershipProtocol<G, P, HP>
+{
+    type Statement = MembershipStatement<G, P>;
+    type Proof = MembershipProof<G, P, HP>;
+
+    fn statement_digest(statement: &Self::Statement) -> Result<StatementDigest, CurveError> {
+        statement.digest()
+    }
+
+    fn accumulator_bytes(statement: &Self::Statement) -> Vec<u8> {
+        G::elem_to_bytes(&statement.c_p)
+    }
+
+    fn verify_on_transcript(
+        &self,
+        transcript: &RefCell<Transcript>,
+        statement: &Self::Statement,
+        proof: &Self::Proof,
+    ) -> Result<ReplayKey, VerificationError> {
+        let mut prover_channel =
+            MembershipTranscriptProverChannel::new(&self.crs, transcript, proof);
+        self.verify(&mut prover_channel, statement)?;
+        prover_channel
+            .pre_proof_digest()
+            .ok_or(VerificationError::VerificationFailed)
+    }
+}
+
+impl<G: ConvertibleUnknownOrderGroup, P: CurvePointProjective, HP: HashToPrimeProtocol<P>>
+    PresentationProtocol for NonMembershipProtocol<G, P, HP>
+{
+    type Statement = NonMembershipStatement<G, P>;
+    type Proof = NonMembershipProof<G, P, HP>;
+
+    fn statement_digest(statement: &Self::Statement) -> Result<StatementDigest, CurveError> {
+        statement.digest()
+    }
+
+    fn accumulator_bytes(statement: &Self::Statement) -> Vec<u8> {
+        G::elem_to_bytes(&statement.c_p)
+    }
+
+    fn verify_on_transcript(
+        &self,
+        transcript: &RefCell<Transcript>,
+        statement: &Self::Statement,
+        proof: &Self::Proof,
+    ) -> Result<ReplayKey, VerificationError> {
+        let mut prover_channel =
+            NonMembershipTranscriptProverChannel::new(&self.crs, transcript, proof);
+        self.verify(&mut prover_channel, statement)?;
+        prover_channel
+            .pre_proof_digest()
+            .ok_or(VerificationError::VerificationFailed)
+    }
+}
+
+pub struct Presentation<V: PresentationProtocol> {
+    pub statement_digest: StatementDigest,
+    /// The statement, or `None` when it is only referenced by its digest,
+    /// e.g. because the verifier already holds it.
+    pub statement: Option<V::Statement>,
+    pub proof: VersionedProof<V::Proof>,
+    pub epoch: u64,
+    pub aad: Vec<u8>,
+    /// The randomness of the verifier the proof is bound to, if any.
+    pub verifier_challenge: Option<VerifierChallenge>,
+}
+
+impl<V: PresentationProtocol> Clone for Presentation<V>
+where
+    V::Statement: Clone,
+    V::Proof: Clone,
+{
+    fn clone(&self) -> Self {
+        Self {
+            statement_digest: self.statement_digest,
+            statement: self.statement.clone(),
+            proof: self.proof.clone(),
+            epoch: self.epoch,
+            aad: self.aad.clone(),
+            verifier_challenge: self.verifier_challenge.clone(),
+        }
+    }
+}
+
+impl<V: PresentationProtocol> Presentation<V> {
+    /// Packs a proof created on a transcript bound to
+    /// `presentation_aad(epoch, aad)` under the CRS with digest `crs_digest`.
+    pub fn new(
+        statement: V::Statement,
+        proof: V::Proof,
+        crs_digest: CrsDigest,
+        epoch: u64,
+        aad: &[u8],
+    ) -> Result<Presentation<V>, CurveError> {
+        Ok(Presentation {
+            statement_digest: V::statement_digest(&statement)?,
+            statement: Some(statement),
+            proof: VersionedProof::new(crs_digest, proof),
+            epoch,
+            aad: aad.to_vec(),
+            verifier_challenge: None,
+        })
+    }
+
+    /// Drops the statement, keeping only its digest as a reference.
+    pub fn without_statement(mut self) -> Presentation<V> {
+        self.statement = None;
+        self
+    }
+
+    /// Fills in the statement a presentation references, e.g. looked up by
+    /// `statement_digest`. It is checked against the digest on verification.
+    pub fn with_statement(mut self, statement: V::Statement) -> Presentation<V> {
+        self.statement = Some(statement);
+        self
+    }
+
+    /// Binds the presentation to the verifier's `challenge`, for a proof
+    /// created on `transcript_with_verifier_challenge`.
+    pub fn with_verifier_challenge(mut self, challenge: VerifierChallenge) -> Presentation<V> {
+        self.verifier_challenge = Some(challenge);
+        self
+    }
+}
+
+/// Verifies `presentation` with the verifier of its CRS. Presentations
+/// without their statement are rejected with `MissingStatement`, those from
+/// an epoch older than `registry.epoch()` with `StaleEpoch`, and those whose
+/// statement isn't about the accumulator the registry holds for their epoch
+/// with `UnknownEpoch` or `AccumulatorMismatch`.
+pub fn verify_presentation<V: PresentationProtocol>(
+    registry: &CrsRegistry<V>,
+    presentation: &Presentation<V>,
+) -> Result<(), VerificationError> {
+    verify_presentation_key(registry, presentation).map(|_| ())
+}
+
+/// Verifies `presentation` as `verify_presentation` does, and rejects it with
+/// `VerifierChallengeMismatch` unless it is bound to `challenge`, the one the
+/// verifier handed the prover.
+pub fn verify_presentation_with_challenge<V: PresentationProtocol>(
+    registry: &CrsRegistry<V>,
+    presentation: &Presentation<V>,
+    challenge: &VerifierChallenge,
+) -> Result<(), VerificationError> {
+    if presentation.verifier_challenge.as_ref() != Some(challenge) {
+        return Err(VerificationError::VerifierChallengeMismatch);
+    }
+    verify_presentation(registry, presentation)
+}
+
+/// Like `verify_presentation`, returning the replay key of the proof.
+pub(crate) fn verify_presentation_key<V: PresentationProtocol>(
+    registry: &CrsRegistry<V>,
+    presentation: &Presentation<V>,
+) -> Result<ReplayKey, VerificationError> {
+    let statement = presentation
+        .statement
+        .as_ref()
+        .ok_or(VerificationError::MissingStatement)?;
+    if V::statement_digest(statement)? != presentation.statement_digest {
+        return Err(VerificationError::StatementMismatch);
+    }
+    if presentation.epoch < registry.epoch() {
+        return Err(VerificationError::StaleEpoch);
+    }
+    let accumulator = registry
+        .accumulator(presentation.epoch)
+        .ok_or(VerificationError::UnknownEpoch)?;
+    if V::accumulator_bytes(statement) != accumulator {
+        return Err(VerificationError::AccumulatorMismatch);
+    }
+    registry.check_protocol_version(presentation.proof.protocol_version)?;
+    let protocol = registry
+        .get(&presentation.proof.crs_digest)
+        .ok_or(VerificationError::UnknownCrs)?;
+    let aad = presentation_aad(presentation.epoch, &presentation.aad);
+    let transcript = RefCell::new(match &presentation.verifier_challenge {
+        Some(challenge) => {
+            transcript_with_verifier_challenge(registry.transcript_label(), &aad, challenge)
+        }
+        None => transcript_with_aad(registry.transcript_label(), &aad),
+    });
+    protocol.verify_on_transcript(&transcript, statement, &presentation.proof.proof)
+}
+
+#[cfg(all(test, feature = "arkworks"))]
+mod test {
+    use super::{
+        presentation_aad, verify_presentation, verify_presentation_with_challenge, Presentation,
+    };
+    use crate::{
+        commitments::Commitment,
+        parameters::Parameters,
+        protocols::{
+            hash_to_prime::snark_range::Protocol as HPProtocol,
+            membership::{transcript::TranscriptVerifierChannel, Protocol, Statement, Witness},
+            registry::CrsRegistry,
+            replay::{verify_presentation_once, ReplayGuard},
+            VerificationError,
+        },
+        transcript::{transcript_with_aad, VerifierChallenge},
+    };
+    use accumulator::{group::Rsa2048, AccumulatorWithoutHashToPrime};
+    use ark_bls12_381::{Bls12_381, Fr, G1Projective};
+    use ark_ec::{AffineCurve, ProjectiveCurve};
+    use ark_ff::{Field, PrimeField, UniformRand};
+    use rand::thread_rng;
+    use rug::rand::RandState;
+    use rug::Integer;
+    use std::cell::RefCell;
+
+    type MembershipProtocol = Protocol<Rsa2048, G1Projective, HPProtocol<Bls12_381>>;
+
+    #[test]
+    fn test_presentation() {
+        let params = Parameters::from_security_level(128).unwrap();
+        let mut rng1 = RandState::new();
+        rng1.seed(&Integer::from(13));
+        let mut rng2 = thread_rng();
+
+        let crs = MembershipProtocol::setup(&params, &mut rng1, &mut rng2)
+            .unwrap()
+            .crs;
+        let protocol = MembershipProtocol::from_crs(&crs);
+
+        let value = Integer::from(Integer::u_pow_u(2, params.hash_to_prime_bits as u32))
+            - &Integer::from(245);
+        let randomness = Integer::from(5);
+        let accum =
+            accumulator::Accumulator::<Rsa2048, Integer, AccumulatorWithoutHashToPrime>::empty()
+                .add_with_proof(&[value.clone()]);
+        let statement = Statement {
+            c_p: accum.0.value,
+            c_e_q: crs
+                .crs_modeq
+                .pedersen_commitment_parameters
+                .commit(&value, &randomness)
+                .unwrap(),
+        };
+        let other_c_e_q = crs
+            .crs_modeq
+            .pedersen_commitment_parameters
+            .commit(&value, &Integer::from(6))
+            .unwrap();
+
+        let proof_transcript = RefCell::new(transcript_with_aad(
+            b"membership",
+            &presentation_aad(3, b"session 1"),
+        ));
+        let mut verifier_channel = TranscriptVerifierChannel::new(&crs, &proof_transcript);
+        protocol
+            .prove(
+                &mut verifier_channel,
+                &mut rng1,
+                &mut rng2,
+                &statement,
+                &Witness {
+                    e: value,
+                    r_q: randomness,
+                    w: accum.1.witness.0.value,
+                },
+            )
+            .unwrap();
+        let proof = verifier_channel.proof().unwrap();
+
+        let mut registry = CrsRegistry::<MembershipProtocol>::new(b"membership");
+        let crs_digest = registry
+            .register(MembershipProtocol::from_crs(&crs))
+            .unwrap();
+        registry.set_accumulator(3, &statement.c_p);
+        let presentation = Presentation::<MembershipProtocol>::new(
+            statement.clone(),
+            proof,
+            crs_digest,
+            3,
+            b"session 1",
+        )
+        .unwrap();
+        verify_presentation(&registry, &presentation).unwrap();
+
+        let mut guard = ReplayGuard::new(16, 3);
+        verify_presentation_once(&registry, &mut guard, &presentation).unwrap();
+        assert!(matches!(
+            verify_presentation_once(&registry, &mut guard, &presentation),
+            Err(VerificationError::Replayed)
+        ));
+
+        // re-randomizing the LegoGroth16 proof gives another valid proof,
+        // which is still a replay
+        let mut rerandomized = presentation.clone();
+        let hash_to_prime_proof = &mut rerandomized.proof.proof.proof_hash_to_prime;
+        let r = Fr::rand(&mut rng2);
+        hash_to_prime_proof.a = hash_to_prime_proof
+            .a
+            .mul(r.inverse().unwrap().into_repr())
+            .into_affine();
+        hash_to_prime_proof.b = hash_to_prime_proof.b.mul(r.into_repr()).into_affine();
+        verify_presentation(&registry, &rerandomized).unwrap();
+        assert!(matches!(
+            verify_presentation_once(&registry, &mut guard, &rerandomized),
+            Err(VerificationError::Replayed)
+        ));
+
+        // the proof is bound to the epoch and the associated data
+        let mut other = presentation.clone();
+        other.epoch = 4;
+        assert!(verify_presentation(&registry, &other).is_err());
+        let mut other = presentation.clone();
+        other.aad = b"session 2".to_vec();
+        assert!(verify_presentation(&registry, &other).is_err());
+
+        let reference = presentation.clone().without_statement();
+        assert!(matches!(
+            verify_presentation(&registry, &reference),
+            Err(VerificationError::MissingStatement)
+        ));
+        verify_presentation(&registry, &reference.with_statement(statement.clone())).unwrap();
+
+        let mut other_statement = statement;
+        other_statement.c_e_q = other_c_e_q;
+        let mismatched = presentation.clone().with_statement(other_statement);
+        assert!(matches!(
+            verify_presentation(&registry, &mismatched),
+            Err(VerificationError::StatementMismatch)
+        ));
+
+        // the epoch is tied to the accumulator value the registry holds
+        let mut other = presentation.clone();
+        other.epoch = 5;
+        assert!(matches!(
+            verify_presentation(&registry, &other),
+            Err(VerificationError::UnknownEpoch)
+        ));
+        registry.set_accumulator(
+            3,
+            &accumulator::Accumulator::<Rsa2048, Integer, AccumulatorWithoutHashToPrime>::empty()
+                .value,
+        );
+        assert!(matches!(
+            verify_presentation(&registry, &presentation),
+            Err(VerificationError::AccumulatorMismatch)
+        ));
+
+        registry.advance_epoch(4);
+        assert!(matches!(
+            verify_presentation(&registry, &presentation),
+            Err(VerificationError::StaleEpoch)
+        ));
+        assert!(registry.accumulator(3).is_none());
+    }
+    #[test]
+    fn test_presentation_verifier_challenge() {
+        let params = Parameters::from_security_level(128).unwrap();
+        let mut rng1 = RandState::new();
+        rng1.seed(&Integer::from(13));
+        let mut rng2 = thread_rng();
+
+        let crs = MembershipProtocol::setup(&params, &mut rng1, &mut rng2)
+            .unwrap()
+            .crs;
+        let protocol = MembershipProtocol::from_crs(&crs);
+
+        let value = Integer::from(Integer::u_pow_u(2, params.hash_to_prime_bits as u32))
+            - &Integer::from(245);
+        let randomness = Integer::from(5);
+        let accum =
+            accumulator::Accumulator::<Rsa2048, Integer, AccumulatorWithoutHashToPrime>::empty()
+                .add_with_proof(&[value.clone()]);
+        let statement = Statement {
+            c_p: accum.0.value,
+            c_e_q: crs
+                .crs_modeq
+                .pedersen_commitment_parameters
+                .commit(&value, &randomness)
+                .unwrap(),
+        };
+
+        // the verifier hands out its challenge before the proof is created
+        let challenge = VerifierChallenge::generate(&mut rng2);
+        assert!(VerifierChallenge::new(&[0u8; 8]).is_err());
+        let proof_transcript = RefCell::new(transcript_with_aad(
+            b"membership",
+            &presentation_aad(3, b"session 1"),
+        ));
+        let mut verifier_channel =
+            TranscriptVerifierChannel::with_verifier_challenge(&crs, &proof_transcript, &challenge)
+                .unwrap();
+        protocol
+            .prove(
+                &mut verifier_channel,
+                &mut rng1,
+                &mut rng2,
+                &statement,
+                &Witness {
+                    e: value,
+                    r_q: randomness,
+                    w: accum.1.witness.0.value,
+                },
+            )
+            .unwrap();
+        let proof = verifier_channel.proof().unwrap();
+
+        let mut registry = CrsRegistry::<MembershipProtocol>::new(b"membership");
+        let crs_digest = registry
+            .register(MembershipProtocol::from_crs(&crs))
+            .unwrap();
+        registry.set_accumulator(3, &statement.c_p);
+        let presentation =
+            Presentation::<MembershipProtocol>::new(statement, proof, crs_digest, 3, b"session 1")
+                .unwrap()
+                .with_verifier_challenge(challenge.clone());
+        verify_presentation_with_challenge(&registry, &presentation, &challenge).unwrap();
+
+        assert!(matches!(
+            verify_presentation_with_challenge(
+                &registry,
+                &presentation,
+                &VerifierChallenge::generate(&mut rng2)
+            ),
+            Err(VerificationError::VerifierChallengeMismatch)
+        ));
+        // the proof doesn't verify without the challenge it is bound to
+        let mut other = presentation.clone();
+        other.verifier_challenge = None;
+        assert!(verify_presentation(&registry, &other).is_err());
+    }
+}
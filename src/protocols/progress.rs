@@ -0,0 +1,131 @@
+//! Progress reporting and cancellation for long-running operations, such as
+//! the SNARK setup and the hash-to-prime search, and statistics of the
+//! hash-to-prime search for monitoring.
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc, Mutex,
+};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Stage {
+    IntegerCommitmentSetup,
+    HashToPrimeSetup,
+    HashToPrimeSearch,
+}
+
+/// Observes the progress of an operation. Cancellation is cooperative: it is
+/// checked between steps and, while the SNARK parameters are generated on
+/// another thread, every 50 ms. A cancelled setup returns right away, while
+/// the parameter generation finishes in the background.
+pub trait ProgressObserver {
+    /// Called after `done` out of `total` steps of `stage` have completed.
+    fn on_progress(&self, _stage: Stage, _done: u64, _total: u64) {}
+
+    fn is_cancelled(&self) -> bool {
+        false
+    }
+
+    /// Called when the hash-to-prime search found a prime.
+    fn on_hash_to_prime(&self, _stats: &HashToPrimeStats) {}
+}
+
+/// How a prime was found by the hash-to-prime search, e.g. to tune
+/// `MESSAGE_SIZE` or the security level from the number of candidates.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct HashToPrimeStats {
+    /// The indices or offsets tried, including the one of the prime.
+    pub candidates: u64,
+    /// The Miller-Rabin rounds of each primality test.
+    pub miller_rabin_rounds: u32,
+}
+
+/// Aggregates the statistics of the hash-to-prime searches it observes.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct HashToPrimeSummary {
+    pub primes: u64,
+    pub candidates: u64,
+    pub max_candidates: u64,
+    pub last: Option<HashToPrimeStats>,
+}
+
+impl HashToPrimeSummary {
+    pub fn record(&mut self, stats: &HashToPrimeStats) {
+        self.primes += 1;
+        self.candidates += stats.candidates;
+        self.max_candidates = self.max_candidates.max(stats.candidates);
+        self.last = Some(*stats);
+    }
+
+    /// The mean number of candidates per prime.
+    pub fn mean_candidates(&self) -> f64 {
+        if self.primes == 0 {
+            return 0.0;
+        }
+        self.candidates as f64 / self.primes as f64
+    }
+}
+
+/// Collects a `HashToPrimeSummary`, and can be shared with other threads.
+#[derive(Clone, Debug, Default)]
+pub struct HashToPrimeStatsCollector {
+    summary: Arc<Mutex<HashToPrimeSummary>>,
+}
+
+impl HashToPrimeStatsCollector {
+    pub fn new() -> HashToPrimeStatsCollector {
+        HashToPrimeStatsCollector::default()
+    }
+
+    pub fn summary(&self) -> HashToPrimeSummary {
+        self.summary
+            .lock()
+            .map(|summary| summary.clone())
+            .unwrap_or_default()
+    }
+}
+
+impl ProgressObserver for HashToPrimeStatsCollector {
+    fn on_hash_to_prime(&self, stats: &HashToPrimeStats) {
+        if let Ok(mut summary) = self.summary.lock() {
+            summary.record(stats);
+        }
+    }
+}
+
+/// Ignores progress and never cancels.
+pub struct NoProgress;
+
+impl ProgressObserver for NoProgress {}
+
+/// A cancellation flag which can be shared with another thread.
+#[derive(Clone, Debug, Default)]
+pub struct CancellationToken {
+    cancelled: Arc<AtomicBool>,
+}
+
+impl CancellationToken {
+    pub fn new() -> CancellationToken {
+        CancellationToken::default()
+    }
+
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::SeqCst);
+    }
+}
+
+impl ProgressObserver for CancellationToken {
+    fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::SeqCst)
+    }
+}
+
+/// Reports progress and returns whether the operation should continue.
+pub(crate) fn report<O: ProgressObserver>(
+    observer: &O,
+    stage: Stage,
+    done: u64,
+    total: u64,
+) -> bool {
+    observer.on_progress(stage, done, total);
+    !observer.is_cancelled()
+}
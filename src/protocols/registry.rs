@@ -0,0 +1,263 @@
+//! Verification against several CRS versions, for rolling CRS upgrades.
+//!
+//! During a transition window, provers may still use the old CRS while others
+//! already use the new one. Proofs carry the digest of the CRS they were
+//! created under, and the registry dispatches each proof to the verifier
+//! loaded for that digest.
+//!
+//! The digest binds the parameters, the integer and Pedersen commitment bases
+//! and what the verifier uses of the hash-to-prime parameters, e.g. the
+//! verifying key of the SNARK. Every membership and nonmembership proof
+//! carries the digest of its CRS, so that verifying under another CRS fails with
+//! `VerificationError::CrsMismatch`.
+use crate::{
+    commitments::{integer::IntegerCommitment, pedersen::PedersenCommitment},
+    parameters::Parameters,
+    protocols::{
+        hash_to_prime::HashToPrimeProtocol,
+        membership::{
+            transcript::TranscriptProverChannel as MembershipTranscriptProverChannel,
+            Proof as MembershipProof, Protocol as MembershipProtocol,
+            Statement as MembershipStatement,
+        },
+        nonmembership::{
+            transcript::TranscriptProverChannel as NonMembershipTranscriptProverChannel,
+            Proof as NonMembershipProof, Protocol as NonMembershipProtocol,
+            Statement as NonMembershipStatement,
+        },
+        VerificationError,
+    },
+    transcript::{transcript_with_aad, PROTOCOL_VERSION},
+    utils::{
+        curve::{CurveError, CurvePointProjective},
+        ConvertibleUnknownOrderGroup,
+    },
+};
+use blake2::{Blake2s, Digest};
+use std::{
+    cell::RefCell,
+    collections::{BTreeMap, HashMap},
+};
+
+pub type CrsDigest = [u8; 32];
+
+/// A proof together with the digest of the CRS it was created under and the
+/// `PROTOCOL_VERSION` of the transcript rules it was created with.
+#[derive(Clone)]
+pub struct VersionedProof<Pr> {
+    pub crs_digest: CrsDigest,
+    pub protocol_version: u8,
+    pub proof: Pr,
+}
+
+impl<Pr> VersionedProof<Pr> {
+    /// Tags a proof created with the current `PROTOCOL_VERSION`.
+    pub fn new(crs_digest: CrsDigest, proof: Pr) -> VersionedProof<Pr> {
+        VersionedProof {
+            crs_digest,
+            protocol_version: PROTOCOL_VERSION,
+            proof,
+        }
+    }
+}
+
+pub(crate) fn crs_digest<G: ConvertibleUnknownOrderGroup, P: CurvePointProjective>(
+    label: &[u8],
+    parameters: &[&Parameters],
+    integer_commitment_parameters: &IntegerCommitment<G>,
+    pedersen_commitment_parameters: &PedersenCommitment<P>,
+    hash_to_prime_parameters: &[u8],
+) -> Result<CrsDigest, CurveError> {
+    let mut hasher = Blake2s::default();
+    let mut append = |bytes: &[u8]| {
+        hasher.update(&(bytes.len() as u64).to_le_bytes());
+        hasher.update(bytes);
+    };
+    append(label);
+    for p in parameters {
+        for value in &[
+            p.security_level,
+            p.security_zk,
+            p.security_soundness,
+            p.hash_to_prime_bits,
+            p.field_size_bits,
+        ] {
+            append(&value.to_le_bytes());
+        }
+    }
+    append(&G::elem_to_bytes(&integer_commitment_parameters.g));
+    append(&G::elem_to_bytes(&integer_commitment_parameters.h));
+    append(&pedersen_commitment_parameters.g.to_affine_bytes()?);
+    append(&pedersen_commitment_parameters.h.to_affine_bytes()?);
+    append(hash_to_prime_parameters);
+
+    let mut digest = [0u8; 32];
+    digest.copy_from_slice(&hasher.finalize());
+    Ok(digest)
+}
+
+/// Verifiers loaded for the CRS versions currently accepted.
+pub struct CrsRegistry<V> {
+    transcript_label: &'static [u8],
+    verifiers: HashMap<CrsDigest, V>,
+    epoch: u64,
+    /// The accumulator value of each epoch still accepted, as encoded by
+    /// `G::elem_to_bytes`.
+    accumulators: BTreeMap<u64, Vec<u8>>,
+    min_protocol_version: u8,
+}
+
+impl<V> CrsRegistry<V> {
+    /// Creates an empty registry, verifying proofs on transcripts created
+    /// with `transcript_label`, which has to match the provers'.
+    pub fn new(transcript_label: &'static [u8]) -> CrsRegistry<V> {
+        CrsRegistry {
+            transcript_label,
+            verifiers: HashMap::new(),
+            epoch: 0,
+            accumulators: BTreeMap::new(),
+            min_protocol_version: PROTOCOL_VERSION,
+        }
+    }
+
+    pub fn transcript_label(&self) -> &'static [u8] {
+        self.transcript_label
+    }
+
+    /// Oldest accumulator epoch accepted in presentations, see
+    /// `protocols::presentation`.
+    pub fn epoch(&self) -> u64 {
+        self.epoch
+    }
+
+    /// Moves to a newer epoch, rejecting presentations from older ones and
+    /// forgetting their accumulator values.
+    pub fn advance_epoch(&mut self, epoch: u64) {
+        if epoch > self.epoch {
+            self.epoch = epoch;
+            self.accumulators = self.accumulators.split_off(&epoch);
+        }
+    }
+
+    /// The encoded accumulator value recorded for `epoch`, if any.
+    pub fn accumulator(&self, epoch: u64) -> Option<&[u8]> {
+        self.accumulators.get(&epoch).map(|value| value.as_slice())
+    }
+
+    /// Oldest protocol version accepted, `PROTOCOL_VERSION` by default.
+    pub fn min_protocol_version(&self) -> u8 {
+        self.min_protocol_version
+    }
+
+    /// Accepts proofs tagged with versions from `version` on, e.g. lowered
+    /// during a transition to a new version or raised after a fix, so that
+    /// proofs can't be replayed under the old transcript rules.
+    pub fn set_min_protocol_version(&mut self, version: u8) {
+        self.min_protocol_version = version;
+    }
+
+    /// Rejects versions older than the minimum, or newer than this
+    /// implementation.
+    pub fn check_protocol_version(&self, version: u8) -> Result<(), VerificationError> {
+        if version < self.min_protocol_version || version > PROTOCOL_VERSION {
+            return Err(VerificationError::UnsupportedVersion);
+        }
+        Ok(())
+    }
+
+    /// Stops accepting proofs under the CRS with `digest`, e.g. at the end of
+    /// a transition window.
+    pub fn remove(&mut self, digest: &CrsDigest) -> Option<V> {
+        self.verifiers.remove(digest)
+    }
+
+    pub fn get(&self, digest: &CrsDigest) -> Option<&V> {
+        self.verifiers.get(digest)
+    }
+
+    pub fn len(&self) -> usize {
+        self.verifiers.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.verifiers.is_empty()
+    }
+
+    fn verifier(&self, digest: &CrsDigest) -> Result<&V, VerificationError> {
+        self.verifiers
+            .get(digest)
+            .ok_or(VerificationError::UnknownCrs)
+    }
+}
+
+impl<G: ConvertibleUnknownOrderGroup, P: CurvePointProjective, HP: HashToPrimeProtocol<P>>
+    CrsRegistry<MembershipProtocol<G, P, HP>>
+{
+    /// Loads a verifier, returning the digest of its CRS.
+    pub fn register(
+        &mut self,
+        protocol: MembershipProtocol<G, P, HP>,
+    ) -> Result<CrsDigest, CurveError> {
+        let digest = protocol.crs_digest()?;
+        self.verifiers.insert(digest, protocol);
+        Ok(digest)
+    }
+
+    /// Records `value`, the accumulator of `epoch`, which the statements of
+    /// presentations for `epoch` have to be about.
+    pub fn set_accumulator(&mut self, epoch: u64, value: &G::Elem) {
+        self.accumulators.insert(epoch, G::elem_to_bytes(value));
+    }
+
+    /// Verifies `proof`, bound to the associated data `aad`, with the
+    /// verifier of the CRS it was created under.
+    pub fn verify_any(
+        &self,
+        statement: &MembershipStatement<G, P>,
+        proof: &VersionedProof<MembershipProof<G, P, HP>>,
+        aad: &[u8],
+    ) -> Result<(), VerificationError> {
+        self.check_protocol_version(proof.protocol_version)?;
+        let protocol = self.verifier(&proof.crs_digest)?;
+        let transcript = RefCell::new(transcript_with_aad(self.transcript_label, aad));
+        let mut prover_channel =
+            MembershipTranscriptProverChannel::new(&protocol.crs, &transcript, &proof.proof);
+        protocol.verify(&mut prover_channel, statement)
+    }
+}
+
+impl<G: ConvertibleUnknownOrderGroup, P: CurvePointProjective, HP: HashToPrimeProtocol<P>>
+    CrsRegistry<NonMembershipProtocol<G, P, HP>>
+{
+    /// Loads a verifier, returning the digest of its CRS.
+    pub fn register(
+        &mut self,
+        protocol: NonMembershipProtocol<G, P, HP>,
+    ) -> Result<CrsDigest, CurveError> {
+        let digest = protocol.crs_digest()?;
+        self.verifiers.insert(digest, protocol);
+        Ok(digest)
+    }
+
+    /// Records `value`, the accumulator of `epoch`, which the statements of
+    /// presentations for `epoch` have to be about.
+    pub fn set_accumulator(&mut self, epoch: u64, value: &G::Elem) {
+        self.accumulators.insert(epoch, G::elem_to_bytes(value));
+    }
+
+    /// Verifies `proof`, bound to the associated data `aad`, with the
+    /// verifier of the CRS it was created under.
+    pub fn verify_any(
+        &self,
+        statement: &NonMembershipStatement<G, P>,
+        proof: &VersionedProof<NonMembershipProof<G, P, HP>>,
+        aad: &[u8],
+    ) -> Result<(), VerificationError> {
+        self.check_protocol_version(proof.protocol_version)?;
+        let protocol = self.verifier(&proof.crs_digest)?;
+        let transcript = RefCell::new(transcript_with_aad(self.transcript_label, aad));
+        let mut prover_channel =
+            NonMembershipTranscriptProverChannel::new(&protocol.crs, &transcript, &proof.proof);
+        protocol.verify(&mut prover_channel, statement)
+    }
+}
@@ -0,0 +1,144 @@
+//! Replay protection for verifiers. A proof doesn't prevent its own replay: a
+//! presentation verified once verifies again when sent by someone else, as
+//! long as its epoch and associated data are accepted.
+//!
+//! `ReplayGuard` records a key for each accepted proof and rejects proofs
+//! whose key it already holds. `verify_presentation_once` uses a challenge
+//! drawn from the verifier's transcript right before the hash-to-prime proof,
+//! which binds the CRS, the statement, the epoch, the associated data and
+//! every message of the sigma protocols. The LegoGroth16 proof is left out:
+//! anyone can re-randomize it into another valid proof, which would otherwise
+//! get a fresh key. Other integrations can record their own keys, e.g. a
+//! nullifier, with `check_and_record`.
+//!
+//! The guard only holds the keys of the current epoch and older presentations
+//! are rejected as stale, so it forgets everything when the epoch advances.
+//! Within an epoch it keeps the `window` most recent keys and evicts the
+//! oldest one when full, after which that presentation is accepted again. A
+//! verifier whose window doesn't cover the presentations it accepts per epoch
+//! has to check a nonce carried in the associated data instead.
+use crate::protocols::{
+    presentation::{verify_presentation_key, Presentation, PresentationProtocol},
+    registry::CrsRegistry,
+    VerificationError,
+};
+use std::collections::{HashMap, VecDeque};
+
+pub type ReplayKey = [u8; 32];
+
+pub struct ReplayGuard {
+    window: usize,
+    epoch: u64,
+    seen: HashMap<ReplayKey, u64>,
+    order: VecDeque<ReplayKey>,
+}
+
+impl ReplayGuard {
+    /// A guard keeping up to `window` keys, evicting the oldest to record
+    /// another. A `window` of 0 records nothing and only rejects stale epochs.
+    pub fn new(window: usize, epoch: u64) -> ReplayGuard {
+        ReplayGuard {
+            window,
+            epoch,
+            seen: HashMap::new(),
+            order: VecDeque::new(),
+        }
+    }
+
+    pub fn epoch(&self) -> u64 {
+        self.epoch
+    }
+
+    pub fn len(&self) -> usize {
+        self.seen.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.seen.is_empty()
+    }
+
+    /// Moves to a newer epoch, forgetting the keys of older ones.
+    pub fn advance_epoch(&mut self, epoch: u64) {
+        if epoch > self.epoch {
+            self.epoch = epoch;
+            let seen = &mut self.seen;
+            seen.retain(|_, key_epoch| *key_epoch >= epoch);
+            self.order.retain(|key| seen.contains_key(key));
+        }
+    }
+
+    /// Records `key` of a proof accepted for `epoch`, rejecting it with
+    /// `Replayed` if it was already recorded and with `StaleEpoch` if `epoch`
+    /// is older than the guard's.
+    pub fn check_and_record(
+        &mut self,
+        epoch: u64,
+        key: ReplayKey,
+    ) -> Result<(), VerificationError> {
+        if epoch < self.epoch {
+            return Err(VerificationError::StaleEpoch);
+        }
+        if self.seen.contains_key(&key) {
+            return Err(VerificationError::Replayed);
+        }
+        if self.window == 0 {
+            return Ok(());
+        }
+        if self.order.len() >= self.window {
+            if let Some(oldest) = self.order.pop_front() {
+                self.seen.remove(&oldest);
+            }
+        }
+        self.seen.insert(key, epoch);
+        self.order.push_back(key);
+
+        Ok(())
+    }
+}
+
+/// Verifies `presentation` as `verify_presentation` does and rejects it with
+/// `Replayed` if `guard` already accepted it. The guard is first moved to the
+/// epoch of `registry`.
+pub fn verify_presentation_once<V: PresentationProtocol>(
+    registry: &CrsRegistry<V>,
+    guard: &mut ReplayGuard,
+    presentation: &Presentation<V>,
+) -> Result<(), VerificationError> {
+    guard.advance_epoch(registry.epoch());
+    let key = verify_presentation_key(registry, presentation)?;
+    guard.check_and_record(presentation.epoch, key)
+}
+
+#[cfg(test)]
+mod test {
+    use super::ReplayGuard;
+    use crate::protocols::VerificationError;
+
+    #[test]
+    fn test_replay_guard() {
+        let mut guard = ReplayGuard::new(2, 3);
+        guard.check_and_record(3, [1u8; 32]).unwrap();
+        assert!(matches!(
+            guard.check_and_record(3, [1u8; 32]),
+            Err(VerificationError::Replayed)
+        ));
+        assert!(matches!(
+            guard.check_and_record(2, [2u8; 32]),
+            Err(VerificationError::StaleEpoch)
+        ));
+        // a presentation for a later epoch is kept after the guard advances
+        guard.check_and_record(4, [2u8; 32]).unwrap();
+        guard.advance_epoch(4);
+        assert_eq!(guard.len(), 1);
+        assert!(matches!(
+            guard.check_and_record(4, [2u8; 32]),
+            Err(VerificationError::Replayed)
+        ));
+        guard.check_and_record(4, [1u8; 32]).unwrap();
+
+        // the oldest key leaves the window
+        guard.check_and_record(4, [3u8; 32]).unwrap();
+        assert_eq!(guard.len(), 2);
+        guard.check_and_record(4, [2u8; 32]).unwrap();
+    }
+}
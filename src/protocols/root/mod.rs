@@ -1,13 +1,27 @@
 //! Implements root, to be used in the membership protocol.
 use crate::{
-    commitments::{integer::IntegerCommitment, Commitment},
+    commitments::{integer::IntegerCommitment, Commitment, CommitmentError},
     parameters::Parameters,
-    protocols::{ProofError, VerificationError},
-    utils::{random_symmetric_range, ConvertibleUnknownOrderGroup},
+    protocols::{
+        blinding::{BlindingHistory, BlindingRanges},
+        cache::CacheKeyBuilder,
+        statement::{StatementDigest, StatementHasher},
+        validate_soundness, CRSError, ProofError, VerificationError,
+    },
+    utils::{
+        exp_signed, exp_signed_with, integer_to_signed_bytes, modulus::validate_group_modulus,
+        random_symmetric_range, ConvertibleUnknownOrderGroup, ExpScratch,
+    },
 };
 use channel::{RootProverChannel, RootVerifierChannel};
 use rug::rand::MutRandState;
 use rug::Integer;
+#[cfg(feature = "compressed")]
+use std::cell::RefCell;
+use std::cmp::Ordering;
+use std::hash::{Hash, Hasher};
+#[cfg(feature = "compressed")]
+use transcript::TranscriptProtocolRoot;
 
 pub mod channel;
 pub mod transcript;
@@ -23,12 +37,130 @@ pub struct Statement<G: ConvertibleUnknownOrderGroup> {
     pub acc: G::Elem,
 }
 
+impl<G: ConvertibleUnknownOrderGroup> Clone for Statement<G> {
+    fn clone(&self) -> Self {
+        Self {
+            c_e: self.c_e.clone(),
+            acc: self.acc.clone(),
+        }
+    }
+}
+
+impl<G: ConvertibleUnknownOrderGroup> PartialEq for Statement<G> {
+    fn eq(&self, other: &Self) -> bool {
+        self.c_e == other.c_e && self.acc == other.acc
+    }
+}
+
+impl<G: ConvertibleUnknownOrderGroup> Eq for Statement<G> {}
+
+impl<G: ConvertibleUnknownOrderGroup> Hash for Statement<G> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.c_e.hash(state);
+        self.acc.hash(state);
+    }
+}
+
+impl<G: ConvertibleUnknownOrderGroup> Statement<G> {
+    /// Stable identifier of the statement, see `protocols::statement`.
+    pub fn digest(&self) -> StatementDigest {
+        let mut hasher = StatementHasher::new(b"root");
+        hasher.append_group_element::<G>(b"c_e", &self.c_e);
+        hasher.append_group_element::<G>(b"acc", &self.acc);
+        hasher.finish()
+    }
+}
+
 pub struct Witness<G: ConvertibleUnknownOrderGroup> {
     pub e: Integer,
     pub r: Integer,
     pub w: G::Elem,
 }
 
+/// An accumulator value hidden as `c_acc = acc h^{r_a}`, where `c_a` is an
+/// integer commitment to `r_a`, so that proofs can be made relative to it
+/// without revealing the accumulator.
+pub struct HiddenAccumulator<G: ConvertibleUnknownOrderGroup> {
+    pub c_acc: G::Elem,
+    pub c_a: <IntegerCommitment<G> as Commitment>::Instance,
+}
+
+impl<G: ConvertibleUnknownOrderGroup> Clone for HiddenAccumulator<G> {
+    fn clone(&self) -> Self {
+        Self {
+            c_acc: self.c_acc.clone(),
+            c_a: self.c_a.clone(),
+        }
+    }
+}
+
+/// The randomness of a `HiddenAccumulator`, `c_a = g^{r_a} h^{r_a_prime}`.
+#[derive(Clone)]
+pub struct HiddenAccumulatorOpening {
+    pub r_a: Integer,
+    pub r_a_prime: Integer,
+}
+
+impl<G: ConvertibleUnknownOrderGroup> HiddenAccumulator<G> {
+    /// Hides `acc` with randomness sampled like the blinding of the witness.
+    pub fn hide<R: MutRandState>(
+        crs: &CRSRoot<G>,
+        acc: &G::Elem,
+        rng: &mut R,
+    ) -> Result<(HiddenAccumulator<G>, HiddenAccumulatorOpening), CommitmentError> {
+        let ranges = BlindingRanges::new::<G>(&crs.parameters);
+        let opening = HiddenAccumulatorOpening {
+            r_a: random_symmetric_range(rng, &ranges.randomness),
+            r_a_prime: random_symmetric_range(rng, &ranges.randomness),
+        };
+        let hidden = HiddenAccumulator {
+            c_acc: G::op(
+                acc,
+                &exp_signed::<G>(&crs.integer_commitment_parameters.h, &opening.r_a),
+            ),
+            c_a: crs
+                .integer_commitment_parameters
+                .commit(&opening.r_a, &opening.r_a_prime)?,
+        };
+        Ok((hidden, opening))
+    }
+
+    /// Checks `acc` and `opening` match the hidden accumulator.
+    pub fn open(
+        &self,
+        crs: &CRSRoot<G>,
+        acc: &G::Elem,
+        opening: &HiddenAccumulatorOpening,
+    ) -> Result<(), CommitmentError> {
+        crs.integer_commitment_parameters
+            .open(&self.c_a, &opening.r_a, &opening.r_a_prime)?;
+        let c_acc = G::op(
+            acc,
+            &exp_signed::<G>(&crs.integer_commitment_parameters.h, &opening.r_a),
+        );
+        if c_acc != self.c_acc {
+            return Err(CommitmentError::WrongOpening);
+        }
+
+        Ok(())
+    }
+}
+
+/// A statement where the accumulator is only given as a `HiddenAccumulator`.
+pub struct HiddenStatement<G: ConvertibleUnknownOrderGroup> {
+    pub c_e: <IntegerCommitment<G> as Commitment>::Instance,
+    pub acc: HiddenAccumulator<G>,
+}
+
+impl<G: ConvertibleUnknownOrderGroup> Clone for HiddenStatement<G> {
+    fn clone(&self) -> Self {
+        Self {
+            c_e: self.c_e.clone(),
+            acc: self.acc.clone(),
+        }
+    }
+}
+
 #[derive(Clone)]
 pub struct Message1<G: ConvertibleUnknownOrderGroup> {
     pub c_w: G::Elem,
@@ -60,59 +192,185 @@ pub struct Proof<G: ConvertibleUnknownOrderGroup> {
     pub message3: Message3,
 }
 
+impl<G: ConvertibleUnknownOrderGroup> Proof<G> {
+    pub fn append_to_cache_key(&self, key: &mut CacheKeyBuilder) {
+        key.append_group_element::<G>(b"c_w", &self.message1.c_w);
+        key.append_group_element::<G>(b"c_r", &self.message1.c_r);
+        key.append_group_element::<G>(b"alpha1", &self.message2.alpha1);
+        key.append_group_element::<G>(b"alpha2", &self.message2.alpha2);
+        key.append_group_element::<G>(b"alpha3", &self.message2.alpha3);
+        key.append_group_element::<G>(b"alpha4", &self.message2.alpha4);
+        key.append_integer(b"s_e", &self.message3.s_e);
+        key.append_integer(b"s_r", &self.message3.s_r);
+        key.append_integer(b"s_r_2", &self.message3.s_r_2);
+        key.append_integer(b"s_r_3", &self.message3.s_r_3);
+        key.append_integer(b"s_beta", &self.message3.s_beta);
+        key.append_integer(b"s_delta", &self.message3.s_delta);
+    }
+
+    /// Size in bytes, with group elements encoded by `G::elem_to_bytes` and
+    /// integers by `integer_to_signed_bytes`.
+    pub fn serialized_size(&self) -> usize {
+        let elements = [
+            &self.message1.c_w,
+            &self.message1.c_r,
+            &self.message2.alpha1,
+            &self.message2.alpha2,
+            &self.message2.alpha3,
+            &self.message2.alpha4,
+        ];
+        let integers = [
+            &self.message3.s_e,
+            &self.message3.s_r,
+            &self.message3.s_r_2,
+            &self.message3.s_r_3,
+            &self.message3.s_beta,
+            &self.message3.s_delta,
+        ];
+        elements
+            .iter()
+            .map(|e| G::elem_to_bytes(e).len())
+            .sum::<usize>()
+            + integers
+                .iter()
+                .map(|i| integer_to_signed_bytes(i).len())
+                .sum::<usize>()
+    }
+}
+
+/// A proof without the alphas, which are recomputed from the challenge and
+/// the responses.
+#[cfg(feature = "compressed")]
+#[derive(Clone)]
+pub struct CompressedProof<G: ConvertibleUnknownOrderGroup> {
+    pub message1: Message1<G>,
+    pub challenge: Integer,
+    pub message3: Message3,
+}
+
+/// The CRS is private so that it can't be changed without recomputing
+/// `precomputed`, use `from_crs` to switch to another one.
 pub struct Protocol<G: ConvertibleUnknownOrderGroup> {
-    pub crs: CRSRoot<G>,
+    crs: CRSRoot<G>,
+    precomputed: Precomputed<G>,
+}
+
+/// Values of the verification equations which only depend on the CRS, so
+/// they are computed once per protocol rather than once per proof.
+struct Precomputed<G: ConvertibleUnknownOrderGroup> {
+    h_inv: G::Elem,
+    g_inv: G::Elem,
+    s_e_bound: Integer,
+}
+
+impl<G: ConvertibleUnknownOrderGroup> Precomputed<G> {
+    fn from_crs(crs: &CRSRoot<G>) -> Precomputed<G> {
+        Precomputed {
+            h_inv: G::inv(&crs.integer_commitment_parameters.h),
+            g_inv: G::inv(&crs.integer_commitment_parameters.g),
+            s_e_bound: BlindingRanges::new::<G>(&crs.parameters).exponent_response_bound(),
+        }
+    }
 }
 
 impl<G: ConvertibleUnknownOrderGroup> Protocol<G> {
-    pub fn from_crs(crs: &CRSRoot<G>) -> Protocol<G> {
-        Protocol { crs: crs.clone() }
+    pub fn from_crs(crs: &CRSRoot<G>) -> Result<Protocol<G>, CRSError> {
+        if crs.parameters.security_soundness + 1 >= crs.parameters.hash_to_prime_bits {
+            return Err(CRSError::InvalidParameters);
+        }
+        validate_soundness::<G>(&crs.parameters)?;
+        validate_group_modulus::<G>(&crs.parameters).map_err(|_| CRSError::InvalidParameters)?;
+        Ok(Protocol {
+            crs: crs.clone(),
+            precomputed: Precomputed::from_crs(crs),
+        })
+    }
+
+    pub fn crs(&self) -> &CRSRoot<G> {
+        &self.crs
     }
 
     pub fn prove<R: MutRandState, C: RootVerifierChannel<G>>(
+        &self,
+        verifier_channel: &mut C,
+        rng: &mut R,
+        statement: &Statement<G>,
+        witness: &Witness<G>,
+    ) -> Result<(), ProofError> {
+        self.prove_with_history(
+            verifier_channel,
+            rng,
+            statement,
+            witness,
+            &mut BlindingHistory::new(0),
+        )
+    }
+
+    /// Proves while recording the blinding values in `history`, failing if
+    /// they were used before.
+    pub fn prove_with_history<R: MutRandState, C: RootVerifierChannel<G>>(
         &self,
         verifier_channel: &mut C,
         rng: &mut R,
         _: &Statement<G>,
         witness: &Witness<G>,
+        history: &mut BlindingHistory,
     ) -> Result<(), ProofError> {
-        let r_2 = random_symmetric_range(rng, &(G::order_upper_bound() / Integer::from(2)));
-        let r_3 = random_symmetric_range(rng, &(G::order_upper_bound() / Integer::from(2)));
+        self.prove_internal(verifier_channel, rng, witness, None, history)?;
+        Ok(())
+    }
+
+    /// Proves `witness.w` is an `e`-th root of the accumulator hidden in
+    /// `statement`, recording the blinding values in `history`.
+    pub fn prove_hidden_with_history<R: MutRandState, C: RootVerifierChannel<G>>(
+        &self,
+        verifier_channel: &mut C,
+        rng: &mut R,
+        _: &HiddenStatement<G>,
+        witness: &Witness<G>,
+        opening: &HiddenAccumulatorOpening,
+        history: &mut BlindingHistory,
+    ) -> Result<(), ProofError> {
+        self.prove_internal(verifier_channel, rng, witness, Some(opening), history)?;
+        Ok(())
+    }
+
+    /// With a hidden accumulator, `beta` and `delta` are shifted by its
+    /// randomness, which is zero for a public one. `c_w` and `c_r` hide `w` as
+    /// a `HiddenAccumulator`, whose opening is returned so that a proof can be
+    /// chained to this one.
+    pub(crate) fn prove_internal<R: MutRandState, C: RootVerifierChannel<G>>(
+        &self,
+        verifier_channel: &mut C,
+        rng: &mut R,
+        witness: &Witness<G>,
+        opening: Option<&HiddenAccumulatorOpening>,
+        history: &mut BlindingHistory,
+    ) -> Result<HiddenAccumulatorOpening, ProofError> {
+        let ranges = BlindingRanges::new::<G>(&self.crs.parameters);
+        let r_2 = random_symmetric_range(rng, &ranges.randomness);
+        let r_3 = random_symmetric_range(rng, &ranges.randomness);
         let c_w = G::op(
             &witness.w,
-            &G::exp(&self.crs.integer_commitment_parameters.h, &r_2),
+            &exp_signed::<G>(&self.crs.integer_commitment_parameters.h, &r_2),
         );
         let c_r = self.crs.integer_commitment_parameters.commit(&r_2, &r_3)?;
 
         let message1 = Message1::<G> { c_w, c_r };
-        verifier_channel.send_message1(&message1)?;
 
-        let r_e_range = Integer::from(Integer::u_pow_u(
-            2,
-            (self.crs.parameters.security_zk
-                + self.crs.parameters.security_soundness
-                + self.crs.parameters.hash_to_prime_bits) as u32,
-        ));
-        let r_e = random_symmetric_range(rng, &r_e_range);
-
-        let r_r_range: Integer = G::order_upper_bound() / 2
-            * Integer::from(Integer::u_pow_u(
-                2,
-                (self.crs.parameters.security_zk + self.crs.parameters.security_soundness) as u32,
-            ));
-        let r_r = random_symmetric_range(rng, &r_r_range);
-        let r_r_2 = random_symmetric_range(rng, &r_r_range);
-        let r_r_3 = random_symmetric_range(rng, &r_r_range);
-
-        let r_beta_delta_range: Integer = G::order_upper_bound() / 2
-            * Integer::from(Integer::u_pow_u(
-                2,
-                (self.crs.parameters.security_zk
-                    + self.crs.parameters.security_soundness
-                    + self.crs.parameters.hash_to_prime_bits) as u32,
-            ));
-        let r_beta = random_symmetric_range(rng, &r_beta_delta_range);
-        let r_delta = random_symmetric_range(rng, &r_beta_delta_range);
+        let r_e = random_symmetric_range(rng, &ranges.exponent);
+
+        let r_r = random_symmetric_range(rng, &ranges.randomness_mask);
+        let r_r_2 = random_symmetric_range(rng, &ranges.randomness_mask);
+        let r_r_3 = random_symmetric_range(rng, &ranges.randomness_mask);
+
+        let r_beta = random_symmetric_range(rng, &ranges.product_mask);
+        let r_delta = random_symmetric_range(rng, &ranges.product_mask);
+        history.record(
+            b"root",
+            &[&r_2, &r_3, &r_e, &r_r, &r_r_2, &r_r_3, &r_beta, &r_delta],
+        )?;
+        verifier_channel.send_message1(&message1)?;
 
         let alpha1 = self.crs.integer_commitment_parameters.commit(&r_e, &r_r)?;
         let alpha2 = self
@@ -129,7 +387,7 @@ impl<G: ConvertibleUnknownOrderGroup> Protocol<G> {
             &G::inv(&self.crs.integer_commitment_parameters.g),
         );
         let alpha4 = G::op(
-            &G::exp(&message1.c_r, &r_e),
+            &exp_signed::<G>(&message1.c_r, &r_e),
             &integer_commitment_alpha4.commit(&r_delta, &r_beta)?,
         );
         let message2 = Message2::<G> {
@@ -145,8 +403,15 @@ impl<G: ConvertibleUnknownOrderGroup> Protocol<G> {
         let s_r = r_r - c.clone() * witness.r.clone();
         let s_r_2 = r_r_2 - c.clone() * r_2.clone();
         let s_r_3 = r_r_3 - c.clone() * r_3.clone();
-        let s_beta = r_beta - c.clone() * witness.e.clone() * r_2;
-        let s_delta = r_delta - c * witness.e.clone() * r_3;
+        let (beta, delta) = match opening {
+            Some(opening) => (
+                witness.e.clone() * &r_2 - &opening.r_a,
+                witness.e.clone() * &r_3 - &opening.r_a_prime,
+            ),
+            None => (witness.e.clone() * &r_2, witness.e.clone() * &r_3),
+        };
+        let s_beta = r_beta - c.clone() * beta;
+        let s_delta = r_delta - c * delta;
         let message3 = Message3 {
             s_e,
             s_r,
@@ -157,84 +422,241 @@ impl<G: ConvertibleUnknownOrderGroup> Protocol<G> {
         };
         verifier_channel.send_message3(&message3)?;
 
-        Ok(())
+        Ok(HiddenAccumulatorOpening {
+            r_a: r_2,
+            r_a_prime: r_3,
+        })
     }
 
     pub fn verify<C: RootProverChannel<G>>(
         &self,
         prover_channel: &mut C,
         statement: &Statement<G>,
+    ) -> Result<(), VerificationError> {
+        self.verify_with_scratch(prover_channel, statement, &mut ExpScratch::new())
+    }
+
+    /// Verifies like `verify`, negating exponents into `scratch`, so that a
+    /// batch of proofs verified with the same scratch allocates it once.
+    pub fn verify_with_scratch<C: RootProverChannel<G>>(
+        &self,
+        prover_channel: &mut C,
+        statement: &Statement<G>,
+        scratch: &mut ExpScratch,
     ) -> Result<(), VerificationError> {
         let message1 = prover_channel.receive_message1()?;
         let message2 = prover_channel.receive_message2()?;
         let c = prover_channel.generate_and_send_challenge()?;
         let message3 = prover_channel.receive_message3()?;
+        let expected_message2 = self.expected_message2_internal(
+            &statement.c_e,
+            &statement.acc,
+            None,
+            &message1,
+            &c,
+            &message3,
+            scratch,
+        );
+        self.check_message2(&message2, &expected_message2, &message3)
+    }
+
+    /// Verifies a proof against a statement with a hidden accumulator.
+    pub fn verify_hidden<C: RootProverChannel<G>>(
+        &self,
+        prover_channel: &mut C,
+        statement: &HiddenStatement<G>,
+    ) -> Result<(), VerificationError> {
+        let message1 = prover_channel.receive_message1()?;
+        let message2 = prover_channel.receive_message2()?;
+        let c = prover_channel.generate_and_send_challenge()?;
+        let message3 = prover_channel.receive_message3()?;
+        let expected_message2 = self.expected_message2_internal(
+            &statement.c_e,
+            &statement.acc.c_acc,
+            Some(&statement.acc.c_a),
+            &message1,
+            &c,
+            &message3,
+            &mut ExpScratch::new(),
+        );
+        self.check_message2(&message2, &expected_message2, &message3)
+    }
+
+    fn check_message2(
+        &self,
+        message2: &Message2<G>,
+        expected_message2: &Message2<G>,
+        message3: &Message3,
+    ) -> Result<(), VerificationError> {
+        // all the checks run, so that timing doesn't reveal the failing one
+        if (expected_message2.alpha1 == message2.alpha1)
+            & (expected_message2.alpha2 == message2.alpha2)
+            & (expected_message2.alpha3 == message2.alpha3)
+            & (expected_message2.alpha4 == message2.alpha4)
+            & self.is_s_e_in_range(&message3.s_e)
+        {
+            Ok(())
+        } else {
+            Err(VerificationError::VerificationFailed)
+        }
+    }
+
+    /// Receives a proof without checking it, advancing `prover_channel`
+    /// through the same messages and challenge as `verify`.
+    pub fn receive_unverified<C: RootProverChannel<G>>(
+        &self,
+        prover_channel: &mut C,
+    ) -> Result<Proof<G>, VerificationError> {
+        let message1 = prover_channel.receive_message1()?;
+        let message2 = prover_channel.receive_message2()?;
+        prover_channel.generate_and_send_challenge()?;
+        let message3 = prover_channel.receive_message3()?;
+        Ok(Proof {
+            message1,
+            message2,
+            message3,
+        })
+    }
+
+    /// Recomputes the alphas the verifier expects given the challenge and the
+    /// responses of the prover.
+    pub fn expected_message2(
+        &self,
+        statement: &Statement<G>,
+        message1: &Message1<G>,
+        c: &Integer,
+        message3: &Message3,
+    ) -> Result<Message2<G>, VerificationError> {
+        Ok(self.expected_message2_internal(
+            &statement.c_e,
+            &statement.acc,
+            None,
+            message1,
+            c,
+            message3,
+            &mut ExpScratch::new(),
+        ))
+    }
+
+    /// `c_a` is the commitment to the randomness of a hidden accumulator,
+    /// which is absent from the fourth equation for a public one. The
+    /// commitments are expanded into their exponentiations, so that none of
+    /// the bases is cloned into an `IntegerCommitment`.
+    #[allow(clippy::too_many_arguments)]
+    fn expected_message2_internal(
+        &self,
+        c_e: &G::Elem,
+        acc: &G::Elem,
+        c_a: Option<&G::Elem>,
+        message1: &Message1<G>,
+        c: &Integer,
+        message3: &Message3,
+        scratch: &mut ExpScratch,
+    ) -> Message2<G> {
+        let g = &self.crs.integer_commitment_parameters.g;
+        let h = &self.crs.integer_commitment_parameters.h;
         let expected_alpha1 = G::op(
-            &G::exp(&statement.c_e, &c),
-            &self
-                .crs
-                .integer_commitment_parameters
-                .commit(&message3.s_e, &message3.s_r)?,
+            &G::op(
+                &exp_signed_with::<G>(scratch, c_e, c),
+                &exp_signed_with::<G>(scratch, g, &message3.s_e),
+            ),
+            &exp_signed_with::<G>(scratch, h, &message3.s_r),
         );
         let expected_alpha2 = G::op(
-            &G::exp(&message1.c_r, &c),
-            &self
-                .crs
-                .integer_commitment_parameters
-                .commit(&message3.s_r_2, &message3.s_r_3)?,
-        );
-        let integer_commitment_alpha3 = IntegerCommitment::<G>::new(
-            &message1.c_w,
-            &G::inv(&self.crs.integer_commitment_parameters.h),
+            &G::op(
+                &exp_signed_with::<G>(scratch, &message1.c_r, c),
+                &exp_signed_with::<G>(scratch, g, &message3.s_r_2),
+            ),
+            &exp_signed_with::<G>(scratch, h, &message3.s_r_3),
         );
         let expected_alpha3 = G::op(
-            &G::exp(&statement.acc, &c),
-            &integer_commitment_alpha3.commit(&message3.s_e, &message3.s_beta)?,
-        );
-        let integer_commitment_alpha4 = IntegerCommitment::<G>::new(
-            &G::inv(&self.crs.integer_commitment_parameters.h),
-            &G::inv(&self.crs.integer_commitment_parameters.g),
+            &G::op(
+                &exp_signed_with::<G>(scratch, acc, c),
+                &exp_signed_with::<G>(scratch, &message1.c_w, &message3.s_e),
+            ),
+            &exp_signed_with::<G>(scratch, &self.precomputed.h_inv, &message3.s_beta),
         );
         let expected_alpha4 = G::op(
-            &G::exp(&message1.c_r, &message3.s_e),
-            &integer_commitment_alpha4.commit(&message3.s_delta, &message3.s_beta)?,
+            &G::op(
+                &exp_signed_with::<G>(scratch, &message1.c_r, &message3.s_e),
+                &exp_signed_with::<G>(scratch, &self.precomputed.h_inv, &message3.s_delta),
+            ),
+            &exp_signed_with::<G>(scratch, &self.precomputed.g_inv, &message3.s_beta),
         );
+        let expected_alpha4 = match c_a {
+            Some(c_a) => G::op(&exp_signed_with::<G>(scratch, c_a, c), &expected_alpha4),
+            None => expected_alpha4,
+        };
 
-        let s_e_expected_right = Integer::from(Integer::u_pow_u(
-            2,
-            (self.crs.parameters.security_zk
-                + self.crs.parameters.security_soundness
-                + self.crs.parameters.hash_to_prime_bits
-                + 1) as u32,
-        ));
-
-        let s_e_expected_left: Integer = -s_e_expected_right.clone();
-        let is_s_e_in_range =
-            message3.s_e >= s_e_expected_left && message3.s_e <= s_e_expected_right;
-
-        if expected_alpha1 == message2.alpha1
-            && expected_alpha2 == message2.alpha2
-            && expected_alpha3 == message2.alpha3
-            && expected_alpha4 == message2.alpha4
-            && is_s_e_in_range
-        {
-            Ok(())
-        } else {
-            Err(VerificationError::VerificationFailed)
+        Message2 {
+            alpha1: expected_alpha1,
+            alpha2: expected_alpha2,
+            alpha3: expected_alpha3,
+            alpha4: expected_alpha4,
         }
     }
+
+    fn is_s_e_in_range(&self, s_e: &Integer) -> bool {
+        s_e.cmp_abs(&self.precomputed.s_e_bound) != Ordering::Greater
+    }
+
+    /// Restores the alphas of a compressed proof, so it can be verified as a
+    /// regular proof. Since the verifier rederives the challenge from the
+    /// restored alphas, a wrong challenge makes verification fail.
+    #[cfg(feature = "compressed")]
+    pub fn decompress(
+        &self,
+        statement: &Statement<G>,
+        proof: &CompressedProof<G>,
+    ) -> Result<Proof<G>, VerificationError> {
+        let message2 = self.expected_message2(
+            statement,
+            &proof.message1,
+            &proof.challenge,
+            &proof.message3,
+        )?;
+        Ok(Proof {
+            message1: proof.message1.clone(),
+            message2,
+            message3: proof.message3.clone(),
+        })
+    }
+
+    /// Verifies a compressed proof, checking the challenge it contains is the
+    /// one derived from the transcript.
+    #[cfg(feature = "compressed")]
+    pub fn verify_compressed<T: TranscriptProtocolRoot<G>>(
+        &self,
+        transcript: &RefCell<T>,
+        statement: &Statement<G>,
+        proof: &CompressedProof<G>,
+    ) -> Result<(), VerificationError> {
+        let decompressed = self.decompress(statement, proof)?;
+        let c = decompressed.recompute_challenge(&self.crs, transcript)?;
+        if c != proof.challenge {
+            return Err(VerificationError::ChallengeMismatch);
+        }
+        if !self.is_s_e_in_range(&proof.message3.s_e) {
+            return Err(VerificationError::VerificationFailed);
+        }
+
+        Ok(())
+    }
 }
 
 #[cfg(all(test, feature = "arkworks"))]
 mod test {
-    use super::{Protocol, Statement, Witness};
+    use super::{HiddenAccumulator, HiddenStatement, Protocol, Statement, Witness};
     use crate::{
         commitments::Commitment,
         parameters::Parameters,
         protocols::{
+            blinding::BlindingHistory,
             hash_to_prime::snark_range::Protocol as HPProtocol,
             root::transcript::{TranscriptProverChannel, TranscriptVerifierChannel},
+            ProofError,
         },
+        utils::ExpScratch,
     };
     use accumulator::{
         group::{Group, Rsa2048},
@@ -269,7 +691,7 @@ mod test {
         .unwrap()
         .crs
         .crs_root;
-        let protocol = Protocol::<Rsa2048>::from_crs(&crs);
+        let protocol = Protocol::<Rsa2048>::from_crs(&crs).unwrap();
 
         let value = Integer::from(LARGE_PRIMES[0]);
         let randomness = Integer::from(5);
@@ -318,5 +740,262 @@ mod test {
         let mut prover_channel =
             TranscriptProverChannel::new(&crs, &verification_transcript, &proof);
         protocol.verify(&mut prover_channel, &statement).unwrap();
+
+        // a scratch left over from a previous verification gives the same result
+        let mut scratch = ExpScratch::new();
+        for _ in 0..2 {
+            let verification_transcript = RefCell::new(Transcript::new(b"root"));
+            let mut prover_channel =
+                TranscriptProverChannel::new(&crs, &verification_transcript, &proof);
+            protocol
+                .verify_with_scratch(&mut prover_channel, &statement, &mut scratch)
+                .unwrap();
+        }
+    }
+
+    #[test]
+    fn test_hidden_accumulator() {
+        let params = Parameters::from_security_level(128).unwrap();
+        let mut rng1 = RandState::new();
+        rng1.seed(&Integer::from(13));
+        let mut rng2 = thread_rng();
+
+        let crs = crate::protocols::membership::Protocol::<
+            Rsa2048,
+            G1Projective,
+            HPProtocol<Bls12_381>,
+        >::setup(&params, &mut rng1, &mut rng2)
+        .unwrap()
+        .crs
+        .crs_root;
+        let protocol = Protocol::<Rsa2048>::from_crs(&crs).unwrap();
+
+        let value = Integer::from(LARGE_PRIMES[0]);
+        let randomness = Integer::from(5);
+        let commitment = protocol
+            .crs
+            .integer_commitment_parameters
+            .commit(&value, &randomness)
+            .unwrap();
+        let accum =
+            accumulator::Accumulator::<Rsa2048, Integer, AccumulatorWithoutHashToPrime>::empty();
+        let accum = accum.add_with_proof(&[value.clone()]);
+        let (hidden, opening) = HiddenAccumulator::hide(&crs, &accum.0.value, &mut rng1).unwrap();
+        hidden.open(&crs, &accum.0.value, &opening).unwrap();
+        assert!(hidden.c_acc != accum.0.value);
+
+        let statement = HiddenStatement {
+            c_e: commitment,
+            acc: hidden,
+        };
+        let proof_transcript = RefCell::new(Transcript::new(b"root"));
+        let mut verifier_channel = TranscriptVerifierChannel::new(&crs, &proof_transcript);
+        protocol
+            .prove_hidden_with_history(
+                &mut verifier_channel,
+                &mut rng1,
+                &statement,
+                &Witness {
+                    e: value,
+                    r: randomness,
+                    w: accum.1.witness.0.value,
+                },
+                &opening,
+                &mut BlindingHistory::new(0),
+            )
+            .unwrap();
+        let proof = verifier_channel.proof().unwrap();
+
+        let verification_transcript = RefCell::new(Transcript::new(b"root"));
+        let mut prover_channel =
+            TranscriptProverChannel::new(&crs, &verification_transcript, &proof);
+        protocol
+            .verify_hidden(&mut prover_channel, &statement)
+            .unwrap();
+
+        // the proof doesn't hold for the accumulator it hides
+        let mut wrong_statement = statement;
+        wrong_statement.acc.c_acc = accum.0.value;
+        let verification_transcript = RefCell::new(Transcript::new(b"root"));
+        let mut prover_channel =
+            TranscriptProverChannel::new(&crs, &verification_transcript, &proof);
+        assert!(protocol
+            .verify_hidden(&mut prover_channel, &wrong_statement)
+            .is_err());
+    }
+
+    #[test]
+    fn test_blinding_reuse() {
+        let params = Parameters::from_security_level(128).unwrap();
+        let mut rng1 = RandState::new();
+        rng1.seed(&Integer::from(13));
+        let mut rng2 = thread_rng();
+
+        let crs = crate::protocols::membership::Protocol::<
+            Rsa2048,
+            G1Projective,
+            HPProtocol<Bls12_381>,
+        >::setup(&params, &mut rng1, &mut rng2)
+        .unwrap()
+        .crs
+        .crs_root;
+        let protocol = Protocol::<Rsa2048>::from_crs(&crs).unwrap();
+
+        let value = Integer::from(LARGE_PRIMES[0]);
+        let randomness = Integer::from(5);
+        let commitment = protocol
+            .crs
+            .integer_commitment_parameters
+            .commit(&value, &randomness)
+            .unwrap();
+        let accum =
+            accumulator::Accumulator::<Rsa2048, Integer, AccumulatorWithoutHashToPrime>::empty();
+        let accum = accum.add_with_proof(&[value.clone()]);
+        let statement = Statement {
+            c_e: commitment,
+            acc: accum.0.value,
+        };
+        let witness = Witness {
+            e: value,
+            r: randomness,
+            w: accum.1.witness.0.value,
+        };
+
+        let mut history = BlindingHistory::default();
+        for i in 0..2 {
+            // a broken RNG which repeats its output
+            let mut broken_rng = RandState::new();
+            broken_rng.seed(&Integer::from(17));
+            let proof_transcript = RefCell::new(Transcript::new(b"root"));
+            let mut verifier_channel = TranscriptVerifierChannel::new(&crs, &proof_transcript);
+            let result = protocol.prove_with_history(
+                &mut verifier_channel,
+                &mut broken_rng,
+                &statement,
+                &witness,
+                &mut history,
+            );
+            if i == 0 {
+                result.unwrap();
+            } else {
+                assert!(matches!(result, Err(ProofError::BlindingReuse)));
+            }
+        }
+        assert_eq!(history.len(), 1);
+    }
+
+    #[cfg(feature = "compressed")]
+    #[test]
+    fn test_compressed_proof() {
+        let params = Parameters::from_security_level(128).unwrap();
+        let mut rng1 = RandState::new();
+        rng1.seed(&Integer::from(13));
+        let mut rng2 = thread_rng();
+
+        let crs = crate::protocols::membership::Protocol::<
+            Rsa2048,
+            G1Projective,
+            HPProtocol<Bls12_381>,
+        >::setup(&params, &mut rng1, &mut rng2)
+        .unwrap()
+        .crs
+        .crs_root;
+        let protocol = Protocol::<Rsa2048>::from_crs(&crs).unwrap();
+
+        let value = Integer::from(LARGE_PRIMES[0]);
+        let randomness = Integer::from(5);
+        let commitment = protocol
+            .crs
+            .integer_commitment_parameters
+            .commit(&value, &randomness)
+            .unwrap();
+
+        let accum =
+            accumulator::Accumulator::<Rsa2048, Integer, AccumulatorWithoutHashToPrime>::empty();
+        let accum = accum.add(
+            &LARGE_PRIMES
+                .iter()
+                .skip(1)
+                .map(|p| Integer::from(*p))
+                .collect::<Vec<_>>(),
+        );
+
+        let accum = accum.add_with_proof(&[value.clone()]);
+        let acc = accum.0.value;
+        let w = accum.1.witness.0.value;
+        assert_eq!(Rsa2048::exp(&w, &value), acc);
+
+        let proof_transcript = RefCell::new(Transcript::new(b"root"));
+        let mut verifier_channel = TranscriptVerifierChannel::new(&crs, &proof_transcript);
+        let statement = Statement {
+            c_e: commitment,
+            acc,
+        };
+        protocol
+            .prove(
+                &mut verifier_channel,
+                &mut rng1,
+                &statement,
+                &Witness {
+                    e: value,
+                    r: randomness,
+                    w,
+                },
+            )
+            .unwrap();
+
+        let compressed_proof = verifier_channel.compressed_proof().unwrap();
+        let proof = protocol.decompress(&statement, &compressed_proof).unwrap();
+        let verification_transcript = RefCell::new(Transcript::new(b"root"));
+        let mut prover_channel =
+            TranscriptProverChannel::new(&crs, &verification_transcript, &proof);
+        protocol.verify(&mut prover_channel, &statement).unwrap();
+
+        let verification_transcript = RefCell::new(Transcript::new(b"root"));
+        protocol
+            .verify_compressed(&verification_transcript, &statement, &compressed_proof)
+            .unwrap();
+
+        let mut wrong_compressed_proof = compressed_proof;
+        wrong_compressed_proof.challenge += 1;
+        let verification_transcript = RefCell::new(Transcript::new(b"root"));
+        match protocol.verify_compressed(
+            &verification_transcript,
+            &statement,
+            &wrong_compressed_proof,
+        ) {
+            Err(crate::protocols::VerificationError::ChallengeMismatch) => {}
+            _ => panic!("expected a challenge mismatch"),
+        }
+        let wrong_proof = protocol
+            .decompress(&statement, &wrong_compressed_proof)
+            .unwrap();
+        let verification_transcript = RefCell::new(Transcript::new(b"root"));
+        let mut prover_channel =
+            TranscriptProverChannel::new(&crs, &verification_transcript, &wrong_proof);
+        protocol
+            .verify(&mut prover_channel, &statement)
+            .unwrap_err();
+    }
+
+    #[test]
+    fn test_invalid_soundness() {
+        let params = Parameters::from_security_level(128).unwrap();
+        let mut rng1 = RandState::new();
+        rng1.seed(&Integer::from(13));
+        let mut rng2 = thread_rng();
+
+        let mut crs = crate::protocols::membership::Protocol::<
+            Rsa2048,
+            G1Projective,
+            HPProtocol<Bls12_381>,
+        >::setup(&params, &mut rng1, &mut rng2)
+        .unwrap()
+        .crs
+        .crs_root;
+        crs.parameters.security_soundness = 1024;
+        assert!(Protocol::<Rsa2048>::from_crs(&crs).is_err());
+        crs.parameters.security_soundness = params.security_soundness;
+        assert!(Protocol::<Rsa2048>::from_crs(&crs).is_ok());
     }
 }
@@ -0,0 +1,257 @@
+//! Estimates of the security level a CRS actually achieves.
+//!
+//! `Parameters::from_security_level(128)` only sets the statistical
+//! parameters of the sigma protocols. The security of a proof is also bounded
+//! by the hardness of the group of unknown order and of the discrete logarithm
+//! on the curve, so `SecurityEstimate` reports the minimum of all of them,
+//! along with the breakdown. Group levels follow the NIST SP 800-57 estimates
+//! for RSA moduli and the estimates used for class groups of imaginary
+//! quadratic fields. Curve levels are half the size of the scalar field,
+//! which overestimates pairing-friendly curves attacked through the pairing,
+//! e.g. BLS12-381 is estimated at around 120 bits.
+use crate::{
+    protocols::{
+        coprime::CRSCoprime,
+        hash_to_prime::{CRSHashToPrime, HashToPrimeProtocol},
+        membership::CRS as MembershipCRS,
+        mixed::CRS as MixedCRS,
+        modeq::CRSModEq,
+        nonmembership::CRS as NonMembershipCRS,
+        pokcp::CRSPoKCP,
+        poke::CRSPoKE,
+        root::CRSRoot,
+    },
+    utils::{
+        curve::{CurvePointProjective, Field},
+        ConvertibleUnknownOrderGroup,
+    },
+};
+
+/// Security levels in bits, for the components a protocol relies on.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SecurityBreakdown {
+    /// Hardness of the group of unknown order, if used.
+    pub group: Option<u16>,
+    /// Hardness of the discrete logarithm on the curve, if used.
+    pub curve: Option<u16>,
+    pub soundness: u16,
+    pub zk: u16,
+}
+
+impl SecurityBreakdown {
+    /// The security level achieved, the minimum of the breakdown.
+    pub fn level(&self) -> u16 {
+        [self.group, self.curve, Some(self.soundness), Some(self.zk)]
+            .iter()
+            .filter_map(|l| *l)
+            .min()
+            .unwrap_or(0)
+    }
+
+    /// The breakdown of a protocol composing `self` and `other`.
+    pub fn combine(&self, other: &SecurityBreakdown) -> SecurityBreakdown {
+        let min = |a: Option<u16>, b: Option<u16>| match (a, b) {
+            (Some(a), Some(b)) => Some(a.min(b)),
+            (a, None) => a,
+            (None, b) => b,
+        };
+        SecurityBreakdown {
+            group: min(self.group, other.group),
+            curve: min(self.curve, other.curve),
+            soundness: self.soundness.min(other.soundness),
+            zk: self.zk.min(other.zk),
+        }
+    }
+}
+
+pub trait SecurityEstimate {
+    fn security_estimate(&self) -> SecurityBreakdown;
+}
+
+/// Security level of an RSA modulus with `bits` bits.
+pub fn rsa_security_level(bits: u32) -> u16 {
+    match bits {
+        b if b >= 15360 => 256,
+        b if b >= 7680 => 192,
+        b if b >= 3072 => 128,
+        b if b >= 2048 => 112,
+        b if b >= 1024 => 80,
+        _ => 0,
+    }
+}
+
+/// Security level of a class group with a discriminant of `bits` bits.
+pub fn class_group_security_level(bits: u32) -> u16 {
+    match bits {
+        b if b >= 5971 => 256,
+        b if b >= 3598 => 192,
+        b if b >= 1827 => 128,
+        b if b >= 1348 => 112,
+        b if b >= 642 => 80,
+        _ => 0,
+    }
+}
+
+/// Security level of `G`, an RSA group or a class group, whose order bound
+/// is about the square root of the discriminant.
+pub fn group_security_level<G: ConvertibleUnknownOrderGroup>() -> u16 {
+    match G::rsa_modulus() {
+        Ok(modulus) => rsa_security_level(modulus.significant_bits()),
+        Err(_) => class_group_security_level(2 * G::order_upper_bound().significant_bits()),
+    }
+}
+
+/// Security level of the discrete logarithm on `P`.
+pub fn curve_security_level<P: CurvePointProjective>() -> u16 {
+    (P::ScalarField::size_in_bits() / 2) as u16
+}
+
+impl<G: ConvertibleUnknownOrderGroup> SecurityEstimate for CRSRoot<G> {
+    fn security_estimate(&self) -> SecurityBreakdown {
+        SecurityBreakdown {
+            group: Some(group_security_level::<G>()),
+            curve: None,
+            soundness: self.parameters.security_soundness,
+            zk: self.parameters.security_zk,
+        }
+    }
+}
+
+impl<G: ConvertibleUnknownOrderGroup> SecurityEstimate for CRSCoprime<G> {
+    fn security_estimate(&self) -> SecurityBreakdown {
+        SecurityBreakdown {
+            group: Some(group_security_level::<G>()),
+            curve: None,
+            soundness: self.parameters.security_soundness,
+            zk: self.parameters.security_zk,
+        }
+    }
+}
+
+impl<G: ConvertibleUnknownOrderGroup> SecurityEstimate for CRSPoKE<G> {
+    fn security_estimate(&self) -> SecurityBreakdown {
+        SecurityBreakdown {
+            group: Some(group_security_level::<G>()),
+            curve: None,
+            soundness: self.parameters.security_soundness,
+            zk: self.parameters.security_zk,
+        }
+    }
+}
+
+impl<G: ConvertibleUnknownOrderGroup> SecurityEstimate for CRSPoKCP<G> {
+    fn security_estimate(&self) -> SecurityBreakdown {
+        SecurityBreakdown {
+            group: Some(group_security_level::<G>()),
+            curve: None,
+            soundness: self.parameters.security_soundness,
+            zk: self.parameters.security_zk,
+        }
+    }
+}
+
+impl<G: ConvertibleUnknownOrderGroup, P: CurvePointProjective> SecurityEstimate for CRSModEq<G, P> {
+    fn security_estimate(&self) -> SecurityBreakdown {
+        SecurityBreakdown {
+            group: Some(group_security_level::<G>()),
+            curve: Some(curve_security_level::<P>()),
+            soundness: self.parameters.security_soundness,
+            zk: self.parameters.security_zk,
+        }
+    }
+}
+
+/// The range proofs and SNARKs are computationally sound and perfectly
+/// zero-knowledge over Pedersen commitments, so only the curve bounds them.
+impl<P: CurvePointProjective, HP: HashToPrimeProtocol<P>> SecurityEstimate
+    for CRSHashToPrime<P, HP>
+{
+    fn security_estimate(&self) -> SecurityBreakdown {
+        let curve = curve_security_level::<P>();
+        SecurityBreakdown {
+            group: None,
+            curve: Some(curve),
+            soundness: curve,
+            zk: curve,
+        }
+    }
+}
+
+impl<G: ConvertibleUnknownOrderGroup, P: CurvePointProjective, HP: HashToPrimeProtocol<P>>
+    SecurityEstimate for MembershipCRS<G, P, HP>
+{
+    fn security_estimate(&self) -> SecurityBreakdown {
+        self.crs_root
+            .security_estimate()
+            .combine(&self.crs_modeq.security_estimate())
+            .combine(&self.crs_hash_to_prime.security_estimate())
+    }
+}
+
+impl<G: ConvertibleUnknownOrderGroup, P: CurvePointProjective, HP: HashToPrimeProtocol<P>>
+    SecurityEstimate for NonMembershipCRS<G, P, HP>
+{
+    fn security_estimate(&self) -> SecurityBreakdown {
+        self.crs_coprime
+            .security_estimate()
+            .combine(&self.crs_modeq.security_estimate())
+            .combine(&self.crs_hash_to_prime.security_estimate())
+    }
+}
+
+impl<
+        G: ConvertibleUnknownOrderGroup,
+        P: CurvePointProjective,
+        Q: CurvePointProjective,
+        HP: HashToPrimeProtocol<Q>,
+    > SecurityEstimate for MixedCRS<G, P, Q, HP>
+{
+    fn security_estimate(&self) -> SecurityBreakdown {
+        self.crs_root
+            .security_estimate()
+            .combine(&self.crs_coprime.security_estimate())
+            .combine(&self.crs_modeq.security_estimate())
+            .combine(&self.crs_modeq_bridge.security_estimate())
+            .combine(&self.crs_hash_to_prime.security_estimate())
+    }
+}
+
+#[cfg(all(test, feature = "arkworks"))]
+mod test {
+    use super::{SecurityBreakdown, SecurityEstimate};
+    use crate::{
+        parameters::Parameters,
+        protocols::{hash_to_prime::snark_range::Protocol as HPProtocol, membership::Protocol},
+    };
+    use accumulator::group::Rsa2048;
+    use ark_bls12_381::{Bls12_381, G1Projective};
+    use rand::thread_rng;
+    use rug::rand::RandState;
+    use rug::Integer;
+
+    #[test]
+    fn test_security_estimate() {
+        let params = Parameters::from_security_level(128).unwrap();
+        let mut rng1 = RandState::new();
+        rng1.seed(&Integer::from(13));
+        let mut rng2 = thread_rng();
+
+        let crs = Protocol::<Rsa2048, G1Projective, HPProtocol<Bls12_381>>::setup(
+            &params, &mut rng1, &mut rng2,
+        )
+        .unwrap()
+        .crs;
+        let estimate = crs.security_estimate();
+        assert_eq!(
+            estimate,
+            SecurityBreakdown {
+                group: Some(112),
+                curve: Some(127),
+                soundness: params.security_soundness,
+                zk: params.security_zk,
+            }
+        );
+        // RSA-2048 bounds the security, not the parameters
+        assert_eq!(estimate.level(), 112);
+    }
+}
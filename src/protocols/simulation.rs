@@ -0,0 +1,313 @@
+//! Simulators for the root, coprime and modeq protocols, documenting their
+//! honest-verifier zero-knowledge property.
+//!
+//! A simulator samples the challenge and the responses from the distribution
+//! of the blinding values, and derives the alphas from the verification
+//! equations. The resulting transcripts are accepted by the verifier and,
+//! thanks to the `security_zk` padding of the blinding ranges, statistically
+//! close to real ones, which `statistical_distance` allows to test.
+use crate::{
+    commitments::Commitment,
+    parameters::Parameters,
+    protocols::{
+        blinding::BlindingRanges,
+        coprime::{self, Protocol as CoprimeProtocol, Statement as CoprimeStatement},
+        modeq::{self, Protocol as ModEqProtocol, Statement as ModEqStatement},
+        root::{self, Protocol as RootProtocol, Statement as RootStatement},
+        VerificationError,
+    },
+    utils::{
+        curve::{CurvePointProjective, Field},
+        exp_signed, random_symmetric_range, ConvertibleUnknownOrderGroup,
+    },
+};
+use rand::{CryptoRng, RngCore};
+use rug::{rand::MutRandState, Integer};
+
+fn pow2(bits: u16) -> Integer {
+    Integer::from(Integer::u_pow_u(2, bits as u32))
+}
+
+fn random_challenge<R: MutRandState>(parameters: &Parameters, rng: &mut R) -> Integer {
+    pow2(parameters.security_soundness).random_below(rng)
+}
+
+/// Simulates a root transcript, returning the proof and its challenge.
+pub fn simulate_root<G: ConvertibleUnknownOrderGroup, R: MutRandState>(
+    protocol: &RootProtocol<G>,
+    statement: &RootStatement<G>,
+    rng: &mut R,
+) -> Result<(root::Proof<G>, Integer), VerificationError> {
+    let c = random_challenge(&protocol.crs().parameters, rng);
+    let proof = simulate_root_with_challenge(protocol, statement, &c, rng)?;
+    Ok((proof, c))
+}
+
+/// Simulates a root transcript for the challenge `c`, e.g. for the branch of
+/// an OR proof the prover has no witness for.
+pub fn simulate_root_with_challenge<G: ConvertibleUnknownOrderGroup, R: MutRandState>(
+    protocol: &RootProtocol<G>,
+    statement: &RootStatement<G>,
+    c: &Integer,
+    rng: &mut R,
+) -> Result<root::Proof<G>, VerificationError> {
+    let parameters = &protocol.crs().parameters;
+    let commitment = &protocol.crs().integer_commitment_parameters;
+    let ranges = BlindingRanges::new::<G>(parameters);
+    let message1 = root::Message1 {
+        c_w: exp_signed::<G>(
+            &commitment.h,
+            &random_symmetric_range(rng, &ranges.randomness),
+        ),
+        c_r: commitment.commit(
+            &random_symmetric_range(rng, &ranges.randomness),
+            &random_symmetric_range(rng, &ranges.randomness),
+        )?,
+    };
+
+    let message3 = root::Message3 {
+        s_e: random_symmetric_range(rng, &ranges.exponent),
+        s_r: random_symmetric_range(rng, &ranges.randomness_mask),
+        s_r_2: random_symmetric_range(rng, &ranges.randomness_mask),
+        s_r_3: random_symmetric_range(rng, &ranges.randomness_mask),
+        s_beta: random_symmetric_range(rng, &ranges.product_mask),
+        s_delta: random_symmetric_range(rng, &ranges.product_mask),
+    };
+    let message2 = protocol.expected_message2(statement, &message1, c, &message3)?;
+
+    Ok(root::Proof {
+        message1,
+        message2,
+        message3,
+    })
+}
+
+/// Simulates a coprime transcript, returning the proof and its challenge.
+pub fn simulate_coprime<G: ConvertibleUnknownOrderGroup, R: MutRandState>(
+    protocol: &CoprimeProtocol<G>,
+    statement: &CoprimeStatement<G>,
+    rng: &mut R,
+) -> Result<(coprime::Proof<G>, Integer), VerificationError> {
+    let c = random_challenge(&protocol.crs().parameters, rng);
+    let proof = simulate_coprime_with_challenge(protocol, statement, &c, rng)?;
+    Ok((proof, c))
+}
+
+/// Simulates a coprime transcript for the challenge `c`.
+pub fn simulate_coprime_with_challenge<G: ConvertibleUnknownOrderGroup, R: MutRandState>(
+    protocol: &CoprimeProtocol<G>,
+    statement: &CoprimeStatement<G>,
+    c: &Integer,
+    rng: &mut R,
+) -> Result<coprime::Proof<G>, VerificationError> {
+    let parameters = &protocol.crs().parameters;
+    let commitment = &protocol.crs().integer_commitment_parameters;
+    let ranges = BlindingRanges::new::<G>(parameters);
+    let message1 = coprime::Message1 {
+        c_a: exp_signed::<G>(
+            &commitment.h,
+            &random_symmetric_range(rng, &ranges.randomness),
+        ),
+        c_r_a: commitment.commit(
+            &random_symmetric_range(rng, &ranges.randomness),
+            &random_symmetric_range(rng, &ranges.randomness),
+        )?,
+        c_b_cap: exp_signed::<G>(
+            &commitment.h,
+            &random_symmetric_range(rng, &ranges.randomness),
+        ),
+        c_rho_b_cap: commitment.commit(
+            &random_symmetric_range(rng, &ranges.randomness),
+            &random_symmetric_range(rng, &ranges.randomness),
+        )?,
+    };
+
+    let message3 = coprime::Message3 {
+        s_b: random_symmetric_range(rng, &ranges.exponent),
+        s_e: random_symmetric_range(rng, &ranges.exponent),
+        s_rho_b_cap: random_symmetric_range(rng, &ranges.randomness_mask),
+        s_r: random_symmetric_range(rng, &ranges.randomness_mask),
+        s_r_a: random_symmetric_range(rng, &ranges.randomness_mask),
+        s_r_a_prime: random_symmetric_range(rng, &ranges.randomness_mask),
+        s_rho_b_cap_prime: random_symmetric_range(rng, &ranges.randomness_mask),
+        s_beta: random_symmetric_range(rng, &ranges.product_mask),
+        s_delta: random_symmetric_range(rng, &ranges.product_mask),
+    };
+    let message2 = protocol.expected_message2(statement, &message1, c, &message3)?;
+
+    Ok(coprime::Proof {
+        message1,
+        message2,
+        message3,
+    })
+}
+
+/// Simulates a modeq transcript, returning the proof and its challenge.
+pub fn simulate_modeq<
+    G: ConvertibleUnknownOrderGroup,
+    P: CurvePointProjective,
+    R1: MutRandState,
+    R2: RngCore + CryptoRng,
+>(
+    protocol: &ModEqProtocol<G, P>,
+    statement: &ModEqStatement<G, P>,
+    rng1: &mut R1,
+    rng2: &mut R2,
+) -> Result<(modeq::Proof<G, P>, Integer), VerificationError> {
+    let parameters = &protocol.crs.parameters;
+    let ranges = BlindingRanges::new::<G>(parameters);
+    let c = random_challenge(parameters, rng1);
+    let message2 = modeq::Message2::<P> {
+        s_e: random_symmetric_range(rng1, &ranges.exponent),
+        s_r: random_symmetric_range(rng1, &ranges.randomness_mask),
+        s_r_q: P::ScalarField::rand(rng2),
+    };
+    let message1 = protocol.expected_message1(statement, &c, &message2)?;
+
+    Ok((modeq::Proof { message1, message2 }, c))
+}
+
+/// Estimates the statistical distance between two samples of values in
+/// `[-range, range]`, by comparing their histograms over `buckets` buckets.
+pub fn statistical_distance(
+    samples_1: &[Integer],
+    samples_2: &[Integer],
+    range: &Integer,
+    buckets: usize,
+) -> f64 {
+    let histogram = |samples: &[Integer]| {
+        let mut counts = vec![0usize; buckets];
+        let width = Integer::from(2 * range) / buckets as u64 + 1;
+        for s in samples {
+            let bucket = Integer::from(s + range) / &width;
+            let bucket = bucket.to_usize().unwrap_or(buckets - 1).min(buckets - 1);
+            counts[bucket] += 1;
+        }
+        counts
+            .into_iter()
+            .map(|count| count as f64 / samples.len() as f64)
+            .collect::<Vec<_>>()
+    };
+
+    histogram(samples_1)
+        .into_iter()
+        .zip(histogram(samples_2))
+        .map(|(p, q)| (p - q).abs())
+        .sum::<f64>()
+        / 2.0
+}
+
+#[cfg(all(test, feature = "arkworks"))]
+mod test {
+    use super::{simulate_modeq, simulate_root, statistical_distance};
+    use crate::{
+        commitments::Commitment,
+        parameters::Parameters,
+        protocols::{
+            blinding::BlindingRanges,
+            extraction::RootRewindingChannel,
+            hash_to_prime::snark_range::Protocol as HPProtocol,
+            membership::Protocol as MembershipProtocol,
+            modeq::{Protocol as ModEqProtocol, Statement as ModEqStatement},
+            root::{Protocol as RootProtocol, Statement, Witness},
+        },
+    };
+    use accumulator::{group::Rsa2048, AccumulatorWithoutHashToPrime};
+    use ark_bls12_381::{Bls12_381, G1Projective};
+    use rand::thread_rng;
+    use rug::rand::RandState;
+    use rug::Integer;
+
+    const SAMPLES: usize = 64;
+
+    #[test]
+    fn test_root_simulation() {
+        let params = Parameters::from_security_level(128).unwrap();
+        let mut rng1 = RandState::new();
+        rng1.seed(&Integer::from(13));
+        let mut rng2 = thread_rng();
+        let crs = MembershipProtocol::<Rsa2048, G1Projective, HPProtocol<Bls12_381>>::setup(
+            &params, &mut rng1, &mut rng2,
+        )
+        .unwrap()
+        .crs;
+        let protocol = RootProtocol::from_crs(&crs.crs_root).unwrap();
+
+        let value = Integer::from(553_525_575_239_331_913u64);
+        let randomness = Integer::from(5);
+        let accum =
+            accumulator::Accumulator::<Rsa2048, Integer, AccumulatorWithoutHashToPrime>::empty()
+                .add_with_proof(&[value.clone()]);
+        let statement = Statement {
+            c_e: crs
+                .crs_root
+                .integer_commitment_parameters
+                .commit(&value, &randomness)
+                .unwrap(),
+            acc: accum.0.value,
+        };
+        let witness = Witness {
+            e: value,
+            r: randomness,
+            w: accum.1.witness.0.value,
+        };
+
+        let mut real = vec![];
+        let mut simulated = vec![];
+        for _ in 0..SAMPLES {
+            let c = Integer::from(Integer::u_pow_u(2, params.security_soundness as u32))
+                .random_below(&mut rng1);
+            let mut channel = RootRewindingChannel::<Rsa2048>::new(&c);
+            protocol
+                .prove(&mut channel, &mut rng1, &statement, &witness)
+                .unwrap();
+            real.push(channel.proof().unwrap().message3.s_e);
+
+            let (proof, c) = simulate_root(&protocol, &statement, &mut rng1).unwrap();
+            let expected_message2 = protocol
+                .expected_message2(&statement, &proof.message1, &c, &proof.message3)
+                .unwrap();
+            assert!(expected_message2.alpha1 == proof.message2.alpha1);
+            simulated.push(proof.message3.s_e);
+        }
+
+        let distance = statistical_distance(
+            &real,
+            &simulated,
+            &BlindingRanges::new::<Rsa2048>(&params).exponent,
+            4,
+        );
+        assert!(distance < 0.3, "distance {}", distance);
+    }
+
+    #[test]
+    fn test_modeq_simulation() {
+        let params = Parameters::from_security_level(128).unwrap();
+        let mut rng1 = RandState::new();
+        rng1.seed(&Integer::from(13));
+        let mut rng2 = thread_rng();
+        let crs = MembershipProtocol::<Rsa2048, G1Projective, HPProtocol<Bls12_381>>::setup(
+            &params, &mut rng1, &mut rng2,
+        )
+        .unwrap()
+        .crs
+        .crs_modeq;
+        let protocol = ModEqProtocol::<Rsa2048, G1Projective>::from_crs(&crs);
+        let statement = ModEqStatement {
+            c_e: crs
+                .integer_commitment_parameters
+                .commit(&Integer::from(2), &Integer::from(5))
+                .unwrap(),
+            c_e_q: crs
+                .pedersen_commitment_parameters
+                .commit(&Integer::from(2), &Integer::from(9))
+                .unwrap(),
+        };
+
+        let (proof, c) = simulate_modeq(&protocol, &statement, &mut rng1, &mut rng2).unwrap();
+        let expected_message1 = protocol
+            .expected_message1(&statement, &c, &proof.message2)
+            .unwrap();
+        assert!(expected_message1.alpha2 == proof.message1.alpha2);
+    }
+}
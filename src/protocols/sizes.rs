@@ -0,0 +1,163 @@
+//! Closed-form estimates of the sizes of proofs and CRSs, to budget storage
+//! and bandwidth before running the setup, which is expensive for the SNARK
+//! backends.
+//!
+//! Group elements and integers are counted with the encodings of the proofs'
+//! `serialized_size`, at their largest: an RSA group element takes as many
+//! bytes as the modulus, and a response as many as the bound the verifier
+//! checks. The hash-to-prime parts are those of `HP::estimate_sizes`, which
+//! for the SNARK backends fails with `SetupError::ProverNotEnabled` without
+//! the `prover` feature, as the constraints are counted by synthesizing the
+//! circuit.
+use crate::{
+    parameters::{Parameters, SubProtocol},
+    protocols::{blinding::BlindingRanges, hash_to_prime::HashToPrimeProtocol, SetupError},
+    utils::{curve::CurvePointProjective, scalar_byte_length, ConvertibleUnknownOrderGroup},
+};
+use rug::Integer;
+
+/// Length of the CRS digest the proofs carry.
+const CRS_DIGEST_BYTES: usize = 32;
+
+/// Sizes in bytes of a proof and of the CRS. The prover's CRS includes
+/// everything the verifier's does.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct SizeEstimate {
+    pub proof_bytes: usize,
+    pub crs_prover_bytes: usize,
+    pub crs_verifier_bytes: usize,
+}
+
+/// Largest length of `G::elem_to_bytes`: the length of the modulus for RSA
+/// groups, and of the encoding of the generator for class groups, whose
+/// elements are reduced forms of the same size.
+pub fn group_element_bytes<G: ConvertibleUnknownOrderGroup>() -> usize {
+    match G::rsa_modulus() {
+        Ok(modulus) => (modulus.significant_bits() as usize + 7) / 8,
+        Err(_) => G::elem_to_bytes(&G::unknown_order_elem()).len(),
+    }
+}
+
+/// Largest length of `integer_to_signed_bytes` of a response masked by a
+/// blinding value in `[-range, range]`, which stays within `2 * range`.
+pub fn response_bytes(range: &Integer) -> usize {
+    let bound = Integer::from(range * 2);
+    1 + (bound.significant_bits() as usize + 7) / 8
+}
+
+/// The sizes of `crs_root` and `crs_modeq` of the membership CRS, and of
+/// `crs_coprime` and `crs_modeq` of the nonmembership one: the integer
+/// commitment and the Pedersen commitment.
+fn commitments_bytes<G: ConvertibleUnknownOrderGroup, P: CurvePointProjective>() -> usize {
+    2 * group_element_bytes::<G>() + 2 * P::affine_bytes_len()
+}
+
+fn modeq_proof_bytes<G: ConvertibleUnknownOrderGroup, P: CurvePointProjective>(
+    parameters: &Parameters,
+) -> usize {
+    let ranges = BlindingRanges::new::<G>(&parameters.for_sub_protocol(SubProtocol::ModEq));
+    group_element_bytes::<G>()
+        + P::affine_bytes_len()
+        + response_bytes(&ranges.exponent)
+        + response_bytes(&ranges.randomness_mask)
+        + scalar_byte_length::<P>()
+}
+
+fn sizes<G: ConvertibleUnknownOrderGroup, P: CurvePointProjective, HP: HashToPrimeProtocol<P>>(
+    parameters: &Parameters,
+    sub_proof_bytes: usize,
+) -> Result<SizeEstimate, SetupError> {
+    let hash_to_prime = HP::estimate_sizes(&parameters.for_sub_protocol(SubProtocol::HashToPrime))?;
+    let crs_verifier_bytes = commitments_bytes::<G, P>() + hash_to_prime.verifier_parameters_bytes;
+    Ok(SizeEstimate {
+        proof_bytes: CRS_DIGEST_BYTES
+            + group_element_bytes::<G>()
+            + sub_proof_bytes
+            + modeq_proof_bytes::<G, P>(parameters)
+            + hash_to_prime.proof_bytes,
+        crs_prover_bytes: crs_verifier_bytes + hash_to_prime.prover_parameters_bytes,
+        crs_verifier_bytes,
+    })
+}
+
+/// Estimates the sizes of a membership proof and of its CRS for
+/// `parameters`, with the hash-to-prime backend `HP`, without the setup.
+pub fn estimate_sizes<
+    G: ConvertibleUnknownOrderGroup,
+    P: CurvePointProjective,
+    HP: HashToPrimeProtocol<P>,
+>(
+    parameters: &Parameters,
+) -> Result<SizeEstimate, SetupError> {
+    let ranges = BlindingRanges::new::<G>(&parameters.for_sub_protocol(SubProtocol::Root));
+    // c_w, c_r and the four alphas, s_e, s_r, s_r_2, s_r_3, s_beta and s_delta
+    let root_bytes = 6 * group_element_bytes::<G>()
+        + response_bytes(&ranges.exponent)
+        + 3 * response_bytes(&ranges.randomness_mask)
+        + 2 * response_bytes(&ranges.product_mask);
+    sizes::<G, P, HP>(parameters, root_bytes)
+}
+
+/// Like `estimate_sizes`, for a nonmembership proof.
+pub fn estimate_nonmembership_sizes<
+    G: ConvertibleUnknownOrderGroup,
+    P: CurvePointProjective,
+    HP: HashToPrimeProtocol<P>,
+>(
+    parameters: &Parameters,
+) -> Result<SizeEstimate, SetupError> {
+    let ranges = BlindingRanges::new::<G>(&parameters.for_sub_protocol(SubProtocol::Coprime));
+    // four commitments and six alphas, s_b and s_e, the five responses for
+    // the randomness, s_beta and s_delta
+    let coprime_bytes = 10 * group_element_bytes::<G>()
+        + 2 * response_bytes(&ranges.exponent)
+        + 5 * response_bytes(&ranges.randomness_mask)
+        + 2 * response_bytes(&ranges.product_mask);
+    sizes::<G, P, HP>(parameters, coprime_bytes)
+}
+
+#[cfg(all(test, feature = "arkworks"))]
+mod test {
+    use super::{
+        estimate_nonmembership_sizes, estimate_sizes, group_element_bytes, response_bytes,
+    };
+    use crate::{
+        parameters::Parameters,
+        protocols::hash_to_prime::{snark_range::Protocol as HPProtocol, HashToPrimeProtocol},
+    };
+    use accumulator::group::Rsa2048;
+    use ark_bls12_381::{Bls12_381, G1Projective};
+    use rug::Integer;
+
+    #[test]
+    fn test_response_bytes() {
+        assert_eq!(group_element_bytes::<Rsa2048>(), 256);
+        // responses to blindings in [-2^127, 2^127] take 129 bits and a sign
+        let range = Integer::from(Integer::u_pow_u(2, 127));
+        assert_eq!(response_bytes(&range), 18);
+    }
+
+    #[test]
+    fn test_estimate_sizes() {
+        let params = Parameters::from_security_level(128).unwrap();
+        let hash_to_prime = HPProtocol::<Bls12_381>::estimate_sizes(&params).unwrap();
+        // five points of G1 and one of G2
+        assert_eq!(hash_to_prime.proof_bytes, 5 * 48 + 96);
+
+        let membership =
+            estimate_sizes::<Rsa2048, G1Projective, HPProtocol<Bls12_381>>(&params).unwrap();
+        let nonmembership =
+            estimate_nonmembership_sizes::<Rsa2048, G1Projective, HPProtocol<Bls12_381>>(&params)
+                .unwrap();
+        assert!(membership.proof_bytes > 7 * 256 + hash_to_prime.proof_bytes);
+        assert!(nonmembership.proof_bytes > membership.proof_bytes);
+        assert_eq!(
+            membership.crs_prover_bytes - membership.crs_verifier_bytes,
+            hash_to_prime.prover_parameters_bytes
+        );
+        assert_eq!(
+            membership.crs_verifier_bytes,
+            2 * 256 + 2 * 48 + hash_to_prime.verifier_parameters_bytes
+        );
+    }
+}
@@ -0,0 +1,244 @@
+//! A uniform verifier interface, for frameworks handling many proof systems,
+//! e.g. rollups or credential gateways.
+//!
+//! `CpSnarkVerifier` is implemented by `TranscriptVerifier`, which verifies
+//! the membership and nonmembership protocols on a fresh transcript, and by
+//! `CrsRegistry`. `DynVerifier` erases the statement and proof types, so
+//! verifiers of different protocols can be stored together as
+//! `Box<dyn DynVerifier>`.
+use crate::{
+    protocols::{
+        hash_to_prime::HashToPrimeProtocol,
+        membership::{
+            transcript::TranscriptProverChannel as MembershipTranscriptProverChannel,
+            Proof as MembershipProof, Protocol as MembershipProtocol,
+            Statement as MembershipStatement,
+        },
+        nonmembership::{
+            transcript::TranscriptProverChannel as NonMembershipTranscriptProverChannel,
+            Proof as NonMembershipProof, Protocol as NonMembershipProtocol,
+            Statement as NonMembershipStatement,
+        },
+        registry::{CrsRegistry, VersionedProof},
+        VerificationError,
+    },
+    transcript::transcript_with_aad,
+    utils::{curve::CurvePointProjective, ConvertibleUnknownOrderGroup},
+};
+use std::{any::Any, cell::RefCell};
+
+pub trait CpSnarkVerifier {
+    type Statement;
+    type Proof;
+
+    /// Verifies `proof`, bound to the associated data `aad` it was created
+    /// with.
+    fn verify(
+        &self,
+        statement: &Self::Statement,
+        proof: &Self::Proof,
+        aad: &[u8],
+    ) -> Result<(), VerificationError>;
+}
+
+/// Verifies proofs of `V` on transcripts created with `transcript_label`,
+/// which has to match the provers'.
+pub struct TranscriptVerifier<V> {
+    pub protocol: V,
+    pub transcript_label: &'static [u8],
+}
+
+impl<V> TranscriptVerifier<V> {
+    pub fn new(protocol: V, transcript_label: &'static [u8]) -> TranscriptVerifier<V> {
+        TranscriptVerifier {
+            protocol,
+            transcript_label,
+        }
+    }
+}
+
+impl<G: ConvertibleUnknownOrderGroup, P: CurvePointProjective, HP: HashToPrimeProtocol<P>>
+    CpSnarkVerifier for TranscriptVerifier<MembershipProtocol<G, P, HP>>
+{
+    type Statement = MembershipStatement<G, P>;
+    type Proof = MembershipProof<G, P, HP>;
+
+    fn verify(
+        &self,
+        statement: &Self::Statement,
+        proof: &Self::Proof,
+        aad: &[u8],
+    ) -> Result<(), VerificationError> {
+        let transcript = RefCell::new(transcript_with_aad(self.transcript_label, aad));
+        let mut prover_channel =
+            MembershipTranscriptProverChannel::new(&self.protocol.crs, &transcript, proof);
+        self.protocol.verify(&mut prover_channel, statement)
+    }
+}
+
+impl<G: ConvertibleUnknownOrderGroup, P: CurvePointProjective, HP: HashToPrimeProtocol<P>>
+    CpSnarkVerifier for TranscriptVerifier<NonMembershipProtocol<G, P, HP>>
+{
+    type Statement = NonMembershipStatement<G, P>;
+    type Proof = NonMembershipProof<G, P, HP>;
+
+    fn verify(
+        &self,
+        statement: &Self::Statement,
+        proof: &Self::Proof,
+        aad: &[u8],
+    ) -> Result<(), VerificationError> {
+        let transcript = RefCell::new(transcript_with_aad(self.transcript_label, aad));
+        let mut prover_channel =
+            NonMembershipTranscriptProverChannel::new(&self.protocol.crs, &transcript, proof);
+        self.protocol.verify(&mut prover_channel, statement)
+    }
+}
+
+impl<G: ConvertibleUnknownOrderGroup, P: CurvePointProjective, HP: HashToPrimeProtocol<P>>
+    CpSnarkVerifier for CrsRegistry<MembershipProtocol<G, P, HP>>
+{
+    type Statement = MembershipStatement<G, P>;
+    type Proof = VersionedProof<MembershipProof<G, P, HP>>;
+
+    fn verify(
+        &self,
+        statement: &Self::Statement,
+        proof: &Self::Proof,
+        aad: &[u8],
+    ) -> Result<(), VerificationError> {
+        self.verify_any(statement, proof, aad)
+    }
+}
+
+impl<G: ConvertibleUnknownOrderGroup, P: CurvePointProjective, HP: HashToPrimeProtocol<P>>
+    CpSnarkVerifier for CrsRegistry<NonMembershipProtocol<G, P, HP>>
+{
+    type Statement = NonMembershipStatement<G, P>;
+    type Proof = VersionedProof<NonMembershipProof<G, P, HP>>;
+
+    fn verify(
+        &self,
+        statement: &Self::Statement,
+        proof: &Self::Proof,
+        aad: &[u8],
+    ) -> Result<(), VerificationError> {
+        self.verify_any(statement, proof, aad)
+    }
+}
+
+/// Object-safe version of `CpSnarkVerifier`, taking type-erased statements
+/// and proofs. Values of the wrong type are rejected with `WrongType`.
+pub trait DynVerifier {
+    fn verify_dyn(
+        &self,
+        statement: &dyn Any,
+        proof: &dyn Any,
+        aad: &[u8],
+    ) -> Result<(), VerificationError>;
+}
+
+impl<V: CpSnarkVerifier> DynVerifier for V
+where
+    V::Statement: 'static,
+    V::Proof: 'static,
+{
+    fn verify_dyn(
+        &self,
+        statement: &dyn Any,
+        proof: &dyn Any,
+        aad: &[u8],
+    ) -> Result<(), VerificationError> {
+        let statement = statement
+            .downcast_ref::<V::Statement>()
+            .ok_or(VerificationError::WrongType)?;
+        let proof = proof
+            .downcast_ref::<V::Proof>()
+            .ok_or(VerificationError::WrongType)?;
+        self.verify(statement, proof, aad)
+    }
+}
+
+#[cfg(all(test, feature = "arkworks"))]
+mod test {
+    use super::{CpSnarkVerifier, DynVerifier, TranscriptVerifier};
+    use crate::{
+        commitments::Commitment,
+        parameters::Parameters,
+        protocols::{
+            hash_to_prime::snark_range::Protocol as HPProtocol,
+            membership::{transcript::TranscriptVerifierChannel, Protocol, Statement, Witness},
+            VerificationError,
+        },
+        transcript::transcript_with_aad,
+    };
+    use accumulator::{group::Rsa2048, AccumulatorWithoutHashToPrime};
+    use ark_bls12_381::{Bls12_381, G1Projective};
+    use rand::thread_rng;
+    use rug::rand::RandState;
+    use rug::Integer;
+    use std::cell::RefCell;
+
+    #[test]
+    fn test_dyn_verifier() {
+        let params = Parameters::from_security_level(128).unwrap();
+        let mut rng1 = RandState::new();
+        rng1.seed(&Integer::from(13));
+        let mut rng2 = thread_rng();
+
+        let crs = Protocol::<Rsa2048, G1Projective, HPProtocol<Bls12_381>>::setup(
+            &params, &mut rng1, &mut rng2,
+        )
+        .unwrap()
+        .crs;
+        let protocol = Protocol::<Rsa2048, G1Projective, HPProtocol<Bls12_381>>::from_crs(&crs);
+
+        let value = Integer::from(Integer::u_pow_u(2, params.hash_to_prime_bits as u32))
+            - &Integer::from(245);
+        let randomness = Integer::from(5);
+        let accum =
+            accumulator::Accumulator::<Rsa2048, Integer, AccumulatorWithoutHashToPrime>::empty()
+                .add_with_proof(&[value.clone()]);
+        let statement = Statement {
+            c_p: accum.0.value,
+            c_e_q: crs
+                .crs_modeq
+                .pedersen_commitment_parameters
+                .commit(&value, &randomness)
+                .unwrap(),
+        };
+        let witness = Witness {
+            e: value,
+            r_q: randomness,
+            w: accum.1.witness.0.value,
+        };
+
+        let proof_transcript = RefCell::new(transcript_with_aad(b"membership", b"session 1"));
+        let mut verifier_channel = TranscriptVerifierChannel::new(&crs, &proof_transcript);
+        protocol
+            .prove(
+                &mut verifier_channel,
+                &mut rng1,
+                &mut rng2,
+                &statement,
+                &witness,
+            )
+            .unwrap();
+        let proof = verifier_channel.proof().unwrap();
+
+        let verifier = TranscriptVerifier::new(protocol, b"membership");
+        CpSnarkVerifier::verify(&verifier, &statement, &proof, b"session 1").unwrap();
+        // the proof is bound to its associated data
+        assert!(CpSnarkVerifier::verify(&verifier, &statement, &proof, b"session 2").is_err());
+        assert!(CpSnarkVerifier::verify(&verifier, &statement, &proof, &[]).is_err());
+
+        let verifiers: Vec<Box<dyn DynVerifier>> = vec![Box::new(verifier)];
+        verifiers[0]
+            .verify_dyn(&statement, &proof, b"session 1")
+            .unwrap();
+        assert!(matches!(
+            verifiers[0].verify_dyn(&statement, &statement, b"session 1"),
+            Err(VerificationError::WrongType)
+        ));
+    }
+}
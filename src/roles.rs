@@ -0,0 +1,911 @@
+//! The three roles of an anonymous credential deployment, as in the
+//! application of the paper: the issuer manages the accumulator and issues
+//! credentials, holders present them, and verifiers check presentations
+//! against the accumulator they track.
+//!
+//! A credential is an element of at most `hash_to_prime_bits` bits, whose
+//! prime is added to the accumulator. The issuer publishes every change of the
+//! accumulator as a signed `AccumulatorUpdate`, which holders apply to update
+//! their witness and verifiers apply to their `AccumulatorTracker`.
+//!
+//! A presentation is a membership proof for a fresh commitment `c_e_q` to the
+//! element, together with a nullifier `u^e` for the public base `u` of the
+//! `CredentialSystem`. The nullifier is tied to `c_e_q` by an integer
+//! commitment `c_n` to the element, with a modeq proof between `c_n` and
+//! `c_e_q` and a PoKE for `c_n` and the nullifier. Verifiers reject nullifiers
+//! they saw before, so each credential can be presented once to a verifier.
+//! All the proofs share a transcript bound to the epoch and associated data of
+//! the presentation.
+//!
+//! For rate limiting, e.g. faucets or polls, a presentation can instead be
+//! made for a nullifier epoch chosen by the verifier, such as the day or the
+//! poll. Its nullifier is `u_t^e` for a base `u_t` hashed from `u` and the
+//! nullifier epoch `t`, which gives one nullifier per credential and nullifier
+//! epoch, unlinkable across nullifier epochs. This needs a group with a
+//! `HashToGroup`, i.e. an RSA group. The nullifier epoch is independent of the
+//! accumulator epoch, which changes with every update. Verifiers keep the
+//! nullifiers in a `NullifierStore`, which can be shared by several instances.
+#[cfg(feature = "prover")]
+use crate::{
+    commitments::Commitment,
+    parameters::Parameters,
+    protocols::{
+        membership::{
+            transcript::TranscriptVerifierChannel as MembershipTranscriptVerifierChannel,
+            Witness as MembershipWitness,
+        },
+        modeq::{
+            transcript::TranscriptVerifierChannel as ModEqTranscriptVerifierChannel,
+            Witness as ModEqWitness,
+        },
+        poke::{
+            transcript::TranscriptVerifierChannel as PoKETranscriptVerifierChannel,
+            Witness as PoKEWitness,
+        },
+        SetupError,
+    },
+    utils::{bigint_to_integer, curve::Field, random_between},
+};
+use crate::{
+    protocols::{
+        hash_to_prime::HashToPrimeProtocol,
+        membership::{
+            transcript::TranscriptProverChannel as MembershipTranscriptProverChannel,
+            Proof as MembershipProof, Protocol as MembershipProtocol,
+            Statement as MembershipStatement,
+        },
+        modeq::{
+            transcript::TranscriptProverChannel as ModEqTranscriptProverChannel,
+            Proof as ModEqProof, Protocol as ModEqProtocol, Statement as ModEqStatement,
+        },
+        poke::{
+            transcript::TranscriptProverChannel as PoKETranscriptProverChannel, CRSPoKE,
+            Proof as PoKEProof, Protocol as PoKEProtocol, Statement as PoKEStatement,
+        },
+        presentation::presentation_aad,
+        ProofError, VerificationError,
+    },
+    set::{
+        tracker::{
+            AccumulatorTracker, AccumulatorUpdate, TrackerError, UpdateKind,
+            UpdateSignatureVerifier,
+        },
+        AccumulatorProvider, AccumulatorProviderError, SetAccumulator,
+    },
+    transcript::{transcript_with_aad, TranscriptProtocolInteger},
+    utils::{
+        curve::CurvePointProjective, hash_to_group::HashToGroup, ConvertibleUnknownOrderGroup,
+    },
+};
+use merlin::Transcript;
+#[cfg(feature = "prover")]
+use rand::{CryptoRng, RngCore};
+#[cfg(feature = "prover")]
+use rug::rand::MutRandState;
+use rug::Integer;
+use std::{cell::RefCell, collections::HashSet};
+
+quick_error! {
+    #[derive(Debug)]
+    pub enum RolesError {
+        AlreadyIssued {}
+        WitnessOutdated {}
+        NullifierReused {}
+        NullifierEpochMismatch {}
+        NullifierStoreError(err: String) {}
+        ProofError(err: ProofError) {
+            from()
+        }
+        VerificationError(err: VerificationError) {
+            from()
+        }
+        TrackerError(err: TrackerError) {
+            from()
+        }
+        ProviderError(err: AccumulatorProviderError) {
+            from()
+        }
+    }
+}
+
+const TRANSCRIPT_LABEL: &[u8] = b"credential";
+const NULLIFIER_EPOCH_LABEL: &[u8] = b"nullifier-epoch";
+
+/// Keeps the nullifiers seen by a verifier, keyed by the nullifier epoch of
+/// the presentation, if any.
+pub trait NullifierStore {
+    fn contains(&self, nullifier_epoch: Option<u64>, nullifier: &[u8]) -> Result<bool, RolesError>;
+    /// Records `nullifier`, returning whether it wasn't recorded yet. Stores
+    /// shared by several verifiers have to check and insert atomically.
+    fn insert(
+        &mut self,
+        nullifier_epoch: Option<u64>,
+        nullifier: &[u8],
+    ) -> Result<bool, RolesError>;
+    /// Forgets the nullifiers of the nullifier epochs before `nullifier_epoch`.
+    fn prune(&mut self, nullifier_epoch: u64) -> Result<(), RolesError>;
+}
+
+/// Keeps the nullifiers in memory.
+#[derive(Clone, Default)]
+pub struct MemoryNullifierStore {
+    nullifiers: HashSet<(Option<u64>, Vec<u8>)>,
+}
+
+impl NullifierStore for MemoryNullifierStore {
+    fn contains(&self, nullifier_epoch: Option<u64>, nullifier: &[u8]) -> Result<bool, RolesError> {
+        Ok(self
+            .nullifiers
+            .contains(&(nullifier_epoch, nullifier.to_vec())))
+    }
+
+    fn insert(
+        &mut self,
+        nullifier_epoch: Option<u64>,
+        nullifier: &[u8],
+    ) -> Result<bool, RolesError> {
+        Ok(self
+            .nullifiers
+            .insert((nullifier_epoch, nullifier.to_vec())))
+    }
+
+    fn prune(&mut self, nullifier_epoch: u64) -> Result<(), RolesError> {
+        self.nullifiers
+            .retain(|(epoch, _)| epoch.map_or(true, |epoch| epoch >= nullifier_epoch));
+        Ok(())
+    }
+}
+
+/// Signs the accumulator updates of the issuer, for the
+/// `UpdateSignatureVerifier` of holders and verifiers.
+pub trait UpdateSigner {
+    fn sign(&self, message: &[u8]) -> Vec<u8>;
+}
+
+/// The public parameters shared by all the roles.
+pub struct CredentialSystem<
+    G: ConvertibleUnknownOrderGroup,
+    P: CurvePointProjective,
+    HP: HashToPrimeProtocol<P>,
+> {
+    pub protocol: MembershipProtocol<G, P, HP>,
+    /// Base of the nullifiers, with a discrete logarithm nobody knows.
+    pub nullifier_base: G::Elem,
+}
+
+impl<G: ConvertibleUnknownOrderGroup, P: CurvePointProjective, HP: HashToPrimeProtocol<P>> Clone
+    for CredentialSystem<G, P, HP>
+{
+    fn clone(&self) -> Self {
+        Self {
+            protocol: MembershipProtocol::from_crs(&self.protocol.crs),
+            nullifier_base: self.nullifier_base.clone(),
+        }
+    }
+}
+
+impl<G: ConvertibleUnknownOrderGroup, P: CurvePointProjective, HP: HashToPrimeProtocol<P>>
+    CredentialSystem<G, P, HP>
+{
+    #[cfg(feature = "prover")]
+    pub fn setup<R1: MutRandState, R2: RngCore + CryptoRng>(
+        parameters: &Parameters,
+        rng1: &mut R1,
+        rng2: &mut R2,
+    ) -> Result<CredentialSystem<G, P, HP>, SetupError> {
+        let protocol = MembershipProtocol::setup(parameters, rng1, rng2)?;
+        let nullifier_base = G::exp(
+            &G::unknown_order_elem(),
+            &G::order_upper_bound().random_below(rng1),
+        );
+        Ok(CredentialSystem {
+            protocol,
+            nullifier_base,
+        })
+    }
+
+    /// The nullifier base `u_t` of the nullifier epoch `t`.
+    pub fn epoch_nullifier_base(&self, nullifier_epoch: u64) -> G::Elem
+    where
+        G: HashToGroup,
+    {
+        let mut message = G::elem_to_bytes(&self.nullifier_base);
+        message.extend_from_slice(&nullifier_epoch.to_be_bytes());
+        G::hash_to_elem(NULLIFIER_EPOCH_LABEL, &message)
+    }
+
+    fn crs_poke(&self) -> CRSPoKE<G> {
+        CRSPoKE {
+            parameters: self.protocol.crs.crs_modeq.parameters.clone(),
+            integer_commitment_parameters: self
+                .protocol
+                .crs
+                .crs_modeq
+                .integer_commitment_parameters
+                .clone(),
+        }
+    }
+}
+
+/// A credential as issued: the element, the membership witness of its prime
+/// and the accumulator at the epoch of issuance.
+pub struct Credential<G: ConvertibleUnknownOrderGroup> {
+    pub e: Integer,
+    pub w: G::Elem,
+    pub epoch: u64,
+    pub value: G::Elem,
+}
+
+impl<G: ConvertibleUnknownOrderGroup> Clone for Credential<G> {
+    fn clone(&self) -> Self {
+        Self {
+            e: self.e.clone(),
+            w: self.w.clone(),
+            epoch: self.epoch,
+            value: self.value.clone(),
+        }
+    }
+}
+
+pub struct Issuer<
+    G: ConvertibleUnknownOrderGroup,
+    P: CurvePointProjective,
+    HP: HashToPrimeProtocol<P>,
+    S: UpdateSigner,
+> {
+    system: CredentialSystem<G, P, HP>,
+    set: SetAccumulator<G>,
+    epoch: u64,
+    signer: S,
+}
+
+impl<
+        G: ConvertibleUnknownOrderGroup,
+        P: CurvePointProjective,
+        HP: HashToPrimeProtocol<P>,
+        S: UpdateSigner,
+    > Issuer<G, P, HP, S>
+{
+    /// Starts with an empty accumulator at epoch 0.
+    pub fn new(system: &CredentialSystem<G, P, HP>, signer: S) -> Issuer<G, P, HP, S> {
+        Issuer {
+            system: system.clone(),
+            set: SetAccumulator::empty(),
+            epoch: 0,
+            signer,
+        }
+    }
+
+    pub fn epoch(&self) -> u64 {
+        self.epoch
+    }
+
+    /// The current accumulator value.
+    pub fn value(&self) -> G::Elem {
+        self.set.value()
+    }
+
+    /// Adds the prime of `e` to the accumulator, moving to the next epoch,
+    /// and returns the credential along with the update to publish.
+    pub fn issue(
+        &mut self,
+        e: &Integer,
+    ) -> Result<(Credential<G>, AccumulatorUpdate<G>), RolesError> {
+        let (hashed_e, _) = self
+            .system
+            .protocol
+            .hash_to_prime(e)
+            .map_err(ProofError::from)?;
+        if self.set.elements().contains(&hashed_e) {
+            return Err(RolesError::AlreadyIssued);
+        }
+        let elements = vec![hashed_e.clone()];
+        let (accumulator, proof) = self.set.accumulator().clone().add_with_proof(&elements);
+        self.set.add(&elements);
+        self.epoch += 1;
+
+        let message = AccumulatorUpdate::<G>::signed_message(
+            self.epoch,
+            UpdateKind::Add,
+            &elements,
+            &accumulator.value,
+        );
+        let update = AccumulatorUpdate {
+            epoch: self.epoch,
+            kind: UpdateKind::Add,
+            elements,
+            new_value: accumulator.value.clone(),
+            proof: proof.proof,
+            signature: self.signer.sign(&message),
+        };
+        let credential = Credential {
+            e: e.clone(),
+            w: self.set.membership_witness(&hashed_e)?,
+            epoch: self.epoch,
+            value: accumulator.value,
+        };
+        Ok((credential, update))
+    }
+
+    /// The current witness for `e`, for holders whose witness can't be
+    /// updated from the published updates.
+    pub fn witness(&self, e: &Integer) -> Result<G::Elem, RolesError> {
+        let (hashed_e, _) = self
+            .system
+            .protocol
+            .hash_to_prime(e)
+            .map_err(ProofError::from)?;
+        Ok(self.set.membership_witness(&hashed_e)?)
+    }
+}
+
+/// A presentation of a credential, see the module documentation.
+pub struct NullifiedPresentation<
+    G: ConvertibleUnknownOrderGroup,
+    P: CurvePointProjective,
+    HP: HashToPrimeProtocol<P>,
+> {
+    pub epoch: u64,
+    /// The nullifier epoch of the nullifier, if any.
+    pub nullifier_epoch: Option<u64>,
+    pub c_e_q: P,
+    pub nullifier: G::Elem,
+    pub c_n: G::Elem,
+    pub proof_membership: MembershipProof<G, P, HP>,
+    pub proof_modeq: ModEqProof<G, P>,
+    pub proof_poke: PoKEProof<G>,
+}
+
+impl<G: ConvertibleUnknownOrderGroup, P: CurvePointProjective, HP: HashToPrimeProtocol<P>> Clone
+    for NullifiedPresentation<G, P, HP>
+{
+    fn clone(&self) -> Self {
+        Self {
+            epoch: self.epoch,
+            nullifier_epoch: self.nullifier_epoch,
+            c_e_q: self.c_e_q.clone(),
+            nullifier: self.nullifier.clone(),
+            c_n: self.c_n.clone(),
+            proof_membership: self.proof_membership.clone(),
+            proof_modeq: self.proof_modeq.clone(),
+            proof_poke: self.proof_poke.clone(),
+        }
+    }
+}
+
+/// Creates the transcript of a presentation, with the nullifier epoch, the
+/// nullifier and its commitment added.
+fn presentation_transcript<G: ConvertibleUnknownOrderGroup>(
+    epoch: u64,
+    aad: &[u8],
+    nullifier_epoch: Option<u64>,
+    nullifier: &G::Elem,
+    c_n: &G::Elem,
+) -> RefCell<Transcript> {
+    let mut transcript = transcript_with_aad(TRANSCRIPT_LABEL, &presentation_aad(epoch, aad));
+    if let Some(nullifier_epoch) = nullifier_epoch {
+        transcript.append_u64(NULLIFIER_EPOCH_LABEL, nullifier_epoch);
+    }
+    TranscriptProtocolInteger::<G>::append_integer_point(&mut transcript, b"nullifier", nullifier);
+    TranscriptProtocolInteger::<G>::append_integer_point(&mut transcript, b"c_n", c_n);
+    RefCell::new(transcript)
+}
+
+pub struct Holder<
+    G: ConvertibleUnknownOrderGroup,
+    P: CurvePointProjective,
+    HP: HashToPrimeProtocol<P>,
+    V: UpdateSignatureVerifier,
+> {
+    system: CredentialSystem<G, P, HP>,
+    credential: Credential<G>,
+    tracker: AccumulatorTracker<G, V>,
+    outdated: bool,
+}
+
+impl<
+        G: ConvertibleUnknownOrderGroup,
+        P: CurvePointProjective,
+        HP: HashToPrimeProtocol<P>,
+        V: UpdateSignatureVerifier,
+    > Holder<G, P, HP, V>
+{
+    pub fn new(
+        system: &CredentialSystem<G, P, HP>,
+        credential: Credential<G>,
+        signature_verifier: V,
+    ) -> Holder<G, P, HP, V> {
+        Holder {
+            system: system.clone(),
+            tracker: AccumulatorTracker::new(
+                credential.epoch,
+                &credential.value,
+                signature_verifier,
+            ),
+            credential,
+            outdated: false,
+        }
+    }
+
+    pub fn epoch(&self) -> u64 {
+        self.tracker.epoch()
+    }
+
+    /// Applies an update of the issuer. Additions update the witness, while
+    /// after a deletion the holder needs a new witness from the issuer,
+    /// passed to `refresh_witness`.
+    pub fn apply(&mut self, update: &AccumulatorUpdate<G>) -> Result<(), RolesError> {
+        self.tracker.apply(update)?;
+        match update.kind {
+            UpdateKind::Add => {
+                let product: Integer = update.elements.iter().product();
+                self.credential.w = G::exp(&self.credential.w, &product);
+            }
+            UpdateKind::Delete => self.outdated = true,
+        }
+        Ok(())
+    }
+
+    pub fn refresh_witness(&mut self, w: &G::Elem) {
+        self.credential.w = w.clone();
+        self.outdated = false;
+    }
+
+    /// Presents the credential at the current epoch, bound to `aad`.
+    #[cfg(feature = "prover")]
+    pub fn present<R1: MutRandState, R2: RngCore + CryptoRng>(
+        &self,
+        rng1: &mut R1,
+        rng2: &mut R2,
+        aad: &[u8],
+    ) -> Result<NullifiedPresentation<G, P, HP>, RolesError> {
+        self.present_with_base(rng1, rng2, aad, None, &self.system.nullifier_base)
+    }
+
+    /// Presents the credential at the current epoch, bound to `aad`, with the
+    /// nullifier of `nullifier_epoch`, of which there is one per credential.
+    #[cfg(feature = "prover")]
+    pub fn present_in_epoch<R1: MutRandState, R2: RngCore + CryptoRng>(
+        &self,
+        rng1: &mut R1,
+        rng2: &mut R2,
+        aad: &[u8],
+        nullifier_epoch: u64,
+    ) -> Result<NullifiedPresentation<G, P, HP>, RolesError>
+    where
+        G: HashToGroup,
+    {
+        let nullifier_base = self.system.epoch_nullifier_base(nullifier_epoch);
+        self.present_with_base(rng1, rng2, aad, Some(nullifier_epoch), &nullifier_base)
+    }
+
+    #[cfg(feature = "prover")]
+    fn present_with_base<R1: MutRandState, R2: RngCore + CryptoRng>(
+        &self,
+        rng1: &mut R1,
+        rng2: &mut R2,
+        aad: &[u8],
+        nullifier_epoch: Option<u64>,
+        nullifier_base: &G::Elem,
+    ) -> Result<NullifiedPresentation<G, P, HP>, RolesError> {
+        if self.outdated {
+            return Err(RolesError::WitnessOutdated);
+        }
+        let crs = &self.system.protocol.crs;
+        let e = &self.credential.e;
+        let r_q = bigint_to_integer::<P>(&P::ScalarField::rand(rng2));
+        let c_e_q = crs
+            .crs_modeq
+            .pedersen_commitment_parameters
+            .commit(e, &r_q)
+            .map_err(ProofError::from)?;
+        let r_n = random_between(rng1, &Integer::from(0), &G::order_upper_bound());
+        let c_n = crs
+            .crs_modeq
+            .integer_commitment_parameters
+            .commit(e, &r_n)
+            .map_err(ProofError::from)?;
+        let nullifier = G::exp(nullifier_base, e);
+        let epoch = self.tracker.epoch();
+        let transcript =
+            presentation_transcript::<G>(epoch, aad, nullifier_epoch, &nullifier, &c_n);
+
+        let mut membership_channel = MembershipTranscriptVerifierChannel::new(crs, &transcript);
+        self.system.protocol.prove(
+            &mut membership_channel,
+            rng1,
+            rng2,
+            &MembershipStatement {
+                c_p: self.tracker.value().clone(),
+                c_e_q: c_e_q.clone(),
+            },
+            &MembershipWitness {
+                e: e.clone(),
+                r_q: r_q.clone(),
+                w: self.credential.w.clone(),
+            },
+        )?;
+        let proof_membership = membership_channel
+            .proof()
+            .map_err(|_| ProofError::CouldNotCreateProof)?;
+
+        let modeq = ModEqProtocol::from_crs(&crs.crs_modeq);
+        let mut modeq_channel = ModEqTranscriptVerifierChannel::new(&crs.crs_modeq, &transcript);
+        modeq.prove(
+            &mut modeq_channel,
+            rng1,
+            rng2,
+            &ModEqStatement {
+                c_e: c_n.clone(),
+                c_e_q: c_e_q.clone(),
+            },
+            &ModEqWitness {
+                e: e.clone(),
+                r: r_n.clone(),
+                r_q,
+            },
+        )?;
+        let proof_modeq = modeq_channel
+            .proof()
+            .map_err(|_| ProofError::CouldNotCreateProof)?;
+
+        let crs_poke = self.system.crs_poke();
+        let poke = PoKEProtocol::from_crs(&crs_poke).map_err(ProofError::from)?;
+        let mut poke_channel = PoKETranscriptVerifierChannel::new(&crs_poke, &transcript);
+        poke.prove(
+            &mut poke_channel,
+            rng1,
+            &PoKEStatement {
+                c_e: c_n.clone(),
+                u: nullifier_base.clone(),
+                y: nullifier.clone(),
+            },
+            &PoKEWitness {
+                e: e.clone(),
+                r: r_n,
+            },
+        )?;
+        let proof_poke = poke_channel
+            .proof()
+            .map_err(|_| ProofError::CouldNotCreateProof)?;
+
+        Ok(NullifiedPresentation {
+            epoch,
+            nullifier_epoch,
+            c_e_q,
+            nullifier,
+            c_n,
+            proof_membership,
+            proof_modeq,
+            proof_poke,
+        })
+    }
+}
+
+pub struct Verifier<
+    G: ConvertibleUnknownOrderGroup,
+    P: CurvePointProjective,
+    HP: HashToPrimeProtocol<P>,
+    V: UpdateSignatureVerifier,
+    S: NullifierStore = MemoryNullifierStore,
+> {
+    system: CredentialSystem<G, P, HP>,
+    tracker: AccumulatorTracker<G, V>,
+    nullifiers: S,
+}
+
+impl<
+        G: ConvertibleUnknownOrderGroup,
+        P: CurvePointProjective,
+        HP: HashToPrimeProtocol<P>,
+        V: UpdateSignatureVerifier,
+    > Verifier<G, P, HP, V>
+{
+    /// Starts tracking the accumulator from a trusted value at `epoch`, with
+    /// the nullifiers kept in memory.
+    pub fn new(
+        system: &CredentialSystem<G, P, HP>,
+        epoch: u64,
+        value: &G::Elem,
+        signature_verifier: V,
+    ) -> Verifier<G, P, HP, V> {
+        Self::with_store(
+            system,
+            epoch,
+            value,
+            signature_verifier,
+            MemoryNullifierStore::default(),
+        )
+    }
+}
+
+impl<
+        G: ConvertibleUnknownOrderGroup,
+        P: CurvePointProjective,
+        HP: HashToPrimeProtocol<P>,
+        V: UpdateSignatureVerifier,
+        S: NullifierStore,
+    > Verifier<G, P, HP, V, S>
+{
+    /// Starts tracking the accumulator from a trusted value at `epoch`, with
+    /// the nullifiers kept in `store`.
+    pub fn with_store(
+        system: &CredentialSystem<G, P, HP>,
+        epoch: u64,
+        value: &G::Elem,
+        signature_verifier: V,
+        store: S,
+    ) -> Verifier<G, P, HP, V, S> {
+        Verifier {
+            system: system.clone(),
+            tracker: AccumulatorTracker::new(epoch, value, signature_verifier),
+            nullifiers: store,
+        }
+    }
+
+    pub fn store(&self) -> &S {
+        &self.nullifiers
+    }
+
+    pub fn store_mut(&mut self) -> &mut S {
+        &mut self.nullifiers
+    }
+
+    pub fn epoch(&self) -> u64 {
+        self.tracker.epoch()
+    }
+
+    pub fn apply(&mut self, update: &AccumulatorUpdate<G>) -> Result<(), RolesError> {
+        Ok(self.tracker.apply(update)?)
+    }
+
+    /// Verifies a presentation at the current epoch bound to `aad`, and
+    /// records its nullifier.
+    pub fn verify(
+        &mut self,
+        presentation: &NullifiedPresentation<G, P, HP>,
+        aad: &[u8],
+    ) -> Result<(), RolesError> {
+        let nullifier_base = self.system.nullifier_base.clone();
+        self.verify_with_base(presentation, aad, None, &nullifier_base)
+    }
+
+    /// Verifies a presentation at the current epoch bound to `aad`, with the
+    /// nullifier of `nullifier_epoch`, and records its nullifier.
+    pub fn verify_in_epoch(
+        &mut self,
+        presentation: &NullifiedPresentation<G, P, HP>,
+        aad: &[u8],
+        nullifier_epoch: u64,
+    ) -> Result<(), RolesError>
+    where
+        G: HashToGroup,
+    {
+        let nullifier_base = self.system.epoch_nullifier_base(nullifier_epoch);
+        self.verify_with_base(presentation, aad, Some(nullifier_epoch), &nullifier_base)
+    }
+
+    fn verify_with_base(
+        &mut self,
+        presentation: &NullifiedPresentation<G, P, HP>,
+        aad: &[u8],
+        nullifier_epoch: Option<u64>,
+        nullifier_base: &G::Elem,
+    ) -> Result<(), RolesError> {
+        if presentation.epoch != self.tracker.epoch() {
+            return Err(VerificationError::StaleEpoch.into());
+        }
+        if presentation.nullifier_epoch != nullifier_epoch {
+            return Err(RolesError::NullifierEpochMismatch);
+        }
+        let nullifier = G::elem_to_bytes(&presentation.nullifier);
+        if self.nullifiers.contains(nullifier_epoch, &nullifier)? {
+            return Err(RolesError::NullifierReused);
+        }
+        let crs = &self.system.protocol.crs;
+        let transcript = presentation_transcript::<G>(
+            presentation.epoch,
+            aad,
+            nullifier_epoch,
+            &presentation.nullifier,
+            &presentation.c_n,
+        );
+
+        let mut membership_channel = MembershipTranscriptProverChannel::new(
+            crs,
+            &transcript,
+            &presentation.proof_membership,
+        );
+        self.system.protocol.verify(
+            &mut membership_channel,
+            &MembershipStatement {
+                c_p: self.tracker.value().clone(),
+                c_e_q: presentation.c_e_q.clone(),
+            },
+        )?;
+
+        let modeq = ModEqProtocol::from_crs(&crs.crs_modeq);
+        let mut modeq_channel = ModEqTranscriptProverChannel::new(
+            &crs.crs_modeq,
+            &transcript,
+            &presentation.proof_modeq,
+        );
+        modeq.verify(
+            &mut modeq_channel,
+            &ModEqStatement {
+                c_e: presentation.c_n.clone(),
+                c_e_q: presentation.c_e_q.clone(),
+            },
+        )?;
+
+        let crs_poke = self.system.crs_poke();
+        let poke = PoKEProtocol::from_crs(&crs_poke).map_err(VerificationError::from)?;
+        let mut poke_channel =
+            PoKETranscriptProverChannel::new(&crs_poke, &transcript, &presentation.proof_poke);
+        poke.verify(
+            &mut poke_channel,
+            &PoKEStatement {
+                c_e: presentation.c_n.clone(),
+                u: nullifier_base.clone(),
+                y: presentation.nullifier.clone(),
+            },
+        )?;
+
+        if !self.nullifiers.insert(nullifier_epoch, &nullifier)? {
+            return Err(RolesError::NullifierReused);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(all(test, feature = "arkworks"))]
+mod test {
+    use super::{
+        CredentialSystem, Holder, Issuer, NullifierStore, RolesError, UpdateSigner, Verifier,
+    };
+    use crate::{
+        parameters::Parameters,
+        protocols::{hash_to_prime::snark_range::Protocol as HPProtocol, VerificationError},
+        set::tracker::UpdateSignatureVerifier,
+    };
+    use accumulator::group::ElemToBytes;
+    use accumulator::group::Rsa2048;
+    use ark_bls12_381::{Bls12_381, G1Projective};
+    use blake2::{Blake2s, Digest};
+    use rand::thread_rng;
+    use rug::rand::RandState;
+    use rug::Integer;
+
+    #[derive(Clone)]
+    struct KeyedHash {
+        key: Vec<u8>,
+    }
+
+    impl UpdateSigner for KeyedHash {
+        fn sign(&self, message: &[u8]) -> Vec<u8> {
+            let mut hasher = Blake2s::default();
+            hasher.update(&self.key);
+            hasher.update(message);
+            hasher.finalize().to_vec()
+        }
+    }
+
+    impl UpdateSignatureVerifier for KeyedHash {
+        fn verify(&self, message: &[u8], signature: &[u8]) -> bool {
+            self.sign(message) == signature
+        }
+    }
+
+    #[test]
+    fn test_roles() {
+        let params = Parameters::from_security_level(128).unwrap();
+        let mut rng1 = RandState::new();
+        rng1.seed(&Integer::from(13));
+        let mut rng2 = thread_rng();
+        let key = KeyedHash {
+            key: b"issuer".to_vec(),
+        };
+
+        let system = CredentialSystem::<Rsa2048, G1Projective, HPProtocol<Bls12_381>>::setup(
+            &params, &mut rng1, &mut rng2,
+        )
+        .unwrap();
+        let mut issuer = Issuer::new(&system, key.clone());
+        let mut verifier = Verifier::new(&system, issuer.epoch(), &issuer.value(), key.clone());
+
+        let element = |offset: u64| {
+            Integer::from(Integer::u_pow_u(2, params.hash_to_prime_bits as u32))
+                - Integer::from(offset)
+        };
+        let (credential, update) = issuer.issue(&element(245)).unwrap();
+        assert!(matches!(
+            issuer.issue(&element(245)),
+            Err(RolesError::AlreadyIssued)
+        ));
+        verifier.apply(&update).unwrap();
+        let mut holder = Holder::new(&system, credential, key.clone());
+
+        let presentation = holder.present(&mut rng1, &mut rng2, b"login").unwrap();
+        assert!(verifier.verify(&presentation, b"other").is_err());
+        verifier.verify(&presentation, b"login").unwrap();
+        // the nullifier is the same for every presentation of a credential
+        let presentation = holder.present(&mut rng1, &mut rng2, b"login").unwrap();
+        assert!(matches!(
+            verifier.verify(&presentation, b"login"),
+            Err(RolesError::NullifierReused)
+        ));
+
+        // after another issuance, presentations from the previous epoch are
+        // rejected until the holder updates its witness
+        let (other_credential, update) = issuer.issue(&element(257)).unwrap();
+        verifier.apply(&update).unwrap();
+        let other_holder = Holder::new(&system, other_credential, key.clone());
+        assert!(matches!(
+            verifier.verify(&presentation, b"login"),
+            Err(RolesError::VerificationError(VerificationError::StaleEpoch))
+        ));
+        holder.apply(&update).unwrap();
+        assert_eq!(holder.epoch(), verifier.epoch());
+        let presentation = other_holder
+            .present(&mut rng1, &mut rng2, b"login")
+            .unwrap();
+        verifier.verify(&presentation, b"login").unwrap();
+    }
+
+    #[test]
+    fn test_epoch_nullifiers() {
+        let params = Parameters::from_security_level(128).unwrap();
+        let mut rng1 = RandState::new();
+        rng1.seed(&Integer::from(13));
+        let mut rng2 = thread_rng();
+        let key = KeyedHash {
+            key: b"issuer".to_vec(),
+        };
+
+        let system = CredentialSystem::<Rsa2048, G1Projective, HPProtocol<Bls12_381>>::setup(
+            &params, &mut rng1, &mut rng2,
+        )
+        .unwrap();
+        let mut issuer = Issuer::new(&system, key.clone());
+        let mut verifier = Verifier::new(&system, issuer.epoch(), &issuer.value(), key.clone());
+        let e = Integer::from(Integer::u_pow_u(2, params.hash_to_prime_bits as u32))
+            - Integer::from(245);
+        let (credential, update) = issuer.issue(&e).unwrap();
+        verifier.apply(&update).unwrap();
+        let holder = Holder::new(&system, credential, key);
+
+        let presentation = holder
+            .present_in_epoch(&mut rng1, &mut rng2, b"faucet", 1)
+            .unwrap();
+        assert!(matches!(
+            verifier.verify_in_epoch(&presentation, b"faucet", 2),
+            Err(RolesError::NullifierEpochMismatch)
+        ));
+        assert!(matches!(
+            verifier.verify(&presentation, b"faucet"),
+            Err(RolesError::NullifierEpochMismatch)
+        ));
+        verifier
+            .verify_in_epoch(&presentation, b"faucet", 1)
+            .unwrap();
+        // one presentation per nullifier epoch
+        let again = holder
+            .present_in_epoch(&mut rng1, &mut rng2, b"faucet", 1)
+            .unwrap();
+        assert_eq!(again.nullifier, presentation.nullifier);
+        assert!(matches!(
+            verifier.verify_in_epoch(&again, b"faucet", 1),
+            Err(RolesError::NullifierReused)
+        ));
+        let next = holder
+            .present_in_epoch(&mut rng1, &mut rng2, b"faucet", 2)
+            .unwrap();
+        assert_ne!(next.nullifier, presentation.nullifier);
+        verifier.verify_in_epoch(&next, b"faucet", 2).unwrap();
+
+        let nullifier = Rsa2048::elem_to_bytes(&presentation.nullifier);
+        assert!(verifier.store().contains(Some(1), &nullifier).unwrap());
+        verifier.store_mut().prune(2).unwrap();
+        assert!(!verifier.store().contains(Some(1), &nullifier).unwrap());
+    }
+}
@@ -0,0 +1,106 @@
+//! Canonical encodings of application identifiers as set elements, i.e. as
+//! the integers given to the hash-to-prime.
+//!
+//! An element is `tag * 2^248 + payload`, where the tag identifies the kind
+//! of identifier and the payload is below `2^248`:
+//!
+//! | kind       | tag | payload                                          |
+//! |------------|-----|--------------------------------------------------|
+//! | `u64`      | 1   | the value                                        |
+//! | `i128`     | 2   | the value plus `2^127`                           |
+//! | UUID       | 3   | the 16 bytes, big-endian                         |
+//! | string     | 4   | the first 31 bytes of Blake2s(string), big-endian |
+//!
+//! Elements therefore have at most 251 bits, which fits the message size of
+//! the hash-to-prime over BLS12-381, and identifiers of different kinds never
+//! collide.
+use blake2::{Blake2s, Digest};
+use rug::{integer::Order, Integer};
+
+const PAYLOAD_BITS: u32 = 248;
+
+const TAG_U64: u32 = 1;
+const TAG_I128: u32 = 2;
+const TAG_UUID: u32 = 3;
+const TAG_STR_HASH: u32 = 4;
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Element(Integer);
+
+impl Element {
+    /// Maximum number of bits of an encoded element.
+    pub const MAX_BITS: u32 = 251;
+
+    fn tagged(tag: u32, payload: Integer) -> Element {
+        Element((Integer::from(tag) << PAYLOAD_BITS) + payload)
+    }
+
+    pub fn from_u64(value: u64) -> Element {
+        Self::tagged(TAG_U64, Integer::from(value))
+    }
+
+    pub fn from_i128(value: i128) -> Element {
+        let offset = Integer::from(Integer::u_pow_u(2, 127));
+        Self::tagged(TAG_I128, Integer::from(value) + offset)
+    }
+
+    pub fn from_uuid(uuid: &[u8; 16]) -> Element {
+        Self::tagged(TAG_UUID, Integer::from_digits(uuid, Order::MsfBe))
+    }
+
+    /// Hashes an arbitrary string, e.g. an email address, into an element.
+    pub fn from_str_hash(value: &str) -> Element {
+        let mut hasher = Blake2s::default();
+        hasher.update(value.as_bytes());
+        let hash = hasher.finalize();
+        let payload_bytes = PAYLOAD_BITS as usize / 8;
+        Self::tagged(
+            TAG_STR_HASH,
+            Integer::from_digits(&hash[..payload_bytes], Order::MsfBe),
+        )
+    }
+
+    pub fn as_integer(&self) -> &Integer {
+        &self.0
+    }
+}
+
+impl From<Element> for Integer {
+    fn from(element: Element) -> Integer {
+        element.0
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::Element;
+    use rug::Integer;
+
+    #[test]
+    fn test_encodings() {
+        let elements = vec![
+            Element::from_u64(0),
+            Element::from_u64(u64::max_value()),
+            Element::from_i128(i128::min_value()),
+            Element::from_i128(-1),
+            Element::from_i128(i128::max_value()),
+            Element::from_uuid(&[0xff; 16]),
+            Element::from_str_hash("alice@example.com"),
+        ];
+        for e in &elements {
+            assert!(e.as_integer().significant_bits() <= Element::MAX_BITS);
+        }
+        for (i, e) in elements.iter().enumerate() {
+            assert!(elements.iter().skip(i + 1).all(|other| other != e));
+        }
+
+        assert_eq!(
+            Integer::from(Element::from_u64(5)),
+            (Integer::from(1) << 248) + 5
+        );
+        assert_eq!(
+            Element::from_str_hash("alice@example.com"),
+            Element::from_str_hash("alice@example.com")
+        );
+    }
+}
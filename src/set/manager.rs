@@ -0,0 +1,332 @@
+//! Concurrent set management for issuers with high write rates.
+//!
+//! `SetManager::add` and `SetManager::remove` take `&self` and can be called
+//! from several threads. Requests are queued in shards chosen by the element,
+//! so that requests for different elements rarely contend, and all the
+//! requests for an element end up in the same shard, where they are checked
+//! against the committed set and the other pending requests. `commit_epoch`
+//! applies the queued requests in a deterministic order - deletions before
+//! additions, each sorted by element - regardless of the order they arrived
+//! in.
+
+use crate::set::{AccumulatorProvider, AccumulatorProviderError, SetAccumulator};
+use crate::utils::ConvertibleUnknownOrderGroup;
+use accumulator::proof::Poe;
+use rug::Integer;
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::sync::{Mutex, MutexGuard, RwLock, RwLockReadGuard};
+
+quick_error! {
+    #[derive(Debug)]
+    pub enum SetManagerError {
+        ElementInSet {}
+        ElementNotInSet {}
+        LockPoisoned {}
+        ProviderError(err: AccumulatorProviderError) {
+            from()
+        }
+    }
+}
+
+/// A change of the accumulator from the value of the previous transition.
+pub struct Transition<G: ConvertibleUnknownOrderGroup> {
+    pub elements: Vec<Integer>,
+    /// The accumulator value after the transition.
+    pub value: G::Elem,
+    /// Proof of exponentiation by the product of `elements`, from the previous
+    /// value to `value` for additions and from `value` to the previous value
+    /// for deletions, as checked by `AccumulatorTracker::apply`.
+    pub proof: Poe<G>,
+}
+
+impl<G: ConvertibleUnknownOrderGroup> Clone for Transition<G> {
+    fn clone(&self) -> Self {
+        Self {
+            elements: self.elements.clone(),
+            value: self.value.clone(),
+            proof: self.proof.clone(),
+        }
+    }
+}
+
+/// The result of committing an epoch.
+pub struct EpochCommit<G: ConvertibleUnknownOrderGroup> {
+    pub epoch: u64,
+    pub value: G::Elem,
+    pub deletion: Option<Transition<G>>,
+    pub addition: Option<Transition<G>>,
+    /// New membership witnesses: for the added elements, and if elements were
+    /// deleted, for all the remaining elements, whose witnesses can't be
+    /// updated from the published transitions. Other holders raise their
+    /// witness to the product of the added elements.
+    pub witnesses: Vec<(Integer, G::Elem)>,
+}
+
+impl<G: ConvertibleUnknownOrderGroup> Clone for EpochCommit<G> {
+    fn clone(&self) -> Self {
+        Self {
+            epoch: self.epoch,
+            value: self.value.clone(),
+            deletion: self.deletion.clone(),
+            addition: self.addition.clone(),
+            witnesses: self.witnesses.clone(),
+        }
+    }
+}
+
+#[derive(Default)]
+struct Shard {
+    additions: Vec<Integer>,
+    deletions: Vec<Integer>,
+}
+
+struct State<G: ConvertibleUnknownOrderGroup> {
+    set: SetAccumulator<G>,
+    epoch: u64,
+}
+
+pub struct SetManager<G: ConvertibleUnknownOrderGroup> {
+    state: RwLock<State<G>>,
+    shards: Vec<Mutex<Shard>>,
+}
+
+impl<G: ConvertibleUnknownOrderGroup> SetManager<G> {
+    /// Manages `set`, currently at `epoch`, queueing requests in `shards`
+    /// shards.
+    pub fn new(set: SetAccumulator<G>, epoch: u64, shards: usize) -> SetManager<G> {
+        SetManager {
+            state: RwLock::new(State { set, epoch }),
+            shards: (0..shards.max(1)).map(|_| Mutex::default()).collect(),
+        }
+    }
+
+    pub fn epoch(&self) -> Result<u64, SetManagerError> {
+        Ok(self.read_state()?.epoch)
+    }
+
+    pub fn value(&self) -> Result<G::Elem, SetManagerError> {
+        Ok(self.read_state()?.set.value())
+    }
+
+    /// Queues the addition of `e` to the next epoch. Cancels a pending
+    /// deletion of `e` instead, if there is one.
+    pub fn add(&self, e: &Integer) -> Result<(), SetManagerError> {
+        let state = self.read_state()?;
+        let mut shard = self.shard(e)?;
+        if let Some(position) = shard.deletions.iter().position(|x| x == e) {
+            shard.deletions.remove(position);
+            return Ok(());
+        }
+        if shard.additions.contains(e) || state.set.elements().contains(e) {
+            return Err(SetManagerError::ElementInSet);
+        }
+        shard.additions.push(e.clone());
+        Ok(())
+    }
+
+    /// Queues the deletion of `e` from the next epoch. Cancels a pending
+    /// addition of `e` instead, if there is one.
+    pub fn remove(&self, e: &Integer) -> Result<(), SetManagerError> {
+        let state = self.read_state()?;
+        let mut shard = self.shard(e)?;
+        if let Some(position) = shard.additions.iter().position(|x| x == e) {
+            shard.additions.remove(position);
+            return Ok(());
+        }
+        if shard.deletions.contains(e) || !state.set.elements().contains(e) {
+            return Err(SetManagerError::ElementNotInSet);
+        }
+        shard.deletions.push(e.clone());
+        Ok(())
+    }
+
+    /// Applies the queued requests as the next epoch. Requests made while
+    /// committing wait for the commit and go to the epoch after.
+    pub async fn commit_epoch(&self) -> Result<EpochCommit<G>, SetManagerError> {
+        let mut state = self
+            .state
+            .write()
+            .map_err(|_| SetManagerError::LockPoisoned)?;
+        let mut additions = vec![];
+        let mut deletions = vec![];
+        for shard in &self.shards {
+            let mut shard = shard.lock().map_err(|_| SetManagerError::LockPoisoned)?;
+            additions.append(&mut shard.additions);
+            deletions.append(&mut shard.deletions);
+        }
+        additions.sort();
+        deletions.sort();
+
+        let deletion = if deletions.is_empty() {
+            None
+        } else {
+            let previous = state.set.value();
+            state.set.remove(&deletions);
+            let value = state.set.value();
+            let product: Integer = deletions.iter().product();
+            Some(Transition {
+                proof: Poe::prove(&value, &product, &previous),
+                elements: deletions,
+                value,
+            })
+        };
+        let addition = if additions.is_empty() {
+            None
+        } else {
+            let previous = state.set.value();
+            state.set.add(&additions);
+            let value = state.set.value();
+            let product: Integer = additions.iter().product();
+            Some(Transition {
+                proof: Poe::prove(&previous, &product, &value),
+                elements: additions,
+                value,
+            })
+        };
+
+        let updated = if deletion.is_some() {
+            state.set.elements()
+        } else {
+            addition.as_ref().map_or(&[][..], |a| &a.elements[..])
+        };
+        let witnesses = updated
+            .iter()
+            .map(|e| Ok((e.clone(), state.set.membership_witness(e)?)))
+            .collect::<Result<Vec<_>, SetManagerError>>()?;
+
+        state.epoch += 1;
+        Ok(EpochCommit {
+            epoch: state.epoch,
+            value: state.set.value(),
+            deletion,
+            addition,
+            witnesses,
+        })
+    }
+
+    fn read_state(&self) -> Result<RwLockReadGuard<State<G>>, SetManagerError> {
+        self.state.read().map_err(|_| SetManagerError::LockPoisoned)
+    }
+
+    fn shard(&self, e: &Integer) -> Result<MutexGuard<Shard>, SetManagerError> {
+        let mut hasher = DefaultHasher::new();
+        e.hash(&mut hasher);
+        let index = (hasher.finish() % self.shards.len() as u64) as usize;
+        self.shards[index]
+            .lock()
+            .map_err(|_| SetManagerError::LockPoisoned)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{SetManager, SetManagerError};
+    use crate::set::SetAccumulator;
+    use accumulator::group::{Group, Rsa2048};
+    use accumulator::proof::Poe;
+    use rug::Integer;
+    use std::future::Future;
+    use std::pin::Pin;
+    use std::sync::Arc;
+    use std::task::{Context, Poll, Wake, Waker};
+    use std::thread;
+
+    const LARGE_PRIMES: [u64; 4] = [
+        553_525_575_239_331_913,
+        12_702_637_924_034_044_211,
+        378_373_571_372_703_133,
+        8_640_171_141_336_142_787,
+    ];
+
+    struct NoopWaker;
+
+    impl Wake for NoopWaker {
+        fn wake(self: Arc<Self>) {}
+    }
+
+    // commit_epoch doesn't wait on anything, so it completes on the first poll
+    fn block_on<F: Future>(future: F) -> F::Output {
+        let waker: Waker = Arc::new(NoopWaker).into();
+        let mut context = Context::from_waker(&waker);
+        let mut future = Box::pin(future);
+        match Pin::as_mut(&mut future).poll(&mut context) {
+            Poll::Ready(output) => output,
+            Poll::Pending => panic!("commit_epoch is pending"),
+        }
+    }
+
+    #[test]
+    fn test_concurrent_commit() {
+        let elements = LARGE_PRIMES
+            .iter()
+            .map(|p| Integer::from(*p))
+            .collect::<Vec<_>>();
+        let manager = Arc::new(SetManager::<Rsa2048>::new(
+            SetAccumulator::from_elements(&elements[..1]),
+            0,
+            4,
+        ));
+
+        let handles = elements[1..]
+            .iter()
+            .map(|e| {
+                let manager = manager.clone();
+                let e = e.clone();
+                thread::spawn(move || manager.add(&e).unwrap())
+            })
+            .collect::<Vec<_>>();
+        for handle in handles {
+            handle.join().unwrap();
+        }
+        assert!(matches!(
+            manager.add(&elements[2]),
+            Err(SetManagerError::ElementInSet)
+        ));
+
+        let previous = manager.value().unwrap();
+        let commit = block_on(manager.commit_epoch()).unwrap();
+        assert_eq!(commit.epoch, 1);
+        assert!(commit.deletion.is_none());
+        let addition = commit.addition.unwrap();
+        // sorted regardless of the order of the requests
+        let mut sorted = elements[1..].to_vec();
+        sorted.sort();
+        assert_eq!(addition.elements, sorted);
+        let product: Integer = sorted.iter().product();
+        assert!(Poe::verify(
+            &previous,
+            &product,
+            &commit.value,
+            &addition.proof
+        ));
+        assert_eq!(commit.witnesses.len(), 3);
+        for (e, w) in &commit.witnesses {
+            assert_eq!(Rsa2048::exp(w, e), commit.value);
+        }
+
+        // a deletion cancels a pending addition
+        let e = Integer::from(3);
+        manager.add(&e).unwrap();
+        manager.remove(&e).unwrap();
+        manager.remove(&elements[0]).unwrap();
+        let previous = commit.value;
+        let commit = block_on(manager.commit_epoch()).unwrap();
+        assert_eq!(commit.epoch, 2);
+        assert!(commit.addition.is_none());
+        let deletion = commit.deletion.unwrap();
+        assert_eq!(deletion.elements, vec![elements[0].clone()]);
+        assert!(Poe::verify(
+            &commit.value,
+            &elements[0],
+            &previous,
+            &deletion.proof
+        ));
+        // all the remaining witnesses are reissued after a deletion
+        assert_eq!(commit.witnesses.len(), 3);
+        assert!(matches!(
+            manager.remove(&elements[0]),
+            Err(SetManagerError::ElementNotInSet)
+        ));
+    }
+}
@@ -0,0 +1,280 @@
+//! Set management: accumulators over the set elements and the witnesses
+//! needed by the membership and nonmembership protocols.
+//!
+//! The elements handled here are the ones actually accumulated, i.e. primes -
+//! if a hash-to-prime is used, these are the hashed elements.
+
+use crate::utils::{bezout_witness, BezoutError, ConvertibleUnknownOrderGroup};
+use accumulator::{Accumulator, AccumulatorWithoutHashToPrime};
+use rug::Integer;
+
+pub mod element;
+pub mod manager;
+pub mod service;
+pub mod storage;
+pub mod tracker;
+
+pub use element::Element;
+
+quick_error! {
+    #[derive(Debug)]
+    pub enum AccumulatorProviderError {
+        ElementNotInSet {}
+        ElementInSet {}
+        CouldNotComputeWitness {}
+    }
+}
+
+/// Provides the accumulator value and witnesses to the protocols, so that
+/// accumulators other than the ones from the `accumulator` crate can be used.
+pub trait AccumulatorProvider<G: ConvertibleUnknownOrderGroup> {
+    /// The current accumulator value, used as `c_p` in the statements.
+    fn value(&self) -> G::Elem;
+    /// A root `w` such that `w^e` is the accumulator value.
+    fn membership_witness(&self, e: &Integer) -> Result<G::Elem, AccumulatorProviderError>;
+    /// `(d, b)` such that `d^e * acc^b` is the generator of the group.
+    fn nonmembership_witness(
+        &self,
+        e: &Integer,
+    ) -> Result<(G::Elem, Integer), AccumulatorProviderError>;
+}
+
+impl From<BezoutError> for AccumulatorProviderError {
+    fn from(e: BezoutError) -> Self {
+        match e {
+            BezoutError::NotCoprime => AccumulatorProviderError::ElementInSet,
+            BezoutError::InvalidAccumulator => AccumulatorProviderError::CouldNotComputeWitness,
+        }
+    }
+}
+
+/// Computes a nonmembership witness `(d, b)` for `e` from the accumulated
+/// elements and the accumulator value, for accumulators stored outside of
+/// `SetAccumulator`. The accumulator is expected to start from the group's
+/// `unknown_order_elem`, as the ones of the `accumulator` crate do.
+pub fn nonmembership_witness_from_elements<G: ConvertibleUnknownOrderGroup>(
+    elements: &[Integer],
+    e: &Integer,
+    acc: &G::Elem,
+) -> Result<(G::Elem, Integer), AccumulatorProviderError> {
+    let set_product = elements
+        .iter()
+        .fold(Integer::from(1), |product, x| product * x);
+    Ok(bezout_witness::<G>(
+        &set_product,
+        e,
+        &G::unknown_order_elem(),
+        acc,
+    )?)
+}
+
+/// Computes the membership witnesses of all the `elements` in
+/// `base^{∏ elements}` with the product tree algorithm (RootFactor): the
+/// witness of each half is `base` raised to the product of the other half,
+/// and so on recursively. This takes `O(n log n)` group operations instead of
+/// the `O(n^2)` of computing each witness on its own.
+pub fn membership_witnesses_from_elements<G: ConvertibleUnknownOrderGroup>(
+    base: &G::Elem,
+    elements: &[Integer],
+) -> Vec<G::Elem> {
+    if elements.len() <= 1 {
+        return elements.iter().map(|_| base.clone()).collect();
+    }
+    let (left, right) = elements.split_at(elements.len() / 2);
+    let product = |half: &[Integer]| half.iter().product::<Integer>();
+    let mut witnesses =
+        membership_witnesses_from_elements::<G>(&G::exp(base, &product(right)), left);
+    witnesses.extend(membership_witnesses_from_elements::<G>(
+        &G::exp(base, &product(left)),
+        right,
+    ));
+    witnesses
+}
+
+/// An accumulator from the `accumulator` crate, together with the accumulated
+/// elements needed to compute witnesses.
+pub struct SetAccumulator<G: ConvertibleUnknownOrderGroup> {
+    accumulator: Accumulator<G, Integer, AccumulatorWithoutHashToPrime>,
+    elements: Vec<Integer>,
+}
+
+impl<G: ConvertibleUnknownOrderGroup> Clone for SetAccumulator<G> {
+    fn clone(&self) -> Self {
+        Self {
+            accumulator: self.accumulator.clone(),
+            elements: self.elements.clone(),
+        }
+    }
+}
+
+impl<G: ConvertibleUnknownOrderGroup> SetAccumulator<G> {
+    pub fn empty() -> SetAccumulator<G> {
+        SetAccumulator {
+            accumulator: Accumulator::<G, Integer, AccumulatorWithoutHashToPrime>::empty(),
+            elements: vec![],
+        }
+    }
+
+    pub fn from_elements(elements: &[Integer]) -> SetAccumulator<G> {
+        let mut set = Self::empty();
+        set.add(elements);
+        set
+    }
+
+    pub fn add(&mut self, elements: &[Integer]) {
+        self.accumulator = self.accumulator.clone().add(elements);
+        self.elements.extend_from_slice(elements);
+    }
+
+    /// Removes `elements`, recomputing the accumulator from the remaining
+    /// ones.
+    pub fn remove(&mut self, elements: &[Integer]) {
+        self.elements.retain(|x| !elements.contains(x));
+        self.accumulator =
+            Accumulator::<G, Integer, AccumulatorWithoutHashToPrime>::empty().add(&self.elements);
+    }
+
+    /// The membership witnesses of all the elements, in the order of
+    /// `elements`.
+    pub fn membership_witnesses(&self) -> Vec<G::Elem> {
+        membership_witnesses_from_elements::<G>(&G::unknown_order_elem(), &self.elements)
+    }
+
+    pub fn elements(&self) -> &[Integer] {
+        &self.elements
+    }
+
+    pub fn accumulator(&self) -> &Accumulator<G, Integer, AccumulatorWithoutHashToPrime> {
+        &self.accumulator
+    }
+
+    /// How many times `e` was added.
+    pub fn multiplicity(&self, e: &Integer) -> usize {
+        self.elements.iter().filter(|x| *x == e).count()
+    }
+
+    /// A root `w` such that `w^(e^k)` is the accumulator value, for
+    /// `Protocol::prove_multiplicity`.
+    pub fn multiplicity_witness(
+        &self,
+        e: &Integer,
+        k: usize,
+    ) -> Result<G::Elem, AccumulatorProviderError> {
+        if k == 0 || self.multiplicity(e) < k {
+            return Err(AccumulatorProviderError::ElementNotInSet);
+        }
+        let mut skipped = 0;
+        let others = self
+            .elements
+            .iter()
+            .filter(|x| {
+                if *x == e && skipped < k {
+                    skipped += 1;
+                    false
+                } else {
+                    true
+                }
+            })
+            .cloned()
+            .collect::<Vec<_>>();
+        Ok(
+            Accumulator::<G, Integer, AccumulatorWithoutHashToPrime>::empty()
+                .add(&others)
+                .value,
+        )
+    }
+}
+
+impl<G: ConvertibleUnknownOrderGroup> AccumulatorProvider<G> for SetAccumulator<G> {
+    fn value(&self) -> G::Elem {
+        self.accumulator.value.clone()
+    }
+
+    fn membership_witness(&self, e: &Integer) -> Result<G::Elem, AccumulatorProviderError> {
+        let position = self
+            .elements
+            .iter()
+            .position(|x| x == e)
+            .ok_or(AccumulatorProviderError::ElementNotInSet)?;
+        let others = self
+            .elements
+            .iter()
+            .enumerate()
+            .filter(|(i, _)| *i != position)
+            .map(|(_, x)| x.clone())
+            .collect::<Vec<_>>();
+        Ok(
+            Accumulator::<G, Integer, AccumulatorWithoutHashToPrime>::empty()
+                .add(&others)
+                .value,
+        )
+    }
+
+    fn nonmembership_witness(
+        &self,
+        e: &Integer,
+    ) -> Result<(G::Elem, Integer), AccumulatorProviderError> {
+        if self.elements.contains(e) {
+            return Err(AccumulatorProviderError::ElementInSet);
+        }
+        nonmembership_witness_from_elements::<G>(&self.elements, e, &self.accumulator.value)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{nonmembership_witness_from_elements, AccumulatorProvider, SetAccumulator};
+    use crate::utils::exp_signed;
+    use accumulator::group::{Group, Rsa2048};
+    use rug::Integer;
+
+    const LARGE_PRIMES: [u64; 4] = [
+        553_525_575_239_331_913,
+        12_702_637_924_034_044_211,
+        378_373_571_372_703_133,
+        8_640_171_141_336_142_787,
+    ];
+
+    #[test]
+    fn test_witnesses() {
+        let elements = LARGE_PRIMES
+            .iter()
+            .skip(1)
+            .map(|p| Integer::from(*p))
+            .collect::<Vec<_>>();
+        let set = SetAccumulator::<Rsa2048>::from_elements(&elements);
+        let acc = set.value();
+
+        let w = set.membership_witness(&elements[1]).unwrap();
+        assert_eq!(Rsa2048::exp(&w, &elements[1]), acc);
+
+        let value = Integer::from(LARGE_PRIMES[0]);
+        set.membership_witness(&value).unwrap_err();
+        let (d, b) = set.nonmembership_witness(&value).unwrap();
+        assert_eq!(
+            Rsa2048::op(&Rsa2048::exp(&d, &value), &exp_signed::<Rsa2048>(&acc, &b)),
+            Rsa2048::unknown_order_elem()
+        );
+        set.nonmembership_witness(&elements[0]).unwrap_err();
+        let (d_elements, b_elements) =
+            nonmembership_witness_from_elements::<Rsa2048>(&elements, &value, &acc).unwrap();
+        assert_eq!((d_elements, b_elements), (d, b));
+    }
+
+    #[test]
+    fn test_membership_witnesses() {
+        let elements = LARGE_PRIMES
+            .iter()
+            .map(|p| Integer::from(*p))
+            .collect::<Vec<_>>();
+        // odd and even sizes split unevenly and evenly
+        for n in 0..=elements.len() {
+            let set = SetAccumulator::<Rsa2048>::from_elements(&elements[..n]);
+            let witnesses = set.membership_witnesses();
+            assert_eq!(witnesses.len(), n);
+            for (e, w) in elements.iter().zip(witnesses.iter()) {
+                assert_eq!(*w, set.membership_witness(e).unwrap());
+            }
+        }
+    }
+}
@@ -0,0 +1,145 @@
+//! Issuer-side witness generation for clients.
+//!
+//! A client sends the hashed prime of its element and gets back only its own
+//! membership witness. `WitnessService` keeps a product tree over the set:
+//! every node caches the product of the elements below it and, once a request
+//! went through it, the accumulator base raised to the product of all the
+//! elements outside of it. A request walks from the root to its leaf, raising
+//! the witness of each node to the product of its sibling, so requests share
+//! the exponentiations near the root instead of each raising the base to the
+//! product of all the other elements. With a trapdoor, a multiple of the group
+//! order such as `φ(N)` for an RSA modulus, the witness is computed directly
+//! as the `e`-th root of the accumulator.
+
+use crate::set::{AccumulatorProvider, AccumulatorProviderError, SetAccumulator};
+use crate::utils::ConvertibleUnknownOrderGroup;
+use rug::Integer;
+use std::collections::HashMap;
+
+pub struct WitnessService<G: ConvertibleUnknownOrderGroup> {
+    value: G::Elem,
+    positions: HashMap<Integer, usize>,
+    /// Products of the nodes of each level, starting from the leaves.
+    products: Vec<Vec<Integer>>,
+    /// Witnesses of the nodes computed by previous requests.
+    witnesses: Vec<Vec<Option<G::Elem>>>,
+    trapdoor: Option<Integer>,
+}
+
+impl<G: ConvertibleUnknownOrderGroup> WitnessService<G> {
+    /// Builds the product tree over the elements of `set`.
+    pub fn new(set: &SetAccumulator<G>) -> WitnessService<G> {
+        let elements = set.elements();
+        let positions = elements
+            .iter()
+            .enumerate()
+            .map(|(i, e)| (e.clone(), i))
+            .collect();
+        let mut products = vec![elements.to_vec()];
+        while products[products.len() - 1].len() > 1 {
+            let next = products[products.len() - 1]
+                .chunks(2)
+                .map(|pair| pair.iter().product())
+                .collect();
+            products.push(next);
+        }
+        let mut witnesses = products
+            .iter()
+            .map(|level| vec![None; level.len()])
+            .collect::<Vec<_>>();
+        // nothing is outside of the root
+        if let Some(root) = witnesses.last_mut().and_then(|level| level.first_mut()) {
+            *root = Some(G::unknown_order_elem());
+        }
+        WitnessService {
+            value: set.value(),
+            positions,
+            products,
+            witnesses,
+            trapdoor: None,
+        }
+    }
+
+    /// Builds the service with a multiple of the group order, computing
+    /// witnesses with a single exponentiation.
+    pub fn with_trapdoor(set: &SetAccumulator<G>, trapdoor: &Integer) -> WitnessService<G> {
+        let mut service = Self::new(set);
+        service.trapdoor = Some(trapdoor.clone());
+        service
+    }
+
+    /// The accumulator value the witnesses are for.
+    pub fn value(&self) -> &G::Elem {
+        &self.value
+    }
+
+    /// The membership witness of the hashed prime `e`.
+    pub fn witness(&mut self, e: &Integer) -> Result<G::Elem, AccumulatorProviderError> {
+        let position = *self
+            .positions
+            .get(e)
+            .ok_or(AccumulatorProviderError::ElementNotInSet)?;
+        if let Some(trapdoor) = &self.trapdoor {
+            let inverse = e
+                .invert_ref(trapdoor)
+                .map(Integer::from)
+                .ok_or(AccumulatorProviderError::CouldNotComputeWitness)?;
+            return Ok(G::exp(&self.value, &inverse));
+        }
+
+        for level in (1..self.products.len()).rev() {
+            let node = position >> level;
+            let witness = self.witnesses[level][node]
+                .clone()
+                .ok_or(AccumulatorProviderError::CouldNotComputeWitness)?;
+            let child = position >> (level - 1);
+            if self.witnesses[level - 1][child].is_some() {
+                continue;
+            }
+            let sibling = child ^ 1;
+            self.witnesses[level - 1][child] = Some(match self.products[level - 1].get(sibling) {
+                Some(product) => G::exp(&witness, product),
+                None => witness,
+            });
+        }
+        self.witnesses[0][position]
+            .clone()
+            .ok_or(AccumulatorProviderError::CouldNotComputeWitness)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::WitnessService;
+    use crate::set::{AccumulatorProvider, AccumulatorProviderError, SetAccumulator};
+    use accumulator::group::{Group, Rsa2048};
+    use rug::Integer;
+
+    const LARGE_PRIMES: [u64; 5] = [
+        553_525_575_239_331_913,
+        12_702_637_924_034_044_211,
+        378_373_571_372_703_133,
+        8_640_171_141_336_142_787,
+        1_000_000_000_000_000_003,
+    ];
+
+    #[test]
+    fn test_witness_service() {
+        let elements = LARGE_PRIMES
+            .iter()
+            .map(|p| Integer::from(*p))
+            .collect::<Vec<_>>();
+        let set = SetAccumulator::<Rsa2048>::from_elements(&elements);
+        let mut service = WitnessService::new(&set);
+        // the odd number of elements leaves a node without a sibling
+        for e in elements.iter().rev() {
+            let w = service.witness(e).unwrap();
+            assert_eq!(w, set.membership_witness(e).unwrap());
+            assert_eq!(Rsa2048::exp(&w, e), *service.value());
+        }
+        assert!(matches!(
+            service.witness(&Integer::from(3)),
+            Err(AccumulatorProviderError::ElementNotInSet)
+        ));
+    }
+}
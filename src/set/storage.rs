@@ -0,0 +1,264 @@
+//! Persistent storage for the set: the accumulated elements, the history of
+//! accumulator values and the witnesses issued to members.
+//!
+//! Group elements are stored in their byte encoding, while the accumulator
+//! itself is recomputed from the stored elements when the set is loaded.
+
+use crate::{
+    set::{AccumulatorProvider, AccumulatorProviderError, SetAccumulator},
+    utils::{integer_to_bytes, ConvertibleUnknownOrderGroup},
+};
+use rug::Integer;
+use std::collections::BTreeMap;
+
+quick_error! {
+    #[derive(Debug)]
+    pub enum StorageError {
+        BackendError(err: String) {}
+        AccumulatorError(err: AccumulatorProviderError) {
+            from()
+        }
+    }
+}
+
+/// A set of changes applied atomically - either all of them are persisted or
+/// none are.
+#[derive(Clone, Default)]
+pub struct StorageBatch {
+    pub added_elements: Vec<Integer>,
+    pub accumulator_value: Option<Vec<u8>>,
+    pub witnesses: Vec<(Integer, Vec<u8>)>,
+}
+
+pub trait SetStorage {
+    fn elements(&self) -> Result<Vec<Integer>, StorageError>;
+    fn accumulator_history(&self) -> Result<Vec<Vec<u8>>, StorageError>;
+    fn witness(&self, e: &Integer) -> Result<Option<Vec<u8>>, StorageError>;
+    fn apply_batch(&mut self, batch: &StorageBatch) -> Result<(), StorageError>;
+}
+
+/// Keeps everything in memory, useful for tests and short-lived issuers.
+#[derive(Clone, Default)]
+pub struct MemoryStorage {
+    elements: Vec<Integer>,
+    history: Vec<Vec<u8>>,
+    witnesses: BTreeMap<Vec<u8>, Vec<u8>>,
+}
+
+impl SetStorage for MemoryStorage {
+    fn elements(&self) -> Result<Vec<Integer>, StorageError> {
+        Ok(self.elements.clone())
+    }
+
+    fn accumulator_history(&self) -> Result<Vec<Vec<u8>>, StorageError> {
+        Ok(self.history.clone())
+    }
+
+    fn witness(&self, e: &Integer) -> Result<Option<Vec<u8>>, StorageError> {
+        Ok(self.witnesses.get(&integer_to_bytes(e)).cloned())
+    }
+
+    fn apply_batch(&mut self, batch: &StorageBatch) -> Result<(), StorageError> {
+        self.elements.extend_from_slice(&batch.added_elements);
+        if let Some(value) = &batch.accumulator_value {
+            self.history.push(value.clone());
+        }
+        for (e, w) in &batch.witnesses {
+            self.witnesses.insert(integer_to_bytes(e), w.clone());
+        }
+        Ok(())
+    }
+}
+
+#[cfg(feature = "sled")]
+pub use self::sled_storage::SledStorage;
+
+#[cfg(feature = "sled")]
+mod sled_storage {
+    use super::{SetStorage, StorageBatch, StorageError};
+    use crate::utils::{bytes_to_integer, integer_to_bytes};
+    use rug::Integer;
+    use sled::{
+        transaction::{ConflictableTransactionError, TransactionError},
+        Transactional,
+    };
+    use std::path::Path;
+
+    impl From<sled::Error> for StorageError {
+        fn from(err: sled::Error) -> Self {
+            StorageError::BackendError(err.to_string())
+        }
+    }
+
+    impl From<TransactionError> for StorageError {
+        fn from(err: TransactionError) -> Self {
+            StorageError::BackendError(format!("{:?}", err))
+        }
+    }
+
+    /// Stores the set in an embedded sled database. Batches are applied in a
+    /// single transaction across all trees and flushed before returning.
+    pub struct SledStorage {
+        db: sled::Db,
+        elements: sled::Tree,
+        history: sled::Tree,
+        witnesses: sled::Tree,
+    }
+
+    impl SledStorage {
+        pub fn open<P: AsRef<Path>>(path: P) -> Result<SledStorage, StorageError> {
+            let db = sled::open(path)?;
+            Ok(SledStorage {
+                elements: db.open_tree(b"elements")?,
+                history: db.open_tree(b"history")?,
+                witnesses: db.open_tree(b"witnesses")?,
+                db,
+            })
+        }
+    }
+
+    impl SetStorage for SledStorage {
+        fn elements(&self) -> Result<Vec<Integer>, StorageError> {
+            let mut elements = vec![];
+            for entry in self.elements.iter() {
+                let (_, e) = entry?;
+                elements.push(bytes_to_integer(&e));
+            }
+            Ok(elements)
+        }
+
+        fn accumulator_history(&self) -> Result<Vec<Vec<u8>>, StorageError> {
+            let mut history = vec![];
+            for entry in self.history.iter() {
+                let (_, value) = entry?;
+                history.push(value.to_vec());
+            }
+            Ok(history)
+        }
+
+        fn witness(&self, e: &Integer) -> Result<Option<Vec<u8>>, StorageError> {
+            Ok(self.witnesses.get(integer_to_bytes(e))?.map(|w| w.to_vec()))
+        }
+
+        fn apply_batch(&mut self, batch: &StorageBatch) -> Result<(), StorageError> {
+            // keys are big-endian counters, so iteration follows insertion order
+            let first_element_index = self.elements.len() as u64;
+            let epoch = self.history.len() as u64;
+            (&self.elements, &self.history, &self.witnesses).transaction(
+                |(elements, history, witnesses)| {
+                    for (i, e) in batch.added_elements.iter().enumerate() {
+                        let key = (first_element_index + i as u64).to_be_bytes();
+                        elements.insert(&key[..], integer_to_bytes(e))?;
+                    }
+                    if let Some(value) = &batch.accumulator_value {
+                        history.insert(&epoch.to_be_bytes()[..], value.clone())?;
+                    }
+                    for (e, w) in &batch.witnesses {
+                        witnesses.insert(integer_to_bytes(e), w.clone())?;
+                    }
+                    Ok::<(), ConflictableTransactionError>(())
+                },
+            )?;
+            self.db.flush()?;
+            Ok(())
+        }
+    }
+}
+
+/// A set accumulator whose state is persisted to a storage backend.
+pub struct PersistentSet<G: ConvertibleUnknownOrderGroup, S: SetStorage> {
+    set: SetAccumulator<G>,
+    storage: S,
+}
+
+impl<G: ConvertibleUnknownOrderGroup, S: SetStorage> PersistentSet<G, S> {
+    /// Loads the set from the storage, recomputing the accumulator.
+    pub fn open(storage: S) -> Result<PersistentSet<G, S>, StorageError> {
+        let elements = storage.elements()?;
+        Ok(PersistentSet {
+            set: SetAccumulator::from_elements(&elements),
+            storage,
+        })
+    }
+
+    pub fn add(&mut self, elements: &[Integer]) -> Result<(), StorageError> {
+        let mut set = self.set.clone();
+        set.add(elements);
+        self.storage.apply_batch(&StorageBatch {
+            added_elements: elements.to_vec(),
+            accumulator_value: Some(G::elem_to_bytes(&set.value())),
+            witnesses: vec![],
+        })?;
+        self.set = set;
+        Ok(())
+    }
+
+    /// Computes a membership witness for `e` and records it as issued.
+    pub fn issue_membership_witness(&mut self, e: &Integer) -> Result<G::Elem, StorageError> {
+        let w = self.set.membership_witness(e)?;
+        self.storage.apply_batch(&StorageBatch {
+            witnesses: vec![(e.clone(), G::elem_to_bytes(&w))],
+            ..Default::default()
+        })?;
+        Ok(w)
+    }
+
+    pub fn issued_witness(&self, e: &Integer) -> Result<Option<Vec<u8>>, StorageError> {
+        self.storage.witness(e)
+    }
+
+    pub fn set(&self) -> &SetAccumulator<G> {
+        &self.set
+    }
+
+    pub fn storage(&self) -> &S {
+        &self.storage
+    }
+}
+
+impl<G: ConvertibleUnknownOrderGroup, S: SetStorage> AccumulatorProvider<G>
+    for PersistentSet<G, S>
+{
+    fn value(&self) -> G::Elem {
+        self.set.value()
+    }
+
+    fn membership_witness(&self, e: &Integer) -> Result<G::Elem, AccumulatorProviderError> {
+        self.set.membership_witness(e)
+    }
+
+    fn nonmembership_witness(
+        &self,
+        e: &Integer,
+    ) -> Result<(G::Elem, Integer), AccumulatorProviderError> {
+        self.set.nonmembership_witness(e)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{MemoryStorage, PersistentSet, SetStorage};
+    use crate::set::AccumulatorProvider;
+    use accumulator::group::{ElemToBytes, Rsa2048};
+    use rug::Integer;
+
+    #[test]
+    fn test_persistent_set() {
+        let mut set = PersistentSet::<Rsa2048, _>::open(MemoryStorage::default()).unwrap();
+        set.add(&[Integer::from(553_525_575_239_331_913u64)])
+            .unwrap();
+        set.add(&[Integer::from(378_373_571_372_703_133u64)])
+            .unwrap();
+        let e = Integer::from(553_525_575_239_331_913u64);
+        let w = set.issue_membership_witness(&e).unwrap();
+        assert_eq!(
+            set.issued_witness(&e).unwrap().unwrap(),
+            Rsa2048::elem_to_bytes(&w)
+        );
+
+        let storage = set.storage().clone();
+        assert_eq!(storage.accumulator_history().unwrap().len(), 2);
+        let reopened = PersistentSet::<Rsa2048, _>::open(storage).unwrap();
+        assert_eq!(reopened.value(), set.value());
+    }
+}
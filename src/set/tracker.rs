@@ -0,0 +1,217 @@
+//! Verifier-side tracking of the accumulator value from signed update
+//! messages, so that verifiers always use the current `c_p` in statements.
+//!
+//! Each update carries a proof of exponentiation showing the new value is
+//! consistent with the elements added or deleted, and a signature of the set
+//! manager over the update.
+
+use crate::utils::{integer_to_bytes, ConvertibleUnknownOrderGroup};
+use accumulator::proof::Poe;
+use rug::Integer;
+
+quick_error! {
+    #[derive(Debug)]
+    pub enum TrackerError {
+        InvalidSignature {}
+        InvalidEpoch {}
+        InvalidTransition {}
+    }
+}
+
+/// Verifies the signatures of the set manager over updates.
+pub trait UpdateSignatureVerifier {
+    fn verify(&self, message: &[u8], signature: &[u8]) -> bool;
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum UpdateKind {
+    Add,
+    Delete,
+}
+
+pub struct AccumulatorUpdate<G: ConvertibleUnknownOrderGroup> {
+    pub epoch: u64,
+    pub kind: UpdateKind,
+    pub elements: Vec<Integer>,
+    pub new_value: G::Elem,
+    pub proof: Poe<G>,
+    pub signature: Vec<u8>,
+}
+
+impl<G: ConvertibleUnknownOrderGroup> Clone for AccumulatorUpdate<G> {
+    fn clone(&self) -> Self {
+        Self {
+            epoch: self.epoch,
+            kind: self.kind,
+            elements: self.elements.clone(),
+            new_value: self.new_value.clone(),
+            proof: self.proof.clone(),
+            signature: self.signature.clone(),
+        }
+    }
+}
+
+impl<G: ConvertibleUnknownOrderGroup> AccumulatorUpdate<G> {
+    /// The bytes the set manager signs.
+    pub fn signed_message(
+        epoch: u64,
+        kind: UpdateKind,
+        elements: &[Integer],
+        new_value: &G::Elem,
+    ) -> Vec<u8> {
+        let mut message = vec![];
+        message.extend_from_slice(&epoch.to_be_bytes());
+        message.push(match kind {
+            UpdateKind::Add => 0,
+            UpdateKind::Delete => 1,
+        });
+        message.extend_from_slice(&(elements.len() as u64).to_be_bytes());
+        for e in elements {
+            let bytes = integer_to_bytes(e);
+            message.extend_from_slice(&(bytes.len() as u64).to_be_bytes());
+            message.extend_from_slice(&bytes);
+        }
+        message.extend_from_slice(&G::elem_to_bytes(new_value));
+        message
+    }
+}
+
+/// Keeps the current accumulator value, accepting only updates which are
+/// signed, in order and consistent with the previous value.
+pub struct AccumulatorTracker<G: ConvertibleUnknownOrderGroup, V: UpdateSignatureVerifier> {
+    epoch: u64,
+    value: G::Elem,
+    signature_verifier: V,
+}
+
+impl<G: ConvertibleUnknownOrderGroup, V: UpdateSignatureVerifier> AccumulatorTracker<G, V> {
+    /// Starts tracking from a trusted accumulator value at a given epoch.
+    pub fn new(epoch: u64, value: &G::Elem, signature_verifier: V) -> AccumulatorTracker<G, V> {
+        AccumulatorTracker {
+            epoch,
+            value: value.clone(),
+            signature_verifier,
+        }
+    }
+
+    pub fn epoch(&self) -> u64 {
+        self.epoch
+    }
+
+    /// The current accumulator value, to be used as `c_p`.
+    pub fn value(&self) -> &G::Elem {
+        &self.value
+    }
+
+    pub fn apply(&mut self, update: &AccumulatorUpdate<G>) -> Result<(), TrackerError> {
+        if update.epoch != self.epoch + 1 {
+            return Err(TrackerError::InvalidEpoch);
+        }
+        let message = AccumulatorUpdate::<G>::signed_message(
+            update.epoch,
+            update.kind,
+            &update.elements,
+            &update.new_value,
+        );
+        if !self.signature_verifier.verify(&message, &update.signature) {
+            return Err(TrackerError::InvalidSignature);
+        }
+
+        let product: Integer = update.elements.iter().product();
+        let is_valid = match update.kind {
+            UpdateKind::Add => Poe::verify(&self.value, &product, &update.new_value, &update.proof),
+            UpdateKind::Delete => {
+                Poe::verify(&update.new_value, &product, &self.value, &update.proof)
+            }
+        };
+        if !is_valid {
+            return Err(TrackerError::InvalidTransition);
+        }
+
+        self.epoch = update.epoch;
+        self.value = update.new_value.clone();
+        Ok(())
+    }
+
+    pub fn apply_all(&mut self, updates: &[AccumulatorUpdate<G>]) -> Result<(), TrackerError> {
+        for update in updates {
+            self.apply(update)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{
+        AccumulatorTracker, AccumulatorUpdate, TrackerError, UpdateKind, UpdateSignatureVerifier,
+    };
+    use accumulator::{group::Rsa2048, Accumulator, AccumulatorWithoutHashToPrime};
+    use blake2::{Blake2s, Digest};
+    use rug::Integer;
+
+    struct KeyedHashVerifier {
+        key: Vec<u8>,
+    }
+
+    impl KeyedHashVerifier {
+        fn sign(&self, message: &[u8]) -> Vec<u8> {
+            let mut hasher = Blake2s::default();
+            hasher.update(&self.key);
+            hasher.update(message);
+            hasher.finalize().to_vec()
+        }
+    }
+
+    impl UpdateSignatureVerifier for KeyedHashVerifier {
+        fn verify(&self, message: &[u8], signature: &[u8]) -> bool {
+            self.sign(message) == signature
+        }
+    }
+
+    #[test]
+    fn test_tracker() {
+        let signer = KeyedHashVerifier {
+            key: b"key".to_vec(),
+        };
+        let accum = Accumulator::<Rsa2048, Integer, AccumulatorWithoutHashToPrime>::empty();
+        let mut tracker = AccumulatorTracker::<Rsa2048, _>::new(
+            0,
+            &accum.value,
+            KeyedHashVerifier {
+                key: b"key".to_vec(),
+            },
+        );
+
+        let elements = vec![Integer::from(553_525_575_239_331_913u64)];
+        let (accum, proof) = accum.add_with_proof(&elements);
+        let message = AccumulatorUpdate::<Rsa2048>::signed_message(
+            1,
+            UpdateKind::Add,
+            &elements,
+            &accum.value,
+        );
+        let update = AccumulatorUpdate::<Rsa2048> {
+            epoch: 1,
+            kind: UpdateKind::Add,
+            elements,
+            new_value: accum.value.clone(),
+            proof: proof.proof,
+            signature: signer.sign(&message),
+        };
+
+        let mut wrong_update = update.clone();
+        wrong_update.elements = vec![Integer::from(378_373_571_372_703_133u64)];
+        match tracker.apply(&wrong_update) {
+            Err(TrackerError::InvalidSignature) => {}
+            _ => panic!("expected an invalid signature"),
+        }
+
+        tracker.apply(&update).unwrap();
+        assert_eq!(tracker.value(), &accum.value);
+        match tracker.apply(&update) {
+            Err(TrackerError::InvalidEpoch) => {}
+            _ => panic!("expected an invalid epoch"),
+        }
+    }
+}
@@ -0,0 +1,302 @@
+//! Picks a configuration of the membership protocol, the group of unknown
+//! order, the hash-to-prime backend and the parameters, from constraints on
+//! the proofs.
+//!
+//! The proof sizes are those of `protocols::sizes::estimate_sizes` at the
+//! parameters `Parameters::from_curve` derives for the curve of the backend,
+//! so only the backends compiled in, with the `arkworks` and `dalek`
+//! features, are considered. The SNARK backends count their constraints by
+//! synthesizing the circuit, so they also need the `prover` feature. The proving times are orders of magnitude of the
+//! benches on a single core, only precise enough to rank the configurations;
+//! `benchmarks::MembershipFixture::measure` gives the costs on the target
+//! hardware.
+use crate::{
+    parameters::{HashToPrimeBackend, Parameters, ParametersError},
+    utils::ConvertibleUnknownOrderGroup,
+};
+use accumulator::group::{ClassGroup, Rsa2048};
+
+quick_error! {
+    #[derive(Debug)]
+    pub enum StrategyError {
+        NoConfiguration {}
+        InvalidParameters(err: ParametersError) {
+            from()
+        }
+    }
+}
+
+/// The groups of unknown order of the accumulator library.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum GroupKind {
+    /// `Rsa2048`, whose modulus has to be generated by a trusted party.
+    Rsa2048,
+    /// `ClassGroup`, which needs no trusted setup but is much slower.
+    /// Nonmembership over it needs the `class` feature, see
+    /// `protocols::coprime::validate_coprime_group`.
+    ClassGroup,
+}
+
+/// The hash-to-prime protocols.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum HashToPrimeKind {
+    /// A LegoGroth16 backend over BLS12-381, with a trusted setup, used
+    /// through `DynHashToPrime`.
+    Snark(HashToPrimeBackend),
+    /// `bp::Protocol`, a range proof over Ristretto with no trusted setup.
+    /// Requires the `dalek` feature.
+    Bulletproofs,
+}
+
+impl HashToPrimeKind {
+    /// Whether the protocol hashes arbitrary elements to primes, rather than
+    /// proving a range for elements which are primes already.
+    pub fn hashes_elements(&self) -> bool {
+        match self {
+            HashToPrimeKind::Snark(HashToPrimeBackend::SnarkRange) => false,
+            HashToPrimeKind::Snark(_) => true,
+            HashToPrimeKind::Bulletproofs => false,
+        }
+    }
+
+    fn field_size_bits(&self) -> u16 {
+        match self {
+            // the scalar field of BLS12-381
+            HashToPrimeKind::Snark(_) => 255,
+            // the scalar field of Ristretto, as its `Field::size_in_bits`
+            HashToPrimeKind::Bulletproofs => 252,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct Constraints {
+    /// Largest acceptable proof, in bytes.
+    pub max_proof_bytes: Option<usize>,
+    /// Longest acceptable proving time, in milliseconds.
+    pub max_prover_ms: Option<u64>,
+    /// Rules out configurations with a trusted setup, of the group or of the
+    /// hash-to-prime backend.
+    pub trustless_setup: bool,
+    /// The elements aren't primes, so they have to be hashed to primes.
+    pub arbitrary_elements: bool,
+}
+
+/// A configuration ready to set up, with its estimated costs.
+#[derive(Clone, Debug)]
+pub struct Configuration {
+    pub group: GroupKind,
+    pub hash_to_prime: HashToPrimeKind,
+    /// The parameters to set up with, selecting the backend of
+    /// `DynHashToPrime` for the SNARK backends.
+    pub parameters: Parameters,
+    pub proof_bytes: usize,
+    pub prover_ms: u64,
+}
+
+struct Benchmark {
+    group: GroupKind,
+    hash_to_prime: HashToPrimeKind,
+    prover_ms: u64,
+}
+
+const BENCHMARKS: [Benchmark; 5] = [
+    Benchmark {
+        group: GroupKind::Rsa2048,
+        hash_to_prime: HashToPrimeKind::Snark(HashToPrimeBackend::SnarkRange),
+        prover_ms: 400,
+    },
+    Benchmark {
+        group: GroupKind::Rsa2048,
+        hash_to_prime: HashToPrimeKind::Bulletproofs,
+        prover_ms: 900,
+    },
+    Benchmark {
+        group: GroupKind::Rsa2048,
+        hash_to_prime: HashToPrimeKind::Snark(HashToPrimeBackend::SnarkHash),
+        prover_ms: 3_000,
+    },
+    Benchmark {
+        group: GroupKind::ClassGroup,
+        hash_to_prime: HashToPrimeKind::Snark(HashToPrimeBackend::SnarkRange),
+        prover_ms: 30_000,
+    },
+    Benchmark {
+        group: GroupKind::ClassGroup,
+        hash_to_prime: HashToPrimeKind::Bulletproofs,
+        prover_ms: 30_500,
+    },
+];
+
+/// The size of a membership proof over `G` with `hash_to_prime`, or `None` if
+/// its backend isn't compiled in. The LegoGroth16 proofs of the SNARK
+/// backends all have the same size, so they are estimated with `snark_range`.
+fn estimate_proof_bytes<G: ConvertibleUnknownOrderGroup>(
+    hash_to_prime: HashToPrimeKind,
+    parameters: &Parameters,
+) -> Option<usize> {
+    match hash_to_prime {
+        #[cfg(feature = "arkworks")]
+        HashToPrimeKind::Snark(_) => crate::protocols::sizes::estimate_sizes::<
+            G,
+            ark_bls12_381::G1Projective,
+            crate::protocols::hash_to_prime::snark_range::Protocol<ark_bls12_381::Bls12_381>,
+        >(parameters)
+        .ok()
+        .map(|sizes| sizes.proof_bytes),
+        #[cfg(feature = "dalek")]
+        HashToPrimeKind::Bulletproofs => crate::protocols::sizes::estimate_sizes::<
+            G,
+            curve25519_dalek::ristretto::RistrettoPoint,
+            crate::protocols::hash_to_prime::bp::Protocol,
+        >(parameters)
+        .ok()
+        .map(|sizes| sizes.proof_bytes),
+        #[allow(unreachable_patterns)]
+        _ => None,
+    }
+}
+
+impl Benchmark {
+    fn trustless(&self) -> bool {
+        self.group == GroupKind::ClassGroup && self.hash_to_prime == HashToPrimeKind::Bulletproofs
+    }
+
+    fn configuration(&self) -> Result<Option<Configuration>, StrategyError> {
+        let (mut parameters, _) =
+            Parameters::from_field_size_bits(self.hash_to_prime.field_size_bits())?;
+        if let HashToPrimeKind::Snark(backend) = self.hash_to_prime {
+            parameters = parameters.with_hash_to_prime_backend(backend);
+        }
+        let proof_bytes = match self.group {
+            GroupKind::Rsa2048 => estimate_proof_bytes::<Rsa2048>(self.hash_to_prime, &parameters),
+            GroupKind::ClassGroup => {
+                estimate_proof_bytes::<ClassGroup>(self.hash_to_prime, &parameters)
+            }
+        };
+        Ok(proof_bytes.map(|proof_bytes| Configuration {
+            group: self.group,
+            hash_to_prime: self.hash_to_prime,
+            parameters,
+            proof_bytes,
+            prover_ms: self.prover_ms,
+        }))
+    }
+}
+
+impl Configuration {
+    fn satisfies(&self, constraints: &Constraints, trustless: bool) -> bool {
+        constraints
+            .max_proof_bytes
+            .map_or(true, |max| self.proof_bytes <= max)
+            && constraints
+                .max_prover_ms
+                .map_or(true, |max| self.prover_ms <= max)
+            && (!constraints.trustless_setup || trustless)
+            && (!constraints.arbitrary_elements || self.hash_to_prime.hashes_elements())
+    }
+}
+
+pub struct Strategy;
+
+impl Strategy {
+    /// Selects the configuration with the fastest prover among those
+    /// satisfying `constraints`, failing with `StrategyError::NoConfiguration`
+    /// if there's none.
+    pub fn select(constraints: &Constraints) -> Result<Configuration, StrategyError> {
+        let mut selected: Option<Configuration> = None;
+        for benchmark in &BENCHMARKS {
+            let configuration = match benchmark.configuration()? {
+                Some(configuration) => configuration,
+                None => continue,
+            };
+            if !configuration.satisfies(constraints, benchmark.trustless()) {
+                continue;
+            }
+            let faster = selected.as_ref().map_or(true, |s| {
+                (configuration.prover_ms, configuration.proof_bytes) < (s.prover_ms, s.proof_bytes)
+            });
+            if faster {
+                selected = Some(configuration);
+            }
+        }
+        selected.ok_or(StrategyError::NoConfiguration)
+    }
+}
+
+#[cfg(all(test, feature = "arkworks"))]
+mod test {
+    use super::{Constraints, GroupKind, HashToPrimeKind, Strategy, StrategyError};
+    use crate::{
+        parameters::HashToPrimeBackend,
+        protocols::{hash_to_prime::snark_range::Protocol as HPProtocol, sizes::estimate_sizes},
+    };
+    use accumulator::group::Rsa2048;
+    use ark_bls12_381::{Bls12_381, G1Projective};
+
+    #[test]
+    fn test_select() {
+        let configuration = Strategy::select(&Constraints::default()).unwrap();
+        assert_eq!(configuration.group, GroupKind::Rsa2048);
+        assert_eq!(
+            configuration.hash_to_prime,
+            HashToPrimeKind::Snark(HashToPrimeBackend::SnarkRange)
+        );
+        assert_eq!(
+            configuration.parameters.hash_to_prime_backend,
+            Some(HashToPrimeBackend::SnarkRange)
+        );
+        configuration.parameters.is_valid().unwrap();
+        assert_eq!(
+            configuration.proof_bytes,
+            estimate_sizes::<Rsa2048, G1Projective, HPProtocol<Bls12_381>>(
+                &configuration.parameters
+            )
+            .unwrap()
+            .proof_bytes
+        );
+
+        let configuration = Strategy::select(&Constraints {
+            arbitrary_elements: true,
+            ..Constraints::default()
+        })
+        .unwrap();
+        assert!(configuration.hash_to_prime.hashes_elements());
+
+        let trustless = Strategy::select(&Constraints {
+            trustless_setup: true,
+            ..Constraints::default()
+        });
+        if cfg!(feature = "dalek") {
+            let configuration = trustless.unwrap();
+            assert_eq!(configuration.group, GroupKind::ClassGroup);
+            assert_eq!(configuration.hash_to_prime, HashToPrimeKind::Bulletproofs);
+            assert_eq!(configuration.parameters.hash_to_prime_backend, None);
+        } else {
+            // the only configuration with no trusted setup needs Bulletproofs
+            assert!(matches!(trustless, Err(StrategyError::NoConfiguration)));
+        }
+
+        for constraints in &[
+            Constraints {
+                trustless_setup: true,
+                arbitrary_elements: true,
+                ..Constraints::default()
+            },
+            Constraints {
+                trustless_setup: true,
+                max_prover_ms: Some(1_000),
+                ..Constraints::default()
+            },
+            Constraints {
+                max_proof_bytes: Some(1_000),
+                ..Constraints::default()
+            },
+        ] {
+            assert!(matches!(
+                Strategy::select(constraints),
+                Err(StrategyError::NoConfiguration)
+            ));
+        }
+    }
+}
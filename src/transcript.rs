@@ -3,19 +3,28 @@
 //!
 //! Each protocol defines a transcript that defines a domain separator, how to
 //! consume each message in the protocol and how to generate challenge scalars.
-use crate::{
-    protocols::{
-        hash_to_prime::transcript::TranscriptProtocolHashToPrime,
-        modeq::transcript::TranscriptProtocolModEq, root::transcript::TranscriptProtocolRoot,
-    },
-    utils::{
-        bigint_to_bytes,
-        curve::{CurveError, CurvePointProjective},
-        integer_to_bytes, ConvertibleUnknownOrderGroup,
-    },
+//! These live next to the protocol, in `protocols::<protocol>::transcript`,
+//! along with its `TranscriptProverChannel` and `TranscriptVerifierChannel`,
+//! and a new protocol adds exactly one such module. This module only holds
+//! what they share: absorbing integers and curve points, challenges, witness
+//! randomness, domain separator versions and the channel errors.
+//!
+//! Integers are absorbed with an `IntegerEncoding`. The default is a sign
+//! byte followed by the big-endian magnitude, so that `x` and `-x` are
+//! absorbed differently; `IntegerEncoding::Canonical` with a bit length fixes
+//! the width of the magnitude as well, so that an implementation stripping
+//! leading zeros differently derives the same challenges.
+//! `IntegerEncoding::Legacy`, the minimal-length magnitude without the sign,
+//! is kept for compatibility with transcripts of earlier releases.
+use crate::utils::{
+    bigint_to_bytes, bytes_to_integer,
+    curve::{CurveError, CurvePointProjective},
+    integer_to_bytes, integer_to_signed_bytes, ConvertibleUnknownOrderGroup,
 };
-use merlin::Transcript;
+use merlin::{Transcript, TranscriptRng};
+use rand::{CryptoRng, RngCore};
 use rug::integer::Order;
+use rug::rand::RandGen;
 use rug::Integer;
 
 quick_error! {
@@ -25,21 +34,296 @@ quick_error! {
     }
 }
 
-pub trait TranscriptProtocolMembershipPrime<
-    G: ConvertibleUnknownOrderGroup,
-    P: CurvePointProjective,
->:
-    TranscriptProtocolRoot<G> + TranscriptProtocolModEq<G, P> + TranscriptProtocolHashToPrime<P>
-{
+quick_error! {
+    #[derive(Debug)]
+    pub enum VerifierChallengeError {
+        InvalidLength(len: usize) {
+            display("verifier challenge has {} bytes, 16 to 32 are accepted", len)
+        }
+    }
+}
+
+quick_error! {
+    #[derive(Debug)]
+    pub enum IntegerEncodingError {
+        TooWide(bits: u32, width: u32) {
+            display("integer has {} bits, encoding holds {}", bits, width)
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ByteOrder {
+    BigEndian,
+    LittleEndian,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum IntegerEncoding {
+    /// The big-endian magnitude with leading zeros stripped. Drops the sign.
+    Legacy,
+    /// A sign byte, 0 for non-negative and 1 for negative integers, followed
+    /// by the magnitude in `order`. With `bits` set, the magnitude is padded
+    /// to `bits` rounded up to whole bytes and wider integers are rejected,
+    /// otherwise leading zeros are stripped.
+    Canonical { bits: Option<u32>, order: ByteOrder },
+}
+
+impl Default for IntegerEncoding {
+    fn default() -> Self {
+        IntegerEncoding::Canonical {
+            bits: None,
+            order: ByteOrder::BigEndian,
+        }
+    }
+}
+
+impl IntegerEncoding {
+    pub fn encode(&self, value: &Integer) -> Result<Vec<u8>, IntegerEncodingError> {
+        let (bits, order) = match *self {
+            IntegerEncoding::Legacy => return Ok(integer_to_bytes(value)),
+            IntegerEncoding::Canonical { bits, order } => (bits, order),
+        };
+        let width = match bits {
+            Some(bits) => {
+                if value.significant_bits() > bits {
+                    return Err(IntegerEncodingError::TooWide(
+                        value.significant_bits(),
+                        bits,
+                    ));
+                }
+                (bits as usize + 7) / 8
+            }
+            None => value.significant_digits::<u8>(),
+        };
+        let mut bytes = vec![0u8; 1 + width];
+        bytes[0] = (*value < 0) as u8;
+        // writes the magnitude, padded with zeros
+        let order = match order {
+            ByteOrder::BigEndian => Order::MsfBe,
+            ByteOrder::LittleEndian => Order::LsfLe,
+        };
+        value.write_digits(&mut bytes[1..], order);
+        Ok(bytes)
+    }
+}
+
+quick_error! {
+    #[derive(Debug)]
+    pub enum GroupElementEncodingError {
+        NotRsa {}
+        TooWide(bits: u32, width: u32) {
+            display("element has {} bits, encoding holds {}", bits, width)
+        }
+    }
+}
+
+/// Encodings of group elements, for matching the values stored by other
+/// stacks, e.g. accumulators in smart contracts. All but `Native` only apply
+/// to RSA groups, whose elements are integers.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum GroupElementEncoding {
+    /// `G::elem_to_bytes`, as absorbed by the transcripts of the protocols.
+    Native,
+    /// The big-endian integer with leading zeros stripped.
+    Minimal,
+    /// The big-endian integer padded to `bytes`, e.g. 256 for RSA-2048.
+    FixedBigEndian { bytes: usize },
+    /// `limbs` 32-byte words, each big-endian as an EVM `uint256`, ordered by
+    /// `order`: `BigEndian` puts the most significant word first.
+    Limbs { limbs: usize, order: ByteOrder },
+}
+
+impl Default for GroupElementEncoding {
+    fn default() -> Self {
+        GroupElementEncoding::Native
+    }
+}
+
+impl GroupElementEncoding {
+    pub fn encode<G: ConvertibleUnknownOrderGroup>(
+        &self,
+        element: &G::Elem,
+    ) -> Result<Vec<u8>, GroupElementEncodingError> {
+        if *self == GroupElementEncoding::Native {
+            return Ok(G::elem_to_bytes(element));
+        }
+        if G::rsa_modulus().is_err() {
+            return Err(GroupElementEncodingError::NotRsa);
+        }
+        let value = bytes_to_integer(&G::elem_to_bytes(element));
+        let width = match *self {
+            GroupElementEncoding::Native => unreachable!(),
+            GroupElementEncoding::Minimal => return Ok(integer_to_bytes(&value)),
+            GroupElementEncoding::FixedBigEndian { bytes } => bytes,
+            GroupElementEncoding::Limbs { limbs, .. } => 32 * limbs,
+        };
+        if value.significant_bits() as usize > 8 * width {
+            return Err(GroupElementEncodingError::TooWide(
+                value.significant_bits(),
+                8 * width as u32,
+            ));
+        }
+        let mut bytes = vec![0u8; width];
+        value.write_digits(&mut bytes, Order::MsfBe);
+        if let GroupElementEncoding::Limbs {
+            order: ByteOrder::LittleEndian,
+            ..
+        } = *self
+        {
+            bytes = bytes.chunks(32).rev().flatten().cloned().collect();
+        }
+        Ok(bytes)
+    }
+}
+
+/// Version of the transcript rules and of the proof formats. It is part of
+/// every domain separator, so bumping it, e.g. when fixing a soundness bug,
+/// makes proofs created under the old rules fail verification.
+pub const PROTOCOL_VERSION: u8 = 2;
+
+/// The domain separator of `protocol`, followed by `PROTOCOL_VERSION`.
+pub fn versioned_domain_sep(protocol: &[u8]) -> Vec<u8> {
+    let mut domain_sep = protocol.to_vec();
+    domain_sep.push(PROTOCOL_VERSION);
+    domain_sep
+}
+
+/// Creates the transcript of a non-interactive proof, bound to the associated
+/// data `aad`, e.g. a session identifier, a verifier nonce or a chain ID.
+/// Empty associated data leaves the transcript as created by
+/// `Transcript::new`.
+pub fn transcript_with_aad(label: &'static [u8], aad: &[u8]) -> Transcript {
+    let mut transcript = Transcript::new(label);
+    if !aad.is_empty() {
+        transcript.append_message(b"aad", aad);
+    }
+    transcript
+}
+
+/// The accepted lengths of a `VerifierChallenge`, in bytes.
+pub const MIN_VERIFIER_CHALLENGE_BYTES: usize = 16;
+pub const MAX_VERIFIER_CHALLENGE_BYTES: usize = 32;
+
+/// Fresh randomness an online verifier hands the prover before the proof is
+/// created, e.g. out of band. It is absorbed before any challenge is derived,
+/// so a proof bound to it can't have been prepared in advance or replayed to
+/// another verifier, without the rounds of an interactive proof.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct VerifierChallenge(Vec<u8>);
+
+impl VerifierChallenge {
+    pub fn new(bytes: &[u8]) -> Result<VerifierChallenge, VerifierChallengeError> {
+        if bytes.len() < MIN_VERIFIER_CHALLENGE_BYTES || bytes.len() > MAX_VERIFIER_CHALLENGE_BYTES
+        {
+            return Err(VerifierChallengeError::InvalidLength(bytes.len()));
+        }
+        Ok(VerifierChallenge(bytes.to_vec()))
+    }
+
+    /// Samples a challenge of `MAX_VERIFIER_CHALLENGE_BYTES` bytes.
+    pub fn generate<R: RngCore + CryptoRng>(rng: &mut R) -> VerifierChallenge {
+        let mut bytes = vec![0u8; MAX_VERIFIER_CHALLENGE_BYTES];
+        rng.fill_bytes(&mut bytes);
+        VerifierChallenge(bytes)
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+pub trait TranscriptProtocolVerifierChallenge {
+    fn append_verifier_challenge(&mut self, challenge: &VerifierChallenge);
+}
+
+impl TranscriptProtocolVerifierChallenge for Transcript {
+    fn append_verifier_challenge(&mut self, challenge: &VerifierChallenge) {
+        self.append_message(b"verifier-challenge", challenge.as_bytes());
+    }
+}
+
+/// `transcript_with_aad`, followed by the verifier's `challenge`.
+pub fn transcript_with_verifier_challenge(
+    label: &'static [u8],
+    aad: &[u8],
+    challenge: &VerifierChallenge,
+) -> Transcript {
+    let mut transcript = transcript_with_aad(label, aad);
+    transcript.append_verifier_challenge(challenge);
+    transcript
 }
 
+/// Moved to `protocols::membership::transcript`, re-exported for the code
+/// importing it from here.
+#[doc(hidden)]
+pub use crate::protocols::membership::transcript::TranscriptProtocolMembershipPrime;
+
 pub trait TranscriptProtocolChallenge {
     fn challenge_scalar(&mut self, label: &'static [u8], length_in_bits: u16) -> Integer;
 }
 
+/// Derives prover randomness bound to the transcript state and the witness.
+pub trait TranscriptProtocolWitnessRng {
+    /// Rekeys the transcript RNG with `label` and `witness`, and finalizes it
+    /// with `rng`. Blinding values derived this way don't repeat across
+    /// transcripts or witnesses, even if `rng` does.
+    fn witness_rng<R: RngCore + CryptoRng>(
+        &self,
+        label: &'static [u8],
+        witness: &[&[u8]],
+        rng: &mut R,
+    ) -> WitnessRng;
+}
+
+/// A `merlin::TranscriptRng`, which can also be used for the integer
+/// randomness through `RandState::new_custom`.
+pub struct WitnessRng {
+    rng: TranscriptRng,
+}
+
+impl RngCore for WitnessRng {
+    fn next_u32(&mut self) -> u32 {
+        self.rng.next_u32()
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        self.rng.next_u64()
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        self.rng.fill_bytes(dest)
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), rand::Error> {
+        self.rng.try_fill_bytes(dest)
+    }
+}
+
+impl CryptoRng for WitnessRng {}
+
+impl RandGen for WitnessRng {
+    fn gen(&mut self) -> u32 {
+        self.rng.next_u32()
+    }
+}
+
 pub trait TranscriptProtocolInteger<G: ConvertibleUnknownOrderGroup> {
+    /// Absorbs `scalar` with `IntegerEncoding::default()`.
     fn append_integer_scalar(&mut self, label: &'static [u8], scalar: &Integer);
+    fn append_integer_scalar_with_encoding(
+        &mut self,
+        label: &'static [u8],
+        scalar: &Integer,
+        encoding: &IntegerEncoding,
+    ) -> Result<(), IntegerEncodingError>;
     fn append_integer_point(&mut self, label: &'static [u8], point: &G::Elem);
+    fn append_integer_point_with_encoding(
+        &mut self,
+        label: &'static [u8],
+        point: &G::Elem,
+        encoding: &GroupElementEncoding,
+    ) -> Result<(), GroupElementEncodingError>;
 }
 
 pub trait TranscriptProtocolCurve<P: CurvePointProjective> {
@@ -49,12 +333,33 @@ pub trait TranscriptProtocolCurve<P: CurvePointProjective> {
 
 impl<G: ConvertibleUnknownOrderGroup> TranscriptProtocolInteger<G> for Transcript {
     fn append_integer_scalar(&mut self, label: &'static [u8], scalar: &Integer) {
-        self.append_message(label, &integer_to_bytes(scalar));
+        // same as `IntegerEncoding::default()`
+        self.append_message(label, &integer_to_signed_bytes(scalar));
+    }
+
+    fn append_integer_scalar_with_encoding(
+        &mut self,
+        label: &'static [u8],
+        scalar: &Integer,
+        encoding: &IntegerEncoding,
+    ) -> Result<(), IntegerEncodingError> {
+        self.append_message(label, &encoding.encode(scalar)?);
+        Ok(())
     }
 
     fn append_integer_point(&mut self, label: &'static [u8], point: &G::Elem) {
         self.append_message(label, &G::elem_to_bytes(point));
     }
+
+    fn append_integer_point_with_encoding(
+        &mut self,
+        label: &'static [u8],
+        point: &G::Elem,
+        encoding: &GroupElementEncoding,
+    ) -> Result<(), GroupElementEncodingError> {
+        self.append_message(label, &encoding.encode::<G>(point)?);
+        Ok(())
+    }
 }
 
 impl<P: CurvePointProjective> TranscriptProtocolCurve<P> for Transcript {
@@ -69,6 +374,23 @@ impl<P: CurvePointProjective> TranscriptProtocolCurve<P> for Transcript {
     }
 }
 
+impl TranscriptProtocolWitnessRng for Transcript {
+    fn witness_rng<R: RngCore + CryptoRng>(
+        &self,
+        label: &'static [u8],
+        witness: &[&[u8]],
+        rng: &mut R,
+    ) -> WitnessRng {
+        let mut builder = self.build_rng().rekey_with_witness_bytes(b"rng", label);
+        for bytes in witness {
+            builder = builder.rekey_with_witness_bytes(b"witness", bytes);
+        }
+        WitnessRng {
+            rng: builder.finalize(rng),
+        }
+    }
+}
+
 impl TranscriptProtocolChallenge for Transcript {
     fn challenge_scalar(&mut self, label: &'static [u8], length_in_bits: u16) -> Integer {
         let mut buf = vec![0u8; (length_in_bits / 8) as usize];
@@ -76,3 +398,97 @@ impl TranscriptProtocolChallenge for Transcript {
         Integer::from_digits(&buf[..], Order::MsfBe)
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::{
+        ByteOrder, GroupElementEncoding, GroupElementEncodingError, IntegerEncoding,
+        IntegerEncodingError,
+    };
+    use accumulator::group::{ClassGroup, Group, Rsa2048, UnknownOrderGroup};
+    use rug::Integer;
+
+    #[test]
+    fn test_integer_encoding() {
+        let value = Integer::from(0x0102);
+        let negative = Integer::from(-0x0102);
+
+        assert_eq!(IntegerEncoding::Legacy.encode(&value).unwrap(), vec![1, 2]);
+        // the legacy encoding drops the sign
+        assert_eq!(
+            IntegerEncoding::Legacy.encode(&negative).unwrap(),
+            vec![1, 2]
+        );
+
+        let default = IntegerEncoding::default();
+        assert_eq!(default.encode(&value).unwrap(), vec![0, 1, 2]);
+        assert_eq!(default.encode(&negative).unwrap(), vec![1, 1, 2]);
+        assert_eq!(default.encode(&Integer::from(0)).unwrap(), vec![0]);
+
+        let big_endian = IntegerEncoding::Canonical {
+            bits: Some(32),
+            order: ByteOrder::BigEndian,
+        };
+        let little_endian = IntegerEncoding::Canonical {
+            bits: Some(20),
+            order: ByteOrder::LittleEndian,
+        };
+        assert_eq!(big_endian.encode(&value).unwrap(), vec![0, 0, 0, 1, 2]);
+        assert_eq!(big_endian.encode(&negative).unwrap(), vec![1, 0, 0, 1, 2]);
+        assert_eq!(little_endian.encode(&value).unwrap(), vec![0, 2, 1, 0]);
+        assert!(matches!(
+            little_endian.encode(&Integer::from(1 << 20)),
+            Err(IntegerEncodingError::TooWide(21, 20))
+        ));
+    }
+
+    #[test]
+    fn test_group_element_encoding() {
+        let element = Rsa2048::id();
+
+        assert_eq!(
+            GroupElementEncoding::Minimal
+                .encode::<Rsa2048>(&element)
+                .unwrap(),
+            vec![1]
+        );
+        let fixed = GroupElementEncoding::FixedBigEndian { bytes: 256 }
+            .encode::<Rsa2048>(&element)
+            .unwrap();
+        assert_eq!(fixed.len(), 256);
+        assert_eq!(&fixed[255..], &[1]);
+        assert!(matches!(
+            GroupElementEncoding::FixedBigEndian { bytes: 0 }.encode::<Rsa2048>(&element),
+            Err(GroupElementEncodingError::TooWide(1, 0))
+        ));
+
+        let limbs = GroupElementEncoding::Limbs {
+            limbs: 2,
+            order: ByteOrder::LittleEndian,
+        }
+        .encode::<Rsa2048>(&element)
+        .unwrap();
+        assert_eq!(limbs.len(), 64);
+        assert_eq!(limbs[31], 1);
+        assert!(limbs[32..].iter().all(|b| *b == 0));
+        let limbs = GroupElementEncoding::Limbs {
+            limbs: 2,
+            order: ByteOrder::BigEndian,
+        }
+        .encode::<Rsa2048>(&element)
+        .unwrap();
+        assert_eq!(limbs[63], 1);
+    }
+
+    #[test]
+    fn test_group_element_encoding_class_group() {
+        let element = ClassGroup::unknown_order_elem();
+        assert!(GroupElementEncoding::Native
+            .encode::<ClassGroup>(&element)
+            .is_ok());
+        assert!(matches!(
+            GroupElementEncoding::Minimal.encode::<ClassGroup>(&element),
+            Err(GroupElementEncodingError::NotRsa)
+        ));
+    }
+}
@@ -17,6 +17,7 @@ where
     fn modulus() -> Integer;
     fn size_in_bits() -> usize;
     fn to_bits(&self) -> Vec<bool>;
+    /// Reduces the big-endian `bits`, of any length, modulo the field order.
     fn from_bits(bits: &[bool]) -> Self;
     fn add(&self, other: &Self) -> Self;
     fn sub(&self, other: &Self) -> Self;
@@ -36,14 +37,32 @@ where
     fn add(&self, other: &Self) -> Self;
 
     fn to_affine_bytes(&self) -> Result<Vec<u8>, CurveError>;
+    /// Length of `to_affine_bytes`, which is the same for every point.
+    fn affine_bytes_len() -> usize;
     fn rand<R: RngCore + CryptoRng>(rng: &mut R) -> Self;
 }
 
+/// Encodings of `utils::bigint_to_bytes` shared by the scalar fields of
+/// BLS12-381 and Ristretto, both 32 bytes long.
+#[cfg(all(test, any(feature = "arkworks", feature = "dalek")))]
+pub(crate) fn scalar_test_vectors() -> Vec<(Integer, Vec<u8>)> {
+    let mut ten = vec![0u8; 32];
+    ten[31] = 10;
+    let mut large = vec![0u8; 32];
+    large[23] = 1;
+    large[31] = 5;
+    vec![
+        (Integer::from(0), vec![0u8; 32]),
+        (Integer::from(10), ten),
+        (Integer::from(Integer::u_pow_u(2, 64)) + 5, large),
+    ]
+}
+
 #[cfg(feature = "arkworks")]
 mod arkworks {
     use super::{CurvePointProjective, Field};
     use crate::utils::{bits_big_endian_to_bytes_big_endian, bytes_to_integer, curve::CurveError};
-    use ark_ec::ProjectiveCurve;
+    use ark_ec::{AffineCurve, ProjectiveCurve};
     use ark_ff::{BigInteger, FpParameters, PrimeField};
     use ark_serialize::{CanonicalSerialize, SerializationError};
 
@@ -70,7 +89,7 @@ mod arkworks {
             self.into_repr().to_bits_be()
         }
         fn from_bits(bits: &[bool]) -> Self {
-            F::from(F::BigInt::from_bits_be(bits))
+            F::from_be_bytes_mod_order(&bits_big_endian_to_bytes_big_endian(bits))
         }
         fn add(&self, other: &Self) -> Self {
             F::add(*self, *other)
@@ -110,6 +129,10 @@ mod arkworks {
             Ok(bytes)
         }
 
+        fn affine_bytes_len() -> usize {
+            P::Affine::prime_subgroup_generator().serialized_size()
+        }
+
         fn rand<R: RngCore + CryptoRng>(rng: &mut R) -> Self {
             P::rand(rng)
         }
@@ -120,16 +143,17 @@ mod arkworks {
 mod dalek {
     use super::{CurvePointProjective, Field};
     use crate::utils::{
-        bigint_to_integer, bits_big_endian_to_bytes_big_endian,
-        bytes_big_endian_to_bits_big_endian, curve::CurveError,
+        bits_big_endian_to_bytes_big_endian, bytes_big_endian_to_bits_big_endian, bytes_to_integer,
+        curve::CurveError,
     };
     use curve25519_dalek::{constants::BASEPOINT_ORDER, ristretto::RistrettoPoint, scalar::Scalar};
     use rand::{CryptoRng, RngCore};
-    use rug::Integer;
+    use rug::{integer::Order, Integer};
 
     impl Field for Scalar {
         fn modulus() -> Integer {
-            bigint_to_integer::<RistrettoPoint>(&BASEPOINT_ORDER)
+            // not through `bigint_to_integer`, which reduces modulo the order
+            Integer::from_digits(BASEPOINT_ORDER.as_bytes(), Order::Lsf)
         }
 
         fn size_in_bits() -> usize {
@@ -146,24 +170,10 @@ mod dalek {
         }
         fn from_bits(bits: &[bool]) -> Self {
             let big_endian_bytes = bits_big_endian_to_bytes_big_endian(&bits);
-            let little_endian_bytes = big_endian_bytes
-                .to_vec()
-                .into_iter()
-                .rev()
-                .collect::<Vec<_>>();
-            let little_endian_bytes_length = little_endian_bytes.len();
-            let little_endian_bytes_padded = if little_endian_bytes_length < 32 {
-                [
-                    little_endian_bytes,
-                    vec![0u8; 32 - little_endian_bytes_length],
-                ]
-                .concat()
-            } else {
-                little_endian_bytes
-            };
-            let mut little_endian_fixed_bytes = [0u8; 32];
-            little_endian_fixed_bytes[..].copy_from_slice(little_endian_bytes_padded.as_ref());
-            Scalar::from_bits(little_endian_fixed_bytes)
+            let reduced = bytes_to_integer(&big_endian_bytes) % Self::modulus();
+            let mut little_endian_bytes = [0u8; 32];
+            reduced.write_digits(&mut little_endian_bytes, Order::Lsf);
+            Scalar::from_bits(little_endian_bytes)
         }
         fn add(&self, other: &Self) -> Self {
             self + other
@@ -202,6 +212,9 @@ mod dalek {
         fn to_affine_bytes(&self) -> Result<Vec<u8>, CurveError> {
             Ok(self.compress().to_bytes()[..].to_vec())
         }
+        fn affine_bytes_len() -> usize {
+            32
+        }
         fn rand<R: RngCore + CryptoRng>(rng: &mut R) -> Self {
             RistrettoPoint::random(rng)
         }
@@ -210,7 +223,11 @@ mod dalek {
     #[cfg(test)]
     mod test {
         use super::Field;
-        use curve25519_dalek::scalar::Scalar;
+        use crate::utils::{
+            bigint_to_bytes, bigint_to_integer, curve::scalar_test_vectors, integer_to_bigint,
+            scalar_byte_length,
+        };
+        use curve25519_dalek::{ristretto::RistrettoPoint, scalar::Scalar};
         #[test]
         fn test_to_from_bits() {
             let s = Scalar::from(10 as u64);
@@ -218,5 +235,25 @@ mod dalek {
             let s2 = <Scalar as Field>::from_bits(&bits);
             assert_eq!(s, s2);
         }
+
+        #[test]
+        fn test_scalar_encoding() {
+            assert_eq!(scalar_byte_length::<RistrettoPoint>(), 32);
+            for (value, bytes) in scalar_test_vectors() {
+                let scalar = integer_to_bigint::<RistrettoPoint>(&value);
+                assert_eq!(bigint_to_bytes::<RistrettoPoint>(&scalar), bytes);
+            }
+            // an unreduced scalar is encoded as its reduced value
+            let mut unreduced_bytes = [0xffu8; 32];
+            unreduced_bytes[31] = 0x7f;
+            let unreduced = Scalar::from_bits(unreduced_bytes);
+            let reduced = unreduced.reduce();
+            assert_ne!(unreduced.to_bytes(), reduced.to_bytes());
+            assert_eq!(
+                bigint_to_bytes::<RistrettoPoint>(&unreduced),
+                bigint_to_bytes::<RistrettoPoint>(&reduced)
+            );
+            assert!(bigint_to_integer::<RistrettoPoint>(&unreduced) < <Scalar as Field>::modulus());
+        }
     }
 }
@@ -0,0 +1,57 @@
+//! Hashing to elements of groups of unknown order, for bases whose discrete
+//! logarithms nobody knows, e.g. the nullifier bases of the epochs in
+//! `roles`.
+//!
+//! For RSA groups, the message is expanded with a merlin transcript to 128
+//! bits more than the modulus, reduced modulo the modulus and squared, so that
+//! the element is a quadratic residue. Class groups aren't supported, as there
+//! is no hash to their forms in the `accumulator` crate.
+use crate::utils::{bytes_to_integer, modulus::RsaModulus, ConvertibleUnknownOrderGroup};
+use accumulator::group::{ElemFrom, Group, Rsa2048};
+use merlin::Transcript;
+use rug::Integer;
+
+pub trait HashToGroup: ConvertibleUnknownOrderGroup {
+    /// Hashes `message` to an element, with `label` separating the uses.
+    fn hash_to_elem(label: &'static [u8], message: &[u8]) -> Self::Elem;
+}
+
+/// Hashes `message` to an integer below `modulus`, with a negligible bias.
+fn hash_to_integer_mod(modulus: &Integer, label: &'static [u8], message: &[u8]) -> Integer {
+    let mut transcript = Transcript::new(label);
+    transcript.append_message(b"message", message);
+    let mut bytes = vec![0u8; (modulus.significant_bits() as usize + 128 + 7) / 8];
+    transcript.challenge_bytes(b"elem", &mut bytes);
+    bytes_to_integer(&bytes) % modulus
+}
+
+impl HashToGroup for Rsa2048 {
+    fn hash_to_elem(label: &'static [u8], message: &[u8]) -> Self::Elem {
+        // `Rsa2048` is the group of the RSA-2048 challenge number.
+        let modulus = RsaModulus::rsa_2048_challenge().modulus;
+        let x = hash_to_integer_mod(&modulus, label, message);
+        Self::exp(&Self::elem(x), &Integer::from(2))
+    }
+}
+
+#[cfg(any(test, feature = "insecure-trapdoor-group"))]
+impl HashToGroup for super::trapdoor::TrapdoorRsa512 {
+    fn hash_to_elem(label: &'static [u8], message: &[u8]) -> Self::Elem {
+        let x = hash_to_integer_mod(&Self::modulus(), label, message);
+        Self::exp(&Self::elem(&x), &Integer::from(2))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::HashToGroup;
+    use crate::utils::trapdoor::TrapdoorRsa512;
+
+    #[test]
+    fn test_hash_to_group() {
+        let a = TrapdoorRsa512::hash_to_elem(b"test", b"a");
+        assert_eq!(a, TrapdoorRsa512::hash_to_elem(b"test", b"a"));
+        assert_ne!(a, TrapdoorRsa512::hash_to_elem(b"test", b"b"));
+        assert_ne!(a, TrapdoorRsa512::hash_to_elem(b"other", b"a"));
+    }
+}
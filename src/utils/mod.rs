@@ -1,12 +1,29 @@
 use accumulator::group::{ElemToBytes, UnknownOrderGroup};
 use rug::integer::Order;
 use rug::rand::MutRandState;
-use rug::Integer;
+use rug::{Assign, Integer};
 
 pub mod curve;
+pub mod hash_to_group;
+pub mod modulus;
+pub mod rng;
+pub mod secret;
+#[cfg(any(test, feature = "insecure-trapdoor-group"))]
+pub mod trapdoor;
 use curve::{CurvePointProjective, Field};
 
-pub trait ConvertibleUnknownOrderGroup: UnknownOrderGroup + ElemToBytes {}
+pub trait ConvertibleUnknownOrderGroup: UnknownOrderGroup + ElemToBytes {
+    /// An upper bound for the challenge space of the sigma protocols over this
+    /// group. For RSA groups, challenges must stay below the smallest prime
+    /// factor of the modulus, and for class groups below the order bound
+    /// derived from the discriminant.
+    fn soundness_bound() -> Integer {
+        match Self::rsa_modulus() {
+            Ok(modulus) => modulus.sqrt() / 2,
+            Err(_) => Self::order_upper_bound(),
+        }
+    }
+}
 impl<T: UnknownOrderGroup + ElemToBytes> ConvertibleUnknownOrderGroup for T {}
 
 pub fn random_between<R: MutRandState>(rng: &mut R, min: &Integer, max: &Integer) -> Integer {
@@ -17,6 +34,44 @@ pub fn random_symmetric_range<R: MutRandState>(rng: &mut R, max: &Integer) -> In
     Integer::from(-max) + Integer::from(2 * max).random_below(rng)
 }
 
+/// `base^exp` for an exponent of either sign, inverting `base^|exp|` for
+/// negative ones instead of relying on how the group backend treats them.
+pub fn exp_signed<G: ConvertibleUnknownOrderGroup>(base: &G::Elem, exp: &Integer) -> G::Elem {
+    if *exp < 0 {
+        G::inv(&G::exp(base, &Integer::from(-exp)))
+    } else {
+        G::exp(base, exp)
+    }
+}
+
+/// Buffer for the absolute value of negative exponents in `exp_signed_with`,
+/// kept across calls so that its limbs are only allocated once.
+#[derive(Default)]
+pub struct ExpScratch {
+    abs: Integer,
+}
+
+impl ExpScratch {
+    pub fn new() -> ExpScratch {
+        ExpScratch::default()
+    }
+}
+
+/// Same as `exp_signed`, but negating the exponent into `scratch` rather than
+/// a fresh integer.
+pub fn exp_signed_with<G: ConvertibleUnknownOrderGroup>(
+    scratch: &mut ExpScratch,
+    base: &G::Elem,
+    exp: &Integer,
+) -> G::Elem {
+    if *exp < 0 {
+        scratch.abs.assign(exp.abs_ref());
+        G::inv(&G::exp(base, &scratch.abs))
+    } else {
+        G::exp(base, exp)
+    }
+}
+
 pub fn bytes_big_endian_to_bits_big_endian(bytes: &[u8]) -> Vec<bool> {
     let mut bits = vec![];
     for b in bytes {
@@ -29,19 +84,24 @@ pub fn bytes_big_endian_to_bits_big_endian(bytes: &[u8]) -> Vec<bool> {
     bits
 }
 
+/// Packs big-endian bits into bytes, padding with leading zeros when their
+/// number isn't a multiple of 8.
 pub fn bits_big_endian_to_bytes_big_endian(bits: &[bool]) -> Vec<u8> {
-    let byte_length = (bits.len() + 7) / 8;
-    let mut bytes = vec![];
-    for b in 0..byte_length {
-        let mut byte = 0 as u8;
-        for i in 0..8 {
-            byte |= (bits[8 * b + i] as u8) << (7 - i);
-        }
-        bytes.push(byte);
-    }
-    bytes
+    let padding = (8 - bits.len() % 8) % 8;
+    let padded = [&vec![false; padding][..], bits].concat();
+    padded
+        .chunks(8)
+        .map(|chunk| {
+            chunk
+                .iter()
+                .fold(0u8, |byte, bit| (byte << 1) | (*bit as u8))
+        })
+        .collect()
 }
 
+/// Big-endian bytes of the absolute value of `num`. Use
+/// `integer_to_signed_bytes` for values that can be negative, such as the
+/// responses of the sigma protocols.
 pub fn integer_to_bytes(num: &Integer) -> Vec<u8> {
     let digits = num.significant_digits::<u8>();
     let mut bytes = vec![0u8; digits];
@@ -49,8 +109,34 @@ pub fn integer_to_bytes(num: &Integer) -> Vec<u8> {
     bytes
 }
 
+/// A sign byte, 0 for non-negative and 1 for negative values, followed by
+/// `integer_to_bytes(num)`.
+pub fn integer_to_signed_bytes(num: &Integer) -> Vec<u8> {
+    let mut bytes = vec![(*num < 0) as u8];
+    bytes.extend_from_slice(&integer_to_bytes(num));
+    bytes
+}
+
+/// Inverse of `integer_to_signed_bytes`. Returns `None` for an invalid sign
+/// byte and for a negative zero, so that every integer has one encoding.
+pub fn signed_bytes_to_integer(bytes: &[u8]) -> Option<Integer> {
+    let (sign, magnitude) = bytes.split_first()?;
+    let magnitude = bytes_to_integer(magnitude);
+    match sign {
+        0 => Some(magnitude),
+        1 if magnitude != 0 => Some(-magnitude),
+        _ => None,
+    }
+}
+
 pub fn integer_to_bigint<P: CurvePointProjective>(num: &Integer) -> P::ScalarField {
-    let bytes = integer_to_bytes(num);
+    // the byte encoding drops the sign, so reduce into the field first
+    let q = P::ScalarField::modulus();
+    let mut reduced = Integer::from(num % &q);
+    if reduced < 0 {
+        reduced += &q;
+    }
+    let bytes = integer_to_bytes(&reduced);
     let bits = bytes_big_endian_to_bits_big_endian(&bytes);
     P::ScalarField::from_bits(&bits)
 }
@@ -68,9 +154,23 @@ pub fn integer_to_bigint_mod_q<P: CurvePointProjective>(
     Ok(P::ScalarField::from_bits(&bits))
 }
 
+/// The length of `bigint_to_bytes` for the scalar field of `P`, the number of
+/// bytes of its modulus.
+pub fn scalar_byte_length<P: CurvePointProjective>() -> usize {
+    (P::ScalarField::modulus().significant_bits() as usize + 7) / 8
+}
+
+/// Canonical encoding of a scalar: the big-endian bytes of its reduced value,
+/// padded to `scalar_byte_length`. It doesn't depend on the number of bits
+/// `to_bits` returns for a backend, nor on whether the backend keeps the
+/// scalar reduced, so the same value is encoded the same way by every curve
+/// with a scalar field of the same size.
 pub fn bigint_to_bytes<P: CurvePointProjective>(num: &P::ScalarField) -> Vec<u8> {
-    let bits = num.to_bits();
-    bits_big_endian_to_bytes_big_endian(&bits)
+    let value = bytes_to_integer(&bits_big_endian_to_bytes_big_endian(&num.to_bits()))
+        % P::ScalarField::modulus();
+    let mut bytes = vec![0u8; scalar_byte_length::<P>()];
+    value.write_digits(&mut bytes, Order::MsfBe);
+    bytes
 }
 
 pub fn bytes_to_integer(bytes: &[u8]) -> Integer {
@@ -86,6 +186,36 @@ pub fn bigint_to_integer<P: CurvePointProjective>(num: &P::ScalarField) -> Integ
     big
 }
 
+quick_error! {
+    #[derive(Debug)]
+    pub enum BezoutError {
+        NotCoprime {}
+        InvalidAccumulator {}
+    }
+}
+
+/// Computes a nonmembership witness `(d, b)` for `e`, such that
+/// `d^e * acc^b = g`, where `acc = g^set_product`. The coefficient satisfies
+/// `|b| < e`, as `coprime::Witness` expects.
+pub fn bezout_witness<G: ConvertibleUnknownOrderGroup>(
+    set_product: &Integer,
+    e: &Integer,
+    g: &G::Elem,
+    acc: &G::Elem,
+) -> Result<(G::Elem, Integer), BezoutError> {
+    // a * e + b * set_product = 1, with |b| <= e / 2
+    let (gcd, a, b) = <(Integer, Integer, Integer)>::from(e.gcd_cofactors_ref(set_product));
+    if gcd != 1 {
+        return Err(BezoutError::NotCoprime);
+    }
+    let d = exp_signed::<G>(g, &a);
+    if G::op(&exp_signed::<G>(&d, e), &exp_signed::<G>(acc, &b)) != *g {
+        return Err(BezoutError::InvalidAccumulator);
+    }
+
+    Ok((d, b))
+}
+
 pub fn log2(x: usize) -> u32 {
     if x <= 1 {
         return 0;
@@ -97,8 +227,15 @@ pub fn log2(x: usize) -> u32 {
 
 #[cfg(all(test, feature = "arkworks"))]
 mod test {
-    use crate::utils::{bigint_to_integer, integer_to_bigint};
-    use ark_bls12_381::G1Projective;
+    use crate::utils::{
+        bezout_witness, bigint_to_bytes, bigint_to_integer, bits_big_endian_to_bytes_big_endian,
+        curve::scalar_test_vectors, curve::CurvePointProjective, curve::Field, exp_signed,
+        exp_signed_with, integer_to_bigint, integer_to_bytes, integer_to_signed_bytes,
+        scalar_byte_length, signed_bytes_to_integer, BezoutError, ConvertibleUnknownOrderGroup,
+        ExpScratch,
+    };
+    use accumulator::group::{ClassGroup, Group, Rsa2048, UnknownOrderGroup};
+    use ark_bls12_381::{Fr, G1Projective};
     use rug::Integer;
 
     #[test]
@@ -108,4 +245,129 @@ mod test {
         let int2 = bigint_to_integer::<G1Projective>(&big);
         assert_eq!(int, int2);
     }
+
+    #[test]
+    fn test_unexpected_lengths() {
+        // bits that don't fill a byte are padded with leading zeros
+        assert_eq!(
+            bits_big_endian_to_bytes_big_endian(&[
+                true, false, false, false, false, false, false, false, true
+            ]),
+            vec![1, 1]
+        );
+        // integers wider than the field are reduced
+        let q = <G1Projective as CurvePointProjective>::ScalarField::modulus();
+        let wide = Integer::from(Integer::u_pow_u(2, 300)) + 5;
+        let big = integer_to_bigint::<G1Projective>(&wide);
+        assert_eq!(bigint_to_integer::<G1Projective>(&big), wide % &q);
+        let bits = vec![true; 600];
+        assert_eq!(
+            bigint_to_integer::<G1Projective>(&<Fr as Field>::from_bits(&bits)),
+            (Integer::from(Integer::u_pow_u(2, 600)) - 1) % q
+        );
+    }
+
+    #[test]
+    fn test_scalar_encoding() {
+        assert_eq!(scalar_byte_length::<G1Projective>(), 32);
+        for (value, bytes) in scalar_test_vectors() {
+            let scalar = integer_to_bigint::<G1Projective>(&value);
+            assert_eq!(bigint_to_bytes::<G1Projective>(&scalar), bytes);
+        }
+        // the largest scalar has no leading zero byte to drop
+        let q = <G1Projective as CurvePointProjective>::ScalarField::modulus();
+        let largest = integer_to_bigint::<G1Projective>(&Integer::from(&q - 1));
+        assert_eq!(
+            bigint_to_bytes::<G1Projective>(&largest),
+            integer_to_bytes(&Integer::from(&q - 1))
+        );
+    }
+
+    #[test]
+    fn test_negative_integers() {
+        let s_e = Integer::from(-2_493_823);
+        let s_r = -Integer::from(Integer::u_pow_u(2, 300));
+        for value in &[s_e.clone(), s_r] {
+            // the unsigned encoding loses the sign
+            assert_eq!(
+                integer_to_bytes(value),
+                integer_to_bytes(&Integer::from(-value))
+            );
+            let bytes = integer_to_signed_bytes(value);
+            assert_eq!(bytes[0], 1);
+            assert_eq!(signed_bytes_to_integer(&bytes), Some(value.clone()));
+        }
+        assert_eq!(integer_to_signed_bytes(&Integer::from(0)), vec![0]);
+        assert_eq!(signed_bytes_to_integer(&[1]), None);
+        assert_eq!(signed_bytes_to_integer(&[2, 1]), None);
+        assert_eq!(signed_bytes_to_integer(&[]), None);
+
+        let q = <G1Projective as CurvePointProjective>::ScalarField::modulus();
+        let big = integer_to_bigint::<G1Projective>(&s_e);
+        assert_eq!(bigint_to_integer::<G1Projective>(&big), q.clone() + &s_e);
+        assert_eq!(
+            bigint_to_integer::<G1Projective>(&integer_to_bigint::<G1Projective>(&-q)),
+            0
+        );
+    }
+
+    fn check_exp_signed<G: ConvertibleUnknownOrderGroup>() {
+        let g = G::unknown_order_elem();
+        let e = Integer::from(553_525_575_239_331_913u64);
+        let positive = exp_signed::<G>(&g, &e);
+        assert_eq!(positive, G::exp(&g, &e));
+        let negative = exp_signed::<G>(&g, &Integer::from(-&e));
+        assert_eq!(G::op(&positive, &negative), G::id());
+        assert_eq!(exp_signed::<G>(&g, &Integer::from(0)), G::id());
+        // g^(a - b) = g^a * g^(-b)
+        let a = Integer::from(12_702_637_924_034_044_211u64);
+        assert_eq!(
+            exp_signed::<G>(&g, &Integer::from(&e - &a)),
+            G::op(&G::exp(&g, &e), &exp_signed::<G>(&g, &-a))
+        );
+        // the scratch holds a longer exponent before a shorter one
+        let mut scratch = ExpScratch::new();
+        let long = Integer::from(-&e) * &e;
+        assert_eq!(
+            exp_signed_with::<G>(&mut scratch, &g, &long),
+            exp_signed::<G>(&g, &long)
+        );
+        assert_eq!(
+            exp_signed_with::<G>(&mut scratch, &g, &Integer::from(-&e)),
+            negative
+        );
+        assert_eq!(exp_signed_with::<G>(&mut scratch, &g, &e), positive);
+    }
+
+    #[test]
+    fn test_exp_signed() {
+        check_exp_signed::<Rsa2048>();
+        check_exp_signed::<ClassGroup>();
+    }
+
+    #[test]
+    fn test_bezout_witness() {
+        let g = Rsa2048::unknown_order_elem();
+        let set_product = Integer::from(12_702_637_924_034_044_211u64)
+            * Integer::from(378_373_571_372_703_133u64);
+        let acc = Rsa2048::exp(&g, &set_product);
+        let e = Integer::from(553_525_575_239_331_913u64);
+
+        let (d, b) = bezout_witness::<Rsa2048>(&set_product, &e, &g, &acc).unwrap();
+        assert_eq!(
+            Rsa2048::op(&Rsa2048::exp(&d, &e), &exp_signed::<Rsa2048>(&acc, &b)),
+            g
+        );
+        assert!(b.cmp_abs(&e) == std::cmp::Ordering::Less);
+
+        assert!(matches!(
+            bezout_witness::<Rsa2048>(&set_product, &e, &g, &g),
+            Err(BezoutError::InvalidAccumulator)
+        ));
+        let member = Integer::from(378_373_571_372_703_133u64);
+        assert!(matches!(
+            bezout_witness::<Rsa2048>(&set_product, &member, &g, &acc),
+            Err(BezoutError::NotCoprime)
+        ));
+    }
 }
@@ -0,0 +1,184 @@
+//! RSA moduli with unknown factorization.
+//!
+//! The security of the RSA groups relies on nobody knowing the factorization
+//! of the modulus. The RSA-2048 challenge number, published by RSA
+//! Laboratories in 1991, was generated by a party that destroyed the factors
+//! and is the modulus of `accumulator::group::Rsa2048`, selected at compile
+//! time through the group type parameter of the protocols.
+//!
+//! A deployment-specific modulus, e.g. the output of an MPC ceremony, is
+//! supplied by defining a group type for it in the accumulator library and
+//! using that type instead of `Rsa2048`. `RsaModulus::validate` checks a
+//! candidate modulus against the parameters before it's used, and the root and
+//! coprime protocols check the modulus of their group when created.
+use crate::{parameters::Parameters, utils::ConvertibleUnknownOrderGroup};
+use rug::{integer::IsPrime, Integer};
+
+/// The RSA-2048 challenge number, in decimal.
+pub const RSA_2048_CHALLENGE: &str = "25195908475657893494027183240048398571429282126204032027777137836043662020707595556264018525880784406918290641249515082189298559149176184502808489120072844992687392807287776735971418347270261896375014971824691165077613379859095700097330459748808428401797429100642458691817195118746121515172654632282216869987549182422433637259085141865462043576798423387184774447920739934236584823824281198163815010674810451660377306056201619676256133844143603833904414952634432190114657544454178424020924616515723350778707749817125772467962926386356373289912154831438167899885040445364023527381951378636564391212010397122822120720357";
+
+/// Moduli shorter than this are within reach of factoring records.
+pub const MIN_RSA_MODULUS_BITS: u32 = 2048;
+
+/// The group of the RSA-2048 challenge number.
+pub type Rsa2048Challenge = accumulator::group::Rsa2048;
+
+quick_error! {
+    #[derive(Debug)]
+    pub enum ModulusError {
+        InvalidEncoding {}
+        TooShort(bits: u32, required: u32) {
+            display("modulus has {} bits, {} required", bits, required)
+        }
+        NotComposite {}
+        TriviallyFactorable {}
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RsaModulus {
+    pub modulus: Integer,
+}
+
+impl RsaModulus {
+    pub fn from_decimal(modulus: &str) -> Result<RsaModulus, ModulusError> {
+        let modulus =
+            Integer::from_str_radix(modulus, 10).map_err(|_| ModulusError::InvalidEncoding)?;
+        Ok(RsaModulus { modulus })
+    }
+
+    pub fn rsa_2048_challenge() -> RsaModulus {
+        RsaModulus::from_decimal(RSA_2048_CHALLENGE).expect("valid constant")
+    }
+
+    pub fn bits(&self) -> u32 {
+        self.modulus.significant_bits()
+    }
+
+    /// Number of bits a modulus needs for `parameters`: at least
+    /// `MIN_RSA_MODULUS_BITS`, and enough for the challenges of the sigma
+    /// protocols to stay below its smallest prime factor, assuming balanced
+    /// factors.
+    pub fn required_bits(parameters: &Parameters) -> u32 {
+        std::cmp::max(
+            MIN_RSA_MODULUS_BITS,
+            2 * (parameters.security_soundness as u32 + 2),
+        )
+    }
+
+    /// Checks the modulus is long enough for `parameters` and isn't trivially
+    /// factorable. It can't check that nobody knows the factorization.
+    pub fn validate(&self, parameters: &Parameters) -> Result<(), ModulusError> {
+        let required = Self::required_bits(parameters);
+        if self.bits() < required {
+            return Err(ModulusError::TooShort(self.bits(), required));
+        }
+        // even moduli and perfect powers are composite, but factor at once
+        if self.modulus.is_even() || self.modulus.is_perfect_power() {
+            return Err(ModulusError::TriviallyFactorable);
+        }
+        if self
+            .modulus
+            .is_probably_prime(parameters.security_level as u32 / 2)
+            != IsPrime::No
+        {
+            return Err(ModulusError::NotComposite);
+        }
+
+        Ok(())
+    }
+}
+
+/// Checks the modulus of `G`, if it's an RSA group, is long enough for
+/// `parameters`.
+pub fn validate_group_modulus<G: ConvertibleUnknownOrderGroup>(
+    parameters: &Parameters,
+) -> Result<(), ModulusError> {
+    if let Ok(modulus) = G::rsa_modulus() {
+        let required = RsaModulus::required_bits(parameters);
+        // the trapdoor group only needs room for the challenges
+        #[cfg(any(test, feature = "insecure-trapdoor-group"))]
+        let required = if modulus == super::trapdoor::TrapdoorRsa512::modulus() {
+            2 * (parameters.security_soundness as u32 + 2)
+        } else {
+            required
+        };
+        if modulus.significant_bits() < required {
+            return Err(ModulusError::TooShort(modulus.significant_bits(), required));
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(all(test, feature = "arkworks"))]
+mod test {
+    use super::{
+        validate_group_modulus, ModulusError, Rsa2048Challenge, RsaModulus, RSA_2048_CHALLENGE,
+    };
+    use crate::{parameters::Parameters, utils::ConvertibleUnknownOrderGroup};
+    use accumulator::group::ClassGroup;
+    use rug::Integer;
+
+    fn rsa_modulus<G: ConvertibleUnknownOrderGroup>() -> Option<Integer> {
+        G::rsa_modulus().ok()
+    }
+
+    #[test]
+    fn test_rsa_2048_challenge() {
+        let modulus = RsaModulus::rsa_2048_challenge();
+        assert_eq!(modulus.bits(), 2048);
+        assert_eq!(RSA_2048_CHALLENGE.len(), 617);
+        assert_eq!(
+            rsa_modulus::<Rsa2048Challenge>(),
+            Some(modulus.modulus.clone())
+        );
+
+        let params = Parameters::from_security_level(128).unwrap();
+        modulus.validate(&params).unwrap();
+        validate_group_modulus::<Rsa2048Challenge>(&params).unwrap();
+        // class groups have no modulus to check
+        validate_group_modulus::<ClassGroup>(&params).unwrap();
+    }
+
+    #[test]
+    fn test_invalid_moduli() {
+        let params = Parameters::from_security_level(128).unwrap();
+        assert!(matches!(
+            RsaModulus::from_decimal("0x1234"),
+            Err(ModulusError::InvalidEncoding)
+        ));
+
+        let short = RsaModulus {
+            modulus: Integer::from(3233),
+        };
+        assert!(matches!(
+            short.validate(&params),
+            Err(ModulusError::TooShort(12, 2048))
+        ));
+
+        let mut params_large = params.clone();
+        params_large.security_soundness = 1200;
+        assert!(matches!(
+            RsaModulus::rsa_2048_challenge().validate(&params_large),
+            Err(ModulusError::TooShort(2048, 2404))
+        ));
+        assert!(validate_group_modulus::<Rsa2048Challenge>(&params_large).is_err());
+
+        let even = RsaModulus {
+            modulus: Integer::from(Integer::u_pow_u(2, 2047)) * 3,
+        };
+        assert!(matches!(
+            even.validate(&params),
+            Err(ModulusError::TriviallyFactorable)
+        ));
+
+        let power = RsaModulus {
+            modulus: Integer::from(Integer::u_pow_u(3, 1300)),
+        };
+        assert!(matches!(
+            power.validate(&params),
+            Err(ModulusError::TriviallyFactorable)
+        ));
+    }
+}
@@ -0,0 +1,155 @@
+//! Randomness management for long-running provers.
+//!
+//! The sigma protocols leak the witness if the same blinding values are ever
+//! used to answer two different challenges, so a prover must never emit a
+//! proof from a generator whose state may have been duplicated or whose
+//! entropy source failed. `ProverRng` periodically reseeds from the OS,
+//! reseeds after a `fork()`, and refuses to produce output once reseeding
+//! has failed.
+use rand::{
+    rngs::{OsRng, StdRng},
+    CryptoRng, RngCore, SeedableRng,
+};
+use rug::rand::RandGen;
+use std::process;
+
+quick_error! {
+    #[derive(Debug)]
+    pub enum ProverRngError {
+        EntropyUnavailable(err: rand::Error) {
+            from()
+        }
+        Compromised {}
+    }
+}
+
+pub struct ProverRng {
+    rng: StdRng,
+    pid: u32,
+    bytes_since_reseed: u64,
+    reseed_threshold: u64,
+    compromised: bool,
+}
+
+impl ProverRng {
+    /// Number of output bytes after which the generator is reseeded.
+    pub const DEFAULT_RESEED_THRESHOLD: u64 = 1 << 20;
+
+    pub fn new() -> Result<ProverRng, ProverRngError> {
+        Self::with_reseed_threshold(Self::DEFAULT_RESEED_THRESHOLD)
+    }
+
+    pub fn with_reseed_threshold(reseed_threshold: u64) -> Result<ProverRng, ProverRngError> {
+        Ok(ProverRng {
+            rng: StdRng::from_rng(OsRng)?,
+            pid: process::id(),
+            bytes_since_reseed: 0,
+            reseed_threshold,
+            compromised: false,
+        })
+    }
+
+    /// Reseeds from the OS. On failure the generator is marked as compromised
+    /// and stays unusable.
+    pub fn reseed(&mut self) -> Result<(), ProverRngError> {
+        match StdRng::from_rng(OsRng) {
+            Ok(rng) => {
+                self.rng = rng;
+                self.pid = process::id();
+                self.bytes_since_reseed = 0;
+                Ok(())
+            }
+            Err(e) => {
+                self.compromised = true;
+                Err(e.into())
+            }
+        }
+    }
+
+    /// Makes sure the generator is safe to use, reseeding if the process has
+    /// forked or the reseed threshold was reached. Provers should call this
+    /// before each proof to handle failures gracefully, since the `RngCore`
+    /// methods can only panic.
+    pub fn check(&mut self) -> Result<(), ProverRngError> {
+        if self.compromised {
+            return Err(ProverRngError::Compromised);
+        }
+        if self.pid != process::id() || self.bytes_since_reseed >= self.reseed_threshold {
+            self.reseed()?;
+        }
+
+        Ok(())
+    }
+
+    pub fn is_compromised(&self) -> bool {
+        self.compromised
+    }
+}
+
+impl RngCore for ProverRng {
+    fn next_u32(&mut self) -> u32 {
+        let mut bytes = [0u8; 4];
+        self.fill_bytes(&mut bytes);
+        u32::from_le_bytes(bytes)
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        let mut bytes = [0u8; 8];
+        self.fill_bytes(&mut bytes);
+        u64::from_le_bytes(bytes)
+    }
+
+    /// Panics if the generator is compromised.
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        if let Err(e) = self.try_fill_bytes(dest) {
+            panic!("prover randomness unavailable: {:?}", e);
+        }
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), rand::Error> {
+        self.check().map_err(rand::Error::new)?;
+        self.rng.fill_bytes(dest);
+        self.bytes_since_reseed += dest.len() as u64;
+        Ok(())
+    }
+}
+
+impl CryptoRng for ProverRng {}
+
+/// Allows using a `ProverRng` for the integer randomness as well, through
+/// `RandState::new_custom`.
+impl RandGen for ProverRng {
+    fn gen(&mut self) -> u32 {
+        self.next_u32()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{ProverRng, ProverRngError};
+    use rand::RngCore;
+    use rug::{rand::RandState, Integer};
+
+    #[test]
+    fn test_reseed_threshold() {
+        let mut rng = ProverRng::with_reseed_threshold(16).unwrap();
+        let mut bytes = [0u8; 32];
+        rng.fill_bytes(&mut bytes);
+        assert_eq!(rng.bytes_since_reseed, 32);
+        rng.fill_bytes(&mut bytes);
+        assert_eq!(rng.bytes_since_reseed, 32);
+
+        let mut rand_state = RandState::new_custom(&mut rng);
+        let r = Integer::from(Integer::u_pow_u(2, 128)).random_below(&mut rand_state);
+        assert!(r < Integer::from(Integer::u_pow_u(2, 128)));
+    }
+
+    #[test]
+    fn test_compromised() {
+        let mut rng = ProverRng::new().unwrap();
+        rng.compromised = true;
+        assert!(matches!(rng.check(), Err(ProverRngError::Compromised)));
+        let mut bytes = [0u8; 32];
+        assert!(rng.try_fill_bytes(&mut bytes).is_err());
+    }
+}
@@ -0,0 +1,162 @@
+//! Caller-supplied sources of secret randomness.
+//!
+//! Deployments with key-isolation policies can produce the Pedersen randomness
+//! `r_q` and the prover's blinding values in an HSM or a secure enclave by
+//! implementing `SecretSource`. `SecretRng` adapts a source to the `rand` RNG
+//! the provers take for their curve-side randomness, and `random_scalar`
+//! samples `r_q` without going through an intermediate generator. Buffers
+//! holding secret bytes are zeroed once they were consumed, but the values
+//! themselves end up in the witness and the field elements of the prover.
+use crate::utils::{
+    bytes_to_integer,
+    curve::{CurvePointProjective, Field},
+};
+use rand::{CryptoRng, RngCore};
+use rug::{rand::RandGen, Integer};
+
+quick_error! {
+    #[derive(Debug)]
+    pub enum SecretSourceError {
+        Unavailable(description: String) {
+            display("secret source unavailable: {}", description)
+        }
+    }
+}
+
+pub trait SecretSource {
+    /// Fills `dest` with uniformly random bytes from a cryptographically
+    /// secure source.
+    fn fill_secret(&mut self, dest: &mut [u8]) -> Result<(), SecretSourceError>;
+}
+
+impl<S: SecretSource> SecretSource for &mut S {
+    fn fill_secret(&mut self, dest: &mut [u8]) -> Result<(), SecretSourceError> {
+        (**self).fill_secret(dest)
+    }
+}
+
+/// Samples a scalar of the curve's field from `source`, reducing 128 more
+/// bits than the field size to keep the bias negligible.
+pub fn random_scalar<P: CurvePointProjective, S: SecretSource>(
+    source: &mut S,
+) -> Result<Integer, SecretSourceError> {
+    let mut bytes = vec![0u8; (P::ScalarField::size_in_bits() + 128 + 7) / 8];
+    let result = source.fill_secret(&mut bytes);
+    let scalar = result.map(|_| bytes_to_integer(&bytes) % P::ScalarField::modulus());
+    zero(&mut bytes);
+    scalar
+}
+
+fn zero(bytes: &mut [u8]) {
+    for b in bytes.iter_mut() {
+        // volatile so that the write isn't optimized away
+        unsafe { std::ptr::write_volatile(b, 0) };
+    }
+}
+
+/// Uses a `SecretSource` as the RNG of the provers.
+pub struct SecretRng<S: SecretSource> {
+    source: S,
+}
+
+impl<S: SecretSource> SecretRng<S> {
+    pub fn new(source: S) -> SecretRng<S> {
+        SecretRng { source }
+    }
+
+    pub fn into_inner(self) -> S {
+        self.source
+    }
+}
+
+impl<S: SecretSource> RngCore for SecretRng<S> {
+    fn next_u32(&mut self) -> u32 {
+        let mut bytes = [0u8; 4];
+        self.fill_bytes(&mut bytes);
+        let value = u32::from_le_bytes(bytes);
+        zero(&mut bytes);
+        value
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        let mut bytes = [0u8; 8];
+        self.fill_bytes(&mut bytes);
+        let value = u64::from_le_bytes(bytes);
+        zero(&mut bytes);
+        value
+    }
+
+    /// Panics if the source fails.
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        if let Err(e) = self.try_fill_bytes(dest) {
+            panic!("prover randomness unavailable: {:?}", e);
+        }
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), rand::Error> {
+        self.source.fill_secret(dest).map_err(rand::Error::new)
+    }
+}
+
+/// `SecretSource` implementations are required to be cryptographically
+/// secure.
+impl<S: SecretSource> CryptoRng for SecretRng<S> {}
+
+/// Allows using a `SecretSource` for the integer randomness as well, through
+/// `RandState::new_custom`.
+impl<S: SecretSource> RandGen for SecretRng<S> {
+    fn gen(&mut self) -> u32 {
+        self.next_u32()
+    }
+}
+
+#[cfg(all(test, feature = "arkworks"))]
+mod test {
+    use super::{random_scalar, SecretRng, SecretSource, SecretSourceError};
+    use crate::{
+        commitments::{pedersen::PedersenCommitment, Commitment},
+        utils::curve::{CurvePointProjective, Field},
+    };
+    use ark_bls12_381::G1Projective;
+    use rand::{thread_rng, RngCore};
+    use rug::Integer;
+
+    /// Stands in for a hardware source, counting the requested bytes.
+    struct CountingSource {
+        requested: usize,
+        available: bool,
+    }
+
+    impl SecretSource for CountingSource {
+        fn fill_secret(&mut self, dest: &mut [u8]) -> Result<(), SecretSourceError> {
+            if !self.available {
+                return Err(SecretSourceError::Unavailable("disconnected".to_owned()));
+            }
+            thread_rng().fill_bytes(dest);
+            self.requested += dest.len();
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_random_scalar() {
+        let mut source = CountingSource {
+            requested: 0,
+            available: true,
+        };
+        let r_q = random_scalar::<G1Projective, _>(&mut source).unwrap();
+        assert!(r_q < <G1Projective as CurvePointProjective>::ScalarField::modulus());
+        assert_eq!(source.requested, 48);
+
+        let pedersen = PedersenCommitment::<G1Projective>::setup(&mut SecretRng::new(&mut source));
+        assert!(source.requested > 48);
+        let value = Integer::from(5);
+        let commitment = pedersen.commit(&value, &r_q).unwrap();
+        pedersen.open(&commitment, &value, &r_q).unwrap();
+
+        source.available = false;
+        assert!(random_scalar::<G1Projective, _>(&mut source).is_err());
+        let mut rng = SecretRng::new(source);
+        assert!(rng.try_fill_bytes(&mut [0u8; 8]).is_err());
+    }
+}
@@ -0,0 +1,156 @@
+//! An RSA group with a published factorization, for tests only.
+//!
+//! `TrapdoorRsa512` is the group of a 512-bit modulus whose factors are
+//! public, so that the protocols can run in CI and in downstream tests without
+//! 2048-bit exponentiations. Anybody can compute roots and the order of
+//! elements in it, which breaks the soundness of every protocol over it.
+//!
+//! It is only compiled for the tests of this crate and with the
+//! `insecure-trapdoor-group` feature, and enabling the feature in a build
+//! without debug assertions, e.g. a release build, fails to compile.
+//! `validate_group_modulus` accepts its modulus, which is shorter than
+//! `MIN_RSA_MODULUS_BITS`, as long as the challenges fit.
+use accumulator::{
+    group::{ElemToBytes, Group, GroupError, UnknownOrderGroup},
+    util::TypeRep,
+};
+use rug::{integer::Order, Integer};
+
+#[cfg(all(feature = "insecure-trapdoor-group", not(debug_assertions)))]
+compile_error!(
+    "the insecure-trapdoor-group feature is for tests and can't be used in release builds"
+);
+
+/// The safe prime `p = 2p' + 1`, in hexadecimal.
+pub const TRAPDOOR_P: &str = "bace009f916e12c05eba14da5057c85902949ff449aed6880bd5e971f415714b";
+/// The safe prime `q = 2q' + 1`, in hexadecimal.
+pub const TRAPDOOR_Q: &str = "c6ac12ead6585a2ac1d52d493a366ddf315ad0c7c6e2532561f15e80233ee5df";
+/// The modulus `N = pq`, in hexadecimal.
+pub const TRAPDOOR_MODULUS: &str = "90f8e4b1ae714fab48534d62cf13b5f3ffaf4f29345cf3068d7aec2ff5900a4bfd9702369e9316debda3392aaf16fb24849cc70bcd3fd95fc40e84c97e2fc755";
+
+const ELEM_BYTES: usize = 64;
+
+fn from_hex(hex: &str) -> Integer {
+    Integer::from_str_radix(hex, 16).expect("valid constant")
+}
+
+/// `Z_N^* / {±1}` for the trapdoor modulus, as `Rsa2048` for the RSA-2048
+/// challenge number.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum TrapdoorRsa512 {}
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct TrapdoorRsa512Elem(Integer);
+
+impl TrapdoorRsa512 {
+    pub fn modulus() -> Integer {
+        from_hex(TRAPDOOR_MODULUS)
+    }
+
+    /// The factors `(p, q)` of the modulus.
+    pub fn factors() -> (Integer, Integer) {
+        (from_hex(TRAPDOOR_P), from_hex(TRAPDOOR_Q))
+    }
+
+    /// The order `p'q'` of the squares, e.g. to compute roots in tests.
+    pub fn squares_order() -> Integer {
+        let (p, q) = Self::factors();
+        (p - 1u32) / 2u32 * ((q - 1u32) / 2u32)
+    }
+
+    /// The element of `value`, reduced modulo `N` and to its representative
+    /// of `{x, -x}`.
+    pub fn elem(value: &Integer) -> TrapdoorRsa512Elem {
+        let modulus = Self::modulus();
+        let x = Integer::from(value.rem_euc_ref(&modulus));
+        let minus_x = Integer::from(&modulus - &x);
+        TrapdoorRsa512Elem(std::cmp::min(x, minus_x))
+    }
+}
+
+impl TypeRep for TrapdoorRsa512 {
+    type Rep = ();
+
+    fn rep() -> &'static Self::Rep {
+        &()
+    }
+}
+
+impl Group for TrapdoorRsa512 {
+    type Elem = TrapdoorRsa512Elem;
+
+    fn id_(_: &()) -> TrapdoorRsa512Elem {
+        TrapdoorRsa512Elem(Integer::from(1))
+    }
+
+    fn op_(_: &(), a: &TrapdoorRsa512Elem, b: &TrapdoorRsa512Elem) -> TrapdoorRsa512Elem {
+        Self::elem(&Integer::from(&a.0 * &b.0))
+    }
+
+    fn exp_(_: &(), a: &TrapdoorRsa512Elem, n: &Integer) -> TrapdoorRsa512Elem {
+        // negative exponents invert, as pow_mod does
+        Self::elem(&Integer::from(
+            a.0.pow_mod_ref(n, &Self::modulus())
+                .expect("invertible element"),
+        ))
+    }
+
+    fn inv_(_: &(), a: &TrapdoorRsa512Elem) -> TrapdoorRsa512Elem {
+        Self::elem(&Integer::from(
+            a.0.invert_ref(&Self::modulus())
+                .expect("invertible element"),
+        ))
+    }
+}
+
+impl UnknownOrderGroup for TrapdoorRsa512 {
+    fn unknown_order_elem_(_: &()) -> TrapdoorRsa512Elem {
+        TrapdoorRsa512Elem(Integer::from(2))
+    }
+
+    fn order_upper_bound_(_: &()) -> Integer {
+        Self::modulus()
+    }
+
+    fn rsa_modulus() -> Result<Integer, GroupError> {
+        Ok(Self::modulus())
+    }
+}
+
+impl ElemToBytes for TrapdoorRsa512 {
+    fn elem_to_bytes(val: &TrapdoorRsa512Elem) -> Vec<u8> {
+        let mut bytes = vec![0u8; ELEM_BYTES];
+        val.0.write_digits(&mut bytes, Order::MsfBe);
+        bytes
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::TrapdoorRsa512;
+    use crate::{
+        parameters::Parameters,
+        utils::{exp_signed, modulus::validate_group_modulus},
+    };
+    use accumulator::group::{Group, UnknownOrderGroup};
+    use rug::Integer;
+
+    #[test]
+    fn test_trapdoor_group() {
+        let (p, q) = TrapdoorRsa512::factors();
+        assert_eq!(p * q, TrapdoorRsa512::modulus());
+        assert_eq!(TrapdoorRsa512::modulus().significant_bits(), 512);
+
+        let g = TrapdoorRsa512::unknown_order_elem();
+        let order = TrapdoorRsa512::squares_order();
+        let square = TrapdoorRsa512::op(&g, &g);
+        assert_eq!(TrapdoorRsa512::exp(&square, &order), TrapdoorRsa512::id());
+        assert_eq!(
+            TrapdoorRsa512::op(&g, &exp_signed::<TrapdoorRsa512>(&g, &Integer::from(-1))),
+            TrapdoorRsa512::id()
+        );
+
+        let params = Parameters::from_security_level(128).unwrap();
+        validate_group_modulus::<TrapdoorRsa512>(&params).unwrap();
+    }
+}
@@ -0,0 +1,119 @@
+//! Differential tests against the design evaluated in the paper,
+//! https://eprint.iacr.org/2019/1255: the parameters it derives for
+//! BLS12-381 and RSA-2048, the length of `s_e` following from them and the
+//! shape of the LegoGroth16 proofs. The expected values are written out
+//! rather than recomputed with the formulas of the crate, and the size of
+//! the range circuit is counted from the gadgets it's built with. The proof
+//! sizes and constraint counts of the tables of the paper are not encoded.
+#![cfg(all(feature = "arkworks", feature = "prover"))]
+
+use accumulator::group::Rsa2048;
+use ark_bls12_381::{Bls12_381, Fr, G1Projective};
+use cpsnarks_set::{
+    benchmarks::MembershipFixture,
+    parameters::{Parameters, SubProtocol},
+    protocols::{
+        blinding::BlindingRanges,
+        hash_to_prime::{snark_range::Protocol as RangeProtocol, HashToPrimeProtocol},
+        membership,
+        sizes::{estimate_sizes, group_element_bytes, response_bytes},
+    },
+};
+use rand::thread_rng;
+use rug::rand::RandState;
+use rug::Integer;
+
+/// The evaluated configuration: λ from the 255 bits of the scalar field of
+/// BLS12-381, λ_s = λ - 2, λ_z = λ - 3 and primes of μ = 2λ - 2 bits.
+struct Reference {
+    security_level: u16,
+    security_zk: u16,
+    security_soundness: u16,
+    hash_to_prime_bits: u16,
+    field_size_bits: u16,
+}
+
+const REFERENCE: Reference = Reference {
+    security_level: 127,
+    security_zk: 124,
+    security_soundness: 125,
+    hash_to_prime_bits: 252,
+    field_size_bits: 255,
+};
+
+const RSA_ELEMENT_BYTES: usize = 256;
+const G1_BYTES: usize = 48;
+const G2_BYTES: usize = 96;
+
+/// A LegoGroth16 proof: A, C, D, the link commitment and its proof in G1,
+/// and B in G2.
+const LEGOGRO16_PROOF_BYTES: usize = 5 * G1_BYTES + G2_BYTES;
+
+fn reference_parameters() -> Parameters {
+    Parameters::from_curve::<Fr>().unwrap().0
+}
+
+#[test]
+fn test_parameters() {
+    let params = reference_parameters();
+    assert_eq!(params.security_level, REFERENCE.security_level);
+    assert_eq!(params.security_zk, REFERENCE.security_zk);
+    assert_eq!(params.security_soundness, REFERENCE.security_soundness);
+    assert_eq!(params.hash_to_prime_bits, REFERENCE.hash_to_prime_bits);
+    assert_eq!(params.field_size_bits, REFERENCE.field_size_bits);
+}
+
+#[test]
+fn test_response_lengths() {
+    let params = reference_parameters();
+    let ranges = BlindingRanges::new::<Rsa2048>(&params.for_sub_protocol(SubProtocol::Root));
+    // r_e is in [-2^(λ_z + λ_s + μ), 2^(λ_z + λ_s + μ)], with
+    // λ_z + λ_s + μ = 124 + 125 + 252 = 501
+    assert_eq!(ranges.exponent.significant_bits(), 502);
+    // s_e = r_e - c e is below 2^503, in 63 bytes plus a sign byte
+    assert_eq!(response_bytes(&ranges.exponent), 64);
+}
+
+#[test]
+fn test_range_circuit() {
+    let params = reference_parameters();
+    let range = RangeProtocol::<Bls12_381>::circuit_stats(&params).unwrap();
+    let field_bits = REFERENCE.field_size_bits as usize;
+    let top_bits = field_bits - REFERENCE.hash_to_prime_bits as usize;
+    // the element is the only public input
+    assert_eq!(range.public_inputs, 1);
+    // a boolean constraint for each of the 255 bits of the element, one
+    // packing them back into it, one for each of the 3 bits above μ forced to
+    // zero and one for the bit μ forced to one
+    assert_eq!(range.constraints, field_bits + 1 + top_bits + 1);
+    // the constant one, the element and its bits
+    assert_eq!(range.variables, 2 + field_bits);
+}
+
+#[test]
+fn test_proof_sizes() {
+    let params = reference_parameters();
+    let hash_to_prime = RangeProtocol::<Bls12_381>::estimate_sizes(&params).unwrap();
+    assert_eq!(hash_to_prime.proof_bytes, LEGOGRO16_PROOF_BYTES);
+    assert_eq!(group_element_bytes::<Rsa2048>(), RSA_ELEMENT_BYTES);
+
+    // an actual proof is no larger than the estimate
+    let estimate =
+        estimate_sizes::<Rsa2048, G1Projective, RangeProtocol<Bls12_381>>(&params).unwrap();
+    let mut rng1 = RandState::new();
+    rng1.seed(&Integer::from(13));
+    let mut rng2 = thread_rng();
+    let crs = membership::Protocol::<Rsa2048, G1Projective, RangeProtocol<Bls12_381>>::setup(
+        &params, &mut rng1, &mut rng2,
+    )
+    .unwrap()
+    .crs;
+    let set = vec![Integer::from(378_373_571_372_703_133u64)];
+    // 2^252 - 129 is the largest prime of μ = 252 bits
+    let prime = Integer::from(Integer::u_pow_u(2, REFERENCE.hash_to_prime_bits as u32))
+        - &Integer::from(129);
+    assert_ne!(prime.is_probably_prime(64), rug::integer::IsPrime::No);
+    let fixture =
+        MembershipFixture::new(&crs, b"membership", &set, &prime, &mut rng1, &mut rng2).unwrap();
+    assert!(fixture.proof.serialized_size().unwrap() <= estimate.proof_bytes);
+}